@@ -0,0 +1,70 @@
+//! Benchmark for the quick-stats phase of `collect_metrics`.
+//!
+//! `collect_footprint` (git count-objects) and `collect_refs` (git
+//! for-each-ref) are independent git calls; this compares running them back
+//! to back with running them on scoped threads as `collect_metrics` does.
+
+use std::path::Path;
+use std::process::Command;
+use std::thread;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use filter_repo_rs::analysis::{collect_footprint, collect_refs};
+use tempfile::TempDir;
+
+fn run_git(dir: &Path, args: &[&str]) {
+    let status = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .status()
+        .expect("run git");
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+/// A small repository with many refs so for-each-ref has real work to do.
+fn build_fixture(n_refs: usize) -> TempDir {
+    let dir = TempDir::new().expect("create fixture dir");
+    let path = dir.path();
+    run_git(path, &["init", "-q", "-b", "main"]);
+    run_git(path, &["config", "user.email", "bench@test.local"]);
+    run_git(path, &["config", "user.name", "Bench User"]);
+    std::fs::write(path.join("README.md"), "bench\n").unwrap();
+    run_git(path, &["add", "."]);
+    run_git(path, &["commit", "-q", "-m", "seed"]);
+    for i in 0..n_refs {
+        run_git(path, &["tag", &format!("v0.{i}")]);
+    }
+    dir
+}
+
+fn bench_quick_stats(c: &mut Criterion) {
+    let fixture = build_fixture(200);
+    let repo = fixture.path();
+    let mut group = c.benchmark_group("analyze_quick_stats");
+
+    group.bench_function("sequential", |b| {
+        b.iter(|| {
+            let footprint = collect_footprint(repo).unwrap();
+            let refs = collect_refs(repo).unwrap();
+            (footprint, refs)
+        });
+    });
+
+    group.bench_function("scoped_threads", |b| {
+        b.iter(|| {
+            thread::scope(|scope| {
+                let footprint = scope.spawn(|| collect_footprint(repo));
+                let refs = scope.spawn(|| collect_refs(repo));
+                (
+                    footprint.join().unwrap().unwrap(),
+                    refs.join().unwrap().unwrap(),
+                )
+            })
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_quick_stats);
+criterion_main!(benches);
@@ -12,21 +12,29 @@ fn build_default_patterns() -> Vec<SecretPattern> {
             name: "aws_access_key_id".into(),
             regex: Regex::new(r"\b(?:AKIA|ASIA)[0-9A-Z]{16}\b").unwrap(),
             capture_group: None,
+            severity: None,
+            example: "",
         },
         SecretPattern {
             name: "github_token".into(),
             regex: Regex::new(r"\bgh[pousr]_[A-Za-z0-9]{36}\b").unwrap(),
             capture_group: None,
+            severity: None,
+            example: "",
         },
         SecretPattern {
             name: "slack_token".into(),
             regex: Regex::new(r"\bxox[baprs]-[A-Za-z0-9-]{10,128}\b").unwrap(),
             capture_group: None,
+            severity: None,
+            example: "",
         },
         SecretPattern {
             name: "google_api_key".into(),
             regex: Regex::new(r"\bAIza[0-9A-Za-z_-]{35}\b").unwrap(),
             capture_group: None,
+            severity: None,
+            example: "",
         },
         SecretPattern {
             name: "jwt".into(),
@@ -35,11 +43,15 @@ fn build_default_patterns() -> Vec<SecretPattern> {
             )
             .unwrap(),
             capture_group: None,
+            severity: None,
+            example: "",
         },
         SecretPattern {
             name: "openai_api_key".into(),
             regex: Regex::new(r"\b(?:sk-|sk-proj-)[A-Za-z0-9_-]{20,200}\b").unwrap(),
             capture_group: None,
+            severity: None,
+            example: "",
         },
         SecretPattern {
             name: "assignment_value".into(),
@@ -48,11 +60,15 @@ fn build_default_patterns() -> Vec<SecretPattern> {
             )
             .unwrap(),
             capture_group: Some(1),
+            severity: None,
+            example: "",
         },
         SecretPattern {
             name: "db_url_password".into(),
             regex: Regex::new(r"\b[a-z][a-z0-9+.-]*://[^/\s:@]+:([^/\s@]{8,})@[^/\s]+").unwrap(),
             capture_group: Some(1),
+            severity: None,
+            example: "",
         },
     ]
 }
@@ -0,0 +1,83 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+use filter_repo_rs::pathutil::{glob_match_bytes, glob_match_bytes_recursive};
+
+// A 50-byte path, typical of a nested source file.
+const PATH_50: &[u8] = b"crates/engine/src/render/pipeline/shader_caches.rs";
+
+fn bench_single_pattern(c: &mut Criterion) {
+    let mut group = c.benchmark_group("glob_match");
+
+    group.bench_function("double_star_ext/hit", |b| {
+        b.iter(|| glob_match_bytes(black_box(b"**/*.rs"), black_box(PATH_50)))
+    });
+    group.bench_function("double_star_ext/miss", |b| {
+        b.iter(|| glob_match_bytes(black_box(b"**/*.py"), black_box(PATH_50)))
+    });
+
+    group.finish();
+}
+
+fn bench_many_patterns(c: &mut Criterion) {
+    let mut group = c.benchmark_group("glob_match_20_patterns");
+
+    let patterns: Vec<Vec<u8>> = (0..19)
+        .map(|i| format!("module_{i}/**/*.rs").into_bytes())
+        .chain(std::iter::once(b"crates/engine/**/*.rs".to_vec()))
+        .collect();
+    let misses: Vec<Vec<u8>> = (0..20)
+        .map(|i| format!("module_{i}/**/*.rs").into_bytes())
+        .collect();
+
+    group.bench_function("hit_last", |b| {
+        b.iter(|| {
+            patterns
+                .iter()
+                .any(|p| glob_match_bytes(p, black_box(PATH_50)))
+        })
+    });
+    group.bench_function("no_match", |b| {
+        b.iter(|| {
+            misses
+                .iter()
+                .any(|p| glob_match_bytes(p, black_box(PATH_50)))
+        })
+    });
+
+    group.finish();
+}
+
+// Fast paths (`**`, `<prefix>/**`) against the general recursive matcher on
+// the same inputs.
+fn bench_fast_paths(c: &mut Criterion) {
+    let mut group = c.benchmark_group("glob_match_fast_path");
+
+    group.bench_function("match_all/fast", |b| {
+        b.iter(|| glob_match_bytes(black_box(b"**"), black_box(PATH_50)))
+    });
+    group.bench_function("match_all/recursive", |b| {
+        b.iter(|| glob_match_bytes_recursive(black_box(b"**"), black_box(PATH_50)))
+    });
+    group.bench_function("prefix_tree/fast", |b| {
+        b.iter(|| glob_match_bytes(black_box(b"crates/engine/**"), black_box(PATH_50)))
+    });
+    group.bench_function("prefix_tree/recursive", |b| {
+        b.iter(|| glob_match_bytes_recursive(black_box(b"crates/engine/**"), black_box(PATH_50)))
+    });
+    group.bench_function("prefix_tree_miss/fast", |b| {
+        b.iter(|| glob_match_bytes(black_box(b"crates/editor/**"), black_box(PATH_50)))
+    });
+    group.bench_function("prefix_tree_miss/recursive", |b| {
+        b.iter(|| glob_match_bytes_recursive(black_box(b"crates/editor/**"), black_box(PATH_50)))
+    });
+
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_single_pattern,
+    bench_many_patterns,
+    bench_fast_paths
+);
+criterion_main!(benches);
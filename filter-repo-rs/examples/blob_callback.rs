@@ -0,0 +1,70 @@
+//! Example `--blob-callback-so` library: strips trailing spaces and tabs from
+//! every line of every blob.
+//!
+//! Build it with
+//!
+//! ```sh
+//! cargo build --release --example blob_callback
+//! filter-repo-rs --blob-callback-so target/release/examples/libblob_callback.so
+//! ```
+//!
+//! See `docs/CALLBACK_ABI.md` for the full contract.
+
+use std::slice;
+
+/// Transforms one blob.
+///
+/// # Safety
+///
+/// `data` must point to `len` readable bytes; `out` and `out_len` must be
+/// valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn filter_repo_blob_transform(
+    data: *const u8,
+    len: usize,
+    out: *mut *mut u8,
+    out_len: *mut usize,
+) -> i32 {
+    let input = if len == 0 {
+        &[][..]
+    } else {
+        slice::from_raw_parts(data, len)
+    };
+    // Leave binary content alone.
+    if input.contains(&0) {
+        return 1;
+    }
+    let mut output = Vec::with_capacity(input.len());
+    for line in input.split_inclusive(|&b| b == b'\n') {
+        let (body, newline) = match line.strip_suffix(b"\n") {
+            Some(body) => (body, &b"\n"[..]),
+            None => (line, &b""[..]),
+        };
+        let end = body
+            .iter()
+            .rposition(|&b| b != b' ' && b != b'\t')
+            .map_or(0, |i| i + 1);
+        output.extend_from_slice(&body[..end]);
+        output.extend_from_slice(newline);
+    }
+    if output == input {
+        return 1;
+    }
+    let boxed = output.into_boxed_slice();
+    *out_len = boxed.len();
+    *out = Box::into_raw(boxed).cast::<u8>();
+    0
+}
+
+/// Releases a buffer returned by [`filter_repo_blob_transform`].
+///
+/// # Safety
+///
+/// `ptr` and `len` must come from one successful call to
+/// `filter_repo_blob_transform` and be freed only once.
+#[no_mangle]
+pub unsafe extern "C" fn filter_repo_blob_free(ptr: *mut u8, len: usize) {
+    if !ptr.is_null() {
+        drop(Box::from_raw(std::ptr::slice_from_raw_parts_mut(ptr, len)));
+    }
+}
@@ -1,47 +1,37 @@
 use colored::{Color, Colorize};
 use comfy_table::{
     modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Attribute, Cell, CellAlignment,
-    ContentArrangement, Table,
+    ColumnConstraint, ContentArrangement, Table,
 };
 use serde::Serialize;
 use std::borrow::Cow;
 use std::cmp::Reverse;
-use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap, HashSet};
 use std::io::{self, BufRead, BufReader, Write};
 use std::path::Path;
 use std::process::{Child, ChildStdout, Command, Stdio};
+use std::thread;
 use std::time::Instant;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
 
+use crate::cancel::{self, CancellationToken};
+use crate::diagnostics::{self, Phase};
 use crate::gitutil;
 use crate::opts::{AnalyzeConfig, AnalyzeThresholds, Mode, Options};
+use crate::pathutil::glob_match_bytes;
+use crate::progress::{stderr_supports_color, stdout_supports_color, Progress};
 use std::fs::{create_dir_all, File};
 
-fn color_output_enabled(is_terminal: bool, no_color: bool, force_color: bool) -> bool {
-    if no_color {
-        return false;
-    }
-    is_terminal || force_color
-}
-
-fn stdout_supports_color() -> bool {
-    use std::io::IsTerminal;
+mod baseline;
+mod suggest;
 
-    color_output_enabled(
-        std::io::stdout().is_terminal(),
-        std::env::var_os("NO_COLOR").is_some(),
-        std::env::var_os("FORCE_COLOR").is_some(),
-    )
-}
-
-fn stderr_supports_color() -> bool {
-    use std::io::IsTerminal;
+pub use baseline::BaselineDelta;
+pub use suggest::{PathCategory, Suggestion};
 
-    color_output_enabled(
-        std::io::stderr().is_terminal(),
-        std::env::var_os("NO_COLOR").is_some(),
-        std::env::var_os("FORCE_COLOR").is_some(),
-    )
-}
+/// Bumped when fields of the `--analyze-json` report change meaning;
+/// `--analyze-baseline` refuses reports from a newer schema.
+pub const ANALYSIS_SCHEMA_VERSION: u32 = 1;
 
 fn styled_text(text: &str, color: Color, bold: bool, enabled: bool) -> String {
     if !enabled {
@@ -57,10 +47,8 @@ fn styled_text(text: &str, color: Color, bold: bool, enabled: bool) -> String {
 }
 
 fn eprintln_color(color: Color, msg: &str) {
-    eprintln!(
-        "{}",
-        styled_text(msg, color, false, stderr_supports_color())
-    );
+    let colored = !diagnostics::json_enabled() && stderr_supports_color();
+    diagnostics::info(Phase::Analyze, styled_text(msg, color, false, colored));
 }
 
 #[derive(Debug, Clone, Copy, Serialize, PartialEq, Eq)]
@@ -74,6 +62,9 @@ pub enum WarningLevel {
 #[derive(Debug, Clone, Serialize)]
 pub struct Warning {
     pub level: WarningLevel,
+    /// Stable identifier for warnings that tools may want to filter on.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<&'static str>,
     pub message: String,
     pub recommendation: Option<String>,
 }
@@ -82,8 +73,39 @@ pub struct Warning {
 pub struct ObjectStat {
     pub oid: String,
     pub size: u64,
+    /// First entry of `paths`; deprecated, kept for one release for readers
+    /// of the single-path report.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub path: Option<String>,
+    /// Sorted paths the blob appeared at in history, capped at
+    /// `--analyze-max-blob-paths`; `path_count` has the uncapped number.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    #[serde(skip_serializing_if = "is_zero")]
+    pub path_count: usize,
+    /// RFC 3339 time of the commit that first added `path`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub introduced_at: Option<String>,
+    /// Share of `unique_blob_bytes`, in percent; set for blobs only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pct_of_total: Option<f64>,
+}
+
+fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+impl ObjectStat {
+    /// `path`, followed by "(+N more)" when the blob appeared elsewhere too.
+    pub fn display_path(&self) -> String {
+        let Some(path) = &self.path else {
+            return String::new();
+        };
+        match self.path_count.saturating_sub(1) {
+            0 => path.clone(),
+            more => format!("{path} (+{more} more)"),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Default)]
@@ -92,6 +114,10 @@ pub struct FileStat {
     pub size: u64,
     pub versions: usize,
     pub largest_oid: String,
+    /// Share of `unique_blob_bytes` taken by `largest_oid`, in percent.
+    pub pct_of_total: f64,
+    /// What the path looks like by name and history (vendored, generated, ...).
+    pub category: PathCategory,
 }
 
 #[derive(Debug, Clone, Serialize, Default)]
@@ -100,6 +126,17 @@ pub struct DirectoryStat {
     pub entries: usize,
 }
 
+/// Blobs under one `--analyze-depth` prefix. Directories end in `/`; a file
+/// shallower than the depth is its own prefix.
+#[derive(Debug, Clone, Serialize, Default, PartialEq, Eq)]
+pub struct PathRollup {
+    pub prefix: String,
+    /// Each distinct blob under the prefix counted once.
+    pub unique_blob_bytes: u64,
+    pub blob_count: usize,
+    pub file_count: usize,
+}
+
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct PathStat {
     pub path: String,
@@ -112,6 +149,26 @@ pub struct CommitMessageStat {
     pub length: usize,
 }
 
+/// A commit whose subject is over `warn_subject_length` characters or whose
+/// message holds NUL or other control bytes.
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct ProblematicCommitMessage {
+    pub oid: String,
+    pub subject_length: usize,
+    /// NUL or a C0 control character other than newline and tab.
+    pub has_control_chars: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Default)]
+pub struct SuspectIdentityStat {
+    pub oid: String,
+    /// "author" or "committer".
+    pub role: String,
+    pub name: String,
+    pub email: String,
+    pub reason: String,
+}
+
 #[derive(Debug, Clone, Serialize, Default)]
 pub struct RepositoryMetrics {
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -120,8 +177,21 @@ pub struct RepositoryMetrics {
     pub loose_size_bytes: u64,
     pub packed_objects: u64,
     pub packed_size_bytes: u64,
+    /// Stores read through `objects/info/alternates`; their objects are
+    /// included in the totals.
+    pub alternates: Vec<AlternateStore>,
     pub total_objects: u64,
     pub total_size_bytes: u64,
+    /// Blobs left out of the metrics by `--analyze-ignore-path`.
+    pub ignored_blob_count: u64,
+    pub pack_count: u64,
+    /// `.pack` files on disk, including ones without an index that git
+    /// ignores (e.g. left behind by interrupted fetches).
+    pub pack_file_count: usize,
+    pub largest_pack_bytes: u64,
+    pub has_multi_pack_index: bool,
+    pub has_commit_graph: bool,
+    pub has_bitmaps: bool,
     pub object_types: BTreeMap<String, u64>,
     pub tree_total_size_bytes: u64,
     pub refs_total: usize,
@@ -129,20 +199,182 @@ pub struct RepositoryMetrics {
     pub refs_tags: usize,
     pub refs_remotes: usize,
     pub refs_other: usize,
+    /// Every `refs/notes/*` ref, and the notes they hold between them.
+    pub notes_refs: Vec<String>,
+    pub notes_object_count: u64,
+    /// Uncompressed size of every distinct blob, counted once however many
+    /// paths it appears at; the denominator of `pct_of_total`.
+    pub unique_blob_bytes: u64,
+    /// Reachable blobs that are Git LFS pointer files, and the first
+    /// `--analyze-top` of their ids.
+    pub lfs_pointer_count: u64,
+    pub lfs_pointer_oids: Vec<String>,
     pub largest_blobs: Vec<ObjectStat>,
     pub largest_files: Vec<FileStat>,
+    /// Every prefix at `--analyze-depth`, largest first.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub rollup: Vec<PathRollup>,
+    /// Filters that would drop recognized vendored, generated or media paths.
+    pub suggestions: Vec<Suggestion>,
     pub largest_trees: Vec<ObjectStat>,
     pub blobs_over_threshold: Vec<ObjectStat>,
+    /// Of the largest blobs, the one whose path was added earliest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub oldest_surviving_blob: Option<ObjectStat>,
     pub directory_hotspots: Option<DirectoryStat>,
     pub longest_path: Option<PathStat>,
     pub max_commit_parents: usize,
+    /// Commits per committer-date month (`YYYY-MM`) across all refs.
+    pub commit_frequency: BTreeMap<String, u64>,
     pub oversized_commit_messages: Vec<CommitMessageStat>,
+    pub problematic_commit_messages: Vec<ProblematicCommitMessage>,
+    pub suspect_identities: Vec<SuspectIdentityStat>,
+    pub suspect_identities_total: usize,
+}
+
+impl RepositoryMetrics {
+    /// Folds `other`, from a separate analysis run, into `self` for a
+    /// combined view. Counts and sizes are summed, so objects both runs saw
+    /// are counted twice; per-object lists are deduplicated by id (files by
+    /// path) and cut back to `cfg.top`. Path suggestions are kept from
+    /// `self`, since they depend on blob paths neither run shares.
+    pub fn merge_from(&mut self, other: &RepositoryMetrics, cfg: &AnalyzeConfig) {
+        self.loose_objects += other.loose_objects;
+        self.loose_size_bytes += other.loose_size_bytes;
+        self.packed_objects += other.packed_objects;
+        self.packed_size_bytes += other.packed_size_bytes;
+        self.total_objects += other.total_objects;
+        self.total_size_bytes += other.total_size_bytes;
+        self.ignored_blob_count += other.ignored_blob_count;
+        self.pack_count += other.pack_count;
+        self.pack_file_count += other.pack_file_count;
+        self.largest_pack_bytes = self.largest_pack_bytes.max(other.largest_pack_bytes);
+        self.has_multi_pack_index |= other.has_multi_pack_index;
+        self.has_commit_graph |= other.has_commit_graph;
+        self.has_bitmaps |= other.has_bitmaps;
+        self.tree_total_size_bytes += other.tree_total_size_bytes;
+        self.refs_total += other.refs_total;
+        self.refs_heads += other.refs_heads;
+        self.refs_tags += other.refs_tags;
+        self.refs_remotes += other.refs_remotes;
+        self.refs_other += other.refs_other;
+        self.notes_object_count += other.notes_object_count;
+        self.unique_blob_bytes += other.unique_blob_bytes;
+        self.max_commit_parents = self.max_commit_parents.max(other.max_commit_parents);
+        self.suspect_identities_total += other.suspect_identities_total;
+        for (kind, count) in &other.object_types {
+            *self.object_types.entry(kind.clone()).or_default() += count;
+        }
+        for (month, count) in &other.commit_frequency {
+            *self.commit_frequency.entry(month.clone()).or_default() += count;
+        }
+        for store in &other.alternates {
+            if !self.alternates.iter().any(|a| a.path == store.path) {
+                self.alternates.push(store.clone());
+            }
+        }
+        for notes_ref in &other.notes_refs {
+            if !self.notes_refs.contains(notes_ref) {
+                self.notes_refs.push(notes_ref.clone());
+            }
+        }
+
+        extend_unique(&mut self.largest_blobs, &other.largest_blobs, |b| &b.oid);
+        extend_unique(&mut self.largest_trees, &other.largest_trees, |t| &t.oid);
+        extend_unique(
+            &mut self.blobs_over_threshold,
+            &other.blobs_over_threshold,
+            |b| &b.oid,
+        );
+        for list in [
+            &mut self.largest_blobs,
+            &mut self.largest_trees,
+            &mut self.blobs_over_threshold,
+        ] {
+            list.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.oid.cmp(&b.oid)));
+        }
+        self.largest_blobs.truncate(cfg.top);
+        self.largest_trees.truncate(cfg.top);
+        extend_unique(&mut self.largest_files, &other.largest_files, |f| &f.path);
+        self.largest_files
+            .sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        self.largest_files.truncate(cfg.top);
+        self.lfs_pointer_count += other.lfs_pointer_count;
+        extend_unique(&mut self.lfs_pointer_oids, &other.lfs_pointer_oids, |oid| {
+            oid
+        });
+        self.lfs_pointer_oids.sort();
+        self.lfs_pointer_oids.truncate(cfg.top);
+        for other_prefix in &other.rollup {
+            match self
+                .rollup
+                .iter_mut()
+                .find(|r| r.prefix == other_prefix.prefix)
+            {
+                Some(r) => {
+                    r.unique_blob_bytes += other_prefix.unique_blob_bytes;
+                    r.blob_count += other_prefix.blob_count;
+                    r.file_count += other_prefix.file_count;
+                }
+                None => self.rollup.push(other_prefix.clone()),
+            }
+        }
+        sort_rollup(&mut self.rollup);
+        apply_size_shares(self);
+
+        extend_unique(
+            &mut self.oversized_commit_messages,
+            &other.oversized_commit_messages,
+            |c| &c.oid,
+        );
+        extend_unique(
+            &mut self.problematic_commit_messages,
+            &other.problematic_commit_messages,
+            |c| &c.oid,
+        );
+        for identity in &other.suspect_identities {
+            if !self
+                .suspect_identities
+                .iter()
+                .any(|s| s.oid == identity.oid && s.role == identity.role)
+            {
+                self.suspect_identities.push(identity.clone());
+            }
+        }
+        if self.oldest_surviving_blob.is_none() {
+            self.oldest_surviving_blob = other.oldest_surviving_blob.clone();
+        }
+        if other.directory_hotspots.as_ref().map(|d| d.entries)
+            > self.directory_hotspots.as_ref().map(|d| d.entries)
+        {
+            self.directory_hotspots = other.directory_hotspots.clone();
+        }
+        if other.longest_path.as_ref().map(|p| p.length)
+            > self.longest_path.as_ref().map(|p| p.length)
+        {
+            self.longest_path = other.longest_path.clone();
+        }
+    }
+}
+
+/// Appends the entries of `from` whose key is not in `into` yet.
+fn extend_unique<T: Clone>(into: &mut Vec<T>, from: &[T], key: impl Fn(&T) -> &String) {
+    let mut seen: HashSet<String> = into.iter().map(|item| key(item).clone()).collect();
+    for item in from {
+        if seen.insert(key(item).clone()) {
+            into.push(item.clone());
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize)]
 pub struct AnalysisReport {
+    pub schema_version: u32,
     pub metrics: RepositoryMetrics,
     pub warnings: Vec<Warning>,
+    /// Comparison with `--analyze-baseline`, when given.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta: Option<BaselineDelta>,
 }
 
 #[cfg(test)]
@@ -157,7 +389,7 @@ pub fn run(opts: &Options) -> io::Result<()> {
     debug_assert_eq!(opts.mode, Mode::Analyze);
     let report = generate_report(opts)?;
     if opts.analyze.json {
-        let json = serde_json::to_string_pretty(&report).map_err(to_io_error)?;
+        let json = report_to_json(&report, opts.analyze.json_compact)?;
         println!("{}", json);
     } else {
         print_human(&report, &opts.analyze);
@@ -180,16 +412,22 @@ pub fn run(opts: &Options) -> io::Result<()> {
             let report_path = debug_dir.join("report.txt");
             let mut f = File::create(&report_path)?;
             write_text_report(&mut f, &report)?;
-            eprintln!("Analysis report written to {}", report_path.display());
+            diagnostics::info(
+                Phase::Analyze,
+                format!("Analysis report written to {}", report_path.display()),
+            );
         }
 
         // Write JSON report
         if opts.write_report_json {
             let json_path = debug_dir.join("report.json");
             let mut f = File::create(&json_path)?;
-            let json = serde_json::to_string_pretty(&report).map_err(to_io_error)?;
+            let json = report_to_json(&report, opts.analyze.json_compact)?;
             f.write_all(json.as_bytes())?;
-            eprintln!("Analysis JSON report written to {}", json_path.display());
+            diagnostics::info(
+                Phase::Analyze,
+                format!("Analysis JSON report written to {}", json_path.display()),
+            );
         }
     }
 
@@ -222,6 +460,25 @@ fn write_text_report<W: Write>(f: &mut W, report: &AnalysisReport) -> io::Result
         "Packed objects: {} ({} bytes)",
         m.packed_objects, m.packed_size_bytes
     )?;
+    for alternate in &m.alternates {
+        writeln!(
+            f,
+            "Alternate objects: {} ({} bytes) in {}",
+            alternate.objects, alternate.size_bytes, alternate.path
+        )?;
+    }
+    if m.ignored_blob_count > 0 {
+        writeln!(f, "Ignored blobs: {}", m.ignored_blob_count)?;
+    }
+    writeln!(f)?;
+
+    writeln!(f, "=== Packfiles ===")?;
+    writeln!(f, "Packs: {}", m.pack_count)?;
+    writeln!(f, "Pack files: {}", m.pack_file_count)?;
+    writeln!(f, "Largest pack: {} bytes", m.largest_pack_bytes)?;
+    writeln!(f, "Multi-pack-index: {}", yes_no(m.has_multi_pack_index))?;
+    writeln!(f, "Commit-graph: {}", yes_no(m.has_commit_graph))?;
+    writeln!(f, "Bitmaps: {}", yes_no(m.has_bitmaps))?;
     writeln!(f)?;
 
     writeln!(f, "=== Objects ===")?;
@@ -246,6 +503,9 @@ fn write_text_report<W: Write>(f: &mut W, report: &AnalysisReport) -> io::Result
         m.object_types.get("tag").copied().unwrap_or(0)
     )?;
     writeln!(f, "Max commit parents: {}", m.max_commit_parents)?;
+    if let Some((from, to, line)) = commit_sparkline(&m.commit_frequency) {
+        writeln!(f, "Commit activity ({from} .. {to}): {line}")?;
+    }
     writeln!(f)?;
 
     writeln!(f, "=== References ===")?;
@@ -254,44 +514,121 @@ fn write_text_report<W: Write>(f: &mut W, report: &AnalysisReport) -> io::Result
     writeln!(f, "  Tags: {}", m.refs_tags)?;
     writeln!(f, "  Remotes: {}", m.refs_remotes)?;
     writeln!(f, "  Other: {}", m.refs_other)?;
+    writeln!(
+        f,
+        "  Notes: {} in {} notes refs",
+        m.notes_object_count,
+        m.notes_refs.len()
+    )?;
     writeln!(f)?;
 
     if !m.largest_blobs.is_empty() {
-        writeln!(f, "=== Largest Blobs (Top {}) ===", m.largest_blobs.len())?;
+        writeln!(
+            f,
+            "=== Largest Blobs (Top {}, % of {} bytes of unique blobs) ===",
+            m.largest_blobs.len(),
+            m.unique_blob_bytes
+        )?;
+        let cumulative = cumulative_pct(
+            m.largest_blobs.iter().map(|b| (b.oid.as_str(), b.size)),
+            m.unique_blob_bytes,
+        );
         for (i, blob) in m.largest_blobs.iter().enumerate() {
             writeln!(
                 f,
-                "  {}. OID: {}, Size: {} bytes",
+                "  {}. OID: {}, Size: {} bytes ({:.1}%, cumulative {:.1}%)",
                 i + 1,
                 blob.oid,
-                blob.size
+                blob.size,
+                blob.pct_of_total.unwrap_or_default(),
+                cumulative[i]
             )?;
-            if let Some(ref path) = blob.path {
-                writeln!(f, "      Path: {}", path)?;
+            if blob.path.is_some() {
+                writeln!(f, "      Path: {}", blob.display_path())?;
             }
         }
         writeln!(f)?;
     }
 
+    if let Some(ref blob) = m.oldest_surviving_blob {
+        writeln!(f, "=== Oldest Surviving Blob ===")?;
+        writeln!(
+            f,
+            "  OID: {}, Size: {} bytes, Introduced: {}",
+            blob.oid,
+            blob.size,
+            blob.introduced_at.as_deref().unwrap_or("unknown")
+        )?;
+        if blob.path.is_some() {
+            writeln!(f, "      Path: {}", blob.display_path())?;
+        }
+        writeln!(f)?;
+    }
+
     if !m.largest_files.is_empty() {
         writeln!(
             f,
-            "=== Largest Files by History (Top {}) ===",
-            m.largest_files.len()
+            "=== Largest Files by History (Top {}, % of {} bytes of unique blobs) ===",
+            m.largest_files.len(),
+            m.unique_blob_bytes
         )?;
+        let cumulative = cumulative_pct(
+            m.largest_files
+                .iter()
+                .map(|file| (file.largest_oid.as_str(), file.size)),
+            m.unique_blob_bytes,
+        );
         for (i, file) in m.largest_files.iter().enumerate() {
             writeln!(
                 f,
-                "  {}. Path: {}, Size: {} bytes, Versions: {}",
+                "  {}. Path: {}, Size: {} bytes ({:.1}%, cumulative {:.1}%), Versions: {}",
                 i + 1,
                 file.path,
                 file.size,
+                file.pct_of_total,
+                cumulative[i],
                 file.versions
             )?;
         }
         writeln!(f)?;
     }
 
+    if !m.rollup.is_empty() {
+        writeln!(
+            f,
+            "=== Size by Path Prefix ({} prefixes, % of {} bytes of unique blobs) ===",
+            m.rollup.len(),
+            m.unique_blob_bytes
+        )?;
+        for r in &m.rollup {
+            writeln!(
+                f,
+                "  {}: {} bytes ({:.1}%), Blobs: {}, Files: {}",
+                r.prefix,
+                r.unique_blob_bytes,
+                pct_of_total(r.unique_blob_bytes, m.unique_blob_bytes),
+                r.blob_count,
+                r.file_count
+            )?;
+        }
+        writeln!(f)?;
+    }
+
+    if !m.suggestions.is_empty() {
+        writeln!(f, "=== Suggested filters ===")?;
+        for (i, suggestion) in m.suggestions.iter().enumerate() {
+            writeln!(
+                f,
+                "  {}. {} ({}): saves about {} bytes",
+                i + 1,
+                suggestion.command,
+                suggestion.category.as_str(),
+                suggestion.estimated_bytes
+            )?;
+        }
+        writeln!(f)?;
+    }
+
     if !m.blobs_over_threshold.is_empty() {
         writeln!(
             f,
@@ -306,8 +643,8 @@ fn write_text_report<W: Write>(f: &mut W, report: &AnalysisReport) -> io::Result
                 blob.oid,
                 blob.size
             )?;
-            if let Some(ref path) = blob.path {
-                writeln!(f, "      Path: {}", path)?;
+            if blob.path.is_some() {
+                writeln!(f, "      Path: {}", blob.display_path())?;
             }
         }
         writeln!(f)?;
@@ -343,6 +680,43 @@ fn write_text_report<W: Write>(f: &mut W, report: &AnalysisReport) -> io::Result
         writeln!(f)?;
     }
 
+    if !m.problematic_commit_messages.is_empty() {
+        writeln!(f, "=== Problematic Commit Messages ===")?;
+        for (i, msg) in m.problematic_commit_messages.iter().enumerate() {
+            writeln!(
+                f,
+                "  {}. Commit: {}, Subject: {} chars, Control characters: {}",
+                i + 1,
+                msg.oid,
+                msg.subject_length,
+                if msg.has_control_chars { "yes" } else { "no" }
+            )?;
+        }
+        writeln!(f)?;
+    }
+
+    if !m.suspect_identities.is_empty() {
+        writeln!(
+            f,
+            "=== Suspect Identities (Top {} of {}) ===",
+            m.suspect_identities.len(),
+            m.suspect_identities_total
+        )?;
+        for (i, ident) in m.suspect_identities.iter().enumerate() {
+            writeln!(
+                f,
+                "  {}. Commit: {}, {}: {} <{}> ({})",
+                i + 1,
+                ident.oid,
+                ident.role,
+                ident.name,
+                ident.email,
+                ident.reason
+            )?;
+        }
+        writeln!(f)?;
+    }
+
     writeln!(f, "=== Warnings ({}) ===", report.warnings.len())?;
     if report.warnings.is_empty() {
         writeln!(f, "  No warnings.")?;
@@ -368,12 +742,25 @@ pub fn generate_report(opts: &Options) -> io::Result<AnalysisReport> {
     // Avoid Windows verbatim (\\?\) paths which can confuse external tools like Git when
     // passed via command-line flags. Use the provided path directly.
     let repo = opts.source.clone();
-    let metrics = collect_metrics(&repo, &opts.analyze)?;
+    let metrics = collect_metrics(&repo, &opts.analyze, opts.cancel.as_ref())?;
     let warnings = evaluate_warnings(&metrics, &opts.analyze.thresholds);
-    Ok(AnalysisReport { metrics, warnings })
+    let mut report = AnalysisReport {
+        schema_version: ANALYSIS_SCHEMA_VERSION,
+        metrics,
+        warnings,
+        delta: None,
+    };
+    if let Some(path) = &opts.analyze.baseline {
+        report.delta = Some(baseline::compute(&baseline::load(path)?, &report));
+    }
+    Ok(report)
 }
 
-fn collect_metrics(repo: &Path, cfg: &AnalyzeConfig) -> io::Result<RepositoryMetrics> {
+fn collect_metrics(
+    repo: &Path,
+    cfg: &AnalyzeConfig,
+    cancel: Option<&CancellationToken>,
+) -> io::Result<RepositoryMetrics> {
     let _start_time = Instant::now();
     let mut metrics = RepositoryMetrics {
         workdir: Some(repo.display().to_string()),
@@ -384,19 +771,22 @@ fn collect_metrics(repo: &Path, cfg: &AnalyzeConfig) -> io::Result<RepositoryMet
 
     // First, get all blob sizes in one pass
     eprintln_color(Color::Cyan, "[*] Gathering blob sizes...");
-    let (unpacked_size, packed_size) = gather_all_blob_sizes(repo)?;
+    let AllBlobSizes {
+        unpacked_size,
+        packed_size,
+        lfs_pointers,
+    } = gather_all_blob_sizes(repo)?;
 
     // Initialize metrics with blob sizes - pre-allocate reasonable capacities
     let estimated_blobs = unpacked_size.len();
-    let _blob_paths: HashMap<String, Vec<String>> = HashMap::new();
     let mut stats = StatsCollection {
         blob_paths: HashMap::with_capacity(estimated_blobs),
-        all_names: HashSet::with_capacity(estimated_blobs * 2), // Rough estimate
         num_commits: 0,
         max_parents: 0,
     };
 
     // Then process commit history
+    cancel::check(cancel)?;
     eprintln_color(Color::Cyan, "[*] Processing commit history...");
     gather_commit_history(repo, &mut stats)?;
 
@@ -405,58 +795,57 @@ fn collect_metrics(repo: &Path, cfg: &AnalyzeConfig) -> io::Result<RepositoryMet
         stats.max_parents = maxp;
     }
 
+    metrics.commit_frequency = compute_commit_frequency_histogram(repo)?;
+
     // Now map blob OIDs to paths efficiently using the collected blob sizes
+    cancel::check(cancel)?;
     eprintln_color(Color::Cyan, "[*] Mapping blob paths (streaming)...");
-    let blob_oids: HashSet<String> = unpacked_size.keys().cloned().collect();
-
-    // Use streaming approach to avoid loading all objects into memory
-    let mut blob_path_map: HashMap<String, String> = HashMap::new();
     let (mut reader, mut child) =
         run_git_capture_stream(repo, &["rev-list", "--objects", "--all"])?;
-
-    let mut line_buf = String::new();
-    while reader.read_line(&mut line_buf)? > 0 {
-        let line = line_buf.trim_end();
-        let mut parts = line.splitn(2, ' ');
-        if let (Some(oid), Some(path)) = (parts.next(), parts.next()) {
-            if blob_oids.contains(oid) && !path.is_empty() {
-                blob_path_map.insert(oid.to_string(), path.to_string());
-                if blob_path_map.len() >= blob_oids.len() {
-                    break;
-                }
-            }
+    let scan = scan_blob_paths(&mut reader, &unpacked_size)?;
+    drop(reader);
+    if scan.complete {
+        // Every blob is accounted for; rev-list may still be writing trees
+        // and commits into the closed pipe, so stop it instead of waiting.
+        let _ = child.kill();
+        let _ = child.wait();
+    } else {
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "git rev-list --objects --all failed: {}",
+                status
+            )));
         }
-        line_buf.clear();
-    }
-
-    // Wait for git command to complete
-    let status = child.wait()?;
-    if !status.success() {
-        return Err(io::Error::other(format!(
-            "git rev-list --objects --all failed: {}",
-            status
-        )));
     }
 
     eprintln_color(
         Color::Green,
-        &format!("[*] Found {} blob-to-path mappings", blob_path_map.len()),
+        &format!("[*] Found {} blob-to-path mappings", scan.mapped),
     );
-
-    // Convert path map (oid -> path) to blob_paths structure (oid -> Vec<path>)
-    for (oid, path) in blob_path_map {
-        stats
-            .blob_paths
-            .entry(oid.clone())
-            .or_default()
-            .push(path.clone());
-        stats.all_names.insert(path);
+    stats.blob_paths = scan.blob_paths;
+
+    cancel::check(cancel)?;
+    // Quick repository stats: count-objects and for-each-ref are independent
+    // git calls, so run them side by side.
+    let (footprint, refs) = thread::scope(|scope| {
+        let footprint = scope.spawn(|| collect_footprint(repo));
+        let refs = scope.spawn(|| collect_refs(repo));
+        (join_collector(footprint), join_collector(refs))
+    });
+    footprint?.merge_into(&mut metrics);
+    refs?.merge_into(&mut metrics);
+    gather_pack_layout(repo, &mut metrics)?;
+
+    if !cfg.analyze_ignore_globs.is_empty() {
+        exclude_ignored_blobs(
+            &mut stats,
+            &cfg.analyze_ignore_globs,
+            &packed_size,
+            &mut metrics,
+        );
     }
 
-    // Quick repository stats
-    gather_footprint(repo, &mut metrics)?;
-    gather_refs(repo, &mut metrics)?;
-
     // Update metrics from gathered data
     metrics
         .object_types
@@ -475,20 +864,57 @@ fn collect_metrics(repo: &Path, cfg: &AnalyzeConfig) -> io::Result<RepositoryMet
             .get(oid)
             .copied()
             .unwrap_or_else(|| packed_size.get(oid).copied().unwrap_or(0));
+        metrics.unique_blob_bytes += actual_size;
         push_top(&mut largest_blobs, cfg.top, actual_size, oid);
         if actual_size >= cfg.thresholds.warn_blob_bytes {
             push_top(&mut threshold_hits, cfg.top, actual_size, oid);
         }
     }
 
+    let mut lfs_pointer_oids: Vec<String> = lfs_pointers
+        .into_iter()
+        .filter(|oid| stats.blob_paths.contains_key(oid))
+        .collect();
+    metrics.lfs_pointer_count = lfs_pointer_oids.len() as u64;
+    lfs_pointer_oids.sort();
+    lfs_pointer_oids.truncate(cfg.top);
+    metrics.lfs_pointer_oids = lfs_pointer_oids;
+
     // Convert to ObjectStat with paths
-    metrics.largest_blobs = heap_to_object_stats_with_paths(largest_blobs, &stats.blob_paths);
-    metrics.blobs_over_threshold =
-        heap_to_object_stats_with_paths(threshold_hits, &stats.blob_paths);
+    metrics.largest_blobs = heap_to_object_stats(largest_blobs);
+    metrics.blobs_over_threshold = heap_to_object_stats(threshold_hits);
+    cancel::check(cancel)?;
+    fill_blob_paths(repo, &stats.blob_paths, cfg.max_blob_paths, &mut metrics)?;
 
     // Group blobs by file path to find unique files
     metrics.largest_files =
         compute_largest_files(&stats.blob_paths, &unpacked_size, &packed_size, cfg.top);
+    if let Some(depth) = cfg.depth {
+        metrics.rollup = compute_path_rollup(
+            &stats.blob_paths,
+            |oid| {
+                unpacked_size
+                    .get(oid)
+                    .or_else(|| packed_size.get(oid))
+                    .copied()
+                    .unwrap_or(0)
+            },
+            depth,
+        );
+    }
+    metrics.suggestions = suggest::suggest(
+        &stats.blob_paths,
+        |oid| {
+            unpacked_size
+                .get(oid)
+                .or_else(|| packed_size.get(oid))
+                .copied()
+                .unwrap_or(0)
+        },
+        cfg.top,
+    );
+    apply_size_shares(&mut metrics);
+    metrics.oldest_surviving_blob = detect_oldest_surviving_blob(repo, &metrics.largest_blobs);
 
     // Tree inventory via cat-file for counts and top sizes (lightweight)
     eprintln_color(Color::Cyan, "[*] Gathering tree inventory...");
@@ -496,9 +922,15 @@ fn collect_metrics(repo: &Path, cfg: &AnalyzeConfig) -> io::Result<RepositoryMet
     // Keep a quick HEAD snapshot for context (simplified)
     eprintln_color(Color::Cyan, "[*] Analyzing working directory...");
 
-    // Gather oversized commit messages based on configured threshold
-    metrics.oversized_commit_messages =
-        gather_oversized_commit_messages(repo, cfg.thresholds.warn_commit_msg_bytes)?;
+    cancel::check(cancel)?;
+    // Gather oversized and otherwise problematic commit messages
+    let messages = gather_commit_message_stats(repo, &cfg.thresholds)?;
+    metrics.oversized_commit_messages = messages.oversized;
+    metrics.problematic_commit_messages = messages.problematic;
+
+    let (suspects, suspects_total) = gather_suspect_identities(repo, &cfg.bad_identities, cfg.top)?;
+    metrics.suspect_identities = suspects;
+    metrics.suspect_identities_total = suspects_total;
 
     eprintln_color(Color::Green, "[*] Analysis complete!");
     Ok(metrics)
@@ -506,27 +938,273 @@ fn collect_metrics(repo: &Path, cfg: &AnalyzeConfig) -> io::Result<RepositoryMet
 
 struct StatsCollection {
     blob_paths: HashMap<String, Vec<String>>,
-    all_names: HashSet<String>,
     num_commits: u64,
     max_parents: usize,
 }
 
-fn gather_footprint(repo: &Path, metrics: &mut RepositoryMetrics) -> io::Result<()> {
-    let output = run_git_capture(repo, &["count-objects", "-v"])?;
+/// Outcome of [`scan_blob_paths`].
+struct BlobPathScan {
+    /// One entry per reachable blob: the last path rev-list listed it at,
+    /// or no path for blobs only reachable directly, e.g. through a tag.
+    blob_paths: HashMap<String, Vec<String>>,
+    /// Number of blobs that were given a path.
+    mapped: usize,
+    /// Whether every wanted blob got a path before the stream ended, in
+    /// which case the rest of the output was not read.
+    complete: bool,
+}
+
+/// Where a wanted blob stands while scanning `rev-list --objects` output.
+#[derive(Clone, Copy)]
+enum BlobSlot {
+    Unseen,
+    Pathless,
+    Path(u32),
+}
+
+/// Maps the blobs in `wanted` to a path from `git rev-list --objects --all`
+/// output.
+///
+/// The stream lists every commit, tree and blob in history, so it is read
+/// into one reusable byte buffer and looked up by borrowed OID bytes; only
+/// lines for wanted blobs allocate, and identical paths are stored once.
+/// The early exit counts down the blobs that have no path yet; a blob listed
+/// without a path stays pending, since a later line may still name one.
+fn scan_blob_paths<R: BufRead>(
+    reader: &mut R,
+    wanted: &HashMap<String, u64>,
+) -> io::Result<BlobPathScan> {
+    let mut slots: HashMap<&[u8], BlobSlot> = wanted
+        .keys()
+        .map(|oid| (oid.as_bytes(), BlobSlot::Unseen))
+        .collect();
+    let mut remaining = slots.len();
+    let mut path_ids: HashMap<Box<[u8]>, u32> = HashMap::new();
+    let mut paths: Vec<String> = Vec::new();
+    let mut line = Vec::with_capacity(256);
+
+    while remaining > 0 {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let trimmed = line.trim_ascii_end();
+        let (oid, path) = match trimmed.iter().position(|&b| b == b' ') {
+            Some(pos) => (&trimmed[..pos], &trimmed[pos + 1..]),
+            None => (trimmed, &[][..]),
+        };
+        let Some(slot) = slots.get_mut(oid) else {
+            continue;
+        };
+        if path.is_empty() {
+            if matches!(slot, BlobSlot::Unseen) {
+                *slot = BlobSlot::Pathless;
+            }
+            continue;
+        }
+        let id = match path_ids.get(path) {
+            Some(&id) => id,
+            None => {
+                let id = paths.len() as u32;
+                paths.push(String::from_utf8_lossy(path).into_owned());
+                path_ids.insert(path.into(), id);
+                id
+            }
+        };
+        // Later paths replace earlier ones, as the map insert used to.
+        if !matches!(slot, BlobSlot::Path(_)) {
+            remaining -= 1;
+        }
+        *slot = BlobSlot::Path(id);
+    }
+
+    let mut blob_paths = HashMap::with_capacity(slots.len());
+    let mut mapped = 0;
+    for (oid, slot) in slots {
+        let entry = match slot {
+            BlobSlot::Unseen => continue,
+            BlobSlot::Pathless => Vec::new(),
+            BlobSlot::Path(id) => {
+                mapped += 1;
+                vec![paths[id as usize].clone()]
+            }
+        };
+        // Keys borrow from `wanted`, which only ever holds UTF-8 hex.
+        blob_paths.insert(String::from_utf8_lossy(oid).into_owned(), entry);
+    }
+    Ok(BlobPathScan {
+        blob_paths,
+        mapped,
+        complete: remaining == 0,
+    })
+}
+
+/// Drops blobs whose path matches an `--analyze-ignore-path` glob from the
+/// collected stats, taking them out of the object totals as well.
+fn exclude_ignored_blobs(
+    stats: &mut StatsCollection,
+    globs: &[Vec<u8>],
+    packed_size: &HashMap<String, u64>,
+    metrics: &mut RepositoryMetrics,
+) {
+    stats.blob_paths.retain(|oid, paths| {
+        let ignored = paths.iter().any(|path| {
+            globs
+                .iter()
+                .any(|glob| glob_match_bytes(glob, path.as_bytes()))
+        });
+        if ignored {
+            metrics.ignored_blob_count += 1;
+            metrics.total_objects = metrics.total_objects.saturating_sub(1);
+            metrics.total_size_bytes = metrics
+                .total_size_bytes
+                .saturating_sub(packed_size.get(oid).copied().unwrap_or(0));
+        }
+        !ignored
+    });
+}
+
+/// Object store footprint as reported by `git count-objects -v`. The loose
+/// and packed counts cover the repository's own store; stores borrowed via
+/// `objects/info/alternates` are listed separately.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FootprintData {
+    pub loose_objects: u64,
+    pub loose_size_bytes: u64,
+    pub packed_objects: u64,
+    pub packed_size_bytes: u64,
+    pub pack_count: u64,
+    pub alternates: Vec<AlternateStore>,
+}
+
+/// An object store the repository reads through `objects/info/alternates`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct AlternateStore {
+    pub path: String,
+    pub objects: u64,
+    pub size_bytes: u64,
+}
+
+impl FootprintData {
+    fn merge_into(self, metrics: &mut RepositoryMetrics) {
+        metrics.loose_objects = self.loose_objects;
+        metrics.loose_size_bytes = self.loose_size_bytes;
+        metrics.packed_objects = self.packed_objects;
+        metrics.packed_size_bytes = self.packed_size_bytes;
+        metrics.pack_count = self.pack_count;
+        metrics.total_objects = self.loose_objects
+            + self.packed_objects
+            + self.alternates.iter().map(|a| a.objects).sum::<u64>();
+        metrics.total_size_bytes = self.loose_size_bytes
+            + self.packed_size_bytes
+            + self.alternates.iter().map(|a| a.size_bytes).sum::<u64>();
+        metrics.alternates = self.alternates;
+    }
+}
+
+/// Ref counts by namespace.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RefData {
+    pub total: usize,
+    pub heads: usize,
+    pub tags: usize,
+    pub remotes: usize,
+    pub other: usize,
+    pub notes_refs: Vec<String>,
+    pub notes_object_count: u64,
+}
+
+impl RefData {
+    fn merge_into(self, metrics: &mut RepositoryMetrics) {
+        metrics.refs_total = self.total;
+        metrics.refs_heads = self.heads;
+        metrics.refs_tags = self.tags;
+        metrics.refs_remotes = self.remotes;
+        metrics.refs_other = self.other;
+        metrics.notes_refs = self.notes_refs;
+        metrics.notes_object_count = self.notes_object_count;
+    }
+}
+
+/// Re-raises a collector thread's panic on the calling thread.
+fn join_collector<T>(handle: thread::ScopedJoinHandle<'_, io::Result<T>>) -> io::Result<T> {
+    handle
+        .join()
+        .unwrap_or_else(|payload| std::panic::resume_unwind(payload))
+}
+
+pub fn collect_footprint(repo: &Path) -> io::Result<FootprintData> {
+    let mut data = parse_count_objects(&run_git_capture(repo, &["count-objects", "-v"])?);
+    // count-objects only counts the primary store; point it at each
+    // alternate in turn to size those too.
+    for dir in gitutil::alternate_object_dirs(repo)? {
+        let out = Command::new("git")
+            .current_dir(repo)
+            .args(["count-objects", "-v"])
+            .env("GIT_OBJECT_DIRECTORY", &dir)
+            .env_remove("GIT_ALTERNATE_OBJECT_DIRECTORIES")
+            .stderr(Stdio::inherit())
+            .output()?;
+        if !out.status.success() {
+            return Err(io::Error::other(format!(
+                "git count-objects -v failed for alternate {}",
+                dir.display()
+            )));
+        }
+        let store = parse_count_objects(&String::from_utf8_lossy(&out.stdout));
+        data.alternates.push(AlternateStore {
+            path: dir.display().to_string(),
+            objects: store.loose_objects + store.packed_objects,
+            size_bytes: store.loose_size_bytes + store.packed_size_bytes,
+        });
+    }
+    Ok(data)
+}
+
+fn parse_count_objects(output: &str) -> FootprintData {
+    let mut data = FootprintData::default();
     for line in output.lines() {
         let mut parts = line.splitn(2, ':');
         let key = parts.next().unwrap_or("").trim();
         let value = parts.next().unwrap_or("").trim();
         match key {
-            "count" => metrics.loose_objects = value.parse::<u64>().unwrap_or(0),
-            "size" => metrics.loose_size_bytes = value.parse::<u64>().unwrap_or(0) * 1024,
-            "in-pack" => metrics.packed_objects = value.parse::<u64>().unwrap_or(0),
-            "size-pack" => metrics.packed_size_bytes = value.parse::<u64>().unwrap_or(0) * 1024,
+            "count" => data.loose_objects = value.parse::<u64>().unwrap_or(0),
+            "size" => data.loose_size_bytes = value.parse::<u64>().unwrap_or(0) * 1024,
+            "in-pack" => data.packed_objects = value.parse::<u64>().unwrap_or(0),
+            "size-pack" => data.packed_size_bytes = value.parse::<u64>().unwrap_or(0) * 1024,
+            "packs" => data.pack_count = value.parse::<u64>().unwrap_or(0),
             _ => {}
         }
     }
-    metrics.total_objects = metrics.loose_objects + metrics.packed_objects;
-    metrics.total_size_bytes = metrics.loose_size_bytes + metrics.packed_size_bytes;
+    data
+}
+
+/// Pack layout details `count-objects` does not report: the number of pack
+/// files, the largest pack and whether the multi-pack-index, commit-graph and reachability bitmaps exist.
+fn gather_pack_layout(repo: &Path, metrics: &mut RepositoryMetrics) -> io::Result<()> {
+    let objects_dir = gitutil::git_common_dir(repo)?.join("objects");
+    let pack_dir = objects_dir.join("pack");
+    if let Ok(entries) = std::fs::read_dir(&pack_dir) {
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name.ends_with(".pack") {
+                metrics.pack_file_count += 1;
+                let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+                metrics.largest_pack_bytes = metrics.largest_pack_bytes.max(size);
+            } else if name.ends_with(".bitmap") {
+                metrics.has_bitmaps = true;
+            } else if name == "multi-pack-index" {
+                metrics.has_multi_pack_index = true;
+            }
+        }
+    }
+    let info_dir = objects_dir.join("info");
+    metrics.has_commit_graph = info_dir.join("commit-graph").is_file()
+        || info_dir
+            .join("commit-graphs")
+            .join("commit-graph-chain")
+            .is_file();
     Ok(())
 }
 
@@ -568,27 +1246,126 @@ fn collect_blob_sizes_from_reader<R: BufRead>(reader: &mut R) -> io::Result<Blob
     })
 }
 
-fn gather_refs(repo: &Path, metrics: &mut RepositoryMetrics) -> io::Result<()> {
-    let refs = gitutil::get_all_refs(repo)?;
-    for name in refs.keys() {
-        let name = name.as_str();
-        metrics.refs_total += 1;
+pub fn collect_refs(repo: &Path) -> io::Result<RefData> {
+    let mut data = RefData::default();
+    crate::gitutil::stream_all_refs(repo, |name, _oid| {
+        data.total += 1;
         if name.starts_with("refs/heads/") {
-            metrics.refs_heads += 1;
+            data.heads += 1;
         } else if name.starts_with("refs/tags/") {
-            metrics.refs_tags += 1;
+            data.tags += 1;
         } else if name.starts_with("refs/remotes/") {
-            metrics.refs_remotes += 1;
+            data.remotes += 1;
         } else {
-            metrics.refs_other += 1;
+            data.other += 1;
         }
+        Ok(())
+    })?;
+    data.notes_refs = crate::gitutil::list_notes_refs(repo)?;
+    for notes_ref in &data.notes_refs {
+        let listed = run_git_capture(repo, &["notes", "--ref", notes_ref, "list"])?;
+        data.notes_object_count += listed.lines().count() as u64;
+    }
+    Ok(data)
+}
+
+/// First line of every Git LFS pointer file (spec v1).
+const LFS_POINTER_PREFIX: &[u8] = b"version https://git-lfs.github.com/spec/v1\n";
+/// LFS pointer files are always smaller than this, so bigger blobs are
+/// never read.
+const LFS_POINTER_MAX_BYTES: u64 = 1024;
+
+/// Which of `candidates` are LFS pointers, from one `git cat-file --batch`
+/// fed every oid at once. A thread writes the oids while the answers are
+/// read, so neither pipe fills up.
+fn find_lfs_pointers(repo: &Path, candidates: &[String]) -> io::Result<Vec<String>> {
+    if candidates.is_empty() {
+        return Ok(Vec::new());
     }
-    Ok(())
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["cat-file", "--batch"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let stdin = child
+        .stdin
+        .take()
+        .ok_or_else(|| io::Error::other("failed to open git cat-file stdin"))?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| io::Error::other("failed to read git cat-file stdout"))?;
+    let pointers = std::thread::scope(|scope| {
+        let writer = scope.spawn(move || -> io::Result<()> {
+            let mut stdin = io::BufWriter::new(stdin);
+            for oid in candidates {
+                writeln!(stdin, "{oid}")?;
+            }
+            stdin.flush()
+        });
+        let pointers = read_lfs_pointers(&mut BufReader::new(stdout), candidates);
+        let written = writer
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("git cat-file writer panicked")));
+        // A failed read explains a broken pipe on the writer side.
+        let pointers = pointers?;
+        written?;
+        Ok::<_, io::Error>(pointers)
+    })?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "git cat-file --batch failed: {}",
+            status
+        )));
+    }
+    Ok(pointers)
+}
+
+/// Reads one `git cat-file --batch` answer per candidate, in order.
+fn read_lfs_pointers<R: BufRead>(reader: &mut R, candidates: &[String]) -> io::Result<Vec<String>> {
+    let mut pointers = Vec::new();
+    let mut header = String::new();
+    let mut payload = Vec::new();
+    for oid in candidates {
+        header.clear();
+        if reader.read_line(&mut header)? == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "git cat-file --batch exited early",
+            ));
+        }
+        if header.trim_end().ends_with(" missing") {
+            continue;
+        }
+        let size = header
+            .split_whitespace()
+            .nth(2)
+            .and_then(|size| size.parse::<usize>().ok())
+            .ok_or_else(|| io::Error::other(format!("bad cat-file header: {header:?}")))?;
+        // The payload is followed by a newline.
+        payload.resize(size + 1, 0);
+        reader.read_exact(&mut payload)?;
+        if payload.starts_with(LFS_POINTER_PREFIX) {
+            pointers.push(oid.clone());
+        }
+    }
+    Ok(pointers)
+}
+
+struct AllBlobSizes {
+    unpacked_size: HashMap<String, u64>,
+    packed_size: HashMap<String, u64>,
+    /// Blobs whose content is an LFS pointer, reachable or not.
+    lfs_pointers: Vec<String>,
 }
 
-// History-wide metrics via single rev-list | diff-tree pipeline
-fn gather_all_blob_sizes(repo: &Path) -> io::Result<(HashMap<String, u64>, HashMap<String, u64>)> {
-    let start_time = Instant::now();
+// History-wide metrics via single rev-list | diff-tree pipeline. Blobs small
+// enough to be LFS pointers are read in one batch once the scan is done.
+fn gather_all_blob_sizes(repo: &Path) -> io::Result<AllBlobSizes> {
     let (mut reader, mut child) = run_git_capture_stream(
         repo,
         &[
@@ -603,7 +1380,8 @@ fn gather_all_blob_sizes(repo: &Path) -> io::Result<(HashMap<String, u64>, HashM
     let mut packed_size = HashMap::with_capacity(100_000);
     let mut blob_count = 0usize;
     let mut processed_objects = 0usize;
-    let mut progress_output_enabled = true;
+    let mut lfs_candidates = Vec::new();
+    let mut progress = Progress::stderr("Processing objects", None);
     let mut line_buf = String::new();
 
     while reader.read_line(&mut line_buf)? > 0 {
@@ -623,26 +1401,17 @@ fn gather_all_blob_sizes(repo: &Path) -> io::Result<(HashMap<String, u64>, HashM
                         unpacked_size.insert(sha.to_string(), objsize);
                         packed_size.insert(sha.to_string(), objdisksize);
                         blob_count += 1;
+                        if objsize >= LFS_POINTER_PREFIX.len() as u64
+                            && objsize < LFS_POINTER_MAX_BYTES
+                        {
+                            lfs_candidates.push(sha.to_string());
+                        }
                     }
                 }
             }
             processed_objects += 1;
 
-            // Keep lightweight progress reporting without requiring full output buffering.
-            if progress_output_enabled && processed_objects.is_multiple_of(1000) {
-                let elapsed = start_time.elapsed();
-                let rate = if elapsed.as_secs_f64() > 0.0 {
-                    processed_objects as f64 / elapsed.as_secs_f64()
-                } else {
-                    0.0
-                };
-                progress_output_enabled = write_progress_stdout(format_args!(
-                    "\r[*] Processing objects {} {} ({:.0}/s)",
-                    processed_objects,
-                    format_elapsed(elapsed),
-                    rate
-                ))?;
-            }
+            progress.update(processed_objects)?;
         }
         line_buf.clear();
     }
@@ -654,27 +1423,21 @@ fn gather_all_blob_sizes(repo: &Path) -> io::Result<(HashMap<String, u64>, HashM
             status
         )));
     }
+    progress.finish(processed_objects)?;
+    let lfs_pointers = find_lfs_pointers(repo, &lfs_candidates)?;
 
-    if processed_objects > 0 && progress_output_enabled {
-        let elapsed = start_time.elapsed();
-        let rate = if elapsed.as_secs_f64() > 0.0 {
-            processed_objects as f64 / elapsed.as_secs_f64()
-        } else {
-            0.0
-        };
-        let _ = write_progress_stdout(format_args!(
-            "\r[*] Processing objects {} {} ({:.0}/s)\n",
-            processed_objects,
-            format_elapsed(elapsed),
-            rate
-        ))?;
-    }
-
-    eprintln!(
-        "[*] Found {} blobs out of {} total objects",
-        blob_count, processed_objects
+    diagnostics::info(
+        Phase::Analyze,
+        format!(
+            "[*] Found {} blobs out of {} total objects",
+            blob_count, processed_objects
+        ),
     );
-    Ok((unpacked_size, packed_size))
+    Ok(AllBlobSizes {
+        unpacked_size,
+        packed_size,
+        lfs_pointers,
+    })
 }
 
 fn gather_commit_history(repo: &Path, stats: &mut StatsCollection) -> io::Result<()> {
@@ -704,7 +1467,7 @@ fn gather_commit_history(repo: &Path, stats: &mut StatsCollection) -> io::Result
     let mut line_buf = String::new();
     let mut processed: usize = 0;
     let mut commit_data: Vec<String> = Vec::new();
-    let mut progress_output_enabled = true;
+    let mut progress = Progress::stderr("Processing commits", Some(total_commits));
 
     while reader.read_line(&mut line_buf)? > 0 {
         let line = line_buf.trim_end();
@@ -715,19 +1478,7 @@ fn gather_commit_history(repo: &Path, stats: &mut StatsCollection) -> io::Result
                 commit_data.clear();
                 processed += 1;
 
-                // Progress indicator every 1000 commits
-                if progress_output_enabled && processed.is_multiple_of(1000) {
-                    let progress = ((processed as f64 / total_commits as f64) * 100.0) as u32;
-                    let bar_length = 30;
-                    let filled = progress as usize * bar_length / 100;
-                    let bar: String = (0..bar_length)
-                        .map(|i| if i < filled { '=' } else { ' ' })
-                        .collect();
-                    progress_output_enabled = write_progress_stdout(format_args!(
-                        "\r[*] Processing commits [{}] {}% ({}/{})",
-                        bar, progress, processed, total_commits
-                    ))?;
-                }
+                progress.update(processed)?;
             }
         } else {
             commit_data.push(line.to_string());
@@ -749,14 +1500,14 @@ fn gather_commit_history(repo: &Path, stats: &mut StatsCollection) -> io::Result
         )));
     }
 
-    // Clear progress line and show final result
-    if progress_output_enabled {
-        let _ = write_progress_stdout(format_args!("\n"))?;
-    }
+    progress.finish(processed)?;
     stats.num_commits = total_commits as u64;
-    eprintln!(
-        "[*] Commit processing completed. Total: {}",
-        stats.num_commits
+    diagnostics::info(
+        Phase::Analyze,
+        format!(
+            "[*] Commit processing completed. Total: {}",
+            stats.num_commits
+        ),
     );
     Ok(())
 }
@@ -808,16 +1559,88 @@ fn gather_max_parents(repo: &Path) -> io::Result<usize> {
     Ok(max_parents)
 }
 
-fn gather_oversized_commit_messages(
-    repo: &Path,
-    threshold_bytes: usize,
-) -> io::Result<Vec<CommitMessageStat>> {
-    if threshold_bytes == 0 {
-        return Ok(Vec::new());
+/// Number of commits reachable from any ref, keyed by the `YYYY-MM` of their
+/// committer date.
+pub fn compute_commit_frequency_histogram(repo: &Path) -> io::Result<BTreeMap<String, u64>> {
+    let (mut reader, mut child) = run_git_capture_stream(
+        repo,
+        &["log", "--all", "--format=%cd", "--date=format:%Y-%m"],
+    )?;
+    let mut histogram = BTreeMap::new();
+    let mut line = String::new();
+    while reader.read_line(&mut line)? > 0 {
+        let month = line.trim_end();
+        if !month.is_empty() {
+            *histogram.entry(month.to_string()).or_insert(0) += 1;
+        }
+        line.clear();
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "git log --all --format=%cd failed: {}",
+            status
+        )));
     }
+
+    Ok(histogram)
+}
+
+/// Months shown in the commit activity sparkline.
+const SPARKLINE_MONTHS: u32 = 24;
+const SPARKLINE_BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+fn month_index(key: &str) -> Option<u32> {
+    let (year, month) = key.split_once('-')?;
+    let year: u32 = year.parse().ok()?;
+    let month: u32 = month.parse().ok()?;
+    (1..=12).contains(&month).then(|| year * 12 + month - 1)
+}
+
+/// Sparkline of the last [`SPARKLINE_MONTHS`] months of history, one block
+/// per calendar month (quiet months included) scaled to the busiest one.
+/// Returns the first and last month shown alongside the line.
+fn commit_sparkline(histogram: &BTreeMap<String, u64>) -> Option<(String, String, String)> {
+    let counts: BTreeMap<u32, u64> = histogram
+        .iter()
+        .filter_map(|(key, count)| Some((month_index(key)?, *count)))
+        .collect();
+    let (&first, _) = counts.first_key_value()?;
+    let (&last, _) = counts.last_key_value()?;
+    let start = first.max(last.saturating_sub(SPARKLINE_MONTHS - 1));
+    let window: Vec<u64> = (start..=last)
+        .map(|idx| counts.get(&idx).copied().unwrap_or(0))
+        .collect();
+    let max = window.iter().copied().max().unwrap_or(0).max(1);
+    let line = window
+        .iter()
+        .map(|&count| {
+            let level = (count * (SPARKLINE_BLOCKS.len() as u64 - 1)).div_ceil(max) as usize;
+            SPARKLINE_BLOCKS[level]
+        })
+        .collect();
+    let label = |idx: u32| format!("{:04}-{:02}", idx / 12, idx % 12 + 1);
+    Some((label(start), label(last), line))
+}
+
+#[derive(Debug, Default)]
+struct CommitMessageScan {
+    oversized: Vec<CommitMessageStat>,
+    problematic: Vec<ProblematicCommitMessage>,
+}
+
+fn gather_commit_message_stats(
+    repo: &Path,
+    thresholds: &AnalyzeThresholds,
+) -> io::Result<CommitMessageScan> {
     let (mut reader, mut child) =
         run_git_capture_stream(repo, &["log", "--all", "--pretty=%H%x00%B%x00"])?;
-    let stats = collect_oversized_commit_messages_from_reader(&mut reader, threshold_bytes)?;
+    let stats = collect_commit_message_stats_from_reader(
+        &mut reader,
+        thresholds.warn_commit_msg_bytes,
+        thresholds.warn_subject_length,
+    )?;
 
     let status = child.wait()?;
     if !status.success() {
@@ -830,17 +1653,20 @@ fn gather_oversized_commit_messages(
     Ok(stats)
 }
 
-fn collect_oversized_commit_messages_from_reader<R: BufRead>(
+/// Scans `%H%x00%B%x00` output. A threshold of 0 disables that check;
+/// control characters are always reported.
+fn collect_commit_message_stats_from_reader<R: BufRead>(
     reader: &mut R,
     threshold_bytes: usize,
-) -> io::Result<Vec<CommitMessageStat>> {
-    if threshold_bytes == 0 {
-        return Ok(Vec::new());
-    }
-
-    let mut stats = Vec::new();
+    subject_threshold: usize,
+) -> io::Result<CommitMessageScan> {
+    let mut stats = CommitMessageScan::default();
+    // Last commit read: OID, subject length and its `problematic` entry
+    let mut last: Option<(String, usize, Option<usize>)> = None;
     let mut oid_buf = Vec::new();
     let mut msg_buf = Vec::new();
+    // Bytes of misframed fields dropped while looking for the next OID
+    let mut skipped = 0usize;
 
     loop {
         oid_buf.clear();
@@ -851,8 +1677,39 @@ fn collect_oversized_commit_messages_from_reader<R: BufRead>(
         if oid_buf.last() == Some(&0) {
             oid_buf.pop();
         }
-        if oid_buf.is_empty() {
-            break;
+        if oid_buf.trim_ascii().is_empty() {
+            // Separator left after the last entry, or two NULs in a row
+            continue;
+        }
+        // A NUL inside a commit message shifts every later field by one;
+        // skip fields until one looks like an OID again.
+        let Some(oid) = parse_log_oid(&oid_buf) else {
+            // Fields only misframe after a message with a NUL in it.
+            if let Some((oid, subject_length, idx)) = last.as_mut() {
+                match idx {
+                    Some(i) => stats.problematic[*i].has_control_chars = true,
+                    None => {
+                        *idx = Some(stats.problematic.len());
+                        stats.problematic.push(ProblematicCommitMessage {
+                            oid: oid.clone(),
+                            subject_length: *subject_length,
+                            has_control_chars: true,
+                        });
+                    }
+                }
+            }
+            skipped += oid_read;
+            continue;
+        };
+        if skipped > 0 {
+            diagnostics::warning(
+                Phase::Analyze,
+                format!(
+                    "skipped {} byte(s) of misframed git log output before commit {}",
+                    skipped, oid
+                ),
+            );
+            skipped = 0;
         }
 
         msg_buf.clear();
@@ -864,17 +1721,151 @@ fn collect_oversized_commit_messages_from_reader<R: BufRead>(
             msg_buf.pop();
         }
 
-        if msg_buf.len() >= threshold_bytes {
-            stats.push(CommitMessageStat {
-                oid: String::from_utf8_lossy(&oid_buf).trim().to_string(),
+        if threshold_bytes > 0 && msg_buf.len() >= threshold_bytes {
+            stats.oversized.push(CommitMessageStat {
+                oid: oid.clone(),
                 length: msg_buf.len(),
             });
         }
+        let subject = msg_buf.split(|&b| b == b'\n').next().unwrap_or_default();
+        let subject_length = String::from_utf8_lossy(subject).chars().count();
+        let long_subject = subject_threshold > 0 && subject_length > subject_threshold;
+        let has_control_chars = msg_buf
+            .iter()
+            .any(|&b| b < 0x20 && b != b'\n' && b != b'\t');
+        let idx = (long_subject || has_control_chars).then(|| {
+            stats.problematic.push(ProblematicCommitMessage {
+                oid: oid.clone(),
+                subject_length,
+                has_control_chars,
+            });
+            stats.problematic.len() - 1
+        });
+        last = Some((oid, subject_length, idx));
+    }
+    if skipped > 0 {
+        diagnostics::warning(
+            Phase::Analyze,
+            format!(
+                "skipped {} byte(s) of misframed git log output at end of stream",
+                skipped
+            ),
+        );
     }
 
     Ok(stats)
 }
 
+/// The OID of a `%H` field (preceded by the previous entry's newline), if
+/// it is exactly 40 lowercase hex characters.
+fn parse_log_oid(field: &[u8]) -> Option<String> {
+    let oid = field.trim_ascii_start();
+    let valid = oid.len() == 40 && oid.iter().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+    valid.then(|| String::from_utf8_lossy(oid).into_owned())
+}
+
+fn gather_suspect_identities(
+    repo: &Path,
+    denylist: &[String],
+    top: usize,
+) -> io::Result<(Vec<SuspectIdentityStat>, usize)> {
+    let (mut reader, mut child) = run_git_capture_stream(
+        repo,
+        &[
+            "log",
+            "--all",
+            "--pretty=%H%x00%an%x00%ae%x00%cn%x00%ce%x00",
+        ],
+    )?;
+    let result = collect_suspect_identities_from_reader(&mut reader, denylist, top)?;
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "git log --all --pretty=%H%x00%an%x00%ae%x00%cn%x00%ce%x00 failed: {}",
+            status
+        )));
+    }
+
+    Ok(result)
+}
+
+/// Parse `oid NUL author-name NUL author-email NUL committer-name NUL
+/// committer-email NUL` records and keep the first `top` suspect identities
+/// along with the total number found.
+fn collect_suspect_identities_from_reader<R: BufRead>(
+    reader: &mut R,
+    denylist: &[String],
+    top: usize,
+) -> io::Result<(Vec<SuspectIdentityStat>, usize)> {
+    let denylist: Vec<String> = denylist.iter().map(|p| p.to_lowercase()).collect();
+    let mut suspects = Vec::new();
+    let mut total = 0usize;
+    let mut fields: [Vec<u8>; 5] = Default::default();
+
+    'records: loop {
+        for field in fields.iter_mut() {
+            field.clear();
+            if reader.read_until(0, field)? == 0 || field.last() != Some(&0) {
+                break 'records;
+            }
+            field.pop();
+        }
+        let oid = String::from_utf8_lossy(&fields[0]).trim().to_string();
+        for (role, name, email) in [
+            ("author", &fields[1], &fields[2]),
+            ("committer", &fields[3], &fields[4]),
+        ] {
+            let name = String::from_utf8_lossy(name);
+            let email = String::from_utf8_lossy(email);
+            let Some(reason) = identity_issue(&name, &email, &denylist) else {
+                continue;
+            };
+            total += 1;
+            if suspects.len() < top {
+                suspects.push(SuspectIdentityStat {
+                    oid: oid.clone(),
+                    role: role.to_string(),
+                    name: name.into_owned(),
+                    email: email.into_owned(),
+                    reason,
+                });
+            }
+        }
+    }
+
+    Ok((suspects, total))
+}
+
+fn identity_issue(name: &str, email: &str, denylist: &[String]) -> Option<String> {
+    let mut reasons = Vec::new();
+    if name.trim().is_empty() {
+        reasons.push("empty name");
+    }
+    if email.trim().is_empty() {
+        reasons.push("empty email");
+    } else if !email.contains('@') {
+        reasons.push("email missing '@'");
+    }
+    if name.contains(['<', '>']) || email.contains(['<', '>']) {
+        reasons.push("mismatched angle brackets");
+    }
+    if !denylist.is_empty() {
+        let ident = format!("{} <{}>", name, email).to_lowercase();
+        if denylist
+            .iter()
+            .any(|pattern| ident.contains(pattern.as_str()))
+        {
+            reasons.push("denylisted identity");
+        }
+    }
+    if reasons.is_empty() {
+        None
+    } else {
+        Some(reasons.join(", "))
+    }
+}
+
 // (removed old gather_history_stats; superseded by gather_history_fast_export)
 
 fn evaluate_warnings(metrics: &RepositoryMetrics, thresholds: &AnalyzeThresholds) -> Vec<Warning> {
@@ -882,6 +1873,7 @@ fn evaluate_warnings(metrics: &RepositoryMetrics, thresholds: &AnalyzeThresholds
     if metrics.total_size_bytes >= thresholds.crit_total_bytes {
         warnings.push(Warning {
       level: WarningLevel::Critical,
+      code: None,
       message: format!(
         "Repository is {:.2} GiB (threshold {:.2} GiB).", to_gib(metrics.total_size_bytes), to_gib(thresholds.crit_total_bytes)
       ),
@@ -890,6 +1882,7 @@ fn evaluate_warnings(metrics: &RepositoryMetrics, thresholds: &AnalyzeThresholds
     } else if metrics.total_size_bytes >= thresholds.warn_total_bytes {
         warnings.push(Warning {
             level: WarningLevel::Warning,
+            code: None,
             message: format!(
                 "Repository is {:.2} GiB (warning threshold {:.2} GiB).",
                 to_gib(metrics.total_size_bytes),
@@ -903,6 +1896,7 @@ fn evaluate_warnings(metrics: &RepositoryMetrics, thresholds: &AnalyzeThresholds
     if metrics.refs_total >= thresholds.warn_ref_count {
         warnings.push(Warning {
             level: WarningLevel::Warning,
+            code: None,
             message: format!(
                 "Repository has {} refs (warning threshold {}).",
                 metrics.refs_total, thresholds.warn_ref_count
@@ -913,9 +1907,73 @@ fn evaluate_warnings(metrics: &RepositoryMetrics, thresholds: &AnalyzeThresholds
             ),
         });
     }
+    if metrics.pack_count > thresholds.warn_pack_count as u64 {
+        warnings.push(Warning {
+            level: WarningLevel::Warning,
+            code: None,
+            message: format!(
+                "Repository has {} packfiles (warning threshold {}).",
+                metrics.pack_count, thresholds.warn_pack_count
+            ),
+            recommendation: Some(
+                "Run `git maintenance run --task=gc` or `git repack -adb` to consolidate packs."
+                    .to_string(),
+            ),
+        });
+    }
+    if metrics.pack_file_count > thresholds.warn_pack_fragmentation
+        && metrics.pack_count <= thresholds.warn_pack_count as u64
+    {
+        warnings.push(Warning {
+            level: WarningLevel::Info,
+            code: None,
+            message: format!(
+                "Objects are spread over {} pack files (fragmentation threshold {}).",
+                metrics.pack_file_count, thresholds.warn_pack_fragmentation
+            ),
+            recommendation: Some(
+                "Run `git gc --aggressive` to consolidate them into a single pack.".to_string(),
+            ),
+        });
+    }
+    if metrics.total_objects > 0 {
+        let loose_ratio = metrics.loose_objects as f64 / metrics.total_objects as f64;
+        if loose_ratio > thresholds.warn_loose_object_ratio {
+            warnings.push(Warning {
+                level: WarningLevel::Info,
+                code: None,
+                message: format!(
+                    "{:.0}% of objects are loose ({} of {}; threshold {:.0}%).",
+                    loose_ratio * 100.0,
+                    metrics.loose_objects,
+                    metrics.total_objects,
+                    thresholds.warn_loose_object_ratio * 100.0
+                ),
+                recommendation: Some(
+                    "Run `git gc` to pack them; loose objects slow down lookups and transfers."
+                        .to_string(),
+                ),
+            });
+        }
+    }
+    if !metrics.has_commit_graph && metrics.total_size_bytes >= thresholds.warn_commit_graph_bytes {
+        warnings.push(Warning {
+            level: WarningLevel::Info,
+            code: None,
+            message: format!(
+                "No commit-graph file for a {:.2} MiB repository.",
+                to_mib(metrics.total_size_bytes)
+            ),
+            recommendation: Some(
+                "Run `git commit-graph write --reachable` or enable `git maintenance start`."
+                    .to_string(),
+            ),
+        });
+    }
     if metrics.total_objects as usize >= thresholds.warn_object_count {
         warnings.push(Warning {
       level: WarningLevel::Warning,
+      code: None,
       message: format!(
         "Repository contains {} Git objects (warning threshold {}).",
         metrics.total_objects,
@@ -928,6 +1986,7 @@ fn evaluate_warnings(metrics: &RepositoryMetrics, thresholds: &AnalyzeThresholds
         if dir.entries >= thresholds.warn_tree_entries {
             warnings.push(Warning {
         level: WarningLevel::Warning,
+        code: None,
         message: format!(
           "Directory '{}' has {} entries (threshold {}).", dir.path, dir.entries, thresholds.warn_tree_entries
         ),
@@ -939,6 +1998,7 @@ fn evaluate_warnings(metrics: &RepositoryMetrics, thresholds: &AnalyzeThresholds
         if path.length >= thresholds.warn_path_length {
             warnings.push(Warning {
         level: WarningLevel::Warning,
+        code: None,
         message: format!(
           "Path '{}' is {} characters long (threshold {}).", path.path, path.length, thresholds.warn_path_length
         ),
@@ -949,9 +2009,11 @@ fn evaluate_warnings(metrics: &RepositoryMetrics, thresholds: &AnalyzeThresholds
     for blob in &metrics.blobs_over_threshold {
         warnings.push(Warning {
             level: WarningLevel::Warning,
+            code: None,
             message: format!(
-                "Blob {} is {:.2} MiB (threshold {:.2} MiB).",
+                "Blob {}{} is {:.2} MiB (threshold {:.2} MiB).",
                 blob.oid,
+                warning_paths(blob),
                 to_mib(blob.size),
                 to_mib(thresholds.warn_blob_bytes)
             ),
@@ -960,9 +2022,25 @@ fn evaluate_warnings(metrics: &RepositoryMetrics, thresholds: &AnalyzeThresholds
             ),
         });
     }
+    if metrics.lfs_pointer_count > 0 {
+        warnings.push(Warning {
+            level: WarningLevel::Info,
+            code: Some("lfs-pointers"),
+            message: format!(
+                "{} blob(s) in history are Git LFS pointer files, not file contents.",
+                metrics.lfs_pointer_count
+            ),
+            recommendation: Some(
+                "If the repository no longer uses LFS, or only partly migrated to it, \
+                 `git lfs migrate import` (or `export`) makes history consistent."
+                    .to_string(),
+            ),
+        });
+    }
     if metrics.max_commit_parents > thresholds.warn_max_parents {
         warnings.push(Warning {
             level: WarningLevel::Info,
+            code: None,
             message: format!(
         "Commit with {} parents detected (threshold {}). Octopus merges can complicate history.",
         metrics.max_commit_parents,
@@ -977,6 +2055,7 @@ fn evaluate_warnings(metrics: &RepositoryMetrics, thresholds: &AnalyzeThresholds
     for msg in &metrics.oversized_commit_messages {
         warnings.push(Warning {
             level: WarningLevel::Info,
+            code: None,
             message: format!(
                 "Commit {} has a {} byte message (threshold {}).",
                 msg.oid, msg.length, thresholds.warn_commit_msg_bytes
@@ -986,39 +2065,130 @@ fn evaluate_warnings(metrics: &RepositoryMetrics, thresholds: &AnalyzeThresholds
             ),
         });
     }
+    for msg in &metrics.problematic_commit_messages {
+        if thresholds.warn_subject_length > 0 && msg.subject_length > thresholds.warn_subject_length
+        {
+            warnings.push(Warning {
+                level: WarningLevel::Info,
+                code: Some("long-commit-subject"),
+                message: format!(
+                    "Commit {} has a {} character subject line (threshold {}).",
+                    msg.oid, msg.subject_length, thresholds.warn_subject_length
+                ),
+                recommendation: Some(
+                    "Keep the first line short; tools that show one-line logs may truncate or choke on it."
+                        .to_string(),
+                ),
+            });
+        }
+        if msg.has_control_chars {
+            warnings.push(Warning {
+                level: WarningLevel::Warning,
+                code: Some("commit-message-control-chars"),
+                message: format!(
+                    "Commit {} has NUL or control characters in its message.",
+                    msg.oid
+                ),
+                recommendation: Some(
+                    "Some hosting platforms reject such commits; rewrite the message with --replace-message."
+                        .to_string(),
+                ),
+            });
+        }
+    }
+    if metrics.suspect_identities_total > 0 {
+        warnings.push(Warning {
+            level: WarningLevel::Warning,
+            code: None,
+            message: format!(
+                "{} author/committer identities are missing or malformed.",
+                metrics.suspect_identities_total
+            ),
+            recommendation: Some(
+                "Map them to valid identities with --mailmap before pushing to a strict host."
+                    .to_string(),
+            ),
+        });
+    }
     if warnings.is_empty() {
         warnings.push(Warning {
             level: WarningLevel::Info,
+            code: None,
             message: "No size-related issues detected above configured thresholds.".to_string(),
             recommendation: None,
         });
     }
+    if metrics.notes_object_count > 0 {
+        warnings.push(Warning {
+            level: WarningLevel::Info,
+            code: None,
+            message: format!(
+                "{} notes in {} notes refs ({}) are not rewritten; they keep annotating the original commit ids.",
+                metrics.notes_object_count,
+                metrics.notes_refs.len(),
+                metrics.notes_refs.join(", ")
+            ),
+            recommendation: Some(
+                "Re-add the notes to the rewritten commits using .git/filter-repo/commit-map."
+                    .to_string(),
+            ),
+        });
+    }
     warnings
 }
 
-fn print_human(report: &AnalysisReport, _cfg: &AnalyzeConfig) {
-    println!("{}", banner("Repository analysis"));
+/// " (a, b, c, +N more)" for a blob warning: up to three of its paths.
+fn warning_paths(blob: &ObjectStat) -> String {
+    const SHOWN: usize = 3;
+    if blob.paths.is_empty() {
+        return String::new();
+    }
+    let mut listed = blob.paths[..blob.paths.len().min(SHOWN)].join(", ");
+    let more = blob.path_count.saturating_sub(SHOWN);
+    if more > 0 {
+        listed.push_str(&format!(", +{more} more"));
+    }
+    format!(" ({listed})")
+}
+
+fn print_human(report: &AnalysisReport, cfg: &AnalyzeConfig) {
+    let style = TableStyle::new(cfg);
+    println!("{}", banner(style, "Repository analysis"));
     if let Some(path) = &report.metrics.workdir {
         println!("{}", path);
     }
     // Unified summary table (without concern column)
-    print_section("Repository summary");
+    print_section(style, "Repository summary");
     let rows = build_summary_rows(&report.metrics);
     print_table(
+        style,
         &[
-            ("Name", CellAlignment::Left),
-            ("Value", CellAlignment::Right),
+            ("Name", CellAlignment::Left, ColumnKind::Text),
+            ("Value", CellAlignment::Right, ColumnKind::Fixed),
         ],
         rows,
     );
 
+    if let Some((from, to, line)) = commit_sparkline(&report.metrics.commit_frequency) {
+        println!("  Commit activity ({from} .. {to}): {line}");
+    }
+
     // (Checkout (HEAD) moved near Warnings for better layout)
 
     // Show largest files (unique files, grouped by path) instead of individual blob versions
     if !report.metrics.largest_files.is_empty() {
         println!(
-            "  Top {} files by size:",
-            format_count(report.metrics.largest_files.len() as u64)
+            "  Top {} files by size (% of {} of unique blobs):",
+            format_count(report.metrics.largest_files.len() as u64),
+            format_size_human(report.metrics.unique_blob_bytes)
+        );
+        let cumulative = cumulative_pct(
+            report
+                .metrics
+                .largest_files
+                .iter()
+                .map(|file| (file.largest_oid.as_str(), file.size)),
+            report.metrics.unique_blob_bytes,
         );
         let rows = report
             .metrics
@@ -1029,7 +2199,9 @@ fn print_human(report: &AnalysisReport, _cfg: &AnalyzeConfig) {
                 let truncated_oid = format!("{:.8}", file.largest_oid);
                 vec![
                     Cow::Owned(format!("{}", idx + 1)),
-                    Cow::Owned(format!("{:.2} MiB", to_mib(file.size))),
+                    Cow::Owned(format_size_human(file.size)),
+                    Cow::Owned(format!("{:.1}", file.pct_of_total)),
+                    Cow::Owned(format!("{:.1}", cumulative[idx])),
                     Cow::Owned(file.path.clone()),
                     Cow::Owned(format!("{} ver", file.versions)),
                     Cow::Owned(truncated_oid),
@@ -1037,12 +2209,50 @@ fn print_human(report: &AnalysisReport, _cfg: &AnalyzeConfig) {
             })
             .collect();
         print_table(
+            style,
+            &[
+                ("#", CellAlignment::Right, ColumnKind::Fixed),
+                ("Size", CellAlignment::Right, ColumnKind::Fixed),
+                ("%", CellAlignment::Right, ColumnKind::Fixed),
+                ("Cum %", CellAlignment::Right, ColumnKind::Fixed),
+                ("Path", CellAlignment::Left, ColumnKind::Path),
+                ("Vers", CellAlignment::Center, ColumnKind::Fixed),
+                ("OID", CellAlignment::Center, ColumnKind::Fixed),
+            ],
+            rows,
+        );
+    }
+    if !report.metrics.rollup.is_empty() {
+        let rollup = &report.metrics.rollup;
+        let shown = rollup.len().min(cfg.top);
+        println!(
+            "  Size by path prefix (top {} of {}; all in --analyze-json):",
+            format_count(shown as u64),
+            format_count(rollup.len() as u64)
+        );
+        let rows = rollup[..shown]
+            .iter()
+            .map(|r| {
+                vec![
+                    Cow::Owned(format_size_human(r.unique_blob_bytes)),
+                    Cow::Owned(format!(
+                        "{:.1}",
+                        pct_of_total(r.unique_blob_bytes, report.metrics.unique_blob_bytes)
+                    )),
+                    Cow::Owned(format_count(r.blob_count as u64)),
+                    Cow::Owned(format_count(r.file_count as u64)),
+                    Cow::Borrowed(r.prefix.as_str()),
+                ]
+            })
+            .collect();
+        print_table(
+            style,
             &[
-                ("#", CellAlignment::Right),
-                ("Size", CellAlignment::Right),
-                ("Path", CellAlignment::Left),
-                ("Vers", CellAlignment::Center),
-                ("OID", CellAlignment::Center),
+                ("Size", CellAlignment::Right, ColumnKind::Fixed),
+                ("%", CellAlignment::Right, ColumnKind::Fixed),
+                ("Blobs", CellAlignment::Right, ColumnKind::Fixed),
+                ("Files", CellAlignment::Right, ColumnKind::Fixed),
+                ("Prefix", CellAlignment::Left, ColumnKind::Path),
             ],
             rows,
         );
@@ -1061,21 +2271,66 @@ fn print_human(report: &AnalysisReport, _cfg: &AnalyzeConfig) {
                 let truncated_oid = format!("{:.8}", tree.oid);
                 vec![
                     Cow::Owned(format!("{}", idx + 1)),
-                    Cow::Owned(format!("{:.2} KiB", tree.size as f64 / 1024.0)),
+                    Cow::Owned(format_size_human(tree.size)),
                     Cow::Owned(truncated_oid),
                 ]
             })
             .collect();
         print_table(
+            style,
+            &[
+                ("#", CellAlignment::Right, ColumnKind::Fixed),
+                ("Size", CellAlignment::Right, ColumnKind::Fixed),
+                ("OID", CellAlignment::Center, ColumnKind::Fixed),
+            ],
+            rows,
+        );
+    }
+
+    if !report.metrics.suggestions.is_empty() {
+        print_section(style, "Suggested filters");
+        let rows = report
+            .metrics
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                vec![
+                    Cow::Owned(format_size_human(suggestion.estimated_bytes)),
+                    Cow::Borrowed(suggestion.category.as_str()),
+                    Cow::Borrowed(suggestion.command.as_str()),
+                ]
+            })
+            .collect();
+        print_table(
+            style,
             &[
-                ("#", CellAlignment::Right),
-                ("Size", CellAlignment::Right),
-                ("OID", CellAlignment::Center),
+                ("Saves", CellAlignment::Right, ColumnKind::Fixed),
+                ("Category", CellAlignment::Left, ColumnKind::Text),
+                ("Filter", CellAlignment::Left, ColumnKind::Text),
             ],
             rows,
         );
     }
 
+    if let Some(blob) = &report.metrics.oldest_surviving_blob {
+        println!("  Oldest surviving blob (by when its path was added):");
+        print_table(
+            style,
+            &[
+                ("Size", CellAlignment::Right, ColumnKind::Fixed),
+                ("Path", CellAlignment::Left, ColumnKind::Path),
+                ("Introduced", CellAlignment::Left, ColumnKind::Text),
+                ("OID", CellAlignment::Center, ColumnKind::Fixed),
+            ],
+            vec![vec![
+                Cow::Owned(format_size_human(blob.size)),
+                Cow::Owned(blob.display_path()),
+                Cow::Borrowed(blob.introduced_at.as_deref().unwrap_or("unknown")),
+                Cow::Owned(format!("{:.8}", blob.oid)),
+            ]],
+        );
+    }
+
     // History oddities are summarized above; keep oversized messages as a list
     if !report.metrics.oversized_commit_messages.is_empty() {
         println!("  Oversized commit messages:");
@@ -1094,10 +2349,78 @@ fn print_human(report: &AnalysisReport, _cfg: &AnalyzeConfig) {
             })
             .collect();
         print_table(
+            style,
+            &[
+                ("#", CellAlignment::Right, ColumnKind::Fixed),
+                ("Bytes", CellAlignment::Right, ColumnKind::Fixed),
+                ("OID", CellAlignment::Center, ColumnKind::Fixed),
+            ],
+            rows,
+        );
+    }
+    if !report.metrics.problematic_commit_messages.is_empty() {
+        println!("  Problematic commit messages:");
+        let rows = report
+            .metrics
+            .problematic_commit_messages
+            .iter()
+            .enumerate()
+            .map(|(idx, msg)| {
+                let subject_limit = cfg.thresholds.warn_subject_length;
+                let long_subject = subject_limit > 0 && msg.subject_length > subject_limit;
+                let issue = match (long_subject, msg.has_control_chars) {
+                    (true, true) => "long subject, control characters",
+                    (true, false) => "long subject",
+                    (false, _) => "control characters",
+                };
+                vec![
+                    Cow::Owned(format!("{}", idx + 1)),
+                    Cow::Owned(format_count(msg.subject_length as u64)),
+                    Cow::Borrowed(issue),
+                    Cow::Owned(format!("{:.8}", msg.oid)),
+                ]
+            })
+            .collect();
+        print_table(
+            style,
+            &[
+                ("#", CellAlignment::Right, ColumnKind::Fixed),
+                ("Subject chars", CellAlignment::Right, ColumnKind::Fixed),
+                ("Issue", CellAlignment::Left, ColumnKind::Text),
+                ("OID", CellAlignment::Center, ColumnKind::Fixed),
+            ],
+            rows,
+        );
+    }
+
+    if !report.metrics.suspect_identities.is_empty() {
+        print_section(
+            style,
+            &format!(
+                "Suspect identities ({} total)",
+                format_count(report.metrics.suspect_identities_total as u64)
+            ),
+        );
+        let rows = report
+            .metrics
+            .suspect_identities
+            .iter()
+            .map(|ident| {
+                vec![
+                    Cow::Owned(format!("{:.8}", ident.oid)),
+                    Cow::Borrowed(ident.role.as_str()),
+                    Cow::Owned(format!("{} <{}>", ident.name, ident.email)),
+                    Cow::Borrowed(ident.reason.as_str()),
+                ]
+            })
+            .collect();
+        print_table(
+            style,
             &[
-                ("#", CellAlignment::Right),
-                ("Bytes", CellAlignment::Right),
-                ("OID", CellAlignment::Center),
+                ("OID", CellAlignment::Center, ColumnKind::Fixed),
+                ("Role", CellAlignment::Left, ColumnKind::Text),
+                ("Identity", CellAlignment::Left, ColumnKind::Text),
+                ("Reason", CellAlignment::Left, ColumnKind::Text),
             ],
             rows,
         );
@@ -1120,18 +2443,19 @@ fn print_human(report: &AnalysisReport, _cfg: &AnalyzeConfig) {
         ]);
     }
     if !snapshot_rows.is_empty() {
-        print_section("Checkout (HEAD)");
+        print_section(style, "Checkout (HEAD)");
         print_table(
+            style,
             &[
-                ("Metric", CellAlignment::Left),
-                ("Value", CellAlignment::Left),
-                ("Details", CellAlignment::Left),
+                ("Metric", CellAlignment::Left, ColumnKind::Text),
+                ("Value", CellAlignment::Left, ColumnKind::Path),
+                ("Details", CellAlignment::Left, ColumnKind::Text),
             ],
             snapshot_rows,
         );
     }
 
-    print_section("Warnings");
+    print_section(style, "Warnings");
     let warning_rows = report
         .warnings
         .iter()
@@ -1149,13 +2473,18 @@ fn print_human(report: &AnalysisReport, _cfg: &AnalyzeConfig) {
         })
         .collect();
     print_table(
+        style,
         &[
-            ("Level", CellAlignment::Center),
-            ("Message", CellAlignment::Left),
-            ("Recommendation", CellAlignment::Left),
+            ("Level", CellAlignment::Center, ColumnKind::Fixed),
+            ("Message", CellAlignment::Left, ColumnKind::Text),
+            ("Recommendation", CellAlignment::Left, ColumnKind::Text),
         ],
         warning_rows,
     );
+
+    if let Some(delta) = &report.delta {
+        baseline::print_human(delta, style);
+    }
 }
 
 // Attempt to replace OID in a known-warning message pattern with a footnote marker.
@@ -1194,16 +2523,9 @@ fn find_blob_context(metrics: &RepositoryMetrics, oid: &str) -> Option<String> {
     metrics
         .blobs_over_threshold
         .iter()
-        .find(|b| b.oid == oid)
-        .and_then(|b| b.path.as_ref())
-        .or_else(|| {
-            metrics
-                .largest_blobs
-                .iter()
-                .find(|b| b.oid == oid)
-                .and_then(|b| b.path.as_ref())
-        })
-        .cloned()
+        .chain(&metrics.largest_blobs)
+        .find(|b| b.oid == oid && b.path.is_some())
+        .map(ObjectStat::display_path)
 }
 
 fn run_git_capture(repo: &Path, args: &[&str]) -> io::Result<String> {
@@ -1243,24 +2565,6 @@ fn run_git_capture_stream(
     Ok((BufReader::new(stdout), cmd))
 }
 
-fn flush_progress_writer<W: Write>(writer: &mut W) -> io::Result<bool> {
-    match writer.flush() {
-        Ok(()) => Ok(true),
-        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(false),
-        Err(err) => Err(err),
-    }
-}
-
-fn write_progress_stdout(args: std::fmt::Arguments<'_>) -> io::Result<bool> {
-    // Progress belongs on stderr so machine-readable stdout (e.g. --analyze-json) stays clean.
-    let mut stderr = io::stderr();
-    match stderr.write_fmt(args) {
-        Ok(()) => flush_progress_writer(&mut stderr),
-        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(false),
-        Err(err) => Err(err),
-    }
-}
-
 fn to_mib(bytes: u64) -> f64 {
     bytes as f64 / 1024.0 / 1024.0
 }
@@ -1269,32 +2573,232 @@ fn to_gib(bytes: u64) -> f64 {
     bytes as f64 / 1024.0 / 1024.0 / 1024.0
 }
 
+fn report_to_json(report: &AnalysisReport, compact: bool) -> io::Result<String> {
+    if compact {
+        serde_json::to_string(report).map_err(to_io_error)
+    } else {
+        serde_json::to_string_pretty(report).map_err(to_io_error)
+    }
+}
+
 fn to_io_error(err: serde_json::Error) -> io::Error {
     io::Error::other(err)
 }
 
-fn heap_to_object_stats_with_paths(
-    heap: BinaryHeap<Reverse<(u64, String)>>,
-    blob_paths: &HashMap<String, Vec<String>>,
-) -> Vec<ObjectStat> {
+fn heap_to_object_stats(heap: BinaryHeap<Reverse<(u64, String)>>) -> Vec<ObjectStat> {
     heap.into_sorted_vec()
         .into_iter()
-        .map(|Reverse((size, oid))| {
-            let path = blob_paths
-                .get(&oid)
-                .and_then(|paths| paths.first().cloned());
-            ObjectStat { oid, size, path }
+        .map(|Reverse((size, oid))| ObjectStat {
+            oid,
+            size,
+            ..Default::default()
         })
         .collect()
 }
 
-fn compute_largest_files(
+/// `rev-list --objects` prints each object once, with the first path it was
+/// reached at, or none when a tag points straight at the blob. A blob left
+/// pathless gets the path of the commit that added it. With `limit`
+/// (`--analyze-max-blob-paths`), every path the reported blobs were written
+/// at is collected instead, and the first `limit` of them kept in sorted
+/// order.
+fn fill_blob_paths(
+    repo: &Path,
     blob_paths: &HashMap<String, Vec<String>>,
-    unpacked_size: &HashMap<String, u64>,
-    packed_size: &HashMap<String, u64>,
-    top: usize,
-) -> Vec<FileStat> {
-    if top == 0 {
+    limit: Option<usize>,
+    metrics: &mut RepositoryMetrics,
+) -> io::Result<()> {
+    let mut found: HashMap<String, BTreeSet<String>> = metrics
+        .largest_blobs
+        .iter()
+        .chain(&metrics.blobs_over_threshold)
+        .map(|blob| {
+            let known = blob_paths.get(&blob.oid).into_iter().flatten().cloned();
+            (blob.oid.clone(), known.collect())
+        })
+        .collect();
+    if found.is_empty() {
+        return Ok(());
+    }
+    match limit {
+        Some(_) => collect_every_blob_path(repo, &mut found)?,
+        None => {
+            let missing = found
+                .iter()
+                .filter(|(_, paths)| paths.is_empty())
+                .map(|(oid, _)| oid.clone())
+                .collect();
+            for (oid, path) in first_added_paths(repo, missing)? {
+                found.entry(oid).or_default().insert(path);
+            }
+        }
+    }
+    let limit = limit.unwrap_or(usize::MAX);
+    for blob in metrics
+        .largest_blobs
+        .iter_mut()
+        .chain(metrics.blobs_over_threshold.iter_mut())
+    {
+        if let Some(paths) = found.get(&blob.oid) {
+            blob.path_count = paths.len();
+            blob.paths = paths.iter().take(limit).cloned().collect();
+            blob.path = blob.paths.first().cloned();
+        }
+    }
+    Ok(())
+}
+
+/// The path each of `missing` was first added at, from the commits' `--raw`
+/// diffs, which carry the blob id next to the path. Stops reading as soon
+/// as every blob has one.
+fn first_added_paths(
+    repo: &Path,
+    mut missing: HashSet<String>,
+) -> io::Result<HashMap<String, String>> {
+    let mut found = HashMap::new();
+    if missing.is_empty() {
+        return Ok(found);
+    }
+    let (mut reader, mut child) = run_git_capture_stream(
+        repo,
+        &[
+            "-c",
+            "core.quotepath=false",
+            "log",
+            "--all",
+            "--diff-filter=A",
+            "--format=",
+            "--raw",
+            "--no-abbrev",
+        ],
+    )?;
+    let mut line_buf = String::new();
+    while !missing.is_empty() && reader.read_line(&mut line_buf)? > 0 {
+        // ":000000 100644 <old> <new> A\t<path>"
+        if let Some((meta, path)) = line_buf.trim_end_matches('\n').split_once('\t') {
+            if let Some(oid) = meta.split_whitespace().nth(3) {
+                if missing.remove(oid) {
+                    found.insert(oid.to_string(), path.to_string());
+                }
+            }
+        }
+        line_buf.clear();
+    }
+    drop(reader);
+    if missing.is_empty() {
+        // Stopped early; the rest of the log is not needed.
+        let _ = child.kill();
+        let _ = child.wait();
+    } else {
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "git log --all --diff-filter=A --raw failed: {}",
+                status
+            )));
+        }
+    }
+    Ok(found)
+}
+
+/// Adds every path the blobs in `found` were written at. `--find-object`
+/// has git keep only the diffs that touch those blobs, so the walk covers
+/// all of history but prints just the reported ones.
+fn collect_every_blob_path(
+    repo: &Path,
+    found: &mut HashMap<String, BTreeSet<String>>,
+) -> io::Result<()> {
+    let mut args: Vec<String> = [
+        "-c",
+        "core.quotepath=false",
+        "log",
+        "--all",
+        "--format=",
+        "--raw",
+        "--no-abbrev",
+        "--no-renames",
+    ]
+    .into_iter()
+    .map(str::to_string)
+    .collect();
+    args.extend(found.keys().map(|oid| format!("--find-object={oid}")));
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let (mut reader, mut child) = run_git_capture_stream(repo, &args)?;
+    let mut line_buf = String::new();
+    while reader.read_line(&mut line_buf)? > 0 {
+        // ":100644 100644 <old> <new> M\t<path>"
+        if let Some((meta, path)) = line_buf.trim_end_matches('\n').split_once('\t') {
+            let paths = meta
+                .split_whitespace()
+                .nth(3)
+                .and_then(|oid| found.get_mut(oid));
+            if let Some(paths) = paths {
+                if !paths.contains(path) {
+                    paths.insert(path.to_string());
+                }
+            }
+        }
+        line_buf.clear();
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "git log --all --raw --find-object failed: {}",
+            status
+        )));
+    }
+    Ok(())
+}
+
+/// Best-effort provenance for the largest blobs: the blob whose path was
+/// added earliest (following renames) wins. A path that was deleted and
+/// re-added counts from its first addition, so the blob itself may be newer.
+fn detect_oldest_surviving_blob(repo: &Path, blobs: &[ObjectStat]) -> Option<ObjectStat> {
+    let mut oldest: Option<(i64, &ObjectStat)> = None;
+    for blob in blobs {
+        let Some(path) = &blob.path else {
+            continue;
+        };
+        let Ok(log) = run_git_capture(
+            repo,
+            &[
+                "log",
+                "--all",
+                "--diff-filter=A",
+                "--follow",
+                "--format=%ct %H",
+                "--",
+                path,
+            ],
+        ) else {
+            continue;
+        };
+        let first_added = log
+            .lines()
+            .filter_map(|line| line.split_whitespace().next()?.parse::<i64>().ok())
+            .min();
+        if let Some(ts) = first_added {
+            if oldest.is_none_or(|(best, _)| ts < best) {
+                oldest = Some((ts, blob));
+            }
+        }
+    }
+    let (ts, blob) = oldest?;
+    Some(ObjectStat {
+        introduced_at: OffsetDateTime::from_unix_timestamp(ts)
+            .ok()
+            .and_then(|t| t.format(&Rfc3339).ok()),
+        ..blob.clone()
+    })
+}
+
+fn compute_largest_files(
+    blob_paths: &HashMap<String, Vec<String>>,
+    unpacked_size: &HashMap<String, u64>,
+    packed_size: &HashMap<String, u64>,
+    top: usize,
+) -> Vec<FileStat> {
+    if top == 0 {
         return Vec::new();
     }
 
@@ -1319,18 +2823,113 @@ fn compute_largest_files(
     let mut files: Vec<FileStat> = file_map
         .into_iter()
         .map(|(path, (size, largest_oid, versions))| FileStat {
+            category: suggest::classify(&path, versions),
             path,
             size,
             versions,
             largest_oid,
+            pct_of_total: 0.0,
         })
         .collect();
 
-    files.sort_by(|a, b| b.size.cmp(&a.size));
+    files.sort_by_key(|f| Reverse(f.size));
     files.truncate(top);
     files
 }
 
+/// `path` cut to its first `depth` components, with a trailing `/` when
+/// anything was cut off.
+fn rollup_prefix(path: &str, depth: usize) -> &str {
+    match path.match_indices('/').nth(depth.saturating_sub(1)) {
+        Some((slash, _)) => &path[..=slash],
+        None => path,
+    }
+}
+
+/// Rolls every blob up into the prefix of each path it appears at. A blob
+/// at several paths under one prefix is counted once there; `file_count`
+/// is the distinct paths under the prefix.
+fn compute_path_rollup(
+    blob_paths: &HashMap<String, Vec<String>>,
+    size_of: impl Fn(&str) -> u64,
+    depth: usize,
+) -> Vec<PathRollup> {
+    let mut by_prefix: HashMap<&str, (PathRollup, HashSet<&str>)> = HashMap::new();
+    for (oid, paths) in blob_paths {
+        let size = size_of(oid);
+        let mut seen: HashSet<&str> = HashSet::new();
+        for path in paths {
+            let prefix = rollup_prefix(path, depth);
+            let (rollup, files) = by_prefix.entry(prefix).or_default();
+            files.insert(path);
+            if seen.insert(prefix) {
+                rollup.unique_blob_bytes += size;
+                rollup.blob_count += 1;
+            }
+        }
+    }
+    let mut rollup: Vec<PathRollup> = by_prefix
+        .into_iter()
+        .map(|(prefix, (rollup, files))| PathRollup {
+            prefix: prefix.to_string(),
+            file_count: files.len(),
+            ..rollup
+        })
+        .collect();
+    sort_rollup(&mut rollup);
+    rollup
+}
+
+fn sort_rollup(rollup: &mut [PathRollup]) {
+    rollup.sort_by(|a, b| {
+        b.unique_blob_bytes
+            .cmp(&a.unique_blob_bytes)
+            .then_with(|| a.prefix.cmp(&b.prefix))
+    });
+}
+
+/// `size` as a percentage of `total`.
+pub fn pct_of_total(size: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    size as f64 * 100.0 / total as f64
+}
+
+/// Running share of `total` taken by `entries` (blob id, size) in order.
+/// A blob listed again, e.g. as the largest version of a second path,
+/// adds nothing the second time.
+pub fn cumulative_pct<'a>(
+    entries: impl IntoIterator<Item = (&'a str, u64)>,
+    total: u64,
+) -> Vec<f64> {
+    let mut seen = HashSet::new();
+    let mut sum = 0u64;
+    entries
+        .into_iter()
+        .map(|(oid, size)| {
+            if seen.insert(oid) {
+                sum += size;
+            }
+            pct_of_total(sum, total)
+        })
+        .collect()
+}
+
+fn apply_size_shares(metrics: &mut RepositoryMetrics) {
+    let total = metrics.unique_blob_bytes;
+    for blob in metrics
+        .largest_blobs
+        .iter_mut()
+        .chain(metrics.blobs_over_threshold.iter_mut())
+    {
+        blob.pct_of_total = Some(pct_of_total(blob.size, total));
+    }
+    for file in &mut metrics.largest_files {
+        file.pct_of_total = pct_of_total(file.size, total);
+    }
+}
+
 fn push_top(heap: &mut BinaryHeap<Reverse<(u64, String)>>, limit: usize, size: u64, oid: &str) {
     if limit == 0 {
         return;
@@ -1346,13 +2945,20 @@ fn push_top(heap: &mut BinaryHeap<Reverse<(u64, String)>>, limit: usize, size: u
     }
 }
 
-fn banner(title: &str) -> String {
+fn banner(style: TableStyle, title: &str) -> String {
+    if style.plain {
+        return format!("# {title}");
+    }
     let banner = format!("{:=^64}", format!(" {} ", title));
     styled_text(&banner, Color::Cyan, true, stdout_supports_color())
 }
 
-fn print_section(title: &str) {
+fn print_section(style: TableStyle, title: &str) {
     println!();
+    if style.plain {
+        println!("# {title}");
+        return;
+    }
     let section = format!("{:-^64}", format!(" {} ", title));
     println!(
         "{}",
@@ -1360,10 +2966,69 @@ fn print_section(title: &str) {
     );
 }
 
-fn print_table(headers: &[(&str, CellAlignment)], rows: Vec<Vec<Cow<'_, str>>>) {
-    if rows.is_empty() {
-        return;
+/// How `print_table` lays out a column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColumnKind {
+    /// Free text, wrapped to fit the terminal.
+    Text,
+    /// Counts, sizes and ids: always on one line at full width.
+    Fixed,
+    /// Repository paths, shortened in the middle past `max_path_width`.
+    Path,
+}
+
+type Column<'a> = (&'a str, CellAlignment, ColumnKind);
+
+/// Settings shared by every table of one human report.
+#[derive(Debug, Clone, Copy)]
+struct TableStyle {
+    plain: bool,
+    max_path_width: usize,
+}
+
+impl TableStyle {
+    fn new(cfg: &AnalyzeConfig) -> Self {
+        Self {
+            plain: cfg.plain,
+            max_path_width: cfg.max_path_width,
+        }
+    }
+}
+
+/// Shortens `path` to `max` characters by replacing its middle with `…`,
+/// keeping more of the tail so the file name stays visible.
+fn shorten_path_middle(path: &str, max: usize) -> Cow<'_, str> {
+    let len = path.chars().count();
+    if len <= max || max < 2 {
+        return Cow::Borrowed(path);
+    }
+    let keep = max - 1;
+    let head = keep / 3;
+    let tail = keep - head;
+    let mut out: String = path.chars().take(head).collect();
+    out.push('…');
+    out.extend(path.chars().skip(len - tail));
+    Cow::Owned(out)
+}
+
+fn render_table(style: TableStyle, headers: &[Column<'_>], rows: Vec<Vec<Cow<'_, str>>>) -> String {
+    if style.plain {
+        // Tab-separated, with full paths; tabs and newlines inside a value
+        // would break the columns, so they become spaces.
+        let field = |value: &str| value.trim().replace(['\t', '\n', '\r'], " ");
+        let mut out = headers
+            .iter()
+            .map(|(title, _, _)| *title)
+            .collect::<Vec<_>>()
+            .join("\t");
+        out.push('\n');
+        for row in rows {
+            out.push_str(&row.iter().map(|v| field(v)).collect::<Vec<_>>().join("\t"));
+            out.push('\n');
+        }
+        return out;
     }
+
     let mut table = Table::new();
     table.load_preset(UTF8_FULL);
     table.apply_modifier(UTF8_ROUND_CORNERS);
@@ -1371,7 +3036,7 @@ fn print_table(headers: &[(&str, CellAlignment)], rows: Vec<Vec<Cow<'_, str>>>)
 
     let header_cells = headers
         .iter()
-        .map(|(title, align)| {
+        .map(|(title, align, _)| {
             Cell::new(*title)
                 .add_attribute(Attribute::Bold)
                 .set_alignment(*align)
@@ -1382,15 +3047,39 @@ fn print_table(headers: &[(&str, CellAlignment)], rows: Vec<Vec<Cow<'_, str>>>)
     for row in rows {
         let cells = headers
             .iter()
-            .zip(row.into_iter())
-            .map(|((_, align), value)| Cell::new(value.as_ref()).set_alignment(*align))
+            .zip(row)
+            .map(|((_, align, kind), value)| {
+                let value = match kind {
+                    ColumnKind::Path => shorten_path_middle(&value, style.max_path_width),
+                    _ => Cow::Borrowed(value.as_ref()),
+                };
+                Cell::new(value).set_alignment(*align)
+            })
             .collect::<Vec<_>>();
         table.add_row(cells);
     }
+    // Paths are already capped, and numbers must never wrap; only free text
+    // gives way when the terminal is narrow.
+    for (column, (_, _, kind)) in table.column_iter_mut().zip(headers) {
+        if *kind != ColumnKind::Text {
+            column.set_constraint(ColumnConstraint::ContentWidth);
+        }
+    }
 
+    let mut out = String::new();
     for line in table.to_string().lines() {
-        println!("  {}", line);
+        out.push_str("  ");
+        out.push_str(line);
+        out.push('\n');
     }
+    out
+}
+
+fn print_table(style: TableStyle, headers: &[Column<'_>], rows: Vec<Vec<Cow<'_, str>>>) {
+    if rows.is_empty() {
+        return;
+    }
+    print!("{}", render_table(style, headers, rows));
 }
 
 fn format_count<T: Into<u64>>(value: T) -> String {
@@ -1405,115 +3094,171 @@ fn format_count<T: Into<u64>>(value: T) -> String {
     out.chars().rev().collect()
 }
 
-fn format_elapsed(duration: std::time::Duration) -> String {
-    let secs = duration.as_secs();
-    if secs < 60 {
-        format!("{:.1}s", secs as f64 + duration.subsec_nanos() as f64 / 1e9)
-    } else if secs < 3600 {
-        format!("{}m {}s", secs / 60, secs % 60)
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/// Formats a byte count in the largest unit that keeps it at or above 1.
+pub fn format_size_human(bytes: u64) -> String {
+    const KIB: u64 = 1024;
+    const MIB: u64 = KIB * 1024;
+    const GIB: u64 = MIB * 1024;
+    if bytes < KIB {
+        format!("{} B", bytes)
+    } else if bytes < MIB {
+        format!("{:.2} KiB", bytes as f64 / KIB as f64)
+    } else if bytes < GIB {
+        format!("{:.2} MiB", to_mib(bytes))
     } else {
-        format!("{}h {}m", secs / 3600, (secs % 3600) / 60)
-    }
-}
-
-fn format_size_gib(bytes: u64) -> String {
-    format!("{:.2} GiB", to_gib(bytes))
-}
-
-fn build_summary_rows(metrics: &RepositoryMetrics) -> Vec<Vec<Cow<'_, str>>> {
-    let mut rows: Vec<Vec<Cow<'_, str>>> = Vec::new();
-
-    // Overall repository size
-    rows.push(vec![
-        Cow::Borrowed("Overall repository size"),
-        Cow::Borrowed(""),
-    ]);
-    // * Total objects
-    rows.push(vec![
-        Cow::Borrowed("  * Total objects"),
-        Cow::Owned(format_count(metrics.total_objects)),
-    ]);
-    // * Total size
-    rows.push(vec![
-        Cow::Borrowed("  * Total size"),
-        Cow::Owned(format_size_gib(metrics.total_size_bytes)),
-    ]);
-    // * Loose objects
-    rows.push(vec![
-        Cow::Borrowed("  * Loose objects"),
-        Cow::Owned(format!(
-            "{} ({:.2} MiB)",
+        format!("{:.2} GiB", to_gib(bytes))
+    }
+}
+
+fn metric_row(label: &'static str, value: String) -> Vec<Cow<'static, str>> {
+    vec![Cow::Borrowed(label), Cow::Owned(value)]
+}
+
+fn section_row(label: &'static str) -> Vec<Cow<'static, str>> {
+    vec![Cow::Borrowed(label), Cow::Borrowed("")]
+}
+
+/// Row for a metric nested under a section header, e.g. `  * Total objects`.
+macro_rules! metric_sub_row {
+    ($label:literal, $value:expr) => {
+        metric_row(concat!("  * ", $label), $value)
+    };
+}
+
+fn build_summary_rows(metrics: &RepositoryMetrics) -> Vec<Vec<Cow<'static, str>>> {
+    let mut rows = Vec::new();
+
+    rows.push(section_row("Overall repository size"));
+    rows.push(metric_sub_row!(
+        "Total objects",
+        format_count(metrics.total_objects)
+    ));
+    rows.push(metric_sub_row!(
+        "Total size",
+        format_size_human(metrics.total_size_bytes)
+    ));
+    rows.push(metric_sub_row!(
+        "Loose objects",
+        format!(
+            "{} ({})",
             format_count(metrics.loose_objects),
-            to_mib(metrics.loose_size_bytes)
-        )),
-    ]);
-    // * Packed objects
-    rows.push(vec![
-        Cow::Borrowed("  * Packed objects"),
-        Cow::Owned(format!(
-            "{} ({:.2} MiB)",
+            format_size_human(metrics.loose_size_bytes)
+        )
+    ));
+    rows.push(metric_sub_row!(
+        "Packed objects",
+        format!(
+            "{} ({})",
             format_count(metrics.packed_objects),
-            to_mib(metrics.packed_size_bytes)
-        )),
-    ]);
-
-    // Objects
-    rows.push(vec![Cow::Borrowed("Objects"), Cow::Borrowed("")]);
+            format_size_human(metrics.packed_size_bytes)
+        )
+    ));
+    if !metrics.alternates.is_empty() {
+        rows.push(metric_sub_row!(
+            "Alternate objects",
+            format!(
+                "{} ({}) in {} store(s)",
+                format_count(metrics.alternates.iter().map(|a| a.objects).sum::<u64>()),
+                format_size_human(metrics.alternates.iter().map(|a| a.size_bytes).sum()),
+                format_count(metrics.alternates.len() as u64)
+            )
+        ));
+    }
+    rows.push(metric_sub_row!(
+        "Pack fragmentation",
+        format!(
+            "{} pack file(s), largest {}",
+            format_count(metrics.pack_file_count as u64),
+            format_size_human(metrics.largest_pack_bytes)
+        )
+    ));
+
+    if metrics.ignored_blob_count > 0 {
+        rows.push(metric_sub_row!(
+            "Ignored blobs",
+            format_count(metrics.ignored_blob_count)
+        ));
+    }
+
+    rows.push(section_row("Packfiles"));
+    rows.push(metric_sub_row!("Packs", format_count(metrics.pack_count)));
+    rows.push(metric_sub_row!(
+        "Largest pack",
+        format_size_human(metrics.largest_pack_bytes)
+    ));
+    rows.push(metric_sub_row!(
+        "Multi-pack-index",
+        yes_no(metrics.has_multi_pack_index).to_string()
+    ));
+    rows.push(metric_sub_row!(
+        "Commit-graph",
+        yes_no(metrics.has_commit_graph).to_string()
+    ));
+    rows.push(metric_sub_row!(
+        "Bitmaps",
+        yes_no(metrics.has_bitmaps).to_string()
+    ));
+
+    rows.push(section_row("Objects"));
     if let Some(count) = metrics.object_types.get("commit") {
-        rows.push(vec![
-            Cow::Borrowed("  * Commits (count)"),
-            Cow::Owned(format_count(*count)),
-        ]);
+        rows.push(metric_sub_row!("Commits (count)", format_count(*count)));
     }
     if let Some(count) = metrics.object_types.get("blob") {
-        rows.push(vec![
-            Cow::Borrowed("  * Blobs (count)"),
-            Cow::Owned(format_count(*count)),
-        ]);
-    }
-
-    // References
-    rows.push(vec![Cow::Borrowed("References"), Cow::Borrowed("")]);
-    rows.push(vec![
-        Cow::Borrowed("  * Total"),
-        Cow::Owned(format_count(metrics.refs_total as u64)),
-    ]);
-    rows.push(vec![
-        Cow::Borrowed("  * Heads"),
-        Cow::Owned(format_count(metrics.refs_heads as u64)),
-    ]);
-    rows.push(vec![
-        Cow::Borrowed("  * Tags"),
-        Cow::Owned(format_count(metrics.refs_tags as u64)),
-    ]);
-    rows.push(vec![
-        Cow::Borrowed("  * Remotes"),
-        Cow::Owned(format_count(metrics.refs_remotes as u64)),
-    ]);
-    rows.push(vec![
-        Cow::Borrowed("  * Other"),
-        Cow::Owned(format_count(metrics.refs_other as u64)),
-    ]);
-
-    // History structure
-    rows.push(vec![Cow::Borrowed("History"), Cow::Borrowed("")]);
-    rows.push(vec![
-        Cow::Borrowed("  * Max parents"),
-        Cow::Owned(format_count(metrics.max_commit_parents as u64)),
-    ]);
-
-    // Trees
-    rows.push(vec![Cow::Borrowed("Trees"), Cow::Borrowed("")]);
+        rows.push(metric_sub_row!("Blobs (count)", format_count(*count)));
+    }
+
+    rows.push(section_row("References"));
+    rows.push(metric_sub_row!(
+        "Total",
+        format_count(metrics.refs_total as u64)
+    ));
+    rows.push(metric_sub_row!(
+        "Heads",
+        format_count(metrics.refs_heads as u64)
+    ));
+    rows.push(metric_sub_row!(
+        "Tags",
+        format_count(metrics.refs_tags as u64)
+    ));
+    rows.push(metric_sub_row!(
+        "Remotes",
+        format_count(metrics.refs_remotes as u64)
+    ));
+    rows.push(metric_sub_row!(
+        "Other",
+        format_count(metrics.refs_other as u64)
+    ));
+    rows.push(metric_sub_row!(
+        "Notes refs",
+        format_count(metrics.notes_refs.len() as u64)
+    ));
+    rows.push(metric_sub_row!(
+        "Notes",
+        format_count(metrics.notes_object_count)
+    ));
+
+    rows.push(section_row("History"));
+    rows.push(metric_sub_row!(
+        "Max parents",
+        format_count(metrics.max_commit_parents as u64)
+    ));
+
+    rows.push(section_row("Trees"));
     if let Some(count) = metrics.object_types.get("tree") {
-        rows.push(vec![
-            Cow::Borrowed("  * Trees (count)"),
-            Cow::Owned(format_count(*count)),
-        ]);
+        rows.push(metric_sub_row!("Trees (count)", format_count(*count)));
     }
-    rows.push(vec![
-        Cow::Borrowed("  * Trees total size"),
-        Cow::Owned(format!("{:.2} GiB", to_gib(metrics.tree_total_size_bytes))),
-    ]);
+    rows.push(metric_sub_row!(
+        "Trees total size",
+        format_size_human(metrics.tree_total_size_bytes)
+    ));
 
     rows
 }
@@ -1521,23 +3266,262 @@ fn build_summary_rows(metrics: &RepositoryMetrics) -> Vec<Vec<Cow<'_, str>>> {
 #[cfg(test)]
 mod tests {
     use super::{
-        collect_blob_sizes_from_reader, collect_oversized_commit_messages_from_reader,
-        color_output_enabled, flush_progress_writer,
+        apply_size_shares, build_summary_rows, collect_blob_sizes_from_reader,
+        collect_commit_message_stats_from_reader, collect_suspect_identities_from_reader,
+        commit_sparkline, compute_largest_files, compute_path_rollup, cumulative_pct,
+        evaluate_warnings, format_size_human, pct_of_total, read_lfs_pointers, render_table,
+        rollup_prefix, scan_blob_paths, shorten_path_middle, ColumnKind, CommitMessageStat,
+        ObjectStat, PathRollup, RepositoryMetrics, TableStyle, WarningLevel,
     };
-    use std::io::{Cursor, ErrorKind, Write};
+    use crate::opts::{AnalyzeConfig, AnalyzeThresholds};
+    use comfy_table::CellAlignment;
+    use std::borrow::Cow;
+    use std::collections::{BTreeMap, HashMap, HashSet};
+    use std::io::{BufRead, Cursor};
+
+    fn blob(oid: &str, size: u64) -> ObjectStat {
+        ObjectStat {
+            oid: oid.to_string(),
+            size,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn merge_from_sums_counts_and_deduplicates_blobs() {
+        let mut left = RepositoryMetrics {
+            total_objects: 10,
+            total_size_bytes: 1000,
+            loose_objects: 4,
+            max_commit_parents: 2,
+            object_types: BTreeMap::from([("blob".to_string(), 6), ("commit".to_string(), 4)]),
+            largest_blobs: vec![blob("aaa", 500), blob("bbb", 300)],
+            oversized_commit_messages: vec![CommitMessageStat {
+                oid: "c1".to_string(),
+                length: 20_000,
+            }],
+            ..Default::default()
+        };
+        let right = RepositoryMetrics {
+            total_objects: 7,
+            total_size_bytes: 700,
+            loose_objects: 1,
+            max_commit_parents: 3,
+            object_types: BTreeMap::from([("blob".to_string(), 5), ("tree".to_string(), 2)]),
+            largest_blobs: vec![blob("bbb", 300), blob("ccc", 400), blob("ddd", 100)],
+            oversized_commit_messages: vec![
+                CommitMessageStat {
+                    oid: "c1".to_string(),
+                    length: 20_000,
+                },
+                CommitMessageStat {
+                    oid: "c2".to_string(),
+                    length: 15_000,
+                },
+            ],
+            ..Default::default()
+        };
+        let cfg = AnalyzeConfig {
+            top: 3,
+            ..Default::default()
+        };
 
-    struct ErrorWriter {
-        kind: ErrorKind,
+        left.merge_from(&right, &cfg);
+
+        assert_eq!(left.total_objects, 17);
+        assert_eq!(left.total_size_bytes, 1700);
+        assert_eq!(left.loose_objects, 5);
+        assert_eq!(left.max_commit_parents, 3);
+        assert_eq!(left.object_types["blob"], 11);
+        assert_eq!(left.object_types["tree"], 2);
+        let oids: Vec<&str> = left.largest_blobs.iter().map(|b| b.oid.as_str()).collect();
+        assert_eq!(oids, ["aaa", "ccc", "bbb"]);
+        let messages: Vec<&str> = left
+            .oversized_commit_messages
+            .iter()
+            .map(|c| c.oid.as_str())
+            .collect();
+        assert_eq!(messages, ["c1", "c2"]);
     }
 
-    impl Write for ErrorWriter {
-        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
-            Ok(buf.len())
+    #[test]
+    fn summary_rows_have_two_columns_and_borrowed_labels() {
+        let mut metrics = RepositoryMetrics::default();
+        for kind in ["commit", "blob", "tree"] {
+            metrics.object_types.insert(kind.to_string(), 3);
+        }
+        let rows = build_summary_rows(&metrics);
+        assert!(!rows.is_empty());
+        for row in &rows {
+            assert_eq!(row.len(), 2, "row {row:?}");
+            assert!(matches!(row[0], Cow::Borrowed(_)), "label {:?}", row[0]);
         }
+        assert!(rows
+            .iter()
+            .any(|row| row[0] == "  * Total objects" && row[1] == "0"));
+        assert!(rows.iter().any(|row| row[0] == "  * Trees (count)"));
+    }
+
+    /// The blob path pass as it was before `scan_blob_paths`, kept to check
+    /// that the faster version produces the same mapping.
+    fn legacy_blob_paths<R: BufRead>(
+        reader: &mut R,
+        wanted: &HashMap<String, u64>,
+    ) -> HashMap<String, Vec<String>> {
+        let blob_oids: HashSet<String> = wanted.keys().cloned().collect();
+        let mut blob_path_map: HashMap<String, String> = HashMap::new();
+        let mut pathless_blobs: HashSet<String> = HashSet::new();
+        let mut line_buf = String::new();
+        while reader.read_line(&mut line_buf).unwrap() > 0 {
+            let line = line_buf.trim_end();
+            let mut parts = line.splitn(2, ' ');
+            if let Some(oid) = parts.next() {
+                let path = parts.next().unwrap_or("");
+                if blob_oids.contains(oid) {
+                    if path.is_empty() {
+                        pathless_blobs.insert(oid.to_string());
+                    } else {
+                        blob_path_map.insert(oid.to_string(), path.to_string());
+                        if blob_path_map.len() >= blob_oids.len() {
+                            break;
+                        }
+                    }
+                }
+            }
+            line_buf.clear();
+        }
+        let mut blob_paths: HashMap<String, Vec<String>> = HashMap::new();
+        for (oid, path) in blob_path_map {
+            blob_paths.entry(oid).or_default().push(path);
+        }
+        for oid in pathless_blobs {
+            blob_paths.entry(oid).or_default();
+        }
+        blob_paths
+    }
 
-        fn flush(&mut self) -> std::io::Result<()> {
-            Err(std::io::Error::new(self.kind, "forced flush error"))
+    /// `rev-list --objects --all`-shaped output with `commits` commits, each
+    /// touching one blob under a handful of shared paths, plus a tagged blob.
+    fn rev_list_fixture(commits: usize) -> (String, HashMap<String, u64>) {
+        let mut out = String::new();
+        let mut wanted = HashMap::new();
+        for i in 0..commits {
+            out.push_str(&format!("c{:039x}\n", i));
+        }
+        for i in 0..commits {
+            out.push_str(&format!("d{:039x}\n", i));
+            out.push_str(&format!("e{:039x} src\n", i));
+            let blob = format!("{:040x}", i + 1);
+            out.push_str(&format!("{blob} src/file-{}.bin\n", i % 7));
+            // Every third blob was pruned from history but is still in the
+            // object store, so rev-list never lists it.
+            if i % 3 != 0 {
+                wanted.insert(blob, i as u64);
+            }
         }
+        let tagged = format!("{:040x}", u64::MAX);
+        out.push_str(&format!("{tagged}\n"));
+        wanted.insert(tagged, 1);
+        (out, wanted)
+    }
+
+    #[test]
+    fn scan_blob_paths_matches_previous_implementation() {
+        let (input, wanted) = rev_list_fixture(50);
+
+        let scan = scan_blob_paths(&mut Cursor::new(input.as_bytes()), &wanted).unwrap();
+        let legacy = legacy_blob_paths(&mut Cursor::new(input.as_bytes()), &wanted);
+
+        assert_eq!(scan.blob_paths, legacy);
+        assert_eq!(scan.mapped, 33);
+        assert!(!scan.complete, "the tagged blob never gets a path");
+        assert_eq!(
+            scan.blob_paths[&format!("{:040x}", 2)],
+            vec!["src/file-1.bin".to_string()]
+        );
+        assert!(scan.blob_paths[&format!("{:040x}", u64::MAX)].is_empty());
+        assert!(!scan.blob_paths.contains_key(&format!("{:040x}", 1)));
+    }
+
+    #[test]
+    fn scan_blob_paths_matches_previous_implementation_for_repeated_blobs() {
+        let (a, b, c) = ("a".repeat(40), "b".repeat(40), "c".repeat(40));
+        let wanted = HashMap::from([(a.clone(), 1), (b.clone(), 2), (c.clone(), 3)]);
+        // `a` appears at several paths, `b` pathless before it gets one, and
+        // `c` only pathless.
+        let input =
+            format!("{a} first.txt\n{b}\n{c}\n{a} second.txt\n{b} late/path.txt\n{a} third.txt\n");
+
+        let scan = scan_blob_paths(&mut Cursor::new(input.as_bytes()), &wanted).unwrap();
+        let legacy = legacy_blob_paths(&mut Cursor::new(input.as_bytes()), &wanted);
+
+        assert_eq!(scan.blob_paths, legacy);
+        assert_eq!(scan.blob_paths[&a], vec!["third.txt".to_string()]);
+        assert_eq!(scan.blob_paths[&b], vec!["late/path.txt".to_string()]);
+        assert!(scan.blob_paths[&c].is_empty());
+        assert_eq!(scan.mapped, 2);
+        assert!(!scan.complete, "`c` never got a path");
+    }
+
+    #[test]
+    fn scan_blob_paths_stops_once_every_wanted_blob_has_a_path() {
+        let wanted = HashMap::from([("a".repeat(40), 1), ("b".repeat(40), 2)]);
+        let input = format!(
+            "{} one.txt\n{} two.txt\n{} trailing-tree\nnot even an object line\n",
+            "a".repeat(40),
+            "b".repeat(40),
+            "c".repeat(40)
+        );
+        let mut reader = Cursor::new(input.as_bytes());
+
+        let scan = scan_blob_paths(&mut reader, &wanted).unwrap();
+
+        assert!(scan.complete);
+        assert_eq!(scan.mapped, 2);
+        let rest: Vec<String> = reader.lines().map(Result::unwrap).collect();
+        assert_eq!(
+            rest.len(),
+            2,
+            "lines after the last wanted blob are left unread"
+        );
+    }
+
+    #[test]
+    fn scan_blob_paths_reports_incomplete_when_wanted_blobs_are_unreachable() {
+        let wanted = HashMap::from([("a".repeat(40), 1), ("d".repeat(40), 2)]);
+        let input = format!("{} dir/a b.txt\n", "a".repeat(40));
+
+        let scan = scan_blob_paths(&mut Cursor::new(input.as_bytes()), &wanted).unwrap();
+
+        assert!(!scan.complete);
+        assert_eq!(scan.blob_paths.len(), 1);
+        assert_eq!(
+            scan.blob_paths[&"a".repeat(40)],
+            vec!["dir/a b.txt".to_string()]
+        );
+    }
+
+    #[test]
+    fn read_lfs_pointers_answers_each_candidate_in_order() {
+        let pointer = b"version https://git-lfs.github.com/spec/v1\noid sha256:ab\nsize 9\n";
+        let plain = b"just some text";
+        let mut answers = Vec::new();
+        answers.extend_from_slice(format!("{} blob {}\n", "a".repeat(40), plain.len()).as_bytes());
+        answers.extend_from_slice(plain);
+        answers.extend_from_slice(format!("\n{} missing\n", "b".repeat(40)).as_bytes());
+        answers
+            .extend_from_slice(format!("{} blob {}\n", "c".repeat(40), pointer.len()).as_bytes());
+        answers.extend_from_slice(pointer);
+        answers.push(b'\n');
+        let candidates = ["a".repeat(40), "b".repeat(40), "c".repeat(40)];
+
+        let pointers = read_lfs_pointers(&mut Cursor::new(&answers), &candidates).unwrap();
+
+        assert_eq!(pointers, ["c".repeat(40)]);
+        // cat-file exits after answering only the first candidate.
+        let first = 40 + " blob 14\n".len() + plain.len() + 1;
+        let err = read_lfs_pointers(&mut Cursor::new(&answers[..first]), &candidates).unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
     }
 
     #[test]
@@ -1601,37 +3585,217 @@ ffffffffffffffffffffffffffffffffffffffff blob 12 6
     }
 
     #[test]
-    fn flush_progress_writer_treats_broken_pipe_as_non_fatal() {
-        let mut writer = ErrorWriter {
-            kind: ErrorKind::BrokenPipe,
+    fn pack_and_loose_object_thresholds_raise_info_warnings() {
+        let metrics = RepositoryMetrics {
+            pack_count: 6,
+            pack_file_count: 6,
+            loose_objects: 20,
+            packed_objects: 80,
+            total_objects: 100,
+            ..Default::default()
         };
-        let result = flush_progress_writer(&mut writer);
-        assert!(result.is_ok(), "BrokenPipe should not propagate as error");
-        assert!(
-            !result.expect("BrokenPipe should map to non-fatal false"),
-            "BrokenPipe should return false to indicate no further progress output"
+        let thresholds = AnalyzeThresholds::default();
+
+        let warnings = evaluate_warnings(&metrics, &thresholds);
+        let fragmentation = warnings
+            .iter()
+            .find(|w| w.message.contains("6 pack files"))
+            .expect("fragmentation warning");
+        assert_eq!(fragmentation.level, WarningLevel::Info);
+        let loose = warnings
+            .iter()
+            .find(|w| w.message.contains("objects are loose"))
+            .expect("loose object warning");
+        assert_eq!(loose.level, WarningLevel::Info);
+        assert!(loose.message.starts_with("20%"), "{}", loose.message);
+
+        let quiet = AnalyzeThresholds {
+            warn_pack_fragmentation: 10,
+            warn_loose_object_ratio: 0.25,
+            ..AnalyzeThresholds::default()
+        };
+        let warnings = evaluate_warnings(&metrics, &quiet);
+        assert!(!warnings.iter().any(|w| w.message.contains("pack files")));
+        assert!(!warnings.iter().any(|w| w.message.contains("loose")));
+    }
+
+    fn strip_ansi(s: &str) -> String {
+        let mut out = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c == '\u{1b}' {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            } else {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn shorten_path_middle_keeps_the_file_name() {
+        let path = "vendor/a-very-long-package/lib/deeply/nested/module.min.js";
+        let short = shorten_path_middle(path, 30);
+        assert_eq!(short, "vendor/a-…nested/module.min.js");
+        assert_eq!(short.chars().count(), 30);
+        assert_eq!(shorten_path_middle("src/lib.rs", 30), "src/lib.rs");
+    }
+
+    #[test]
+    fn render_table_caps_paths_and_keeps_numbers_whole() {
+        let style = TableStyle {
+            plain: false,
+            max_path_width: 24,
+        };
+        let headers = [
+            ("Size", CellAlignment::Right, ColumnKind::Fixed),
+            ("Path", CellAlignment::Left, ColumnKind::Path),
+        ];
+        let rows = vec![vec![
+            Cow::Borrowed("123.45 MiB"),
+            Cow::Borrowed("node_modules/some-package/dist/esm/bundle.js"),
+        ]];
+        let rendered = strip_ansi(&render_table(style, &headers, rows));
+        let expected = [
+            "  ╭────────────┬──────────────────────────╮",
+            "  │       Size ┆ Path                     │",
+            "  ╞════════════╪══════════════════════════╡",
+            "  │ 123.45 MiB ┆ node_mo…st/esm/bundle.js │",
+            "  ╰────────────┴──────────────────────────╯",
+        ];
+        let rendered: Vec<&str> = rendered.lines().collect();
+        assert_eq!(rendered, expected);
+    }
+
+    #[test]
+    fn render_table_plain_prints_tab_separated_full_values() {
+        let style = TableStyle {
+            plain: true,
+            max_path_width: 16,
+        };
+        let headers = [
+            ("Size", CellAlignment::Right, ColumnKind::Fixed),
+            ("Path", CellAlignment::Left, ColumnKind::Path),
+            ("Note", CellAlignment::Left, ColumnKind::Text),
+        ];
+        let rows = vec![vec![
+            Cow::Borrowed("1 B"),
+            Cow::Borrowed("a/long/path/that/is/not/shortened.txt"),
+            Cow::Borrowed("tab\there"),
+        ]];
+        assert_eq!(
+            render_table(style, &headers, rows),
+            "Size\tPath\tNote\n1 B\ta/long/path/that/is/not/shortened.txt\ttab here\n"
         );
     }
 
     #[test]
-    fn flush_progress_writer_propagates_other_flush_errors() {
-        let mut writer = ErrorWriter {
-            kind: ErrorKind::PermissionDenied,
+    fn format_size_human_picks_unit_by_magnitude() {
+        assert_eq!(format_size_human(512), "512 B");
+        assert_eq!(format_size_human(2048), "2.00 KiB");
+        assert_eq!(format_size_human(1_572_864), "1.50 MiB");
+        assert_eq!(format_size_human(3_221_225_472), "3.00 GiB");
+    }
+
+    #[test]
+    fn rollup_prefix_keeps_the_first_components() {
+        assert_eq!(rollup_prefix("src/core/lib.rs", 2), "src/core/");
+        assert_eq!(rollup_prefix("src/core/lib.rs", 1), "src/");
+        assert_eq!(rollup_prefix("src/main.rs", 2), "src/main.rs");
+        assert_eq!(rollup_prefix("README.md", 2), "README.md");
+        assert_eq!(rollup_prefix("a/b/c", 3), "a/b/c");
+    }
+
+    #[test]
+    fn path_rollup_counts_blobs_once_per_prefix() {
+        // "a" is at two paths under vendor/lib/ and one under src/core/.
+        let blob_paths: HashMap<String, Vec<String>> = [
+            (
+                "a",
+                vec!["vendor/lib/x.bin", "vendor/lib/y/x.bin", "src/core/x.bin"],
+            ),
+            ("b", vec!["vendor/lib/z.bin"]),
+            ("c", vec!["README.md"]),
+            ("d", vec!["README.md", "src/main.rs"]),
+        ]
+        .into_iter()
+        .map(|(oid, paths)| {
+            (
+                oid.to_string(),
+                paths.into_iter().map(String::from).collect(),
+            )
+        })
+        .collect();
+        let sizes: HashMap<&str, u64> = [("a", 100), ("b", 60), ("c", 5), ("d", 7)]
+            .into_iter()
+            .collect();
+
+        let rollup = compute_path_rollup(&blob_paths, |oid| sizes[oid], 2);
+
+        let row = |prefix: &str, bytes, blobs, files| PathRollup {
+            prefix: prefix.to_string(),
+            unique_blob_bytes: bytes,
+            blob_count: blobs,
+            file_count: files,
         };
-        let result = flush_progress_writer(&mut writer);
-        assert!(
-            result.is_err(),
-            "non-BrokenPipe flush errors should propagate"
+        assert_eq!(
+            rollup,
+            vec![
+                row("vendor/lib/", 160, 2, 3),
+                row("src/core/", 100, 1, 1),
+                row("README.md", 12, 2, 1),
+                row("src/main.rs", 7, 1, 1),
+            ]
         );
     }
 
     #[test]
-    fn color_output_enabled_respects_no_color_and_force_color() {
-        assert!(color_output_enabled(true, false, false));
-        assert!(!color_output_enabled(false, false, false));
-        assert!(color_output_enabled(false, false, true));
-        assert!(!color_output_enabled(true, true, false));
-        assert!(!color_output_enabled(false, true, true));
+    fn size_shares_count_a_blob_at_several_paths_once() {
+        // Blob "a" is reachable as both x.bin and y.bin.
+        let blob_paths: HashMap<String, Vec<String>> = [
+            ("a", vec!["x.bin", "y.bin"]),
+            ("b", vec!["z.bin"]),
+            ("c", vec!["w.bin"]),
+        ]
+        .into_iter()
+        .map(|(oid, paths)| {
+            (
+                oid.to_string(),
+                paths.into_iter().map(String::from).collect(),
+            )
+        })
+        .collect();
+        let sizes: HashMap<String, u64> = [("a", 100), ("b", 60), ("c", 40)]
+            .into_iter()
+            .map(|(oid, size)| (oid.to_string(), size))
+            .collect();
+        let mut metrics = RepositoryMetrics {
+            unique_blob_bytes: sizes.values().sum(),
+            largest_files: compute_largest_files(&blob_paths, &sizes, &HashMap::new(), 10),
+            ..Default::default()
+        };
+        apply_size_shares(&mut metrics);
+
+        assert_eq!(metrics.unique_blob_bytes, 200);
+        let pcts: Vec<f64> = metrics
+            .largest_files
+            .iter()
+            .map(|f| f.pct_of_total)
+            .collect();
+        assert_eq!(pcts, vec![50.0, 50.0, 30.0, 20.0]);
+        let cumulative = cumulative_pct(
+            metrics
+                .largest_files
+                .iter()
+                .map(|f| (f.largest_oid.as_str(), f.size)),
+            metrics.unique_blob_bytes,
+        );
+        assert_eq!(cumulative, vec![50.0, 50.0, 80.0, 100.0]);
+        assert_eq!(pct_of_total(5, 0), 0.0);
     }
 
     #[test]
@@ -1640,8 +3804,9 @@ ffffffffffffffffffffffffffffffffffffffff blob 12 6
 bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\0this message is long enough\0";
         let mut reader = Cursor::new(&input[..]);
 
-        let stats =
-            collect_oversized_commit_messages_from_reader(&mut reader, 10).expect("parse stream");
+        let stats = collect_commit_message_stats_from_reader(&mut reader, 10, 0)
+            .expect("parse stream")
+            .oversized;
 
         assert_eq!(stats.len(), 1, "expected only one message above threshold");
         assert_eq!(
@@ -1654,17 +3819,109 @@ bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\0this message is long enough\0";
         );
     }
 
+    #[test]
+    fn collect_oversized_commit_messages_from_reader_resyncs_after_embedded_nul() {
+        let input = b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\0imported\0binary patch\n\0\n\
+bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\0this message is long enough\n\0\n";
+        let mut reader = Cursor::new(&input[..]);
+
+        let stats = collect_commit_message_stats_from_reader(&mut reader, 10, 0)
+            .expect("parse stream")
+            .oversized;
+
+        assert_eq!(stats.len(), 1, "misframed fields should be skipped");
+        assert_eq!(stats[0].oid, "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+        assert_eq!(stats[0].length, "this message is long enough\n".len());
+    }
+
+    #[test]
+    fn collect_commit_message_stats_flags_long_subjects_and_control_chars() {
+        let input = b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\0imported\0binary patch\n\0\n\
+bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\0a subject that is far too long\n\nbody\n\0\n\
+cccccccccccccccccccccccccccccccccccccccc\0bell \x07 in body\r\n\0\n\
+dddddddddddddddddddddddddddddddddddddddd\0fine\tsubject\n\0\n";
+        let mut reader = Cursor::new(&input[..]);
+
+        let scan =
+            collect_commit_message_stats_from_reader(&mut reader, 0, 20).expect("parse stream");
+
+        assert!(scan.oversized.is_empty());
+        let flagged: Vec<(&str, usize, bool)> = scan
+            .problematic
+            .iter()
+            .map(|m| (&m.oid[..1], m.subject_length, m.has_control_chars))
+            .collect();
+        assert_eq!(
+            flagged,
+            vec![("a", 8, true), ("b", 30, false), ("c", 15, true)]
+        );
+    }
+
     #[test]
     fn collect_oversized_commit_messages_from_reader_ignores_truncated_pairs() {
         let input = b"cccccccccccccccccccccccccccccccccccccccc\0";
         let mut reader = Cursor::new(&input[..]);
 
-        let stats =
-            collect_oversized_commit_messages_from_reader(&mut reader, 1).expect("parse stream");
+        let stats = collect_commit_message_stats_from_reader(&mut reader, 1, 0)
+            .expect("parse stream")
+            .oversized;
 
         assert!(
             stats.is_empty(),
             "truncated oid/message pair should be ignored without panic"
         );
     }
+
+    #[test]
+    fn collect_suspect_identities_from_reader_reports_reasons_and_total() {
+        let input = b"aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\0Ok User\0ok@example.com\0Ok User\0ok@example.com\0\n\
+bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb\0\0\0root\0root@localhost\0\n\
+cccccccccccccccccccccccccccccccccccccccc\0Bad <Name\0nobody\0Ok User\0ok@example.com\0\n";
+        let mut reader = Cursor::new(&input[..]);
+
+        let (stats, total) = collect_suspect_identities_from_reader(
+            &mut reader,
+            &["ROOT@localhost".to_string()],
+            10,
+        )
+        .expect("parse stream");
+
+        assert_eq!(total, 3);
+        assert_eq!(stats.len(), 3);
+        assert_eq!(stats[0].oid, "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb");
+        assert_eq!(stats[0].role, "author");
+        assert_eq!(stats[0].reason, "empty name, empty email");
+        assert_eq!(stats[1].role, "committer");
+        assert_eq!(stats[1].reason, "denylisted identity");
+        assert_eq!(stats[2].oid, "cccccccccccccccccccccccccccccccccccccccc");
+        assert_eq!(
+            stats[2].reason,
+            "email missing '@', mismatched angle brackets"
+        );
+
+        let mut reader = Cursor::new(&input[..]);
+        let (capped, total) =
+            collect_suspect_identities_from_reader(&mut reader, &[], 1).expect("parse stream");
+        assert_eq!(total, 2, "root@localhost is only suspect when denylisted");
+        assert_eq!(capped.len(), 1);
+    }
+    #[test]
+    fn commit_sparkline_fills_quiet_months_and_keeps_the_last_24() {
+        let histogram: BTreeMap<String, u64> = [("2023-11", 4), ("2024-02", 8), ("2024-01", 1)]
+            .into_iter()
+            .map(|(month, count)| (month.to_string(), count))
+            .collect();
+        assert_eq!(
+            commit_sparkline(&histogram),
+            Some(("2023-11".into(), "2024-02".into(), "▅▁▂█".into()))
+        );
+
+        let long: BTreeMap<String, u64> = (2020..2024)
+            .flat_map(|year| (1..=12).map(move |month| (format!("{year}-{month:02}"), 1)))
+            .collect();
+        let (from, to, line) = commit_sparkline(&long).expect("sparkline");
+        assert_eq!((from.as_str(), to.as_str()), ("2022-01", "2023-12"));
+        assert_eq!(line.chars().count(), 24);
+        assert_eq!(commit_sparkline(&BTreeMap::new()), None);
+    }
 }
@@ -0,0 +1,235 @@
+//! `--analyze-baseline FILE`: compare the current analysis with a report
+//! saved earlier by `--analyze-json`, so CI can tell new bloat from old.
+//!
+//! The baseline is read as loose JSON rather than into `AnalysisReport`:
+//! reports from older versions may lack any field, and a missing field only
+//! makes the matching delta unknown (`None`) instead of failing the run.
+
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use comfy_table::CellAlignment;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{
+    format_count, format_size_human, print_section, print_table, AnalysisReport, ColumnKind,
+    ObjectStat, TableStyle, Warning, ANALYSIS_SCHEMA_VERSION,
+};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BaselineDelta {
+    /// `None` for reports written before the schema was versioned.
+    pub baseline_schema_version: Option<u64>,
+    pub total_size_bytes_change: Option<i64>,
+    pub total_objects_change: Option<i64>,
+    /// Blobs over the warning threshold that the baseline did not list.
+    pub new_blobs_over_threshold: Option<Vec<ObjectStat>>,
+    /// Warnings whose message does not appear in the baseline.
+    pub new_warnings: Option<Vec<Warning>>,
+}
+
+pub(super) fn load(path: &Path) -> io::Result<Value> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("failed to read --analyze-baseline {}: {e}", path.display()),
+        )
+    })?;
+    let baseline: Value = serde_json::from_str(&content).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid --analyze-baseline {}: {e}", path.display()),
+        )
+    })?;
+    if !baseline.is_object() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "invalid --analyze-baseline {}: expected an --analyze-json report",
+                path.display()
+            ),
+        ));
+    }
+    if let Some(version) = baseline["schema_version"].as_u64() {
+        if version > u64::from(ANALYSIS_SCHEMA_VERSION) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "--analyze-baseline {} uses schema version {version}, newer than the supported {ANALYSIS_SCHEMA_VERSION}",
+                    path.display()
+                ),
+            ));
+        }
+    }
+    Ok(baseline)
+}
+
+pub(super) fn compute(baseline: &Value, report: &AnalysisReport) -> BaselineDelta {
+    let metrics = &baseline["metrics"];
+    let change = |field: &str, current: u64| {
+        metrics[field]
+            .as_u64()
+            .map(|before| current as i64 - before as i64)
+    };
+    let new_blobs_over_threshold = metrics["blobs_over_threshold"].as_array().map(|before| {
+        let known: HashSet<&str> = before.iter().filter_map(|b| b["oid"].as_str()).collect();
+        report
+            .metrics
+            .blobs_over_threshold
+            .iter()
+            .filter(|blob| !known.contains(blob.oid.as_str()))
+            .cloned()
+            .collect()
+    });
+    let new_warnings = baseline["warnings"].as_array().map(|before| {
+        let known: HashSet<&str> = before
+            .iter()
+            .filter_map(|w| w["message"].as_str())
+            .collect();
+        report
+            .warnings
+            .iter()
+            .filter(|w| !known.contains(w.message.as_str()))
+            .cloned()
+            .collect()
+    });
+    BaselineDelta {
+        baseline_schema_version: baseline["schema_version"].as_u64(),
+        total_size_bytes_change: change("total_size_bytes", report.metrics.total_size_bytes),
+        total_objects_change: change("total_objects", report.metrics.total_objects),
+        new_blobs_over_threshold,
+        new_warnings,
+    }
+}
+
+pub(super) fn print_human(delta: &BaselineDelta, style: TableStyle) {
+    print_section(style, "Changes since baseline");
+    let unknown = || Cow::Borrowed("unknown");
+    let rows = vec![
+        vec![
+            Cow::Borrowed("Total size"),
+            delta
+                .total_size_bytes_change
+                .map(|d| {
+                    Cow::Owned(format!(
+                        "{}{}",
+                        sign(d),
+                        format_size_human(d.unsigned_abs())
+                    ))
+                })
+                .unwrap_or_else(unknown),
+        ],
+        vec![
+            Cow::Borrowed("Objects"),
+            delta
+                .total_objects_change
+                .map(|d| Cow::Owned(format!("{}{}", sign(d), format_count(d.unsigned_abs()))))
+                .unwrap_or_else(unknown),
+        ],
+        vec![
+            Cow::Borrowed("New blobs over threshold"),
+            delta
+                .new_blobs_over_threshold
+                .as_ref()
+                .map(|b| Cow::Owned(format_count(b.len() as u64)))
+                .unwrap_or_else(unknown),
+        ],
+        vec![
+            Cow::Borrowed("New warnings"),
+            delta
+                .new_warnings
+                .as_ref()
+                .map(|w| Cow::Owned(format_count(w.len() as u64)))
+                .unwrap_or_else(unknown),
+        ],
+    ];
+    print_table(
+        style,
+        &[
+            ("Name", CellAlignment::Left, ColumnKind::Text),
+            ("Change", CellAlignment::Right, ColumnKind::Fixed),
+        ],
+        rows,
+    );
+
+    if let Some(blobs) = delta.new_blobs_over_threshold.as_ref() {
+        let rows = blobs
+            .iter()
+            .map(|blob| {
+                vec![
+                    Cow::Owned(format_size_human(blob.size)),
+                    Cow::Owned(blob.display_path()),
+                    Cow::Owned(format!("{:.8}", blob.oid)),
+                ]
+            })
+            .collect();
+        print_table(
+            style,
+            &[
+                ("Size", CellAlignment::Right, ColumnKind::Fixed),
+                ("Path", CellAlignment::Left, ColumnKind::Path),
+                ("OID", CellAlignment::Center, ColumnKind::Fixed),
+            ],
+            rows,
+        );
+    }
+    if let Some(warnings) = delta.new_warnings.as_ref() {
+        let rows = warnings
+            .iter()
+            .map(|w| {
+                vec![
+                    Cow::Owned(format!("{:?}", w.level)),
+                    Cow::Borrowed(w.message.as_str()),
+                ]
+            })
+            .collect();
+        print_table(
+            style,
+            &[
+                ("Level", CellAlignment::Center, ColumnKind::Fixed),
+                ("New warning", CellAlignment::Left, ColumnKind::Text),
+            ],
+            rows,
+        );
+    }
+}
+
+fn sign(delta: i64) -> &'static str {
+    if delta < 0 {
+        "-"
+    } else {
+        "+"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::RepositoryMetrics;
+
+    #[test]
+    fn missing_baseline_fields_make_deltas_unknown() {
+        let report = AnalysisReport {
+            schema_version: ANALYSIS_SCHEMA_VERSION,
+            metrics: RepositoryMetrics {
+                total_size_bytes: 500,
+                total_objects: 7,
+                ..Default::default()
+            },
+            warnings: Vec::new(),
+            delta: None,
+        };
+        let old = serde_json::json!({ "metrics": { "total_size_bytes": 200 } });
+
+        let delta = compute(&old, &report);
+        assert_eq!(delta.baseline_schema_version, None);
+        assert_eq!(delta.total_size_bytes_change, Some(300));
+        assert_eq!(delta.total_objects_change, None);
+        assert!(delta.new_blobs_over_threshold.is_none());
+        assert!(delta.new_warnings.is_none());
+    }
+}
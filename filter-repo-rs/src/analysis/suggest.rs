@@ -0,0 +1,273 @@
+//! Name-based guesses at where history bloat usually lives (vendored
+//! dependencies, build output, media, churned lockfiles) and the filters
+//! that would drop it.
+//!
+//! The rules are a plain table; add a row to [`RULES`] to recognize another
+//! directory, file name or suffix.
+
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum PathCategory {
+    Vendored,
+    Generated,
+    BinaryMedia,
+    LockfileChurn,
+    #[default]
+    Other,
+}
+
+impl PathCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PathCategory::Vendored => "vendored",
+            PathCategory::Generated => "generated",
+            PathCategory::BinaryMedia => "binary-media",
+            PathCategory::LockfileChurn => "lockfile-churn",
+            PathCategory::Other => "other",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Matcher {
+    /// A directory with this name anywhere in the path.
+    Dir(&'static str),
+    /// A file with exactly this name; only a candidate once it has more
+    /// than [`LOCKFILE_CHURN_VERSIONS`] versions.
+    Lockfile(&'static str),
+    /// A file name ending in this suffix.
+    Suffix(&'static str),
+}
+
+const RULES: &[(Matcher, PathCategory)] = &[
+    (Matcher::Dir("node_modules"), PathCategory::Vendored),
+    (Matcher::Dir("bower_components"), PathCategory::Vendored),
+    (Matcher::Dir("vendor"), PathCategory::Vendored),
+    (Matcher::Dir("third_party"), PathCategory::Vendored),
+    (Matcher::Dir("Pods"), PathCategory::Vendored),
+    (Matcher::Dir("dist"), PathCategory::Generated),
+    (Matcher::Dir("build"), PathCategory::Generated),
+    (Matcher::Dir("target"), PathCategory::Generated),
+    (Matcher::Dir("__pycache__"), PathCategory::Generated),
+    (Matcher::Dir(".next"), PathCategory::Generated),
+    (Matcher::Dir("coverage"), PathCategory::Generated),
+    (
+        Matcher::Lockfile("package-lock.json"),
+        PathCategory::LockfileChurn,
+    ),
+    (Matcher::Lockfile("yarn.lock"), PathCategory::LockfileChurn),
+    (
+        Matcher::Lockfile("pnpm-lock.yaml"),
+        PathCategory::LockfileChurn,
+    ),
+    (Matcher::Lockfile("Cargo.lock"), PathCategory::LockfileChurn),
+    (
+        Matcher::Lockfile("Gemfile.lock"),
+        PathCategory::LockfileChurn,
+    ),
+    (
+        Matcher::Lockfile("composer.lock"),
+        PathCategory::LockfileChurn,
+    ),
+    (
+        Matcher::Lockfile("poetry.lock"),
+        PathCategory::LockfileChurn,
+    ),
+    (Matcher::Suffix(".min.js"), PathCategory::Generated),
+    (Matcher::Suffix(".min.css"), PathCategory::Generated),
+    (Matcher::Suffix(".js.map"), PathCategory::Generated),
+    (Matcher::Suffix(".class"), PathCategory::Generated),
+    (Matcher::Suffix(".pyc"), PathCategory::Generated),
+    (Matcher::Suffix(".png"), PathCategory::BinaryMedia),
+    (Matcher::Suffix(".jpg"), PathCategory::BinaryMedia),
+    (Matcher::Suffix(".jpeg"), PathCategory::BinaryMedia),
+    (Matcher::Suffix(".gif"), PathCategory::BinaryMedia),
+    (Matcher::Suffix(".psd"), PathCategory::BinaryMedia),
+    (Matcher::Suffix(".mp3"), PathCategory::BinaryMedia),
+    (Matcher::Suffix(".mp4"), PathCategory::BinaryMedia),
+    (Matcher::Suffix(".mov"), PathCategory::BinaryMedia),
+    (Matcher::Suffix(".zip"), PathCategory::BinaryMedia),
+    (Matcher::Suffix(".tar.gz"), PathCategory::BinaryMedia),
+    (Matcher::Suffix(".jar"), PathCategory::BinaryMedia),
+    (Matcher::Suffix(".iso"), PathCategory::BinaryMedia),
+];
+
+/// A lockfile rewritten more often than this is worth dropping from history.
+pub const LOCKFILE_CHURN_VERSIONS: usize = 100;
+
+/// A filter that would remove one recognized group of paths.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Suggestion {
+    /// The `--path` prefix or `--path-glob` pattern.
+    pub pattern: String,
+    pub category: PathCategory,
+    /// Bytes of the unique blobs found only under `pattern`.
+    pub estimated_bytes: u64,
+    /// Ready-to-copy filter-repo-rs arguments.
+    pub command: String,
+}
+
+/// The filter that would drop `path`, if any rule recognizes it.
+fn filter_for(path: &str, versions: usize) -> Option<(String, PathCategory, bool)> {
+    let (dirs, name) = match path.rfind('/') {
+        Some(slash) => (&path[..slash], &path[slash + 1..]),
+        None => ("", path),
+    };
+    // The outermost recognized directory wins, so nested node_modules fold
+    // into one suggestion.
+    let mut offset = 0;
+    for dir in dirs.split('/').filter(|_| !dirs.is_empty()) {
+        offset += dir.len() + 1;
+        let rule = RULES
+            .iter()
+            .find(|(m, _)| matches!(m, Matcher::Dir(d) if *d == dir));
+        if let Some((_, category)) = rule {
+            return Some((path[..offset].to_string(), *category, false));
+        }
+    }
+    for (matcher, category) in RULES {
+        match matcher {
+            Matcher::Lockfile(file) if *file == name && versions > LOCKFILE_CHURN_VERSIONS => {
+                return Some((path.to_string(), *category, false));
+            }
+            Matcher::Suffix(suffix) if name.len() > suffix.len() && name.ends_with(suffix) => {
+                return Some((format!("**/*{suffix}"), *category, true));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Category of a file whose path had `versions` distinct blobs.
+pub fn classify(path: &str, versions: usize) -> PathCategory {
+    filter_for(path, versions).map_or(PathCategory::Other, |(_, category, _)| category)
+}
+
+/// Suggested filters, largest estimated saving first. A blob counts toward a
+/// suggestion only when every path it appears at would be dropped by it.
+pub fn suggest(
+    blob_paths: &HashMap<String, Vec<String>>,
+    size_of: impl Fn(&str) -> u64,
+    top: usize,
+) -> Vec<Suggestion> {
+    let mut versions: HashMap<&str, usize> = HashMap::new();
+    for paths in blob_paths.values() {
+        for path in paths {
+            *versions.entry(path.as_str()).or_default() += 1;
+        }
+    }
+
+    let mut by_pattern: HashMap<String, Suggestion> = HashMap::new();
+    for (oid, paths) in blob_paths {
+        let mut filters = paths
+            .iter()
+            .map(|path| filter_for(path, versions.get(path.as_str()).copied().unwrap_or(0)));
+        let Some(Some(first)) = filters.next() else {
+            continue;
+        };
+        if !filters.all(|other| other.as_ref() == Some(&first)) {
+            continue;
+        }
+        let (pattern, category, is_glob) = first;
+        let size = size_of(oid);
+        by_pattern
+            .entry(pattern.clone())
+            .or_insert_with(|| Suggestion {
+                command: if is_glob {
+                    format!("--invert-paths --path-glob '{pattern}'")
+                } else {
+                    format!("--invert-paths --path {pattern}")
+                },
+                pattern,
+                category,
+                estimated_bytes: 0,
+            })
+            .estimated_bytes += size;
+    }
+
+    let mut suggestions: Vec<Suggestion> = by_pattern
+        .into_values()
+        .filter(|s| s.estimated_bytes > 0)
+        .collect();
+    suggestions.sort_by(|a, b| {
+        b.estimated_bytes
+            .cmp(&a.estimated_bytes)
+            .then_with(|| a.pattern.cmp(&b.pattern))
+    });
+    suggestions.truncate(top);
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifier_recognizes_each_category() {
+        assert_eq!(
+            classify("web/node_modules/react/index.js", 1),
+            PathCategory::Vendored
+        );
+        assert_eq!(classify("dist/app.js", 1), PathCategory::Generated);
+        assert_eq!(classify("static/app.min.js", 1), PathCategory::Generated);
+        assert_eq!(classify("docs/intro.mp4", 1), PathCategory::BinaryMedia);
+        assert_eq!(
+            classify("package-lock.json", LOCKFILE_CHURN_VERSIONS + 1),
+            PathCategory::LockfileChurn
+        );
+        assert_eq!(
+            classify("package-lock.json", LOCKFILE_CHURN_VERSIONS),
+            PathCategory::Other
+        );
+        assert_eq!(classify("src/build.rs", 1), PathCategory::Other);
+        assert_eq!(classify(".min.js", 1), PathCategory::Other);
+    }
+
+    #[test]
+    fn suggestions_group_by_outermost_directory_and_skip_shared_blobs() {
+        let blob_paths: HashMap<String, Vec<String>> = [
+            ("a", vec!["node_modules/x/index.js"]),
+            ("b", vec!["node_modules/x/node_modules/y.js"]),
+            // Also kept at a path the filter would not drop.
+            ("c", vec!["node_modules/z.js", "src/z.js"]),
+            ("d", vec!["assets/logo.png", "docs/logo.png"]),
+            ("e", vec!["src/main.rs"]),
+        ]
+        .into_iter()
+        .map(|(oid, paths)| {
+            (
+                oid.to_string(),
+                paths.into_iter().map(str::to_string).collect(),
+            )
+        })
+        .collect();
+        let sizes: HashMap<&str, u64> = [("a", 100), ("b", 50), ("c", 1000), ("d", 70), ("e", 5)]
+            .into_iter()
+            .collect();
+
+        let suggestions = suggest(&blob_paths, |oid| sizes[oid], 10);
+
+        assert_eq!(
+            suggestions,
+            vec![
+                Suggestion {
+                    pattern: "node_modules/".to_string(),
+                    category: PathCategory::Vendored,
+                    estimated_bytes: 150,
+                    command: "--invert-paths --path node_modules/".to_string(),
+                },
+                Suggestion {
+                    pattern: "**/*.png".to_string(),
+                    category: PathCategory::BinaryMedia,
+                    estimated_bytes: 70,
+                    command: "--invert-paths --path-glob '**/*.png'".to_string(),
+                },
+            ]
+        );
+        assert_eq!(suggest(&blob_paths, |oid| sizes[oid], 1).len(), 1);
+    }
+}
@@ -0,0 +1,200 @@
+//! Opt-in cache (`--blob-cache DIR`) of blobs a replace-text rule set is known
+//! to leave unchanged, so repeated runs with the same rules can pass them
+//! straight through.
+//!
+//! Entries live in `DIR/<rule-set hash>.unmodified`, one original blob id per
+//! line. The rule-set hash is computed with `git hash-object` over the tool
+//! version, the `--replace-text` file and the blobs that path-scoped rules and
+//! `--normalize-eol` globs resolved to in history, so editing any rule or
+//! moving a blob into scope starts a fresh cache file; files for other rule
+//! sets are removed when the cache is opened.
+
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::finalize::BlobCacheReport;
+use crate::opts::Options;
+
+const ENTRY_SUFFIX: &str = ".unmodified";
+
+pub(crate) struct BlobCache {
+    file: PathBuf,
+    known_unmodified: HashSet<Vec<u8>>,
+    new_unmodified: Vec<Vec<u8>>,
+    hits: usize,
+    misses: usize,
+}
+
+impl BlobCache {
+    /// Opens the cache for the current rule set, or returns `None` when no
+    /// cache directory or no `--replace-text` rules are configured. `scope`
+    /// is the resolved history scope of path-restricted rules.
+    pub(crate) fn open(opts: &Options, scope: &[u8]) -> io::Result<Option<Self>> {
+        let Some(dir) = opts.blob_cache_dir.as_deref() else {
+            return Ok(None);
+        };
+        if opts.blob_cache_clear {
+            clear(dir)?;
+        }
+        let Some(rules_path) = opts.replace_text_file.as_deref() else {
+            return Ok(None);
+        };
+        let mut fingerprint =
+            format!("filter-repo-rs {}\n", env!("CARGO_PKG_VERSION")).into_bytes();
+        fingerprint.extend_from_slice(&fs::read(rules_path)?);
+        // A blob the callback would rewrite is not "unmodified" under the
+        // same rules alone.
+        if let Some(library) = opts.blob_callback_so.as_deref() {
+            fingerprint.extend_from_slice(&fs::read(library)?);
+        }
+        if let Some(eol) = &opts.normalize_eol {
+            fingerprint.extend_from_slice(format!("\nnormalize-eol {eol:?}").as_bytes());
+        }
+        if !scope.is_empty() {
+            fingerprint.extend_from_slice(b"\nscope\n");
+            fingerprint.extend_from_slice(scope);
+        }
+        Self::open_for_rules(dir, &fingerprint).map(Some)
+    }
+
+    fn open_for_rules(dir: &Path, fingerprint: &[u8]) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let file = dir.join(format!("{}{}", hash_object(fingerprint)?, ENTRY_SUFFIX));
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path != file && is_entry_file(&path) {
+                fs::remove_file(&path)?;
+            }
+        }
+        let known_unmodified = match fs::read(&file) {
+            Ok(content) => content
+                .split(|&b| b == b'\n')
+                .filter(|line| !line.is_empty())
+                .map(<[u8]>::to_vec)
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            file,
+            known_unmodified,
+            new_unmodified: Vec::new(),
+            hits: 0,
+            misses: 0,
+        })
+    }
+
+    /// Returns true (and counts a hit) when `oid` is known to be unmodified.
+    pub(crate) fn is_known_unmodified(&mut self, oid: &[u8]) -> bool {
+        let hit = self.known_unmodified.contains(oid);
+        if hit {
+            self.hits += 1;
+        }
+        hit
+    }
+
+    /// Records the outcome for a blob that was not served from the cache.
+    pub(crate) fn record(&mut self, oid: &[u8], changed: bool) {
+        self.misses += 1;
+        if !changed && self.known_unmodified.insert(oid.to_vec()) {
+            self.new_unmodified.push(oid.to_vec());
+        }
+    }
+
+    /// Appends entries learned during this run to the cache file.
+    pub(crate) fn persist(&mut self) -> io::Result<()> {
+        if self.new_unmodified.is_empty() {
+            return Ok(());
+        }
+        let mut buf = Vec::with_capacity(self.new_unmodified.len() * 41);
+        for oid in self.new_unmodified.drain(..) {
+            buf.extend_from_slice(&oid);
+            buf.push(b'\n');
+        }
+        let mut f = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.file)?;
+        f.write_all(&buf)
+    }
+
+    pub(crate) fn report(&self) -> BlobCacheReport {
+        BlobCacheReport {
+            hits: self.hits,
+            misses: self.misses,
+            entries: self.known_unmodified.len(),
+        }
+    }
+}
+
+fn is_entry_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|n| n.ends_with(ENTRY_SUFFIX))
+}
+
+/// Removes all cache entry files from `dir`.
+pub(crate) fn clear(dir: &Path) -> io::Result<()> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if is_entry_file(&path) {
+            fs::remove_file(&path)?;
+        }
+    }
+    Ok(())
+}
+
+fn hash_object(data: &[u8]) -> io::Result<String> {
+    let mut child = Command::new("git")
+        .args(["hash-object", "--stdin"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .ok_or_else(|| io::Error::other("failed to open git hash-object stdin"))?
+        .write_all(data)?;
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git hash-object failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changing_rules_invalidates_previous_entries() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let oid = b"0123456789abcdef0123456789abcdef01234567";
+
+        let mut cache = BlobCache::open_for_rules(dir.path(), b"A==>B\n").unwrap();
+        assert!(!cache.is_known_unmodified(oid));
+        cache.record(oid, false);
+        cache.persist().unwrap();
+
+        let mut same = BlobCache::open_for_rules(dir.path(), b"A==>B\n").unwrap();
+        assert!(same.is_known_unmodified(oid));
+        assert_eq!(same.report().hits, 1);
+
+        let mut changed = BlobCache::open_for_rules(dir.path(), b"A==>C\n").unwrap();
+        assert!(!changed.is_known_unmodified(oid));
+        let files = fs::read_dir(dir.path()).unwrap().count();
+        assert_eq!(files, 0, "stale rule-set entries should be removed");
+    }
+}
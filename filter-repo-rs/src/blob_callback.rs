@@ -0,0 +1,125 @@
+//! `--blob-callback-so`: blob transforms loaded from a shared library.
+//!
+//! The library exports `filter_repo_blob_transform`, which receives each blob
+//! after the `--replace-text` rules have run and may hand back a replacement
+//! buffer. The contract is spelled out in `docs/CALLBACK_ABI.md`. Loading
+//! needs the `dynamic-callbacks` feature; without it the option is rejected.
+
+use std::io;
+use std::path::Path;
+
+#[cfg(feature = "dynamic-callbacks")]
+mod imp {
+    use libloading::{Library, Symbol};
+    use std::io;
+    use std::path::Path;
+
+    /// The callback returned a new buffer in `out`/`out_len`.
+    const TRANSFORMED: i32 = 0;
+    /// The callback left the blob as it was.
+    const UNCHANGED: i32 = 1;
+
+    type TransformFn = unsafe extern "C" fn(*const u8, usize, *mut *mut u8, *mut usize) -> i32;
+    type FreeFn = unsafe extern "C" fn(*mut u8, usize);
+
+    extern "C" {
+        fn free(ptr: *mut std::ffi::c_void);
+    }
+
+    pub(crate) struct BlobCallback {
+        transform: TransformFn,
+        release: Option<FreeFn>,
+        // Keeps the function pointers above valid.
+        _library: Library,
+    }
+
+    impl BlobCallback {
+        pub(crate) fn load(path: &Path) -> io::Result<Self> {
+            let err = |e: libloading::Error| {
+                io::Error::other(format!("--blob-callback-so {}: {e}", path.display()))
+            };
+            // SAFETY: loading a library runs its initializers; the user asked
+            // for this library to be run.
+            let library = unsafe { Library::new(path) }.map_err(err)?;
+            // SAFETY: the signatures are the ones CALLBACK_ABI.md requires.
+            let transform = unsafe {
+                let symbol: Symbol<TransformFn> =
+                    library.get(b"filter_repo_blob_transform\0").map_err(err)?;
+                *symbol
+            };
+            let release = unsafe {
+                library
+                    .get::<FreeFn>(b"filter_repo_blob_free\0")
+                    .ok()
+                    .map(|symbol| *symbol)
+            };
+            Ok(Self {
+                transform,
+                release,
+                _library: library,
+            })
+        }
+
+        pub(crate) fn apply(&self, data: Vec<u8>) -> io::Result<(Vec<u8>, bool)> {
+            let mut out: *mut u8 = std::ptr::null_mut();
+            let mut out_len: usize = 0;
+            // SAFETY: `data` outlives the call and the out-pointers are valid
+            // for writes; the callback owns the buffer it returns.
+            let status =
+                unsafe { (self.transform)(data.as_ptr(), data.len(), &mut out, &mut out_len) };
+            match status {
+                UNCHANGED => Ok((data, false)),
+                TRANSFORMED if out.is_null() && out_len == 0 => Ok((Vec::new(), !data.is_empty())),
+                TRANSFORMED if out.is_null() => Err(io::Error::other(
+                    "blob callback reported a transformed blob but returned no buffer",
+                )),
+                TRANSFORMED => {
+                    // SAFETY: the callback returned `out_len` bytes at `out`.
+                    let transformed = unsafe { std::slice::from_raw_parts(out, out_len) }.to_vec();
+                    // SAFETY: the buffer came from the library and is freed
+                    // exactly once, with the library's allocator if it has one.
+                    unsafe {
+                        match self.release {
+                            Some(release) => release(out, out_len),
+                            None => free(out.cast()),
+                        }
+                    }
+                    let changed = transformed != data;
+                    Ok((transformed, changed))
+                }
+                code => Err(io::Error::other(format!(
+                    "blob callback failed with status {code}"
+                ))),
+            }
+        }
+    }
+}
+
+#[cfg(feature = "dynamic-callbacks")]
+pub(crate) use imp::BlobCallback;
+
+/// Stand-in when the `dynamic-callbacks` feature is off; it cannot be built.
+#[cfg(not(feature = "dynamic-callbacks"))]
+pub(crate) enum BlobCallback {}
+
+#[cfg(not(feature = "dynamic-callbacks"))]
+impl BlobCallback {
+    pub(crate) fn load(path: &Path) -> io::Result<Self> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "--blob-callback-so {}: filter-repo-rs was built without the dynamic-callbacks feature",
+                path.display()
+            ),
+        ))
+    }
+
+    pub(crate) fn apply(&self, _data: Vec<u8>) -> io::Result<(Vec<u8>, bool)> {
+        match *self {}
+    }
+}
+
+/// Loads the `--blob-callback-so` library, if one was given.
+pub(crate) fn load(path: Option<&Path>) -> io::Result<Option<BlobCallback>> {
+    path.map(BlobCallback::load).transpose()
+}
@@ -0,0 +1,71 @@
+//! Cooperative cancellation for programs that embed [`crate::run`].
+//!
+//! The token is polled at natural boundaries: before the rewrite starts,
+//! between commits of the fast-export stream, before the stream is handed to
+//! fast-import for its final ref updates, and between analysis phases. A
+//! cancelled rewrite kills its git children and returns
+//! [`FilterRepoError::Cancelled`]; refs are only touched after the last
+//! check, so they are left as they were.
+
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use crate::error::FilterRepoError;
+
+/// Shared flag; clones observe the same cancellation.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// The shared flag, for signal handlers that may only store to it.
+    #[cfg(unix)]
+    pub(crate) fn flag(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.0)
+    }
+}
+
+/// Fails with [`FilterRepoError::Cancelled`] once `token` is cancelled.
+///
+/// The error is wrapped in an `io::Error` for the many `io::Result` paths;
+/// converting it back into a `FilterRepoError` restores `Cancelled`.
+pub(crate) fn check(token: Option<&CancellationToken>) -> io::Result<()> {
+    match token {
+        Some(token) if token.is_cancelled() => Err(io::Error::new(
+            io::ErrorKind::Interrupted,
+            FilterRepoError::Cancelled,
+        )),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancelled_check_survives_io_error_round_trip() {
+        let token = CancellationToken::new();
+        assert!(check(Some(&token)).is_ok());
+        assert!(check(None).is_ok());
+
+        token.clone().cancel();
+        let err = check(Some(&token)).expect_err("token is cancelled");
+        assert!(matches!(
+            FilterRepoError::from(err),
+            FilterRepoError::Cancelled
+        ));
+    }
+}
@@ -0,0 +1,77 @@
+//! `--clone-to PATH`: filter a fresh mirror clone and leave the source
+//! repository pristine.
+//!
+//! The clone is made with `git clone --mirror --no-local`. A plain local
+//! clone hardlinks the source's object files, so the clone starts out
+//! sharing the very packs that hold the data being removed, and a pack
+//! kept by the clone's gc stays one inode with the source's. `--no-local`
+//! copies objects through the pack protocol instead, so the clone owns its
+//! object files and a cleanup there really drops the old blobs.
+//! `--clone-local` trades that guarantee for speed when nothing sensitive
+//! is being removed.
+//!
+//! The whole pipeline then runs with the clone as both source and target.
+//! A mirror clone has no reflogs and a single pack, so it passes the
+//! freshness sanity checks without `--force`.
+
+use std::io;
+use std::path::Path;
+use std::process::Command;
+
+use crate::diagnostics::{self, Phase};
+use crate::error::Result as FilterRepoResult;
+use crate::opts::Options;
+
+pub(crate) fn run(opts: &Options, clone: &Path) -> FilterRepoResult<()> {
+    let clone = if clone.is_absolute() {
+        clone.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(clone)
+    };
+    if clone.exists() {
+        return Err(io::Error::other(format!(
+            "{} already exists; --clone-to needs a path that does not exist yet",
+            clone.display()
+        ))
+        .into());
+    }
+
+    let mut cmd = Command::new("git");
+    cmd.args(["clone", "--mirror", "-q"]);
+    if !opts.clone_local {
+        cmd.arg("--no-local");
+    }
+    cmd.arg(&opts.source).arg(&clone);
+    let status = cmd
+        .status()
+        .map_err(|e| io::Error::other(format!("failed to run git clone: {e}")))?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "git clone --mirror {} {} failed: {status}",
+            opts.source.display(),
+            clone.display()
+        ))
+        .into());
+    }
+    diagnostics::info(
+        Phase::Fetch,
+        format!(
+            "Cloned {} to {}{}",
+            opts.source.display(),
+            clone.display(),
+            if opts.clone_local {
+                " (objects hardlinked)"
+            } else {
+                ""
+            }
+        ),
+    );
+
+    crate::rewrite(&Options {
+        source: clone.clone(),
+        target: clone,
+        clone_to: None,
+        clone_local: false,
+        ..opts.clone()
+    })
+}
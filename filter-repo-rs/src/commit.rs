@@ -1,15 +1,19 @@
-use std::collections::{BTreeSet, HashMap};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fs::File;
 use std::io::{self, BufRead, BufReader, Read, Write};
 use std::path::Path;
-use std::process::ChildStdout;
 
 use aho_corasick::AhoCorasick;
 
+use crate::diagnostics::{self, Phase};
 use crate::filechange;
 use crate::limits::parse_data_size_header;
-use crate::message::{msg_regex, MessageReplacer, ShortHashMapper};
-use crate::opts::Options;
+use crate::message::{
+    msg_regex, MessageReplacer, ShortHashMapper, SubjectNormalizer, TagNameReferences,
+};
+use crate::opts::{Options, OverrideDate};
+use crate::pipes::ExportReader;
 
 pub fn rename_commit_header_ref(
     line: &[u8],
@@ -68,9 +72,117 @@ pub fn rename_commit_header_ref(
     line.to_vec()
 }
 
+struct RootFile {
+    path: Vec<u8>,
+    mark: u32,
+    data: Vec<u8>,
+}
+
+/// Adds the `--add-root-file` blobs to every root commit that lacks them.
+pub struct RootFileInjector {
+    files: Vec<RootFile>,
+    // paths written by the commit currently being buffered
+    present: HashSet<Vec<u8>>,
+    blobs_emitted: bool,
+    roots_modified: usize,
+}
+
+impl RootFileInjector {
+    /// Reads the local files named by `--add-root-file`; `None` when unused.
+    pub fn from_options(opts: &Options) -> io::Result<Option<Self>> {
+        if opts.root_files.is_empty() {
+            return Ok(None);
+        }
+        let mut files = Vec::with_capacity(opts.root_files.len());
+        for (idx, (path, local)) in opts.root_files.iter().enumerate() {
+            let data = std::fs::read(local).map_err(|e| {
+                io::Error::new(
+                    e.kind(),
+                    format!("failed to read --add-root-file {}: {e}", local.display()),
+                )
+            })?;
+            files.push(RootFile {
+                path: path.clone(),
+                // Count down from the top of the mark space so the blobs never
+                // collide with marks allocated by fast-export.
+                mark: u32::MAX - idx as u32,
+                data,
+            });
+        }
+        Ok(Some(Self {
+            files,
+            present: HashSet::new(),
+            blobs_emitted: false,
+            roots_modified: 0,
+        }))
+    }
+
+    fn note_file_change(&mut self, line: &[u8]) {
+        if let Some(path) = filechange::written_path(line) {
+            self.present.insert(path);
+        }
+    }
+
+    /// Called at the end of each commit; appends the missing files when the
+    /// commit is a root and returns whether `commit_buf` changed.
+    fn finish_commit(&mut self, commit_buf: &mut Vec<u8>, is_root: bool) -> bool {
+        let present = std::mem::take(&mut self.present);
+        if !is_root {
+            return false;
+        }
+        let mut added = false;
+        for file in &self.files {
+            if present.contains(&file.path) {
+                continue;
+            }
+            commit_buf.extend_from_slice(format!("M 100644 :{} ", file.mark).as_bytes());
+            commit_buf.extend_from_slice(&crate::pathutil::encode_path_for_fi(&file.path));
+            commit_buf.push(b'\n');
+            added = true;
+        }
+        if added {
+            self.roots_modified += 1;
+        }
+        added
+    }
+
+    /// Blob commands defining the injected files, returned only the first time.
+    fn take_blob_commands(&mut self) -> Option<Vec<u8>> {
+        if self.blobs_emitted {
+            return None;
+        }
+        self.blobs_emitted = true;
+        let mut out = Vec::new();
+        for file in &self.files {
+            out.extend_from_slice(
+                format!("blob\nmark :{}\ndata {}\n", file.mark, file.data.len()).as_bytes(),
+            );
+            out.extend_from_slice(&file.data);
+            out.push(b'\n');
+        }
+        Some(out)
+    }
+
+    pub fn roots_modified(&self) -> usize {
+        self.roots_modified
+    }
+}
+
 pub enum CommitAction {
     Consumed,
-    Ended,
+    Ended(CommitOutcome),
+}
+
+/// What happened to a commit once its block ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommitOutcome {
+    /// Emitted to fast-import, rather than pruned.
+    pub kept: bool,
+    /// A merge left with fewer than two parents after pruning.
+    pub degenerate_merge: bool,
+    /// Some surviving change adds, modifies or deletes content; false when
+    /// the commit only renames or copies paths (or has no changes).
+    pub content_changed: bool,
 }
 
 pub struct ParentLine {
@@ -104,6 +216,7 @@ pub fn start_commit(
     ref_renames: &mut BTreeSet<(Vec<u8>, Vec<u8>)>,
     commit_buf: &mut Vec<u8>,
     commit_has_changes: &mut bool,
+    commit_has_content_changes: &mut bool,
     commit_mark: &mut Option<u32>,
     first_parent_mark: &mut Option<u32>,
     parent_lines: &mut Vec<ParentLine>,
@@ -112,6 +225,7 @@ pub fn start_commit(
         return false;
     }
     *commit_has_changes = false;
+    *commit_has_content_changes = false;
     *commit_mark = None;
     *first_parent_mark = None;
     parent_lines.clear();
@@ -125,15 +239,18 @@ pub fn start_commit(
 pub fn process_commit_line(
     line: &[u8],
     opts: &Options,
-    fe_out: &mut BufReader<ChildStdout>,
+    fe_out: &mut ExportReader,
     orig_file: Option<&mut dyn Write>,
     filt_file: &mut dyn Write,
     mut fi_in: Option<&mut dyn Write>,
     replacer: &Option<MessageReplacer>,
     msg_regex: Option<&msg_regex::RegexReplacer>,
     short_mapper: Option<&ShortHashMapper>,
+    subject_normalizer: Option<&SubjectNormalizer>,
+    tag_name_refs: Option<&TagNameReferences>,
     commit_buf: &mut Vec<u8>,
     commit_has_changes: &mut bool,
+    commit_has_content_changes: &mut bool,
     commit_mark: &mut Option<u32>,
     first_parent_mark: &mut Option<u32>,
     commit_original_oid: &mut Option<Vec<u8>>,
@@ -142,8 +259,13 @@ pub fn process_commit_line(
     import_broken: &mut bool,
     parent_lines: &mut Vec<ParentLine>,
     alias_map: &mut HashMap<u32, u32>,
+    pruned_commits: &mut HashMap<u32, Vec<u8>>,
     emitted_marks: &std::collections::HashSet<u32>,
     path_compat_events: &mut Vec<crate::pathutil::PathCompatEvent>,
+    sanitized_paths: &mut crate::pathutil::SanitizedPathTable,
+    mode_rule_hits: &mut [usize],
+    mut root_files: Option<&mut RootFileInjector>,
+    diff_tree: Option<&mut filechange::DiffTreeBatch>,
 ) -> io::Result<CommitAction> {
     // mark line
     if let Some(m) = parse_mark_number(line) {
@@ -173,10 +295,14 @@ pub fn process_commit_line(
             replacer,
             msg_regex,
             short_mapper,
+            subject_normalizer,
+            tag_name_refs,
         )?;
         return Ok(CommitAction::Consumed);
     }
     // parents
+    let grafted = graft_rewritten_parent(line, opts, short_mapper)?;
+    let line = grafted.as_deref().unwrap_or(line);
     if line.starts_with(b"from ") {
         if first_parent_mark.is_none() {
             if let Some(m) = parse_from_mark(line) {
@@ -215,9 +341,22 @@ pub fn process_commit_line(
         || line.starts_with(b"R ")
         || line == b"deleteall\n"
     {
-        let outcome = filechange::handle_file_change_line(line, opts).map_err(io::Error::other)?;
+        let outcome = filechange::handle_file_change_line_with_paths(line, opts, sanitized_paths)
+            .map_err(io::Error::other)?;
         path_compat_events.extend(outcome.path_compat_events);
+        if let Some(hits) = outcome
+            .mode_rule_hit
+            .and_then(|idx| mode_rule_hits.get_mut(idx))
+        {
+            *hits += 1;
+        }
         if let Some(newline) = outcome.line {
+            if let Some(injector) = root_files.as_deref_mut() {
+                injector.note_file_change(&newline);
+            }
+            if changes_content(&newline) {
+                *commit_has_content_changes = true;
+            }
             commit_buf.extend_from_slice(&newline);
             *commit_has_changes = true;
         }
@@ -234,9 +373,18 @@ pub fn process_commit_line(
             alias_map,
         );
         *parent_count = kept_parents;
+        if let Some(injector) = root_files.as_deref_mut() {
+            if injector.finish_commit(commit_buf, kept_parents == 0) {
+                *commit_has_changes = true;
+                *commit_has_content_changes = true;
+            }
+        }
+        if let (Some(diff_tree), Some(oid)) = (diff_tree, commit_original_oid.as_deref()) {
+            filechange::synthesize_renames(commit_buf, oid, opts, diff_tree)?;
+        }
         let was_merge = original_parents >= 2;
         let is_degenerate = was_merge && kept_parents < 2;
-        if should_keep_commit(
+        let kept = should_keep_commit(
             *commit_has_changes,
             *first_parent_mark,
             *commit_mark,
@@ -244,9 +392,15 @@ pub fn process_commit_line(
             was_merge,
             is_degenerate,
             opts,
-        ) {
+        );
+        if kept {
             // keep commit
             commit_buf.extend_from_slice(b"\n");
+            if let Some(blobs) = root_files.and_then(RootFileInjector::take_blob_commands) {
+                // The injected blobs must be defined before the first commit
+                // that references them.
+                commit_buf.splice(0..0, blobs);
+            }
             filt_file.write_all(commit_buf)?;
             if let Some(ref mut fi) = fi_in {
                 if let Err(e) = fi.write_all(commit_buf) {
@@ -264,6 +418,10 @@ pub fn process_commit_line(
                 }
             }
         } else {
+            if let Some(m) = *commit_mark {
+                let original = commit_original_oid.clone().unwrap_or_default();
+                pruned_commits.insert(m, original);
+            }
             if let Some(old) = commit_original_oid.take() {
                 commit_pairs.push((old, None));
             }
@@ -287,13 +445,78 @@ pub fn process_commit_line(
             }
             // If no alias possible, just skip the commit entirely (mark becomes invalid)
         }
-        return Ok(CommitAction::Ended);
+        return Ok(CommitAction::Ended(CommitOutcome {
+            kept,
+            degenerate_merge: is_degenerate,
+            content_changed: *commit_has_content_changes,
+        }));
+    }
+    if line.starts_with(b"committer ")
+        && (opts.author_date_is_committer_date || opts.committer_date_is_author_date)
+    {
+        let committer = sync_commit_dates(commit_buf, line, opts);
+        commit_buf.extend_from_slice(&committer);
+        return Ok(CommitAction::Consumed);
+    }
+    // Queries would answer on fast-import's stdout; see TopLevelCommand::Strip.
+    if crate::stream::is_query_command(line) {
+        return Ok(CommitAction::Consumed);
     }
     // other commit lines: buffer as-is
     commit_buf.extend_from_slice(line);
     Ok(CommitAction::Consumed)
 }
 
+/// Byte range of the timestamp in an `author`/`committer`/`tagger` line
+/// (`Name <email> 1700000000 +0000`); the timezone follows it.
+fn extract_identity_timestamp(line: &[u8]) -> Option<(usize, usize)> {
+    let close = line.iter().rposition(|&b| b == b'>')?;
+    if line.get(close + 1) != Some(&b' ') {
+        return None;
+    }
+    let start = close + 2;
+    let len = line[start..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    (len > 0).then_some((start, start + len))
+}
+
+/// Timestamp and timezone of an identity line, without the line ending.
+fn identity_date_range(line: &[u8]) -> Option<(usize, usize)> {
+    let (start, _) = extract_identity_timestamp(line)?;
+    let body = line.strip_suffix(b"\n").unwrap_or(line);
+    Some((start, body.len()))
+}
+
+/// Copies the date between `committer` and the `author` line already in
+/// `commit_buf`, in the direction the options ask for. Returns the committer
+/// line to buffer.
+fn sync_commit_dates(commit_buf: &mut Vec<u8>, committer: &[u8], opts: &Options) -> Vec<u8> {
+    let mut offset = 0;
+    let author = commit_buf.split_inclusive(|&b| b == b'\n').find_map(|l| {
+        let start = offset;
+        offset += l.len();
+        l.starts_with(b"author ").then_some((start, l))
+    });
+    let Some((author_start, author)) = author else {
+        return committer.to_vec();
+    };
+    let (Some(a), Some(c)) = (identity_date_range(author), identity_date_range(committer)) else {
+        return committer.to_vec();
+    };
+    if opts.committer_date_is_author_date {
+        let mut out = committer[..c.0].to_vec();
+        out.extend_from_slice(&author[a.0..a.1]);
+        out.extend_from_slice(&committer[c.1..]);
+        out
+    } else {
+        let date = committer[c.0..c.1].to_vec();
+        commit_buf.splice(author_start + a.0..author_start + a.1, date);
+        committer.to_vec()
+    }
+}
+
 fn parse_mark_value(line: &[u8], prefix_len: usize) -> Option<u32> {
     let mut num: u32 = 0;
     let mut seen = false;
@@ -319,6 +542,34 @@ pub fn parse_mark_number(line: &[u8]) -> Option<u32> {
     parse_mark_value(line, b"mark :".len())
 }
 
+/// `--watch-interval` cycles export only new commits, so a parent an earlier
+/// cycle already filtered arrives as its original id. It is replaced with the
+/// id the commit-map gives it, or its nearest kept ancestor's if it was
+/// pruned. Returns `None` when the line stays as it is.
+fn graft_rewritten_parent(
+    line: &[u8],
+    opts: &Options,
+    short_mapper: Option<&ShortHashMapper>,
+) -> io::Result<Option<Vec<u8>>> {
+    let (Some(mapper), Some(_)) = (short_mapper, opts.watch_interval) else {
+        return Ok(None);
+    };
+    let keyword: &[u8] = if line.starts_with(b"from ") {
+        b"from "
+    } else if line.starts_with(b"merge ") {
+        b"merge "
+    } else {
+        return Ok(None);
+    };
+    let oid = line[keyword.len()..].trim_ascii_end();
+    if !mapper.commit_map().contains_key(oid) {
+        return Ok(None);
+    }
+    let oid = String::from_utf8_lossy(oid);
+    let surviving = crate::finalize::surviving_commit(&opts.source, &oid, mapper.commit_map())?;
+    Ok(surviving.map(|new_oid| [keyword, new_oid.as_bytes(), b"\n"].concat()))
+}
+
 pub fn parse_from_mark(line: &[u8]) -> Option<u32> {
     if !line.starts_with(b"from ") {
         return None;
@@ -341,14 +592,17 @@ fn parse_merge_mark(line: &[u8]) -> Option<u32> {
 
 // Handle a commit message 'data <n>' header line: read payload from fe_out,
 // mirror to orig_file, apply replacer, and append to commit_buf.
+#[allow(clippy::too_many_arguments)]
 pub fn handle_commit_data(
     header_line: &[u8],
-    fe_out: &mut BufReader<ChildStdout>,
+    fe_out: &mut ExportReader,
     orig_file: Option<&mut dyn Write>,
     commit_buf: &mut Vec<u8>,
     replacer: &Option<MessageReplacer>,
     msg_regex: Option<&msg_regex::RegexReplacer>,
     short_mapper: Option<&ShortHashMapper>,
+    subject_normalizer: Option<&SubjectNormalizer>,
+    tag_name_refs: Option<&TagNameReferences>,
 ) -> io::Result<()> {
     if !header_line.starts_with(b"data ") {
         return Ok(());
@@ -367,15 +621,27 @@ pub fn handle_commit_data(
     if let Some(rr) = msg_regex {
         new_payload = rr.apply_regex(new_payload);
     }
+    if let Some(refs) = tag_name_refs {
+        new_payload = refs.apply(new_payload);
+    }
     if let Some(mapper) = short_mapper {
         new_payload = mapper.rewrite(new_payload);
     }
+    if let Some(normalizer) = subject_normalizer {
+        new_payload = normalizer.apply(new_payload);
+    }
     let header = format!("data {}\n", new_payload.len());
     commit_buf.extend_from_slice(header.as_bytes());
     commit_buf.extend_from_slice(&new_payload);
     Ok(())
 }
 
+/// Renames and copies only move existing content around; every other
+/// file change (`M`, `D`, `deleteall`) changes content.
+pub fn changes_content(filechange: &[u8]) -> bool {
+    !filechange.starts_with(b"R ") && !filechange.starts_with(b"C ")
+}
+
 // Should the commit be kept based on observed properties
 pub fn should_keep_commit(
     commit_has_changes: bool,
@@ -391,7 +657,9 @@ pub fn should_keep_commit(
         return true;
     }
 
-    // If there were any file changes, keep regardless of prune settings
+    // If there were any file changes, keep regardless of prune settings.
+    // That includes commits that only rename or copy paths: pruning one
+    // would leave its descendants with the old paths.
     if commit_has_changes {
         return true;
     }
@@ -422,6 +690,70 @@ pub fn should_keep_commit(
     }
 }
 
+/// Structured commit used to synthesize new commits in the fast-import stream.
+///
+/// `author` and `committer` hold the value following the keyword, i.e.
+/// `Name <email> <timestamp> <tz>`. An empty `author` omits the author line.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommitSpec {
+    pub ref_name: Vec<u8>,
+    pub mark: Option<u32>,
+    pub author: Vec<u8>,
+    pub committer: Vec<u8>,
+    pub message: Vec<u8>,
+    pub parents: Vec<u32>,
+    pub file_changes: Vec<Vec<u8>>,
+}
+
+/// Serialize a [`CommitSpec`] into a fast-import `commit` command, including
+/// the terminating blank line.
+pub fn encode_commit_for_fi(spec: &CommitSpec) -> Vec<u8> {
+    let mut out = Vec::with_capacity(
+        64 + spec.ref_name.len()
+            + spec.author.len()
+            + spec.committer.len()
+            + spec.message.len()
+            + spec.file_changes.iter().map(|c| c.len() + 1).sum::<usize>(),
+    );
+    out.extend_from_slice(b"commit ");
+    out.extend_from_slice(&spec.ref_name);
+    out.push(b'\n');
+    if let Some(m) = spec.mark {
+        out.extend_from_slice(format!("mark :{}\n", m).as_bytes());
+    }
+    if !spec.author.is_empty() {
+        out.extend_from_slice(b"author ");
+        out.extend_from_slice(&spec.author);
+        out.push(b'\n');
+    }
+    out.extend_from_slice(b"committer ");
+    out.extend_from_slice(&spec.committer);
+    out.push(b'\n');
+    out.extend_from_slice(format!("data {}\n", spec.message.len()).as_bytes());
+    out.extend_from_slice(&spec.message);
+    // fast-import accepts an optional LF after the payload; add it so the next
+    // command always starts on its own line.
+    if !spec.message.ends_with(b"\n") {
+        out.push(b'\n');
+    }
+    for (idx, parent) in spec.parents.iter().enumerate() {
+        let kind = if idx == 0 {
+            ParentKind::From
+        } else {
+            ParentKind::Merge
+        };
+        out.extend_from_slice(&rebuild_parent_line(kind, *parent));
+    }
+    for change in &spec.file_changes {
+        out.extend_from_slice(change);
+        if !change.ends_with(b"\n") {
+            out.push(b'\n');
+        }
+    }
+    out.push(b'\n');
+    out
+}
+
 // Build an alias stanza to map an old mark to its first parent mark
 pub fn build_alias(old_mark: u32, first_parent_mark: u32) -> Vec<u8> {
     format!("alias\nmark :{}\nto :{}\n\n", old_mark, first_parent_mark).into_bytes()
@@ -482,11 +814,7 @@ fn finalize_parent_lines(
 
     let mut new_buf = Vec::with_capacity(commit_buf.len());
     let mut cursor = 0usize;
-    for (idx, (parent, replacement)) in parent_lines
-        .iter()
-        .zip(replacements.into_iter())
-        .enumerate()
-    {
+    for (idx, (parent, replacement)) in parent_lines.iter().zip(replacements).enumerate() {
         if cursor < parent.start {
             new_buf.extend_from_slice(&commit_buf[cursor..parent.start]);
         }
@@ -530,9 +858,19 @@ fn rebuild_parent_line(kind: ParentKind, mark: u32) -> Vec<u8> {
     }
 }
 
-fn resolve_canonical_mark(mark: u32, alias_map: &HashMap<u32, u32>) -> u32 {
+/// Longest alias chain followed before giving up on a malformed alias map.
+const MAX_ALIAS_DEPTH: usize = 1000;
+
+pub(crate) fn resolve_canonical_mark(mark: u32, alias_map: &HashMap<u32, u32>) -> u32 {
+    resolve_alias_chain(mark, alias_map, MAX_ALIAS_DEPTH).0
+}
+
+/// Follows `alias_map` from `mark`, returning the canonical mark and the
+/// number of hops taken. Stops early on a cycle or after `max_depth` hops.
+fn resolve_alias_chain(mark: u32, alias_map: &HashMap<u32, u32>, max_depth: usize) -> (u32, usize) {
     let mut current = mark;
-    let mut seen = std::collections::HashSet::new();
+    let mut hops = 0;
+    let mut seen = HashSet::new();
     while let Some(&next) = alias_map.get(&current) {
         if !seen.insert(current) {
             break;
@@ -540,15 +878,131 @@ fn resolve_canonical_mark(mark: u32, alias_map: &HashMap<u32, u32>) -> u32 {
         if next == current {
             break;
         }
+        if hops == max_depth {
+            if cfg!(debug_assertions) {
+                diagnostics::warning(
+                    Phase::Stream,
+                    format!("alias chain from :{mark} exceeds {max_depth} hops; using :{current}"),
+                );
+            }
+            break;
+        }
         current = next;
+        hops += 1;
+    }
+    (current, hops)
+}
+
+/// Which identity header a rewritten line came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdentityKind {
+    Author,
+    Committer,
+    Tagger,
+}
+
+impl IdentityKind {
+    pub fn from_line(line: &[u8]) -> Option<Self> {
+        if line.starts_with(b"author ") {
+            Some(Self::Author)
+        } else if line.starts_with(b"committer ") {
+            Some(Self::Committer)
+        } else if line.starts_with(b"tagger ") {
+            Some(Self::Tagger)
+        } else {
+            None
+        }
+    }
+
+    fn header_len(self) -> usize {
+        match self {
+            Self::Author => b"author ".len(),
+            Self::Committer => b"committer ".len(),
+            Self::Tagger => b"tagger ".len(),
+        }
+    }
+}
+
+/// Per-rule match counters, split by identity header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct IdentityRuleHits {
+    pub author: usize,
+    pub committer: usize,
+    pub tagger: usize,
+}
+
+impl IdentityRuleHits {
+    fn record(&mut self, kind: IdentityKind) {
+        match kind {
+            IdentityKind::Author => self.author += 1,
+            IdentityKind::Committer => self.committer += 1,
+            IdentityKind::Tagger => self.tagger += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.author + self.committer + self.tagger
+    }
+}
+
+/// Snapshot of a single identity rewrite rule and how often it matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdentityRuleStat {
+    pub old: String,
+    pub new: String,
+    pub hits: IdentityRuleHits,
+}
+
+// Counters are updated behind shared references while the stream is processed,
+// so they live in RefCells rather than requiring `&mut self` on the rewriters.
+#[derive(Clone, Debug, Default)]
+struct IdentityRewriteCounters {
+    hits: RefCell<Vec<IdentityRuleHits>>,
+    identities: RefCell<HashSet<Vec<u8>>>,
+}
+
+impl IdentityRewriteCounters {
+    fn new(rules: usize) -> Self {
+        Self {
+            hits: RefCell::new(vec![IdentityRuleHits::default(); rules]),
+            identities: RefCell::new(HashSet::new()),
+        }
+    }
+
+    fn record_hit(&self, rule: usize, kind: IdentityKind) {
+        if let Some(h) = self.hits.borrow_mut().get_mut(rule) {
+            h.record(kind);
+        }
+    }
+
+    fn record_identity(&self, line: &[u8]) {
+        if let Some(identity) = identity_of_line(line) {
+            self.identities.borrow_mut().insert(identity.to_vec());
+        }
+    }
+
+    fn hits(&self, rule: usize) -> IdentityRuleHits {
+        self.hits.borrow().get(rule).copied().unwrap_or_default()
+    }
+
+    fn identities(&self) -> HashSet<Vec<u8>> {
+        self.identities.borrow().clone()
     }
-    current
+}
+
+// Extract the `Name <email>` portion of an author/committer/tagger line.
+fn identity_of_line(line: &[u8]) -> Option<&[u8]> {
+    let kind = IdentityKind::from_line(line)?;
+    let rest = &line[kind.header_len()..];
+    let close = rest.iter().position(|&b| b == b'>')?;
+    Some(&rest[..=close])
 }
 
 pub struct AuthorRewriter {
     patterns: Vec<String>,
     replacements: Vec<String>,
     ac: AhoCorasick,
+    counters: IdentityRewriteCounters,
 }
 
 impl AuthorRewriter {
@@ -584,16 +1038,19 @@ impl AuthorRewriter {
                 patterns: vec![String::new()],
                 replacements: vec![String::new()],
                 ac: AhoCorasick::new([""]).expect("empty pattern is always valid"),
+                counters: IdentityRewriteCounters::new(0),
             });
         }
 
         let ac = AhoCorasick::new(&patterns)
             .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let counters = IdentityRewriteCounters::new(patterns.len());
 
         Ok(Self {
             patterns,
             replacements,
             ac,
+            counters,
         })
     }
 
@@ -609,9 +1066,49 @@ impl AuthorRewriter {
         result.into_bytes()
     }
 
+    /// Like [`AuthorRewriter::rewrite`], but records which rules matched for `kind`.
+    pub fn rewrite_counted(&self, text: &[u8], kind: IdentityKind) -> Vec<u8> {
+        if self.is_empty() {
+            return text.to_vec();
+        }
+        let text_str = match std::str::from_utf8(text) {
+            Ok(s) => s,
+            Err(_) => return text.to_vec(),
+        };
+        let mut result = String::with_capacity(text_str.len());
+        self.ac
+            .replace_all_with(text_str, &mut result, |mat, _, dst| {
+                self.counters.record_hit(mat.pattern().as_usize(), kind);
+                dst.push_str(&self.replacements[mat.pattern().as_usize()]);
+                true
+            });
+        result.into_bytes()
+    }
+
     pub fn is_empty(&self) -> bool {
         self.patterns.is_empty() || (self.patterns.len() == 1 && self.patterns[0].is_empty())
     }
+
+    pub fn rule_stats(&self) -> Vec<IdentityRuleStat> {
+        if self.is_empty() {
+            return Vec::new();
+        }
+        self.patterns
+            .iter()
+            .zip(self.replacements.iter())
+            .enumerate()
+            .map(|(i, (old, new))| IdentityRuleStat {
+                old: old.clone(),
+                new: new.clone(),
+                hits: self.counters.hits(i),
+            })
+            .collect()
+    }
+
+    /// Distinct original identities (`Name <email>`) this rewriter changed.
+    pub fn rewritten_identities(&self) -> HashSet<Vec<u8>> {
+        self.counters.identities()
+    }
 }
 
 impl Clone for AuthorRewriter {
@@ -621,6 +1118,7 @@ impl Clone for AuthorRewriter {
             replacements: self.replacements.clone(),
             ac: AhoCorasick::new(&self.patterns)
                 .expect("AuthorRewriter patterns already validated during construction"),
+            counters: self.counters.clone(),
         }
     }
 }
@@ -632,6 +1130,7 @@ pub struct MailmapRewriter {
     old_emails: Vec<String>,
     new_names: Vec<String>,
     new_emails: Vec<String>,
+    counters: IdentityRewriteCounters,
 }
 
 impl MailmapRewriter {
@@ -683,11 +1182,13 @@ impl MailmapRewriter {
             }
         }
 
+        let counters = IdentityRewriteCounters::new(old_emails.len());
         Ok(Self {
             parser,
             old_emails,
             new_names,
             new_emails,
+            counters,
         })
     }
 
@@ -697,13 +1198,11 @@ impl MailmapRewriter {
             Err(_) => return line.to_vec(),
         };
 
-        let header_len = if line.starts_with(b"author ") {
-            b"author ".len()
-        } else if line.starts_with(b"committer ") {
-            b"committer ".len()
-        } else {
-            return line.to_vec();
+        let kind = match IdentityKind::from_line(line) {
+            Some(kind) => kind,
+            None => return line.to_vec(),
         };
+        let header_len = kind.header_len();
         let identity = &line_str[header_len..];
 
         if let Some(email_start_rel) = identity.find('<') {
@@ -742,6 +1241,10 @@ impl MailmapRewriter {
 
                         result.push_str(suffix);
 
+                        self.counters.record_hit(i, kind);
+                        if result.as_bytes() != line {
+                            self.counters.record_identity(line);
+                        }
                         return result.into_bytes();
                     }
                 }
@@ -754,6 +1257,29 @@ impl MailmapRewriter {
     pub fn is_empty(&self) -> bool {
         self.old_emails.is_empty()
     }
+
+    pub fn rule_stats(&self) -> Vec<IdentityRuleStat> {
+        (0..self.old_emails.len())
+            .map(|i| {
+                let new = match (self.new_names[i].is_empty(), self.new_emails[i].is_empty()) {
+                    (true, true) => String::new(),
+                    (true, false) => format!("<{}>", self.new_emails[i]),
+                    (false, true) => self.new_names[i].clone(),
+                    (false, false) => format!("{} <{}>", self.new_names[i], self.new_emails[i]),
+                };
+                IdentityRuleStat {
+                    old: format!("<{}>", self.old_emails[i]),
+                    new,
+                    hits: self.counters.hits(i),
+                }
+            })
+            .collect()
+    }
+
+    /// Distinct original identities (`Name <email>`) this rewriter changed.
+    pub fn rewritten_identities(&self) -> HashSet<Vec<u8>> {
+        self.counters.identities()
+    }
 }
 
 impl Clone for MailmapRewriter {
@@ -764,6 +1290,7 @@ impl Clone for MailmapRewriter {
             old_emails: self.old_emails.clone(),
             new_names: self.new_names.clone(),
             new_emails: self.new_emails.clone(),
+            counters: self.counters.clone(),
         }
     }
 }
@@ -773,7 +1300,16 @@ pub fn rewrite_author_line(line: &[u8], rewriter: Option<&AuthorRewriter>) -> Ve
         if rw.is_empty() {
             return line.to_vec();
         }
-        rw.rewrite(line)
+        match IdentityKind::from_line(line) {
+            Some(kind) => {
+                let rewritten = rw.rewrite_counted(line, kind);
+                if rewritten != line {
+                    rw.counters.record_identity(line);
+                }
+                rewritten
+            }
+            None => rw.rewrite(line),
+        }
     } else {
         line.to_vec()
     }
@@ -796,7 +1332,13 @@ pub fn rewrite_email_line(line: &[u8], rewriter: Option<&AuthorRewriter>) -> Vec
                 let email = &line[start + 1..start + end];
                 let after = &line[start + end + 1..];
 
-                let rewritten_email = rw.rewrite(email);
+                let rewritten_email = match IdentityKind::from_line(line) {
+                    Some(kind) => rw.rewrite_counted(email, kind),
+                    None => rw.rewrite(email),
+                };
+                if rewritten_email != email {
+                    rw.counters.record_identity(line);
+                }
                 let mut result =
                     Vec::with_capacity(before.len() + rewritten_email.len() + after.len() + 3);
                 result.extend_from_slice(before);
@@ -822,12 +1364,158 @@ pub fn rewrite_mailmap_line(line: &[u8], rewriter: Option<&MailmapRewriter>) ->
     }
 }
 
+/// Rewrites the `tagger` line of an annotated tag.
+///
+/// The mailmap wins when given, as for commits; otherwise email rules run
+/// first and author rules then apply to the tagger, who is the author of the
+/// tag. `--override-tagger` runs last. Any other line is returned unchanged.
+pub fn rewrite_tagger_line(
+    line: &[u8],
+    author_rewriter: Option<&AuthorRewriter>,
+    email_rewriter: Option<&AuthorRewriter>,
+    mailmap_rewriter: Option<&MailmapRewriter>,
+    tagger_override: Option<&IdentityOverride>,
+) -> Vec<u8> {
+    if IdentityKind::from_line(line) != Some(IdentityKind::Tagger) {
+        return line.to_vec();
+    }
+    let rewritten = if mailmap_rewriter.is_some() {
+        rewrite_mailmap_line(line, mailmap_rewriter)
+    } else {
+        let rewritten = rewrite_email_line(line, email_rewriter);
+        rewrite_author_line(&rewritten, author_rewriter)
+    };
+    match tagger_override {
+        Some(o) => o.apply(&rewritten),
+        None => rewritten,
+    }
+}
+
+/// `--override-committer` / `--override-tagger`: puts one fixed identity on
+/// every `committer` or `tagger` line so rewritten history can be told apart
+/// from the original. Runs after the mailmap and the rewrite rules, so it
+/// always wins.
+#[derive(Debug)]
+pub struct IdentityOverride {
+    kind: IdentityKind,
+    /// `Name <email>`, already validated by the option parser.
+    identity: Vec<u8>,
+    /// `<timestamp> +0000` for `now`; `None` keeps each line's own date.
+    date: Option<Vec<u8>>,
+    /// Set by [`apply`](Self::apply) until [`settle`](Self::settle) learns
+    /// whether the object was emitted.
+    pending: Cell<bool>,
+    stamped: Cell<usize>,
+}
+
+impl IdentityOverride {
+    /// `now` is the run's start time in seconds since the epoch, so every
+    /// stamped line of one run carries the same date.
+    pub fn new(kind: IdentityKind, identity: &[u8], date: OverrideDate, now: i64) -> Self {
+        Self {
+            kind,
+            identity: identity.to_vec(),
+            date: match date {
+                OverrideDate::Keep => None,
+                OverrideDate::Now => Some(format!("{now} +0000").into_bytes()),
+            },
+            pending: Cell::new(false),
+            stamped: Cell::new(0),
+        }
+    }
+
+    /// Rewrites `line` if it is the header this override stamps; any other
+    /// line, or one without a `<email>`, is returned unchanged.
+    pub fn apply(&self, line: &[u8]) -> Vec<u8> {
+        if IdentityKind::from_line(line) != Some(self.kind) {
+            return line.to_vec();
+        }
+        let header = &line[..self.kind.header_len()];
+        let Some(close) = line.iter().rposition(|&b| b == b'>') else {
+            return line.to_vec();
+        };
+        let mut out = Vec::with_capacity(line.len() + self.identity.len());
+        out.extend_from_slice(header);
+        out.extend_from_slice(&self.identity);
+        match (&self.date, identity_date_range(line)) {
+            (Some(date), Some(_)) => {
+                out.push(b' ');
+                out.extend_from_slice(date);
+                if line.ends_with(b"\n") {
+                    out.push(b'\n');
+                }
+            }
+            _ => out.extend_from_slice(&line[close + 1..]),
+        }
+        self.pending.set(true);
+        out
+    }
+
+    /// Called once the commit or tag whose line was just passed to
+    /// [`apply`](Self::apply) is emitted (`true`) or pruned (`false`).
+    pub fn settle(&self, emitted: bool) {
+        if self.pending.replace(false) && emitted {
+            self.stamped.set(self.stamped.get() + 1);
+        }
+    }
+
+    pub fn identity(&self) -> &[u8] {
+        &self.identity
+    }
+
+    /// Number of emitted commits or tags stamped so far.
+    pub fn stamped(&self) -> usize {
+        self.stamped.get()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::collections::{HashMap, HashSet};
     use std::io::Cursor;
 
+    #[test]
+    fn renames_and_copies_do_not_change_content() {
+        assert!(!changes_content(b"R a.txt b.txt\n"));
+        assert!(!changes_content(b"C a.txt b.txt\n"));
+        assert!(changes_content(b"M 100644 :1 a.txt\n"));
+        assert!(changes_content(b"D a.txt\n"));
+        assert!(changes_content(b"deleteall\n"));
+
+        let opts = Options {
+            prune_empty: crate::opts::PruneMode::Always,
+            ..Options::default()
+        };
+        assert!(
+            should_keep_commit(true, Some(1), Some(2), 1, false, false, &opts),
+            "a rename-only commit is a change and is kept"
+        );
+    }
+
+    #[test]
+    fn alias_cycles_stop_before_depth_limit() {
+        // 1 -> 2 -> 3 -> 4 -> 5 -> 3: the cycle re-enters at the third mark.
+        let alias_map: HashMap<u32, u32> = [(1, 2), (2, 3), (3, 4), (4, 5), (5, 3)].into();
+        let (mark, hops) = resolve_alias_chain(1, &alias_map, MAX_ALIAS_DEPTH);
+        assert_eq!(hops, 5, "HashSet cycle detection should fire first");
+        assert_eq!(mark, 3);
+        assert_eq!(resolve_canonical_mark(1, &alias_map), 3);
+
+        let chain: HashMap<u32, u32> = (1..=5).map(|m| (m, m + 1)).collect();
+        assert_eq!(resolve_alias_chain(1, &chain, MAX_ALIAS_DEPTH), (6, 5));
+        assert_eq!(resolve_alias_chain(1, &chain, 3), (4, 3));
+    }
+
+    #[test]
+    fn alias_chains_longer_than_depth_limit_are_cut_short() {
+        let len = MAX_ALIAS_DEPTH as u32 * 2;
+        let chain: HashMap<u32, u32> = (1..=len).map(|m| (m, m + 1)).collect();
+        let (mark, hops) = resolve_alias_chain(1, &chain, MAX_ALIAS_DEPTH);
+        assert_eq!(hops, MAX_ALIAS_DEPTH);
+        assert_eq!(mark, MAX_ALIAS_DEPTH as u32 + 1);
+    }
+
     #[test]
     fn finalize_promotes_first_remaining_merge_to_from() {
         let mut commit_buf = b"from :1\nmerge :2\n".to_vec();
@@ -854,6 +1542,76 @@ mod tests {
         assert_eq!(first_parent_mark, Some(2));
     }
 
+    #[test]
+    fn finalize_keeps_later_merges_after_promoting_the_first_kept_one() {
+        let lines: [&[u8]; 3] = [b"from :1\n", b"merge :2\n", b"merge :3\n"];
+        let mut commit_buf = lines.concat();
+        let mut parent_lines = Vec::new();
+        let mut start = 0;
+        for (line, (mark, kind)) in lines.iter().zip([
+            (1, ParentKind::From),
+            (2, ParentKind::Merge),
+            (3, ParentKind::Merge),
+        ]) {
+            parent_lines.push(ParentLine::new(start, start + line.len(), Some(mark), kind));
+            start += line.len();
+        }
+        let mut first_parent_mark = Some(1);
+        let emitted_marks: HashSet<u32> = [2u32, 3].into_iter().collect();
+        let alias_map: HashMap<u32, u32> = HashMap::new();
+
+        let kept = finalize_parent_lines(
+            &mut commit_buf,
+            &mut parent_lines,
+            &mut first_parent_mark,
+            &emitted_marks,
+            &alias_map,
+        );
+
+        assert_eq!(kept, 2);
+        assert_eq!(commit_buf, b"from :2\nmerge :3\n");
+        assert_eq!(first_parent_mark, Some(2));
+    }
+
+    #[test]
+    fn commit_dates_are_copied_in_the_requested_direction() {
+        let author = b"author A U Thor <a@example.com> 1111111111 +0200\n";
+        let committer = b"committer C O Mitter <c@example.com> 2222222222 -0500\n";
+        assert_eq!(
+            extract_identity_timestamp(author),
+            Some((32, 42)),
+            "{:?}",
+            String::from_utf8_lossy(&author[32..42])
+        );
+        assert_eq!(extract_identity_timestamp(b"author Nobody <n@x>\n"), None);
+
+        let header = b"commit refs/heads/main\nmark :1\n".to_vec();
+        let mut buf = [&header[..], author].concat();
+        let opts = Options {
+            committer_date_is_author_date: true,
+            ..Options::default()
+        };
+        assert_eq!(
+            sync_commit_dates(&mut buf, committer, &opts),
+            b"committer C O Mitter <c@example.com> 1111111111 +0200\n"
+        );
+        assert_eq!(buf, [&header[..], author].concat());
+
+        let opts = Options {
+            author_date_is_committer_date: true,
+            ..Options::default()
+        };
+        assert_eq!(sync_commit_dates(&mut buf, committer, &opts), committer);
+        assert_eq!(
+            buf,
+            [
+                &header[..],
+                b"author A U Thor <a@example.com> 2222222222 -0500\n"
+            ]
+            .concat()
+        );
+    }
+
     #[test]
     fn finalize_promotes_raw_merge_to_from() {
         let mut commit_buf = b"merge deadbeefdeadbeefdeadbeefdeadbeefdeadbeef\n".to_vec();
@@ -893,6 +1651,138 @@ mod tests {
         );
     }
 
+    fn read_encoded_lines(encoded: &[u8]) -> (Vec<Vec<u8>>, Vec<u8>) {
+        let mut reader = BufReader::new(Cursor::new(encoded.to_vec()));
+        let mut lines = Vec::new();
+        let mut message = Vec::new();
+        loop {
+            let mut line = Vec::new();
+            if reader.read_until(b'\n', &mut line).unwrap() == 0 {
+                break;
+            }
+            if line.starts_with(b"data ") {
+                let n = parse_data_size_header(&line).unwrap();
+                message = vec![0u8; n];
+                reader.read_exact(&mut message).unwrap();
+            }
+            lines.push(line);
+        }
+        (lines, message)
+    }
+
+    #[test]
+    fn encode_commit_for_fi_single_parent_round_trips() {
+        let spec = CommitSpec {
+            ref_name: b"refs/heads/main".to_vec(),
+            mark: Some(7),
+            author: b"A U Thor <author@example.com> 1700000000 +0000".to_vec(),
+            committer: b"C O Mitter <committer@example.com> 1700000001 +0000".to_vec(),
+            message: b"subject\n\nbody without trailing newline".to_vec(),
+            parents: vec![3],
+            file_changes: vec![b"M 100644 :5 src/lib.rs".to_vec(), b"D old.txt\n".to_vec()],
+        };
+        let encoded = encode_commit_for_fi(&spec);
+        let (lines, message) = read_encoded_lines(&encoded);
+
+        assert_eq!(lines[0], b"commit refs/heads/main\n");
+        assert_eq!(parse_mark_number(&lines[1]), Some(7));
+        assert!(lines[2].starts_with(b"author A U Thor"));
+        assert!(lines[3].starts_with(b"committer C O Mitter"));
+        assert_eq!(message, spec.message);
+        // payload is followed by the optional LF, then parents and filechanges
+        assert_eq!(lines[5], b"\n");
+        assert_eq!(parse_from_mark(&lines[6]), Some(3));
+        assert_eq!(lines[7], b"M 100644 :5 src/lib.rs\n");
+        assert_eq!(lines[8], b"D old.txt\n");
+        assert_eq!(lines[9], b"\n");
+        assert_eq!(lines.len(), 10);
+    }
+
+    #[test]
+    fn encode_commit_for_fi_two_parents_emits_merge_line() {
+        let spec = CommitSpec {
+            ref_name: b"refs/heads/topic".to_vec(),
+            mark: Some(12),
+            author: Vec::new(),
+            committer: b"C <c@example.com> 1700000000 +0000".to_vec(),
+            message: b"Merge branch 'x'\n".to_vec(),
+            parents: vec![10, 11],
+            file_changes: Vec::new(),
+        };
+        let encoded = encode_commit_for_fi(&spec);
+        let (lines, message) = read_encoded_lines(&encoded);
+
+        assert_eq!(lines[0], b"commit refs/heads/topic\n");
+        assert_eq!(parse_mark_number(&lines[1]), Some(12));
+        assert!(lines[2].starts_with(b"committer "), "author line omitted");
+        assert_eq!(message, b"Merge branch 'x'\n");
+        assert_eq!(parse_from_mark(&lines[4]), Some(10));
+        assert_eq!(parse_merge_mark(&lines[5]), Some(11));
+        assert_eq!(lines[6], b"\n");
+        assert_eq!(lines.len(), 7);
+    }
+
+    #[test]
+    fn author_rewriter_counts_hits_per_identity_kind() {
+        let rw = AuthorRewriter::from_reader(Cursor::new(
+            "old@example.com==>new@example.com\nnobody@example.com==>x@example.com\n",
+        ))
+        .unwrap();
+        let author = b"author A <old@example.com> 1700000000 +0000\n";
+        let committer = b"committer C <old@example.com> 1700000000 +0000\n";
+        assert_eq!(
+            rewrite_author_line(author, Some(&rw)),
+            b"author A <new@example.com> 1700000000 +0000\n"
+        );
+        rewrite_email_line(committer, Some(&rw));
+
+        let stats = rw.rule_stats();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].hits.author, 1);
+        assert_eq!(stats[0].hits.committer, 1);
+        assert_eq!(stats[1].hits.total(), 0);
+        assert_eq!(rw.rewritten_identities().len(), 2);
+
+        let copy = rw.clone();
+        assert_eq!(copy.rule_stats(), stats, "a clone keeps the hits so far");
+        assert_eq!(copy.rewritten_identities(), rw.rewritten_identities());
+    }
+
+    #[test]
+    fn identity_override_stamps_only_its_own_header() {
+        let bot = b"Rewrite Bot <bot@corp>";
+        let keep = IdentityOverride::new(IdentityKind::Committer, bot, OverrideDate::Keep, 42);
+        let author = b"author A <a@example.com> 1700000000 +0100\n";
+        assert_eq!(keep.apply(author), author);
+        assert_eq!(
+            keep.apply(b"committer C <c@example.com> 1700000001 -0500\n"),
+            b"committer Rewrite Bot <bot@corp> 1700000001 -0500\n"
+        );
+        assert_eq!(
+            keep.stamped(),
+            0,
+            "nothing counts until the commit is emitted"
+        );
+        keep.settle(true);
+        assert_eq!(keep.stamped(), 1);
+        keep.apply(b"committer C <c@example.com> 1700000002 -0500\n");
+        keep.settle(false);
+        keep.settle(true);
+        assert_eq!(keep.stamped(), 1, "a pruned commit is not counted");
+
+        let now = IdentityOverride::new(IdentityKind::Tagger, bot, OverrideDate::Now, 42);
+        assert_eq!(
+            now.apply(b"tagger T <t@example.com> 1700000001 -0500\n"),
+            b"tagger Rewrite Bot <bot@corp> 42 +0000\n"
+        );
+        assert_eq!(
+            now.apply(b"committer C <c@example.com> 1700000001 -0500\n"),
+            b"committer C <c@example.com> 1700000001 -0500\n"
+        );
+        now.settle(true);
+        assert_eq!(now.stamped(), 1);
+    }
+
     #[test]
     fn mailmap_rewrite_preserves_name_when_rule_has_only_new_email() {
         let rw = MailmapRewriter::from_reader(Cursor::new("<new@example.com> <old@example.com>\n"))
@@ -903,5 +1793,48 @@ mod tests {
             rewritten,
             b"author Old Name <new@example.com> 1700000000 +0800\n"
         );
+        assert_eq!(rw.clone().rule_stats(), rw.rule_stats());
+        assert_eq!(rw.clone().rule_stats()[0].hits.author, 1);
+    }
+
+    #[test]
+    fn root_file_injector_skips_paths_the_root_already_has() {
+        let mut injector = RootFileInjector {
+            files: vec![
+                RootFile {
+                    path: b"NOTICE".to_vec(),
+                    mark: u32::MAX,
+                    data: b"notice\n".to_vec(),
+                },
+                RootFile {
+                    path: b"docs/LICENSE".to_vec(),
+                    mark: u32::MAX - 1,
+                    data: b"license\n".to_vec(),
+                },
+            ],
+            present: HashSet::new(),
+            blobs_emitted: false,
+            roots_modified: 0,
+        };
+        let mut commit_buf = b"M 100644 :1 NOTICE\n".to_vec();
+        injector.note_file_change(b"M 100644 :1 NOTICE\n");
+        assert!(injector.finish_commit(&mut commit_buf, true));
+        assert_eq!(
+            commit_buf,
+            format!(
+                "M 100644 :1 NOTICE\nM 100644 :{} docs/LICENSE\n",
+                u32::MAX - 1
+            )
+            .as_bytes()
+        );
+
+        let mut child_buf = Vec::new();
+        assert!(!injector.finish_commit(&mut child_buf, false));
+        assert!(child_buf.is_empty());
+        assert_eq!(injector.roots_modified(), 1);
+
+        let blobs = injector.take_blob_commands().unwrap();
+        assert!(blobs.starts_with(format!("blob\nmark :{}\ndata 7\nnotice\n", u32::MAX).as_bytes()));
+        assert!(injector.take_blob_commands().is_none());
     }
 }
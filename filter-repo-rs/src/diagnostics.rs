@@ -0,0 +1,198 @@
+//! Notices, warnings and errors written to stderr.
+//!
+//! Every diagnostic goes through [`Record::emit`], which renders it in the
+//! format chosen with `--log-format`:
+//!
+//! ```text
+//! text: WARNING: identity rewrite rule(s) matched nothing
+//! json: {"level":"warning","phase":"finalize","message":"...","context":{}}
+//! ```
+//!
+//! Tests can collect records instead of printing them with [`capture`].
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use serde::Serialize;
+
+use crate::opts::LogFormat;
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    static CAPTURED: RefCell<Option<Vec<Record>>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    /// Plain status output, printed without a prefix in text mode.
+    Info,
+    Notice,
+    Warning,
+    Error,
+}
+
+/// The part of a run a record was emitted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    Options,
+    Sanity,
+    Fetch,
+    Stream,
+    Finalize,
+    Migrate,
+    Analyze,
+    Watch,
+    /// Errors that end the run, reported by the binary.
+    Exit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Record {
+    pub level: Level,
+    pub phase: Phase,
+    pub message: String,
+    pub context: BTreeMap<&'static str, String>,
+}
+
+impl Record {
+    pub fn new(level: Level, phase: Phase, message: impl Into<String>) -> Self {
+        Self {
+            level,
+            phase,
+            message: message.into(),
+            context: BTreeMap::new(),
+        }
+    }
+
+    /// Adds a machine-readable field; text output shows only the message.
+    pub fn with(mut self, key: &'static str, value: impl ToString) -> Self {
+        self.context.insert(key, value.to_string());
+        self
+    }
+
+    pub fn emit(self) {
+        let uncaptured = CAPTURED.with(|c| match c.borrow_mut().as_mut() {
+            Some(records) => {
+                records.push(self);
+                None
+            }
+            None => Some(self),
+        });
+        let Some(record) = uncaptured else {
+            return;
+        };
+        let line = if json_enabled() {
+            record.to_json()
+        } else {
+            record.to_text()
+        };
+        let _ = writeln!(io::stderr().lock(), "{line}");
+    }
+
+    fn to_text(&self) -> String {
+        match self.level {
+            Level::Info | Level::Error => self.message.clone(),
+            Level::Notice => format!("NOTICE: {}", self.message),
+            Level::Warning => format!("WARNING: {}", self.message),
+        }
+    }
+
+    fn to_json(&self) -> String {
+        let plain = Record {
+            message: strip_ansi(&self.message),
+            ..self.clone()
+        };
+        serde_json::to_string(&plain).unwrap_or_else(|_| self.message.clone())
+    }
+}
+
+pub fn set_format(format: LogFormat) {
+    JSON.store(format == LogFormat::Json, Ordering::Relaxed);
+}
+
+pub fn json_enabled() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+pub fn info(phase: Phase, message: impl Into<String>) {
+    Record::new(Level::Info, phase, message).emit();
+}
+
+pub fn notice(phase: Phase, message: impl Into<String>) {
+    Record::new(Level::Notice, phase, message).emit();
+}
+
+pub fn warning(phase: Phase, message: impl Into<String>) {
+    Record::new(Level::Warning, phase, message).emit();
+}
+
+pub fn error(phase: Phase, message: impl Into<String>) {
+    Record::new(Level::Error, phase, message).emit();
+}
+
+/// Runs `f` with this thread's records collected in memory instead of
+/// written to stderr.
+pub fn capture<R>(f: impl FnOnce() -> R) -> (R, Vec<Record>) {
+    let outer = CAPTURED.with(|c| c.borrow_mut().replace(Vec::new()));
+    let result = f();
+    let records = CAPTURED.with(|c| std::mem::replace(&mut *c.borrow_mut(), outer));
+    (result, records.unwrap_or_default())
+}
+
+/// Drops terminal color codes, which some messages carry for text output.
+fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capture_collects_records_in_order() {
+        let ((), records) = capture(|| {
+            notice(Phase::Migrate, "removing origin");
+            Record::new(Level::Warning, Phase::Finalize, "update failed")
+                .with("ref", "refs/heads/main")
+                .emit();
+        });
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].level, Level::Notice);
+        assert_eq!(records[0].to_text(), "NOTICE: removing origin");
+        assert_eq!(records[1].context["ref"], "refs/heads/main");
+        assert_eq!(records[1].to_text(), "WARNING: update failed");
+    }
+
+    #[test]
+    fn json_lines_carry_phase_and_plain_message() {
+        let record = Record::new(
+            Level::Warning,
+            Phase::Finalize,
+            "\u{1b}[1;36mgit gc\u{1b}[0m failed",
+        )
+        .with("status", 1);
+        let value: serde_json::Value = serde_json::from_str(&record.to_json()).unwrap();
+        assert_eq!(value["level"], "warning");
+        assert_eq!(value["phase"], "finalize");
+        assert_eq!(value["message"], "git gc failed");
+        assert_eq!(value["context"]["status"], "1");
+    }
+}
@@ -0,0 +1,242 @@
+//! `--normalize-eol`: convert line endings in text blobs, after
+//! `--replace-text` has run.
+//!
+//! Binary blobs (per the same heuristic secret detection uses) pass through
+//! untouched. Blobs containing a lone CR (classic Mac line endings) are left
+//! alone as well and counted separately, since converting them would need a
+//! guess about what the CR meant. Converting an already converted blob is a
+//! no-op, so a second run changes nothing.
+
+use std::collections::HashSet;
+use std::io;
+use std::path::Path;
+
+use crate::finalize::EolNormalizationReport;
+use crate::opts::{EolNormalization, EolTarget};
+
+enum EolOutcome {
+    /// Binary, or every line already ends the target way.
+    Unchanged,
+    Converted(Vec<u8>),
+    LoneCr,
+}
+
+fn has_lone_cr(data: &[u8]) -> bool {
+    data.iter()
+        .enumerate()
+        .any(|(i, &b)| b == b'\r' && data.get(i + 1) != Some(&b'\n'))
+}
+
+fn convert(data: &[u8], target: EolTarget) -> EolOutcome {
+    if crate::detect::looks_binary_blob(data) {
+        return EolOutcome::Unchanged;
+    }
+    if has_lone_cr(data) {
+        return EolOutcome::LoneCr;
+    }
+    let out = match target {
+        EolTarget::Lf => {
+            if !data.contains(&b'\r') {
+                return EolOutcome::Unchanged;
+            }
+            // Every CR is followed by LF here, so dropping them all is exact.
+            data.iter().copied().filter(|&b| b != b'\r').collect()
+        }
+        EolTarget::Crlf => {
+            let bare_lf = data
+                .iter()
+                .enumerate()
+                .filter(|&(i, &b)| b == b'\n' && (i == 0 || data[i - 1] != b'\r'))
+                .count();
+            if bare_lf == 0 {
+                return EolOutcome::Unchanged;
+            }
+            let mut out = Vec::with_capacity(data.len() + bare_lf);
+            for (i, &b) in data.iter().enumerate() {
+                if b == b'\n' && (i == 0 || data[i - 1] != b'\r') {
+                    out.push(b'\r');
+                }
+                out.push(b);
+            }
+            out
+        }
+    };
+    EolOutcome::Converted(out)
+}
+
+pub(crate) struct EolNormalizer {
+    target: EolTarget,
+    globs: Vec<Vec<u8>>,
+    // Original ids of blobs ever stored at a path matching `globs`; `None`
+    // when every path is in scope.
+    scoped_blobs: Option<HashSet<Vec<u8>>>,
+    blobs_converted: usize,
+    bytes_changed: usize,
+    skipped_lone_cr: usize,
+}
+
+impl EolNormalizer {
+    pub(crate) fn load(repo: &Path, config: &EolNormalization) -> io::Result<Self> {
+        let scoped_blobs = if config.globs.is_empty() {
+            None
+        } else {
+            // Narrow the history walk with git pathspecs, then confirm each
+            // path with the same matcher inline blobs use.
+            let pathspecs: Vec<Vec<u8>> = config
+                .globs
+                .iter()
+                .map(|glob| {
+                    if glob.contains(&b'/') {
+                        [b":(glob)".as_slice(), glob].concat()
+                    } else {
+                        [b":(glob)**/".as_slice(), glob].concat()
+                    }
+                })
+                .collect();
+            let paths_by_blob = crate::stream::blob_paths_in_history(repo, &pathspecs)?;
+            Some(
+                paths_by_blob
+                    .into_iter()
+                    .filter(|(_, paths)| paths.iter().any(|path| matches_any(&config.globs, path)))
+                    .map(|(oid, _)| oid)
+                    .collect(),
+            )
+        };
+        Ok(Self {
+            target: config.target,
+            globs: config.globs.clone(),
+            scoped_blobs,
+            blobs_converted: 0,
+            bytes_changed: 0,
+            skipped_lone_cr: 0,
+        })
+    }
+
+    /// Normalizes a blob from the stream, identified by its original id.
+    pub(crate) fn apply_to_blob(
+        &mut self,
+        orig_sha: Option<&[u8]>,
+        data: Vec<u8>,
+    ) -> (Vec<u8>, bool) {
+        if let Some(scoped) = &self.scoped_blobs {
+            if !orig_sha.is_some_and(|sha| scoped.contains(sha)) {
+                return (data, false);
+            }
+        }
+        self.apply(data)
+    }
+
+    /// Normalizes an inline blob stored at `path`.
+    pub(crate) fn apply_for_path(&mut self, path: &[u8], data: Vec<u8>) -> (Vec<u8>, bool) {
+        if !self.globs.is_empty() && !matches_any(&self.globs, path) {
+            return (data, false);
+        }
+        self.apply(data)
+    }
+
+    fn apply(&mut self, data: Vec<u8>) -> (Vec<u8>, bool) {
+        match convert(&data, self.target) {
+            EolOutcome::Unchanged => (data, false),
+            EolOutcome::LoneCr => {
+                self.skipped_lone_cr += 1;
+                (data, false)
+            }
+            EolOutcome::Converted(out) => {
+                self.blobs_converted += 1;
+                self.bytes_changed += out.len().abs_diff(data.len());
+                (out, true)
+            }
+        }
+    }
+
+    /// Appends the ids of the blobs the globs resolved to, in a stable order.
+    pub(crate) fn scope_fingerprint(&self, out: &mut Vec<u8>) {
+        let Some(scoped) = &self.scoped_blobs else {
+            return;
+        };
+        let mut oids: Vec<&Vec<u8>> = scoped.iter().collect();
+        oids.sort();
+        out.extend_from_slice(b"normalize-eol scope\n");
+        for oid in oids {
+            out.extend_from_slice(oid);
+            out.push(b'\n');
+        }
+    }
+
+    pub(crate) fn report(&self) -> EolNormalizationReport {
+        let (bytes_removed, bytes_added) = match self.target {
+            EolTarget::Lf => (self.bytes_changed, 0),
+            EolTarget::Crlf => (0, self.bytes_changed),
+        };
+        EolNormalizationReport {
+            target: self.target.as_str().to_string(),
+            globs: self
+                .globs
+                .iter()
+                .map(|g| String::from_utf8_lossy(g).into_owned())
+                .collect(),
+            blobs_converted: self.blobs_converted,
+            bytes_removed,
+            bytes_added,
+            skipped_suspicious_eol: self.skipped_lone_cr,
+        }
+    }
+}
+
+fn matches_any(globs: &[Vec<u8>], path: &[u8]) -> bool {
+    globs
+        .iter()
+        .any(|glob| crate::filechange::attribute_pattern_matches(glob, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn converted(data: &[u8], target: EolTarget) -> Option<Vec<u8>> {
+        match convert(data, target) {
+            EolOutcome::Converted(out) => Some(out),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn lf_target_converts_crlf_and_mixed_endings() {
+        assert_eq!(
+            converted(b"a\r\nb\r\n", EolTarget::Lf).as_deref(),
+            Some(&b"a\nb\n"[..])
+        );
+        assert_eq!(
+            converted(b"a\r\nb\nc\r\n", EolTarget::Lf).as_deref(),
+            Some(&b"a\nb\nc\n"[..])
+        );
+        assert!(matches!(
+            convert(b"a\nb\n", EolTarget::Lf),
+            EolOutcome::Unchanged
+        ));
+    }
+
+    #[test]
+    fn crlf_target_only_touches_bare_lf() {
+        assert_eq!(
+            converted(b"\na\r\nb\n", EolTarget::Crlf).as_deref(),
+            Some(&b"\r\na\r\nb\r\n"[..])
+        );
+        assert!(matches!(
+            convert(b"a\r\nb\r\n", EolTarget::Crlf),
+            EolOutcome::Unchanged
+        ));
+    }
+
+    #[test]
+    fn lone_cr_and_binary_blobs_are_left_alone() {
+        assert!(matches!(
+            convert(b"a\rb\r\n", EolTarget::Lf),
+            EolOutcome::LoneCr
+        ));
+        assert!(matches!(
+            convert(b"a\r\n\0b", EolTarget::Lf),
+            EolOutcome::Unchanged
+        ));
+    }
+}
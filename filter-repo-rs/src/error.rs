@@ -21,6 +21,11 @@ pub enum FilterRepoError {
     Detect { message: String, source: io::Error },
     /// Early, explicit process exit request (e.g. --help/--version).
     Exit(i32),
+    /// The caller's [`crate::CancellationToken`] was cancelled.
+    Cancelled,
+    /// `--post-check` found problems in the rewritten repository; `output`
+    /// is what the check printed.
+    PostCheck { check: &'static str, output: String },
 }
 
 impl fmt::Display for FilterRepoError {
@@ -31,6 +36,14 @@ impl fmt::Display for FilterRepoError {
             FilterRepoError::InvalidOptions(msg) => f.write_str(msg),
             FilterRepoError::Detect { message, .. } => f.write_str(message),
             FilterRepoError::Exit(_) => Ok(()),
+            FilterRepoError::Cancelled => f.write_str("operation cancelled"),
+            FilterRepoError::PostCheck { check, output } => {
+                write!(f, "post-check {check} failed on the rewritten repository")?;
+                if !output.is_empty() {
+                    write!(f, ":\n{output}")?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -43,13 +56,23 @@ impl StdError for FilterRepoError {
             FilterRepoError::InvalidOptions(_) => None,
             FilterRepoError::Detect { source, .. } => Some(source),
             FilterRepoError::Exit(_) => None,
+            FilterRepoError::Cancelled => None,
+            FilterRepoError::PostCheck { .. } => None,
         }
     }
 }
 
 impl From<io::Error> for FilterRepoError {
     fn from(err: io::Error) -> Self {
-        FilterRepoError::Io(err)
+        let cancelled = err
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<FilterRepoError>())
+            .is_some_and(|inner| matches!(inner, FilterRepoError::Cancelled));
+        if cancelled {
+            FilterRepoError::Cancelled
+        } else {
+            FilterRepoError::Io(err)
+        }
     }
 }
 
@@ -1,6 +1,12 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+use crate::message::{expand_bytes_template, replace_all_bytes};
 use crate::opts::Options;
 use crate::pathutil::{
     dequote_c_style_bytes, encode_path_for_fi_with_policy, glob_match_bytes, PathCompatEvent,
+    SanitizedPathTable,
 };
 
 #[derive(Debug)]
@@ -92,6 +98,15 @@ fn parse_file_change_line(line: &[u8]) -> Option<FileChange> {
     }
 }
 
+/// Returns the path a filechange line writes to (`M`, `C` and `R` targets).
+pub(crate) fn written_path(line: &[u8]) -> Option<Vec<u8>> {
+    match parse_file_change_line(line)? {
+        FileChange::Modify { path, .. } => Some(path),
+        FileChange::Copy { dst, .. } | FileChange::Rename { dst, .. } => Some(dst),
+        FileChange::DeleteAll | FileChange::Delete { .. } => None,
+    }
+}
+
 fn parse_path(input: &[u8]) -> Option<(Vec<u8>, &[u8])> {
     if input.is_empty() {
         return None;
@@ -151,6 +166,13 @@ fn path_matches(path: &[u8], opts: &Options) -> bool {
 }
 
 fn should_keep(paths: &[&[u8]], opts: &Options) -> bool {
+    if !opts.strip_path_globs.is_empty()
+        && paths
+            .iter()
+            .any(|p| opts.strip_path_globs.iter().any(|g| glob_match_bytes(g, p)))
+    {
+        return false;
+    }
     if opts.paths.is_empty() && opts.path_globs.is_empty() && opts.path_regexes.is_empty() {
         return true;
     }
@@ -158,16 +180,39 @@ fn should_keep(paths: &[&[u8]], opts: &Options) -> bool {
     opts.invert_paths ^ matched
 }
 
-fn rewrite_path(mut path: Vec<u8>, opts: &Options) -> Vec<u8> {
-    if !opts.path_renames.is_empty() {
-        for (old, new_) in &opts.path_renames {
-            if path.starts_with(old) {
-                let mut tmp = new_.clone();
-                tmp.extend_from_slice(&path[old.len()..]);
-                path = tmp;
-            }
+fn rewrite_path(path: Vec<u8>, opts: &Options) -> Vec<u8> {
+    rewrite_path_with_hits(path, opts, |_| {})
+}
+
+/// [`rewrite_path`], calling `hit` with the index of every rename rule that
+/// matched: prefix renames first, then `--path-rename-regex` rules after them.
+pub(crate) fn rewrite_path_with_hits(
+    mut path: Vec<u8>,
+    opts: &Options,
+    mut hit: impl FnMut(usize),
+) -> Vec<u8> {
+    for (i, (old, new_)) in opts.path_renames.iter().enumerate() {
+        if path.starts_with(old) {
+            hit(i);
+            let mut tmp = new_.clone();
+            tmp.extend_from_slice(&path[old.len()..]);
+            path = tmp;
         }
     }
+    for (i, (re, replacement)) in opts.path_rename_regexes.iter().enumerate() {
+        let Some(caps) = re.captures(&path) else {
+            continue;
+        };
+        hit(opts.path_renames.len() + i);
+        let whole = caps.get(0).expect("group 0 always participates");
+        let mut tmp = path[..whole.start()].to_vec();
+        tmp.extend_from_slice(&expand_bytes_template(replacement, &caps));
+        tmp.extend_from_slice(&path[whole.end()..]);
+        path = tmp;
+    }
+    for (secret, redaction) in &opts.path_redactions {
+        path = replace_all_bytes(&path, secret, redaction);
+    }
     // Path renames are applied. Further sanitization and encoding is handled by `encode_path_for_fi`.
     path
 }
@@ -176,14 +221,54 @@ fn rewrite_path(mut path: Vec<u8>, opts: &Options) -> Vec<u8> {
 pub struct HandleFileChangeOutcome {
     pub line: Option<Vec<u8>>,
     pub path_compat_events: Vec<PathCompatEvent>,
+    /// Index into `opts.mode_normalizations` of the rule that changed the mode.
+    pub mode_rule_hit: Option<usize>,
+}
+
+const MODE_SYMLINK: &[u8] = b"120000";
+const MODE_GITLINK: &[u8] = b"160000";
+
+// Like .gitattributes: a pattern without '/' matches the file name anywhere.
+pub(crate) fn attribute_pattern_matches(pattern: &[u8], path: &[u8]) -> bool {
+    if pattern.contains(&b'/') {
+        return glob_match_bytes(pattern, path);
+    }
+    let name = match path.iter().rposition(|&b| b == b'/') {
+        Some(idx) => &path[idx + 1..],
+        None => path,
+    };
+    glob_match_bytes(pattern, name)
+}
+
+// Return the replacement mode and rule index when a --normalize-mode rule
+// changes `mode` for `path`. Symlinks and gitlinks are never touched.
+fn normalized_mode<'a>(mode: &[u8], path: &[u8], opts: &'a Options) -> Option<(&'a [u8], usize)> {
+    if mode == MODE_SYMLINK || mode == MODE_GITLINK {
+        return None;
+    }
+    let (idx, (_, new_mode)) = opts
+        .mode_normalizations
+        .iter()
+        .enumerate()
+        .find(|(_, (pattern, _))| attribute_pattern_matches(pattern, path))?;
+    if new_mode.as_slice() == mode {
+        return None;
+    }
+    Some((new_mode.as_slice(), idx))
 }
 
 fn encode_path_with_policy(
     path: &[u8],
     opts: &Options,
+    sanitized_paths: &mut SanitizedPathTable,
     path_compat_events: &mut Vec<PathCompatEvent>,
 ) -> Result<Option<Vec<u8>>, String> {
-    let (encoded, event) = encode_path_for_fi_with_policy(path, opts.path_compat_policy)?;
+    let (encoded, event) = encode_path_for_fi_with_policy(
+        path,
+        opts.path_compat_policy,
+        opts.unsafe_path_policy,
+        sanitized_paths,
+    )?;
     if let Some(e) = event {
         path_compat_events.push(e);
     }
@@ -194,6 +279,16 @@ fn encode_path_with_policy(
 pub fn handle_file_change_line(
     line: &[u8],
     opts: &Options,
+) -> Result<HandleFileChangeOutcome, String> {
+    handle_file_change_line_with_paths(line, opts, &mut SanitizedPathTable::default())
+}
+
+/// Like `handle_file_change_line`, resolving sanitized-path collisions
+/// against the paths already written in this run.
+pub fn handle_file_change_line_with_paths(
+    line: &[u8],
+    opts: &Options,
+    sanitized_paths: &mut SanitizedPathTable,
 ) -> Result<HandleFileChangeOutcome, String> {
     let parsed = match parse_file_change_line(line) {
         Some(p) => p,
@@ -201,6 +296,7 @@ pub fn handle_file_change_line(
             return Ok(HandleFileChangeOutcome {
                 line: Some(line.to_vec()),
                 path_compat_events: Vec::new(),
+                mode_rule_hit: None,
             });
         }
     };
@@ -217,6 +313,7 @@ pub fn handle_file_change_line(
         return Ok(HandleFileChangeOutcome {
             line: None,
             path_compat_events: Vec::new(),
+            mode_rule_hit: None,
         });
     }
 
@@ -225,21 +322,29 @@ pub fn handle_file_change_line(
         FileChange::DeleteAll => Ok(HandleFileChangeOutcome {
             line: Some(line.to_vec()),
             path_compat_events,
+            mode_rule_hit: None,
         }),
         FileChange::Modify { mode, id, path } => {
             let new_path = rewrite_path(path, opts);
-            let enc = match encode_path_with_policy(&new_path, opts, &mut path_compat_events)? {
+            let enc = match encode_path_with_policy(
+                &new_path,
+                opts,
+                sanitized_paths,
+                &mut path_compat_events,
+            )? {
                 Some(enc) => enc,
                 None => {
                     return Ok(HandleFileChangeOutcome {
                         line: None,
                         path_compat_events,
+                        mode_rule_hit: None,
                     });
                 }
             };
+            let normalized = normalized_mode(&mode, &new_path, opts);
             let mut rebuilt = Vec::with_capacity(line.len() + new_path.len());
             rebuilt.extend_from_slice(b"M ");
-            rebuilt.extend_from_slice(&mode);
+            rebuilt.extend_from_slice(normalized.map_or(mode.as_slice(), |(m, _)| m));
             rebuilt.push(b' ');
             rebuilt.extend_from_slice(&id);
             rebuilt.push(b' ');
@@ -248,16 +353,23 @@ pub fn handle_file_change_line(
             Ok(HandleFileChangeOutcome {
                 line: Some(rebuilt),
                 path_compat_events,
+                mode_rule_hit: normalized.map(|(_, idx)| idx),
             })
         }
         FileChange::Delete { path } => {
             let new_path = rewrite_path(path, opts);
-            let enc = match encode_path_with_policy(&new_path, opts, &mut path_compat_events)? {
+            let enc = match encode_path_with_policy(
+                &new_path,
+                opts,
+                sanitized_paths,
+                &mut path_compat_events,
+            )? {
                 Some(enc) => enc,
                 None => {
                     return Ok(HandleFileChangeOutcome {
                         line: None,
                         path_compat_events,
+                        mode_rule_hit: None,
                     });
                 }
             };
@@ -268,26 +380,47 @@ pub fn handle_file_change_line(
             Ok(HandleFileChangeOutcome {
                 line: Some(rebuilt),
                 path_compat_events,
+                mode_rule_hit: None,
             })
         }
         FileChange::Copy { src, dst } => {
             let new_src = rewrite_path(src, opts);
             let new_dst = rewrite_path(dst, opts);
-            let enc_src = match encode_path_with_policy(&new_src, opts, &mut path_compat_events)? {
+            // Copying a path onto itself is a no-op.
+            if new_src == new_dst {
+                return Ok(HandleFileChangeOutcome {
+                    line: None,
+                    path_compat_events,
+                    mode_rule_hit: None,
+                });
+            }
+            let enc_src = match encode_path_with_policy(
+                &new_src,
+                opts,
+                sanitized_paths,
+                &mut path_compat_events,
+            )? {
                 Some(enc) => enc,
                 None => {
                     return Ok(HandleFileChangeOutcome {
                         line: None,
                         path_compat_events,
+                        mode_rule_hit: None,
                     });
                 }
             };
-            let enc_dst = match encode_path_with_policy(&new_dst, opts, &mut path_compat_events)? {
+            let enc_dst = match encode_path_with_policy(
+                &new_dst,
+                opts,
+                sanitized_paths,
+                &mut path_compat_events,
+            )? {
                 Some(enc) => enc,
                 None => {
                     return Ok(HandleFileChangeOutcome {
                         line: None,
                         path_compat_events,
+                        mode_rule_hit: None,
                     });
                 }
             };
@@ -300,26 +433,47 @@ pub fn handle_file_change_line(
             Ok(HandleFileChangeOutcome {
                 line: Some(rebuilt),
                 path_compat_events,
+                mode_rule_hit: None,
             })
         }
         FileChange::Rename { src, dst } => {
             let new_src = rewrite_path(src, opts);
             let new_dst = rewrite_path(dst, opts);
-            let enc_src = match encode_path_with_policy(&new_src, opts, &mut path_compat_events)? {
+            // Renaming a path onto itself is rejected by fast-import; drop it.
+            if new_src == new_dst {
+                return Ok(HandleFileChangeOutcome {
+                    line: None,
+                    path_compat_events,
+                    mode_rule_hit: None,
+                });
+            }
+            let enc_src = match encode_path_with_policy(
+                &new_src,
+                opts,
+                sanitized_paths,
+                &mut path_compat_events,
+            )? {
                 Some(enc) => enc,
                 None => {
                     return Ok(HandleFileChangeOutcome {
                         line: None,
                         path_compat_events,
+                        mode_rule_hit: None,
                     });
                 }
             };
-            let enc_dst = match encode_path_with_policy(&new_dst, opts, &mut path_compat_events)? {
+            let enc_dst = match encode_path_with_policy(
+                &new_dst,
+                opts,
+                sanitized_paths,
+                &mut path_compat_events,
+            )? {
                 Some(enc) => enc,
                 None => {
                     return Ok(HandleFileChangeOutcome {
                         line: None,
                         path_compat_events,
+                        mode_rule_hit: None,
                     });
                 }
             };
@@ -332,7 +486,466 @@ pub fn handle_file_change_line(
             Ok(HandleFileChangeOutcome {
                 line: Some(rebuilt),
                 path_compat_events,
+                mode_rule_hit: None,
             })
         }
     }
 }
+
+type BlobSide = Option<(Vec<u8>, Vec<u8>)>;
+
+/// The (mode, blob id) each path had before and after an original commit,
+/// keyed by rewritten path. `None` marks a rewritten path that more than one
+/// original path maps to, which is never folded.
+#[derive(Debug, Default)]
+struct CommitSides {
+    before: HashMap<Vec<u8>, BlobSide>,
+    after: HashMap<Vec<u8>, BlobSide>,
+}
+
+impl CommitSides {
+    fn insert(map: &mut HashMap<Vec<u8>, BlobSide>, path: Vec<u8>, side: (Vec<u8>, Vec<u8>)) {
+        map.entry(path)
+            .and_modify(|existing| *existing = None)
+            .or_insert(Some(side));
+    }
+
+    /// Records one `:<old mode> <new mode> <old id> <new id> <status>` entry.
+    fn record(&mut self, meta: &[u8], path: &[u8], opts: &Options) {
+        let meta: Vec<&[u8]> = meta
+            .strip_prefix(b":")
+            .unwrap_or(meta)
+            .split(|&b| b == b' ')
+            .collect();
+        let [old_mode, new_mode, old_id, new_id, _status] = meta[..] else {
+            return;
+        };
+        let rewritten = rewrite_path(path.to_vec(), opts);
+        let is_null = |id: &[u8]| id.iter().all(|&b| b == b'0');
+        if !is_null(old_id) {
+            Self::insert(
+                &mut self.before,
+                rewritten.clone(),
+                (old_mode.to_vec(), old_id.to_vec()),
+            );
+        }
+        if !is_null(new_id) {
+            Self::insert(
+                &mut self.after,
+                rewritten,
+                (new_mode.to_vec(), new_id.to_vec()),
+            );
+        }
+    }
+}
+
+/// A `git diff-tree --stdin` kept open for `--synthesize-renames`, so the
+/// whole run diffs its commits through one process.
+///
+/// Each original commit is diffed against its first parent, the same diff
+/// fast-export emitted filechanges from. The commit is followed by a
+/// sentinel line comparing it with itself: `--always` prints that header
+/// with no entries, which marks where the commit's own entries end.
+pub struct DiffTreeBatch {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    field: Vec<u8>,
+    /// Every `(path, mode, dataref)` a rewritten `M` line has written.
+    written: HashSet<(Vec<u8>, Vec<u8>, Vec<u8>)>,
+}
+
+impl DiffTreeBatch {
+    pub fn from_options(opts: &Options) -> io::Result<Option<Self>> {
+        if !opts.synthesize_renames {
+            return Ok(None);
+        }
+        let mut child = Command::new("git")
+            .arg("-C")
+            .arg(&opts.source)
+            .args([
+                "diff-tree",
+                "--stdin",
+                "-r",
+                "-z",
+                "--no-renames",
+                "--always",
+                "--diff-merges=first-parent",
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| io::Error::other("failed to open git diff-tree stdin"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| io::Error::other("failed to read git diff-tree stdout"))?;
+        Ok(Some(Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            field: Vec::new(),
+            written: HashSet::new(),
+        }))
+    }
+
+    /// Reads the next NUL-terminated field into `self.field`.
+    fn next_field(&mut self) -> io::Result<()> {
+        self.field.clear();
+        self.stdout.read_until(0, &mut self.field)?;
+        if self.field.pop() != Some(0) {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "git diff-tree --stdin exited early",
+            ));
+        }
+        Ok(())
+    }
+
+    /// The sides of `original_oid` against its first parent. A root commit
+    /// has no parent and yields empty sides.
+    fn sides(&mut self, original_oid: &[u8], opts: &Options) -> io::Result<CommitSides> {
+        let oid = String::from_utf8_lossy(original_oid);
+        write!(self.stdin, "{oid}\n{oid} {oid}\n")?;
+        self.stdin.flush()?;
+        // Header of the commit itself.
+        self.next_field()?;
+        let mut sides = CommitSides::default();
+        loop {
+            self.next_field()?;
+            if !self.field.starts_with(b":") {
+                // Header of the sentinel.
+                return Ok(sides);
+            }
+            let meta = std::mem::take(&mut self.field);
+            self.next_field()?;
+            sides.record(&meta, &self.field, opts);
+        }
+    }
+
+    pub fn finish(self) -> io::Result<()> {
+        let Self {
+            mut child, stdin, ..
+        } = self;
+        drop(stdin);
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "git diff-tree --stdin failed: {}",
+                status
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// `--synthesize-renames`: in a rewritten commit, replaces a `D old` and an
+/// `M mode id new` whose original blobs are identical with one `R old new`.
+///
+/// `R` copies whatever the rewritten parent holds at `old`, which can differ
+/// from the addition when a rule depends on the path (a per-extension size
+/// limit, `--normalize-mode`). A pair is only folded when an earlier `M`
+/// line wrote the same mode and dataref at `old`; since a dataref names one
+/// original blob, that is what the parent holds there, and the imported
+/// tree is the same either way.
+///
+/// The `R` takes the place of the `D`, so it still runs before any addition
+/// fast-export ordered after the deletions. Pairs involving a path that is a
+/// directory prefix of another path changed by the same commit are left
+/// alone, as are commits with `deleteall`.
+pub fn synthesize_renames(
+    commit_buf: &mut Vec<u8>,
+    original_oid: &[u8],
+    opts: &Options,
+    diff_tree: &mut DiffTreeBatch,
+) -> io::Result<()> {
+    let changes = scan_file_changes(commit_buf);
+    for change in &changes {
+        if let FileChange::Modify { mode, id, path } = &change.change {
+            if id.as_slice() != b"inline" {
+                diff_tree
+                    .written
+                    .insert((path.clone(), mode.clone(), id.clone()));
+            }
+        }
+    }
+    let has_delete = changes
+        .iter()
+        .any(|c| matches!(c.change, FileChange::Delete { .. }));
+    let has_modify = changes
+        .iter()
+        .any(|c| matches!(c.change, FileChange::Modify { .. }));
+    if !has_delete || !has_modify {
+        return Ok(());
+    }
+    let sides = diff_tree.sides(original_oid, opts)?;
+    fold_renames(commit_buf, changes, &sides, &diff_tree.written);
+    Ok(())
+}
+
+struct ScannedChange {
+    start: usize,
+    end: usize,
+    change: FileChange,
+}
+
+/// Filechange lines of a buffered commit, skipping `data` payloads.
+fn scan_file_changes(commit_buf: &[u8]) -> Vec<ScannedChange> {
+    let mut changes = Vec::new();
+    let mut pos = 0usize;
+    while pos < commit_buf.len() {
+        let end = commit_buf[pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(commit_buf.len(), |i| pos + i + 1);
+        let line = &commit_buf[pos..end];
+        if let Some(size) = line.strip_prefix(b"data ") {
+            let size = std::str::from_utf8(size)
+                .ok()
+                .and_then(|s| s.trim_end().parse::<usize>().ok())
+                .unwrap_or(0);
+            pos = (end + size).min(commit_buf.len());
+            continue;
+        }
+        if let Some(change) = parse_file_change_line(line) {
+            changes.push(ScannedChange {
+                start: pos,
+                end,
+                change,
+            });
+        }
+        pos = end;
+    }
+    changes
+}
+
+fn is_dir_prefix(dir: &[u8], path: &[u8]) -> bool {
+    path.len() > dir.len() && path.starts_with(dir) && path[dir.len()] == b'/'
+}
+
+fn fold_renames(
+    commit_buf: &mut Vec<u8>,
+    changes: Vec<ScannedChange>,
+    sides: &CommitSides,
+    written: &HashSet<(Vec<u8>, Vec<u8>, Vec<u8>)>,
+) {
+    if changes
+        .iter()
+        .any(|c| matches!(c.change, FileChange::DeleteAll))
+    {
+        return;
+    }
+    let touched: Vec<&[u8]> = changes
+        .iter()
+        .flat_map(|c| match &c.change {
+            FileChange::Modify { path, .. } | FileChange::Delete { path } => {
+                vec![path.as_slice()]
+            }
+            FileChange::Copy { src, dst } | FileChange::Rename { src, dst } => {
+                vec![src.as_slice(), dst.as_slice()]
+            }
+            FileChange::DeleteAll => Vec::new(),
+        })
+        .collect();
+    let nested = |path: &[u8]| {
+        touched
+            .iter()
+            .any(|other| is_dir_prefix(path, other) || is_dir_prefix(other, path))
+    };
+
+    // (range to replace, replacement)
+    let mut edits: Vec<(usize, usize, Vec<u8>)> = Vec::new();
+    let mut used = vec![false; changes.len()];
+    for (di, delete) in changes.iter().enumerate() {
+        let FileChange::Delete { path: src } = &delete.change else {
+            continue;
+        };
+        let Some(Some(before)) = sides.before.get(src) else {
+            continue;
+        };
+        if sides.after.contains_key(src) || nested(src) {
+            continue;
+        }
+        let found = changes.iter().enumerate().position(|(mi, add)| {
+            let FileChange::Modify {
+                mode,
+                id,
+                path: dst,
+            } = &add.change
+            else {
+                return false;
+            };
+            !used[mi]
+                && id.as_slice() != b"inline"
+                && written.contains(&(src.clone(), mode.clone(), id.clone()))
+                && !sides.before.contains_key(dst)
+                && sides.after.get(dst) == Some(&Some(before.clone()))
+                && !nested(dst)
+        });
+        let Some(mi) = found else {
+            continue;
+        };
+        used[mi] = true;
+        used[di] = true;
+        let src_enc = &commit_buf[delete.start + 2..delete.end - 1];
+        let add_line = &commit_buf[changes[mi].start..changes[mi].end - 1];
+        // `M <mode> <id> <path>`: the path is everything after the second space.
+        let dst_enc = add_line
+            .splitn(4, |&b| b == b' ')
+            .nth(3)
+            .unwrap_or_default();
+        let mut rename = b"R ".to_vec();
+        rename.extend_from_slice(src_enc);
+        rename.push(b' ');
+        rename.extend_from_slice(dst_enc);
+        rename.push(b'\n');
+        edits.push((delete.start, delete.end, rename));
+        edits.push((changes[mi].start, changes[mi].end, Vec::new()));
+    }
+    edits.sort_by_key(|(start, _, _)| std::cmp::Reverse(*start));
+    for (start, end, replacement) in edits {
+        commit_buf.splice(start..end, replacement);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matching_delete_and_add_fold_into_a_rename() {
+        let side = |id: &str| Some((b"100644".to_vec(), id.as_bytes().to_vec()));
+        let mut sides = CommitSides::default();
+        sides.before.insert(b"lib/a.rs".to_vec(), side("aaaa"));
+        sides.before.insert(b"lib/b.rs".to_vec(), side("bbbb"));
+        sides.after.insert(b"src/a.rs".to_vec(), side("aaaa"));
+        sides.after.insert(b"src/b c.rs".to_vec(), side("cccc"));
+        let mut buf = b"commit refs/heads/main\nmark :3\ndata 11\nD lib/a.rs\nfrom :2\n\
+D lib/a.rs\nD lib/b.rs\nM 100644 :1 src/a.rs\nM 100644 :2 \"src/b c.rs\"\n"
+            .to_vec();
+        let changes = scan_file_changes(&buf);
+        assert_eq!(changes.len(), 4, "the message payload is not a filechange");
+        let written = HashSet::from([
+            (b"lib/a.rs".to_vec(), b"100644".to_vec(), b":1".to_vec()),
+            (b"lib/b.rs".to_vec(), b"100644".to_vec(), b":4".to_vec()),
+        ]);
+        fold_renames(&mut buf, changes, &sides, &written);
+        assert_eq!(
+            String::from_utf8(buf).unwrap(),
+            "commit refs/heads/main\nmark :3\ndata 11\nD lib/a.rs\nfrom :2\n\
+R lib/a.rs src/a.rs\nD lib/b.rs\nM 100644 :2 \"src/b c.rs\"\n"
+        );
+    }
+
+    #[test]
+    fn delete_is_not_folded_when_the_old_path_held_another_rewrite() {
+        let side = Some((b"100644".to_vec(), b"aaaa".to_vec()));
+        let mut sides = CommitSides::default();
+        sides.before.insert(b"run.txt".to_vec(), side.clone());
+        sides.after.insert(b"run.sh".to_vec(), side);
+        // --normalize-mode made the addition executable; run.txt was not.
+        let written = HashSet::from([(b"run.txt".to_vec(), b"100644".to_vec(), b":1".to_vec())]);
+        let mut buf = b"D run.txt\nM 100755 :1 run.sh\n".to_vec();
+        let changes = scan_file_changes(&buf);
+        fold_renames(&mut buf, changes, &sides, &written);
+        assert_eq!(buf, b"D run.txt\nM 100755 :1 run.sh\n");
+    }
+
+    #[test]
+    fn rename_and_copy_onto_same_path_are_dropped() {
+        let opts = Options {
+            path_renames: vec![(b"src/".to_vec(), b"src/".to_vec())],
+            ..Options::default()
+        };
+        for line in [&b"R src/a.rs src/a.rs\n"[..], b"C src/a.rs src/a.rs\n"] {
+            let outcome = handle_file_change_line(line, &opts).unwrap();
+            assert_eq!(outcome.line, None);
+        }
+
+        // Distinct paths are still emitted.
+        let outcome = handle_file_change_line(b"R src/a.rs src/b.rs\n", &opts).unwrap();
+        assert_eq!(outcome.line.as_deref(), Some(&b"R src/a.rs src/b.rs\n"[..]));
+    }
+
+    fn opts_with_rules(rules: &[(&str, &str)]) -> Options {
+        Options {
+            mode_normalizations: rules
+                .iter()
+                .map(|(p, m)| (p.as_bytes().to_vec(), m.as_bytes().to_vec()))
+                .collect(),
+            ..Options::default()
+        }
+    }
+
+    #[test]
+    fn path_rename_regex_expands_captures_after_prefix_renames() {
+        let opts = Options {
+            path_renames: vec![(b"old/".to_vec(), b"packages/".to_vec())],
+            path_rename_regexes: vec![
+                (
+                    regex::bytes::Regex::new(r"packages/([^/]+)/src/").unwrap(),
+                    b"modules/$1/lib/".to_vec(),
+                ),
+                (
+                    regex::bytes::Regex::new(r"^modules/(?P<pkg>[^/]+)/lib/main\.rs$").unwrap(),
+                    b"modules/${pkg}/lib/lib.rs".to_vec(),
+                ),
+            ],
+            ..Options::default()
+        };
+
+        assert_eq!(
+            rewrite_path(b"packages/mylib/src/util.rs".to_vec(), &opts),
+            b"modules/mylib/lib/util.rs".to_vec()
+        );
+        // Later regexes see the output of earlier ones.
+        assert_eq!(
+            rewrite_path(b"old/mylib/src/main.rs".to_vec(), &opts),
+            b"modules/mylib/lib/lib.rs".to_vec()
+        );
+        assert_eq!(
+            rewrite_path(b"docs/readme.md".to_vec(), &opts),
+            b"docs/readme.md".to_vec()
+        );
+    }
+
+    #[test]
+    fn normalize_mode_rewrites_matching_regular_files() {
+        let opts = opts_with_rules(&[("*.sh", "100755"), ("docs/*.md", "100644")]);
+
+        let out = handle_file_change_line(b"M 100644 :1 bin/run.sh\n", &opts).unwrap();
+        assert_eq!(out.line.as_deref(), Some(&b"M 100755 :1 bin/run.sh\n"[..]));
+        assert_eq!(out.mode_rule_hit, Some(0));
+
+        let out = handle_file_change_line(b"M 100755 :2 docs/x.md\n", &opts).unwrap();
+        assert_eq!(out.line.as_deref(), Some(&b"M 100644 :2 docs/x.md\n"[..]));
+        assert_eq!(out.mode_rule_hit, Some(1));
+
+        // Already the requested mode: not counted as a change.
+        let out = handle_file_change_line(b"M 100755 :3 run.sh\n", &opts).unwrap();
+        assert_eq!(out.mode_rule_hit, None);
+    }
+
+    #[test]
+    fn normalize_mode_leaves_links_and_unmatched_modes_untouched() {
+        let opts = opts_with_rules(&[("*", "100644")]);
+
+        for line in [
+            &b"M 120000 :1 link.sh\n"[..],
+            b"M 160000 0123456789012345678901234567890123456789 sub\n",
+        ] {
+            let out = handle_file_change_line(line, &opts).unwrap();
+            assert_eq!(out.line.as_deref(), Some(line));
+            assert_eq!(out.mode_rule_hit, None);
+        }
+
+        let no_rules = Options::default();
+        let line = b"M 100664 :4 legacy.txt\n";
+        let out = handle_file_change_line(line, &no_rules).unwrap();
+        assert_eq!(out.line.as_deref(), Some(&line[..]));
+    }
+}
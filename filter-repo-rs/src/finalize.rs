@@ -1,21 +1,29 @@
+use std::borrow::Cow;
 use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs::File;
 use std::io::{self, BufRead, BufReader, Read, Write};
-use std::path::Path;
-use std::process::{Child, Command, Stdio};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
 
 use colored::*;
 use serde::Serialize;
 
+use crate::diagnostics::{self, Phase};
 use crate::error::{FilterRepoError, Result};
-use crate::gitutil;
+use crate::log::MigrationLog;
 use crate::migrate;
 use crate::opts::Options;
+use crate::ref_plan::{self, HeadPlan, PlannedRefUpdate, RefPlan};
+use crate::refs::RefSnapshot;
+use crate::reproducible::MarkTable;
 
 #[derive(Debug, Serialize)]
 pub struct Summary {
+    /// `full`, `no-data` or `metadata-only`.
+    pub export_mode: &'static str,
     pub blobs_stripped_by_size: usize,
     pub blobs_stripped_by_sha: usize,
+    pub blobs_stripped_by_content: usize,
     pub blobs_modified: usize,
 }
 
@@ -34,6 +42,24 @@ pub struct Samples {
     pub by_sha: Vec<String>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub modified: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub modified_blobs: Vec<ModifiedBlobSample>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub by_content: Vec<ContentMatchSample>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ContentMatchSample {
+    pub path: String,
+    /// The `--strip-blobs-matching` pattern that matched.
+    pub pattern: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModifiedBlobSample {
+    pub path: String,
+    /// Blob id before the rewrite; `None` for inline blobs.
+    pub original_oid: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -41,6 +67,16 @@ pub struct WindowsPathSummary {
     pub policy: String,
     pub sanitized: usize,
     pub skipped: usize,
+    /// Originals that needed a numeric suffix to avoid overwriting another file.
+    pub collisions: usize,
+}
+
+/// Originals that sanitize to the same path; all but the first seen were
+/// written with a `_N` suffix.
+#[derive(Debug, Serialize)]
+pub struct PathCollisionGroup {
+    pub sanitized: String,
+    pub originals: Vec<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -54,10 +90,174 @@ pub struct WindowsPathSamples {
 #[derive(Debug, Serialize)]
 pub struct WindowsPathReport {
     pub summary: WindowsPathSummary,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub collisions: Vec<PathCollisionGroup>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub samples: Option<WindowsPathSamples>,
 }
 
+#[derive(Debug, Serialize)]
+pub struct IdentityRuleReport {
+    /// Which option supplied the rule (`mailmap`, `author-rewrite`, ...).
+    pub source: String,
+    pub old: String,
+    pub new: String,
+    pub author_hits: usize,
+    pub committer_hits: usize,
+    pub tagger_hits: usize,
+}
+
+impl IdentityRuleReport {
+    pub fn total_hits(&self) -> usize {
+        self.author_hits + self.committer_hits + self.tagger_hits
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct IdentityRewriteReport {
+    pub rules: Vec<IdentityRuleReport>,
+    pub distinct_identities_rewritten: usize,
+    pub zero_hit_rules: usize,
+}
+
+/// One `--override-committer`/`--override-tagger` stamp.
+#[derive(Debug, Serialize)]
+pub struct IdentityOverrideReport {
+    /// `committer` or `tagger`.
+    pub header: &'static str,
+    pub identity: String,
+    /// `keep` or `now`.
+    pub date: &'static str,
+    /// Emitted commits (or tags) stamped; pruned ones are not counted.
+    pub stamped: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ModeNormalizationReport {
+    pub pattern: String,
+    pub mode: String,
+    /// Number of file changes whose mode was rewritten by this rule.
+    pub changed: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EolNormalizationReport {
+    pub target: String,
+    /// Paths the conversion was limited to; empty means every path.
+    pub globs: Vec<String>,
+    pub blobs_converted: usize,
+    pub bytes_removed: usize,
+    pub bytes_added: usize,
+    /// Text blobs with a lone CR, left unconverted.
+    pub skipped_suspicious_eol: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RegexRuleReport {
+    /// Which option supplied the rule (`replace-text`, `replace-message`, ...).
+    pub source: String,
+    pub pattern: String,
+    /// Matches replaced across all processed blobs or messages.
+    pub matches: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BlobCacheReport {
+    /// Blobs passed through because the cache knew them to be unmodified.
+    pub hits: usize,
+    /// Blobs that had replacements applied.
+    pub misses: usize,
+    /// Unmodified blobs recorded for the current rule set.
+    pub entries: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubjectNormalizationReport {
+    /// Messages that had one or more `--strip-subject-prefix` tokens removed.
+    pub prefix_stripped: usize,
+    /// Messages that had a `--strip-pr-suffix` suffix removed.
+    pub pr_suffix_stripped: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagNameReferencesReport {
+    /// Commit and tag messages with a renamed tag mentioned by its old name.
+    pub messages_updated: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RootFilesReport {
+    /// Root commits that received one or more `--add-root-file` files.
+    pub roots_modified: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RefNamespacesReport {
+    /// Namespaces (`refs/heads/`, `refs/tags/`, ...) `--all` exported.
+    pub included: Vec<String>,
+    /// Namespaces left untouched: opt-in ones whose flag was not given and
+    /// everything outside branches and tags.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub excluded: Vec<ExcludedRefNamespace>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExcludedRefNamespace {
+    pub namespace: String,
+    pub refs: usize,
+    /// `--include-*` flag that would rewrite them; `None` when no flag does.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flag: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RefStatsReport {
+    /// Ref named in the commit headers, after any rename.
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub renamed_from: Option<String>,
+    pub commits_emitted: usize,
+    pub commits_pruned: usize,
+    pub merges_degenerated: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DroppedTagReport {
+    pub tag: String,
+    /// Mark or object id of the stripped blob (or dropped inner tag).
+    pub target: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PrunedTagReport {
+    pub tag: String,
+    /// Original id (or mark) of the pruned commit the tag pointed at.
+    pub commit: String,
+    /// `deleted`, `retargeted` or `kept-old`.
+    pub action: &'static str,
+    /// Original id of the surviving ancestor a retargeted tag now points at.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new_target: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PipelineReport {
+    /// `--pipeline-buffer-bytes` in effect for the run.
+    pub buffer_bytes: usize,
+    /// Most filtered stream bytes that were waiting for fast-import at once.
+    pub peak_queued_bytes: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PostCheckReport {
+    /// `connectivity`, `fsck` or `fsck-full`.
+    pub check: &'static str,
+    pub passed: bool,
+    pub elapsed_ms: u64,
+    /// What the check printed, stdout then stderr.
+    pub output: String,
+}
+
 #[derive(Debug, Serialize)]
 pub struct Metadata {
     pub version: String,
@@ -71,6 +271,38 @@ pub struct ReportData {
     pub samples: Samples,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub windows_path: Option<WindowsPathReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity_rewrites: Option<IdentityRewriteReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub identity_overrides: Option<Vec<IdentityOverrideReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode_normalizations: Option<Vec<ModeNormalizationReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eol_normalization: Option<EolNormalizationReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub regex_rules: Option<Vec<RegexRuleReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob_cache: Option<BlobCacheReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject_normalization: Option<SubjectNormalizationReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tag_name_references: Option<TagNameReferencesReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub root_files: Option<RootFilesReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pipeline: Option<PipelineReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ref_namespaces: Option<RefNamespacesReport>,
+    /// Commit counts per ref; a commit on several refs counts for the one
+    /// its `commit` header names.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ref_stats: Option<Vec<RefStatsReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dropped_tags: Option<Vec<DroppedTagReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pruned_tags: Option<Vec<PrunedTagReport>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub post_check: Option<PostCheckReport>,
     pub metadata: Metadata,
 }
 
@@ -85,6 +317,18 @@ pub struct FinalizeContext<'a> {
     pub branch_reset_targets: Vec<(Vec<u8>, Vec<u8>)>,
     pub import_broken: bool,
     pub allow_flush_tag_resets: bool,
+    /// Blob mark -> original blob id for blobs changed by replace-text.
+    pub modified_blob_oids: HashMap<u32, Vec<u8>>,
+    /// Branches and `HEAD` of the target before fast-import ran; a
+    /// detached `HEAD` is updated by fast-import itself.
+    pub branches_before: BTreeSet<String>,
+    pub head_before: HeadState,
+    pub migration_log: &'a MigrationLog,
+    /// Set when `--reproducible` renumbered the marks fast-import saw.
+    pub renumbered_marks: Option<MarkTable>,
+    /// `path-events.jsonl` and its record count, when any path was
+    /// sanitized or skipped.
+    pub path_events: Option<(usize, PathBuf)>,
 }
 
 // Flush buffered lightweight tag resets to outputs prior to sending 'done'.
@@ -139,9 +383,9 @@ pub fn finalize(
     ctx: FinalizeContext<'_>,
     filt_file: &mut dyn Write,
     mut fi_in: Option<Box<dyn Write>>,
-    fe: &mut Child,
+    fe: Option<&mut Child>,
     fi: Option<&mut Child>,
-    report: Option<ReportData>,
+    mut report: Option<ReportData>,
 ) -> Result<()> {
     let FinalizeContext {
         opts,
@@ -154,6 +398,12 @@ pub fn finalize(
         mut branch_reset_targets,
         mut import_broken,
         allow_flush_tag_resets,
+        modified_blob_oids,
+        branches_before,
+        head_before,
+        migration_log,
+        renumbered_marks,
+        path_events,
     } = ctx;
     // Emit buffered lightweight tag resets if any remain (ideally flushed before 'done')
     if allow_flush_tag_resets {
@@ -183,15 +433,17 @@ pub fn finalize(
     }
 
     // Handle process termination and propagate errors
-    if import_broken {
-        let _ = fe.kill();
-    }
-    let fe_status = fe.wait()?;
-    if !fe_status.success() {
-        return Err(FilterRepoError::Io(io::Error::other(format!(
-            "fast-export failed: {}",
-            fe_status
-        ))));
+    if let Some(fe) = fe {
+        if import_broken {
+            let _ = fe.kill();
+        }
+        let fe_status = fe.wait()?;
+        if !fe_status.success() {
+            return Err(FilterRepoError::Io(io::Error::other(format!(
+                "fast-export failed: {}",
+                fe_status
+            ))));
+        }
     }
     if let Some(child) = fi {
         let fi_status = child.wait()?;
@@ -214,6 +466,7 @@ pub fn finalize(
             f.write_all(b" ")?;
             f.write_all(new_)?;
             f.write_all(b"\n")?;
+            migration_log.log_ref_renamed(old, new_)?;
         }
     }
 
@@ -235,74 +488,28 @@ pub fn finalize(
             buf.clear();
         }
     }
+    // Everything below speaks fast-export's mark numbers.
+    if let Some(table) = &renumbered_marks {
+        mark_to_id = mark_to_id
+            .into_iter()
+            .filter_map(|(mark, id)| table.original(mark).map(|m| (m, id)))
+            .collect();
+    }
 
-    if !opts.dry_run {
-        let mut resolved_updates: BTreeMap<Vec<u8>, Vec<u8>> = BTreeMap::new();
-        for (refname, target) in branch_reset_targets.drain(..) {
-            if let Some(oid) = resolve_reset_target(&target, &mark_to_id, opts)? {
-                resolved_updates.insert(refname, oid);
-            }
-        }
-        let mut update_payload: Vec<u8> = Vec::new();
-        let repo_refs_before = gitutil::get_all_refs(&opts.target)?;
-        for (refname, oid) in &resolved_updates {
-            let ref_str = String::from_utf8_lossy(refname);
-            let oid_str = String::from_utf8_lossy(oid);
-            update_payload
-                .extend_from_slice(format!("update {} {}\n", ref_str, oid_str).as_bytes());
-        }
-        for (old, new_) in &refs {
-            if old == new_ {
-                continue;
-            }
-            let old_ref = String::from_utf8_lossy(old).to_string();
-            let mut matches: Vec<&String> = repo_refs_before
-                .keys()
-                .filter(|name| name.starts_with(&old_ref))
-                .collect();
-            matches.sort();
-            let resolved_name = matches.into_iter().next().cloned();
-            let delete_old = resolved_name
-                .as_ref()
-                .map(|name| name == &old_ref)
-                .unwrap_or(false);
-            if delete_old {
-                update_payload.extend_from_slice(b"delete ");
-                update_payload.extend_from_slice(old);
-                update_payload.push(b'\n');
-            } else if let Some(refname) = resolved_name {
-                eprintln!(
-                    "warning: not deleting {} because repository resolves to {}",
-                    old_ref, refname,
-                );
-            } else {
-                eprintln!(
-                    "warning: not deleting {} because it does not exist",
-                    old_ref,
-                );
-            }
-        }
-        if !update_payload.is_empty() {
-            let mut child = Command::new("git")
-                .arg("-C")
-                .arg(&opts.target)
-                .arg("update-ref")
-                .arg("--no-deref")
-                .arg("--stdin")
-                .stdin(Stdio::piped())
-                .spawn()
-                .map_err(|e| io::Error::other(format!("failed to run git update-ref: {e}")))?;
-            if let Some(mut sin) = child.stdin.take() {
-                sin.write_all(&update_payload)?;
-            }
-            let status = child.wait()?;
-            if !status.success() {
-                eprintln!(
-                    "warning: {} failed: {}",
-                    "git update-ref".cyan().bold(),
-                    status
-                );
-            }
+    // Write blob-map (old -> new) for blobs rewritten by replace-text.
+    if !modified_blob_oids.is_empty() {
+        let mut entries: Vec<(&Vec<u8>, &Vec<u8>)> = modified_blob_oids
+            .iter()
+            .filter_map(|(mark, old)| mark_to_id.get(mark).map(|new_id| (old, new_id)))
+            .collect();
+        entries.sort();
+        entries.dedup();
+        let mut f = File::create(debug_dir.join("blob-map"))?;
+        for (old, new_id) in entries {
+            f.write_all(old)?;
+            f.write_all(b" ")?;
+            f.write_all(new_id)?;
+            f.write_all(b"\n")?;
         }
     }
 
@@ -383,6 +590,7 @@ pub fn finalize(
     }
 
     // Always create commit-map (even if empty) for user tooling parity
+    let mut commit_map: HashMap<Vec<u8>, Option<Vec<u8>>> = HashMap::new();
     {
         let mut f = File::create(debug_dir.join("commit-map"))?;
         for (old, mark) in pairs {
@@ -393,16 +601,111 @@ pub fn finalize(
                         f.write_all(b" ")?;
                         f.write_all(newid)?;
                         f.write_all(b"\n")?;
+                        migration_log.log_commit_kept(&old, newid)?;
+                        commit_map.insert(old, Some(newid.clone()));
                     }
                 }
                 None => {
                     f.write_all(&old)?;
                     f.write_all(b" 0000000000000000000000000000000000000000\n")?;
+                    migration_log.log_commit_pruned(&old)?;
+                    commit_map.insert(old, None);
                 }
             }
         }
     }
 
+    // The target's refs once this run's updates are applied
+    let mut refs_after = None;
+    if !opts.dry_run {
+        let mut ref_plan = RefPlan::default();
+        let mut resolved_updates: BTreeMap<Vec<u8>, Vec<u8>> = BTreeMap::new();
+        for (refname, target) in branch_reset_targets.drain(..) {
+            if let Some(oid) = resolve_reset_target(&target, &mark_to_id, opts)? {
+                resolved_updates.insert(refname, oid);
+            }
+        }
+        // fast-import has moved refs since the run's snapshot was taken, so
+        // the updates are planned against the refs it left behind.
+        let repo_refs_before = RefSnapshot::capture(&opts.target)?;
+        for (refname, oid) in &resolved_updates {
+            let name = String::from_utf8_lossy(refname).to_string();
+            ref_plan.updates.push(PlannedRefUpdate {
+                old: repo_refs_before.oid(&name).map(str::to_string),
+                new: Some(String::from_utf8_lossy(oid).to_string()),
+                name,
+            });
+        }
+        for (old, new_) in &refs {
+            if old == new_ {
+                continue;
+            }
+            let old_ref = String::from_utf8_lossy(old).to_string();
+            let resolved_name = repo_refs_before.first_with_prefix(&old_ref);
+            let delete_old = resolved_name == Some(old_ref.as_str());
+            if delete_old {
+                ref_plan.updates.push(PlannedRefUpdate {
+                    old: repo_refs_before.oid(&old_ref).map(str::to_string),
+                    new: None,
+                    name: old_ref,
+                });
+            } else if let Some(refname) = resolved_name {
+                diagnostics::warning(
+                    Phase::Finalize,
+                    format!(
+                        "not deleting {} because repository resolves to {}",
+                        old_ref, refname,
+                    ),
+                );
+            } else {
+                diagnostics::warning(
+                    Phase::Finalize,
+                    format!("not deleting {} because it does not exist", old_ref),
+                );
+            }
+        }
+        let predicted_refs = ref_plan.predicted_refs(&repo_refs_before);
+        let current_head = HeadState::read(&opts.target)?;
+        let head_after = plan_head(
+            opts,
+            &commit_map,
+            &updated_branch_refs,
+            &branches_before,
+            &head_before,
+            &current_head,
+            &predicted_refs,
+        )?;
+        if head_after != current_head {
+            ref_plan.head = Some(HeadPlan {
+                before: current_head.to_string(),
+                after: head_after.to_string(),
+            });
+        }
+        // Saved, HEAD included, before anything moves so
+        // --resume-ref-update can finish the job if this process dies
+        // part-way.
+        ref_plan.save(debug_dir)?;
+        let update_payload = ref_plan::update_ref_payload(&ref_plan.updates);
+        let mut applied = true;
+        if !update_payload.is_empty() {
+            let status = ref_plan::apply_payload(&opts.target, &update_payload)?;
+            if !status.success() {
+                applied = false;
+                diagnostics::warning(
+                    Phase::Finalize,
+                    format!("{} failed: {}", "git update-ref".cyan().bold(), status),
+                );
+            }
+        }
+        // update-ref --stdin is one transaction: all updates or none.
+        refs_after = Some(if applied {
+            predicted_refs
+        } else {
+            repo_refs_before
+        });
+        move_head(opts, debug_dir, &head_before, &current_head, &head_after)?;
+    }
+
     // Optional reset --hard on target
     if !opts.dry_run && opts.reset {
         let mut reset = Command::new("git");
@@ -413,10 +716,9 @@ pub fn finalize(
         reset.arg("--hard");
         let status = reset.status()?;
         if !status.success() {
-            eprintln!(
-                "warning: {} failed: {}",
-                "git reset --hard".cyan().bold(),
-                status
+            diagnostics::warning(
+                Phase::Finalize,
+                format!("{} failed: {}", "git reset --hard".cyan().bold(), status),
             );
         }
     }
@@ -426,12 +728,37 @@ pub fn finalize(
         match opts.cleanup {
             crate::opts::CleanupMode::None => {}
             crate::opts::CleanupMode::Standard => {
-                run_repo_cleanup(&opts.target, false);
+                run_repo_cleanup(&opts.target, false, opts.keep_unreachable);
             }
             crate::opts::CleanupMode::Aggressive => {
-                run_repo_cleanup(&opts.target, true);
+                run_repo_cleanup(&opts.target, true, opts.keep_unreachable);
             }
         }
+        if opts.keep_unreachable && opts.cleanup != crate::opts::CleanupMode::None {
+            diagnostics::warning(
+                Phase::Finalize,
+                format!(
+                    "unreachable objects from the old history were kept; remove them once they are no longer needed with {}",
+                    "git gc --prune=<date>".cyan().bold()
+                ),
+            );
+        }
+    }
+
+    // Verify what cleanup left behind; a failure is raised once the reports
+    // carrying the check's output are written.
+    let mut post_check_failure = None;
+    if let (Some(check), false) = (opts.post_check, opts.dry_run) {
+        let result = crate::post_check::run(opts, check)?;
+        if !result.passed {
+            post_check_failure = Some(FilterRepoError::PostCheck {
+                check: result.check,
+                output: result.output.clone(),
+            });
+        }
+        if let Some(ref mut r) = report {
+            r.post_check = Some(result);
+        }
     }
 
     // Always emit windows path compatibility report when policy had hits.
@@ -445,6 +772,8 @@ pub fn finalize(
                 writeln!(f, "Policy: {}", wp.summary.policy)?;
                 writeln!(f, "Sanitized: {}", wp.summary.sanitized)?;
                 writeln!(f, "Skipped: {}", wp.summary.skipped)?;
+                writeln!(f, "Collisions: {}", wp.summary.collisions)?;
+                write_path_collision_groups(&mut f, &wp.collisions)?;
                 if let Some(samples) = &wp.samples {
                     if !samples.sanitized.is_empty() {
                         writeln!(f, "\n=== Sanitized paths ===")?;
@@ -459,15 +788,46 @@ pub fn finalize(
                         }
                     }
                 }
-                eprintln!(
-                    "warning: path compatibility policy '{}' adjusted history (sanitized: {}, skipped: {}); details: {}",
-                    wp.summary.policy,
-                    wp.summary.sanitized,
-                    wp.summary.skipped,
-                    path_report.display()
+                diagnostics::warning(
+                    Phase::Finalize,
+                    format!(
+                        "path compatibility policy '{}' adjusted history (sanitized: {}, skipped: {}); details: {}",
+                        wp.summary.policy,
+                        wp.summary.sanitized,
+                        wp.summary.skipped,
+                        path_report.display()
+                    ),
+                );
+            }
+        }
+    }
+
+    // Rules that never matched are almost always typos in the mapping file.
+    if let Some(ref r) = report {
+        if let Some(ref ir) = r.identity_rewrites {
+            if ir.zero_hit_rules > 0 && !opts.quiet {
+                diagnostics::warning(
+                    Phase::Finalize,
+                    format!(
+                        "{} identity rewrite rule(s) matched nothing; check the mapping file for typos",
+                        ir.zero_hit_rules
+                    ),
                 );
             }
         }
+        if let Some(ref overrides) = r.identity_overrides {
+            if !opts.quiet {
+                for o in overrides {
+                    diagnostics::info(
+                        Phase::Finalize,
+                        format!(
+                            "Stamped {} {} line(s) with {}",
+                            o.stamped, o.header, o.identity
+                        ),
+                    );
+                }
+            }
+        }
     }
 
     // Optional reporting (use only stream-collected data; no rescans)
@@ -477,6 +837,7 @@ pub fn finalize(
             let mut f = File::create(debug_dir.join("report.txt"))?;
             if let Some(ref r) = report {
                 writeln!(f, "=== Summary ===")?;
+                writeln!(f, "Export mode: {}", r.summary.export_mode)?;
                 writeln!(
                     f,
                     "Blobs stripped by size: {}",
@@ -487,6 +848,11 @@ pub fn finalize(
                     "Blobs stripped by SHA: {}",
                     r.summary.blobs_stripped_by_sha
                 )?;
+                writeln!(
+                    f,
+                    "Blobs stripped by content: {}",
+                    r.summary.blobs_stripped_by_content
+                )?;
                 writeln!(
                     f,
                     "Blobs modified by replace-text: {}",
@@ -512,10 +878,19 @@ pub fn finalize(
                         writeln!(f, "{}", p)?;
                     }
                 }
-                if !r.samples.modified.is_empty() {
+                if !r.samples.by_content.is_empty() {
+                    writeln!(f, "\n=== Sample paths (content) ===")?;
+                    for sample in &r.samples.by_content {
+                        writeln!(f, "{} (matched {})", sample.path, sample.pattern)?;
+                    }
+                }
+                if !r.samples.modified_blobs.is_empty() {
                     writeln!(f, "\n=== Sample paths (modified) ===")?;
-                    for p in &r.samples.modified {
-                        writeln!(f, "{}", p)?;
+                    for sample in &r.samples.modified_blobs {
+                        match &sample.original_oid {
+                            Some(oid) => writeln!(f, "{} (original {})", sample.path, oid)?,
+                            None => writeln!(f, "{}", sample.path)?,
+                        }
                     }
                 }
                 if let Some(ref wp) = r.windows_path {
@@ -525,6 +900,8 @@ pub fn finalize(
                         writeln!(f, "Policy: {}", wp.summary.policy)?;
                         writeln!(f, "Sanitized: {}", wp.summary.sanitized)?;
                         writeln!(f, "Skipped: {}", wp.summary.skipped)?;
+                        writeln!(f, "Collisions: {}", wp.summary.collisions)?;
+                        write_path_collision_groups(&mut f, &wp.collisions)?;
                         if let Some(samples) = &wp.samples {
                             if !samples.sanitized.is_empty() {
                                 writeln!(f, "\n=== Sample paths (path-compat sanitized) ===")?;
@@ -541,6 +918,183 @@ pub fn finalize(
                         }
                     }
                 }
+                if let Some(ref ir) = r.identity_rewrites {
+                    writeln!(f, "\n=== Identity rewrites ===")?;
+                    writeln!(
+                        f,
+                        "Distinct identities rewritten: {}",
+                        ir.distinct_identities_rewritten
+                    )?;
+                    writeln!(f, "Rules with no matches: {}", ir.zero_hit_rules)?;
+                    for rule in &ir.rules {
+                        let flag = if rule.total_hits() == 0 {
+                            "  [NO MATCHES]"
+                        } else {
+                            ""
+                        };
+                        writeln!(
+                            f,
+                            "{}: {} -> {} (author: {}, committer: {}, tagger: {}){}",
+                            rule.source,
+                            rule.old,
+                            rule.new,
+                            rule.author_hits,
+                            rule.committer_hits,
+                            rule.tagger_hits,
+                            flag
+                        )?;
+                    }
+                }
+                if let Some(ref overrides) = r.identity_overrides {
+                    writeln!(f, "\n=== Identity overrides ===")?;
+                    for o in overrides {
+                        writeln!(
+                            f,
+                            "{}: {} (date: {}): {} line(s) stamped",
+                            o.header, o.identity, o.date, o.stamped
+                        )?;
+                    }
+                }
+                if let Some(ref rules) = r.mode_normalizations {
+                    writeln!(f, "\n=== Mode normalizations ===")?;
+                    for rule in rules {
+                        writeln!(
+                            f,
+                            "{} -> {}: {} file change(s)",
+                            rule.pattern, rule.mode, rule.changed
+                        )?;
+                    }
+                }
+                if let Some(ref eol) = r.eol_normalization {
+                    writeln!(f, "\n=== Line ending normalization ===")?;
+                    let scope = if eol.globs.is_empty() {
+                        "all paths".to_string()
+                    } else {
+                        eol.globs.join(", ")
+                    };
+                    writeln!(f, "Target: {} ({})", eol.target, scope)?;
+                    writeln!(f, "Blobs converted: {}", eol.blobs_converted)?;
+                    if eol.bytes_added > 0 {
+                        writeln!(f, "Bytes added: {}", eol.bytes_added)?;
+                    } else {
+                        writeln!(f, "Bytes removed: {}", eol.bytes_removed)?;
+                    }
+                    writeln!(f, "Skipped suspicious EOL: {}", eol.skipped_suspicious_eol)?;
+                }
+                if let Some(ref rules) = r.regex_rules {
+                    writeln!(f, "\n=== Regex rule match counts ===")?;
+                    for rule in rules {
+                        writeln!(
+                            f,
+                            "{} {}: {} match(es)",
+                            rule.source,
+                            truncate_pattern(&rule.pattern),
+                            rule.matches
+                        )?;
+                    }
+                }
+                if let Some(ref cache) = r.blob_cache {
+                    writeln!(f, "\n=== Blob cache ===")?;
+                    writeln!(f, "Hits: {}", cache.hits)?;
+                    writeln!(f, "Misses: {}", cache.misses)?;
+                    writeln!(f, "Entries: {}", cache.entries)?;
+                }
+                if let Some(ref subjects) = r.subject_normalization {
+                    writeln!(f, "\n=== Subject normalization ===")?;
+                    writeln!(f, "Prefixes stripped: {}", subjects.prefix_stripped)?;
+                    writeln!(f, "PR suffixes stripped: {}", subjects.pr_suffix_stripped)?;
+                }
+                if let Some(ref tags) = r.tag_name_references {
+                    writeln!(f, "\n=== Tag name references ===")?;
+                    writeln!(f, "Messages updated: {}", tags.messages_updated)?;
+                }
+                if let Some(ref roots) = r.root_files {
+                    writeln!(f, "\n=== Root files ===")?;
+                    writeln!(f, "Roots modified: {}", roots.roots_modified)?;
+                }
+                if let Some(ref namespaces) = r.ref_namespaces {
+                    writeln!(f, "\n=== Ref namespaces ===")?;
+                    writeln!(f, "Included: {}", namespaces.included.join(", "))?;
+                    for ns in &namespaces.excluded {
+                        match ns.flag {
+                            Some(flag) => writeln!(
+                                f,
+                                "Excluded: {} ({} ref(s); pass {} to rewrite)",
+                                ns.namespace, ns.refs, flag
+                            )?,
+                            None => writeln!(
+                                f,
+                                "Excluded: {} ({} ref(s); outside branches and tags)",
+                                ns.namespace, ns.refs
+                            )?,
+                        }
+                    }
+                }
+                if let Some(ref refs) = r.ref_stats {
+                    writeln!(f, "\n=== Commits per ref ===")?;
+                    let label = |r: &RefStatsReport| match &r.renamed_from {
+                        Some(old) => format!("{} ({})", r.name, old),
+                        None => r.name.clone(),
+                    };
+                    let width = refs.iter().map(|r| label(r).len()).max().unwrap_or(0);
+                    writeln!(
+                        f,
+                        "{:<width$}  {:>8}  {:>8}  {:>10}",
+                        "Ref", "Emitted", "Pruned", "Degenerate"
+                    )?;
+                    for r in refs {
+                        writeln!(
+                            f,
+                            "{:<width$}  {:>8}  {:>8}  {:>10}",
+                            label(r),
+                            r.commits_emitted,
+                            r.commits_pruned,
+                            r.merges_degenerated
+                        )?;
+                    }
+                }
+                if let Some(ref tags) = r.dropped_tags {
+                    writeln!(f, "\n=== Dropped tags ===")?;
+                    for tag in tags {
+                        writeln!(f, "{} (tagged {}, which was stripped)", tag.tag, tag.target)?;
+                    }
+                }
+                if let Some(ref tags) = r.pruned_tags {
+                    writeln!(f, "\n=== Tags on pruned commits ===")?;
+                    for tag in tags {
+                        match (tag.action, &tag.new_target) {
+                            ("retargeted", Some(ancestor)) => writeln!(
+                                f,
+                                "{}: moved from {} to ancestor {}",
+                                tag.tag, tag.commit, ancestor
+                            )?,
+                            ("kept-old", _) => writeln!(
+                                f,
+                                "{}: still points at unrewritten commit {}",
+                                tag.tag, tag.commit
+                            )?,
+                            _ => writeln!(f, "{}: deleted (tagged {})", tag.tag, tag.commit)?,
+                        }
+                    }
+                }
+                if let Some(ref pipeline) = r.pipeline {
+                    writeln!(f, "\n=== Import pipeline ===")?;
+                    writeln!(f, "Buffer budget: {} bytes", pipeline.buffer_bytes)?;
+                    writeln!(f, "Peak queued: {} bytes", pipeline.peak_queued_bytes)?;
+                }
+                if let Some(ref check) = r.post_check {
+                    writeln!(f, "\n=== Post-check ===")?;
+                    writeln!(
+                        f,
+                        "{}: {} in {} ms",
+                        check.check,
+                        if check.passed { "passed" } else { "FAILED" },
+                        check.elapsed_ms
+                    )?;
+                    if !check.output.is_empty() {
+                        writeln!(f, "{}", check.output)?;
+                    }
+                }
             } else {
                 writeln!(f, "No report data collected.")?;
             }
@@ -566,101 +1120,265 @@ pub fn finalize(
         }
     }
 
-    // Finalize HEAD: if HEAD points to a non-existent branch, try to remap;
-    // if detached or missing, prefer first updated branch or first existing branch.
-    // Get HEAD symbolic ref (if any)
-    let head_ref = Command::new("git")
-        .arg("-C")
-        .arg(&opts.target)
-        .arg("symbolic-ref")
-        .arg("-q")
-        .arg("HEAD")
-        .stdout(Stdio::piped())
-        .stderr(Stdio::null())
-        .output()?;
-    if !opts.dry_run {
-        let repo_refs_after = gitutil::get_all_refs(&opts.target)?;
-        if head_ref.status.success() {
-            let head = String::from_utf8_lossy(&head_ref.stdout).trim().to_string();
-            if !repo_refs_after.contains_key(&head) {
-                let mut updated_head: Option<String> = None;
-                if let Some((ref old, ref new_)) = opts.branch_rename {
-                    if let Some(tail) = head.strip_prefix("refs/heads/") {
-                        let tail_b = tail.as_bytes();
-                        if tail_b.starts_with(&old[..]) {
-                            let mut new_full = Vec::with_capacity(
-                                "refs/heads/".len()
-                                    + new_.len()
-                                    + (tail_b.len().saturating_sub(old.len())),
-                            );
-                            new_full.extend_from_slice(b"refs/heads/");
-                            new_full.extend_from_slice(new_);
-                            new_full.extend_from_slice(&tail_b[old.len()..]);
-                            let new_str = String::from_utf8_lossy(&new_full).to_string();
-                            if repo_refs_after.contains_key(&new_str) {
-                                updated_head = Some(new_str);
-                            }
-                        }
-                    }
+    if !opts.quiet {
+        diagnostics::info(
+            Phase::Finalize,
+            format!(
+                "New history written ({}). Debug files in {:?}",
+                env!("CARGO_PKG_VERSION"),
+                debug_dir
+            ),
+        );
+        if let Some((count, path)) = &path_events {
+            diagnostics::info(
+                Phase::Finalize,
+                format!("{} path event(s) written to {}", count, path.display()),
+            );
+        }
+    }
+    if let Some(refs_after) = &refs_after {
+        if let Err(e) = migrate::update_branch_tracking(opts, &refs, refs_after) {
+            diagnostics::warning(
+                Phase::Finalize,
+                format!("failed to update branch tracking: {}", e),
+            );
+        }
+    }
+    // Post-run remote cleanup (non-sensitive parity): remove origin
+    if let Err(e) = migrate::remove_origin_remote_if_applicable(opts) {
+        diagnostics::warning(
+            Phase::Finalize,
+            format!("failed to remove origin remote: {}", e),
+        );
+    }
+    match post_check_failure {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
+}
+
+/// `HEAD` of the target repository, as recorded in `head-map`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeadState {
+    Symbolic(String),
+    Detached(String),
+    Unset,
+}
+
+impl HeadState {
+    pub(crate) fn read(repo: &Path) -> io::Result<Self> {
+        let symref = Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args(["symbolic-ref", "-q", "HEAD"])
+            .output()?;
+        if symref.status.success() {
+            let target = String::from_utf8_lossy(&symref.stdout).trim().to_string();
+            return Ok(Self::Symbolic(target));
+        }
+        let oid = Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args(["rev-parse", "--verify", "-q", "HEAD"])
+            .output()?;
+        if oid.status.success() {
+            let oid = String::from_utf8_lossy(&oid.stdout).trim().to_string();
+            return Ok(Self::Detached(oid));
+        }
+        Ok(Self::Unset)
+    }
+}
+
+impl std::fmt::Display for HeadState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Symbolic(target) => write!(f, "ref: {target}"),
+            Self::Detached(oid) => f.write_str(oid),
+            Self::Unset => f.write_str("(unset)"),
+        }
+    }
+}
+
+/// Keeps long regex rules to one readable line in report.txt.
+fn truncate_pattern(pattern: &str) -> Cow<'_, str> {
+    const MAX_CHARS: usize = 60;
+    match pattern.char_indices().nth(MAX_CHARS) {
+        Some((end, _)) => Cow::Owned(format!("{}...", &pattern[..end])),
+        None => Cow::Borrowed(pattern),
+    }
+}
+
+fn write_path_collision_groups(f: &mut File, groups: &[PathCollisionGroup]) -> io::Result<()> {
+    if groups.is_empty() {
+        return Ok(());
+    }
+    writeln!(f, "\n=== Sanitized path collisions ===")?;
+    for group in groups {
+        writeln!(f, "{} <- {}", group.sanitized, group.originals.join(", "))?;
+    }
+    Ok(())
+}
+
+/// Where `HEAD` should point once the ref updates are applied, worked out
+/// from `refs_after`, the snapshot those updates will produce. The rules,
+/// which apply to bare repositories as well:
+/// - a symbolic `HEAD` whose branch still exists is left alone;
+/// - a renamed branch is followed to its new name, even when that name
+///   already existed (the renamed history wins, as it does for the ref);
+/// - a branch that was pruned entirely moves `HEAD` to the first updated
+///   (or first remaining) branch, with a warning naming it;
+/// - a detached `HEAD` is remapped through the commit-map, falling back to
+///   its nearest first-parent ancestor that survived.
+fn plan_head(
+    opts: &Options,
+    commit_map: &HashMap<Vec<u8>, Option<Vec<u8>>>,
+    updated_branch_refs: &BTreeSet<Vec<u8>>,
+    branches_before: &BTreeSet<String>,
+    before: &HeadState,
+    current: &HeadState,
+    refs_after: &RefSnapshot,
+) -> Result<HeadState> {
+    let after = match before {
+        HeadState::Symbolic(target) if refs_after.contains(target) => current.clone(),
+        HeadState::Symbolic(target) => {
+            if let Some(renamed) = renamed_branch(target, opts, refs_after) {
+                if branches_before.contains(&renamed) {
+                    diagnostics::warning(
+                        Phase::Finalize,
+                        format!(
+                            "HEAD branch {} was renamed onto existing branch {}; HEAD follows the renamed history",
+                            target, renamed
+                        ),
+                    );
                 }
-                let fallback = updated_head
-                    .or_else(|| {
-                        updated_branch_refs
-                            .iter()
-                            .next()
-                            .map(|b| String::from_utf8_lossy(b).to_string())
-                    })
-                    .or_else(|| {
-                        let mut branches: Vec<&String> = repo_refs_after
-                            .keys()
-                            .filter(|name| name.starts_with("refs/heads/"))
-                            .collect();
-                        branches.sort();
-                        branches.into_iter().next().cloned()
-                    });
-                if let Some(refstr) = fallback.filter(|s| !s.is_empty()) {
-                    let status = Command::new("git")
-                        .arg("-C")
-                        .arg(&opts.target)
-                        .arg("symbolic-ref")
-                        .arg("HEAD")
-                        .arg(&refstr)
-                        .status()?;
-                    if !status.success() {
-                        eprintln!("warning: failed to update HEAD to {}: {}", refstr, status);
-                    }
+                HeadState::Symbolic(renamed)
+            } else if let Some(fallback) = fallback_branch(updated_branch_refs, refs_after) {
+                if branches_before.contains(target) && !opts.quiet {
+                    diagnostics::warning(
+                        Phase::Finalize,
+                        format!(
+                            "HEAD branch {} was pruned entirely; HEAD now points at {}",
+                            target, fallback
+                        )
+                        .yellow()
+                        .bold()
+                        .to_string(),
+                    );
                 }
+                HeadState::Symbolic(fallback)
+            } else {
+                current.clone()
             }
-        } else if let Some(first) = updated_branch_refs.iter().next() {
-            let refstr = String::from_utf8_lossy(first).to_string();
-            let status = Command::new("git")
+        }
+        HeadState::Detached(oid) => match surviving_commit(&opts.target, oid, commit_map)? {
+            Some(new_oid) => HeadState::Detached(new_oid),
+            None => current.clone(),
+        },
+        HeadState::Unset => match fallback_branch(updated_branch_refs, refs_after) {
+            Some(branch) => HeadState::Symbolic(branch),
+            None => current.clone(),
+        },
+    };
+    Ok(after)
+}
+
+/// Moves `HEAD` from `current` to `after` and records the move in
+/// `head-map`.
+fn move_head(
+    opts: &Options,
+    debug_dir: &Path,
+    before: &HeadState,
+    current: &HeadState,
+    after: &HeadState,
+) -> Result<()> {
+    if after != current {
+        let status = match after {
+            HeadState::Symbolic(target) => Command::new("git")
                 .arg("-C")
                 .arg(&opts.target)
-                .arg("symbolic-ref")
-                .arg("HEAD")
-                .arg(&refstr)
-                .status()?;
-            if !status.success() {
-                eprintln!("warning: failed to update HEAD to {}: {}", refstr, status);
-            }
+                .args(["symbolic-ref", "HEAD", target])
+                .status()?,
+            HeadState::Detached(oid) => Command::new("git")
+                .arg("-C")
+                .arg(&opts.target)
+                .args(["update-ref", "--no-deref", "HEAD", oid])
+                .status()?,
+            HeadState::Unset => unreachable!("HEAD is never moved to an unset state"),
+        };
+        if !status.success() {
+            diagnostics::warning(
+                Phase::Finalize,
+                format!("failed to update HEAD to {}: {}", after, status),
+            );
         }
     }
 
-    if !opts.quiet {
-        eprintln!(
-            "New history written ({}). Debug files in {:?}",
-            env!("CARGO_PKG_VERSION"),
-            debug_dir
-        );
+    let mut f = File::create(debug_dir.join("head-map"))?;
+    writeln!(f, "before {before}")?;
+    writeln!(f, "after {after}")?;
+    Ok(())
+}
+
+/// New name of `target` under `--branch-rename`, if that branch exists.
+fn renamed_branch(target: &str, opts: &Options, refs_after: &RefSnapshot) -> Option<String> {
+    let (old, new_) = opts.branch_rename.as_ref()?;
+    let tail = target.strip_prefix("refs/heads/")?.as_bytes();
+    let rest = tail.strip_prefix(&old[..])?;
+    let mut new_full = b"refs/heads/".to_vec();
+    new_full.extend_from_slice(new_);
+    new_full.extend_from_slice(rest);
+    let new_full = String::from_utf8_lossy(&new_full).to_string();
+    refs_after.contains(&new_full).then_some(new_full)
+}
+
+/// First updated branch that still exists, else the first remaining branch.
+fn fallback_branch(
+    updated_branch_refs: &BTreeSet<Vec<u8>>,
+    refs_after: &RefSnapshot,
+) -> Option<String> {
+    updated_branch_refs
+        .iter()
+        .map(|b| String::from_utf8_lossy(b).to_string())
+        .find(|b| refs_after.contains(b))
+        .or_else(|| {
+            refs_after
+                .refs
+                .keys()
+                .find(|name| name.starts_with("refs/heads/"))
+                .cloned()
+        })
+}
+
+/// Rewritten id of `oid`, or of its nearest first-parent ancestor that was
+/// kept. Commits absent from the commit-map were not rewritten and are
+/// returned unchanged.
+pub(crate) fn surviving_commit(
+    repo: &Path,
+    oid: &str,
+    commit_map: &HashMap<Vec<u8>, Option<Vec<u8>>>,
+) -> io::Result<Option<String>> {
+    let out = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["rev-list", "--first-parent", oid])
+        .output()?;
+    if !out.status.success() {
+        return Ok(None);
     }
-    // Post-run remote cleanup (non-sensitive parity): remove origin
-    if let Err(e) = migrate::remove_origin_remote_if_applicable(opts) {
-        eprintln!("warning: failed to remove origin remote: {}", e);
+    for commit in String::from_utf8_lossy(&out.stdout).lines() {
+        match commit_map.get(commit.as_bytes()) {
+            Some(Some(new_oid)) => return Ok(Some(String::from_utf8_lossy(new_oid).to_string())),
+            Some(None) => continue,
+            None => return Ok(Some(commit.to_string())),
+        }
     }
-    Ok(())
+    Ok(None)
 }
 
-fn run_repo_cleanup(target: &Path, aggressive: bool) {
+/// Expires reflogs and runs `git gc`. With `keep_unreachable`, refs are
+/// packed and gc runs with `--prune=never`, so objects only the old history
+/// used stay recoverable.
+fn run_repo_cleanup(target: &Path, aggressive: bool, keep_unreachable: bool) {
     let mut reflog = Command::new("git");
     reflog
         .arg("-C")
@@ -674,37 +1392,67 @@ fn run_repo_cleanup(target: &Path, aggressive: bool) {
     reflog.arg("--all");
     match reflog.status() {
         Ok(status) if !status.success() => {
-            eprintln!(
-                "warning: {} failed: {}",
-                "git reflog expire".cyan().bold(),
-                status
+            diagnostics::warning(
+                Phase::Finalize,
+                format!("{} failed: {}", "git reflog expire".cyan().bold(), status),
             );
         }
-        Err(e) => eprintln!(
-            "warning: failed to execute {}: {}",
-            "git reflog expire".cyan().bold(),
-            e
+        Err(e) => diagnostics::warning(
+            Phase::Finalize,
+            format!(
+                "failed to execute {}: {}",
+                "git reflog expire".cyan().bold(),
+                e
+            ),
         ),
         _ => {}
     }
 
+    if keep_unreachable {
+        let mut pack_refs = Command::new("git");
+        pack_refs
+            .arg("-C")
+            .arg(target)
+            .arg("pack-refs")
+            .arg("--all");
+        match pack_refs.status() {
+            Ok(status) if !status.success() => {
+                diagnostics::warning(
+                    Phase::Finalize,
+                    format!("{} failed: {}", "git pack-refs".cyan().bold(), status),
+                );
+            }
+            Err(e) => diagnostics::warning(
+                Phase::Finalize,
+                format!("failed to execute {}: {}", "git pack-refs".cyan().bold(), e),
+            ),
+            _ => {}
+        }
+    }
+
     let mut gc = Command::new("git");
     gc.arg("-C")
         .arg(target)
         .arg("gc")
-        .arg("--prune=now")
+        .arg(if keep_unreachable {
+            "--prune=never"
+        } else {
+            "--prune=now"
+        })
         .arg("--quiet");
     if aggressive {
         gc.arg("--aggressive");
     }
     match gc.status() {
         Ok(status) if !status.success() => {
-            eprintln!("warning: {} failed: {}", "git gc".cyan().bold(), status);
+            diagnostics::warning(
+                Phase::Finalize,
+                format!("{} failed: {}", "git gc".cyan().bold(), status),
+            );
         }
-        Err(e) => eprintln!(
-            "warning: failed to execute {}: {}",
-            "git gc".cyan().bold(),
-            e
+        Err(e) => diagnostics::warning(
+            Phase::Finalize,
+            format!("failed to execute {}: {}", "git gc".cyan().bold(), e),
         ),
         _ => {}
     }
@@ -733,9 +1481,12 @@ fn resolve_reset_target(
             if let Some(oid) = mark_to_id.get(&num) {
                 return Ok(Some(oid.clone()));
             }
-            eprintln!(
-                "warning: mark :{} not found in target marks; skipping ref update",
-                num
+            diagnostics::warning(
+                Phase::Finalize,
+                format!(
+                    "mark :{} not found in target marks; skipping ref update",
+                    num
+                ),
             );
             return Ok(None);
         }
@@ -769,9 +1520,12 @@ fn resolve_reset_target(
         }
         return Ok(Some(oid.into_bytes()));
     }
-    eprintln!(
-        "warning: could not resolve '{}' for ref update: {}",
-        spec, output.status,
+    diagnostics::warning(
+        Phase::Finalize,
+        format!(
+            "could not resolve '{}' for ref update: {}",
+            spec, output.status,
+        ),
     );
     Ok(None)
 }
@@ -779,6 +1533,7 @@ fn resolve_reset_target(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::process::Stdio;
     use tempfile::TempDir;
 
     struct BrokenPipeWriter;
@@ -826,6 +1581,52 @@ mod tests {
         dir
     }
 
+    #[test]
+    fn head_on_pruned_branch_falls_back_to_updated_branch() {
+        let repo = init_repo();
+        assert!(run_git(repo.path(), &["branch", "keep"]).success());
+        assert!(run_git(repo.path(), &["checkout", "-q", "-b", "gone"]).success());
+        assert!(run_git(repo.path(), &["update-ref", "-d", "refs/heads/gone"]).success());
+        let debug_dir = tempfile::tempdir().expect("create debug dir");
+        let opts = Options {
+            source: repo.path().to_path_buf(),
+            target: repo.path().to_path_buf(),
+            quiet: true,
+            ..Options::default()
+        };
+        let branches_before: BTreeSet<String> = ["refs/heads/gone", "refs/heads/keep"]
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let updated = BTreeSet::from([b"refs/heads/keep".to_vec()]);
+
+        let before = HeadState::Symbolic("refs/heads/gone".to_string());
+        let current = HeadState::read(repo.path()).expect("read HEAD");
+        let refs_after = RefSnapshot::capture(repo.path()).expect("capture refs");
+        let after = plan_head(
+            &opts,
+            &HashMap::new(),
+            &updated,
+            &branches_before,
+            &before,
+            &current,
+            &refs_after,
+        )
+        .expect("plan HEAD");
+        move_head(&opts, debug_dir.path(), &before, &current, &after).expect("move HEAD");
+
+        assert_eq!(
+            git_output(repo.path(), &["symbolic-ref", "HEAD"]).trim(),
+            "refs/heads/keep"
+        );
+        let head_map =
+            std::fs::read_to_string(debug_dir.path().join("head-map")).expect("read head-map");
+        assert_eq!(
+            head_map,
+            "before ref: refs/heads/gone\nafter ref: refs/heads/keep\n"
+        );
+    }
+
     #[test]
     fn flush_lightweight_tag_resets_deduplicates_and_skips_annotated_tags() {
         let mut buffered = vec![
@@ -882,6 +1683,13 @@ mod tests {
         assert!(import_broken, "broken pipe should be tracked");
     }
 
+    #[test]
+    fn truncate_pattern_keeps_sixty_chars() {
+        let long = "x".repeat(61);
+        assert_eq!(truncate_pattern(&long), format!("{}...", "x".repeat(60)));
+        assert_eq!(truncate_pattern("ab\\d+"), "ab\\d+");
+    }
+
     #[test]
     fn resolve_reset_target_handles_mark_hex_and_empty_inputs() {
         let repo = init_repo();
@@ -942,12 +1750,14 @@ mod tests {
     #[test]
     fn run_repo_cleanup_tolerates_non_repo_and_repo_paths() {
         let non_repo = tempfile::tempdir().expect("create tempdir");
-        run_repo_cleanup(non_repo.path(), false);
-        run_repo_cleanup(non_repo.path(), true);
+        run_repo_cleanup(non_repo.path(), false, false);
+        run_repo_cleanup(non_repo.path(), true, false);
+        run_repo_cleanup(non_repo.path(), false, true);
 
         let repo = init_repo();
-        run_repo_cleanup(repo.path(), false);
-        run_repo_cleanup(repo.path(), true);
+        run_repo_cleanup(repo.path(), false, false);
+        run_repo_cleanup(repo.path(), true, false);
+        run_repo_cleanup(repo.path(), false, true);
     }
 
     #[test]
@@ -976,8 +1786,10 @@ mod tests {
 
         let report = ReportData {
             summary: Summary {
+                export_mode: "full",
                 blobs_stripped_by_size: 2,
                 blobs_stripped_by_sha: 1,
+                blobs_stripped_by_content: 0,
                 blobs_modified: 3,
             },
             statistics: Statistics {
@@ -989,8 +1801,28 @@ mod tests {
                 by_size: vec!["path/size.bin".to_string()],
                 by_sha: vec!["path/sha.bin".to_string()],
                 modified: vec!["path/modified.bin".to_string()],
+                modified_blobs: vec![ModifiedBlobSample {
+                    path: "path/modified.bin".to_string(),
+                    original_oid: Some("1".repeat(40)),
+                }],
+                by_content: Vec::new(),
             },
             windows_path: None,
+            identity_rewrites: None,
+            identity_overrides: None,
+            mode_normalizations: None,
+            eol_normalization: None,
+            regex_rules: None,
+            blob_cache: None,
+            subject_normalization: None,
+            tag_name_references: None,
+            root_files: None,
+            pipeline: None,
+            ref_namespaces: None,
+            ref_stats: None,
+            dropped_tags: None,
+            pruned_tags: None,
+            post_check: None,
             metadata: Metadata {
                 version: "0.2.0".to_string(),
                 timestamp: "1234567890".to_string(),
@@ -1015,13 +1847,19 @@ mod tests {
                 buffered_tag_resets: vec![(b"refs/tags/v1".to_vec(), b"from :1\n".to_vec())],
                 annotated_tag_refs: BTreeSet::new(),
                 updated_branch_refs: BTreeSet::new(),
+                branches_before: BTreeSet::new(),
+                head_before: HeadState::Unset,
                 branch_reset_targets: Vec::new(),
                 import_broken: false,
                 allow_flush_tag_resets: true,
+                modified_blob_oids: HashMap::new(),
+                migration_log: &MigrationLog::default(),
+                renumbered_marks: None,
+                path_events: None,
             },
             &mut filtered,
             Some(Box::new(Vec::<u8>::new())),
-            &mut fe,
+            Some(&mut fe),
             None,
             Some(report),
         )
@@ -1043,6 +1881,7 @@ mod tests {
         assert!(report_txt.contains("=== Summary ==="));
         assert!(report_txt.contains("Blobs stripped by size: 2"));
         assert!(report_txt.contains("=== Sample paths (modified) ==="));
+        assert!(report_txt.contains(&format!("path/modified.bin (original {})", "1".repeat(40))));
 
         let report_json = std::fs::read_to_string(debug_dir.path().join("report.json"))
             .expect("read report.json");
@@ -1098,13 +1937,19 @@ mod tests {
                 buffered_tag_resets: Vec::new(),
                 annotated_tag_refs: BTreeSet::new(),
                 updated_branch_refs: BTreeSet::new(),
+                branches_before: BTreeSet::new(),
+                head_before: HeadState::Unset,
                 branch_reset_targets: Vec::new(),
                 import_broken: false,
                 allow_flush_tag_resets: false,
+                modified_blob_oids: HashMap::new(),
+                migration_log: &MigrationLog::default(),
+                renumbered_marks: None,
+                path_events: None,
             },
             &mut filtered_out,
             None,
-            &mut fe,
+            Some(&mut fe),
             None,
             None,
         )
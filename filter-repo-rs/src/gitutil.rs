@@ -1,10 +1,12 @@
 use std::collections::{HashMap, HashSet};
-use std::io::{self, Read};
+use std::io::{self, BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 
 use colored::*;
 
+use crate::git_config::GitConfig;
+
 /// Default timeout for git commands (5 minutes).
 pub const DEFAULT_GIT_TIMEOUT_SECS: u64 = 300;
 
@@ -156,19 +158,31 @@ pub fn probe_git_capabilities() -> io::Result<GitCapabilities> {
 }
 
 pub fn git_dir(repo: &Path) -> io::Result<PathBuf> {
+    rev_parse_dir(repo, "--git-dir")
+}
+
+/// The directory holding the object store and shared refs. For a linked
+/// worktree this is the main repository's git dir, not
+/// `.git/worktrees/<name>`.
+pub fn git_common_dir(repo: &Path) -> io::Result<PathBuf> {
+    rev_parse_dir(repo, "--git-common-dir")
+}
+
+fn rev_parse_dir(repo: &Path, flag: &str) -> io::Result<PathBuf> {
     let out = Command::new("git")
         .arg("-C")
         .arg(repo)
         .arg("rev-parse")
-        .arg("--git-dir")
+        .arg(flag)
         .stdout(Stdio::piped())
         .stderr(Stdio::inherit())
         .output()?;
     if !out.status.success() {
         return Err(io::Error::other(format!(
-            "{} -C {:?} rev-parse --git-dir failed",
+            "{} -C {:?} rev-parse {} failed",
             "git".cyan().bold(),
-            repo
+            repo,
+            flag
         )));
     }
     let s = String::from_utf8_lossy(&out.stdout).trim().to_string();
@@ -181,6 +195,118 @@ pub fn git_dir(repo: &Path) -> io::Result<PathBuf> {
     }
 }
 
+/// Whether `repo` was created by `git worktree add`, as opposed to being the
+/// main worktree or a bare repository.
+pub fn is_linked_worktree(repo: &Path) -> io::Result<bool> {
+    let git_dir = git_dir(repo)?.canonicalize()?;
+    let common_dir = git_common_dir(repo)?.canonicalize()?;
+    Ok(git_dir != common_dir)
+}
+
+/// Object directories borrowed through `objects/info/alternates`, followed
+/// transitively. Relative entries resolve against the objects directory
+/// that lists them; missing directories are skipped, as git does.
+pub fn alternate_object_dirs(repo: &Path) -> io::Result<Vec<PathBuf>> {
+    let objects = git_common_dir(repo)?.join("objects");
+    let mut seen = vec![objects.canonicalize().unwrap_or_else(|_| objects.clone())];
+    let mut alternates = Vec::new();
+    let mut pending = vec![objects];
+    while let Some(dir) = pending.pop() {
+        let listing = match std::fs::read_to_string(dir.join("info").join("alternates")) {
+            Ok(listing) => listing,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        for line in listing.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Ok(alternate) = dir.join(line).canonicalize() else {
+                continue;
+            };
+            if seen.contains(&alternate) {
+                continue;
+            }
+            seen.push(alternate.clone());
+            alternates.push(alternate.clone());
+            pending.push(alternate);
+        }
+    }
+    Ok(alternates)
+}
+
+/// Upstream of a local branch as `(remote, merge_ref)`, read from
+/// `branch.<branch>.remote` and `branch.<branch>.merge`.
+///
+/// Returns `None` unless both keys are set.
+pub fn get_upstream_branch(repo: &Path, branch: &str) -> io::Result<Option<(String, String)>> {
+    let remote = GitConfig::get_string_config(repo, &format!("branch.{branch}.remote"))?;
+    let merge = GitConfig::get_string_config(repo, &format!("branch.{branch}.merge"))?;
+    Ok(remote.zip(merge))
+}
+
+/// Upstream (`remote`, `merge`) of every branch that has both keys set,
+/// keyed by branch name.
+///
+/// Reads all `branch.*` sections with one `git config` call, so renaming
+/// many branches does not cost two processes per branch.
+pub fn get_upstream_branches(repo: &Path) -> io::Result<HashMap<String, (String, String)>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args([
+            "config",
+            "-z",
+            "--get-regexp",
+            r"^branch\..*\.(remote|merge)$",
+        ])
+        .stderr(Stdio::piped())
+        .output()?;
+    // Status 1 means no key matched.
+    if !output.status.success() && output.status.code() != Some(1) {
+        return Err(io::Error::other(format!(
+            "failed to read branch upstreams: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(parse_upstream_branches(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Parses `git config -z --get-regexp` output: `key\nvalue\0` per entry.
+fn parse_upstream_branches(output: &str) -> HashMap<String, (String, String)> {
+    let mut remotes = HashMap::new();
+    let mut merges = HashMap::new();
+    for entry in output.split('\0') {
+        let Some((key, value)) = entry.split_once('\n') else {
+            continue;
+        };
+        // Branch names may contain dots; the variable is the last component.
+        let Some((branch, variable)) = key
+            .strip_prefix("branch.")
+            .and_then(|rest| rest.rsplit_once('.'))
+        else {
+            continue;
+        };
+        let map = match variable {
+            "remote" => &mut remotes,
+            "merge" => &mut merges,
+            _ => continue,
+        };
+        // The last value wins, as for `git config --get`.
+        map.insert(branch.to_string(), value.to_string());
+    }
+    remotes
+        .into_iter()
+        .filter_map(|(branch, remote)| {
+            let merge = merges.remove(&branch)?;
+            Some((branch, (remote, merge)))
+        })
+        .collect()
+}
+
 /// Get all references in the repository
 ///
 /// Retrieves all Git references (branches, tags, etc.) and their corresponding
@@ -192,7 +318,8 @@ pub fn git_dir(repo: &Path) -> io::Result<PathBuf> {
 ///
 /// # Returns
 ///
-/// Returns a HashMap mapping reference names to their object hashes.
+/// Returns a HashMap mapping reference names to their object hashes. See
+/// [`crate::refs`] for ordered results, symbolic refs and object types.
 ///
 /// # Examples
 ///
@@ -206,36 +333,162 @@ pub fn git_dir(repo: &Path) -> io::Result<PathBuf> {
 /// }
 /// ```
 pub fn get_all_refs(repo_path: &Path) -> io::Result<HashMap<String, String>> {
-    let output = Command::new("git")
+    Ok(crate::refs::get_all_refs(repo_path)?.into_iter().collect())
+}
+
+/// Stream every reference and its object id to `callback`
+///
+/// Reads `git for-each-ref` line by line instead of collecting a map, so
+/// mirrors with millions of refs can be counted without holding them all in
+/// memory. An error returned by `callback` stops the walk and is returned.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use filter_repo_rs::gitutil;
+/// use std::path::Path;
+///
+/// let mut tags = 0;
+/// gitutil::stream_all_refs(Path::new("."), |refname, _oid| {
+///     if refname.starts_with("refs/tags/") {
+///         tags += 1;
+///     }
+///     Ok(())
+/// })
+/// .unwrap();
+/// ```
+pub fn stream_all_refs<F>(repo: &Path, callback: F) -> io::Result<()>
+where
+    F: FnMut(&str, &str) -> io::Result<()>,
+{
+    stream_refs(repo, &[], callback)
+}
+
+/// Like [`stream_all_refs`], but only for refs under the given prefixes
+/// (e.g. `refs/heads/`), which `for-each-ref` filters before printing.
+pub fn stream_refs<F>(repo: &Path, prefixes: &[&str], mut callback: F) -> io::Result<()>
+where
+    F: FnMut(&str, &str) -> io::Result<()>,
+{
+    let mut child = Command::new("git")
         .arg("-C")
-        .arg(repo_path)
+        .arg(repo)
         .arg("for-each-ref")
         .arg("--format=%(refname) %(objectname)")
+        .args(prefixes)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| io::Error::other("failed to capture for-each-ref stdout"))?;
+    let mut reader = BufReader::new(stdout);
+    let mut line = String::new();
+    let walked = loop {
+        line.clear();
+        match reader.read_line(&mut line) {
+            Ok(0) => break Ok(()),
+            Ok(_) => {}
+            Err(e) => break Err(e),
+        }
+        let Some((refname, oid)) = line.trim_end_matches('\n').rsplit_once(' ') else {
+            continue;
+        };
+        if let Err(e) = callback(refname, oid) {
+            break Err(e);
+        }
+    };
+    if walked.is_err() {
+        let _ = child.kill();
+    }
+    let status = child.wait()?;
+    walked?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "git -C {:?} for-each-ref failed",
+            repo
+        )));
+    }
+    Ok(())
+}
+
+/// A worktree entry reported by `git worktree list --porcelain`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorktreeStat {
+    pub path: PathBuf,
+    /// Checked-out branch ref, the commit id for a detached HEAD, or empty
+    /// for a bare entry.
+    pub head_ref: String,
+    pub is_bare: bool,
+}
+
+/// List the worktrees attached to the repository
+///
+/// Runs `git worktree list --porcelain`. The first entry is always the main
+/// worktree (or the bare repository itself); any further entries are linked
+/// worktrees created with `git worktree add`.
+///
+/// # Arguments
+///
+/// * `repo` - Path to the Git repository
+///
+/// # Returns
+///
+/// Returns the worktrees in the order git reports them.
+pub fn get_worktree_list(repo: &Path) -> io::Result<Vec<WorktreeStat>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .arg("worktree")
+        .arg("list")
+        .arg("--porcelain")
         .stdout(Stdio::piped())
         .stderr(Stdio::null())
         .output()?;
 
     if !output.status.success() {
         return Err(io::Error::other(format!(
-            "{} -C {:?} for-each-ref failed",
+            "{} -C {:?} worktree list failed",
             "git".cyan().bold(),
-            repo_path
+            repo
         )));
     }
 
-    let mut refs = HashMap::new();
-    let output_str = String::from_utf8_lossy(&output.stdout);
+    Ok(parse_worktree_porcelain(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
 
-    for line in output_str.lines() {
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() >= 2 {
-            let refname = parts[0].to_string();
-            let hash = parts[1].to_string();
-            refs.insert(refname, hash);
+pub(crate) fn parse_worktree_porcelain(output: &str) -> Vec<WorktreeStat> {
+    let mut worktrees = Vec::new();
+    let mut current: Option<WorktreeStat> = None;
+    for line in output.lines() {
+        if let Some(path) = line.strip_prefix("worktree ") {
+            worktrees.extend(current.take());
+            current = Some(WorktreeStat {
+                path: PathBuf::from(path),
+                head_ref: String::new(),
+                is_bare: false,
+            });
+            continue;
+        }
+        let Some(entry) = current.as_mut() else {
+            continue;
+        };
+        if line == "bare" {
+            entry.is_bare = true;
+        } else if let Some(branch) = line.strip_prefix("branch ") {
+            entry.head_ref = branch.to_string();
+        } else if let Some(oid) = line.strip_prefix("HEAD ") {
+            // A later "branch" line takes precedence; keep the oid for detached HEADs.
+            if entry.head_ref.is_empty() {
+                entry.head_ref = oid.to_string();
+            }
         }
     }
-
-    Ok(refs)
+    worktrees.extend(current);
+    worktrees
 }
 
 /// Check if the repository is bare
@@ -274,6 +527,58 @@ pub fn is_bare_repository(repo_path: &Path) -> io::Result<bool> {
     Ok(result == "true")
 }
 
+/// Check if the repository is a shallow clone
+///
+/// Uses `git rev-parse --is-shallow-repository`.
+pub fn is_shallow_repository(repo_path: &Path) -> io::Result<bool> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo_path)
+        .arg("rev-parse")
+        .arg("--is-shallow-repository")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{} -C {:?} rev-parse --is-shallow-repository failed",
+            "git".cyan().bold(),
+            repo_path
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).trim() == "true")
+}
+
+/// List every notes ref, not just the default `refs/notes/commits`
+///
+/// Uses `git for-each-ref --format=%(refname) refs/notes/`.
+pub fn list_notes_refs(repo: &Path) -> io::Result<Vec<String>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .arg("for-each-ref")
+        .arg("--format=%(refname)")
+        .arg("refs/notes/")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "{} -C {:?} for-each-ref refs/notes/ failed",
+            "git".cyan().bold(),
+            repo
+        )));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
 /// Get reflog entries for a specific reference
 ///
 /// Retrieves all reflog entries for a given reference using `git reflog show`.
@@ -597,6 +902,37 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_list_notes_refs_finds_every_namespace() -> io::Result<()> {
+        let temp_repo = create_test_repo()?;
+        create_commit(temp_repo.path())?;
+        assert!(list_notes_refs(temp_repo.path())?.is_empty());
+
+        for args in [
+            &["notes", "add", "-m", "default note"][..],
+            &[
+                "notes",
+                "--ref",
+                "refs/notes/review",
+                "add",
+                "-m",
+                "reviewed",
+            ],
+        ] {
+            let status = Command::new("git")
+                .args(args)
+                .current_dir(temp_repo.path())
+                .status()?;
+            assert!(status.success());
+        }
+
+        assert_eq!(
+            list_notes_refs(temp_repo.path())?,
+            vec!["refs/notes/commits", "refs/notes/review"]
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_get_all_refs_empty_repo() -> io::Result<()> {
         let temp_repo = create_test_repo()?;
@@ -623,6 +959,90 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn stream_all_refs_calls_back_once_per_ref() -> io::Result<()> {
+        let temp_repo = create_test_repo()?;
+        create_commit(temp_repo.path())?;
+        for args in [
+            &["branch", "feature"][..],
+            &["tag", "v1"][..],
+            &["update-ref", "refs/custom/x", "HEAD"][..],
+        ] {
+            Command::new("git")
+                .arg("-C")
+                .arg(temp_repo.path())
+                .args(args)
+                .output()?;
+        }
+
+        let mut seen = Vec::new();
+        stream_all_refs(temp_repo.path(), |refname, oid| {
+            seen.push((refname.to_string(), oid.to_string()));
+            Ok(())
+        })?;
+
+        let expected = get_all_refs(temp_repo.path())?;
+        assert_eq!(seen.len(), expected.len());
+        assert_eq!(seen.len(), 4);
+        for (refname, oid) in &seen {
+            assert_eq!(expected.get(refname), Some(oid));
+        }
+
+        let mut calls = 0;
+        let err = stream_all_refs(temp_repo.path(), |_, _| {
+            calls += 1;
+            Err(io::Error::other("stop"))
+        })
+        .expect_err("callback error should stop the walk");
+        assert_eq!(err.to_string(), "stop");
+        assert_eq!(calls, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn stream_refs_only_lists_the_given_namespaces() -> io::Result<()> {
+        let temp_repo = create_test_repo()?;
+        create_commit(temp_repo.path())?;
+        for args in [&["branch", "feature"][..], &["tag", "v1"][..]] {
+            Command::new("git")
+                .arg("-C")
+                .arg(temp_repo.path())
+                .args(args)
+                .output()?;
+        }
+
+        let mut seen = Vec::new();
+        stream_refs(temp_repo.path(), &["refs/tags/"], |refname, _| {
+            seen.push(refname.to_string());
+            Ok(())
+        })?;
+
+        assert_eq!(seen, ["refs/tags/v1"]);
+        Ok(())
+    }
+
+    #[test]
+    fn parse_upstream_branches_pairs_remote_and_merge() {
+        let output = "branch.main.remote\norigin\0\
+                      branch.main.merge\nrefs/heads/main\0\
+                      branch.release.1.0.remote\nupstream\0\
+                      branch.release.1.0.merge\nrefs/heads/release/1.0\0\
+                      branch.local-only.remote\n.\0\
+                      branch.main.rebase\ntrue\0";
+
+        let upstreams = parse_upstream_branches(output);
+
+        assert_eq!(upstreams.len(), 2, "{upstreams:?}");
+        assert_eq!(
+            upstreams["main"],
+            ("origin".to_string(), "refs/heads/main".to_string())
+        );
+        assert_eq!(
+            upstreams["release.1.0"],
+            ("upstream".to_string(), "refs/heads/release/1.0".to_string())
+        );
+    }
+
     #[test]
     fn test_is_bare_repository_false() -> io::Result<()> {
         let temp_repo = create_test_repo()?;
@@ -755,6 +1175,59 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_get_worktree_list_includes_linked_worktree() -> io::Result<()> {
+        let temp_repo = create_test_repo()?;
+        create_commit(temp_repo.path())?;
+
+        let worktree_root = TempDir::new()?;
+        let worktree_path = worktree_root.path().join("linked");
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(temp_repo.path())
+            .arg("worktree")
+            .arg("add")
+            .arg("-b")
+            .arg("side")
+            .arg(&worktree_path)
+            .output()?;
+        assert!(
+            output.status.success(),
+            "git worktree add failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let worktrees = get_worktree_list(temp_repo.path())?;
+
+        assert_eq!(worktrees.len(), 2);
+        assert!(!worktrees[0].is_bare);
+        assert!(worktrees[0].head_ref.starts_with("refs/heads/"));
+        assert_eq!(worktrees[1].head_ref, "refs/heads/side");
+        assert!(worktrees[1].path.ends_with("linked"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_worktree_porcelain_handles_bare_and_detached() {
+        let output = "worktree /srv/repo.git\nbare\n\n\
+worktree /srv/wt-a\nHEAD 1111111111111111111111111111111111111111\nbranch refs/heads/a\n\n\
+worktree /srv/wt-b\nHEAD 2222222222222222222222222222222222222222\ndetached\nlocked\n\n";
+
+        let worktrees = parse_worktree_porcelain(output);
+
+        assert_eq!(worktrees.len(), 3);
+        assert_eq!(worktrees[0].path, PathBuf::from("/srv/repo.git"));
+        assert!(worktrees[0].is_bare);
+        assert!(worktrees[0].head_ref.is_empty());
+        assert_eq!(worktrees[1].head_ref, "refs/heads/a");
+        assert!(!worktrees[1].is_bare);
+        assert_eq!(
+            worktrees[2].head_ref,
+            "2222222222222222222222222222222222222222"
+        );
+    }
+
     #[test]
     fn test_validate_git_dir_structure_bare() -> io::Result<()> {
         let temp_repo = create_bare_repo()?;
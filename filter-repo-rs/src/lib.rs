@@ -1,9 +1,15 @@
 pub mod analysis;
 mod backup;
+mod blob_cache;
+mod blob_callback;
+pub mod cancel;
+mod clone_to;
 #[doc(hidden)]
 pub mod commit;
 #[doc(hidden)]
 pub mod detect;
+pub mod diagnostics;
+mod eol;
 pub mod error;
 #[doc(hidden)]
 pub mod filechange;
@@ -11,16 +17,28 @@ mod finalize;
 pub mod git_config;
 pub mod gitutil;
 mod limits;
+mod log;
 #[doc(hidden)]
 pub mod message;
 mod migrate;
 pub mod opts;
+mod output_bundle;
 pub mod pathutil;
+mod pipeline;
 mod pipes;
+mod post_check;
+mod progress;
+mod ref_plan;
+pub mod refs;
+mod reproducible;
+mod rules;
 pub mod sanity;
+mod split;
 mod stream;
 mod tag;
+mod watch;
 
+pub use self::cancel::CancellationToken;
 pub use self::error::{FilterRepoError, Result as FilterRepoResult};
 pub use opts::{AnalyzeConfig, AnalyzeThresholds, Mode, Options};
 pub use pathutil::dequote_c_style_bytes;
@@ -28,11 +46,110 @@ pub use pathutil::dequote_c_style_bytes;
 pub use stream::{benchmark_rewrite_commit_identity_line, benchmark_rewrite_timestamp_line};
 
 fn validate_options(opts: &Options) -> FilterRepoResult<()> {
+    opts::reject_option_conflicts(opts)?;
     if !opts.detect_secrets && !opts.detect_patterns.is_empty() {
         return Err(FilterRepoError::invalid_options(
             "--detect-pattern requires --detect-secrets",
         ));
     }
+    if !opts.detect_secrets && opts.detect_patterns_file.is_some() {
+        return Err(FilterRepoError::invalid_options(
+            "--detect-patterns-file requires --detect-secrets",
+        ));
+    }
+    if opts.detect_no_builtin {
+        if !opts.detect_secrets {
+            return Err(FilterRepoError::invalid_options(
+                "--detect-no-builtin requires --detect-secrets",
+            ));
+        }
+        if opts.detect_patterns.is_empty() && opts.detect_patterns_file.is_none() {
+            return Err(FilterRepoError::invalid_options(
+                "--detect-no-builtin requires --detect-pattern or --detect-patterns-file",
+            ));
+        }
+    }
+    if opts.blob_cache_clear && opts.blob_cache_dir.is_none() {
+        return Err(FilterRepoError::invalid_options(
+            "--blob-cache-clear requires --blob-cache",
+        ));
+    }
+    if !opts.detect_secrets && opts.scan_messages {
+        return Err(FilterRepoError::invalid_options(
+            "--detect-scan-messages requires --detect-secrets",
+        ));
+    }
+    if !opts.detect_secrets && opts.detect_emit != opts::DetectEmit::Global {
+        return Err(FilterRepoError::invalid_options(
+            "--detect-emit requires --detect-secrets",
+        ));
+    }
+    if opts.reproducible && opts.date_order {
+        return Err(FilterRepoError::invalid_options(
+            "--reproducible pins fast-export to topological order; drop --date-order",
+        ));
+    }
+    if opts.also_tags && opts.strip_subject_prefixes.is_empty() && !opts.strip_pr_suffix {
+        return Err(FilterRepoError::invalid_options(
+            "--also-tags requires --strip-subject-prefix or --strip-pr-suffix",
+        ));
+    }
+
+    if opts.metadata_only {
+        let blob_options = [
+            (opts.replace_text_file.is_some(), "--replace-text"),
+            (opts.blob_callback_so.is_some(), "--blob-callback-so"),
+            (opts.has_blob_size_limits(), "--max-blob-size"),
+            (
+                opts.strip_blobs_with_ids.is_some(),
+                "--strip-blobs-with-ids",
+            ),
+            (
+                !opts.strip_blobs_matching.is_empty(),
+                "--strip-blobs-matching",
+            ),
+        ];
+        if let Some((_, flag)) = blob_options.iter().find(|(set, _)| *set) {
+            return Err(FilterRepoError::invalid_options(format!(
+                "--metadata-only cannot be combined with {flag}, which needs blob contents"
+            )));
+        }
+    }
+    if opts.no_data && opts.replace_text_file.is_some() {
+        return Err(FilterRepoError::invalid_options(
+            "--no-data cannot be combined with --replace-text, which rewrites blob contents",
+        ));
+    }
+    if opts.no_data && opts.blob_callback_so.is_some() {
+        return Err(FilterRepoError::invalid_options(
+            "--no-data cannot be combined with --blob-callback-so, which rewrites blob contents",
+        ));
+    }
+    if opts.blob_callback_so.is_some() && !cfg!(feature = "dynamic-callbacks") {
+        return Err(FilterRepoError::invalid_options(
+            "--blob-callback-so requires filter-repo-rs built with the dynamic-callbacks feature",
+        ));
+    }
+    if opts.redact_paths && opts.replace_text_file.is_none() {
+        return Err(FilterRepoError::invalid_options(
+            "--sensitive-redact-paths requires --replace-text (or --detect-secrets to find the values)",
+        ));
+    }
+    if opts.no_data && !opts.strip_blobs_matching.is_empty() {
+        return Err(FilterRepoError::invalid_options(
+            "--no-data cannot be combined with --strip-blobs-matching, which reads blob contents",
+        ));
+    }
+    if opts.pipeline_buffer_bytes == 0 {
+        return Err(FilterRepoError::invalid_options(
+            "--pipeline-buffer-bytes must be greater than zero",
+        ));
+    }
+    if opts.strip_blobs_matching_max_bytes == 0 {
+        return Err(FilterRepoError::invalid_options(
+            "--strip-blobs-matching-max-bytes must be greater than zero",
+        ));
+    }
 
     if let Some(max) = opts.max_blob_size {
         if max == 0 || max == usize::MAX {
@@ -41,6 +158,56 @@ fn validate_options(opts: &Options) -> FilterRepoResult<()> {
             ));
         }
     }
+    for (ext, limit) in &opts.max_blob_size_per_ext {
+        if *limit == 0 || *limit == usize::MAX {
+            return Err(FilterRepoError::invalid_options(format!(
+                "max-blob-size-per-ext limit for '{}' must be greater than zero and smaller than usize::MAX",
+                String::from_utf8_lossy(ext)
+            )));
+        }
+    }
+
+    if opts.replace_blobs_with_pointer && !opts.has_blob_size_limits() {
+        return Err(FilterRepoError::invalid_options(
+            "--replace-blobs-with-pointer requires --max-blob-size or --max-blob-size-per-ext",
+        ));
+    }
+
+    if opts.watch_interval.is_some() {
+        if opts.dry_run || opts.fe_stream_override.is_some() {
+            return Err(FilterRepoError::invalid_options(
+                "--watch-interval cannot be combined with --dry-run or --fe_stream_override",
+            ));
+        }
+        // Watched tips are tracked by ref name, so refs must keep their names.
+        if opts.branch_rename.is_some() || opts.tag_rename.is_some() {
+            return Err(FilterRepoError::invalid_options(
+                "--watch-interval cannot be combined with --branch-rename or --tag-rename",
+            ));
+        }
+    }
+
+    if opts.update_tag_name_references && opts.tag_rename.is_none() {
+        return Err(FilterRepoError::invalid_options(
+            "--update-tag-name-references requires --tag-rename",
+        ));
+    }
+
+    if !opts.split_to.is_empty() {
+        let mut names = std::collections::HashSet::new();
+        for name in opts
+            .split_to
+            .iter()
+            .map(|split| &split.name)
+            .chain(opts.split_rest.as_ref())
+        {
+            if !names.insert(name) {
+                return Err(FilterRepoError::invalid_options(format!(
+                    "split name '{name}' is used more than once"
+                )));
+            }
+        }
+    }
 
     const MAX_PATH_BYTES: usize = 4096;
     for entry in &opts.paths {
@@ -68,23 +235,103 @@ fn validate_options(opts: &Options) -> FilterRepoResult<()> {
 }
 
 pub fn run(opts: &Options) -> FilterRepoResult<()> {
+    diagnostics::set_format(opts.log_format);
+    if opts.help_patterns {
+        return detect::print_built_in_patterns();
+    }
     if opts.detect_secrets {
+        if opts.no_data {
+            return Err(FilterRepoError::invalid_options(
+                "--no-data cannot be combined with --detect-secrets, which scans blob contents",
+            ));
+        }
         return detect::run(opts);
     }
 
     match opts.mode {
         Mode::Filter => {
             validate_options(opts)?;
-            crate::sanity::preflight(opts)?;
-            if opts.backup {
-                if let Some(bundle_path) = crate::backup::create_backup(opts)? {
-                    println!("Backup bundle saved to {}", bundle_path.display());
-                }
+            let expanded;
+            let artifact_globs = opts.build_artifact_globs();
+            let opts = if artifact_globs.is_empty() {
+                opts
+            } else {
+                expanded = Options {
+                    strip_path_globs: artifact_globs
+                        .into_iter()
+                        .chain(opts.strip_path_globs.iter().cloned())
+                        .collect(),
+                    ..opts.clone()
+                };
+                &expanded
+            };
+            if opts.resume_ref_update {
+                return ref_plan::resume(opts);
+            }
+            crate::sanity::check_path_rules(opts);
+            if opts.watch_interval.is_some() {
+                return watch::run(opts);
             }
-            crate::migrate::fetch_all_refs_if_needed(opts)?;
-            crate::migrate::migrate_origin_to_heads(opts)?;
-            stream::run(opts)
+            if let Some(bundle) = &opts.output_bundle {
+                return output_bundle::run(opts, bundle);
+            }
+            if !opts.split_to.is_empty() {
+                return split::run(opts);
+            }
+            if let Some(clone) = &opts.clone_to {
+                return clone_to::run(opts, clone);
+            }
+            rewrite(opts)
         }
         Mode::Analyze => Ok(analysis::run(opts)?),
     }
 }
+
+/// One full pass of the filter pipeline; `--watch-interval` repeats it
+/// with a narrowed `refs` list.
+fn rewrite(opts: &Options) -> FilterRepoResult<()> {
+    let redacting;
+    let opts = if opts.redact_paths {
+        redacting = with_path_redactions(opts)?;
+        &redacting
+    } else {
+        opts
+    };
+    crate::sanity::preflight(opts)?;
+    if opts.backup {
+        if let Some(bundle_path) = crate::backup::create_backup(opts)? {
+            println!("Backup bundle saved to {}", bundle_path.display());
+        }
+    }
+    crate::migrate::fetch_all_refs_if_needed(opts)?;
+    // The source refs are read once; the origin migration applies its own
+    // moves to the snapshot instead of anyone enumerating them again.
+    let mut source_refs = refs::RefSnapshot::capture(&opts.source)?;
+    crate::migrate::migrate_origin_to_heads(opts, &mut source_refs)?;
+    cancel::check(opts.cancel.as_ref())?;
+    let mut migration_log = log::MigrationLog::open(opts.migration_log.as_deref())?;
+    if opts.reproducible {
+        migration_log = migration_log.pinned_to(reproducible::timestamp())?;
+    }
+    stream::run(opts, &migration_log, &source_refs)?;
+    Ok(migration_log.flush()?)
+}
+
+/// `--sensitive-redact-paths`: the literal `--replace-text` rules also apply
+/// to path names, so a file named after a secret is renamed in every commit.
+pub(crate) fn with_path_redactions(opts: &Options) -> FilterRepoResult<Options> {
+    let Some(path) = &opts.replace_text_file else {
+        return Ok(opts.clone());
+    };
+    let rules = rules::RuleFile::read(path, &rules::RuleLimits::from_options(opts))
+        .map_err(|e| std::io::Error::other(format!("failed to read --replace-text: {e}")))?;
+    Ok(Options {
+        path_redactions: rules
+            .rules
+            .into_iter()
+            .filter(|rule| rule.kind == rules::RuleKind::Literal)
+            .map(|rule| (rule.pattern, rule.replacement))
+            .collect(),
+        ..opts.clone()
+    })
+}
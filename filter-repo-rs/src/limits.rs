@@ -1,26 +1,79 @@
 use std::io;
 
 /// Maximum allowed data block size to avoid pathological allocations from
-/// malformed fast-export streams.
-pub const MAX_DATA_BLOCK_SIZE: usize = 500 * 1024 * 1024; // 500 MB
+/// malformed fast-export streams. Sizes are parsed as `u64` and checked
+/// against this limit before narrowing to `usize`, so the check behaves the
+/// same on 32-bit targets.
+pub const MAX_DATA_SIZE: u64 = 512 * 1024 * 1024; // 512 MiB
 
 pub fn parse_data_size_header(line: &[u8]) -> io::Result<usize> {
     let size_bytes = line
         .strip_prefix(b"data ")
         .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid data header"))?;
-    let n = std::str::from_utf8(size_bytes)
+    let digits = std::str::from_utf8(size_bytes)
         .ok()
         .map(|s| s.trim())
-        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|s| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit()))
         .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid data header"))?;
-    if n > MAX_DATA_BLOCK_SIZE {
+    // Only overflow can make an all-digit string fail to parse; treat it as
+    // oversized rather than malformed.
+    let n = digits.parse::<u64>().unwrap_or(u64::MAX);
+    if n > MAX_DATA_SIZE {
         return Err(io::Error::new(
             io::ErrorKind::InvalidData,
             format!(
-                "blob size {} exceeds maximum allowed size {}",
-                n, MAX_DATA_BLOCK_SIZE
+                "data size {} bytes exceeds maximum allowed size {}",
+                digits, MAX_DATA_SIZE
             ),
         ));
     }
-    Ok(n)
+    usize::try_from(n).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("data size {} bytes exceeds maximum allowed size", n),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_data_size_header_accepts_sizes_up_to_limit() {
+        assert_eq!(parse_data_size_header(b"data 0\n").unwrap(), 0);
+        assert_eq!(parse_data_size_header(b"data 42\n").unwrap(), 42);
+        assert_eq!(
+            parse_data_size_header(format!("data {}\n", MAX_DATA_SIZE).as_bytes()).unwrap() as u64,
+            MAX_DATA_SIZE
+        );
+    }
+
+    #[test]
+    fn parse_data_size_header_rejects_oversized_values_on_all_platforms() {
+        for size in [
+            (MAX_DATA_SIZE + 1).to_string(),
+            usize::MAX.to_string(),
+            u64::MAX.to_string(),
+            "184467440737095516160".to_string(),
+        ] {
+            let err = parse_data_size_header(format!("data {size}\n").as_bytes())
+                .expect_err("oversized data header must be rejected");
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+            let msg = err.to_string();
+            assert!(
+                msg.contains(&format!("data size {size} bytes"))
+                    && msg.contains("exceeds maximum allowed size"),
+                "unexpected error for {size}: {msg}"
+            );
+        }
+    }
+
+    #[test]
+    fn parse_data_size_header_rejects_malformed_values() {
+        for line in [&b"data \n"[..], b"data -1\n", b"data 12abc\n", b"blob 12\n"] {
+            let err = parse_data_size_header(line).expect_err("malformed header");
+            assert_eq!(err.to_string(), "invalid data header");
+        }
+    }
 }
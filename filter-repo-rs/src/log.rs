@@ -0,0 +1,221 @@
+//! Audit trail for `--write-migration-log PATH`.
+//!
+//! Every action taken during a rewrite is appended to the file as one JSON
+//! object per line, stamped with the RFC 3339 time it was recorded:
+//!
+//! ```text
+//! {"timestamp":"2024-05-01T12:00:00.123Z","type":"commit_kept","old_oid":"…","new_oid":"…"}
+//! ```
+//!
+//! Without a log path every method is a no-op.
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use serde::Serialize;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LogEvent<'a> {
+    CommitKept {
+        old_oid: &'a str,
+        new_oid: &'a str,
+    },
+    CommitPruned {
+        old_oid: &'a str,
+    },
+    /// `oid` is `None` for inline blobs, which have no original object.
+    BlobRewritten {
+        oid: Option<&'a str>,
+        path: &'a str,
+    },
+    RefRenamed {
+        old: &'a str,
+        new: &'a str,
+    },
+}
+
+#[derive(Serialize)]
+struct LogLine<'a> {
+    timestamp: String,
+    #[serde(flatten)]
+    event: LogEvent<'a>,
+}
+
+struct LogWriter {
+    out: BufWriter<File>,
+    // A rewritten blob is logged once per path, not once per commit touching it.
+    blobs_logged: HashSet<(Option<Vec<u8>>, Vec<u8>)>,
+    // Stamp for every line instead of the clock (--reproducible).
+    fixed_timestamp: Option<String>,
+}
+
+/// Writer for the migration log. Methods take `&self` so the log can be
+/// shared by the stream handlers while a rewrite is in progress.
+#[derive(Default)]
+pub(crate) struct MigrationLog {
+    inner: Option<RefCell<LogWriter>>,
+}
+
+impl MigrationLog {
+    /// Creates (or truncates) the log at `path`; `None` yields a no-op log.
+    pub(crate) fn open(path: Option<&Path>) -> io::Result<Self> {
+        let Some(path) = path else {
+            return Ok(Self::default());
+        };
+        let file = File::create(path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("failed to create migration log {}: {e}", path.display()),
+            )
+        })?;
+        Ok(Self {
+            inner: Some(RefCell::new(LogWriter {
+                out: BufWriter::new(file),
+                blobs_logged: HashSet::new(),
+                fixed_timestamp: None,
+            })),
+        })
+    }
+
+    /// Stamps every line with `time` rather than the time it was recorded.
+    pub(crate) fn pinned_to(self, time: OffsetDateTime) -> io::Result<Self> {
+        if let Some(inner) = &self.inner {
+            inner.borrow_mut().fixed_timestamp =
+                Some(time.format(&Rfc3339).map_err(io::Error::other)?);
+        }
+        Ok(self)
+    }
+
+    pub(crate) fn log_commit_kept(&self, old_oid: &[u8], new_oid: &[u8]) -> io::Result<()> {
+        let Some(inner) = &self.inner else {
+            return Ok(());
+        };
+        write_event(
+            &mut inner.borrow_mut(),
+            LogEvent::CommitKept {
+                old_oid: &String::from_utf8_lossy(old_oid),
+                new_oid: &String::from_utf8_lossy(new_oid),
+            },
+        )
+    }
+
+    pub(crate) fn log_commit_pruned(&self, old_oid: &[u8]) -> io::Result<()> {
+        let Some(inner) = &self.inner else {
+            return Ok(());
+        };
+        write_event(
+            &mut inner.borrow_mut(),
+            LogEvent::CommitPruned {
+                old_oid: &String::from_utf8_lossy(old_oid),
+            },
+        )
+    }
+
+    pub(crate) fn log_blob_rewritten(&self, oid: Option<&[u8]>, path: &[u8]) -> io::Result<()> {
+        let Some(inner) = &self.inner else {
+            return Ok(());
+        };
+        let mut inner = inner.borrow_mut();
+        if !inner
+            .blobs_logged
+            .insert((oid.map(<[u8]>::to_vec), path.to_vec()))
+        {
+            return Ok(());
+        }
+        let oid = oid.map(String::from_utf8_lossy);
+        write_event(
+            &mut inner,
+            LogEvent::BlobRewritten {
+                oid: oid.as_deref(),
+                path: &String::from_utf8_lossy(path),
+            },
+        )
+    }
+
+    pub(crate) fn log_ref_renamed(&self, old: &[u8], new: &[u8]) -> io::Result<()> {
+        let Some(inner) = &self.inner else {
+            return Ok(());
+        };
+        write_event(
+            &mut inner.borrow_mut(),
+            LogEvent::RefRenamed {
+                old: &String::from_utf8_lossy(old),
+                new: &String::from_utf8_lossy(new),
+            },
+        )
+    }
+
+    pub(crate) fn flush(&self) -> io::Result<()> {
+        match &self.inner {
+            Some(inner) => inner.borrow_mut().out.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+fn write_event(writer: &mut LogWriter, event: LogEvent<'_>) -> io::Result<()> {
+    let timestamp = match &writer.fixed_timestamp {
+        Some(fixed) => fixed.clone(),
+        None => OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .map_err(io::Error::other)?,
+    };
+    let line = LogLine { timestamp, event };
+    serde_json::to_writer(&mut writer.out, &line).map_err(io::Error::other)?;
+    writer.out.write_all(b"\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_are_tagged_json_lines_and_blobs_are_deduplicated() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let path = dir.path().join("migration.jsonl");
+        let log = MigrationLog::open(Some(&path)).unwrap();
+        log.log_commit_kept(b"aaaa", b"bbbb").unwrap();
+        log.log_commit_pruned(b"cccc").unwrap();
+        log.log_blob_rewritten(Some(b"dddd"), b"src/a.txt").unwrap();
+        log.log_blob_rewritten(Some(b"dddd"), b"src/a.txt").unwrap();
+        log.log_blob_rewritten(None, b"inline.txt").unwrap();
+        log.log_ref_renamed(b"refs/tags/v1", b"refs/tags/release-1")
+            .unwrap();
+        log.flush().unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let events: Vec<serde_json::Value> = content
+            .lines()
+            .map(|l| serde_json::from_str(l).expect("valid JSON line"))
+            .collect();
+        let types: Vec<&str> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+        assert_eq!(
+            types,
+            [
+                "commit_kept",
+                "commit_pruned",
+                "blob_rewritten",
+                "blob_rewritten",
+                "ref_renamed"
+            ]
+        );
+        assert!(events.iter().all(|e| e["timestamp"].is_string()));
+        assert_eq!(events[0]["new_oid"], "bbbb");
+        assert_eq!(events[2]["path"], "src/a.txt");
+        assert!(events[3]["oid"].is_null());
+    }
+
+    #[test]
+    fn disabled_log_ignores_events() {
+        let log = MigrationLog::open(None).unwrap();
+        assert!(log.inner.is_none());
+        log.log_commit_pruned(b"cccc").unwrap();
+        log.flush().unwrap();
+    }
+}
@@ -1,7 +1,28 @@
 use filter_repo_rs as fr;
+use fr::diagnostics::{self, Level, Phase, Record};
 use std::error::Error;
 use std::process;
 
+fn report_error(err: &fr::error::FilterRepoError) {
+    let mut record = Record::new(Level::Error, Phase::Exit, err.to_string());
+    let mut causes = Vec::new();
+    let mut source = err.source();
+    while let Some(cause) = source {
+        causes.push(cause.to_string());
+        source = cause.source();
+    }
+    if diagnostics::json_enabled() {
+        if !causes.is_empty() {
+            record = record.with("caused_by", causes.join("; "));
+        }
+    } else {
+        for cause in &causes {
+            record.message.push_str(&format!("\nCaused by: {cause}"));
+        }
+    }
+    record.emit();
+}
+
 fn main() {
     let opts = match fr::opts::parse_args() {
         Ok(opts) => opts,
@@ -9,21 +30,15 @@ fn main() {
             if let fr::error::FilterRepoError::Exit(code) = err {
                 process::exit(code);
             }
-            eprintln!("{err}");
-            let mut source = err.source();
-            while let Some(cause) = source {
-                eprintln!("Caused by: {cause}");
-                source = cause.source();
-            }
+            report_error(&err);
             process::exit(2);
         }
     };
     if let Err(err) = fr::run(&opts) {
-        eprintln!("{err}");
-        let mut source = err.source();
-        while let Some(cause) = source {
-            eprintln!("Caused by: {cause}");
-            source = cause.source();
+        report_error(&err);
+        // History was rewritten, but the result failed verification.
+        if let fr::error::FilterRepoError::PostCheck { .. } = err {
+            process::exit(3);
         }
         process::exit(1);
     }
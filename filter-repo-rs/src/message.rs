@@ -1,5 +1,5 @@
-use std::cell::RefCell;
-use std::collections::HashMap;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
 use std::io::{self, BufRead};
 #[cfg(test)]
 use std::io::{Read, Write};
@@ -7,6 +7,10 @@ use std::path::Path;
 
 use aho_corasick::AhoCorasick;
 use regex::bytes::{Captures, RegexBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::opts::{Options, SubjectPrefixPattern};
+use crate::rules::{Rule, RuleFile, RuleKind, RuleLimits};
 
 pub fn expand_bytes_template(tpl: &[u8], caps: &Captures) -> Vec<u8> {
     let mut out = Vec::with_capacity(tpl.len() + 16);
@@ -40,6 +44,29 @@ pub fn expand_bytes_template(tpl: &[u8], caps: &Captures) -> Vec<u8> {
                     }
                     continue;
                 }
+                // Named groups: `$name` or `${name}`. Unknown names are kept
+                // verbatim so templates without named groups are unaffected.
+                let (name, end) = if nb == b'{' {
+                    match tpl[i + 1..].iter().position(|&c| c == b'}') {
+                        Some(len) => (&tpl[i + 1..i + 1 + len], i + len + 2),
+                        None => (&tpl[i..i], i),
+                    }
+                } else {
+                    let len = tpl[i..]
+                        .iter()
+                        .take_while(|c| c.is_ascii_alphanumeric() || **c == b'_')
+                        .count();
+                    (&tpl[i..i + len], i + len)
+                };
+                if let Some(m) = std::str::from_utf8(name)
+                    .ok()
+                    .filter(|n| !n.is_empty())
+                    .and_then(|n| caps.name(n))
+                {
+                    out.extend_from_slice(m.as_bytes());
+                    i = end;
+                    continue;
+                }
                 out.push(b'$');
                 out.push(nb);
                 i += 1;
@@ -71,31 +98,39 @@ pub struct MessageReplacer {
 
 impl MessageReplacer {
     pub fn from_file(path: &std::path::Path) -> io::Result<Self> {
-        let content = std::fs::read(path)?;
-        let mut pairs = Vec::new();
-        for raw in content.split(|&b| b == b'\n') {
-            if raw.is_empty() {
-                continue;
-            }
-            if raw.starts_with(b"#") {
-                continue;
-            }
-            if let Some(pos) = find_subslice(raw, b"==>") {
-                let from = raw[..pos].to_vec();
-                let to = raw[pos + 3..].to_vec();
-                if !from.is_empty() {
-                    pairs.push((from, to));
-                }
-            } else {
-                let from = raw.to_vec();
-                if !from.is_empty() {
-                    pairs.push((from, b"***REMOVED***".to_vec()));
-                }
-            }
-        }
+        Ok(Self::from_message_rules(&RuleFile::read(
+            path,
+            &RuleLimits::default(),
+        )?))
+    }
+
+    /// Builds the replacer for a `--replace-message` or `--tag-message-file`
+    /// file, where every rule line but `path:` ones is also a literal: a
+    /// `glob:foo` line replaces the text "glob:foo".
+    pub(crate) fn from_message_rules(file: &RuleFile) -> Self {
+        let pairs = file
+            .rules
+            .iter()
+            .filter_map(|rule| Some((rule.spelled_pattern()?, rule.replacement.clone())))
+            .filter(|(pattern, _)| !pattern.is_empty())
+            .collect();
+        Self::from_pairs(pairs)
+    }
+
+    /// Builds the replacer from the global literal rules of `file`.
+    pub(crate) fn from_rules(file: &RuleFile) -> Self {
+        let pairs = file
+            .rules
+            .iter()
+            .filter(|rule| rule.kind == RuleKind::Literal)
+            .map(|rule| (rule.pattern.clone(), rule.replacement.clone()))
+            .collect();
+        Self::from_pairs(pairs)
+    }
 
+    fn from_pairs(pairs: Vec<(Vec<u8>, Vec<u8>)>) -> Self {
         if pairs.is_empty() {
-            return Ok(Self::default());
+            return Self::default();
         }
 
         let (ac, replacements) = if pairs.len() >= AHO_CORASICK_THRESHOLD {
@@ -107,11 +142,11 @@ impl MessageReplacer {
             (None, Vec::new())
         };
 
-        Ok(Self {
+        Self {
             pairs,
             ac,
             replacements,
-        })
+        }
     }
 
     pub fn apply(&self, data: Vec<u8>) -> Vec<u8> {
@@ -171,6 +206,83 @@ impl MessageReplacer {
     }
 }
 
+type ReplacementPair = (Vec<u8>, Vec<u8>);
+pub(crate) const DEFAULT_REDACTION: &str = "***REMOVED***";
+
+fn default_replacement() -> String {
+    DEFAULT_REDACTION.to_string()
+}
+
+/// JSON form of a `--replace-text` rules file, as written by
+/// `--detect-emit json`. Rules without `paths` apply everywhere; rules with
+/// `paths` only rewrite blobs stored at one of those paths.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ReplaceTextDocument {
+    #[serde(default)]
+    pub version: u32,
+    #[serde(default)]
+    pub rules: Vec<ReplaceTextRule>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReplaceTextRule {
+    pub value: String,
+    #[serde(default = "default_replacement")]
+    pub replacement: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+}
+
+/// Literal replacements that only apply to blobs stored at a given path,
+/// written as `path:<PATH>:FROM==>TO`. The path ends at the first `:`.
+#[derive(Clone, Debug, Default)]
+pub struct PathScopedReplacer {
+    rules: Vec<(Vec<u8>, MessageReplacer)>,
+}
+
+impl PathScopedReplacer {
+    pub fn from_file(path: &std::path::Path) -> io::Result<Option<Self>> {
+        Ok(Self::from_rules(&RuleFile::read(
+            path,
+            &RuleLimits::default(),
+        )?))
+    }
+
+    /// Groups the `path:` rules of `file` by path; `None` when there are none.
+    pub(crate) fn from_rules(file: &RuleFile) -> Option<Self> {
+        let mut by_path: BTreeMap<Vec<u8>, Vec<ReplacementPair>> = BTreeMap::new();
+        for rule in &file.rules {
+            if let RuleKind::Path(ref p) = rule.kind {
+                by_path
+                    .entry(p.clone())
+                    .or_default()
+                    .push((rule.pattern.clone(), rule.replacement.clone()));
+            }
+        }
+        if by_path.is_empty() {
+            return None;
+        }
+        let rules = by_path
+            .into_iter()
+            .map(|(p, pairs)| (p, MessageReplacer::from_pairs(pairs)))
+            .collect();
+        Some(Self { rules })
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &[u8]> {
+        self.rules.iter().map(|(p, _)| p.as_slice())
+    }
+
+    pub fn apply_for_path(&self, path: &[u8], data: Vec<u8>) -> (Vec<u8>, bool) {
+        match self.rules.iter().find(|(p, _)| p.as_slice() == path) {
+            Some((_, replacer)) => replacer.apply_with_change(data),
+            None => (data, false),
+        }
+    }
+}
+
 const MIN_SHORT_HASH_LEN: usize = 7;
 
 const NULL_OID: &[u8] = b"0000000000000000000000000000000000000000";
@@ -276,6 +388,11 @@ impl ShortHashMapper {
         resolved
     }
 
+    /// Full commit-map entries, `None` for pruned commits.
+    pub(crate) fn commit_map(&self) -> &HashMap<Vec<u8>, Option<Vec<u8>>> {
+        &self.lookup
+    }
+
     pub fn update_mapping(&mut self, old_full: &[u8], new_full: &[u8]) {
         if old_full.is_empty() || new_full.is_empty() {
             return;
@@ -312,6 +429,192 @@ impl ShortHashMapper {
     }
 }
 
+/// Commit subject cleanup from `--strip-subject-prefix` and `--strip-pr-suffix`.
+///
+/// Only the first line of a message is touched; the body is kept byte-for-byte.
+/// Counters use `Cell`s so the normalizer can be shared while streaming.
+#[derive(Debug, Default)]
+pub struct SubjectNormalizer {
+    prefixes: Vec<SubjectPrefixPattern>,
+    strip_pr_suffix: bool,
+    prefix_stripped: Cell<usize>,
+    pr_suffix_stripped: Cell<usize>,
+}
+
+impl SubjectNormalizer {
+    pub fn from_options(opts: &Options) -> Option<Self> {
+        if opts.strip_subject_prefixes.is_empty() && !opts.strip_pr_suffix {
+            return None;
+        }
+        Some(Self {
+            prefixes: opts.strip_subject_prefixes.clone(),
+            strip_pr_suffix: opts.strip_pr_suffix,
+            ..Self::default()
+        })
+    }
+
+    pub fn apply(&self, message: Vec<u8>) -> Vec<u8> {
+        let line_end = message
+            .iter()
+            .position(|&b| b == b'\n')
+            .unwrap_or(message.len());
+        // Keep a CRLF line ending out of the subject text.
+        let subject_end = if line_end > 0 && message[line_end - 1] == b'\r' {
+            line_end - 1
+        } else {
+            line_end
+        };
+        let mut subject = &message[..subject_end];
+        // Suffix first, so prefix stripping sees what will actually remain.
+        if self.strip_pr_suffix {
+            if let Some(rest) = strip_pr_suffix(subject) {
+                subject = rest;
+                self.pr_suffix_stripped
+                    .set(self.pr_suffix_stripped.get() + 1);
+            }
+        }
+        if let Some(rest) = self.strip_prefixes(subject) {
+            subject = rest;
+            self.prefix_stripped.set(self.prefix_stripped.get() + 1);
+        }
+        if subject.len() == subject_end {
+            return message;
+        }
+        let mut out = Vec::with_capacity(message.len());
+        out.extend_from_slice(subject);
+        out.extend_from_slice(&message[subject_end..]);
+        out
+    }
+
+    /// Removes matching leading `[TOKEN]` prefixes. The last prefix is kept
+    /// when nothing but whitespace would remain, so subjects never go empty.
+    fn strip_prefixes<'a>(&self, subject: &'a [u8]) -> Option<&'a [u8]> {
+        let mut cur = subject;
+        while let Some((token, rest)) = split_bracket_token(cur) {
+            if !self.prefixes.iter().any(|p| prefix_matches(p, token)) {
+                break;
+            }
+            let rest = rest.trim_ascii_start();
+            if rest.trim_ascii_end().is_empty() {
+                break;
+            }
+            cur = rest;
+        }
+        (cur.len() != subject.len()).then_some(cur)
+    }
+
+    /// Number of subjects that had at least one prefix removed.
+    pub fn prefix_stripped(&self) -> usize {
+        self.prefix_stripped.get()
+    }
+
+    /// Number of subjects that had a ` (#N)` suffix removed.
+    pub fn pr_suffix_stripped(&self) -> usize {
+        self.pr_suffix_stripped.get()
+    }
+}
+
+/// `--update-tag-name-references`: rewrites mentions of renamed tags in
+/// commit and tag messages.
+///
+/// It runs after the `--replace-message` rules, so a user rule that already
+/// rewrote a mention wins. A mention only matches as a whole tag name:
+/// `v-1.2.0` is left alone inside `dev-1.2.0` or `v-1.2.0.1`.
+#[derive(Debug)]
+pub struct TagNameReferences {
+    names: AhoCorasick,
+    replacements: Vec<Vec<u8>>,
+    messages_updated: Cell<usize>,
+}
+
+impl TagNameReferences {
+    /// Builds the matcher from old → new tag names; `None` when empty.
+    pub fn new(pairs: Vec<(Vec<u8>, Vec<u8>)>) -> io::Result<Option<Self>> {
+        if pairs.is_empty() {
+            return Ok(None);
+        }
+        let (old, replacements): (Vec<Vec<u8>>, Vec<Vec<u8>>) = pairs.into_iter().unzip();
+        let names = AhoCorasick::builder()
+            .match_kind(aho_corasick::MatchKind::LeftmostLongest)
+            .build(&old)
+            .map_err(|e| io::Error::other(format!("failed to build tag name matcher: {e}")))?;
+        Ok(Some(Self {
+            names,
+            replacements,
+            messages_updated: Cell::new(0),
+        }))
+    }
+
+    pub fn apply(&self, message: Vec<u8>) -> Vec<u8> {
+        let mut out = Vec::new();
+        let mut copied = 0;
+        for m in self.names.find_iter(&message) {
+            if !is_tag_name_boundary(&message, m.start(), m.end()) {
+                continue;
+            }
+            out.extend_from_slice(&message[copied..m.start()]);
+            out.extend_from_slice(&self.replacements[m.pattern().as_usize()]);
+            copied = m.end();
+        }
+        if copied == 0 {
+            return message;
+        }
+        out.extend_from_slice(&message[copied..]);
+        self.messages_updated.set(self.messages_updated.get() + 1);
+        out
+    }
+
+    /// Number of commit and tag messages that had a tag name rewritten.
+    pub fn messages_updated(&self) -> usize {
+        self.messages_updated.get()
+    }
+}
+
+fn is_tag_name_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'/' | b'.')
+}
+
+/// A trailing `.` still ends the name when it ends a sentence.
+fn is_tag_name_boundary(text: &[u8], start: usize, end: usize) -> bool {
+    if start > 0 && is_tag_name_byte(text[start - 1]) {
+        return false;
+    }
+    match text.get(end) {
+        None => true,
+        Some(b'.') => !text.get(end + 1).is_some_and(|&b| is_tag_name_byte(b)),
+        Some(&b) => !is_tag_name_byte(b),
+    }
+}
+
+fn split_bracket_token(subject: &[u8]) -> Option<(&[u8], &[u8])> {
+    let inner = subject.strip_prefix(b"[")?;
+    let close = inner.iter().position(|&b| b == b']')?;
+    Some((&inner[..close], &inner[close + 1..]))
+}
+
+fn prefix_matches(pattern: &SubjectPrefixPattern, token: &[u8]) -> bool {
+    match pattern {
+        SubjectPrefixPattern::Glob(glob) => crate::pathutil::glob_match_bytes(glob, token),
+        SubjectPrefixPattern::Regex(re) => re.is_match(token),
+    }
+}
+
+/// Returns `subject` without a trailing ` (#N)`, unless that would leave it blank.
+fn strip_pr_suffix(subject: &[u8]) -> Option<&[u8]> {
+    let inner = subject.strip_suffix(b")")?;
+    let start = find_last_subslice(inner, b" (#")?;
+    let digits = &inner[start + 3..];
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    let rest = &subject[..start];
+    (!rest.trim_ascii().is_empty()).then_some(rest)
+}
+
+fn find_last_subslice(h: &[u8], n: &[u8]) -> Option<usize> {
+    h.windows(n.len()).rposition(|w| w == n)
+}
+
 pub fn find_subslice(h: &[u8], n: &[u8]) -> Option<usize> {
     if n.is_empty() {
         return Some(0);
@@ -338,146 +641,186 @@ pub fn replace_all_bytes(h: &[u8], n: &[u8], r: &[u8]) -> Vec<u8> {
     out
 }
 
+const REGEX_SIZE_LIMIT: usize = 10 << 20;
+const DFA_SIZE_LIMIT: usize = 10 << 20;
+
+type RegexRule = (regex::bytes::Regex, Vec<u8>, bool);
+
+/// Compiles a `regex:` rule; `$` in the replacement expands capture groups.
+fn compile_regex_rule(rule: &Rule) -> io::Result<RegexRule> {
+    let pat_str = std::str::from_utf8(&rule.pattern).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("line {}: invalid UTF-8 in regex rule: {e}", rule.line),
+        )
+    })?;
+    let re = build_rule_regex(pat_str).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("line {}: invalid regex pattern: {e}", rule.line),
+        )
+    })?;
+    let has_dollar = rule.replacement.contains(&b'$');
+    Ok((re, rule.replacement.clone(), has_dollar))
+}
+
+/// Compiles a `glob:` rule (`*` and `?` wildcards, unanchored). The
+/// replacement is always literal.
+fn compile_glob_rule(rule: &Rule) -> io::Result<RegexRule> {
+    let glob_str = std::str::from_utf8(&rule.pattern).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("line {}: invalid UTF-8 in glob rule: {e}", rule.line),
+        )
+    })?;
+    // * -> .*, ? -> ., everything else regex-escaped. No anchors
+    let mut rx = String::with_capacity(glob_str.len() + 8);
+    for ch in glob_str.chars() {
+        match ch {
+            '*' => rx.push_str(".*"),
+            '?' => rx.push('.'),
+            '.' | '+' | '(' | ')' | '|' | '{' | '}' | '[' | ']' | '^' | '$' | '\\' => {
+                rx.push('\\');
+                rx.push(ch);
+            }
+            _ => rx.push(ch),
+        }
+    }
+    let re = build_rule_regex(&rx).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("line {}: invalid glob-derived regex: {e}", rule.line),
+        )
+    })?;
+    Ok((re, rule.replacement.clone(), false))
+}
+
+fn build_rule_regex(pattern: &str) -> Result<regex::bytes::Regex, regex::Error> {
+    RegexBuilder::new(pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(DFA_SIZE_LIMIT)
+        .build()
+}
+
+/// Applies `rules` in order, returning the result and how many matches each
+/// rule replaced.
+fn apply_regex_rules(
+    rules: &[(regex::bytes::Regex, Vec<u8>, bool)],
+    data: Vec<u8>,
+) -> (Vec<u8>, Vec<usize>) {
+    let mut cur = data;
+    let mut counts = vec![0usize; rules.len()];
+    for ((re, rep, has_dollar), count) in rules.iter().zip(counts.iter_mut()) {
+        let replaced = re.replace_all(&cur, |caps: &regex::bytes::Captures| {
+            *count += 1;
+            if *has_dollar {
+                expand_bytes_template(rep, caps)
+            } else {
+                rep.clone()
+            }
+        });
+        if *count > 0 {
+            cur = replaced.into_owned();
+        }
+    }
+    (cur, counts)
+}
+
+// Totals are accumulated behind shared references while the stream is
+// processed, like the identity rewrite counters.
+#[derive(Clone, Debug, Default)]
+struct RuleMatchTotals(RefCell<Vec<usize>>);
+
+impl RuleMatchTotals {
+    fn add(&self, counts: &[usize]) {
+        let mut totals = self.0.borrow_mut();
+        if totals.len() < counts.len() {
+            totals.resize(counts.len(), 0);
+        }
+        for (total, n) in totals.iter_mut().zip(counts) {
+            *total += n;
+        }
+    }
+
+    fn snapshot(&self, rules: &[(regex::bytes::Regex, Vec<u8>, bool)]) -> Vec<(String, usize)> {
+        let totals = self.0.borrow();
+        rules
+            .iter()
+            .enumerate()
+            .map(|(i, (re, _, _))| (re.as_str().to_string(), totals.get(i).copied().unwrap_or(0)))
+            .collect()
+    }
+}
+
 // Regex support for blob replacements reuses the same replacement file syntax,
 // where lines starting with "regex:" are treated as regex rules.
 pub mod blob_regex {
     use super::*;
-    use regex::bytes::{Captures, Regex, RegexBuilder};
-
-    const REGEX_SIZE_LIMIT: usize = 10 << 20;
-    const DFA_SIZE_LIMIT: usize = 10 << 20;
+    use regex::bytes::Regex;
 
     #[derive(Clone, Debug, Default)]
     pub struct RegexReplacer {
         pub rules: Vec<(Regex, Vec<u8>, bool)>,
+        match_totals: RuleMatchTotals,
     }
 
     impl RegexReplacer {
         pub fn from_file(path: &std::path::Path) -> io::Result<Option<Self>> {
-            let content = std::fs::read(path)?;
-            let mut rules: Vec<(Regex, Vec<u8>, bool)> = Vec::new();
-            for raw in content.split(|&b| b == b'\n') {
-                if raw.is_empty() {
-                    continue;
-                }
-                if raw.starts_with(b"#") {
-                    continue;
-                }
-                if let Some(rest) = raw.strip_prefix(b"regex:") {
-                    let (pat, rep) = if let Some(pos) = super::find_subslice(rest, b"==>") {
-                        (&rest[..pos], rest[pos + 3..].to_vec())
-                    } else {
-                        (rest, b"***REMOVED***".to_vec())
-                    };
-                    let pat_str = std::str::from_utf8(pat).map_err(|e| {
-                        io::Error::new(
-                            io::ErrorKind::InvalidInput,
-                            format!("invalid UTF-8 in regex rule: {e}"),
-                        )
-                    })?;
-                    let re = RegexBuilder::new(pat_str)
-                        .size_limit(REGEX_SIZE_LIMIT)
-                        .dfa_size_limit(DFA_SIZE_LIMIT)
-                        .build()
-                        .map_err(|e| {
-                            io::Error::new(
-                                io::ErrorKind::InvalidInput,
-                                format!("invalid regex pattern: {e}"),
-                            )
-                        })?;
-                    let has_dollar = rep.contains(&b'$');
-                    rules.push((re, rep, has_dollar));
-                    continue;
-                }
-                if let Some(rest) = raw.strip_prefix(b"glob:") {
-                    // Split at first ==> for replacement; default to ***REMOVED*** if missing
-                    let (pat, rep) = if let Some(pos) = super::find_subslice(rest, b"==>") {
-                        (&rest[..pos], rest[pos + 3..].to_vec())
-                    } else {
-                        (rest, b"***REMOVED***".to_vec())
-                    };
-                    let glob_str = std::str::from_utf8(pat).map_err(|e| {
-                        io::Error::new(
-                            io::ErrorKind::InvalidInput,
-                            format!("invalid UTF-8 in glob rule: {e}"),
-                        )
-                    })?;
-                    // Convert a simple glob pattern to a bytes regex:
-                    // * -> .*, ? -> ., everything else regex-escaped. No anchors
-                    let mut rx = String::with_capacity(glob_str.len() + 8);
-                    for ch in glob_str.chars() {
-                        match ch {
-                            '*' => rx.push_str(".*"),
-                            '?' => rx.push('.'),
-                            // escape regex meta characters
-                            '.' | '+' | '(' | ')' | '|' | '{' | '}' | '[' | ']' | '^' | '$'
-                            | '\\' => {
-                                rx.push('\\');
-                                rx.push(ch);
-                            }
-                            _ => rx.push(ch),
-                        }
-                    }
-                    let re = RegexBuilder::new(&rx)
-                        .size_limit(REGEX_SIZE_LIMIT)
-                        .dfa_size_limit(DFA_SIZE_LIMIT)
-                        .build()
-                        .map_err(|e| {
-                            io::Error::new(
-                                io::ErrorKind::InvalidInput,
-                                format!("invalid glob-derived regex: {e}"),
-                            )
-                        })?;
-                    // For glob-derived rules, treat '$' literally in replacement (no capture groups)
-                    let has_dollar = false;
-                    rules.push((re, rep, has_dollar));
-                    continue;
+            Self::from_rules(&RuleFile::read(path, &RuleLimits::default())?)
+        }
+
+        /// Builds the replacer from `(regex, replacement)` pairs without a
+        /// rules file; `None` when there are no pairs. Compiling can fail, so
+        /// this cannot be `FromIterator`.
+        #[allow(clippy::should_implement_trait)]
+        pub fn from_iter<I>(iter: I) -> io::Result<Option<Self>>
+        where
+            I: IntoIterator<Item = (String, String)>,
+        {
+            Self::from_rules(&RuleFile::from_regex_pairs(iter, &RuleLimits::default())?)
+        }
+
+        /// Compiles the `regex:` and `glob:` rules of `file`, in file order.
+        pub(crate) fn from_rules(file: &RuleFile) -> io::Result<Option<Self>> {
+            let mut rules = Vec::new();
+            for rule in &file.rules {
+                match rule.kind {
+                    RuleKind::Regex => rules.push(compile_regex_rule(rule)?),
+                    RuleKind::Glob => rules.push(compile_glob_rule(rule)?),
+                    RuleKind::Literal | RuleKind::Path(_) => {}
                 }
             }
             if rules.is_empty() {
                 Ok(None)
             } else {
-                Ok(Some(Self { rules }))
+                Ok(Some(Self {
+                    rules,
+                    match_totals: RuleMatchTotals::default(),
+                }))
             }
         }
 
         #[cfg(test)]
         pub fn apply_regex(&self, data: Vec<u8>) -> Vec<u8> {
-            let mut cur = data;
-            for (re, rep, has_dollar) in &self.rules {
-                if *has_dollar {
-                    let tpl = rep.clone();
-                    cur = re
-                        .replace_all(&cur, |caps: &Captures| expand_bytes_template(&tpl, caps))
-                        .into_owned();
-                } else {
-                    cur = re
-                        .replace_all(&cur, regex::bytes::NoExpand(rep))
-                        .into_owned();
-                }
-            }
-            cur
+            self.apply_regex_with_stats(data).0
         }
 
         pub fn apply_regex_with_change(&self, data: Vec<u8>) -> (Vec<u8>, bool) {
-            let mut cur = data;
-            let mut changed = false;
-            for (re, rep, has_dollar) in &self.rules {
-                if re.is_match(&cur) {
-                    changed = true;
-                }
-                if *has_dollar {
-                    let tpl = rep.clone();
-                    cur = re
-                        .replace_all(&cur, |caps: &Captures| expand_bytes_template(&tpl, caps))
-                        .into_owned();
-                } else {
-                    cur = re
-                        .replace_all(&cur, regex::bytes::NoExpand(rep))
-                        .into_owned();
-                }
-            }
-            (cur, changed)
+            let (out, counts) = self.apply_regex_with_stats(data);
+            (out, counts.iter().any(|&n| n > 0))
+        }
+
+        /// Like `apply_regex`, also returning the number of matches replaced
+        /// by each rule (indexed like `rules`).
+        pub fn apply_regex_with_stats(&self, data: Vec<u8>) -> (Vec<u8>, Vec<usize>) {
+            let (out, counts) = apply_regex_rules(&self.rules, data);
+            self.match_totals.add(&counts);
+            (out, counts)
+        }
+
+        /// Each rule's pattern with its match count over all calls so far.
+        pub fn rule_match_counts(&self) -> Vec<(String, usize)> {
+            self.match_totals.snapshot(&self.rules)
         }
     }
 }
@@ -487,75 +830,63 @@ pub mod blob_regex {
 // (?m) for multi-line when matching whole lines.
 pub mod msg_regex {
     use super::*;
-    use regex::bytes::{Captures, Regex, RegexBuilder};
-
-    const REGEX_SIZE_LIMIT: usize = 10 << 20;
-    const DFA_SIZE_LIMIT: usize = 10 << 20;
+    use regex::bytes::Regex;
 
     #[derive(Clone, Debug, Default)]
     pub struct RegexReplacer {
         pub rules: Vec<(Regex, Vec<u8>, bool)>,
+        match_totals: RuleMatchTotals,
     }
 
     impl RegexReplacer {
         pub fn from_file(path: &std::path::Path) -> io::Result<Option<Self>> {
-            let content = std::fs::read(path)?;
-            let mut rules: Vec<(Regex, Vec<u8>, bool)> = Vec::new();
-            for raw in content.split(|&b| b == b'\n') {
-                if raw.is_empty() {
-                    continue;
-                }
-                if raw.starts_with(b"#") {
-                    continue;
-                }
-                if let Some(rest) = raw.strip_prefix(b"regex:") {
-                    let (pat, rep) = if let Some(pos) = super::find_subslice(rest, b"==>") {
-                        (&rest[..pos], rest[pos + 3..].to_vec())
-                    } else {
-                        (rest, b"***REMOVED***".to_vec())
-                    };
-                    let pat_str = std::str::from_utf8(pat).map_err(|e| {
-                        io::Error::new(
-                            io::ErrorKind::InvalidInput,
-                            format!("invalid UTF-8 in regex rule: {e}"),
-                        )
-                    })?;
-                    let re = RegexBuilder::new(pat_str)
-                        .size_limit(REGEX_SIZE_LIMIT)
-                        .dfa_size_limit(DFA_SIZE_LIMIT)
-                        .build()
-                        .map_err(|e| {
-                            io::Error::new(
-                                io::ErrorKind::InvalidInput,
-                                format!("invalid regex pattern: {e}"),
-                            )
-                        })?;
-                    let has_dollar = rep.contains(&b'$');
-                    rules.push((re, rep, has_dollar));
-                }
-            }
+            Self::from_rules(&RuleFile::read(path, &RuleLimits::default())?)
+        }
+
+        /// Builds the replacer from `(regex, replacement)` pairs without a
+        /// rules file; `None` when there are no pairs. Compiling can fail, so
+        /// this cannot be `FromIterator`.
+        #[allow(clippy::should_implement_trait)]
+        pub fn from_iter<I>(iter: I) -> io::Result<Option<Self>>
+        where
+            I: IntoIterator<Item = (String, String)>,
+        {
+            Self::from_rules(&RuleFile::from_regex_pairs(iter, &RuleLimits::default())?)
+        }
+
+        /// Compiles the `regex:` rules of `file`; other rule kinds are ignored.
+        pub(crate) fn from_rules(file: &RuleFile) -> io::Result<Option<Self>> {
+            let rules = file
+                .rules
+                .iter()
+                .filter(|rule| rule.kind == RuleKind::Regex)
+                .map(compile_regex_rule)
+                .collect::<io::Result<Vec<_>>>()?;
             if rules.is_empty() {
                 Ok(None)
             } else {
-                Ok(Some(Self { rules }))
+                Ok(Some(Self {
+                    rules,
+                    match_totals: RuleMatchTotals::default(),
+                }))
             }
         }
 
         pub fn apply_regex(&self, data: Vec<u8>) -> Vec<u8> {
-            let mut cur = data;
-            for (re, rep, has_dollar) in &self.rules {
-                if *has_dollar {
-                    let tpl = rep.clone();
-                    cur = re
-                        .replace_all(&cur, |caps: &Captures| expand_bytes_template(&tpl, caps))
-                        .into_owned();
-                } else {
-                    cur = re
-                        .replace_all(&cur, regex::bytes::NoExpand(rep))
-                        .into_owned();
-                }
-            }
-            cur
+            self.apply_regex_with_stats(data).0
+        }
+
+        /// Like `apply_regex`, also returning the number of matches replaced
+        /// by each rule (indexed like `rules`).
+        pub fn apply_regex_with_stats(&self, data: Vec<u8>) -> (Vec<u8>, Vec<usize>) {
+            let (out, counts) = apply_regex_rules(&self.rules, data);
+            self.match_totals.add(&counts);
+            (out, counts)
+        }
+
+        /// Each rule's pattern with its match count over all calls so far.
+        pub fn rule_match_counts(&self) -> Vec<(String, usize)> {
+            self.match_totals.snapshot(&self.rules)
         }
     }
 }
@@ -590,6 +921,87 @@ mod tests {
         assert_eq!(out, b"BAR + ***REMOVED***".to_vec());
     }
 
+    #[test]
+    fn message_rules_keep_prefixed_lines_as_literals() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let path = dir.path().join("rules.txt");
+        write_file(
+            &path,
+            b"glob:*.tmp==>glob:*.bak
+regex:v1==>v2
+path:a.txt:X==>Y
+FOO==>BAR
+",
+        );
+
+        let replacer = MessageReplacer::from_file(&path).expect("parse rules");
+        assert_eq!(
+            replacer.pairs,
+            vec![
+                (b"glob:*.tmp".to_vec(), b"glob:*.bak".to_vec()),
+                (b"regex:v1".to_vec(), b"v2".to_vec()),
+                (b"FOO".to_vec(), b"BAR".to_vec()),
+            ]
+        );
+    }
+
+    #[test]
+    fn path_scoped_rules_are_split_from_global_rules() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let path = dir.path().join("rules.txt");
+        write_file(
+            &path,
+            b"path:config/prod.env:TOKEN==>***REMOVED***\npath:a.txt:BARE\nGLOBAL==>G\n",
+        );
+
+        let global = MessageReplacer::from_file(&path).expect("parse global rules");
+        assert_eq!(global.pairs, vec![(b"GLOBAL".to_vec(), b"G".to_vec())]);
+
+        let scoped = PathScopedReplacer::from_file(&path)
+            .expect("parse scoped rules")
+            .expect("scoped rules present");
+        let (out, changed) = scoped.apply_for_path(b"config/prod.env", b"k=TOKEN".to_vec());
+        assert!(changed);
+        assert_eq!(out, b"k=***REMOVED***".to_vec());
+        let (out, changed) = scoped.apply_for_path(b"docs/prod.env", b"k=TOKEN".to_vec());
+        assert!(!changed);
+        assert_eq!(out, b"k=TOKEN".to_vec());
+        let (out, _) = scoped.apply_for_path(b"a.txt", b"BARE".to_vec());
+        assert_eq!(out, b"***REMOVED***".to_vec());
+
+        write_file(&path, b"path:no-separator\n");
+        assert!(PathScopedReplacer::from_file(&path).is_err());
+    }
+
+    #[test]
+    fn json_rules_file_feeds_global_and_scoped_replacers() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let path = dir.path().join("rules.json");
+        write_file(
+            &path,
+            br#"{"version":1,"rules":[
+                {"value":"EVERYWHERE"},
+                {"value":"SCOPED","replacement":"X","paths":["a/b.env"]}
+            ]}"#,
+        );
+
+        let global = MessageReplacer::from_file(&path).expect("parse json rules");
+        assert_eq!(
+            global.pairs,
+            vec![(b"EVERYWHERE".to_vec(), b"***REMOVED***".to_vec())]
+        );
+        let scoped = PathScopedReplacer::from_file(&path)
+            .expect("parse json rules")
+            .expect("scoped rules present");
+        assert_eq!(scoped.paths().collect::<Vec<_>>(), vec![&b"a/b.env"[..]]);
+        let (out, changed) = scoped.apply_for_path(b"a/b.env", b"SCOPED".to_vec());
+        assert!(changed);
+        assert_eq!(out, b"X".to_vec());
+
+        write_file(&path, b"{ not json");
+        assert!(MessageReplacer::from_file(&path).is_err());
+    }
+
     #[test]
     fn replace_all_bytes_handles_empty_and_multiple_matches() {
         assert_eq!(replace_all_bytes(b"abcdef", b"", b"X"), b"abcdef".to_vec());
@@ -796,6 +1208,35 @@ glob:cash$==>$100\n",
         assert_eq!(out, b"ID:42:$:$x and bar$".to_vec());
     }
 
+    #[test]
+    fn regex_stats_count_matches_per_rule_and_accumulate() {
+        let dir = tempfile::tempdir().expect("create tempdir");
+        let rules = dir.path().join("stats-rules.txt");
+        write_file(&rules, b"regex:a(\\d)==>b$1\nregex:zzz==>y\nregex:b==>c\n");
+
+        let blob = blob_regex::RegexReplacer::from_file(&rules)
+            .expect("parse blob regex rules")
+            .expect("rules should exist");
+        let (out, counts) = blob.apply_regex_with_stats(b"a1 a2 x".to_vec());
+        assert_eq!(out, b"c1 c2 x".to_vec());
+        assert_eq!(counts, vec![2, 0, 2]);
+        blob.apply_regex_with_stats(b"a3".to_vec());
+        let totals: Vec<usize> = blob
+            .rule_match_counts()
+            .into_iter()
+            .map(|(_, n)| n)
+            .collect();
+        assert_eq!(totals, vec![3, 0, 3]);
+
+        let msg = msg_regex::RegexReplacer::from_file(&rules)
+            .expect("parse msg regex rules")
+            .expect("rules should exist");
+        let (out, counts) = msg.apply_regex_with_stats(b"zzz b".to_vec());
+        assert_eq!(out, b"y c".to_vec());
+        assert_eq!(counts, vec![0, 1, 1]);
+        assert_eq!(msg.rule_match_counts()[1], ("zzz".to_string(), 1));
+    }
+
     #[test]
     fn msg_regex_returns_none_without_regex_rules() {
         let dir = tempfile::tempdir().expect("create tempdir");
@@ -806,4 +1247,114 @@ glob:cash$==>$100\n",
             .expect("parse should succeed")
             .is_none());
     }
+
+    #[test]
+    fn regex_replacers_build_from_pattern_pairs() {
+        let pairs = || {
+            vec![
+                (r"(ID)-(\d+)".to_string(), "$1:$2".to_string()),
+                ("secret".to_string(), "***".to_string()),
+            ]
+        };
+
+        let msg = msg_regex::RegexReplacer::from_iter(pairs())
+            .expect("compile msg regex pairs")
+            .expect("rules should exist");
+        assert_eq!(
+            msg.apply_regex(b"ID-7 leaked secret".to_vec()),
+            b"ID:7 leaked ***".to_vec()
+        );
+        let blob = blob_regex::RegexReplacer::from_iter(pairs())
+            .expect("compile blob regex pairs")
+            .expect("rules should exist");
+        let (out, counts) = blob.apply_regex_with_stats(b"secret ID-1 ID-2".to_vec());
+        assert_eq!(out, b"*** ID:1 ID:2".to_vec());
+        assert_eq!(counts, vec![2, 1]);
+
+        assert!(msg_regex::RegexReplacer::from_iter(Vec::new())
+            .expect("empty input is fine")
+            .is_none());
+        let err = blob_regex::RegexReplacer::from_iter(vec![("(".to_string(), String::new())])
+            .expect_err("invalid pattern");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    fn subject_normalizer(prefixes: &[&str], strip_pr_suffix: bool) -> SubjectNormalizer {
+        let opts = Options {
+            strip_subject_prefixes: prefixes
+                .iter()
+                .map(|p| match p.strip_prefix("regex:") {
+                    Some(re) => SubjectPrefixPattern::Regex(
+                        regex::bytes::Regex::new(&format!("^(?:{re})$")).unwrap(),
+                    ),
+                    None => SubjectPrefixPattern::Glob(p.as_bytes().to_vec()),
+                })
+                .collect(),
+            strip_pr_suffix,
+            ..Options::default()
+        };
+        SubjectNormalizer::from_options(&opts).expect("normalizer configured")
+    }
+
+    #[test]
+    fn tag_name_references_match_whole_names_only() {
+        let refs = TagNameReferences::new(vec![
+            (b"v1".to_vec(), b"r1".to_vec()),
+            (b"v1.2".to_vec(), b"r1.2".to_vec()),
+        ])
+        .unwrap()
+        .expect("pairs configured");
+        let out = refs.apply(b"v1.2 and v1, not dev1 or v1.2.3 or v1_x. End v1.".to_vec());
+        assert_eq!(
+            out,
+            b"r1.2 and r1, not dev1 or v1.2.3 or v1_x. End r1.".to_vec()
+        );
+        assert_eq!(
+            refs.apply(b"no tags here".to_vec()),
+            b"no tags here".to_vec()
+        );
+        assert_eq!(refs.messages_updated(), 1);
+    }
+
+    #[test]
+    fn subject_normalizer_strips_multiple_prefixes_and_keeps_body() {
+        let n = subject_normalizer(&["WIP", "regex:JIRA-\\d+"], true);
+        let msg = b"[WIP] [JIRA-12]  Fix parser (#42)\n\nBody [WIP] (#1)\n".to_vec();
+        assert_eq!(n.apply(msg), b"Fix parser\n\nBody [WIP] (#1)\n".to_vec());
+        assert_eq!(n.prefix_stripped(), 1);
+        assert_eq!(n.pr_suffix_stripped(), 1);
+
+        let untouched = b"[feature] Fix parser (#x)\n".to_vec();
+        assert_eq!(n.apply(untouched.clone()), untouched);
+        assert_eq!(n.prefix_stripped(), 1);
+    }
+
+    #[test]
+    fn subject_normalizer_never_empties_subject() {
+        let n = subject_normalizer(&["*"], true);
+        assert_eq!(n.apply(b"[WIP]\n".to_vec()), b"[WIP]\n".to_vec());
+        assert_eq!(n.apply(b"[a] [b]\n".to_vec()), b"[b]\n".to_vec());
+        assert_eq!(n.apply(b" (#7)".to_vec()), b" (#7)".to_vec());
+        assert_eq!(n.apply(b"[WIP] (#7)\r\n".to_vec()), b"[WIP]\r\n".to_vec());
+    }
+
+    #[test]
+    fn subject_normalizer_is_utf8_safe() {
+        let n = subject_normalizer(&["修复*"], true);
+        let msg = "[修复-ü] Überarbeitung — 改进 (#9)\nnon-UTF-8 body \u{fffd}"
+            .as_bytes()
+            .iter()
+            .copied()
+            .chain([0xff, 0xfe])
+            .collect::<Vec<u8>>();
+        let out = n.apply(msg);
+        let expected = "Überarbeitung — 改进\nnon-UTF-8 body \u{fffd}"
+            .as_bytes()
+            .iter()
+            .copied()
+            .chain([0xff, 0xfe])
+            .collect::<Vec<u8>>();
+        assert_eq!(out, expected);
+        assert!(std::str::from_utf8(&out[..out.len() - 2]).is_ok());
+    }
 }
@@ -1,9 +1,11 @@
 use std::io::{self, Write};
 use std::process::{Command, Stdio};
 
+use crate::diagnostics::{self, Level, Phase, Record};
 use crate::git_config::GitConfig;
 use crate::gitutil;
-use crate::opts::Options;
+use crate::opts::{MigrateConflictPolicy, Options};
+use crate::refs::{RefSnapshot, RefTip};
 use colored::*;
 
 #[allow(dead_code)]
@@ -25,19 +27,28 @@ pub fn fetch_all_refs_if_needed(opts: &Options) -> io::Result<()> {
             ))
         })?;
     if !remotes.status.success() {
-        eprintln!(
-            "WARNING: --sensitive: {} command failed, skipping ref fetch",
-            "git remote".cyan().bold()
+        diagnostics::warning(
+            Phase::Fetch,
+            format!(
+                "--sensitive: {} command failed, skipping ref fetch",
+                "git remote".cyan().bold()
+            ),
         );
         return Ok(());
     }
     let r = String::from_utf8_lossy(&remotes.stdout);
     if !r.lines().any(|l| l.trim() == "origin") {
-        eprintln!("WARNING: --sensitive: no 'origin' remote found, skipping ref fetch");
+        diagnostics::warning(
+            Phase::Fetch,
+            "--sensitive: no 'origin' remote found, skipping ref fetch",
+        );
         return Ok(());
     }
     // Fetch all refs to ensure sensitive-history coverage
-    eprintln!("NOTICE: Fetching all refs from origin to ensure full sensitive-history coverage");
+    diagnostics::notice(
+        Phase::Fetch,
+        "Fetching all refs from origin to ensure full sensitive-history coverage",
+    );
     let status = Command::new("git")
         .arg("-C")
         .arg(&opts.source)
@@ -67,23 +78,26 @@ pub fn fetch_all_refs_if_needed(opts: &Options) -> io::Result<()> {
     Ok(())
 }
 
+/// Turns `refs/remotes/origin/*` into local branches. `snapshot` holds the
+/// source refs; the moves made here are applied to it as well, so later
+/// stages can keep using it without enumerating the refs again.
 #[allow(dead_code)]
-pub fn migrate_origin_to_heads(opts: &Options) -> io::Result<()> {
-    if opts.partial || opts.dry_run {
+pub fn migrate_origin_to_heads(opts: &Options, snapshot: &mut RefSnapshot) -> io::Result<()> {
+    if opts.partial {
         return Ok(());
     }
-    // List refs under refs/remotes/origin/*
-    let refs = match gitutil::get_all_refs(&opts.source) {
-        Ok(refs) => refs,
-        Err(_) => return Ok(()),
-    };
     let mut to_create: Vec<(String, String)> = Vec::new();
+    // (ref, new hash, current hash) for --migrate-conflict overwrite
+    let mut to_update: Vec<(String, String, String)> = Vec::new();
     let mut to_delete: Vec<(String, String)> = Vec::new();
-    for (refname, hash) in refs
+    // Local branches as they will read once the update succeeds
+    let mut moved: Vec<(String, RefTip)> = Vec::new();
+    for (refname, tip) in snapshot
+        .refs
         .iter()
         .filter(|(name, _)| name.starts_with("refs/remotes/origin/"))
     {
-        let hash = hash.clone();
+        let hash = tip.oid.clone();
         if refname == "refs/remotes/origin/HEAD" {
             to_delete.push((refname.clone(), hash));
             continue;
@@ -92,14 +106,68 @@ pub fn migrate_origin_to_heads(opts: &Options) -> io::Result<()> {
             .strip_prefix("refs/remotes/origin/")
             .unwrap_or(refname);
         let newref = format!("refs/heads/{}", suffix);
-        // Only create if newref does not exist
-        let exist = refs.contains_key(&newref);
-        if !exist {
-            to_create.push((newref, hash.clone()));
+        match snapshot.refs.get(&newref) {
+            None => {
+                moved.push((newref.clone(), tip.clone()));
+                to_create.push((newref, hash.clone()));
+            }
+            Some(existing) if existing.oid == hash => {}
+            Some(existing) => match opts.migrate_conflict {
+                MigrateConflictPolicy::Skip => {}
+                MigrateConflictPolicy::Overwrite => {
+                    moved.push((newref.clone(), tip.clone()));
+                    to_update.push((newref, hash.clone(), existing.oid.clone()));
+                }
+                MigrateConflictPolicy::Error => {
+                    return Err(io::Error::other(format!(
+                        "{newref} ({}) conflicts with {refname} ({hash}); \
+                         rerun with --migrate-conflict skip or overwrite",
+                        existing.oid
+                    )));
+                }
+            },
         }
         to_delete.push((refname.clone(), hash));
     }
-    if to_create.is_empty() && to_delete.is_empty() {
+    if to_create.is_empty() && to_update.is_empty() && to_delete.is_empty() {
+        return Ok(());
+    }
+    if opts.dry_run {
+        // Preview only: report the planned ref moves without touching the repository
+        for (r, h) in to_create.iter() {
+            Record::new(
+                Level::Info,
+                Phase::Migrate,
+                format!("WOULD: create {} ({})", r, h),
+            )
+            .with("action", "create")
+            .with("ref", r)
+            .with("oid", h)
+            .emit();
+        }
+        for (r, h, old) in to_update.iter() {
+            Record::new(
+                Level::Info,
+                Phase::Migrate,
+                format!("WOULD: update {} ({} -> {})", r, old, h),
+            )
+            .with("action", "update")
+            .with("ref", r)
+            .with("old_oid", old)
+            .with("oid", h)
+            .emit();
+        }
+        for (r, h) in to_delete.iter() {
+            Record::new(
+                Level::Info,
+                Phase::Migrate,
+                format!("WOULD: delete {} ({})", r, h),
+            )
+            .with("action", "delete")
+            .with("ref", r)
+            .with("oid", h)
+            .emit();
+        }
         return Ok(());
     }
     // Batch update-ref
@@ -117,6 +185,11 @@ pub fn migrate_origin_to_heads(opts: &Options) -> io::Result<()> {
                 io::Error::other(format!("failed to write to git update-ref stdin: {e}"))
             })?;
         }
+        for (r, h, old) in to_update.iter() {
+            writeln!(stdin, "update {} {} {}", r, h, old).map_err(|e| {
+                io::Error::other(format!("failed to write to git update-ref stdin: {e}"))
+            })?;
+        }
         for (r, h) in to_delete.iter() {
             writeln!(stdin, "delete {} {}", r, h).map_err(|e| {
                 io::Error::other(format!("failed to write to git update-ref stdin: {e}"))
@@ -131,6 +204,80 @@ pub fn migrate_origin_to_heads(opts: &Options) -> io::Result<()> {
             "git update-ref command failed with non-zero exit status",
         ));
     }
+    // update-ref --stdin is a single transaction, so every move happened.
+    for (refname, _) in &to_delete {
+        snapshot.refs.remove(refname);
+        snapshot.symbolic_refs.remove(refname);
+    }
+    snapshot.refs.extend(moved);
+    Ok(())
+}
+
+/// Carries upstream tracking (`branch.<old>.remote`/`.merge`) over to the
+/// new name of every branch renamed by the rewrite, and drops the old
+/// `branch.<old>` section once that branch is gone. `refs_after` is the
+/// target's refs once the rewrite's ref updates are applied.
+pub fn update_branch_tracking(
+    opts: &Options,
+    renames: &[(Vec<u8>, Vec<u8>)],
+    refs_after: &RefSnapshot,
+) -> io::Result<()> {
+    if opts.dry_run {
+        return Ok(());
+    }
+    let mut upstreams = None;
+    for (old, new_) in renames {
+        let (Some(old), Some(new_)) = (
+            old.strip_prefix(b"refs/heads/"),
+            new_.strip_prefix(b"refs/heads/"),
+        ) else {
+            continue;
+        };
+        if old == new_ {
+            continue;
+        }
+        let old = String::from_utf8_lossy(old).into_owned();
+        let new_ = String::from_utf8_lossy(new_).into_owned();
+        let upstreams = match &mut upstreams {
+            Some(upstreams) => upstreams,
+            None => upstreams.insert(gitutil::get_upstream_branches(&opts.target)?),
+        };
+        let Some((remote, merge)) = upstreams.remove(&old) else {
+            continue;
+        };
+        set_config(opts, &format!("branch.{new_}.remote"), &remote)?;
+        set_config(opts, &format!("branch.{new_}.merge"), &merge)?;
+        if !refs_after.contains(&format!("refs/heads/{old}")) {
+            // Exit status 128 only means the section is already gone.
+            let _ = Command::new("git")
+                .arg("-C")
+                .arg(&opts.target)
+                .args(["config", "--remove-section", &format!("branch.{old}")])
+                .stderr(Stdio::null())
+                .status();
+        }
+        if !opts.quiet {
+            diagnostics::info(
+                Phase::Finalize,
+                format!("Branch {new_} now tracks {remote} {merge} (was set on {old})"),
+            );
+        }
+    }
+    Ok(())
+}
+
+fn set_config(opts: &Options, key: &str, value: &str) -> io::Result<()> {
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(&opts.target)
+        .args(["config", key, value])
+        .status()
+        .map_err(|e| io::Error::other(format!("failed to run git config: {e}")))?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "git config {key} failed with {status}"
+        )));
+    }
     Ok(())
 }
 
@@ -158,9 +305,18 @@ pub fn remove_origin_remote_if_applicable(opts: &Options) -> io::Result<()> {
         .and_then(|value| value)
         .unwrap_or_default();
     if url.is_empty() {
-        eprintln!("NOTICE: Removing 'origin' remote; see docs if you want to push back there.");
+        diagnostics::notice(
+            Phase::Finalize,
+            "Removing 'origin' remote; see docs if you want to push back there.",
+        );
     } else {
-        eprintln!("NOTICE: Removing 'origin' remote (was: {})", url);
+        Record::new(
+            Level::Notice,
+            Phase::Finalize,
+            format!("Removing 'origin' remote (was: {})", url),
+        )
+        .with("url", &url)
+        .emit();
     }
     let status = Command::new("git")
         .arg("-C")
@@ -301,7 +457,13 @@ mod tests {
             source: repo.path().to_path_buf(),
             ..Options::default()
         };
-        migrate_origin_to_heads(&opts).expect("migration should succeed");
+        let mut snapshot = RefSnapshot::capture(repo.path()).expect("capture refs");
+        migrate_origin_to_heads(&opts, &mut snapshot).expect("migration should succeed");
+        assert_eq!(
+            snapshot,
+            RefSnapshot::capture(repo.path()).expect("capture refs"),
+            "snapshot should follow the migrated refs"
+        );
 
         let (feature_code, _, _) = git_output(repo.path(), &["show-ref", "refs/heads/feature"]);
         assert_eq!(feature_code, 0, "expected refs/heads/feature to be created");
@@ -313,6 +475,102 @@ mod tests {
         assert_ne!(head_code, 0, "origin/HEAD should be removed");
     }
 
+    #[test]
+    fn migrate_origin_to_heads_dry_run_leaves_refs_untouched() {
+        let repo = init_repo_with_commit();
+        let (_code, head, _err) = git_output(repo.path(), &["rev-parse", "HEAD"]);
+        let head = head.trim();
+        assert!(git_status(
+            repo.path(),
+            &["update-ref", "refs/remotes/origin/feature", head]
+        )
+        .success());
+
+        let opts = Options {
+            source: repo.path().to_path_buf(),
+            dry_run: true,
+            ..Options::default()
+        };
+        let mut snapshot = RefSnapshot::capture(repo.path()).expect("capture refs");
+        migrate_origin_to_heads(&opts, &mut snapshot).expect("dry-run migration should succeed");
+
+        let (feature_code, _, _) = git_output(repo.path(), &["show-ref", "refs/heads/feature"]);
+        assert_ne!(
+            feature_code, 0,
+            "dry-run must not create refs/heads/feature"
+        );
+        let (remote_code, _, _) =
+            git_output(repo.path(), &["show-ref", "refs/remotes/origin/feature"]);
+        assert_eq!(remote_code, 0, "dry-run must keep the remote-tracking ref");
+    }
+
+    /// `refs/heads/feature` at the first commit, `origin/feature` at a second.
+    fn init_conflicting_feature() -> (TempDir, String, String) {
+        let repo = init_repo_with_commit();
+        let (_code, local, _err) = git_output(repo.path(), &["rev-parse", "HEAD"]);
+        assert!(git_status(
+            repo.path(),
+            &["commit", "-q", "--allow-empty", "-m", "remote work"]
+        )
+        .success());
+        let (_code, remote, _err) = git_output(repo.path(), &["rev-parse", "HEAD"]);
+        let (local, remote) = (local.trim().to_string(), remote.trim().to_string());
+        assert!(git_status(repo.path(), &["update-ref", "refs/heads/feature", &local]).success());
+        assert!(git_status(
+            repo.path(),
+            &["update-ref", "refs/remotes/origin/feature", &remote]
+        )
+        .success());
+        (repo, local, remote)
+    }
+
+    fn feature_tips(repo: &std::path::Path) -> (String, i32) {
+        let (_c, local, _e) = git_output(repo, &["rev-parse", "refs/heads/feature"]);
+        let (remote_code, _, _) = git_output(repo, &["show-ref", "refs/remotes/origin/feature"]);
+        (local.trim().to_string(), remote_code)
+    }
+
+    #[test]
+    fn migrate_conflict_policies_resolve_existing_local_branch() {
+        for (policy, expect_local) in [
+            (MigrateConflictPolicy::Skip, true),
+            (MigrateConflictPolicy::Overwrite, false),
+        ] {
+            let (repo, local, remote) = init_conflicting_feature();
+            let opts = Options {
+                source: repo.path().to_path_buf(),
+                migrate_conflict: policy,
+                ..Options::default()
+            };
+            let mut snapshot = RefSnapshot::capture(repo.path()).expect("capture refs");
+            migrate_origin_to_heads(&opts, &mut snapshot).expect("migration should succeed");
+            let (tip, remote_code) = feature_tips(repo.path());
+            let expected = if expect_local { &local } else { &remote };
+            assert_eq!(&tip, expected, "{policy:?} left the wrong tip");
+            assert_ne!(remote_code, 0, "{policy:?} should drop origin/feature");
+        }
+    }
+
+    #[test]
+    fn migrate_conflict_error_names_ref_and_changes_nothing() {
+        let (repo, local, _remote) = init_conflicting_feature();
+        let opts = Options {
+            source: repo.path().to_path_buf(),
+            migrate_conflict: MigrateConflictPolicy::Error,
+            ..Options::default()
+        };
+        let mut snapshot = RefSnapshot::capture(repo.path()).expect("capture refs");
+        let err =
+            migrate_origin_to_heads(&opts, &mut snapshot).expect_err("conflict should be an error");
+        assert!(
+            err.to_string().contains("refs/heads/feature"),
+            "unexpected error: {err}"
+        );
+        let (tip, remote_code) = feature_tips(repo.path());
+        assert_eq!(tip, local);
+        assert_eq!(remote_code, 0, "origin/feature must be kept on error");
+    }
+
     #[test]
     fn migrate_origin_to_heads_returns_ok_when_source_is_not_git_repo() {
         let dir = tempfile::tempdir().expect("create tempdir");
@@ -320,7 +578,7 @@ mod tests {
             source: dir.path().to_path_buf(),
             ..Options::default()
         };
-        assert!(migrate_origin_to_heads(&opts).is_ok());
+        assert!(migrate_origin_to_heads(&opts, &mut RefSnapshot::default()).is_ok());
     }
 
     #[test]
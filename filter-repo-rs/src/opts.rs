@@ -4,12 +4,16 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::{Mutex, OnceLock};
 
-use regex::bytes::Regex;
+use regex::bytes::{Regex, RegexBuilder};
 use serde::Deserialize;
 
+use crate::cancel::CancellationToken;
+use crate::diagnostics::{self, Phase};
 use crate::error::FilterRepoError;
 use crate::gitutil::{self, GitCapabilities};
-use crate::pathutil::{normalize_cli_glob_str, normalize_cli_path_str, PathCompatPolicy};
+use crate::pathutil::{
+    normalize_cli_glob_str, normalize_cli_path_str, PathCompatPolicy, UnsafePathPolicy,
+};
 
 /// Stage-3 toggle: set to `false` to error out instead of accepting legacy cleanup syntax.
 const LEGACY_CLEANUP_SYNTAX_ALLOWED: bool = true;
@@ -31,6 +35,49 @@ pub enum Mode {
     Analyze,
 }
 
+/// Output format for `--detect-secrets` rule drafts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectEmit {
+    /// `VALUE==>***REMOVED***`, applied to every blob.
+    Global,
+    /// `path:PATH:VALUE==>***REMOVED***`, one rule per path the value was seen in.
+    Scoped,
+    /// JSON document accepted directly by `--replace-text`.
+    Json,
+}
+
+/// What `migrate_origin_to_heads` does when `refs/heads/<X>` already exists
+/// and points somewhere else than `refs/remotes/origin/<X>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MigrateConflictPolicy {
+    /// Keep the local branch; the remote-tracking ref is still removed.
+    #[default]
+    Skip,
+    /// Point the local branch at the remote-tracking tip.
+    Overwrite,
+    /// Stop before touching any ref.
+    Error,
+}
+
+/// Pattern for `--strip-subject-prefix`, matched against the text inside a
+/// leading `[...]` token of a commit subject.
+#[derive(Debug, Clone)]
+pub enum SubjectPrefixPattern {
+    Glob(Vec<u8>),
+    /// `regex:` patterns, anchored so they must match the whole token.
+    Regex(Regex),
+}
+
+/// How notices and warnings are written to stderr (`--log-format`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    /// `NOTICE: ...` / `WARNING: ...` lines for people.
+    #[default]
+    Text,
+    /// One `{level, phase, message, context}` object per line.
+    Json,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PruneMode {
     Always,
@@ -38,6 +85,109 @@ pub enum PruneMode {
     Never,
 }
 
+impl PruneMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PruneMode::Always => "always",
+            PruneMode::Auto => "auto",
+            PruneMode::Never => "never",
+        }
+    }
+}
+
+/// What happens to a tag whose commit was pruned from the rewritten history
+/// (`--pruned-tag`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PrunedTagPolicy {
+    /// Delete the tag ref and list it in the report.
+    #[default]
+    Delete,
+    /// Move the tag to the nearest surviving ancestor commit.
+    Ancestor,
+    /// Leave the tag on the original, unrewritten commit.
+    KeepOld,
+}
+
+impl PrunedTagPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PrunedTagPolicy::Delete => "delete",
+            PrunedTagPolicy::Ancestor => "ancestor",
+            PrunedTagPolicy::KeepOld => "keep-old",
+        }
+    }
+}
+
+/// Verification run on the rewritten repository (`--post-check`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostCheck {
+    /// Walk every reachable object with `git rev-list --objects --all`.
+    Connectivity,
+    /// `git fsck --no-dangling --connectivity-only`.
+    Fsck,
+    /// `git fsck --no-dangling --full`, which also verifies object contents.
+    FsckFull,
+}
+
+impl PostCheck {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PostCheck::Connectivity => "connectivity",
+            PostCheck::Fsck => "fsck",
+            PostCheck::FsckFull => "fsck-full",
+        }
+    }
+}
+
+/// Date written on lines stamped by `--override-committer`/`--override-tagger`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverrideDate {
+    /// Leave the original timestamp and timezone alone.
+    #[default]
+    Keep,
+    /// Use the time the run started, in UTC.
+    Now,
+}
+
+impl OverrideDate {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OverrideDate::Keep => "keep",
+            OverrideDate::Now => "now",
+        }
+    }
+}
+
+/// One `--split-to NAME=PATHSPEC[,PATHSPEC...]` output repository.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitTarget {
+    pub name: String,
+    pub paths: Vec<Vec<u8>>,
+}
+
+/// Line ending `--normalize-eol` converts text blobs to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EolTarget {
+    Lf,
+    Crlf,
+}
+
+impl EolTarget {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EolTarget::Lf => "lf",
+            EolTarget::Crlf => "crlf",
+        }
+    }
+}
+
+/// `--normalize-eol` settings; an empty `globs` list covers every path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EolNormalization {
+    pub target: EolTarget,
+    pub globs: Vec<Vec<u8>>,
+}
+
 #[derive(Debug, Clone)]
 pub struct AnalyzeThresholds {
     pub warn_total_bytes: u64,
@@ -49,7 +199,16 @@ pub struct AnalyzeThresholds {
     pub warn_path_length: usize,
     pub warn_duplicate_paths: usize,
     pub warn_commit_msg_bytes: usize,
+    // Commit subjects longer than this many characters are flagged
+    pub warn_subject_length: usize,
     pub warn_max_parents: usize,
+    pub warn_pack_count: usize,
+    // More pack files than this get a `git gc --aggressive` hint
+    pub warn_pack_fragmentation: usize,
+    // Repositories at least this large should have a commit-graph
+    pub warn_commit_graph_bytes: u64,
+    // Share of loose objects above which a `git gc` hint is shown
+    pub warn_loose_object_ratio: f64,
 }
 
 impl Default for AnalyzeThresholds {
@@ -64,7 +223,12 @@ impl Default for AnalyzeThresholds {
             warn_path_length: 200,
             warn_duplicate_paths: 1_000,
             warn_commit_msg_bytes: 10_000,
+            warn_subject_length: 150,
             warn_max_parents: 8,
+            warn_pack_count: 50,
+            warn_pack_fragmentation: 5,
+            warn_commit_graph_bytes: 100 * 1024 * 1024,
+            warn_loose_object_ratio: 0.1,
         }
     }
 }
@@ -72,16 +236,49 @@ impl Default for AnalyzeThresholds {
 #[derive(Debug, Clone)]
 pub struct AnalyzeConfig {
     pub json: bool,
+    /// Emit single-line JSON instead of pretty-printed JSON.
+    pub json_compact: bool,
     pub top: usize,
     pub thresholds: AnalyzeThresholds,
+    /// Identity patterns (case-insensitive substrings of `Name <email>`)
+    /// reported as suspect identities.
+    pub bad_identities: Vec<String>,
+    /// Blobs whose path matches one of these globs are left out of the
+    /// blob metrics (`--analyze-ignore-path`).
+    pub analyze_ignore_globs: Vec<Vec<u8>>,
+    /// Saved `--analyze-json` report to compare against (`--analyze-baseline`).
+    pub baseline: Option<PathBuf>,
+    /// Path components kept when rolling blob sizes up by prefix
+    /// (`--analyze-depth`); no rollup when unset.
+    pub depth: Option<usize>,
+    /// Paths longer than this are shortened in the middle in the human
+    /// tables (`--analyze-max-path-width`); JSON keeps them whole.
+    pub max_path_width: usize,
+    /// Walk history for every path a reported blob appeared at, keeping
+    /// this many (`--analyze-max-blob-paths`); the report still counts the
+    /// rest. Unset, each blob has the one path it was reached at.
+    pub max_blob_paths: Option<usize>,
+    /// Print tables as tab-separated values (`--analyze-plain`).
+    pub plain: bool,
 }
 
+/// Default `--analyze-max-path-width`.
+pub const DEFAULT_ANALYZE_MAX_PATH_WIDTH: usize = 60;
+
 impl Default for AnalyzeConfig {
     fn default() -> Self {
         Self {
             json: false,
+            json_compact: false,
             top: 10,
             thresholds: AnalyzeThresholds::default(),
+            bad_identities: Vec::new(),
+            analyze_ignore_globs: Vec::new(),
+            baseline: None,
+            depth: None,
+            max_path_width: DEFAULT_ANALYZE_MAX_PATH_WIDTH,
+            max_blob_paths: None,
+            plain: false,
         }
     }
 }
@@ -89,8 +286,10 @@ impl Default for AnalyzeConfig {
 #[derive(Debug, Default, Deserialize)]
 struct FileAnalyzeConfig {
     json: Option<bool>,
+    json_compact: Option<bool>,
     top: Option<usize>,
     thresholds: Option<AnalyzeThresholdOverrides>,
+    bad_identities: Option<Vec<String>>,
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -109,7 +308,12 @@ struct AnalyzeThresholdOverrides {
     warn_path_length: Option<usize>,
     warn_duplicate_paths: Option<usize>,
     warn_commit_msg_bytes: Option<usize>,
+    warn_subject_length: Option<usize>,
     warn_max_parents: Option<usize>,
+    warn_pack_count: Option<usize>,
+    warn_pack_fragmentation: Option<usize>,
+    warn_commit_graph_bytes: Option<u64>,
+    warn_loose_object_ratio: Option<f64>,
 }
 
 macro_rules! apply_threshold_field {
@@ -131,13 +335,19 @@ impl AnalyzeThresholdOverrides {
         apply_threshold_field!(thresholds, self, warn_path_length);
         apply_threshold_field!(thresholds, self, warn_duplicate_paths);
         apply_threshold_field!(thresholds, self, warn_commit_msg_bytes);
+        apply_threshold_field!(thresholds, self, warn_subject_length);
         apply_threshold_field!(thresholds, self, warn_max_parents);
+        apply_threshold_field!(thresholds, self, warn_pack_count);
+        apply_threshold_field!(thresholds, self, warn_pack_fragmentation);
+        apply_threshold_field!(thresholds, self, warn_commit_graph_bytes);
+        apply_threshold_field!(thresholds, self, warn_loose_object_ratio);
     }
 }
 
 #[derive(Default)]
 struct AnalyzeOverrides {
     json: Option<bool>,
+    json_compact: Option<bool>,
     top: Option<usize>,
     thresholds: AnalyzeThresholdOverrides,
 }
@@ -147,6 +357,9 @@ impl AnalyzeOverrides {
         if let Some(json) = self.json {
             analyze.json = json;
         }
+        if let Some(compact) = self.json_compact {
+            analyze.json_compact = compact;
+        }
         if let Some(top) = self.top {
             analyze.top = top;
         }
@@ -159,30 +372,109 @@ pub struct Options {
     pub source: PathBuf,
     pub target: PathBuf,
     pub refs: Vec<String>,
+    // Full ref names must match one of these globs (if any) and none of the exclusions
+    pub ref_filter_globs: Vec<Vec<u8>>,
+    pub ref_filter_globs_exclude: Vec<Vec<u8>>,
+    // `--all` skips refs/stash, refs/notes/* and refs/remotes/* unless opted back in
+    pub include_stash: bool,
+    pub include_notes: bool,
+    pub include_remotes: bool,
     pub date_order: bool,
     pub no_data: bool,
+    // --metadata-only: --no-data plus a guard against blob-content options
+    pub metadata_only: bool,
     pub quiet: bool,
+    pub log_format: LogFormat,
     pub reset: bool,
     pub replace_message_file: Option<PathBuf>,
+    // Separate rules for annotated tag messages (--tag-message-file)
+    pub replace_tag_message_file: Option<PathBuf>,
+    // Largest tag message emitted after rewriting (--max-tag-message-size);
+    // None means the stream's data limit
+    pub max_tag_message_bytes: Option<usize>,
+    // Cut longer tag messages instead of failing (--truncate-tag-messages)
+    pub truncate_tag_messages: bool,
     pub replace_text_file: Option<PathBuf>,
+    // Shared library whose filter_repo_blob_transform runs on every blob
+    // after --replace-text (--blob-callback-so; dynamic-callbacks feature)
+    pub blob_callback_so: Option<PathBuf>,
+    pub blob_cache_dir: Option<PathBuf>,
+    pub max_replace_rules: Option<usize>,
+    pub max_replace_rule_bytes: Option<usize>,
+    pub blob_cache_clear: bool,
     // Author/committer rewriting
     pub mailmap_file: Option<PathBuf>,
     pub author_rewrite_file: Option<PathBuf>,
     pub committer_rewrite_file: Option<PathBuf>,
     pub email_rewrite_file: Option<PathBuf>,
+    // Copy the committer date (timestamp and timezone) onto the author line
+    pub author_date_is_committer_date: bool,
+    // The other direction: copy the author date onto the committer line, as
+    // `git rebase --committer-date-is-author-date` does
+    pub committer_date_is_author_date: bool,
+    // `Name <email>` stamped on every committer/tagger line after all of the
+    // identity rules above
+    pub override_committer: Option<Vec<u8>>,
+    pub override_committer_date: OverrideDate,
+    pub override_tagger: Option<Vec<u8>>,
+    pub override_tagger_date: OverrideDate,
     pub paths: Vec<Vec<u8>>,
     pub invert_paths: bool,
     pub path_globs: Vec<Vec<u8>>,
     pub path_regexes: Vec<Regex>,
     pub path_renames: Vec<(Vec<u8>, Vec<u8>)>,
+    pub path_rename_regexes: Vec<(Regex, Vec<u8>)>,
+    // Warn about rename rules that match nothing at HEAD or shadow each other
+    pub validate_paths: bool,
+    // Fold a D/M pair that moves the same blob into a single R filechange
+    pub synthesize_renames: bool,
+    // Literal --replace-text rules applied to path names; filled in before
+    // the rewrite when --sensitive-redact-paths is set
+    pub path_redactions: Vec<(Vec<u8>, Vec<u8>)>,
+    // (glob, mode) pairs from --normalize-mode; first matching rule wins
+    pub mode_normalizations: Vec<(Vec<u8>, Vec<u8>)>,
+    // Line-ending conversion for text blobs, applied after --replace-text
+    pub normalize_eol: Option<EolNormalization>,
+    // (repository path, local file) pairs from --add-root-file
+    pub root_files: Vec<(Vec<u8>, PathBuf)>,
     pub tag_rename: Option<(Vec<u8>, Vec<u8>)>,
+    // Also rewrite renamed tag names mentioned in messages
+    pub update_tag_name_references: bool,
+    // Commit subject normalization, applied after --replace-message
+    pub strip_subject_prefixes: Vec<SubjectPrefixPattern>,
+    pub strip_pr_suffix: bool,
+    pub also_tags: bool,
     pub branch_rename: Option<(Vec<u8>, Vec<u8>)>,
     pub max_blob_size: Option<usize>,
+    // Per-extension size limits (lowercase extension without the dot)
+    pub max_blob_size_per_ext: Vec<(Vec<u8>, usize)>,
+    // Oversized blobs become a small text pointer instead of being deleted
+    pub replace_blobs_with_pointer: bool,
     pub strip_blobs_with_ids: Option<PathBuf>,
+    // Files whose path matches any of these globs are removed from history,
+    // independently of the path selection (--strip-blobs-with-path-matching)
+    pub strip_path_globs: Vec<Vec<u8>>,
+    // Shorthands that add well-known build artifact globs to strip_path_globs
+    pub strip_java_class_files: bool,
+    pub strip_node_modules: bool,
+    pub strip_python_cache: bool,
+    // Blobs whose content matches any of these are dropped (--strip-blobs-matching)
+    pub strip_blobs_matching: Vec<Regex>,
+    // Larger blobs are not scanned for --strip-blobs-matching
+    pub strip_blobs_matching_max_bytes: usize,
     pub write_report: bool,
     pub write_report_json: bool,
+    pub migration_log: Option<PathBuf>,
+    // Renumber marks and pin clock-derived values so identical runs match
+    pub reproducible: bool,
+    // Filtered stream bytes that may wait for fast-import before filtering pauses
+    pub pipeline_buffer_bytes: usize,
     pub path_compat_policy: PathCompatPolicy,
+    // Rewritten paths that are absolute or contain ./.. segments or NUL
+    pub unsafe_path_policy: UnsafePathPolicy,
     pub cleanup: CleanupMode,
+    // Cleanup packs refs and runs gc without pruning unreachable objects
+    pub keep_unreachable: bool,
     pub reencode: bool,
     pub reencode_requested: Option<bool>,
     pub quotepath: bool,
@@ -194,18 +486,56 @@ pub struct Options {
     pub dry_run: bool,
     pub detect_secrets: bool,
     pub detect_patterns: Vec<String>,
+    // Named rules for --detect-secrets, one per line (--detect-patterns-file)
+    pub detect_patterns_file: Option<PathBuf>,
+    // Scan with only --detect-pattern/--detect-patterns-file rules
+    pub detect_no_builtin: bool,
+    pub detect_emit: DetectEmit,
+    pub migrate_conflict: MigrateConflictPolicy,
+    // Print the built-in secret patterns and exit (--help-patterns)
+    pub help_patterns: bool,
+    pub scan_messages: bool,
+    // Rewrite only the selected refs: no origin migration or remote removal,
+    // and no reflog expiry or gc
     pub partial: bool,
+    // Seconds between incremental re-filtering cycles (--watch-interval)
+    pub watch_interval: Option<u64>,
     pub sensitive: bool,
+    // Also redact secrets found in path names (--sensitive-redact-paths)
+    pub redact_paths: bool,
     pub no_fetch: bool,
+    // Export original objects even when refs/replace/* exist
+    pub ignore_replace_refs: bool,
+    // Rewrite a shallow clone as-is
+    pub allow_shallow: bool,
     pub backup: bool,
     pub backup_path: Option<PathBuf>,
+    // Finish the ref updates recorded in .git/filter-repo/ref-plan.json
+    pub resume_ref_update: bool,
+    // Write the rewritten history to this bundle instead of updating refs
+    pub output_bundle: Option<PathBuf>,
+    // One bare repository per path set, written under split_output_dir
+    pub split_to: Vec<SplitTarget>,
+    pub split_output_dir: Option<PathBuf>,
+    // Repository for paths matching no --split-to set; dropped when None
+    pub split_rest: Option<String>,
+    // Mirror-clone the source here and filter the clone instead
+    pub clone_to: Option<PathBuf>,
+    // Let --clone-to hardlink objects instead of copying them
+    pub clone_local: bool,
+    // Verify the rewritten repository before reporting success
+    pub post_check: Option<PostCheck>,
     pub mode: Mode,
     pub analyze: AnalyzeConfig,
     pub debug_mode: bool,
     pub git_caps: GitCapabilities,
+    // Set by library callers to abort a run; never set from the command line
+    pub cancel: Option<CancellationToken>,
     // Pruning & merge behavior
     pub prune_empty: PruneMode,
     pub prune_degenerate: PruneMode,
+    // Tags whose commit was pruned: delete, move to an ancestor, or keep
+    pub pruned_tag: PrunedTagPolicy,
     pub no_ff: bool,
     pub date_shift: Option<i64>,
     pub date_set: Option<i64>,
@@ -217,29 +547,74 @@ impl Default for Options {
             source: PathBuf::from("."),
             target: PathBuf::from("."),
             refs: vec!["--all".to_string()],
+            ref_filter_globs: Vec::new(),
+            ref_filter_globs_exclude: Vec::new(),
+            include_stash: false,
+            include_notes: false,
+            include_remotes: false,
             date_order: false,
             no_data: false,
+            metadata_only: false,
             quiet: false,
+            log_format: LogFormat::Text,
             reset: true,
             replace_message_file: None,
+            replace_tag_message_file: None,
+            max_tag_message_bytes: None,
+            truncate_tag_messages: false,
             replace_text_file: None,
+            blob_callback_so: None,
+            blob_cache_dir: None,
+            max_replace_rules: None,
+            max_replace_rule_bytes: None,
+            blob_cache_clear: false,
             mailmap_file: None,
             author_rewrite_file: None,
             committer_rewrite_file: None,
             email_rewrite_file: None,
+            author_date_is_committer_date: false,
+            committer_date_is_author_date: false,
+            override_committer: None,
+            override_committer_date: OverrideDate::Keep,
+            override_tagger: None,
+            override_tagger_date: OverrideDate::Keep,
             paths: Vec::new(),
             invert_paths: false,
             path_globs: Vec::new(),
             path_regexes: Vec::new(),
             path_renames: Vec::new(),
+            path_rename_regexes: Vec::new(),
+            validate_paths: true,
+            synthesize_renames: false,
+            path_redactions: Vec::new(),
+            mode_normalizations: Vec::new(),
+            normalize_eol: None,
+            root_files: Vec::new(),
             tag_rename: None,
+            update_tag_name_references: false,
+            strip_subject_prefixes: Vec::new(),
+            strip_pr_suffix: false,
+            also_tags: false,
             branch_rename: None,
             max_blob_size: None,
+            max_blob_size_per_ext: Vec::new(),
+            replace_blobs_with_pointer: false,
             strip_blobs_with_ids: None,
+            strip_path_globs: Vec::new(),
+            strip_java_class_files: false,
+            strip_node_modules: false,
+            strip_python_cache: false,
+            strip_blobs_matching: Vec::new(),
+            strip_blobs_matching_max_bytes: DEFAULT_CONTENT_SCAN_BYTES,
             write_report: false,
+            reproducible: false,
             write_report_json: false,
+            migration_log: None,
+            pipeline_buffer_bytes: DEFAULT_PIPELINE_BUFFER_BYTES,
             path_compat_policy: PathCompatPolicy::default(),
+            unsafe_path_policy: UnsafePathPolicy::default(),
             cleanup: CleanupMode::None,
+            keep_unreachable: false,
             reencode: true,
             reencode_requested: None,
             quotepath: true,
@@ -251,17 +626,37 @@ impl Default for Options {
             dry_run: false,
             detect_secrets: false,
             detect_patterns: Vec::new(),
+            detect_patterns_file: None,
+            detect_no_builtin: false,
+            detect_emit: DetectEmit::Global,
+            migrate_conflict: MigrateConflictPolicy::Skip,
+            help_patterns: false,
+            scan_messages: false,
             partial: false,
+            watch_interval: None,
             sensitive: false,
+            redact_paths: false,
             no_fetch: false,
+            ignore_replace_refs: false,
+            allow_shallow: false,
             backup: false,
+            resume_ref_update: false,
             backup_path: None,
+            output_bundle: None,
+            split_to: Vec::new(),
+            split_output_dir: None,
+            split_rest: None,
+            clone_to: None,
+            clone_local: false,
+            post_check: None,
             mode: Mode::Filter,
             analyze: AnalyzeConfig::default(),
             debug_mode: false,
             git_caps: GitCapabilities::default(),
+            cancel: None,
             prune_empty: PruneMode::Auto,
             prune_degenerate: PruneMode::Auto,
+            pruned_tag: PrunedTagPolicy::Delete,
             no_ff: false,
             date_shift: None,
             date_set: None,
@@ -269,7 +664,25 @@ impl Default for Options {
     }
 }
 
+pub const JAVA_CLASS_GLOBS: &[&str] = &["**/*.class"];
+pub const NODE_MODULES_GLOBS: &[&str] = &["**/node_modules/**"];
+pub const PYTHON_CACHE_GLOBS: &[&str] = &["**/__pycache__/**", "**/*.pyc"];
+
 impl Options {
+    /// Globs enabled by the `--strip-java-class-files`, `--strip-node-modules`
+    /// and `--strip-python-cache` shorthands.
+    pub fn build_artifact_globs(&self) -> Vec<Vec<u8>> {
+        [
+            (self.strip_java_class_files, JAVA_CLASS_GLOBS),
+            (self.strip_node_modules, NODE_MODULES_GLOBS),
+            (self.strip_python_cache, PYTHON_CACHE_GLOBS),
+        ]
+        .into_iter()
+        .filter(|(enabled, _)| *enabled)
+        .flat_map(|(_, globs)| globs.iter().map(|g| g.as_bytes().to_vec()))
+        .collect()
+    }
+
     pub fn apply_git_capabilities(&mut self, caps: GitCapabilities) -> Result<(), FilterRepoError> {
         self.git_caps = caps;
 
@@ -305,12 +718,623 @@ impl Options {
 
         Ok(())
     }
+
+    /// True when any size-based blob stripping is configured.
+    pub fn has_blob_size_limits(&self) -> bool {
+        self.max_blob_size.is_some() || !self.max_blob_size_per_ext.is_empty()
+    }
+
+    /// Size limit that applies to a blob stored at `path`. An extension
+    /// override takes precedence over the global `--max-blob-size`.
+    pub fn blob_size_limit_for_path(&self, path: &[u8]) -> Option<usize> {
+        if !self.max_blob_size_per_ext.is_empty() {
+            if let Some(ext) = path_extension_lowercase(path) {
+                if let Some((_, limit)) = self
+                    .max_blob_size_per_ext
+                    .iter()
+                    .find(|(e, _)| e.as_slice() == ext.as_slice())
+                {
+                    return Some(*limit);
+                }
+            }
+        }
+        self.max_blob_size
+    }
+
+    /// Size above which a blob is too large for every path, i.e. it can be
+    /// dropped before the referencing commit (and its path) is seen.
+    pub fn blob_size_limit_without_path(&self) -> Option<usize> {
+        let global = self.max_blob_size?;
+        Some(
+            self.max_blob_size_per_ext
+                .iter()
+                .map(|(_, limit)| *limit)
+                .fold(global, usize::max),
+        )
+    }
+
+    /// Smallest size limit configured for any path.
+    pub fn min_blob_size_limit(&self) -> Option<usize> {
+        self.max_blob_size_per_ext
+            .iter()
+            .map(|(_, limit)| *limit)
+            .chain(self.max_blob_size)
+            .min()
+    }
+}
+
+fn path_extension_lowercase(path: &[u8]) -> Option<Vec<u8>> {
+    let name = match path.iter().rposition(|&b| b == b'/') {
+        Some(idx) => &path[idx + 1..],
+        None => path,
+    };
+    let dot = name.iter().rposition(|&b| b == b'.')?;
+    if dot == 0 || dot + 1 == name.len() {
+        return None;
+    }
+    Some(name[dot + 1..].to_ascii_lowercase())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn option_conflict_matrix() {
+        let analyze = || Options {
+            mode: Mode::Analyze,
+            ..Options::default()
+        };
+        let path = || vec![b"src/".to_vec()];
+        let split = || {
+            vec![SplitTarget {
+                name: "api".to_string(),
+                paths: path(),
+            }]
+        };
+        let matrix: Vec<(Options, Option<[&str; 2]>)> = vec![
+            (Options::default(), None),
+            (
+                Options {
+                    replace_text_file: Some("r.txt".into()),
+                    ..analyze()
+                },
+                Some(["--analyze", "--replace-text"]),
+            ),
+            (
+                Options {
+                    replace_message_file: Some("m.txt".into()),
+                    ..analyze()
+                },
+                Some(["--analyze", "--replace-message"]),
+            ),
+            (
+                Options {
+                    replace_tag_message_file: Some("t.txt".into()),
+                    ..analyze()
+                },
+                Some(["--analyze", "--tag-message-file"]),
+            ),
+            (
+                Options {
+                    paths: path(),
+                    ..analyze()
+                },
+                Some(["--analyze", "--path"]),
+            ),
+            (
+                Options {
+                    path_renames: vec![(b"a/".to_vec(), b"b/".to_vec())],
+                    ..analyze()
+                },
+                Some(["--analyze", "--path-rename"]),
+            ),
+            (
+                Options {
+                    max_blob_size: Some(10),
+                    ..analyze()
+                },
+                Some(["--analyze", "--max-blob-size"]),
+            ),
+            (
+                Options {
+                    strip_blobs_matching: vec![Regex::new("x").unwrap()],
+                    ..analyze()
+                },
+                Some(["--analyze", "--strip-blobs-with-ids"]),
+            ),
+            (
+                Options {
+                    tag_rename: Some((b"v".to_vec(), b"r".to_vec())),
+                    ..analyze()
+                },
+                Some(["--analyze", "--branch-rename"]),
+            ),
+            (
+                Options {
+                    detect_secrets: true,
+                    ..analyze()
+                },
+                Some(["--analyze", "--detect-secrets"]),
+            ),
+            (
+                Options {
+                    invert_paths: true,
+                    ..Options::default()
+                },
+                Some(["--invert-paths", "--path"]),
+            ),
+            (
+                Options {
+                    invert_paths: true,
+                    path_globs: vec![b"*.log".to_vec()],
+                    ..Options::default()
+                },
+                None,
+            ),
+            (
+                Options {
+                    author_date_is_committer_date: true,
+                    committer_date_is_author_date: true,
+                    ..Options::default()
+                },
+                Some([
+                    "--author-date-is-committer-date",
+                    "--committer-date-is-author-date",
+                ]),
+            ),
+            (
+                Options {
+                    override_committer_date: OverrideDate::Now,
+                    ..Options::default()
+                },
+                Some(["--override-committer-date", "--override-committer"]),
+            ),
+            (
+                Options {
+                    override_tagger_date: OverrideDate::Now,
+                    ..Options::default()
+                },
+                Some(["--override-tagger-date", "--override-tagger"]),
+            ),
+            (
+                Options {
+                    override_committer: Some(b"Bot <bot@example.com>".to_vec()),
+                    override_committer_date: OverrideDate::Now,
+                    committer_date_is_author_date: true,
+                    ..Options::default()
+                },
+                Some([
+                    "--override-committer-date",
+                    "--committer-date-is-author-date",
+                ]),
+            ),
+            (
+                Options {
+                    override_committer: Some(b"Bot <bot@example.com>".to_vec()),
+                    override_committer_date: OverrideDate::Now,
+                    ..Options::default()
+                },
+                None,
+            ),
+            (
+                Options {
+                    no_data: true,
+                    normalize_eol: Some(EolNormalization {
+                        target: EolTarget::Lf,
+                        globs: Vec::new(),
+                    }),
+                    ..Options::default()
+                },
+                Some(["--no-data", "--normalize-eol"]),
+            ),
+            (
+                Options {
+                    no_data: true,
+                    metadata_only: true,
+                    normalize_eol: Some(EolNormalization {
+                        target: EolTarget::Lf,
+                        globs: Vec::new(),
+                    }),
+                    ..Options::default()
+                },
+                Some(["--metadata-only", "--normalize-eol"]),
+            ),
+            (
+                Options {
+                    output_bundle: Some(PathBuf::from("out.bundle")),
+                    sensitive: true,
+                    ..Options::default()
+                },
+                Some(["--output-bundle", "--sensitive"]),
+            ),
+            (
+                Options {
+                    output_bundle: Some(PathBuf::from("out.bundle")),
+                    dry_run: true,
+                    ..Options::default()
+                },
+                Some(["--output-bundle", "--dry-run"]),
+            ),
+            (
+                Options {
+                    output_bundle: Some(PathBuf::from("out.bundle")),
+                    watch_interval: Some(60),
+                    ..Options::default()
+                },
+                Some(["--output-bundle", "--watch-interval"]),
+            ),
+            (
+                Options {
+                    output_bundle: Some(PathBuf::from("out.bundle")),
+                    fe_stream_override: Some(PathBuf::from("stream")),
+                    ..Options::default()
+                },
+                Some(["--output-bundle", "--fe_stream_override"]),
+            ),
+            (
+                Options {
+                    output_bundle: Some(PathBuf::from("out.bundle")),
+                    no_data: true,
+                    ..Options::default()
+                },
+                Some(["--output-bundle", "--no-data"]),
+            ),
+            (
+                Options {
+                    output_bundle: Some(PathBuf::from("out.bundle")),
+                    no_data: true,
+                    metadata_only: true,
+                    ..Options::default()
+                },
+                Some(["--output-bundle", "--metadata-only"]),
+            ),
+            (
+                Options {
+                    output_bundle: Some(PathBuf::from("out.bundle")),
+                    target: PathBuf::from("elsewhere"),
+                    ..Options::default()
+                },
+                Some(["--output-bundle", "--target"]),
+            ),
+            (
+                Options {
+                    output_bundle: Some(PathBuf::from("out.bundle")),
+                    ..Options::default()
+                },
+                None,
+            ),
+            (
+                Options {
+                    split_output_dir: Some(PathBuf::from("out")),
+                    ..Options::default()
+                },
+                Some(["--split-output-dir", "--split-to"]),
+            ),
+            (
+                Options {
+                    split_rest: Some("rest".to_string()),
+                    ..Options::default()
+                },
+                Some(["--split-rest", "--split-to"]),
+            ),
+            (
+                Options {
+                    split_to: split(),
+                    ..Options::default()
+                },
+                Some(["--split-to", "--split-output-dir"]),
+            ),
+            (
+                Options {
+                    split_to: split(),
+                    split_output_dir: Some(PathBuf::from("out")),
+                    paths: path(),
+                    ..Options::default()
+                },
+                Some(["--split-to", "--path"]),
+            ),
+            (
+                Options {
+                    split_to: split(),
+                    split_output_dir: Some(PathBuf::from("out")),
+                    paths: path(),
+                    invert_paths: true,
+                    ..Options::default()
+                },
+                Some(["--split-to", "--path"]),
+            ),
+            (
+                Options {
+                    split_to: split(),
+                    split_output_dir: Some(PathBuf::from("out")),
+                    sensitive: true,
+                    ..Options::default()
+                },
+                Some(["--split-to", "--sensitive"]),
+            ),
+            (
+                Options {
+                    split_to: split(),
+                    split_output_dir: Some(PathBuf::from("out")),
+                    dry_run: true,
+                    ..Options::default()
+                },
+                Some(["--split-to", "--dry-run"]),
+            ),
+            (
+                Options {
+                    split_to: split(),
+                    split_output_dir: Some(PathBuf::from("out")),
+                    output_bundle: Some(PathBuf::from("out.bundle")),
+                    ..Options::default()
+                },
+                Some(["--split-to", "--output-bundle"]),
+            ),
+            (
+                Options {
+                    split_to: split(),
+                    split_output_dir: Some(PathBuf::from("out")),
+                    watch_interval: Some(60),
+                    ..Options::default()
+                },
+                Some(["--split-to", "--watch-interval"]),
+            ),
+            (
+                Options {
+                    split_to: split(),
+                    split_output_dir: Some(PathBuf::from("out")),
+                    fe_stream_override: Some(PathBuf::from("stream")),
+                    ..Options::default()
+                },
+                Some(["--split-to", "--fe_stream_override"]),
+            ),
+            (
+                Options {
+                    split_to: split(),
+                    split_output_dir: Some(PathBuf::from("out")),
+                    no_data: true,
+                    ..Options::default()
+                },
+                Some(["--split-to", "--no-data"]),
+            ),
+            (
+                Options {
+                    split_to: split(),
+                    split_output_dir: Some(PathBuf::from("out")),
+                    no_data: true,
+                    metadata_only: true,
+                    ..Options::default()
+                },
+                Some(["--split-to", "--metadata-only"]),
+            ),
+            (
+                Options {
+                    split_to: split(),
+                    split_output_dir: Some(PathBuf::from("out")),
+                    target: PathBuf::from("elsewhere"),
+                    ..Options::default()
+                },
+                Some(["--split-to", "--target"]),
+            ),
+            (
+                Options {
+                    split_to: split(),
+                    split_output_dir: Some(PathBuf::from("out")),
+                    split_rest: Some("rest".to_string()),
+                    ..Options::default()
+                },
+                None,
+            ),
+            (
+                Options {
+                    clone_local: true,
+                    ..Options::default()
+                },
+                Some(["--clone-local", "--clone-to"]),
+            ),
+            (
+                Options {
+                    clone_to: Some(PathBuf::from("clone")),
+                    dry_run: true,
+                    ..Options::default()
+                },
+                Some(["--clone-to", "--dry-run"]),
+            ),
+            (
+                Options {
+                    clone_to: Some(PathBuf::from("clone")),
+                    output_bundle: Some(PathBuf::from("out.bundle")),
+                    ..Options::default()
+                },
+                Some(["--clone-to", "--output-bundle"]),
+            ),
+            (
+                Options {
+                    clone_to: Some(PathBuf::from("clone")),
+                    split_to: split(),
+                    split_output_dir: Some(PathBuf::from("out")),
+                    ..Options::default()
+                },
+                Some(["--clone-to", "--split-to"]),
+            ),
+            (
+                Options {
+                    clone_to: Some(PathBuf::from("clone")),
+                    watch_interval: Some(60),
+                    ..Options::default()
+                },
+                Some(["--clone-to", "--watch-interval"]),
+            ),
+            (
+                Options {
+                    clone_to: Some(PathBuf::from("clone")),
+                    fe_stream_override: Some(PathBuf::from("stream")),
+                    ..Options::default()
+                },
+                Some(["--clone-to", "--fe_stream_override"]),
+            ),
+            (
+                Options {
+                    clone_to: Some(PathBuf::from("clone")),
+                    target: PathBuf::from("elsewhere"),
+                    ..Options::default()
+                },
+                Some(["--clone-to", "--target"]),
+            ),
+            (
+                Options {
+                    clone_to: Some(PathBuf::from("clone")),
+                    clone_local: true,
+                    ..Options::default()
+                },
+                None,
+            ),
+            (
+                Options {
+                    post_check: Some(PostCheck::Fsck),
+                    dry_run: true,
+                    ..Options::default()
+                },
+                Some(["--post-check", "--dry-run"]),
+            ),
+            (
+                Options {
+                    post_check: Some(PostCheck::Fsck),
+                    output_bundle: Some(PathBuf::from("out.bundle")),
+                    ..Options::default()
+                },
+                Some(["--post-check", "--output-bundle"]),
+            ),
+            (
+                Options {
+                    post_check: Some(PostCheck::Fsck),
+                    split_to: split(),
+                    split_output_dir: Some(PathBuf::from("out")),
+                    ..Options::default()
+                },
+                Some(["--post-check", "--split-to"]),
+            ),
+            (
+                Options {
+                    post_check: Some(PostCheck::Fsck),
+                    clone_to: Some(PathBuf::from("clone")),
+                    ..Options::default()
+                },
+                None,
+            ),
+            (
+                Options {
+                    keep_unreachable: true,
+                    sensitive: true,
+                    ..Options::default()
+                },
+                Some(["--keep-unreachable", "--sensitive"]),
+            ),
+            (
+                Options {
+                    dry_run: true,
+                    sensitive: true,
+                    ..Options::default()
+                },
+                Some(["--dry-run", "--sensitive"]),
+            ),
+            (
+                Options {
+                    dry_run: true,
+                    sensitive: true,
+                    no_fetch: true,
+                    ..Options::default()
+                },
+                None,
+            ),
+            (
+                Options {
+                    sensitive: true,
+                    partial: true,
+                    ..Options::default()
+                },
+                Some(["--sensitive", "--partial"]),
+            ),
+        ];
+
+        let mut covered = HashSet::new();
+        for (opts, expected) in &matrix {
+            let hits: Vec<_> = option_conflicts(opts).collect();
+            match expected {
+                None => assert!(hits.is_empty(), "unexpected conflict: {}", hits[0].message),
+                Some(flags) => {
+                    assert_eq!(hits.len(), 1, "expected exactly one conflict for {flags:?}");
+                    assert_eq!(&hits[0].flags, flags);
+                    for flag in flags {
+                        assert!(hits[0].message.contains(flag), "{}", hits[0].message);
+                    }
+                    covered.insert(hits[0].flags);
+                }
+            }
+        }
+        for conflict in OPTION_CONFLICTS {
+            assert!(
+                covered.contains(&conflict.flags),
+                "add a matrix case for {:?}",
+                conflict.flags
+            );
+        }
+    }
+
+    #[test]
+    fn option_conflict_errors_reject_and_notices_pass() {
+        let err = check_option_conflicts(&Options {
+            invert_paths: true,
+            ..Options::default()
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("--invert-paths"));
+        assert!(check_option_conflicts(&Options {
+            sensitive: true,
+            partial: true,
+            quiet: true,
+            ..Options::default()
+        })
+        .is_ok());
+    }
+
+    #[test]
+    fn identity_override_requires_name_and_email() {
+        assert_eq!(
+            parse_identity_override("--override-committer", " Rewrite Bot  < bot@corp > ").unwrap(),
+            b"Rewrite Bot <bot@corp>"
+        );
+        for bad in [
+            "Rewrite Bot",
+            "<bot@corp>",
+            "Bot <>",
+            "Bot <bot@corp> trailing",
+            "Bot <a<b@corp>",
+            "Bot\n <bot@corp>",
+        ] {
+            let err = parse_identity_override("--override-tagger", bad).unwrap_err();
+            assert!(err.to_string().contains("--override-tagger"), "{err}");
+        }
+        assert_eq!(
+            parse_override_date("--override-committer-date", "now").unwrap(),
+            OverrideDate::Now
+        );
+        assert!(parse_override_date("--override-committer-date", "later").is_err());
+    }
+
+    #[test]
+    fn path_rename_regex_rejects_dot_segments_in_replacement() {
+        for replacement in ["lib/../x/", "a/./b", "x/../", "../x", "../", "./lib/"] {
+            let err = parse_path_rename_regex(&format!("src/==>{replacement}")).unwrap_err();
+            assert!(err.to_string().contains("'..' segments"), "{err}");
+        }
+        // Edge segments join the unmatched rest of the path.
+        for replacement in ["lib/", "$1/lib", "..x/y", "x/y..", "x/..", ".."] {
+            assert!(parse_path_rename_regex(&format!("src/==>{replacement}")).is_ok());
+        }
+    }
+
     #[test]
     fn parse_timestamp_accepts_unix_seconds_and_iso_8601_variants() {
         // Unix integer seconds.
@@ -402,6 +1426,156 @@ mod tests {
             "unexpected error: {err_msg}"
         );
     }
+
+    #[test]
+    fn size_flags_accept_binary_suffixes_and_reject_unit_bytes() {
+        assert_eq!(parse_max_blob_size("2k"), Ok(2048));
+        assert_eq!(parse_max_blob_size("5M"), Ok(5 * 1024 * 1024));
+        assert_eq!(parse_max_blob_size("1G"), Ok(1024 * 1024 * 1024));
+        assert_eq!(parse_max_blob_size("1_024"), Ok(1024));
+        // Only a bare K/M/G suffix is accepted; "MB"/"KiB" style units are not.
+        assert!(parse_max_blob_size("5MB").is_err());
+        assert!(parse_max_blob_size("5KiB").is_err());
+        assert!(parse_max_blob_size("M").is_err());
+
+        let warn: u64 = parse_size_flag("3M", "--analyze-large-blob").unwrap();
+        assert_eq!(warn, 3 * 1024 * 1024);
+        let msg: usize = parse_size_flag("10k", "--analyze-commit-msg-warn").unwrap();
+        assert_eq!(msg, 10 * 1024);
+        let err = parse_size_flag::<u64>("5MB", "--analyze-total-warn").unwrap_err();
+        assert!(err.to_string().contains("--analyze-total-warn expects"));
+    }
+
+    #[test]
+    fn parse_size_str_accepts_binary_suffixes_and_rejects_non_positive_sizes() {
+        assert_eq!(parse_size_str("1024"), Ok(1024));
+        assert_eq!(parse_size_str("1K"), Ok(1024));
+        assert_eq!(parse_size_str("1M"), Ok(1_048_576));
+        assert_eq!(parse_size_str("1G"), Ok(1_073_741_824));
+        assert_eq!(parse_size_str("1g"), Ok(1_073_741_824));
+        assert!(parse_size_str("0")
+            .unwrap_err()
+            .contains("greater than zero"));
+        assert!(parse_size_str("0K").is_err());
+        assert!(parse_size_str("-1").unwrap_err().contains("negative"));
+        assert!(parse_size_str("1T").is_err());
+        assert!(parse_size_str("").is_err());
+    }
+
+    #[test]
+    fn split_to_parses_name_and_path_list() {
+        assert_eq!(
+            parse_split_target("api=services/api,libs/proto/").unwrap(),
+            SplitTarget {
+                name: "api".to_string(),
+                paths: vec![b"services/api".to_vec(), b"libs/proto/".to_vec()],
+            }
+        );
+        for bad in [
+            "api",
+            "=src",
+            "a/b=src",
+            ".git=src",
+            "api=",
+            "api=src,,lib",
+            "api=../x",
+        ] {
+            assert!(parse_split_target(bad).is_err(), "{bad} should be rejected");
+        }
+    }
+
+    #[test]
+    fn normalize_eol_collects_globs_for_one_target() {
+        let mut eol = None;
+        add_eol_normalization(&mut eol, "lf:*.txt").unwrap();
+        add_eol_normalization(&mut eol, "lf:docs/*.md").unwrap();
+        assert_eq!(
+            eol,
+            Some(EolNormalization {
+                target: EolTarget::Lf,
+                globs: vec![b"*.txt".to_vec(), b"docs/*.md".to_vec()],
+            })
+        );
+        assert!(add_eol_normalization(&mut eol, "crlf:*.bat").is_err());
+        assert!(add_eol_normalization(&mut eol, "lf").is_err());
+
+        let mut eol = None;
+        add_eol_normalization(&mut eol, "crlf").unwrap();
+        assert_eq!(eol.as_ref().map(|e| e.globs.len()), Some(0));
+        assert!(add_eol_normalization(&mut eol, "crlf:*.bat").is_err());
+        for bad in ["cr", "lf:", "LF"] {
+            assert!(
+                add_eol_normalization(&mut None, bad).is_err(),
+                "{bad} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn normalize_mode_accepts_regular_file_modes_only() {
+        assert_eq!(
+            parse_mode_normalization("*.sh=100755").unwrap(),
+            (b"*.sh".to_vec(), b"100755".to_vec())
+        );
+        assert_eq!(
+            parse_mode_normalization("docs/a=b.md=100644").unwrap(),
+            (b"docs/a=b.md".to_vec(), b"100644".to_vec())
+        );
+        for bad in [
+            "*.lnk=120000",
+            "vendor/*=160000",
+            "*.sh=755",
+            "=100644",
+            "*.sh",
+        ] {
+            assert!(
+                parse_mode_normalization(bad).is_err(),
+                "{bad} should be rejected"
+            );
+        }
+    }
+
+    #[test]
+    fn blob_size_per_ext_overrides_global_limit() {
+        let parsed = parse_blob_size_per_ext("zip=50M, .PNG=2m").unwrap();
+        assert_eq!(
+            parsed,
+            vec![
+                (b"zip".to_vec(), 50 * 1024 * 1024),
+                (b"png".to_vec(), 2 * 1024 * 1024)
+            ]
+        );
+        assert!(parse_blob_size_per_ext("zip").is_err());
+        assert!(parse_blob_size_per_ext("zip=5MB").is_err());
+        assert!(parse_blob_size_per_ext("=5M").is_err());
+        assert!(parse_blob_size_per_ext(",").is_err());
+
+        let opts = Options {
+            max_blob_size: Some(1024),
+            max_blob_size_per_ext: parsed,
+            ..Options::default()
+        };
+        assert_eq!(
+            opts.blob_size_limit_for_path(b"assets/Logo.PNG"),
+            Some(2 * 1024 * 1024)
+        );
+        assert_eq!(
+            opts.blob_size_limit_for_path(b"dist/a.zip"),
+            Some(50 * 1024 * 1024)
+        );
+        assert_eq!(opts.blob_size_limit_for_path(b"zip/README"), Some(1024));
+        assert_eq!(opts.blob_size_limit_for_path(b".png"), Some(1024));
+        assert_eq!(opts.min_blob_size_limit(), Some(1024));
+        assert_eq!(opts.blob_size_limit_without_path(), Some(50 * 1024 * 1024));
+
+        let ext_only = Options {
+            max_blob_size_per_ext: vec![(b"png".to_vec(), 10)],
+            ..Options::default()
+        };
+        assert_eq!(ext_only.blob_size_limit_for_path(b"a.txt"), None);
+        assert_eq!(ext_only.blob_size_limit_without_path(), None);
+        assert_eq!(ext_only.min_blob_size_limit(), Some(10));
+    }
 }
 
 pub fn parse_args() -> Result<Options, FilterRepoError> {
@@ -433,11 +1607,15 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
         idx += 1;
     }
 
+    // Parse-time deprecation warnings already follow --log-format.
+    crate::diagnostics::set_format(log_format_requested(&args));
     let mut opts = Options {
         debug_mode: debug_mode_enabled(&args),
         ..Options::default()
     };
     let mut overrides = AnalyzeOverrides::default();
+    // The first --refs replaces the default --all rather than adding to it.
+    let mut refs_given = false;
     let mut it = args.into_iter();
     while let Some(arg) = it.next() {
         match arg.as_str() {
@@ -445,6 +1623,30 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
             "--analyze-json" => {
                 opts.analyze.json = true;
                 overrides.json = Some(true);
+                overrides.json_compact = Some(false);
+            }
+            "--analyze-json-compact" => {
+                opts.analyze.json = true;
+                overrides.json = Some(true);
+                overrides.json_compact = Some(true);
+            }
+            arg if arg.starts_with("--analyze-json-compact=") => {
+                let compact = parse_bool_flag(
+                    &arg["--analyze-json-compact=".len()..],
+                    "--analyze-json-compact",
+                )?;
+                opts.analyze.json = true;
+                overrides.json = Some(true);
+                overrides.json_compact = Some(compact);
+            }
+            arg if arg.starts_with("--analyze-json-pretty=") => {
+                let pretty = parse_bool_flag(
+                    &arg["--analyze-json-pretty=".len()..],
+                    "--analyze-json-pretty",
+                )?;
+                opts.analyze.json = true;
+                overrides.json = Some(true);
+                overrides.json_compact = Some(!pretty);
             }
             "--analyze-top" => {
                 let v = require_arg_value(&mut it, "--analyze-top requires COUNT")?;
@@ -453,6 +1655,81 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
                 opts.analyze.top = top;
                 overrides.top = Some(top);
             }
+            "--warn-pack-count" => {
+                let v = require_arg_value(&mut it, "--warn-pack-count requires COUNT")?;
+                let parsed = parse_usize(&v, "--warn-pack-count")?;
+                opts.analyze.thresholds.warn_pack_count = parsed;
+                overrides.thresholds.warn_pack_count = Some(parsed);
+            }
+            arg if arg.starts_with("--warn-pack-count=") => {
+                let parsed = parse_usize(&arg["--warn-pack-count=".len()..], "--warn-pack-count")?;
+                opts.analyze.thresholds.warn_pack_count = parsed;
+                overrides.thresholds.warn_pack_count = Some(parsed);
+            }
+            "--warn-loose-ratio" => {
+                let v = require_arg_value(&mut it, "--warn-loose-ratio requires RATIO")?;
+                let parsed = parse_ratio(&v, "--warn-loose-ratio")?;
+                opts.analyze.thresholds.warn_loose_object_ratio = parsed;
+                overrides.thresholds.warn_loose_object_ratio = Some(parsed);
+            }
+            arg if arg.starts_with("--warn-loose-ratio=") => {
+                let parsed =
+                    parse_ratio(&arg["--warn-loose-ratio=".len()..], "--warn-loose-ratio")?;
+                opts.analyze.thresholds.warn_loose_object_ratio = parsed;
+                overrides.thresholds.warn_loose_object_ratio = Some(parsed);
+            }
+            "--analyze-depth" => {
+                let v = require_arg_value(&mut it, "--analyze-depth requires N")?;
+                opts.analyze.depth = Some(parse_analyze_depth(&v)?);
+            }
+            arg if arg.starts_with("--analyze-depth=") => {
+                opts.analyze.depth = Some(parse_analyze_depth(&arg["--analyze-depth=".len()..])?);
+            }
+            "--analyze-max-path-width" => {
+                let v = require_arg_value(&mut it, "--analyze-max-path-width requires N")?;
+                opts.analyze.max_path_width = parse_analyze_max_path_width(&v)?;
+            }
+            arg if arg.starts_with("--analyze-max-path-width=") => {
+                opts.analyze.max_path_width =
+                    parse_analyze_max_path_width(&arg["--analyze-max-path-width=".len()..])?;
+            }
+            "--analyze-max-blob-paths" => {
+                let v = require_arg_value(&mut it, "--analyze-max-blob-paths requires N")?;
+                opts.analyze.max_blob_paths = Some(parse_analyze_max_blob_paths(&v)?);
+            }
+            arg if arg.starts_with("--analyze-max-blob-paths=") => {
+                opts.analyze.max_blob_paths = Some(parse_analyze_max_blob_paths(
+                    &arg["--analyze-max-blob-paths=".len()..],
+                )?);
+            }
+            "--analyze-plain" => {
+                opts.analyze.plain = true;
+            }
+            "--analyze-bad-identity" => {
+                let v = require_arg_value(&mut it, "--analyze-bad-identity requires PATTERN")?;
+                if v.trim().is_empty() {
+                    return Err(FilterRepoError::invalid_options(
+                        "--analyze-bad-identity expects a non-empty pattern",
+                    ));
+                }
+                opts.analyze.bad_identities.push(v);
+            }
+            "--analyze-baseline" => {
+                let v = require_arg_value(&mut it, "--analyze-baseline requires FILE")?;
+                opts.analyze.baseline = Some(PathBuf::from(v));
+            }
+            "--analyze-ignore-path" => {
+                let v = require_arg_value(&mut it, "--analyze-ignore-path requires GLOB")?;
+                opts.analyze
+                    .analyze_ignore_globs
+                    .push(parse_analyze_ignore_glob(&v)?);
+            }
+            arg if arg.starts_with("--analyze-ignore-path=") => {
+                let v = &arg["--analyze-ignore-path=".len()..];
+                opts.analyze
+                    .analyze_ignore_globs
+                    .push(parse_analyze_ignore_glob(v)?);
+            }
             "--analyze-total-warn" => {
                 enforce_legacy_analyze_flag_allowed("--analyze-total-warn", opts.debug_mode)?;
                 warn_legacy_analyze_threshold(
@@ -460,7 +1737,7 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
                     "analyze.thresholds.warn_total_bytes",
                 );
                 let v = require_arg_value(&mut it, "--analyze-total-warn requires BYTES")?;
-                let parsed = parse_u64(&v, "--analyze-total-warn")?;
+                let parsed = parse_size_flag(&v, "--analyze-total-warn")?;
                 opts.analyze.thresholds.warn_total_bytes = parsed;
                 overrides.thresholds.warn_total_bytes = Some(parsed);
             }
@@ -471,7 +1748,7 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
                     "analyze.thresholds.crit_total_bytes",
                 );
                 let v = require_arg_value(&mut it, "--analyze-total-critical requires BYTES")?;
-                let parsed = parse_u64(&v, "--analyze-total-critical")?;
+                let parsed = parse_size_flag(&v, "--analyze-total-critical")?;
                 opts.analyze.thresholds.crit_total_bytes = parsed;
                 overrides.thresholds.crit_total_bytes = Some(parsed);
             }
@@ -482,7 +1759,7 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
                     "analyze.thresholds.warn_blob_bytes",
                 );
                 let v = require_arg_value(&mut it, "--analyze-large-blob requires BYTES")?;
-                let parsed = parse_u64(&v, "--analyze-large-blob")?;
+                let parsed = parse_size_flag(&v, "--analyze-large-blob")?;
                 opts.analyze.thresholds.warn_blob_bytes = parsed;
                 overrides.thresholds.warn_blob_bytes = Some(parsed);
             }
@@ -548,7 +1825,7 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
                     "analyze.thresholds.warn_commit_msg_bytes",
                 );
                 let v = require_arg_value(&mut it, "--analyze-commit-msg-warn requires BYTES")?;
-                let parsed = parse_usize(&v, "--analyze-commit-msg-warn")?;
+                let parsed = parse_size_flag(&v, "--analyze-commit-msg-warn")?;
                 opts.analyze.thresholds.warn_commit_msg_bytes = parsed;
                 overrides.thresholds.warn_commit_msg_bytes = Some(parsed);
             }
@@ -576,44 +1853,174 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
             "--ref" | "--refs" => {
                 // --refs implies a partial rewrite
                 // so we do not run remote/cleanup behaviors by default.
-                opts.refs
-                    .push(require_arg_value(&mut it, "--ref requires value")?);
+                let v = require_arg_value(&mut it, "--ref requires value")?;
+                if !refs_given {
+                    opts.refs.clear();
+                    refs_given = true;
+                }
+                opts.refs.push(v);
+                opts.partial = true;
+            }
+            // Ref filters also imply a partial rewrite, like --refs.
+            "--ref-filter" => {
+                let v = require_arg_value(&mut it, "--ref-filter requires GLOB")?;
+                opts.ref_filter_globs.push(v.into_bytes());
+                opts.partial = true;
+            }
+            arg if arg.starts_with("--ref-filter=") => {
+                let v = &arg["--ref-filter=".len()..];
+                opts.ref_filter_globs.push(v.as_bytes().to_vec());
+                opts.partial = true;
+            }
+            "--ref-filter-exclude" => {
+                let v = require_arg_value(&mut it, "--ref-filter-exclude requires GLOB")?;
+                opts.ref_filter_globs_exclude.push(v.into_bytes());
                 opts.partial = true;
             }
+            arg if arg.starts_with("--ref-filter-exclude=") => {
+                let v = &arg["--ref-filter-exclude=".len()..];
+                opts.ref_filter_globs_exclude.push(v.as_bytes().to_vec());
+                opts.partial = true;
+            }
+            "--include-stash" => {
+                opts.include_stash = true;
+            }
+            "--include-notes" => {
+                opts.include_notes = true;
+            }
+            "--include-remotes" => {
+                opts.include_remotes = true;
+            }
             "--date-order" => {
                 guard_debug("--date-order", opts.debug_mode)?;
                 opts.date_order = true;
             }
             "--no-data" => opts.no_data = true,
+            "--metadata-only" => {
+                opts.metadata_only = true;
+                opts.no_data = true;
+            }
             "--quiet" => opts.quiet = true,
+            "--log-format" => {
+                let v = require_arg_value(&mut it, "--log-format requires FORMAT (text|json)")?;
+                opts.log_format = parse_log_format(&v)?;
+            }
+            arg if arg.starts_with("--log-format=") => {
+                opts.log_format = parse_log_format(&arg["--log-format=".len()..])?;
+            }
             "--no-reset" => {
                 guard_debug("--no-reset", opts.debug_mode)?;
                 opts.reset = false;
             }
-            "--replace-message" => {
-                let p = require_arg_value(&mut it, "--replace-message requires file")?;
+            "--replace-message" | "--replace-text-messages" => {
+                let missing = if arg == "--replace-message" {
+                    "--replace-message requires file"
+                } else {
+                    "--replace-text-messages requires file"
+                };
+                let p = require_arg_value(&mut it, missing)?;
                 opts.replace_message_file = Some(PathBuf::from(p));
             }
-            "--replace-text" => {
-                let p = require_arg_value(&mut it, "--replace-text requires file")?;
-                opts.replace_text_file = Some(PathBuf::from(p));
+            "--tag-message-file" => {
+                let p = require_arg_value(&mut it, "--tag-message-file requires file")?;
+                opts.replace_tag_message_file = Some(PathBuf::from(p));
             }
-            "--mailmap" => {
-                let p = require_arg_value(&mut it, "--mailmap requires file")?;
-                opts.mailmap_file = Some(PathBuf::from(p));
+            arg if arg.starts_with("--tag-message-file=") => {
+                opts.replace_tag_message_file =
+                    Some(PathBuf::from(&arg["--tag-message-file=".len()..]));
             }
-            "--author-rewrite" => {
-                let p = require_arg_value(&mut it, "--author-rewrite requires file")?;
-                opts.author_rewrite_file = Some(PathBuf::from(p));
+            "--max-tag-message-size" => {
+                let v = require_arg_value(&mut it, "--max-tag-message-size requires BYTES")?;
+                let n: usize = parse_size_flag(&v, "--max-tag-message-size")?;
+                if n as u64 > crate::limits::MAX_DATA_SIZE {
+                    return Err(FilterRepoError::invalid_options(format!(
+                        "--max-tag-message-size cannot exceed {} bytes",
+                        crate::limits::MAX_DATA_SIZE
+                    )));
+                }
+                opts.max_tag_message_bytes = Some(n);
             }
-            "--committer-rewrite" => {
-                let p = require_arg_value(&mut it, "--committer-rewrite requires file")?;
-                opts.committer_rewrite_file = Some(PathBuf::from(p));
+            "--truncate-tag-messages" => {
+                opts.truncate_tag_messages = true;
             }
-            "--email-rewrite" => {
+            "--strip-subject-prefix" => {
+                let v = require_arg_value(&mut it, "--strip-subject-prefix requires PATTERN")?;
+                opts.strip_subject_prefixes
+                    .push(parse_subject_prefix_pattern(&v)?);
+            }
+            arg if arg.starts_with("--strip-subject-prefix=") => {
+                let v = &arg["--strip-subject-prefix=".len()..];
+                opts.strip_subject_prefixes
+                    .push(parse_subject_prefix_pattern(v)?);
+            }
+            "--strip-pr-suffix" => opts.strip_pr_suffix = true,
+            "--also-tags" => opts.also_tags = true,
+            "--replace-text" | "--replace-text-blobs" => {
+                let missing = if arg == "--replace-text" {
+                    "--replace-text requires file"
+                } else {
+                    "--replace-text-blobs requires file"
+                };
+                let p = require_arg_value(&mut it, missing)?;
+                opts.replace_text_file = Some(PathBuf::from(p));
+            }
+            "--blob-callback-so" => {
+                let p = require_arg_value(&mut it, "--blob-callback-so requires PATH")?;
+                opts.blob_callback_so = Some(PathBuf::from(p));
+            }
+            "--max-replace-rules" => {
+                let v = require_arg_value(&mut it, "--max-replace-rules requires N")?;
+                let n = parse_integer_allowing_underscores::<usize>(&v).map_err(|_| {
+                    FilterRepoError::invalid_options("--max-replace-rules expects an integer")
+                })?;
+                opts.max_replace_rules = Some(n);
+            }
+            "--max-replace-rule-bytes" => {
+                let v = require_arg_value(&mut it, "--max-replace-rule-bytes requires SIZE")?;
+                opts.max_replace_rule_bytes =
+                    Some(parse_size_flag(&v, "--max-replace-rule-bytes")?);
+            }
+            "--blob-cache" => {
+                let p = require_arg_value(&mut it, "--blob-cache requires DIR")?;
+                opts.blob_cache_dir = Some(PathBuf::from(p));
+            }
+            "--blob-cache-clear" => {
+                opts.blob_cache_clear = true;
+            }
+            "--author-date-is-committer-date" => opts.author_date_is_committer_date = true,
+            "--committer-date-is-author-date" => opts.committer_date_is_author_date = true,
+            "--mailmap" => {
+                let p = require_arg_value(&mut it, "--mailmap requires file")?;
+                opts.mailmap_file = Some(PathBuf::from(p));
+            }
+            "--author-rewrite" => {
+                let p = require_arg_value(&mut it, "--author-rewrite requires file")?;
+                opts.author_rewrite_file = Some(PathBuf::from(p));
+            }
+            "--committer-rewrite" => {
+                let p = require_arg_value(&mut it, "--committer-rewrite requires file")?;
+                opts.committer_rewrite_file = Some(PathBuf::from(p));
+            }
+            "--email-rewrite" => {
                 let p = require_arg_value(&mut it, "--email-rewrite requires file")?;
                 opts.email_rewrite_file = Some(PathBuf::from(p));
             }
+            "--override-committer" => {
+                let v = require_arg_value(&mut it, "--override-committer requires 'Name <email>'")?;
+                opts.override_committer = Some(parse_identity_override(&arg, &v)?);
+            }
+            "--override-committer-date" => {
+                let v = require_arg_value(&mut it, "--override-committer-date requires now|keep")?;
+                opts.override_committer_date = parse_override_date(&arg, &v)?;
+            }
+            "--override-tagger" => {
+                let v = require_arg_value(&mut it, "--override-tagger requires 'Name <email>'")?;
+                opts.override_tagger = Some(parse_identity_override(&arg, &v)?);
+            }
+            "--override-tagger-date" => {
+                let v = require_arg_value(&mut it, "--override-tagger-date requires now|keep")?;
+                opts.override_tagger_date = parse_override_date(&arg, &v)?;
+            }
             "--path" => {
                 let raw = require_arg_value(&mut it, "--path requires value")?;
                 let mut norm =
@@ -671,6 +2078,43 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
                     })?;
                 opts.path_renames.push(rename);
             }
+            "--path-rename-regex" => {
+                let v = require_arg_value(
+                    &mut it,
+                    "--path-rename-regex requires PATTERN==>REPLACEMENT",
+                )?;
+                opts.path_rename_regexes.push(parse_path_rename_regex(&v)?);
+            }
+            arg if arg.starts_with("--path-rename-regex=") => {
+                let v = &arg["--path-rename-regex=".len()..];
+                opts.path_rename_regexes.push(parse_path_rename_regex(v)?);
+            }
+            "--no-validate-paths" => {
+                opts.validate_paths = false;
+            }
+            "--synthesize-renames" => {
+                opts.synthesize_renames = true;
+            }
+            "--normalize-mode" => {
+                let v = require_arg_value(&mut it, "--normalize-mode requires PATTERN=MODE")?;
+                opts.mode_normalizations.push(parse_mode_normalization(&v)?);
+            }
+            "--normalize-eol" => {
+                let v = require_arg_value(&mut it, "--normalize-eol requires lf|crlf[:GLOB]")?;
+                add_eol_normalization(&mut opts.normalize_eol, &v)?;
+            }
+            arg if arg.starts_with("--normalize-eol=") => {
+                let v = &arg["--normalize-eol=".len()..];
+                add_eol_normalization(&mut opts.normalize_eol, v)?;
+            }
+            "--add-root-file" => {
+                let v = require_arg_value(&mut it, "--add-root-file requires PATH=LOCALFILE")?;
+                opts.root_files.push(parse_root_file(&v)?);
+            }
+            arg if arg.starts_with("--add-root-file=") => {
+                let v = &arg["--add-root-file=".len()..];
+                opts.root_files.push(parse_root_file(v)?);
+            }
             "--subdirectory-filter" => {
                 let dir = require_arg_value(&mut it, "--subdirectory-filter requires DIRECTORY")?;
                 let mut d = normalize_cli_path_str(&dir, /*allow_empty=*/ false).map_err(|m| {
@@ -713,6 +2157,9 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
                 opts.tag_rename =
                     Some((parts[0].as_bytes().to_vec(), parts[1].as_bytes().to_vec()));
             }
+            "--update-tag-name-references" => {
+                opts.update_tag_name_references = true;
+            }
             "--branch-rename" => {
                 let v = require_arg_value(
                     &mut it,
@@ -727,25 +2174,89 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
                 opts.branch_rename =
                     Some((parts[0].as_bytes().to_vec(), parts[1].as_bytes().to_vec()));
             }
-            "--max-blob-size" => {
-                let v = require_arg_value(&mut it, "--max-blob-size requires BYTES")?;
-                let n = parse_max_blob_size(&v).map_err(|_| {
-                    FilterRepoError::invalid_options(
-                        "--max-blob-size expects an integer number of bytes (optionally suffixed with K, M, or G)",
-                    )
+            "--max-blob-size" | "--strip-blobs-bigger-than" => {
+                let missing = if arg == "--max-blob-size" {
+                    "--max-blob-size requires BYTES"
+                } else {
+                    "--strip-blobs-bigger-than requires BYTES"
+                };
+                let v = require_arg_value(&mut it, missing)?;
+                let n = parse_max_blob_size(&v).map_err(|e| {
+                    FilterRepoError::invalid_options(format!(
+                        "{arg} expects an integer number of bytes: {e}",
+                    ))
                 })?;
                 opts.max_blob_size = Some(n);
             }
+            "--max-blob-size-per-ext" => {
+                let v =
+                    require_arg_value(&mut it, "--max-blob-size-per-ext requires EXT=SIZE[,...]")?;
+                for (ext, limit) in parse_blob_size_per_ext(&v)? {
+                    match opts
+                        .max_blob_size_per_ext
+                        .iter_mut()
+                        .find(|(e, _)| *e == ext)
+                    {
+                        Some(entry) => entry.1 = limit,
+                        None => opts.max_blob_size_per_ext.push((ext, limit)),
+                    }
+                }
+            }
+            "--replace-blobs-with-pointer" => opts.replace_blobs_with_pointer = true,
             "--strip-blobs-with-ids" => {
                 let p = require_arg_value(&mut it, "--strip-blobs-with-ids requires FILE")?;
                 opts.strip_blobs_with_ids = Some(PathBuf::from(p));
             }
+            "--strip-blobs-with-path-matching" => {
+                let raw =
+                    require_arg_value(&mut it, "--strip-blobs-with-path-matching requires GLOB")?;
+                let norm = normalize_cli_glob_str(&raw).map_err(|msg| {
+                    FilterRepoError::invalid_options(format!(
+                        "invalid --strip-blobs-with-path-matching '{}': {}",
+                        raw, msg
+                    ))
+                })?;
+                opts.strip_path_globs.push(norm);
+            }
+            "--strip-java-class-files" => opts.strip_java_class_files = true,
+            "--strip-node-modules" => opts.strip_node_modules = true,
+            "--strip-python-cache" => opts.strip_python_cache = true,
+            "--strip-build-artifacts" => {
+                opts.strip_java_class_files = true;
+                opts.strip_node_modules = true;
+                opts.strip_python_cache = true;
+            }
+            "--strip-blobs-matching" => {
+                let v = require_arg_value(&mut it, "--strip-blobs-matching requires REGEX")?;
+                opts.strip_blobs_matching
+                    .push(parse_strip_blobs_matching(&v)?);
+            }
+            "--strip-blobs-matching-max-bytes" => {
+                let v =
+                    require_arg_value(&mut it, "--strip-blobs-matching-max-bytes requires BYTES")?;
+                opts.strip_blobs_matching_max_bytes =
+                    parse_size_flag(&v, "--strip-blobs-matching-max-bytes")?;
+            }
             "--write-report" => {
                 opts.write_report = true;
             }
+            "--reproducible" => {
+                opts.reproducible = true;
+            }
             "--write-report-json" => {
                 opts.write_report_json = true;
             }
+            "--pipeline-buffer-bytes" => {
+                let v = require_arg_value(&mut it, "--pipeline-buffer-bytes requires BYTES")?;
+                opts.pipeline_buffer_bytes = parse_size_flag(&v, "--pipeline-buffer-bytes")?;
+            }
+            "--write-migration-log" => {
+                let p = require_arg_value(&mut it, "--write-migration-log requires PATH")?;
+                opts.migration_log = Some(PathBuf::from(p));
+            }
+            arg if arg.starts_with("--write-migration-log=") => {
+                opts.migration_log = Some(PathBuf::from(&arg["--write-migration-log=".len()..]));
+            }
             "--path-compat-policy" => {
                 let v = require_arg_value(&mut it, "--path-compat-policy requires MODE")?;
                 opts.path_compat_policy = PathCompatPolicy::parse(&v).ok_or_else(|| {
@@ -754,6 +2265,14 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
                     )
                 })?;
             }
+            "--unsafe-path-policy" => {
+                let v = require_arg_value(&mut it, "--unsafe-path-policy requires MODE")?;
+                opts.unsafe_path_policy = UnsafePathPolicy::parse(&v).ok_or_else(|| {
+                    FilterRepoError::invalid_options(
+                        "--unsafe-path-policy expects one of error|skip",
+                    )
+                })?;
+            }
             "--cleanup" => {
                 if let Some(next) = it.clone().next() {
                     if matches!(next.as_str(), "none" | "standard" | "aggressive") {
@@ -773,6 +2292,9 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
                 }
                 parse_legacy_cleanup_value(value, &mut opts)?;
             }
+            "--keep-unreachable" => {
+                opts.keep_unreachable = true;
+            }
             "--cleanup-aggressive" => {
                 guard_debug("--cleanup-aggressive", opts.debug_mode)?;
                 opts.cleanup = CleanupMode::Aggressive;
@@ -808,10 +2330,47 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
             "--detect-secrets" => {
                 opts.detect_secrets = true;
             }
+            "--help-patterns" => {
+                opts.help_patterns = true;
+            }
             "--detect-pattern" => {
                 let p = require_arg_value(&mut it, "--detect-pattern requires REGEX")?;
                 opts.detect_patterns.push(p);
             }
+            "--detect-patterns-file" => {
+                let p = require_arg_value(&mut it, "--detect-patterns-file requires FILE")?;
+                opts.detect_patterns_file = Some(PathBuf::from(p));
+            }
+            "--detect-no-builtin" => {
+                opts.detect_no_builtin = true;
+            }
+            "--detect-scan-messages" => {
+                opts.scan_messages = true;
+            }
+            "--detect-emit" => {
+                let v =
+                    require_arg_value(&mut it, "--detect-emit requires MODE (global|scoped|json)")?;
+                opts.detect_emit = parse_detect_emit(&v)?;
+            }
+            arg if arg.starts_with("--detect-emit=") => {
+                opts.detect_emit = parse_detect_emit(&arg["--detect-emit=".len()..])?;
+            }
+            "--migrate-conflict" => {
+                let v = require_arg_value(
+                    &mut it,
+                    "--migrate-conflict requires POLICY (skip|overwrite|error)",
+                )?;
+                opts.migrate_conflict = match v.as_str() {
+                    "skip" => MigrateConflictPolicy::Skip,
+                    "overwrite" => MigrateConflictPolicy::Overwrite,
+                    "error" => MigrateConflictPolicy::Error,
+                    _ => {
+                        return Err(FilterRepoError::invalid_options(
+                            "--migrate-conflict expects skip|overwrite|error",
+                        ))
+                    }
+                };
+            }
             "--prune-empty" => {
                 let v =
                     require_arg_value(&mut it, "--prune-empty requires MODE (always|auto|never)")?;
@@ -842,21 +2401,55 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
                     }
                 };
             }
+            "--pruned-tag" => {
+                let v = require_arg_value(
+                    &mut it,
+                    "--pruned-tag requires POLICY (delete|ancestor|keep-old)",
+                )?;
+                opts.pruned_tag = parse_pruned_tag_policy(&v)?;
+            }
+            arg if arg.starts_with("--pruned-tag=") => {
+                opts.pruned_tag = parse_pruned_tag_policy(&arg["--pruned-tag=".len()..])?;
+            }
             "--no-ff" => {
                 opts.no_ff = true;
             }
             "--partial" => {
                 opts.partial = true;
             }
+            "--watch-interval" => {
+                let v = require_arg_value(&mut it, "--watch-interval requires SECONDS")?;
+                let secs = parse_integer_allowing_underscores::<u64>(&v).map_err(|_| {
+                    FilterRepoError::invalid_options("--watch-interval expects an integer")
+                })?;
+                if secs == 0 {
+                    return Err(FilterRepoError::invalid_options(
+                        "--watch-interval must be greater than zero",
+                    ));
+                }
+                opts.watch_interval = Some(secs);
+            }
             "--sensitive" | "--sensitive-data-removal" => {
                 opts.sensitive = true;
             }
+            "--sensitive-redact-paths" => {
+                opts.redact_paths = true;
+            }
+            "--ignore-replace-refs" => {
+                opts.ignore_replace_refs = true;
+            }
+            "--allow-shallow" => {
+                opts.allow_shallow = true;
+            }
             "--no-fetch" => {
                 opts.no_fetch = true;
             }
             "--backup" => {
                 opts.backup = true;
             }
+            "--resume-ref-update" => {
+                opts.resume_ref_update = true;
+            }
             "--backup-path" => {
                 if let Some(p) = it.next() {
                     opts.backup_path = Some(PathBuf::from(p));
@@ -866,6 +2459,38 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
                     ));
                 }
             }
+            "--output-bundle" => {
+                let v = require_arg_value(&mut it, "--output-bundle requires PATH")?;
+                opts.output_bundle = Some(PathBuf::from(v));
+            }
+            "--split-to" => {
+                let v =
+                    require_arg_value(&mut it, "--split-to requires NAME=PATHSPEC[,PATHSPEC...]")?;
+                opts.split_to.push(parse_split_target(&v)?);
+            }
+            "--split-output-dir" => {
+                let v = require_arg_value(&mut it, "--split-output-dir requires DIR")?;
+                opts.split_output_dir = Some(PathBuf::from(v));
+            }
+            "--split-rest" => {
+                let v = require_arg_value(&mut it, "--split-rest requires NAME")?;
+                opts.split_rest = Some(parse_split_name(&v)?);
+            }
+            "--clone-to" => {
+                let v = require_arg_value(&mut it, "--clone-to requires PATH")?;
+                opts.clone_to = Some(PathBuf::from(v));
+            }
+            "--clone-local" => opts.clone_local = true,
+            "--post-check" => {
+                let v = require_arg_value(
+                    &mut it,
+                    "--post-check requires CHECK (connectivity|fsck|fsck-full)",
+                )?;
+                opts.post_check = Some(parse_post_check(&v)?);
+            }
+            arg if arg.starts_with("--post-check=") => {
+                opts.post_check = Some(parse_post_check(&arg["--post-check=".len()..])?);
+            }
             "--date-shift" => {
                 let v = require_arg_value(&mut it, "--date-shift requires DURATION")?;
                 opts.date_shift = Some(parse_duration(&v)?);
@@ -940,9 +2565,372 @@ pub fn parse_args() -> Result<Options, FilterRepoError> {
         opts.cleanup = CleanupMode::Standard;
     }
 
+    check_option_conflicts(&opts)?;
     Ok(opts)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConflictKind {
+    /// The combination is contradictory and rejected.
+    Error,
+    /// The combination works but one flag quietly changes the other.
+    Notice,
+}
+
+/// One row of the option combination matrix checked by `parse_args`.
+struct OptionConflict {
+    // Only read by the matrix test, which checks `message` names both.
+    #[cfg_attr(not(test), allow(dead_code))]
+    flags: [&'static str; 2],
+    kind: ConflictKind,
+    applies: fn(&Options) -> bool,
+    /// Names both flags and how to resolve the combination.
+    message: &'static str,
+}
+
+fn is_analyze(opts: &Options) -> bool {
+    opts.mode == Mode::Analyze
+}
+
+fn has_path_selection(opts: &Options) -> bool {
+    !opts.paths.is_empty() || !opts.path_globs.is_empty() || !opts.path_regexes.is_empty()
+}
+
+/// Combinations that used to be resolved silently mid-run. A flag that
+/// changes the meaning of another flag gets a row here.
+const OPTION_CONFLICTS: &[OptionConflict] = &[
+    OptionConflict {
+        flags: ["--analyze", "--replace-text"],
+        kind: ConflictKind::Error,
+        applies: |o| is_analyze(o) && o.replace_text_file.is_some(),
+        message: "--analyze cannot be combined with --replace-text; analyze first, then run --replace-text without --analyze",
+    },
+    OptionConflict {
+        flags: ["--analyze", "--replace-message"],
+        kind: ConflictKind::Error,
+        applies: |o| is_analyze(o) && o.replace_message_file.is_some(),
+        message: "--analyze cannot be combined with --replace-message; analyze first, then run --replace-message without --analyze",
+    },
+    OptionConflict {
+        flags: ["--analyze", "--tag-message-file"],
+        kind: ConflictKind::Error,
+        applies: |o| is_analyze(o) && o.replace_tag_message_file.is_some(),
+        message: "--analyze cannot be combined with --tag-message-file; analyze first, then run --tag-message-file without --analyze",
+    },
+    OptionConflict {
+        flags: ["--analyze", "--path"],
+        kind: ConflictKind::Error,
+        applies: |o| is_analyze(o) && (has_path_selection(o) || o.invert_paths),
+        message: "--analyze cannot be combined with --path/--path-glob/--path-regex/--invert-paths; use --analyze-ignore-path to leave paths out of the analysis",
+    },
+    OptionConflict {
+        flags: ["--analyze", "--path-rename"],
+        kind: ConflictKind::Error,
+        applies: |o| {
+            is_analyze(o) && (!o.path_renames.is_empty() || !o.path_rename_regexes.is_empty())
+        },
+        message: "--analyze cannot be combined with --path-rename; analyze first, then run --path-rename without --analyze",
+    },
+    OptionConflict {
+        flags: ["--analyze", "--max-blob-size"],
+        kind: ConflictKind::Error,
+        applies: |o| is_analyze(o) && o.has_blob_size_limits(),
+        message: "--analyze cannot be combined with --max-blob-size; use --analyze-large-blob to set the reporting threshold instead",
+    },
+    OptionConflict {
+        flags: ["--analyze", "--strip-blobs-with-ids"],
+        kind: ConflictKind::Error,
+        applies: |o| {
+            is_analyze(o) && (o.strip_blobs_with_ids.is_some() || !o.strip_blobs_matching.is_empty())
+        },
+        message: "--analyze cannot be combined with --strip-blobs-with-ids or --strip-blobs-matching; analyze first, then strip blobs without --analyze",
+    },
+    OptionConflict {
+        flags: ["--analyze", "--branch-rename"],
+        kind: ConflictKind::Error,
+        applies: |o| is_analyze(o) && (o.branch_rename.is_some() || o.tag_rename.is_some()),
+        message: "--analyze cannot be combined with --branch-rename or --tag-rename; analyze first, then rename without --analyze",
+    },
+    OptionConflict {
+        flags: ["--analyze", "--detect-secrets"],
+        kind: ConflictKind::Error,
+        applies: |o| is_analyze(o) && o.detect_secrets,
+        message: "--analyze cannot be combined with --detect-secrets; run them as two separate commands",
+    },
+    OptionConflict {
+        flags: ["--invert-paths", "--path"],
+        kind: ConflictKind::Error,
+        applies: |o| o.invert_paths && !has_path_selection(o),
+        message: "--invert-paths has nothing to invert without --path, --path-glob or --path-regex; add the paths to drop",
+    },
+    OptionConflict {
+        flags: ["--author-date-is-committer-date", "--committer-date-is-author-date"],
+        kind: ConflictKind::Error,
+        applies: |o| o.author_date_is_committer_date && o.committer_date_is_author_date,
+        message: "--author-date-is-committer-date and --committer-date-is-author-date copy dates in opposite directions; pick one",
+    },
+    OptionConflict {
+        flags: ["--override-committer-date", "--override-committer"],
+        kind: ConflictKind::Error,
+        applies: |o| {
+            o.override_committer_date == OverrideDate::Now && o.override_committer.is_none()
+        },
+        message: "--override-committer-date only applies to lines stamped by --override-committer; add the identity to stamp",
+    },
+    OptionConflict {
+        flags: ["--override-tagger-date", "--override-tagger"],
+        kind: ConflictKind::Error,
+        applies: |o| o.override_tagger_date == OverrideDate::Now && o.override_tagger.is_none(),
+        message: "--override-tagger-date only applies to lines stamped by --override-tagger; add the identity to stamp",
+    },
+    OptionConflict {
+        flags: ["--override-committer-date", "--committer-date-is-author-date"],
+        kind: ConflictKind::Error,
+        applies: |o| {
+            o.override_committer_date == OverrideDate::Now && o.committer_date_is_author_date
+        },
+        message: "--override-committer-date now and --committer-date-is-author-date both set the committer date; pick one",
+    },
+    OptionConflict {
+        flags: ["--no-data", "--normalize-eol"],
+        kind: ConflictKind::Error,
+        applies: |o| o.no_data && !o.metadata_only && o.normalize_eol.is_some(),
+        message: "--no-data cannot be combined with --normalize-eol, which rewrites blob contents",
+    },
+    OptionConflict {
+        flags: ["--metadata-only", "--normalize-eol"],
+        kind: ConflictKind::Error,
+        applies: |o| o.metadata_only && o.normalize_eol.is_some(),
+        message: "--metadata-only cannot be combined with --normalize-eol, which needs blob contents",
+    },
+    OptionConflict {
+        flags: ["--output-bundle", "--sensitive"],
+        kind: ConflictKind::Error,
+        applies: |o| o.output_bundle.is_some() && o.sensitive,
+        message: "--output-bundle cannot be combined with --sensitive, which must rewrite the repository in place",
+    },
+    OptionConflict {
+        flags: ["--output-bundle", "--dry-run"],
+        kind: ConflictKind::Error,
+        applies: |o| o.output_bundle.is_some() && o.dry_run,
+        message: "--output-bundle cannot be combined with --dry-run; a dry run writes no history to bundle",
+    },
+    OptionConflict {
+        flags: ["--output-bundle", "--watch-interval"],
+        kind: ConflictKind::Error,
+        applies: |o| o.output_bundle.is_some() && o.watch_interval.is_some(),
+        message: "--output-bundle cannot be combined with --watch-interval; bundle a single run instead",
+    },
+    OptionConflict {
+        flags: ["--output-bundle", "--fe_stream_override"],
+        kind: ConflictKind::Error,
+        applies: |o| o.output_bundle.is_some() && o.fe_stream_override.is_some(),
+        message: "--output-bundle cannot be combined with --fe_stream_override",
+    },
+    // The bundle is built in a fresh scratch repo that has none of the
+    // source objects, so every blob must come through the stream.
+    OptionConflict {
+        flags: ["--output-bundle", "--no-data"],
+        kind: ConflictKind::Error,
+        applies: |o| o.output_bundle.is_some() && o.no_data && !o.metadata_only,
+        message: "--output-bundle cannot be combined with --no-data; the bundle needs every blob in the stream",
+    },
+    OptionConflict {
+        flags: ["--output-bundle", "--metadata-only"],
+        kind: ConflictKind::Error,
+        applies: |o| o.output_bundle.is_some() && o.metadata_only,
+        message: "--output-bundle cannot be combined with --metadata-only; the bundle needs every blob in the stream",
+    },
+    OptionConflict {
+        flags: ["--output-bundle", "--target"],
+        kind: ConflictKind::Error,
+        applies: |o| o.output_bundle.is_some() && o.target != o.source,
+        message: "--output-bundle cannot be combined with --target; the bundle is the output",
+    },
+    OptionConflict {
+        flags: ["--split-output-dir", "--split-to"],
+        kind: ConflictKind::Error,
+        applies: |o| o.split_to.is_empty() && o.split_output_dir.is_some(),
+        message: "--split-output-dir requires --split-to; add the path sets to split by",
+    },
+    OptionConflict {
+        flags: ["--split-rest", "--split-to"],
+        kind: ConflictKind::Error,
+        applies: |o| o.split_to.is_empty() && o.split_rest.is_some(),
+        message: "--split-rest requires --split-to; add the path sets to split by",
+    },
+    OptionConflict {
+        flags: ["--split-to", "--split-output-dir"],
+        kind: ConflictKind::Error,
+        applies: |o| !o.split_to.is_empty() && o.split_output_dir.is_none(),
+        message: "--split-to requires --split-output-dir; add the directory for the split repositories",
+    },
+    // Each split is its own path filter; the source is never rewritten.
+    OptionConflict {
+        flags: ["--split-to", "--path"],
+        kind: ConflictKind::Error,
+        applies: |o| !o.split_to.is_empty() && has_path_selection(o),
+        message: "--split-to cannot be combined with --path, --path-glob, --path-regex or --invert-paths; each split is its own path filter",
+    },
+    OptionConflict {
+        flags: ["--split-to", "--sensitive"],
+        kind: ConflictKind::Error,
+        applies: |o| !o.split_to.is_empty() && o.sensitive,
+        message: "--split-to cannot be combined with --sensitive, which must rewrite the repository in place",
+    },
+    OptionConflict {
+        flags: ["--split-to", "--dry-run"],
+        kind: ConflictKind::Error,
+        applies: |o| !o.split_to.is_empty() && o.dry_run,
+        message: "--split-to cannot be combined with --dry-run; the source is only read either way",
+    },
+    OptionConflict {
+        flags: ["--split-to", "--output-bundle"],
+        kind: ConflictKind::Error,
+        applies: |o| !o.split_to.is_empty() && o.output_bundle.is_some(),
+        message: "--split-to cannot be combined with --output-bundle; bundle each split repository instead",
+    },
+    OptionConflict {
+        flags: ["--split-to", "--watch-interval"],
+        kind: ConflictKind::Error,
+        applies: |o| !o.split_to.is_empty() && o.watch_interval.is_some(),
+        message: "--split-to cannot be combined with --watch-interval; split a single run instead",
+    },
+    OptionConflict {
+        flags: ["--split-to", "--fe_stream_override"],
+        kind: ConflictKind::Error,
+        applies: |o| !o.split_to.is_empty() && o.fe_stream_override.is_some(),
+        message: "--split-to cannot be combined with --fe_stream_override",
+    },
+    // The split repositories start empty, so every blob must come through
+    // the stream.
+    OptionConflict {
+        flags: ["--split-to", "--no-data"],
+        kind: ConflictKind::Error,
+        applies: |o| !o.split_to.is_empty() && o.no_data && !o.metadata_only,
+        message: "--split-to cannot be combined with --no-data; the new repositories need every blob in the stream",
+    },
+    OptionConflict {
+        flags: ["--split-to", "--metadata-only"],
+        kind: ConflictKind::Error,
+        applies: |o| !o.split_to.is_empty() && o.metadata_only,
+        message: "--split-to cannot be combined with --metadata-only; the new repositories need every blob in the stream",
+    },
+    OptionConflict {
+        flags: ["--split-to", "--target"],
+        kind: ConflictKind::Error,
+        applies: |o| !o.split_to.is_empty() && o.target != o.source,
+        message: "--split-to cannot be combined with --target; --split-output-dir holds the new repositories",
+    },
+    OptionConflict {
+        flags: ["--clone-local", "--clone-to"],
+        kind: ConflictKind::Error,
+        applies: |o| o.clone_local && o.clone_to.is_none(),
+        message: "--clone-local requires --clone-to; add the directory to clone into",
+    },
+    OptionConflict {
+        flags: ["--clone-to", "--dry-run"],
+        kind: ConflictKind::Error,
+        applies: |o| o.clone_to.is_some() && o.dry_run,
+        message: "--clone-to cannot be combined with --dry-run; a dry run leaves nothing to clone",
+    },
+    OptionConflict {
+        flags: ["--clone-to", "--output-bundle"],
+        kind: ConflictKind::Error,
+        applies: |o| o.clone_to.is_some() && o.output_bundle.is_some(),
+        message: "--clone-to cannot be combined with --output-bundle, which already leaves the source untouched",
+    },
+    OptionConflict {
+        flags: ["--clone-to", "--split-to"],
+        kind: ConflictKind::Error,
+        applies: |o| o.clone_to.is_some() && !o.split_to.is_empty(),
+        message: "--clone-to cannot be combined with --split-to, which already leaves the source untouched",
+    },
+    OptionConflict {
+        flags: ["--clone-to", "--watch-interval"],
+        kind: ConflictKind::Error,
+        applies: |o| o.clone_to.is_some() && o.watch_interval.is_some(),
+        message: "--clone-to cannot be combined with --watch-interval; clone once, then watch the clone",
+    },
+    OptionConflict {
+        flags: ["--clone-to", "--fe_stream_override"],
+        kind: ConflictKind::Error,
+        applies: |o| o.clone_to.is_some() && o.fe_stream_override.is_some(),
+        message: "--clone-to cannot be combined with --fe_stream_override",
+    },
+    OptionConflict {
+        flags: ["--clone-to", "--target"],
+        kind: ConflictKind::Error,
+        applies: |o| o.clone_to.is_some() && o.target != o.source,
+        message: "--clone-to cannot be combined with --target; the clone is the target",
+    },
+    OptionConflict {
+        flags: ["--post-check", "--dry-run"],
+        kind: ConflictKind::Error,
+        applies: |o| o.post_check.is_some() && o.dry_run,
+        message: "--post-check cannot be combined with --dry-run, which leaves no rewritten repository to check",
+    },
+    OptionConflict {
+        flags: ["--post-check", "--output-bundle"],
+        kind: ConflictKind::Error,
+        applies: |o| o.post_check.is_some() && o.output_bundle.is_some(),
+        message: "--post-check cannot be combined with --output-bundle, which leaves no rewritten repository to check",
+    },
+    OptionConflict {
+        flags: ["--post-check", "--split-to"],
+        kind: ConflictKind::Error,
+        applies: |o| o.post_check.is_some() && !o.split_to.is_empty(),
+        message: "--post-check cannot be combined with --split-to, which leaves no rewritten repository to check",
+    },
+    OptionConflict {
+        flags: ["--keep-unreachable", "--sensitive"],
+        kind: ConflictKind::Error,
+        applies: |o| o.keep_unreachable && o.sensitive,
+        message: "--keep-unreachable cannot be combined with --sensitive, which must prune the objects it strips; drop --keep-unreachable",
+    },
+    OptionConflict {
+        flags: ["--dry-run", "--sensitive"],
+        kind: ConflictKind::Notice,
+        applies: |o| o.dry_run && o.sensitive && !o.no_fetch,
+        message: "--dry-run skips the --sensitive fetch of all refs from origin; only refs already present locally are inspected",
+    },
+    OptionConflict {
+        flags: ["--sensitive", "--partial"],
+        kind: ConflictKind::Notice,
+        applies: |o| o.sensitive && o.partial,
+        message: "--partial with --sensitive still fetches every ref from origin, but leaves refs/remotes/origin/* and the origin remote untouched",
+    },
+];
+
+fn option_conflicts(opts: &Options) -> impl Iterator<Item = &'static OptionConflict> + '_ {
+    OPTION_CONFLICTS.iter().filter(|c| (c.applies)(opts))
+}
+
+/// The rejected combinations alone, for options built without
+/// [`parse_args`]; the notices were already shown when parsing.
+pub(crate) fn reject_option_conflicts(opts: &Options) -> Result<(), FilterRepoError> {
+    match option_conflicts(opts).find(|c| c.kind == ConflictKind::Error) {
+        Some(conflict) => Err(FilterRepoError::invalid_options(conflict.message)),
+        None => Ok(()),
+    }
+}
+
+/// Rejects contradictory option sets and explains the softened ones.
+fn check_option_conflicts(opts: &Options) -> Result<(), FilterRepoError> {
+    for conflict in option_conflicts(opts) {
+        match conflict.kind {
+            ConflictKind::Error => {
+                return Err(FilterRepoError::invalid_options(conflict.message));
+            }
+            ConflictKind::Notice if !opts.quiet => {
+                diagnostics::notice(Phase::Options, conflict.message)
+            }
+            ConflictKind::Notice => {}
+        }
+    }
+    Ok(())
+}
+
 fn require_arg_value(
     it: &mut std::vec::IntoIter<String>,
     message: &'static str,
@@ -965,6 +2953,9 @@ fn apply_config_from_file(opts: &mut Options, path: &Path) -> Result<(), FilterR
         if let Some(json) = analyze.json {
             opts.analyze.json = json;
         }
+        if let Some(compact) = analyze.json_compact {
+            opts.analyze.json_compact = compact;
+        }
         if let Some(top) = analyze.top {
             opts.analyze.top = top.max(1);
         }
@@ -972,6 +2963,9 @@ fn apply_config_from_file(opts: &mut Options, path: &Path) -> Result<(), FilterR
             guard_debug("analyze.thresholds.*", opts.debug_mode)?;
             thresholds.apply(&mut opts.analyze.thresholds);
         }
+        if let Some(patterns) = analyze.bad_identities {
+            opts.analyze.bad_identities.extend(patterns);
+        }
     }
 
     Ok(())
@@ -1004,27 +2998,34 @@ fn warn_legacy_cleanup_usage(mode: &str) {
 
     match mode {
         "none" => {
-            eprintln!(
-        "warning: --cleanup=none is deprecated; simply omit --cleanup to keep cleanup disabled."
-      );
+            diagnostics::warning(
+                Phase::Options,
+                "--cleanup=none is deprecated; simply omit --cleanup to keep cleanup disabled.",
+            );
         }
         "standard" => {
-            eprintln!(
-        "warning: --cleanup=standard is deprecated; use --cleanup (boolean) to request standard cleanup."
-      );
+            diagnostics::warning(
+                Phase::Options,
+                "--cleanup=standard is deprecated; use --cleanup (boolean) to request standard cleanup.",
+            );
         }
         "aggressive" => {
-            eprintln!(
-        "warning: --cleanup=aggressive is deprecated; use --cleanup-aggressive in debug mode if you need the old aggressive behavior."
-      );
+            diagnostics::warning(
+                Phase::Options,
+                "--cleanup=aggressive is deprecated; use --cleanup-aggressive in debug mode if you need the old aggressive behavior.",
+            );
         }
         _ => {
-            eprintln!(
-        "warning: --cleanup with an explicit value is deprecated; use --cleanup or --cleanup-aggressive instead."
-      );
+            diagnostics::warning(
+                Phase::Options,
+                "--cleanup with an explicit value is deprecated; use --cleanup or --cleanup-aggressive instead.",
+            );
         }
     }
-    eprintln!("note: use --cleanup for standard cleanup; --cleanup-aggressive remains debug-only.");
+    diagnostics::info(
+        Phase::Options,
+        "note: use --cleanup for standard cleanup; --cleanup-aggressive remains debug-only.",
+    );
 }
 
 fn legacy_warning_once(key: &str) -> bool {
@@ -1063,10 +3064,11 @@ fn warn_legacy_analyze_threshold(flag: &str, config_key: &str) {
         return;
     }
 
-    eprintln!(
-    "warning: {flag} is deprecated; set {config_key} in your .filter-repo-rs.toml (or --config) file instead."
-  );
-    eprintln!("{}", config_assignment_note(config_key));
+    diagnostics::warning(
+                Phase::Options,
+                "{flag} is deprecated; set {config_key} in your .filter-repo-rs.toml (or --config) file instead.",
+            );
+    diagnostics::info(Phase::Options, config_assignment_note(config_key));
 }
 
 fn config_assignment_note(config_key: &str) -> String {
@@ -1085,6 +3087,34 @@ fn config_assignment_example(config_key: &str) -> String {
     format!("{config_key} = {example_value}")
 }
 
+fn parse_analyze_ignore_glob(raw: &str) -> Result<Vec<u8>, FilterRepoError> {
+    normalize_cli_glob_str(raw).map_err(|msg| {
+        FilterRepoError::invalid_options(format!("invalid --analyze-ignore-path '{raw}': {msg}"))
+    })
+}
+
+fn parse_log_format(value: &str) -> Result<LogFormat, FilterRepoError> {
+    match value {
+        "text" => Ok(LogFormat::Text),
+        "json" => Ok(LogFormat::Json),
+        _ => Err(FilterRepoError::invalid_options(
+            "--log-format expects text|json",
+        )),
+    }
+}
+
+fn log_format_requested(args: &[String]) -> LogFormat {
+    let json = args.iter().enumerate().any(|(i, arg)| {
+        arg == "--log-format=json"
+            || (arg == "--log-format" && args.get(i + 1).is_some_and(|v| v == "json"))
+    });
+    if json {
+        LogFormat::Json
+    } else {
+        LogFormat::Text
+    }
+}
+
 fn debug_mode_enabled(args: &[String]) -> bool {
     use std::env;
     if matches!(env::var("FRRS_DEBUG"), Ok(val) if debug_env_flag_enabled(&val)) {
@@ -1093,6 +3123,17 @@ fn debug_mode_enabled(args: &[String]) -> bool {
     args.iter().any(|arg| arg == "--debug-mode")
 }
 
+fn parse_bool_flag(value: &str, flag: &str) -> Result<bool, FilterRepoError> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Ok(true),
+        "false" | "0" | "no" | "off" => Ok(false),
+        _ => Err(FilterRepoError::invalid_options(format!(
+            "{} expects true or false, got '{}'",
+            flag, value
+        ))),
+    }
+}
+
 fn debug_env_flag_enabled(raw: &str) -> bool {
     let normalized = raw.trim().to_ascii_lowercase();
     if normalized.is_empty() {
@@ -1131,7 +3172,11 @@ const KIB: u64 = 1024;
 const MIB: u64 = 1024 * KIB;
 const GIB: u64 = 1024 * MIB;
 
-fn parse_max_blob_size(s: &str) -> Result<usize, ()> {
+const DEFAULT_CONTENT_SCAN_BYTES: usize = 2 * MIB as usize;
+const DEFAULT_PIPELINE_BUFFER_BYTES: usize = 64 * MIB as usize;
+const CONTENT_REGEX_SIZE_LIMIT: usize = 10 << 20;
+
+fn parse_size_with_suffix(s: &str) -> Result<u64, ()> {
     if s.is_empty() {
         return Err(());
     }
@@ -1149,87 +3194,447 @@ fn parse_max_blob_size(s: &str) -> Result<usize, ()> {
     }
 
     let value = parse_integer_allowing_underscores::<u64>(number).map_err(|_| ())?;
-    let scaled = value.checked_mul(multiplier).ok_or(())?;
+    value.checked_mul(multiplier).ok_or(())
+}
 
-    usize::try_from(scaled).map_err(|_| ())
+/// Parses a human-readable byte size: a positive integer, optionally with
+/// a `K`, `M` or `G` suffix (binary, case-insensitive). Underscores may
+/// separate digits. Zero, negative values and other units are rejected.
+pub fn parse_size_str(s: &str) -> Result<u64, String> {
+    if s.is_empty() {
+        return Err("size must not be empty".to_string());
+    }
+    if s.starts_with('-') {
+        return Err(format!("size '{s}' must not be negative"));
+    }
+    match parse_size_with_suffix(s) {
+        Ok(0) => Err("size must be greater than zero".to_string()),
+        Ok(n) => Ok(n),
+        Err(()) => Err(format!(
+            "'{s}' is not a byte size (optionally suffixed with K, M, or G)"
+        )),
+    }
 }
 
-fn parse_u64(s: &str, flag: &str) -> Result<u64, FilterRepoError> {
-    parse_integer_allowing_underscores::<u64>(s).map_err(|_| {
-        FilterRepoError::invalid_options(format!("{} expects an integer number", flag))
-    })
+fn parse_max_blob_size(s: &str) -> Result<usize, String> {
+    let n = parse_size_str(s)?;
+    usize::try_from(n).map_err(|_| format!("size '{s}' is too large for this platform"))
 }
 
-fn parse_usize(s: &str, flag: &str) -> Result<usize, FilterRepoError> {
-    parse_integer_allowing_underscores::<usize>(s).map_err(|_| {
-        FilterRepoError::invalid_options(format!("{} expects an integer number", flag))
-    })
+fn parse_size_flag<T: TryFrom<u64>>(s: &str, flag: &str) -> Result<T, FilterRepoError> {
+    parse_size_with_suffix(s)
+        .ok()
+        .and_then(|n| T::try_from(n).ok())
+        .ok_or_else(|| {
+            FilterRepoError::invalid_options(format!(
+                "{flag} expects an integer number of bytes (optionally suffixed with K, M, or G)"
+            ))
+        })
 }
 
-fn parse_duration(s: &str) -> Result<i64, FilterRepoError> {
-    let s = s.trim();
-    let (sign, rest) = if let Some(stripped) = s.strip_prefix('+') {
-        (1, stripped)
-    } else if let Some(stripped) = s.strip_prefix('-') {
-        (-1, stripped)
-    } else {
-        (1, s)
+fn parse_path_rename_regex(s: &str) -> Result<(Regex, Vec<u8>), FilterRepoError> {
+    let Some((pattern, replacement)) = s.split_once("==>") else {
+        return Err(FilterRepoError::invalid_options(format!(
+            "--path-rename-regex expects PATTERN==>REPLACEMENT, got '{}'",
+            s
+        )));
     };
+    if pattern.is_empty() {
+        return Err(FilterRepoError::invalid_options(
+            "--path-rename-regex pattern must not be empty",
+        ));
+    }
+    let re = Regex::new(pattern).map_err(|err| {
+        FilterRepoError::invalid_options(format!(
+            "invalid --path-rename-regex '{}': {}",
+            pattern, err
+        ))
+    })?;
+    // The replacement only covers the matched part of a path, so only the
+    // segments it fully spells out can be rejected here; the rewritten path
+    // is checked again before it is written. A leading `./` or `../` is
+    // rejected too: it only stays harmless when the match starts mid-segment,
+    // and it is far more likely an attempt to move files out of the tree.
+    let segments: Vec<&str> = replacement.split('/').collect();
+    let interior = segments.len().saturating_sub(1);
+    if segments
+        .iter()
+        .take(interior)
+        .any(|seg| *seg == "." || *seg == "..")
+    {
+        return Err(FilterRepoError::invalid_options(format!(
+            "invalid --path-rename-regex replacement '{}': '.' and '..' segments are not allowed",
+            replacement
+        )));
+    }
+    Ok((re, replacement.as_bytes().to_vec()))
+}
 
-    let parts: Vec<&str> = rest.split_whitespace().collect();
-    let mut total_seconds: i64 = 0;
-
-    for i in (0..parts.len()).step_by(2) {
-        if i + 1 >= parts.len() {
-            return Err(FilterRepoError::invalid_options(
-                "--date-shift expects format like '+2 hours' or '-1 day 3 hours'",
-            ));
-        }
-
-        let value = parse_integer_allowing_underscores::<i64>(parts[i]).map_err(|_| {
-            FilterRepoError::invalid_options(format!("--date-shift: invalid number '{}'", parts[i]))
-        })?;
+fn parse_strip_blobs_matching(s: &str) -> Result<Regex, FilterRepoError> {
+    if s.is_empty() {
+        return Err(FilterRepoError::invalid_options(
+            "--strip-blobs-matching pattern must not be empty",
+        ));
+    }
+    RegexBuilder::new(s)
+        .size_limit(CONTENT_REGEX_SIZE_LIMIT)
+        .dfa_size_limit(CONTENT_REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|err| {
+            FilterRepoError::invalid_options(format!(
+                "invalid --strip-blobs-matching '{}': {}",
+                s, err
+            ))
+        })
+}
 
-        let unit = parts[i + 1].to_lowercase();
-        let multiplier = match unit.as_str() {
-            "second" | "seconds" | "s" => 1,
-            "minute" | "minutes" | "min" | "mins" | "m" => 60,
-            "hour" | "hours" | "h" => 3600,
-            "day" | "days" | "d" => 86400,
-            "week" | "weeks" | "w" => 604800,
-            "month" | "months" | "mo" => 2592000,
-            "year" | "years" | "y" => 31536000,
-            _ => {
-                return Err(FilterRepoError::invalid_options(format!(
-                    "--date-shift: unknown unit '{}'",
-                    parts[i + 1]
-                )));
-            }
-        };
+fn parse_subject_prefix_pattern(s: &str) -> Result<SubjectPrefixPattern, FilterRepoError> {
+    if s.is_empty() {
+        return Err(FilterRepoError::invalid_options(
+            "--strip-subject-prefix pattern must not be empty",
+        ));
+    }
+    let Some(pattern) = s.strip_prefix("regex:") else {
+        return Ok(SubjectPrefixPattern::Glob(s.as_bytes().to_vec()));
+    };
+    let re = Regex::new(&format!("^(?:{})$", pattern)).map_err(|err| {
+        FilterRepoError::invalid_options(format!("invalid --strip-subject-prefix '{}': {}", s, err))
+    })?;
+    Ok(SubjectPrefixPattern::Regex(re))
+}
 
-        total_seconds = total_seconds.saturating_add(value.saturating_mul(multiplier));
+fn parse_detect_emit(s: &str) -> Result<DetectEmit, FilterRepoError> {
+    match s {
+        "global" => Ok(DetectEmit::Global),
+        "scoped" => Ok(DetectEmit::Scoped),
+        "json" => Ok(DetectEmit::Json),
+        _ => Err(FilterRepoError::invalid_options(format!(
+            "--detect-emit: expected global|scoped|json, got '{}'",
+            s
+        ))),
     }
+}
 
-    Ok(total_seconds.saturating_mul(sign))
+fn parse_pruned_tag_policy(s: &str) -> Result<PrunedTagPolicy, FilterRepoError> {
+    match s {
+        "delete" => Ok(PrunedTagPolicy::Delete),
+        "ancestor" => Ok(PrunedTagPolicy::Ancestor),
+        "keep-old" => Ok(PrunedTagPolicy::KeepOld),
+        _ => Err(FilterRepoError::invalid_options(format!(
+            "--pruned-tag: expected delete|ancestor|keep-old, got '{}'",
+            s
+        ))),
+    }
 }
 
-fn parse_timestamp(s: &str) -> Result<i64, FilterRepoError> {
-    let s = s.trim();
+fn parse_post_check(s: &str) -> Result<PostCheck, FilterRepoError> {
+    match s {
+        "connectivity" => Ok(PostCheck::Connectivity),
+        "fsck" => Ok(PostCheck::Fsck),
+        "fsck-full" => Ok(PostCheck::FsckFull),
+        _ => Err(FilterRepoError::invalid_options(format!(
+            "--post-check: expected connectivity|fsck|fsck-full, got '{}'",
+            s
+        ))),
+    }
+}
 
-    if let Ok(ts) = s.parse::<i64>() {
-        return Ok(ts);
+/// Validates the `Name <email>` value of `--override-committer`/`--override-tagger`
+/// so it cannot break the fast-import header it is written into.
+fn parse_identity_override(flag: &str, s: &str) -> Result<Vec<u8>, FilterRepoError> {
+    let invalid = |why: &str| {
+        FilterRepoError::invalid_options(format!(
+            "{flag}: expected 'Name <email>', got '{s}' ({why})"
+        ))
+    };
+    if s.contains(['\n', '\r', '\0']) {
+        return Err(invalid("contains a control character"));
+    }
+    let Some(rest) = s.trim().strip_suffix('>') else {
+        return Err(invalid("must end with <email>"));
+    };
+    let Some((name, email)) = rest.split_once('<') else {
+        return Err(invalid("must end with <email>"));
+    };
+    if name.contains('>') || email.contains(['<', '>']) {
+        return Err(invalid("stray angle bracket"));
+    }
+    let name = name.trim();
+    if name.is_empty() || email.trim().is_empty() {
+        return Err(invalid("name and email must not be empty"));
     }
+    Ok(format!("{name} <{}>", email.trim()).into_bytes())
+}
 
-    if let Ok(ts) = parse_integer_allowing_underscores::<i64>(s) {
-        return Ok(ts);
+fn parse_override_date(flag: &str, s: &str) -> Result<OverrideDate, FilterRepoError> {
+    match s {
+        "keep" => Ok(OverrideDate::Keep),
+        "now" => Ok(OverrideDate::Now),
+        _ => Err(FilterRepoError::invalid_options(format!(
+            "{flag}: expected now|keep, got '{s}'"
+        ))),
     }
+}
 
-    if let Ok(dt) = time::OffsetDateTime::parse(s, &time::format_description::well_known::Rfc3339) {
-        return Ok(dt.unix_timestamp());
+fn parse_mode_normalization(s: &str) -> Result<(Vec<u8>, Vec<u8>), FilterRepoError> {
+    let Some((pattern, mode)) = s.rsplit_once('=') else {
+        return Err(FilterRepoError::invalid_options(format!(
+            "--normalize-mode expects PATTERN=MODE, got '{s}'"
+        )));
+    };
+    if pattern.is_empty() {
+        return Err(FilterRepoError::invalid_options(format!(
+            "--normalize-mode: empty pattern in '{s}'"
+        )));
+    }
+    // Only regular-file modes can be swapped; symlinks and gitlinks change
+    // what the entry points at, not just its permissions.
+    match mode {
+        "100644" | "100755" => Ok((pattern.as_bytes().to_vec(), mode.as_bytes().to_vec())),
+        "120000" | "160000" => Err(FilterRepoError::invalid_options(format!(
+            "--normalize-mode: cannot map onto symlink/gitlink mode {mode}"
+        ))),
+        _ => Err(FilterRepoError::invalid_options(format!(
+            "--normalize-mode: MODE must be 100644 or 100755, got '{mode}'"
+        ))),
     }
+}
 
-    for fmt in [
-        "[year]-[month]-[day] [hour]:[minute]:[second]",
+// Repeated --normalize-eol flags add globs; they must agree on the target.
+fn add_eol_normalization(
+    current: &mut Option<EolNormalization>,
+    s: &str,
+) -> Result<(), FilterRepoError> {
+    let (target, glob) = match s.split_once(':') {
+        Some((target, glob)) => (target, Some(glob)),
+        None => (s, None),
+    };
+    let target = match target {
+        "lf" => EolTarget::Lf,
+        "crlf" => EolTarget::Crlf,
+        _ => {
+            return Err(FilterRepoError::invalid_options(format!(
+                "--normalize-eol expects lf|crlf[:GLOB], got '{s}'"
+            )))
+        }
+    };
+    if glob == Some("") {
+        return Err(FilterRepoError::invalid_options(format!(
+            "--normalize-eol: empty glob in '{s}'"
+        )));
+    }
+    let Some(config) = current else {
+        *current = Some(EolNormalization {
+            target,
+            globs: glob.map(|g| g.as_bytes().to_vec()).into_iter().collect(),
+        });
+        return Ok(());
+    };
+    if config.target != target {
+        return Err(FilterRepoError::invalid_options(
+            "--normalize-eol: cannot convert to both lf and crlf in one run",
+        ));
+    }
+    // Further flags can only add globs to a glob-restricted conversion.
+    match glob {
+        Some(glob) if !config.globs.is_empty() => {
+            config.globs.push(glob.as_bytes().to_vec());
+            Ok(())
+        }
+        _ => Err(FilterRepoError::invalid_options(
+            "--normalize-eol without a GLOB already covers every path; give it once",
+        )),
+    }
+}
+
+// Split names become directory names under --split-output-dir.
+fn parse_split_name(name: &str) -> Result<String, FilterRepoError> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || name.contains(['/', '\\'])
+        || name.chars().any(char::is_control)
+    {
+        return Err(FilterRepoError::invalid_options(format!(
+            "invalid split name '{name}': must be a plain directory name"
+        )));
+    }
+    Ok(name.to_string())
+}
+
+fn parse_split_target(s: &str) -> Result<SplitTarget, FilterRepoError> {
+    let Some((name, specs)) = s.split_once('=') else {
+        return Err(FilterRepoError::invalid_options(format!(
+            "--split-to expects NAME=PATHSPEC[,PATHSPEC...], got '{s}'"
+        )));
+    };
+    let name = parse_split_name(name)?;
+    let paths = specs
+        .split(',')
+        .map(|spec| {
+            normalize_cli_path_str(spec, /*allow_empty=*/ false).map_err(|msg| {
+                FilterRepoError::invalid_options(format!(
+                    "invalid --split-to path '{spec}' for '{name}': {msg}"
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(SplitTarget { name, paths })
+}
+
+fn parse_root_file(s: &str) -> Result<(Vec<u8>, PathBuf), FilterRepoError> {
+    let Some((path, local)) = s.split_once('=') else {
+        return Err(FilterRepoError::invalid_options(format!(
+            "--add-root-file expects PATH=LOCALFILE, got '{s}'"
+        )));
+    };
+    if local.is_empty() {
+        return Err(FilterRepoError::invalid_options(format!(
+            "--add-root-file: empty LOCALFILE in '{s}'"
+        )));
+    }
+    let path = normalize_cli_path_str(path, /*allow_empty=*/ false).map_err(|m| {
+        FilterRepoError::invalid_options(format!("invalid --add-root-file '{s}': {m}"))
+    })?;
+    Ok((path, PathBuf::from(local)))
+}
+
+fn parse_blob_size_per_ext(s: &str) -> Result<Vec<(Vec<u8>, usize)>, FilterRepoError> {
+    let mut out = Vec::new();
+    for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+        let Some((ext, size)) = entry.split_once('=') else {
+            return Err(FilterRepoError::invalid_options(format!(
+                "--max-blob-size-per-ext expects EXT=SIZE entries, got '{entry}'"
+            )));
+        };
+        let ext = ext.trim().trim_start_matches('.');
+        if ext.is_empty() || ext.contains('/') {
+            return Err(FilterRepoError::invalid_options(format!(
+                "--max-blob-size-per-ext: invalid extension in '{entry}'"
+            )));
+        }
+        let limit = parse_max_blob_size(size.trim()).map_err(|e| {
+            FilterRepoError::invalid_options(format!("--max-blob-size-per-ext: {e} in '{entry}'"))
+        })?;
+        out.push((ext.to_ascii_lowercase().into_bytes(), limit));
+    }
+    if out.is_empty() {
+        return Err(FilterRepoError::invalid_options(
+            "--max-blob-size-per-ext expects at least one EXT=SIZE entry",
+        ));
+    }
+    Ok(out)
+}
+
+fn parse_usize(s: &str, flag: &str) -> Result<usize, FilterRepoError> {
+    parse_integer_allowing_underscores::<usize>(s).map_err(|_| {
+        FilterRepoError::invalid_options(format!("{} expects an integer number", flag))
+    })
+}
+
+fn parse_analyze_depth(s: &str) -> Result<usize, FilterRepoError> {
+    match parse_usize(s, "--analyze-depth")? {
+        0 => Err(FilterRepoError::invalid_options(
+            "--analyze-depth expects at least 1 path component",
+        )),
+        depth => Ok(depth),
+    }
+}
+
+// Below this a shortened path shows too little of either end to be useful.
+const MIN_ANALYZE_PATH_WIDTH: usize = 16;
+
+fn parse_analyze_max_path_width(s: &str) -> Result<usize, FilterRepoError> {
+    match parse_usize(s, "--analyze-max-path-width")? {
+        width if width < MIN_ANALYZE_PATH_WIDTH => Err(FilterRepoError::invalid_options(format!(
+            "--analyze-max-path-width expects at least {MIN_ANALYZE_PATH_WIDTH} characters"
+        ))),
+        width => Ok(width),
+    }
+}
+
+fn parse_analyze_max_blob_paths(s: &str) -> Result<usize, FilterRepoError> {
+    match parse_usize(s, "--analyze-max-blob-paths")? {
+        0 => Err(FilterRepoError::invalid_options(
+            "--analyze-max-blob-paths expects at least 1 path",
+        )),
+        n => Ok(n),
+    }
+}
+
+fn parse_ratio(s: &str, flag: &str) -> Result<f64, FilterRepoError> {
+    match s.trim().parse::<f64>() {
+        Ok(v) if (0.0..=1.0).contains(&v) => Ok(v),
+        _ => Err(FilterRepoError::invalid_options(format!(
+            "{} expects a ratio between 0 and 1",
+            flag
+        ))),
+    }
+}
+
+fn parse_duration(s: &str) -> Result<i64, FilterRepoError> {
+    let s = s.trim();
+    let (sign, rest) = if let Some(stripped) = s.strip_prefix('+') {
+        (1, stripped)
+    } else if let Some(stripped) = s.strip_prefix('-') {
+        (-1, stripped)
+    } else {
+        (1, s)
+    };
+
+    let parts: Vec<&str> = rest.split_whitespace().collect();
+    let mut total_seconds: i64 = 0;
+
+    for i in (0..parts.len()).step_by(2) {
+        if i + 1 >= parts.len() {
+            return Err(FilterRepoError::invalid_options(
+                "--date-shift expects format like '+2 hours' or '-1 day 3 hours'",
+            ));
+        }
+
+        let value = parse_integer_allowing_underscores::<i64>(parts[i]).map_err(|_| {
+            FilterRepoError::invalid_options(format!("--date-shift: invalid number '{}'", parts[i]))
+        })?;
+
+        let unit = parts[i + 1].to_lowercase();
+        let multiplier = match unit.as_str() {
+            "second" | "seconds" | "s" => 1,
+            "minute" | "minutes" | "min" | "mins" | "m" => 60,
+            "hour" | "hours" | "h" => 3600,
+            "day" | "days" | "d" => 86400,
+            "week" | "weeks" | "w" => 604800,
+            "month" | "months" | "mo" => 2592000,
+            "year" | "years" | "y" => 31536000,
+            _ => {
+                return Err(FilterRepoError::invalid_options(format!(
+                    "--date-shift: unknown unit '{}'",
+                    parts[i + 1]
+                )));
+            }
+        };
+
+        total_seconds = total_seconds.saturating_add(value.saturating_mul(multiplier));
+    }
+
+    Ok(total_seconds.saturating_mul(sign))
+}
+
+fn parse_timestamp(s: &str) -> Result<i64, FilterRepoError> {
+    let s = s.trim();
+
+    if let Ok(ts) = s.parse::<i64>() {
+        return Ok(ts);
+    }
+
+    if let Ok(ts) = parse_integer_allowing_underscores::<i64>(s) {
+        return Ok(ts);
+    }
+
+    if let Ok(dt) = time::OffsetDateTime::parse(s, &time::format_description::well_known::Rfc3339) {
+        return Ok(dt.unix_timestamp());
+    }
+
+    for fmt in [
+        "[year]-[month]-[day] [hour]:[minute]:[second]",
         "[year]-[month]-[day]T[hour]:[minute]:[second]",
         "[year]-[month]-[day] [hour]:[minute]",
         "[year]-[month]-[day]",
@@ -1473,12 +3878,56 @@ fn get_base_help_sections() -> Vec<HelpSection> {
                     name: "--refs REF".to_string(),
                     description: vec![
                         "Ref to export. Repeatable. Defaults to --all.".to_string(),
+                        "The first --refs replaces that default. Implies --partial".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--ref-filter GLOB".to_string(),
+                    description: vec![
+                        "Only export refs whose full name matches GLOB. Repeatable.".to_string(),
+                        "e.g. 'refs/heads/**'. Implies --partial".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--ref-filter-exclude GLOB".to_string(),
+                    description: vec![
+                        "Skip refs whose full name matches GLOB. Repeatable.".to_string(),
                         "Implies --partial".to_string(),
                     ],
                 },
+                HelpOption {
+                    name: "--include-stash".to_string(),
+                    description: vec![
+                        "Also rewrite refs/stash. --all leaves it out by default".to_string()
+                    ],
+                },
+                HelpOption {
+                    name: "--include-notes".to_string(),
+                    description: vec![
+                        "Also rewrite refs/notes/*. --all leaves them out by default".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--include-remotes".to_string(),
+                    description: vec![
+                        "Also rewrite refs/remotes/*. --all leaves them out by default;"
+                            .to_string(),
+                        "origin's branches are still migrated to refs/heads/* first".to_string(),
+                    ],
+                },
                 HelpOption {
                     name: "--no-data".to_string(),
-                    description: vec!["Do not include blob data in fast-export".to_string()],
+                    description: vec![
+                        "Do not include blob data in fast-export; blobs are passed".to_string(),
+                        "by id. Incompatible with --replace-text and --detect-secrets".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--metadata-only".to_string(),
+                    description: vec![
+                        "Rewrite only refs, messages and identities; implies --no-data".to_string(),
+                        "and rejects options that read or drop blob contents".to_string(),
+                    ],
                 },
             ],
         },
@@ -1505,6 +3954,48 @@ fn get_base_help_sections() -> Vec<HelpSection> {
                     name: "--path-rename OLD:NEW".to_string(),
                     description: vec!["Rename path prefix in file changes".to_string()],
                 },
+                HelpOption {
+                    name: "--path-rename-regex PATTERN==>REPL".to_string(),
+                    description: vec![
+                        "Regex rename on the full path after prefix renames".to_string(),
+                        "(repeatable, applied in order); REPL may use $1 or ${name}".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--no-validate-paths".to_string(),
+                    description: vec![
+                        "Skip warnings for rename rules that match no path at HEAD".to_string(),
+                        "or rewrite the output of an earlier rule".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--synthesize-renames".to_string(),
+                    description: vec![
+                        "Emit 'R old new' instead of D+M when a commit moves an unchanged file"
+                            .to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--normalize-mode PATTERN=MODE".to_string(),
+                    description: vec![
+                        "Force 100644/100755 on files matching glob (repeatable)".to_string(),
+                        "Patterns without '/' match the file name, e.g. *.sh=100755".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--normalize-eol lf|crlf[:GLOB]".to_string(),
+                    description: vec![
+                        "Convert line endings in text blobs after --replace-text".to_string(),
+                        "GLOB limits it to matching paths (repeatable); binaries and lone-CR files are skipped".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--add-root-file PATH=LOCALFILE".to_string(),
+                    description: vec![
+                        "Add LOCALFILE as PATH to every root commit lacking PATH (repeatable)"
+                            .to_string(),
+                    ],
+                },
                 HelpOption {
                     name: "--subdirectory-filter D".to_string(),
                     description: vec!["Equivalent to --path D/ --path-rename D/:".to_string()],
@@ -1521,17 +4012,106 @@ fn get_base_help_sections() -> Vec<HelpSection> {
                 HelpOption {
                     name: "--replace-text FILE".to_string(),
                     description: vec![
-                        "Literal/regex (feature-gated) replacements for blobs".to_string()
+                        "Literal/regex replacements for blobs only; literal".to_string(),
+                        "rules apply first, then regex/glob rules, each in".to_string(),
+                        "file order. Alias: --replace-text-blobs".to_string(),
                     ],
                 },
+                HelpOption {
+                    name: "--blob-callback-so PATH".to_string(),
+                    description: vec![
+                        "Run filter_repo_blob_transform from this shared library".to_string(),
+                        "on every blob after --replace-text (see docs/CALLBACK_ABI.md;".to_string(),
+                        "requires the dynamic-callbacks build feature)".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--max-replace-rules N".to_string(),
+                    description: vec![
+                        "Reject --replace-text/--replace-message files with more than N rules"
+                            .to_string(),
+                        "(default: 1000000)".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--max-replace-rule-bytes SIZE".to_string(),
+                    description: vec![
+                        "Reject rule files whose patterns total more than SIZE (default: 64M)"
+                            .to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--blob-cache DIR".to_string(),
+                    description: vec![
+                        "Remember blobs left unchanged by the current --replace-text".to_string(),
+                        "rules and pass them through on later runs".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--blob-cache-clear".to_string(),
+                    description: vec!["Empty the --blob-cache directory before running".to_string()],
+                },
                 HelpOption {
                     name: "--max-blob-size BYTES".to_string(),
-                    description: vec!["Drop blobs larger than BYTES".to_string()],
+                    description: vec![
+                        "Drop blobs larger than BYTES (K/M/G suffixes allowed)".to_string(),
+                        "Alias: --strip-blobs-bigger-than".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--max-blob-size-per-ext EXT=SIZE[,...]".to_string(),
+                    description: vec![
+                        "Per-extension limits, e.g. zip=50M,png=2M; override --max-blob-size"
+                            .to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--replace-blobs-with-pointer".to_string(),
+                    description: vec![
+                        "Replace blobs dropped by size limits with a small text file".to_string(),
+                        "recording the original size and id".to_string(),
+                    ],
                 },
                 HelpOption {
                     name: "--strip-blobs-with-ids FILE".to_string(),
                     description: vec!["Drop blobs by 40-hex id (one per line)".to_string()],
                 },
+                HelpOption {
+                    name: "--strip-blobs-with-path-matching GLOB".to_string(),
+                    description: vec![
+                        "Remove files whose path matches GLOB (repeatable),".to_string(),
+                        "regardless of --path selections".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--strip-java-class-files".to_string(),
+                    description: vec!["Remove **/*.class".to_string()],
+                },
+                HelpOption {
+                    name: "--strip-node-modules".to_string(),
+                    description: vec!["Remove **/node_modules/**".to_string()],
+                },
+                HelpOption {
+                    name: "--strip-python-cache".to_string(),
+                    description: vec!["Remove **/__pycache__/** and **/*.pyc".to_string()],
+                },
+                HelpOption {
+                    name: "--strip-build-artifacts".to_string(),
+                    description: vec!["All three of the above".to_string()],
+                },
+                HelpOption {
+                    name: "--strip-blobs-matching REGEX".to_string(),
+                    description: vec![
+                        "Drop text blobs whose content matches REGEX (repeatable)".to_string(),
+                        "Binary blobs and blobs over the scan cap are kept".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--strip-blobs-matching-max-bytes BYTES".to_string(),
+                    description: vec![
+                        "Scan cap for --strip-blobs-matching (default: 2M)".to_string()
+                    ],
+                },
             ],
         },
         HelpSection {
@@ -1539,7 +4119,50 @@ fn get_base_help_sections() -> Vec<HelpSection> {
             options: vec![
                 HelpOption {
                     name: "--replace-message FILE".to_string(),
-                    description: vec!["Literal replacements in commit/tag messages".to_string()],
+                    description: vec![
+                        "Literal/regex replacements in commit/tag messages".to_string(),
+                        "only, in the --replace-text order. Alias:".to_string(),
+                        "--replace-text-messages".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--tag-message-file FILE".to_string(),
+                    description: vec![
+                        "Replacement rules for annotated tag messages only;".to_string(),
+                        "tags then ignore the --replace-message rules".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--max-tag-message-size BYTES".to_string(),
+                    description: vec![
+                        "Fail when a rewritten tag message is larger than this".to_string(),
+                        "(default 512M, the stream data limit); supports K/M/G".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--truncate-tag-messages".to_string(),
+                    description: vec![
+                        "Cut tag messages over --max-tag-message-size to the".to_string(),
+                        "limit instead of failing".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--strip-subject-prefix PATTERN".to_string(),
+                    description: vec![
+                        "Drop leading [TOKEN] prefixes from commit subjects (repeatable)"
+                            .to_string(),
+                        "PATTERN is a glob over TOKEN, or regex:RE".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--strip-pr-suffix".to_string(),
+                    description: vec!["Drop a trailing ' (#123)' from commit subjects".to_string()],
+                },
+                HelpOption {
+                    name: "--also-tags".to_string(),
+                    description: vec![
+                        "Apply subject normalization to annotated tag messages too".to_string()
+                    ],
                 },
                 HelpOption {
                     name: "--mailmap FILE".to_string(),
@@ -1570,10 +4193,54 @@ fn get_base_help_sections() -> Vec<HelpSection> {
                         "Format: oldEmail==>newEmail (one per line)".to_string(),
                     ],
                 },
+                HelpOption {
+                    name: "--author-date-is-committer-date".to_string(),
+                    description: vec![
+                        "Set each author date (timestamp and timezone) to the committer date"
+                            .to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--committer-date-is-author-date".to_string(),
+                    description: vec![
+                        "Set each committer date to the author date, like git rebase"
+                            .to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--override-committer 'NAME <EMAIL>'".to_string(),
+                    description: vec![
+                        "Set the committer of every rewritten commit, after".to_string(),
+                        "--mailmap and the rewrite rules; authors are kept".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--override-committer-date now|keep".to_string(),
+                    description: vec![
+                        "Committer date on stamped commits: keep the original".to_string(),
+                        "(default) or use the time of the run".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--override-tagger 'NAME <EMAIL>'".to_string(),
+                    description: vec!["Set the tagger of every annotated tag".to_string()],
+                },
+                HelpOption {
+                    name: "--override-tagger-date now|keep".to_string(),
+                    description: vec!["Tagger date on stamped tags, as above".to_string()],
+                },
                 HelpOption {
                     name: "--tag-rename OLD:NEW".to_string(),
                     description: vec!["Rename tags with given prefix".to_string()],
                 },
+                HelpOption {
+                    name: "--update-tag-name-references".to_string(),
+                    description: vec![
+                        "With --tag-rename, also rewrite whole-word mentions".to_string(),
+                        "of renamed tags in commit and tag messages, after".to_string(),
+                        "--replace-message rules".to_string(),
+                    ],
+                },
                 HelpOption {
                     name: "--branch-rename OLD:NEW".to_string(),
                     description: vec!["Rename branches with given prefix".to_string()],
@@ -1618,6 +4285,15 @@ fn get_base_help_sections() -> Vec<HelpSection> {
                         "  never: Keep all degenerate merges".to_string(),
                     ],
                 },
+                HelpOption {
+                    name: "--pruned-tag {delete|ancestor|keep-old}".to_string(),
+                    description: vec![
+                        "Handle tags whose commit was pruned (default: delete)".to_string(),
+                        "  delete: Remove the tag and list it in the report".to_string(),
+                        "  ancestor: Move the tag to the nearest surviving ancestor".to_string(),
+                        "  keep-old: Leave the tag on the original, unrewritten commit".to_string(),
+                    ],
+                },
                 HelpOption {
                     name: "--no-ff".to_string(),
                     description: vec![
@@ -1640,6 +4316,30 @@ fn get_base_help_sections() -> Vec<HelpSection> {
                         "Write .git/filter-repo/report.json (machine-readable)".to_string()
                     ],
                 },
+                HelpOption {
+                    name: "--write-migration-log PATH".to_string(),
+                    description: vec![
+                        "Append a timestamped JSON line to PATH for every kept/pruned commit,"
+                            .to_string(),
+                        "rewritten blob and renamed ref".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--reproducible".to_string(),
+                    description: vec![
+                        "Renumber marks in emission order and take timestamps from".to_string(),
+                        "SOURCE_DATE_EPOCH (default 0), so identical source and options"
+                            .to_string(),
+                        "give an identical filtered stream and commit-map".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--pipeline-buffer-bytes BYTES".to_string(),
+                    description: vec![
+                        "Filtered stream queued for fast-import before filtering waits".to_string(),
+                        "(default: 64M)".to_string(),
+                    ],
+                },
                 HelpOption {
                     name: "--path-compat-policy {sanitize|skip|error}".to_string(),
                     description: vec![
@@ -1647,6 +4347,14 @@ fn get_base_help_sections() -> Vec<HelpSection> {
                         "Current scope: enforced only when running on Windows hosts".to_string(),
                     ],
                 },
+                HelpOption {
+                    name: "--unsafe-path-policy {error|skip}".to_string(),
+                    description: vec![
+                        "Rewritten paths that are absolute or contain ./.. segments or NUL"
+                            .to_string(),
+                        "abort the run (default) or are dropped and reported".to_string(),
+                    ],
+                },
                 HelpOption {
                     name: "--cleanup".to_string(),
                     description: vec![
@@ -1655,10 +4363,24 @@ fn get_base_help_sections() -> Vec<HelpSection> {
                             .to_string(),
                     ],
                 },
+                HelpOption {
+                    name: "--keep-unreachable".to_string(),
+                    description: vec![
+                        "Cleanup keeps objects only the old history used (gc --prune=never)"
+                            .to_string(),
+                    ],
+                },
                 HelpOption {
                     name: "--quiet".to_string(),
                     description: vec!["Reduce output noise".to_string()],
                 },
+                HelpOption {
+                    name: "--log-format text|json".to_string(),
+                    description: vec![
+                        "Write notices and warnings to stderr as text (default)".to_string(),
+                        "or as one JSON object per line".to_string(),
+                    ],
+                },
                 HelpOption {
                     name: "-f, --force".to_string(),
                     description: vec![
@@ -1689,9 +4411,77 @@ fn get_base_help_sections() -> Vec<HelpSection> {
                         "Repeatable. First capture group is used when present.".to_string(),
                     ],
                 },
+                HelpOption {
+                    name: "--detect-patterns-file FILE".to_string(),
+                    description: vec![
+                        "Named rules for --detect-secrets, one per line:".to_string(),
+                        "name<TAB>severity<TAB>regex or name==>regex, with an".to_string(),
+                        "optional ' group=N' suffix; '#' starts a comment".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--detect-no-builtin".to_string(),
+                    description: vec![
+                        "Skip the built-in --detect-secrets patterns and scan".to_string(),
+                        "with only --detect-pattern/--detect-patterns-file rules".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--help-patterns".to_string(),
+                    description: vec![
+                        "List the built-in --detect-secrets patterns with".to_string(),
+                        "an example match for each, then exit".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--detect-scan-messages".to_string(),
+                    description: vec!["Also scan commit messages with --detect-secrets".to_string()],
+                },
+                HelpOption {
+                    name: "--detect-emit MODE".to_string(),
+                    description: vec![
+                        "Draft format for --detect-secrets: global (default),".to_string(),
+                        "scoped (path:PATH:VALUE==>... per path found), or json".to_string(),
+                        "(detected-secrets.json, usable with --replace-text)".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--migrate-conflict POLICY".to_string(),
+                    description: vec![
+                        "When refs/heads/X exists and differs from".to_string(),
+                        "refs/remotes/origin/X: skip (default, keep the".to_string(),
+                        "branch), overwrite, or error".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--ignore-replace-refs".to_string(),
+                    description: vec![
+                        "Export the original objects instead of refusing".to_string(),
+                        "to run when refs/replace/* exist".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--allow-shallow".to_string(),
+                    description: vec![
+                        "Rewrite a shallow clone anyway: boundary commits".to_string(),
+                        "become roots and older history is dropped".to_string(),
+                    ],
+                },
                 HelpOption {
                     name: "--partial".to_string(),
-                    description: vec!["Only rewrite current repo; skip remote cleanup".to_string()],
+                    description: vec![
+                        "Rewrite only the selected refs; other refs, origin's".to_string(),
+                        "remote-tracking refs and the origin remote stay as they".to_string(),
+                        "are, and reflogs are not expired".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--watch-interval SECONDS".to_string(),
+                    description: vec![
+                        "After the initial rewrite, keep running and re-filter new".to_string(),
+                        "commits on branches and tags every SECONDS; state and".to_string(),
+                        "heartbeat go to .git/filter-repo/watch-state.json".to_string(),
+                    ],
                 },
                 HelpOption {
                     name: "--sensitive".to_string(),
@@ -1700,6 +4490,13 @@ fn get_base_help_sections() -> Vec<HelpSection> {
                         "avoid remote cleanup; see --no-fetch)".to_string(),
                     ],
                 },
+                HelpOption {
+                    name: "--sensitive-redact-paths".to_string(),
+                    description: vec![
+                        "Also redact secrets in file and directory names: scanned".to_string(),
+                        "with --detect-secrets, rewritten with --replace-text".to_string(),
+                    ],
+                },
                 HelpOption {
                     name: "--no-fetch".to_string(),
                     description: vec![
@@ -1728,6 +4525,66 @@ fn get_base_help_sections() -> Vec<HelpSection> {
                         ".git/filter-repo/backup-<timestamp>.bundle".to_string(),
                     ],
                 },
+                HelpOption {
+                    name: "--output-bundle PATH".to_string(),
+                    description: vec![
+                        "Write the rewritten history to a bundle at PATH".to_string(),
+                        "instead of updating refs; the source repository".to_string(),
+                        "is left untouched".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--split-to NAME=PATHSPEC[,PATHSPEC...]".to_string(),
+                    description: vec![
+                        "Write the history of these paths to a new bare".to_string(),
+                        "repository NAME.git (repeatable). fast-export runs".to_string(),
+                        "once; each split then filters that stream in turn".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--split-output-dir DIR".to_string(),
+                    description: vec!["Directory the --split-to repositories are created in".to_string()],
+                },
+                HelpOption {
+                    name: "--split-rest NAME".to_string(),
+                    description: vec![
+                        "Also write paths matching no --split-to set to NAME.git".to_string(),
+                        "(dropped otherwise)".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--clone-to PATH".to_string(),
+                    description: vec![
+                        "Mirror-clone the source to PATH (git clone --no-local".to_string(),
+                        "--mirror) and filter the clone; the source is left".to_string(),
+                        "untouched".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--clone-local".to_string(),
+                    description: vec![
+                        "Let --clone-to hardlink objects instead of copying".to_string(),
+                        "them; faster, but not for sensitive-data removal".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--post-check {connectivity|fsck|fsck-full}".to_string(),
+                    description: vec![
+                        "Verify the rewritten repository before reporting".to_string(),
+                        "success; exits with status 3 if the check fails".to_string(),
+                        "  connectivity: git rev-list --objects --all".to_string(),
+                        "  fsck: git fsck --no-dangling --connectivity-only".to_string(),
+                        "  fsck-full: git fsck --no-dangling --full (slowest)".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--resume-ref-update".to_string(),
+                    description: vec![
+                        "Finish the ref updates of an interrupted run from".to_string(),
+                        ".git/filter-repo/ref-plan.json; refuses if any ref".to_string(),
+                        "moved since the plan was written".to_string(),
+                    ],
+                },
             ],
         },
         HelpSection {
@@ -1741,12 +4598,82 @@ fn get_base_help_sections() -> Vec<HelpSection> {
                     name: "--analyze-json".to_string(),
                     description: vec!["Emit JSON-formatted analysis report".to_string()],
                 },
+                HelpOption {
+                    name: "--analyze-json-compact[=BOOL]".to_string(),
+                    description: vec![
+                        "Emit single-line JSON (implies --analyze-json);".to_string(),
+                        "--analyze-json-pretty=false is equivalent".to_string(),
+                    ],
+                },
                 HelpOption {
                     name: "--analyze-top N".to_string(),
                     description: vec![
                         "Number of largest blobs/trees to show (default: 10)".to_string()
                     ],
                 },
+                HelpOption {
+                    name: "--analyze-depth N".to_string(),
+                    description: vec![
+                        "Roll blob sizes up by their first N path components".to_string(),
+                        "(every prefix in JSON; the table shows --analyze-top)".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--analyze-max-path-width N".to_string(),
+                    description: vec![
+                        "Shorten longer paths in the middle in tables (default: 60)".to_string(),
+                        "JSON output always has the full path".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--analyze-max-blob-paths N".to_string(),
+                    description: vec![
+                        "List up to N paths per large blob, from a full history walk".to_string(),
+                        "path_count keeps the full number".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--analyze-plain".to_string(),
+                    description: vec![
+                        "Print tables as tab-separated values, for cut/awk".to_string()
+                    ],
+                },
+                HelpOption {
+                    name: "--analyze-bad-identity PATTERN".to_string(),
+                    description: vec![
+                        "Flag identities containing PATTERN, e.g. root@localhost (repeatable)"
+                            .to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--analyze-baseline FILE".to_string(),
+                    description: vec![
+                        "Compare with a saved --analyze-json report and show".to_string(),
+                        "size growth, new large blobs and new warnings".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--warn-pack-count N".to_string(),
+                    description: vec![
+                        "Warn when the repository has more than N packs (default: 50;".to_string(),
+                        "same as analyze.thresholds.warn_pack_count)".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--warn-loose-ratio F".to_string(),
+                    description: vec![
+                        "Warn when more than this share of objects is loose (default: 0.1;"
+                            .to_string(),
+                        "same as analyze.thresholds.warn_loose_object_ratio)".to_string(),
+                    ],
+                },
+                HelpOption {
+                    name: "--analyze-ignore-path GLOB".to_string(),
+                    description: vec![
+                        "Leave blobs whose path matches GLOB (e.g. 'vendor/**')".to_string(),
+                        "out of the blob metrics (repeatable)".to_string(),
+                    ],
+                },
             ],
         },
     ]
@@ -0,0 +1,112 @@
+//! `--output-bundle PATH`: write the rewritten history to a bundle instead
+//! of updating the source repository.
+//!
+//! The normal pipeline imports into a scratch bare repository under the
+//! system temp directory, and `git bundle create PATH --all` is run from
+//! there. The source repository is only read by fast-export: its refs,
+//! remotes and reflogs are left exactly as they were.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::Result as FilterRepoResult;
+use crate::gitutil;
+use crate::opts::{CleanupMode, Options};
+
+static SCRATCH_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Removes the scratch repository however the run ends.
+struct ScratchRepo(PathBuf);
+
+impl ScratchRepo {
+    fn create() -> io::Result<Self> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let counter = SCRATCH_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!(
+            "filter-repo-rs-bundle-{}-{}",
+            std::process::id(),
+            nanos + counter as u128
+        ));
+        let status = Command::new("git")
+            .args(["init", "--bare", "-q"])
+            .arg(&path)
+            .status()
+            .map_err(|e| io::Error::other(format!("failed to run git init: {e}")))?;
+        let scratch = ScratchRepo(path);
+        if !status.success() {
+            return Err(io::Error::other(format!(
+                "git init --bare {} failed: {status}",
+                scratch.0.display()
+            )));
+        }
+        Ok(scratch)
+    }
+}
+
+impl Drop for ScratchRepo {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+pub(crate) fn run(opts: &Options, bundle: &Path) -> FilterRepoResult<()> {
+    // `git bundle create` runs inside the scratch repo, so anchor relative
+    // paths to the current directory first.
+    let bundle = if bundle.is_absolute() {
+        bundle.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(bundle)
+    };
+    let scratch = ScratchRepo::create()?;
+    crate::rewrite(&Options {
+        target: scratch.0.clone(),
+        output_bundle: None,
+        // The scratch repo is empty by design, and nothing in the source may
+        // change: no backup, no origin migration, no reset or repack.
+        force: true,
+        backup: false,
+        partial: true,
+        reset: false,
+        cleanup: CleanupMode::None,
+        ..opts.clone()
+    })?;
+
+    let refs: BTreeMap<String, String> = gitutil::get_all_refs(&scratch.0)?.into_iter().collect();
+    if refs.is_empty() {
+        return Err(io::Error::other("no refs survived filtering; nothing to bundle").into());
+    }
+    if let Some(parent) = bundle.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    let status = Command::new("git")
+        .arg("-C")
+        .arg(&scratch.0)
+        .args(["bundle", "create", "-q"])
+        .arg(&bundle)
+        .arg("--all")
+        .status()
+        .map_err(|e| io::Error::other(format!("failed to run git bundle create: {e}")))?;
+    if !status.success() {
+        return Err(io::Error::other(format!("git bundle create failed: {status}")).into());
+    }
+
+    println!(
+        "Bundle written to {} ({} ref(s)):",
+        bundle.display(),
+        refs.len()
+    );
+    for (name, oid) in &refs {
+        println!("  {oid} {name}");
+    }
+    Ok(())
+}
@@ -25,9 +25,40 @@ impl PathCompatPolicy {
     }
 }
 
+/// What to do with a rewritten path that could escape the repository:
+/// absolute, with a `.` or `..` segment, or containing NUL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnsafePathPolicy {
+    #[default]
+    Error,
+    Skip,
+}
+
+impl UnsafePathPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UnsafePathPolicy::Error => "error",
+            UnsafePathPolicy::Skip => "skip",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "error" => Some(UnsafePathPolicy::Error),
+            "skip" => Some(UnsafePathPolicy::Skip),
+            _ => None,
+        }
+    }
+}
+
+use std::collections::HashMap;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum PathCompatAction {
     Sanitized,
+    /// Sanitized onto a path another original already took, so a numeric
+    /// suffix was added to keep both files.
+    SanitizedWithCollision,
     Skipped,
 }
 
@@ -37,6 +68,75 @@ pub struct PathCompatEvent {
     pub original: Vec<u8>,
     pub rewritten: Option<Vec<u8>>,
     pub reason: String,
+    /// For `SanitizedWithCollision`: the original that owns the plain
+    /// sanitized path.
+    pub collides_with: Option<Vec<u8>>,
+}
+
+/// Written path, and the original that owned the plain sanitized path when
+/// a suffix was needed.
+type Assignment = (Vec<u8>, Option<Vec<u8>>);
+
+/// Which original path each sanitized path belongs to, for the whole run.
+///
+/// `a?.txt` and `a*.txt` both sanitize to `a_.txt`; the first one seen keeps
+/// it and later ones get `a__1.txt`, `a__2.txt`, ... Paths written as they
+/// are own themselves, so a real `a_.txt` seen first pushes `a?.txt` to a
+/// suffix too. Assignments are stable, so later changes to the same original
+/// land on the same path.
+#[derive(Debug, Default)]
+pub struct SanitizedPathTable {
+    assigned: HashMap<Vec<u8>, Assignment>,
+    owners: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+impl SanitizedPathTable {
+    /// Records a path written without sanitizing, so no sanitized path is
+    /// put on top of it.
+    pub fn keep(&mut self, path: &[u8]) {
+        if !self.owners.contains_key(path) {
+            self.owners.insert(path.to_vec(), path.to_vec());
+        }
+    }
+
+    /// Returns the path to write for `original`, plus the original that
+    /// already owns `sanitized` when a suffix was needed.
+    pub fn claim(&mut self, original: &[u8], sanitized: Vec<u8>) -> Assignment {
+        if let Some(assigned) = self.assigned.get(original) {
+            return assigned.clone();
+        }
+        let (path, collides_with) = match self.owners.get(&sanitized) {
+            None => (sanitized, None),
+            Some(owner) => {
+                let owner = owner.clone();
+                let path = (1..)
+                    .map(|n| with_numeric_suffix(&sanitized, n))
+                    .find(|candidate| !self.owners.contains_key(candidate))
+                    .expect("unbounded suffix search");
+                (path, Some(owner))
+            }
+        };
+        self.owners.insert(path.clone(), original.to_vec());
+        self.assigned
+            .insert(original.to_vec(), (path.clone(), collides_with.clone()));
+        (path, collides_with)
+    }
+}
+
+/// `dir/a_.txt` -> `dir/a__1.txt`; names without an extension get the
+/// suffix at the end, and a leading dot does not start an extension.
+fn with_numeric_suffix(path: &[u8], n: usize) -> Vec<u8> {
+    let name_start = path.iter().rposition(|&b| b == b'/').map_or(0, |i| i + 1);
+    let split = path[name_start..]
+        .iter()
+        .rposition(|&b| b == b'.')
+        .filter(|&i| i > 0)
+        .map_or(path.len(), |i| name_start + i);
+    let mut out = Vec::with_capacity(path.len() + 4);
+    out.extend_from_slice(&path[..split]);
+    out.extend_from_slice(format!("_{n}").as_bytes());
+    out.extend_from_slice(&path[split..]);
+    out
 }
 
 fn windows_path_compat_reasons(path: &[u8]) -> Vec<&'static str> {
@@ -67,21 +167,69 @@ fn summarize_windows_path_compat_reason(path: &[u8]) -> String {
     }
 }
 
+/// Why `path` must not reach fast-import, or `None` when it is a plain
+/// relative path.
+pub fn unsafe_path_reason(path: &[u8]) -> Option<&'static str> {
+    if path.contains(&0) {
+        Some("contains a NUL byte")
+    } else if path.first() == Some(&b'/') {
+        Some("is absolute")
+    } else if path.split(|&b| b == b'/').any(|seg| seg == b"..") {
+        Some("contains a '..' segment")
+    } else if path.split(|&b| b == b'/').any(|seg| seg == b".") {
+        Some("contains a '.' segment")
+    } else {
+        None
+    }
+}
+
+/// Checks a rewritten path against [`unsafe_path_reason`]. Unsafe paths are
+/// an error, or a `Skipped` event under [`UnsafePathPolicy::Skip`].
+pub fn apply_unsafe_path_policy(
+    path: &[u8],
+    policy: UnsafePathPolicy,
+) -> Result<Option<PathCompatEvent>, String> {
+    let Some(reason) = unsafe_path_reason(path) else {
+        return Ok(None);
+    };
+    match policy {
+        UnsafePathPolicy::Error => Err(format!(
+            "refusing to write unsafe path {}: path {}; it came from the \
+             fast-export stream or was produced by --path-rename/--path-rename-regex; \
+             use --unsafe-path-policy=skip to drop such paths",
+            format_path_bytes_for_report(path),
+            reason
+        )),
+        UnsafePathPolicy::Skip => Ok(Some(PathCompatEvent {
+            action: PathCompatAction::Skipped,
+            original: path.to_vec(),
+            rewritten: None,
+            reason: format!("path {reason}"),
+            collides_with: None,
+        })),
+    }
+}
+
 pub fn format_path_bytes_for_report(path: &[u8]) -> String {
-    let mut out = String::with_capacity(path.len() + 2);
-    out.push('"');
+    format!("\"{}\"", escape_path_bytes(path))
+}
+
+/// `path` with non-printable and non-ASCII bytes escaped, without the quotes
+/// [`format_path_bytes_for_report`] adds.
+pub fn escape_path_bytes(path: &[u8]) -> String {
+    let mut out = String::with_capacity(path.len());
     for &b in path {
         for c in std::ascii::escape_default(b) {
             out.push(c as char);
         }
     }
-    out.push('"');
     out
 }
 
 pub fn apply_path_compat_policy(
     path: &[u8],
     policy: PathCompatPolicy,
+    sanitized_paths: &mut SanitizedPathTable,
 ) -> Result<(Option<Vec<u8>>, Option<PathCompatEvent>), String> {
     if !cfg!(windows) {
         return Ok((Some(path.to_vec()), None));
@@ -89,20 +237,30 @@ pub fn apply_path_compat_policy(
 
     let sanitized = sanitize_invalid_windows_path_bytes(path);
     if sanitized == path {
+        sanitized_paths.keep(path);
         return Ok((Some(path.to_vec()), None));
     }
 
     let reason = summarize_windows_path_compat_reason(path);
     match policy {
-        PathCompatPolicy::Sanitize => Ok((
-            Some(sanitized.clone()),
-            Some(PathCompatEvent {
-                action: PathCompatAction::Sanitized,
-                original: path.to_vec(),
-                rewritten: Some(sanitized),
-                reason,
-            }),
-        )),
+        PathCompatPolicy::Sanitize => {
+            let (rewritten, collides_with) = sanitized_paths.claim(path, sanitized);
+            let action = if collides_with.is_some() {
+                PathCompatAction::SanitizedWithCollision
+            } else {
+                PathCompatAction::Sanitized
+            };
+            Ok((
+                Some(rewritten.clone()),
+                Some(PathCompatEvent {
+                    action,
+                    original: path.to_vec(),
+                    rewritten: Some(rewritten),
+                    reason,
+                    collides_with,
+                }),
+            ))
+        }
         PathCompatPolicy::Skip => Ok((
             None,
             Some(PathCompatEvent {
@@ -110,6 +268,7 @@ pub fn apply_path_compat_policy(
                 original: path.to_vec(),
                 rewritten: None,
                 reason,
+                collides_with: None,
             }),
         )),
         PathCompatPolicy::Error => Err(format!(
@@ -371,8 +530,13 @@ pub fn encode_path_for_fi(bytes: &[u8]) -> Vec<u8> {
 pub fn encode_path_for_fi_with_policy(
     bytes: &[u8],
     policy: PathCompatPolicy,
+    unsafe_policy: UnsafePathPolicy,
+    sanitized_paths: &mut SanitizedPathTable,
 ) -> Result<(Option<Vec<u8>>, Option<PathCompatEvent>), String> {
-    let (maybe_path, event) = apply_path_compat_policy(bytes, policy)?;
+    if let Some(event) = apply_unsafe_path_policy(bytes, unsafe_policy)? {
+        return Ok((None, Some(event)));
+    }
+    let (maybe_path, event) = apply_path_compat_policy(bytes, policy, sanitized_paths)?;
     let encoded = maybe_path.map(|p| encode_path_for_fi(&p));
     Ok((encoded, event))
 }
@@ -431,6 +595,24 @@ pub fn needs_c_style_quote(bytes: &[u8]) -> bool {
 
 #[allow(dead_code)]
 pub fn glob_match_bytes(pat: &[u8], text: &[u8]) -> bool {
+    // Fast paths for patterns that select a whole tree: `**` matches
+    // everything and `<literal prefix>/**` is a plain directory prefix.
+    if pat == b"**" {
+        return true;
+    }
+    if let Some(prefix) = pat.strip_suffix(b"/**") {
+        if !prefix.iter().any(|&b| b == b'*' || b == b'?') {
+            return text.len() > prefix.len()
+                && text.starts_with(prefix)
+                && text[prefix.len()] == b'/';
+        }
+    }
+    glob_match_bytes_recursive(pat, text)
+}
+
+/// General recursive matcher behind [`glob_match_bytes`], without fast paths.
+#[doc(hidden)]
+pub fn glob_match_bytes_recursive(pat: &[u8], text: &[u8]) -> bool {
     fn match_from(p: &[u8], t: &[u8]) -> bool {
         // Fast path: exact match
         if p.is_empty() {
@@ -489,3 +671,118 @@ pub fn glob_match_bytes(pat: &[u8], text: &[u8]) -> bool {
     }
     match_from(pat, text)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_fast_paths_agree_with_recursive_matcher() {
+        let paths: [&[u8]; 8] = [
+            b"",
+            b"src",
+            b"src/",
+            b"src/main.rs",
+            b"src/a/b/c.rs",
+            b"srcx/main.rs",
+            b"lib/src/main.rs",
+            b"vendor/src/x",
+        ];
+        for pat in [&b"**"[..], b"src/**", b"lib/src/**", b"s?c/**", b"*/**"] {
+            for path in paths {
+                assert_eq!(
+                    glob_match_bytes(pat, path),
+                    glob_match_bytes_recursive(pat, path),
+                    "pattern {:?} path {:?}",
+                    String::from_utf8_lossy(pat),
+                    String::from_utf8_lossy(path)
+                );
+            }
+        }
+    }
+    #[test]
+    fn unsafe_paths_are_rejected_by_default_and_reported_when_skipped() {
+        let cases: [(&[u8], &str); 5] = [
+            (b"../../outside.txt", "'..' segment"),
+            (b"src/../../etc/passwd", "'..' segment"),
+            (b"src/./main.rs", "'.' segment"),
+            (b"/etc/passwd", "absolute"),
+            (b"src/a\0b.rs", "NUL byte"),
+        ];
+        for (path, reason) in cases {
+            assert!(unsafe_path_reason(path).unwrap().contains(reason));
+            let err = apply_unsafe_path_policy(path, UnsafePathPolicy::Error).unwrap_err();
+            assert!(err.contains(&format_path_bytes_for_report(path)), "{err}");
+            let event = apply_unsafe_path_policy(path, UnsafePathPolicy::Skip)
+                .unwrap()
+                .expect("unsafe path is reported");
+            assert_eq!(event.action, PathCompatAction::Skipped);
+            assert_eq!(event.original, path);
+            let (encoded, _) = encode_path_for_fi_with_policy(
+                path,
+                PathCompatPolicy::Sanitize,
+                UnsafePathPolicy::Skip,
+                &mut SanitizedPathTable::default(),
+            )
+            .unwrap();
+            assert_eq!(encoded, None);
+        }
+        for path in [&b"src/..hidden/a..b"[..], b".github/x.yml", b"a/.b"] {
+            assert_eq!(unsafe_path_reason(path), None);
+            assert_eq!(
+                apply_unsafe_path_policy(path, UnsafePathPolicy::Error),
+                Ok(None)
+            );
+        }
+    }
+
+    #[test]
+    fn sanitized_path_table_suffixes_later_collisions_stably() {
+        let mut table = SanitizedPathTable::default();
+        assert_eq!(
+            table.claim(b"docs/a?.txt", b"docs/a_.txt".to_vec()),
+            (b"docs/a_.txt".to_vec(), None)
+        );
+        assert_eq!(
+            table.claim(b"docs/a*.txt", b"docs/a_.txt".to_vec()),
+            (b"docs/a__1.txt".to_vec(), Some(b"docs/a?.txt".to_vec()))
+        );
+        assert_eq!(
+            table.claim(b"docs/a|.txt", b"docs/a_.txt".to_vec()).0,
+            b"docs/a__2.txt".to_vec()
+        );
+        // The same original keeps its assignment.
+        assert_eq!(
+            table.claim(b"docs/a*.txt", b"docs/a_.txt".to_vec()).0,
+            b"docs/a__1.txt".to_vec()
+        );
+    }
+
+    #[test]
+    fn sanitized_path_never_lands_on_a_path_kept_as_is() {
+        let mut table = SanitizedPathTable::default();
+        table.keep(b"docs/a_.txt");
+        table.keep(b"docs/a__1.txt");
+        assert_eq!(
+            table.claim(b"docs/a?.txt", b"docs/a_.txt".to_vec()),
+            (b"docs/a__2.txt".to_vec(), Some(b"docs/a_.txt".to_vec()))
+        );
+        // Keeping a path a sanitized original already owns changes nothing.
+        table.keep(b"docs/a__2.txt");
+        assert_eq!(
+            table.claim(b"docs/a*.txt", b"docs/a_.txt".to_vec()).0,
+            b"docs/a__3.txt".to_vec()
+        );
+        assert_eq!(
+            table.claim(b"docs/a?.txt", b"docs/a_.txt".to_vec()).0,
+            b"docs/a__2.txt".to_vec()
+        );
+    }
+
+    #[test]
+    fn numeric_suffix_goes_before_the_extension() {
+        assert_eq!(with_numeric_suffix(b"a_.tar.gz", 1), b"a_.tar_1.gz");
+        assert_eq!(with_numeric_suffix(b"dir.d/x_", 2), b"dir.d/x__2");
+        assert_eq!(with_numeric_suffix(b".env_", 1), b".env__1");
+    }
+}
@@ -0,0 +1,291 @@
+//! Bounded hand-off between the filter loop and `git fast-import`.
+//!
+//! The filtered stream is queued in memory and written to fast-import's
+//! stdin by a dedicated thread, so the filter loop keeps parsing while
+//! fast-import digests a large blob. The queue holds at most
+//! `--pipeline-buffer-bytes`; once it is full, writes block until the import
+//! side catches up, which in turn stops the loop from reading fast-export.
+
+use std::collections::VecDeque;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+
+/// Queued byte counts, readable while the pipeline runs.
+#[derive(Debug, Default)]
+pub struct PipelineGauge {
+    queued: AtomicUsize,
+    peak: AtomicUsize,
+}
+
+impl PipelineGauge {
+    /// Bytes accepted from the filter loop but not yet written to the import.
+    pub fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Largest value `queued` reached.
+    pub fn peak(&self) -> usize {
+        self.peak.load(Ordering::Relaxed)
+    }
+
+    fn set(&self, queued: usize) {
+        self.queued.store(queued, Ordering::Relaxed);
+        self.peak.fetch_max(queued, Ordering::Relaxed);
+    }
+}
+
+enum Chunk {
+    Data(Vec<u8>),
+    Flush,
+}
+
+#[derive(Default)]
+struct State {
+    chunks: VecDeque<Chunk>,
+    queued: usize,
+    flushes_requested: u64,
+    flushes_done: u64,
+    closed: bool,
+    // io::Error is not Clone; every later write reports the same failure.
+    error: Option<(io::ErrorKind, String)>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+    gauge: Arc<PipelineGauge>,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn wait<'a>(&self, guard: MutexGuard<'a, State>) -> MutexGuard<'a, State> {
+        self.changed.wait(guard).unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+fn stored_error(state: &State) -> Option<io::Error> {
+    state
+        .error
+        .as_ref()
+        .map(|(kind, msg)| io::Error::new(*kind, msg.clone()))
+}
+
+/// Writer whose bytes reach `inner` on a background thread, with at most
+/// `budget` bytes waiting in between.
+pub(crate) struct PipelineWriter {
+    shared: Arc<Shared>,
+    budget: usize,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl PipelineWriter {
+    pub(crate) fn spawn<W: Write + Send + 'static>(inner: W, budget: usize) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State::default()),
+            changed: Condvar::new(),
+            gauge: Arc::new(PipelineGauge::default()),
+        });
+        let worker = {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || drain(inner, &shared))
+        };
+        Self {
+            shared,
+            budget: budget.max(1),
+            worker: Some(worker),
+        }
+    }
+
+    pub(crate) fn gauge(&self) -> Arc<PipelineGauge> {
+        Arc::clone(&self.shared.gauge)
+    }
+
+    /// Writes everything still queued and closes `inner`.
+    pub(crate) fn finish(&mut self) -> io::Result<()> {
+        let Some(worker) = self.worker.take() else {
+            return Ok(());
+        };
+        self.shared.lock().closed = true;
+        self.shared.changed.notify_all();
+        let _ = worker.join();
+        match stored_error(&self.shared.lock()) {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Write for PipelineWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        // Chunks larger than the budget are queued in budget-sized pieces.
+        let len = buf.len().min(self.budget);
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(err) = stored_error(&state) {
+                return Err(err);
+            }
+            if state.queued + len <= self.budget {
+                break;
+            }
+            state = self.shared.wait(state);
+        }
+        state.chunks.push_back(Chunk::Data(buf[..len].to_vec()));
+        state.queued += len;
+        self.shared.gauge.set(state.queued);
+        drop(state);
+        self.shared.changed.notify_all();
+        Ok(len)
+    }
+
+    /// Blocks until every queued byte has been written and `inner` flushed,
+    /// so a request sent before the flush has reached fast-import.
+    fn flush(&mut self) -> io::Result<()> {
+        let mut state = self.shared.lock();
+        state.flushes_requested += 1;
+        let ticket = state.flushes_requested;
+        state.chunks.push_back(Chunk::Flush);
+        self.shared.changed.notify_all();
+        loop {
+            if let Some(err) = stored_error(&state) {
+                return Err(err);
+            }
+            if state.flushes_done >= ticket {
+                return Ok(());
+            }
+            state = self.shared.wait(state);
+        }
+    }
+}
+
+impl Drop for PipelineWriter {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+fn drain<W: Write>(mut inner: W, shared: &Shared) {
+    loop {
+        let chunk = {
+            let mut state = shared.lock();
+            loop {
+                if let Some(chunk) = state.chunks.pop_front() {
+                    break Some(chunk);
+                }
+                if state.closed {
+                    break None;
+                }
+                state = shared.wait(state);
+            }
+        };
+        let result = match &chunk {
+            Some(Chunk::Data(bytes)) => inner.write_all(bytes),
+            Some(Chunk::Flush) | None => inner.flush(),
+        };
+        let mut state = shared.lock();
+        if let Err(err) = result {
+            state.error = Some((err.kind(), err.to_string()));
+            state.chunks.clear();
+            state.queued = 0;
+            shared.gauge.set(0);
+            drop(state);
+            shared.changed.notify_all();
+            return;
+        }
+        match chunk {
+            Some(Chunk::Data(bytes)) => {
+                state.queued -= bytes.len();
+                shared.gauge.set(state.queued);
+            }
+            Some(Chunk::Flush) => state.flushes_done += 1,
+            None => return,
+        }
+        drop(state);
+        shared.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    /// Stands in for a fast-import that is slow to read its stdin.
+    struct SlowSink {
+        received: Arc<Mutex<Vec<u8>>>,
+        delay: Duration,
+    }
+
+    impl Write for SlowSink {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            thread::sleep(self.delay);
+            let n = buf.len().min(4096);
+            self.received.lock().unwrap().extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    struct BrokenSink;
+
+    impl Write for BrokenSink {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "import exited"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn slow_import_never_queues_more_than_the_budget() {
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let sink = SlowSink {
+            received: Arc::clone(&received),
+            delay: Duration::from_micros(200),
+        };
+        let budget = 64 * 1024;
+        let mut writer = PipelineWriter::spawn(sink, budget);
+        let gauge = writer.gauge();
+
+        let blob: Vec<u8> = (0..=255u8).cycle().take(100_000).collect();
+        let mut expected = Vec::new();
+        for i in 0..20 {
+            let header = format!("blob\nmark :{}\ndata {}\n", i + 1, blob.len());
+            writer.write_all(header.as_bytes()).unwrap();
+            writer.write_all(&blob).unwrap();
+            expected.extend_from_slice(header.as_bytes());
+            expected.extend_from_slice(&blob);
+            assert!(gauge.queued() <= budget);
+        }
+        writer.flush().unwrap();
+        assert_eq!(gauge.queued(), 0);
+        writer.finish().unwrap();
+
+        assert!(gauge.peak() > 0);
+        assert!(gauge.peak() <= budget, "peak {}", gauge.peak());
+        assert_eq!(*received.lock().unwrap(), expected);
+    }
+
+    #[test]
+    fn import_errors_surface_on_later_writes() {
+        let mut writer = PipelineWriter::spawn(BrokenSink, 1024);
+        writer.write_all(b"commit refs/heads/main\n").unwrap();
+        let err = writer.flush().expect_err("sink is broken");
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        let err = writer.write_all(b"more").expect_err("sink is broken");
+        assert_eq!(err.kind(), io::ErrorKind::BrokenPipe);
+        assert!(writer.finish().is_err());
+    }
+}
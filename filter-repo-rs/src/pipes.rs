@@ -1,19 +1,26 @@
-use std::io;
+use std::io::{self, BufReader, Read};
 use std::path::Path;
 use std::process::{Command, Stdio};
 
 use crate::gitutil::git_dir;
 use crate::opts::Options;
+use crate::pathutil::glob_match_bytes;
+use crate::refs::RefSnapshot;
 
-pub fn build_fast_export_cmd(opts: &Options) -> io::Result<Command> {
+/// The fast-export stream a run filters: its own fast-export's stdout, or
+/// one copy of the stream `--split-to` fans out.
+pub type ExportReader = BufReader<Box<dyn Read + Send>>;
+
+/// `source_refs` is the snapshot `--all` and `--ref-filter` expand against.
+pub fn build_fast_export_cmd(opts: &Options, source_refs: &RefSnapshot) -> io::Result<Command> {
     // Test override: if provided in opts, read a prebuilt stream from that file
-    if let Some(stream_path) = &opts.fe_stream_override {
-        if !opts.debug_mode {
-            return Err(io::Error::new(
-                io::ErrorKind::PermissionDenied,
-                "error: --fe_stream_override is gated behind debug mode. Set FRRS_DEBUG=1 or pass --debug-mode to access debug-only flags.",
-            ));
-        }
+    if opts.fe_stream_override.is_some() && !opts.debug_mode {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            "error: --fe_stream_override is gated behind debug mode. Set FRRS_DEBUG=1 or pass --debug-mode to access debug-only flags.",
+        ));
+    }
+    if let Some(stream_path) = opts.fe_stream_override.as_ref() {
         #[cfg(windows)]
         {
             let mut cmd = Command::new("cmd");
@@ -44,8 +51,11 @@ pub fn build_fast_export_cmd(opts: &Options) -> io::Result<Command> {
     if opts.quotepath {
         cmd.arg("-c").arg("core.quotepath=false");
     }
+    if opts.ignore_replace_refs {
+        cmd.env("GIT_NO_REPLACE_OBJECTS", "1");
+    }
     cmd.arg("fast-export");
-    for r in &opts.refs {
+    for r in fast_export_refs(opts, source_refs)? {
         cmd.arg(r);
     }
     cmd.arg("--show-original-ids")
@@ -54,22 +64,13 @@ pub fn build_fast_export_cmd(opts: &Options) -> io::Result<Command> {
         .arg("--fake-missing-tagger")
         .arg("--reference-excluded-parents")
         .arg("--use-done-feature");
-    if opts.date_order {
+    if opts.reproducible {
+        // The default order, spelled out so the stream does not depend on it.
+        cmd.arg("--topo-order");
+    } else if opts.date_order {
         cmd.arg("--date-order");
     }
-    // Emit --no-data only when explicitly requested or clearly safe and useful
-    // Safe auto-enable criteria:
-    // - Writing back into the same repository (object store available)
-    // - No blob content replacements requested
-    // - Performing blob filtering by id/size (no need to see blob payloads)
-    let auto_no_data = {
-        let same_repo = opts.source == opts.target;
-        let no_content_replace = opts.replace_text_file.is_none();
-        let id_or_size_filters =
-            opts.max_blob_size.is_some() || opts.strip_blobs_with_ids.is_some();
-        same_repo && no_content_replace && id_or_size_filters
-    };
-    if opts.no_data || auto_no_data {
+    if uses_no_data(opts) {
         cmd.arg("--no-data");
     }
     if opts.reencode {
@@ -107,6 +108,222 @@ pub fn build_fast_export_cmd(opts: &Options) -> io::Result<Command> {
     Ok(cmd)
 }
 
+/// Whether fast-export runs with `--no-data`: when explicitly requested
+/// (`--no-data`, `--metadata-only`) or clearly safe and useful, i.e.
+/// - Writing back into the same repository (object store available)
+/// - No blob content replacements or content matching requested
+/// - Performing blob filtering by id/size (no need to see blob payloads)
+pub(crate) fn uses_no_data(opts: &Options) -> bool {
+    let auto_no_data = {
+        let same_repo = opts.source == opts.target;
+        let no_content_replace = opts.replace_text_file.is_none()
+            && opts.blob_callback_so.is_none()
+            && opts.normalize_eol.is_none()
+            && opts.strip_blobs_matching.is_empty();
+        let id_or_size_filters = opts.has_blob_size_limits() || opts.strip_blobs_with_ids.is_some();
+        // Whether blobs are in the stream must not depend on where it goes.
+        !opts.reproducible && same_repo && no_content_replace && id_or_size_filters
+    };
+    opts.no_data || opts.metadata_only || auto_no_data
+}
+
+/// Namespaces `--all` leaves out unless the `--include-*` flag next to them
+/// is given. A namespace without a trailing slash is a single ref.
+const OPT_IN_REF_NAMESPACES: [(&str, &str); 3] = [
+    ("refs/stash", "--include-stash"),
+    ("refs/notes/", "--include-notes"),
+    ("refs/remotes/", "--include-remotes"),
+];
+
+/// What `--all` always exports; every other ref needs an `--include-*` flag
+/// or is left alone (`refs/pull/*`, `refs/changes/*`, custom namespaces).
+const DEFAULT_REF_NAMESPACES: [&str; 2] = ["refs/heads/", "refs/tags/"];
+
+/// The opt-in namespaces this run skips, with the flag that would keep them.
+/// `--sensitive` must scrub every ref, so it skips none.
+pub(crate) fn excluded_ref_namespaces(opts: &Options) -> Vec<(&'static str, &'static str)> {
+    if opts.sensitive {
+        return Vec::new();
+    }
+    OPT_IN_REF_NAMESPACES
+        .into_iter()
+        .filter(|(namespace, _)| match *namespace {
+            "refs/stash" => !opts.include_stash,
+            "refs/notes/" => !opts.include_notes,
+            _ => !opts.include_remotes,
+        })
+        .collect()
+}
+
+/// Whether `--all` exports `name`: branches, tags and the opt-in namespaces
+/// whose flag was given. `--sensitive` exports every ref.
+fn exported_by_all(opts: &Options, name: &str) -> bool {
+    if opts.sensitive {
+        return true;
+    }
+    let excluded = excluded_ref_namespaces(opts);
+    DEFAULT_REF_NAMESPACES
+        .iter()
+        .chain(OPT_IN_REF_NAMESPACES.iter().map(|(namespace, _)| namespace))
+        .any(|namespace| in_ref_namespace(name, namespace))
+        && !excluded
+            .iter()
+            .any(|(namespace, _)| in_ref_namespace(name, namespace))
+}
+
+fn in_ref_namespace(name: &str, namespace: &str) -> bool {
+    if namespace.ends_with('/') {
+        name.starts_with(namespace)
+    } else {
+        name == namespace
+    }
+}
+
+/// `refs/heads/main` -> `refs/heads/`; refs directly under `refs/` (such as
+/// `refs/stash`) are their own namespace.
+fn ref_namespace(name: &str) -> &str {
+    match name.strip_prefix("refs/").and_then(|rest| rest.find('/')) {
+        Some(slash) => &name[..5 + slash + 1],
+        None => name,
+    }
+}
+
+/// Which namespaces `--all` exported and which it skipped, for the report.
+/// `None` unless `opts.refs` contains `--all`.
+pub(crate) fn ref_namespaces(
+    opts: &Options,
+    source_refs: &RefSnapshot,
+) -> Option<crate::finalize::RefNamespacesReport> {
+    if !opts.refs.iter().any(|r| r == "--all") {
+        return None;
+    }
+    let excluded = excluded_ref_namespaces(opts);
+    let mut included = std::collections::BTreeSet::new();
+    let mut skipped: std::collections::BTreeMap<(String, Option<&'static str>), usize> =
+        std::collections::BTreeMap::new();
+    for name in source_refs.refs.keys() {
+        if !ref_selected(opts, name.as_bytes()) {
+            continue;
+        }
+        if exported_by_all(opts, name) {
+            included.insert(ref_namespace(name).to_string());
+            continue;
+        }
+        let key = match excluded
+            .iter()
+            .find(|(namespace, _)| in_ref_namespace(name, namespace))
+        {
+            Some((namespace, flag)) => (namespace.to_string(), Some(*flag)),
+            None => (ref_namespace(name).to_string(), None),
+        };
+        *skipped.entry(key).or_default() += 1;
+    }
+    Some(crate::finalize::RefNamespacesReport {
+        included: included.into_iter().collect(),
+        excluded: skipped
+            .into_iter()
+            .map(
+                |((namespace, flag), refs)| crate::finalize::ExcludedRefNamespace {
+                    namespace,
+                    refs,
+                    flag,
+                },
+            )
+            .collect(),
+    })
+}
+
+/// Refs passed to fast-export: `opts.refs`, narrowed by `--ref-filter` and
+/// `--ref-filter-exclude`. `--all` becomes the explicit set it stands for
+/// (see [`exported_by_all`]), as rev-list options or, for the filters, as
+/// the expanded ref list.
+fn fast_export_refs(opts: &Options, source_refs: &RefSnapshot) -> io::Result<Vec<String>> {
+    if opts.ref_filter_globs.is_empty() && opts.ref_filter_globs_exclude.is_empty() {
+        let mut refs = Vec::with_capacity(opts.refs.len());
+        for r in &opts.refs {
+            if r == "--all" {
+                refs.extend(all_export_args(opts));
+            } else {
+                refs.push(r.clone());
+            }
+        }
+        return Ok(refs);
+    }
+    let mut candidates = Vec::new();
+    for r in &opts.refs {
+        if r == "--all" {
+            candidates.extend(
+                source_refs
+                    .refs
+                    .keys()
+                    .filter(|name| exported_by_all(opts, name))
+                    .cloned(),
+            );
+        } else {
+            candidates.push(r.clone());
+        }
+    }
+    let selected: Vec<String> = candidates
+        .into_iter()
+        .filter(|name| ref_selected(opts, name.as_bytes()))
+        .collect();
+    if selected.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "no refs match --ref-filter/--ref-filter-exclude",
+        ));
+    }
+    Ok(selected)
+}
+
+/// rev-list arguments for `--all`: branches, tags and `HEAD` (which only
+/// adds commits when it is detached), plus the opt-in namespaces asked for.
+fn all_export_args(opts: &Options) -> Vec<String> {
+    if opts.sensitive {
+        return vec!["--all".to_string()];
+    }
+    let mut args = vec!["--branches".to_string(), "--tags".to_string()];
+    if opts.include_notes {
+        args.push("--glob=refs/notes/*".to_string());
+    }
+    if opts.include_remotes {
+        args.push("--remotes".to_string());
+    }
+    // Named directly: these may not exist, and fast-export rejects a name
+    // that does not resolve.
+    for (rev, wanted) in [("refs/stash", opts.include_stash), ("HEAD", true)] {
+        if wanted && resolves(&opts.source, rev) {
+            args.push(rev.to_string());
+        }
+    }
+    args
+}
+
+pub(crate) fn resolves(repo: &Path, rev: &str) -> bool {
+    Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["rev-parse", "--verify", "-q"])
+        .arg(format!("{rev}^{{commit}}"))
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+pub(crate) fn ref_selected(opts: &Options, name: &[u8]) -> bool {
+    let included = opts.ref_filter_globs.is_empty()
+        || opts
+            .ref_filter_globs
+            .iter()
+            .any(|g| glob_match_bytes(g, name));
+    included
+        && !opts
+            .ref_filter_globs_exclude
+            .iter()
+            .any(|g| glob_match_bytes(g, name))
+}
+
 pub fn build_fast_import_cmd(opts: &Options) -> Command {
     let mut cmd = Command::new("git");
     cmd.arg("-C").arg(&opts.target);
@@ -153,7 +370,7 @@ mod tests {
             ..Options::default()
         };
 
-        let cmd = build_fast_export_cmd(&opts).expect("command");
+        let cmd = build_fast_export_cmd(&opts, &RefSnapshot::default()).expect("command");
         let args = args_as_strings(&cmd);
         assert!(
             !args.iter().any(|arg| arg == "--reencode=yes"),
@@ -171,7 +388,7 @@ mod tests {
         opts.git_caps.fast_export_mark_tags = false;
         opts.mark_tags_requested = Some(true);
 
-        let err = build_fast_export_cmd(&opts).expect_err("should fail");
+        let err = build_fast_export_cmd(&opts, &RefSnapshot::default()).expect_err("should fail");
         let msg = err.to_string();
         assert!(msg.contains("git >= 2.24.0"), "unexpected msg: {msg}");
     }
@@ -182,11 +399,26 @@ mod tests {
         opts.git_caps.fast_export_reencode = false;
         opts.reencode_requested = Some(true);
 
-        let err = build_fast_export_cmd(&opts).expect_err("should fail");
+        let err = build_fast_export_cmd(&opts, &RefSnapshot::default()).expect_err("should fail");
         let msg = err.to_string();
         assert!(msg.contains("git >= 2.23.0"), "unexpected msg: {msg}");
     }
 
+    #[test]
+    fn ref_filter_selects_by_include_and_exclude_globs() {
+        let opts = Options {
+            ref_filter_globs: vec![b"refs/heads/**".to_vec(), b"refs/tags/*".to_vec()],
+            ref_filter_globs_exclude: vec![b"refs/heads/wip/**".to_vec()],
+            ..Options::default()
+        };
+        assert!(ref_selected(&opts, b"refs/heads/main"));
+        assert!(ref_selected(&opts, b"refs/heads/feature/x"));
+        assert!(ref_selected(&opts, b"refs/tags/v1"));
+        assert!(!ref_selected(&opts, b"refs/heads/wip/tmp"));
+        assert!(!ref_selected(&opts, b"refs/remotes/origin/main"));
+        assert!(!ref_selected(&opts, b"refs/pull/1/head"));
+    }
+
     #[test]
     fn fast_import_respects_raw_permissive_capability() {
         let temp = TempDir::new().unwrap();
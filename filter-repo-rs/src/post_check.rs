@@ -0,0 +1,78 @@
+//! `--post-check`: verify the rewritten repository before reporting success.
+//!
+//! Runs after the ref updates and cleanup, so it sees exactly what the user
+//! will push. An interrupted or misbehaving fast-import can leave refs
+//! pointing at trees or blobs that never made it into the object store;
+//! without a check the run still ends with "New history written".
+//!
+//! The checks are opt-in because they walk every reachable object, which on
+//! a large repository can take longer than the rewrite itself:
+//!
+//! * `connectivity`: `git rev-list --objects --all`, which fails on the
+//!   first missing object;
+//! * `fsck`: `git fsck --no-dangling --connectivity-only`, which reports
+//!   every missing object;
+//! * `fsck-full`: `git fsck --no-dangling --full`, which also re-hashes and
+//!   parses every object.
+
+use std::io;
+use std::process::{Command, Stdio};
+use std::time::Instant;
+
+use crate::diagnostics::{self, Phase};
+use crate::finalize::PostCheckReport;
+use crate::opts::{Options, PostCheck};
+
+pub(crate) fn run(opts: &Options, check: PostCheck) -> io::Result<PostCheckReport> {
+    let mut cmd = Command::new("git");
+    cmd.arg("-C").arg(&opts.target);
+    match check {
+        // Only the exit status and stderr matter; the object list can be huge.
+        PostCheck::Connectivity => {
+            cmd.args(["rev-list", "--objects", "--all"]);
+            cmd.stdout(Stdio::null());
+        }
+        PostCheck::Fsck => {
+            cmd.args([
+                "fsck",
+                "--no-dangling",
+                "--connectivity-only",
+                "--no-progress",
+            ]);
+        }
+        PostCheck::FsckFull => {
+            cmd.args(["fsck", "--no-dangling", "--full", "--no-progress"]);
+        }
+    }
+    if !opts.quiet {
+        diagnostics::info(
+            Phase::Finalize,
+            format!("Running post-check {}...", check.as_str()),
+        );
+    }
+    let start = Instant::now();
+    let out = cmd.output().map_err(|e| {
+        io::Error::other(format!("failed to run post-check {}: {e}", check.as_str()))
+    })?;
+    let elapsed = start.elapsed();
+    let mut output = String::from_utf8_lossy(&out.stdout).into_owned();
+    output.push_str(&String::from_utf8_lossy(&out.stderr));
+    let report = PostCheckReport {
+        check: check.as_str(),
+        passed: out.status.success(),
+        elapsed_ms: elapsed.as_millis() as u64,
+        output: output.trim_end().to_string(),
+    };
+    if !opts.quiet {
+        diagnostics::info(
+            Phase::Finalize,
+            format!(
+                "Post-check {} {} in {}",
+                report.check,
+                if report.passed { "passed" } else { "failed" },
+                crate::progress::format_elapsed(elapsed)
+            ),
+        );
+    }
+    Ok(report)
+}
@@ -0,0 +1,424 @@
+//! Progress and color handling for stderr, shared by analysis and the rewrite
+//! pipeline.
+//!
+//! When stderr is a terminal, progress redraws a single line with `\r` and the
+//! bar is sized from `COLUMNS`. Otherwise (CI logs, redirected output) a plain
+//! line is written every [`PLAIN_LINE_EVERY`] items or [`PLAIN_LINE_INTERVAL`],
+//! whichever comes first. A `BrokenPipe` on stderr silently disables further
+//! progress output.
+
+use std::io::{self, IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+/// Items between terminal redraws (and between plain-mode interval checks).
+const UPDATE_EVERY: usize = 1000;
+/// Items between plain-line updates when stderr is not a terminal.
+pub(crate) const PLAIN_LINE_EVERY: usize = 10_000;
+/// Longest gap between plain-line updates when stderr is not a terminal.
+pub(crate) const PLAIN_LINE_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_WIDTH: usize = 80;
+const MIN_BAR_WIDTH: usize = 10;
+const MAX_BAR_WIDTH: usize = 50;
+
+pub(crate) fn color_output_enabled(is_terminal: bool, no_color: bool, force_color: bool) -> bool {
+    if no_color {
+        return false;
+    }
+    is_terminal || force_color
+}
+
+/// `FORCE_COLOR` forces color unless it is set to `0` or `false`.
+fn force_color_requested(value: Option<&std::ffi::OsStr>) -> bool {
+    match value.and_then(|v| v.to_str()) {
+        Some(v) => !matches!(v.trim().to_ascii_lowercase().as_str(), "0" | "false"),
+        None => value.is_some(),
+    }
+}
+
+fn color_enabled_for(is_terminal: bool) -> bool {
+    color_output_enabled(
+        is_terminal,
+        std::env::var_os("NO_COLOR").is_some(),
+        force_color_requested(std::env::var_os("FORCE_COLOR").as_deref()),
+    )
+}
+
+pub(crate) fn stdout_supports_color() -> bool {
+    color_enabled_for(io::stdout().is_terminal())
+}
+
+pub(crate) fn stderr_supports_color() -> bool {
+    color_enabled_for(io::stderr().is_terminal())
+}
+
+/// Terminal width from `COLUMNS`, falling back to 80 columns.
+fn terminal_width(columns: Option<&str>) -> usize {
+    columns
+        .and_then(|c| c.trim().parse::<usize>().ok())
+        .filter(|&w| w > 0)
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+pub(crate) fn format_elapsed(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs < 60 {
+        format!("{:.1}s", secs as f64 + duration.subsec_nanos() as f64 / 1e9)
+    } else if secs < 3600 {
+        format!("{}m {}s", secs / 60, secs % 60)
+    } else {
+        format!("{}h {}m", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
+/// Items per second, or 0 before any time has elapsed.
+pub(crate) fn rate_per_sec(processed: usize, elapsed: Duration) -> f64 {
+    let secs = elapsed.as_secs_f64();
+    if secs > 0.0 {
+        processed as f64 / secs
+    } else {
+        0.0
+    }
+}
+
+fn percent(processed: usize, total: usize) -> usize {
+    if total == 0 {
+        return 100;
+    }
+    (processed.min(total) as u128 * 100 / total as u128) as usize
+}
+
+/// Single-line status without a bar, used for plain output and for progress
+/// with no known total.
+fn format_status(label: &str, processed: usize, total: Option<usize>, elapsed: Duration) -> String {
+    let counts = match total {
+        Some(total) => format!("{}% ({}/{})", percent(processed, total), processed, total),
+        None => processed.to_string(),
+    };
+    format!(
+        "[*] {} {} {} ({:.0}/s)",
+        label,
+        counts,
+        format_elapsed(elapsed),
+        rate_per_sec(processed, elapsed)
+    )
+}
+
+/// Terminal line with a bar sized to fit `width` columns.
+fn format_bar_line(label: &str, processed: usize, total: usize, width: usize) -> String {
+    let pct = percent(processed, total);
+    let prefix = format!("[*] {} [", label);
+    let suffix = format!("] {}% ({}/{})", pct, processed, total);
+    let bar_width = width
+        .saturating_sub(prefix.len() + suffix.len() + 1)
+        .clamp(MIN_BAR_WIDTH, MAX_BAR_WIDTH);
+    let filled = pct * bar_width / 100;
+    format!(
+        "{}{}{}{}",
+        prefix,
+        "=".repeat(filled),
+        " ".repeat(bar_width - filled),
+        suffix
+    )
+}
+
+pub(crate) fn flush_progress_writer<W: Write>(writer: &mut W) -> io::Result<bool> {
+    match writer.flush() {
+        Ok(()) => Ok(true),
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// Writes and flushes; returns `Ok(false)` once the reader has gone away.
+fn write_progress<W: Write>(writer: &mut W, text: &str) -> io::Result<bool> {
+    match writer.write_all(text.as_bytes()) {
+        Ok(()) => flush_progress_writer(writer),
+        Err(err) if err.kind() == io::ErrorKind::BrokenPipe => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RenderMode {
+    /// Redraw in place; the value is the terminal width.
+    Terminal(usize),
+    Plain,
+}
+
+/// Progress reporter for a long-running loop over `label` items.
+pub(crate) struct Progress<W: Write = io::Stderr> {
+    writer: W,
+    label: &'static str,
+    total: Option<usize>,
+    mode: RenderMode,
+    start: Instant,
+    last_plain_at: Instant,
+    last_plain_count: usize,
+    enabled: bool,
+    drawn: bool,
+    // Extra status sampled on each redraw, such as queued import bytes.
+    detail: Option<Box<dyn Fn() -> String>>,
+}
+
+impl Progress {
+    /// Progress on stderr, rendered for a terminal or as plain lines.
+    pub(crate) fn stderr(label: &'static str, total: Option<usize>) -> Self {
+        let mode = if io::stderr().is_terminal() {
+            RenderMode::Terminal(terminal_width(std::env::var("COLUMNS").ok().as_deref()))
+        } else {
+            RenderMode::Plain
+        };
+        let mut progress = Self::with_writer(io::stderr(), label, total, mode);
+        // Progress lines would break one-JSON-object-per-line stderr.
+        progress.enabled = !crate::diagnostics::json_enabled();
+        progress
+    }
+}
+
+impl<W: Write> Progress<W> {
+    fn with_writer(writer: W, label: &'static str, total: Option<usize>, mode: RenderMode) -> Self {
+        let now = Instant::now();
+        Self {
+            writer,
+            label,
+            total,
+            mode,
+            start: now,
+            last_plain_at: now,
+            last_plain_count: 0,
+            enabled: true,
+            drawn: false,
+            detail: None,
+        }
+    }
+
+    /// Appends `detail()` to every status line.
+    pub(crate) fn with_detail(mut self, detail: impl Fn() -> String + 'static) -> Self {
+        self.detail = Some(Box::new(detail));
+        self
+    }
+
+    /// Reports that `processed` items are done. Cheap to call for every item.
+    pub(crate) fn update(&mut self, processed: usize) -> io::Result<()> {
+        if !self.enabled || processed == 0 || !processed.is_multiple_of(UPDATE_EVERY) {
+            return Ok(());
+        }
+        let now = Instant::now();
+        let line = match self.mode {
+            RenderMode::Terminal(width) => format!("\r{}", self.terminal_line(processed, width)),
+            RenderMode::Plain => {
+                if processed - self.last_plain_count < PLAIN_LINE_EVERY
+                    && now.duration_since(self.last_plain_at) < PLAIN_LINE_INTERVAL
+                {
+                    return Ok(());
+                }
+                self.last_plain_at = now;
+                self.last_plain_count = processed;
+                format!(
+                    "{}\n",
+                    self.with_detail_suffix(format_status(
+                        self.label,
+                        processed,
+                        self.total,
+                        now - self.start
+                    ))
+                )
+            }
+        };
+        self.emit(&line)
+    }
+
+    /// Writes the final state, if any progress was shown.
+    pub(crate) fn finish(&mut self, processed: usize) -> io::Result<()> {
+        if !self.enabled || !self.drawn {
+            return Ok(());
+        }
+        let line = match self.mode {
+            RenderMode::Terminal(width) => {
+                format!("\r{}\n", self.terminal_line(processed, width))
+            }
+            RenderMode::Plain if processed == self.last_plain_count => return Ok(()),
+            RenderMode::Plain => format!(
+                "{}\n",
+                self.with_detail_suffix(format_status(
+                    self.label,
+                    processed,
+                    self.total,
+                    self.start.elapsed()
+                ))
+            ),
+        };
+        self.emit(&line)
+    }
+
+    fn terminal_line(&self, processed: usize, width: usize) -> String {
+        let line = match self.total {
+            Some(total) => format_bar_line(self.label, processed, total, width),
+            None => format_status(self.label, processed, None, self.start.elapsed()),
+        };
+        self.with_detail_suffix(line)
+    }
+
+    fn with_detail_suffix(&self, line: String) -> String {
+        match &self.detail {
+            Some(detail) => format!("{} {}", line, detail()),
+            None => line,
+        }
+    }
+
+    fn emit(&mut self, line: &str) -> io::Result<()> {
+        self.enabled = write_progress(&mut self.writer, line)?;
+        self.drawn = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::ErrorKind;
+
+    struct ErrorWriter {
+        kind: ErrorKind,
+        writes: usize,
+    }
+
+    impl Write for ErrorWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.writes += 1;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::Error::new(self.kind, "forced flush error"))
+        }
+    }
+
+    #[test]
+    fn flush_progress_writer_treats_broken_pipe_as_non_fatal() {
+        let mut writer = ErrorWriter {
+            kind: ErrorKind::BrokenPipe,
+            writes: 0,
+        };
+        let result = flush_progress_writer(&mut writer);
+        assert!(result.is_ok(), "BrokenPipe should not propagate as error");
+        assert!(
+            !result.expect("BrokenPipe should map to non-fatal false"),
+            "BrokenPipe should return false to indicate no further progress output"
+        );
+    }
+
+    #[test]
+    fn flush_progress_writer_propagates_other_flush_errors() {
+        let mut writer = ErrorWriter {
+            kind: ErrorKind::PermissionDenied,
+            writes: 0,
+        };
+        let result = flush_progress_writer(&mut writer);
+        assert!(
+            result.is_err(),
+            "non-BrokenPipe flush errors should propagate"
+        );
+    }
+
+    #[test]
+    fn broken_pipe_disables_further_progress_output() {
+        let writer = ErrorWriter {
+            kind: ErrorKind::BrokenPipe,
+            writes: 0,
+        };
+        let mut progress =
+            Progress::with_writer(writer, "commits", Some(5000), RenderMode::Terminal(80));
+        progress.update(1000).unwrap();
+        progress.update(2000).unwrap();
+        progress.finish(5000).unwrap();
+        assert_eq!(progress.writer.writes, 1);
+    }
+
+    #[test]
+    fn color_output_enabled_respects_no_color_and_force_color() {
+        assert!(color_output_enabled(true, false, false));
+        assert!(!color_output_enabled(false, false, false));
+        assert!(color_output_enabled(false, false, true));
+        assert!(!color_output_enabled(true, true, false));
+        assert!(!color_output_enabled(false, true, true));
+    }
+
+    #[test]
+    fn force_color_zero_or_false_does_not_force() {
+        use std::ffi::OsStr;
+        assert!(!force_color_requested(None));
+        assert!(force_color_requested(Some(OsStr::new(""))));
+        assert!(force_color_requested(Some(OsStr::new("1"))));
+        assert!(!force_color_requested(Some(OsStr::new("0"))));
+        assert!(!force_color_requested(Some(OsStr::new("FALSE"))));
+    }
+
+    #[test]
+    fn plain_mode_writes_one_line_per_interval_without_carriage_returns() {
+        let mut progress = Progress::with_writer(
+            Vec::new(),
+            "Processing commits",
+            Some(25_000),
+            RenderMode::Plain,
+        );
+        for processed in 1..=25_000 {
+            progress.update(processed).unwrap();
+        }
+        progress.finish(25_000).unwrap();
+        let out = String::from_utf8(progress.writer).unwrap();
+        assert!(!out.contains('\r'), "plain output must not redraw: {out:?}");
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), 3, "{out}");
+        assert!(lines[0].starts_with("[*] Processing commits 40% (10000/25000) "));
+        assert!(lines[1].starts_with("[*] Processing commits 80% (20000/25000) "));
+        assert!(lines[2].starts_with("[*] Processing commits 100% (25000/25000) "));
+    }
+
+    #[test]
+    fn detail_is_sampled_for_each_line() {
+        let mut progress =
+            Progress::with_writer(Vec::new(), "Rewriting commits", None, RenderMode::Plain)
+                .with_detail(|| "import queue 1.50 MiB".to_string());
+        progress.update(10_000).unwrap();
+        let out = String::from_utf8(progress.writer).unwrap();
+        assert!(out.trim_end().ends_with(") import queue 1.50 MiB"), "{out}");
+    }
+
+    #[test]
+    fn small_runs_print_nothing() {
+        let mut progress =
+            Progress::with_writer(Vec::new(), "Rewriting commits", None, RenderMode::Plain);
+        for processed in 1..=999 {
+            progress.update(processed).unwrap();
+        }
+        progress.finish(999).unwrap();
+        assert!(progress.writer.is_empty());
+    }
+
+    #[test]
+    fn bar_is_sized_to_terminal_width() {
+        let narrow = format_bar_line("Processing commits", 500, 1000, 60);
+        assert_eq!(narrow.len(), 59, "{narrow}");
+        assert!(narrow.contains("] 50% (500/1000)"));
+        let wide = format_bar_line("Processing commits", 1000, 1000, 400);
+        assert!(wide.contains(&format!("[{}]", "=".repeat(MAX_BAR_WIDTH))));
+        let tiny = format_bar_line("Processing commits", 0, 1000, 10);
+        assert!(tiny.contains(&format!("[{}]", " ".repeat(MIN_BAR_WIDTH))));
+    }
+
+    #[test]
+    fn terminal_width_reads_columns_with_fallback() {
+        assert_eq!(terminal_width(Some("120")), 120);
+        assert_eq!(terminal_width(Some(" 100 ")), 100);
+        assert_eq!(terminal_width(Some("0")), DEFAULT_WIDTH);
+        assert_eq!(terminal_width(Some("wide")), DEFAULT_WIDTH);
+        assert_eq!(terminal_width(None), DEFAULT_WIDTH);
+    }
+
+    #[test]
+    fn rate_is_zero_before_time_elapses() {
+        assert_eq!(rate_per_sec(1000, Duration::ZERO), 0.0);
+        assert_eq!(rate_per_sec(1000, Duration::from_millis(500)), 2000.0);
+    }
+}
@@ -0,0 +1,293 @@
+//! The ref updates finalize applies after fast-import, saved to
+//! `.git/filter-repo/ref-plan.json` before any of them runs.
+//!
+//! fast-import has already moved the refs it wrote by then; what remains are
+//! branch resets, deletions of renamed refs and the move of `HEAD`. If the
+//! process dies part-way, `--resume-ref-update` reads the plan back, checks
+//! that every ref is still either where the plan found it or where the plan
+//! puts it, and applies only the updates that are outstanding.
+
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use serde::{Deserialize, Serialize};
+
+use crate::diagnostics::{self, Phase};
+use crate::error::{FilterRepoError, Result};
+use crate::finalize::HeadState;
+use crate::gitutil;
+use crate::opts::Options;
+use crate::refs::{RefObjectType, RefSnapshot, RefTip};
+
+const PLAN_FILE: &str = "ref-plan.json";
+
+/// One ref move; `None` means the ref is absent (before) or deleted (after).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedRefUpdate {
+    pub name: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// `HEAD` before and after, in `head-map` notation (`ref: NAME`, an OID, or
+/// `(unset)`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HeadPlan {
+    pub before: String,
+    pub after: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RefPlan {
+    pub updates: Vec<PlannedRefUpdate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub head: Option<HeadPlan>,
+}
+
+impl RefPlan {
+    pub fn path(debug_dir: &Path) -> PathBuf {
+        debug_dir.join(PLAN_FILE)
+    }
+
+    /// Writes the plan through a temporary file so a crash never leaves a
+    /// truncated plan behind.
+    pub fn save(&self, debug_dir: &Path) -> io::Result<()> {
+        let path = Self::path(debug_dir);
+        let tmp = path.with_extension("json.tmp");
+        let json = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, &path)
+    }
+
+    pub fn load(debug_dir: &Path) -> io::Result<Self> {
+        let path = Self::path(debug_dir);
+        let content = fs::read(&path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("failed to read ref plan {}: {e}", path.display()),
+            )
+        })?;
+        serde_json::from_slice(&content).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid ref plan {}: {e}", path.display()),
+            )
+        })
+    }
+
+    /// The refs `before` will hold once every update has been applied.
+    pub fn predicted_refs(&self, before: &RefSnapshot) -> RefSnapshot {
+        let mut after = before.clone();
+        for update in &self.updates {
+            match &update.new {
+                Some(oid) => {
+                    let object_type = before
+                        .refs
+                        .get(&update.name)
+                        .map_or(RefObjectType::Commit, |tip| tip.object_type);
+                    after.refs.insert(
+                        update.name.clone(),
+                        RefTip {
+                            oid: oid.clone(),
+                            object_type,
+                        },
+                    );
+                }
+                None => {
+                    after.refs.remove(&update.name);
+                }
+            }
+        }
+        after
+    }
+
+    /// Splits the updates into those still to apply and the refs that are
+    /// neither at their planned old nor new value.
+    fn outstanding<'a>(
+        &'a self,
+        snapshot: &RefSnapshot,
+    ) -> (Vec<&'a PlannedRefUpdate>, Vec<String>) {
+        let mut pending = Vec::new();
+        let mut conflicts = Vec::new();
+        for update in &self.updates {
+            let current = snapshot.oid(&update.name);
+            if current == update.new.as_deref() {
+                continue;
+            }
+            if current == update.old.as_deref() {
+                pending.push(update);
+                continue;
+            }
+            conflicts.push(format!(
+                "{}: expected {} (before) or {} (planned), found {}",
+                update.name,
+                describe(update.old.as_deref()),
+                describe(update.new.as_deref()),
+                describe(current)
+            ));
+        }
+        (pending, conflicts)
+    }
+}
+
+fn describe(oid: Option<&str>) -> &str {
+    oid.unwrap_or("(missing)")
+}
+
+/// `git update-ref --stdin` commands that apply `updates`, each checked
+/// against the old value recorded in the plan.
+pub fn update_ref_payload<'a>(updates: impl IntoIterator<Item = &'a PlannedRefUpdate>) -> Vec<u8> {
+    let mut payload = Vec::new();
+    for update in updates {
+        let line = match (&update.old, &update.new) {
+            (Some(old), Some(new)) => format!("update {} {} {}\n", update.name, new, old),
+            (None, Some(new)) => format!("create {} {}\n", update.name, new),
+            (Some(old), None) => format!("delete {} {}\n", update.name, old),
+            (None, None) => continue,
+        };
+        payload.extend_from_slice(line.as_bytes());
+    }
+    payload
+}
+
+/// Runs `git update-ref --stdin`, which applies all commands or none.
+pub fn apply_payload(target: &Path, payload: &[u8]) -> io::Result<std::process::ExitStatus> {
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(target)
+        .arg("update-ref")
+        .arg("--no-deref")
+        .arg("--stdin")
+        .stdin(Stdio::piped())
+        .spawn()
+        .map_err(|e| io::Error::other(format!("failed to run git update-ref: {e}")))?;
+    if let Some(mut sin) = child.stdin.take() {
+        sin.write_all(payload)?;
+    }
+    child.wait()
+}
+
+/// `--resume-ref-update`: finish the ref updates of an interrupted run.
+pub fn resume(opts: &Options) -> Result<()> {
+    let debug_dir = gitutil::git_dir(&opts.target)?.join("filter-repo");
+    let plan = RefPlan::load(&debug_dir)?;
+    let snapshot = RefSnapshot::capture(&opts.target)?;
+    let (pending, mut conflicts) = plan.outstanding(&snapshot);
+
+    let head_now = HeadState::read(&opts.target)?.to_string();
+    let head_pending = match &plan.head {
+        Some(head) if head_now == head.after => None,
+        Some(head) if head_now == head.before => Some(head),
+        Some(head) => {
+            conflicts.push(format!(
+                "HEAD: expected {} (before) or {} (planned), found {}",
+                head.before, head.after, head_now
+            ));
+            None
+        }
+        None => None,
+    };
+    if !conflicts.is_empty() {
+        return Err(FilterRepoError::Io(io::Error::other(format!(
+            "refusing to resume: the repository changed since {} was written:\n  {}",
+            RefPlan::path(&debug_dir).display(),
+            conflicts.join("\n  ")
+        ))));
+    }
+
+    if !pending.is_empty() {
+        let status = apply_payload(&opts.target, &update_ref_payload(pending.iter().copied()))?;
+        if !status.success() {
+            return Err(FilterRepoError::Io(io::Error::other(format!(
+                "git update-ref failed while resuming: {status}"
+            ))));
+        }
+    }
+    if let Some(head) = head_pending {
+        let mut cmd = Command::new("git");
+        cmd.arg("-C").arg(&opts.target);
+        match head.after.strip_prefix("ref: ") {
+            Some(target) => cmd.args(["symbolic-ref", "HEAD", target]),
+            None => cmd.args(["update-ref", "--no-deref", "HEAD", &head.after]),
+        };
+        let status = cmd.status()?;
+        if !status.success() {
+            return Err(FilterRepoError::Io(io::Error::other(format!(
+                "failed to move HEAD to {}: {status}",
+                head.after
+            ))));
+        }
+    }
+    if !opts.quiet {
+        diagnostics::info(
+            Phase::Finalize,
+            format!(
+                "Resumed ref update: applied {} of {} planned ref update(s){}",
+                pending.len(),
+                plan.updates.len(),
+                if head_pending.is_some() {
+                    " and moved HEAD"
+                } else {
+                    ""
+                }
+            ),
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(name: &str, old: Option<&str>, new: Option<&str>) -> PlannedRefUpdate {
+        PlannedRefUpdate {
+            name: name.to_string(),
+            old: old.map(str::to_string),
+            new: new.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn payload_checks_recorded_old_values() {
+        let payload = update_ref_payload(&[
+            update("refs/heads/a", Some("1111"), Some("2222")),
+            update("refs/heads/b", None, Some("3333")),
+            update("refs/tags/old", Some("4444"), None),
+        ]);
+        assert_eq!(
+            String::from_utf8(payload).unwrap(),
+            "update refs/heads/a 2222 1111\ncreate refs/heads/b 3333\ndelete refs/tags/old 4444\n"
+        );
+    }
+
+    #[test]
+    fn predicted_refs_apply_updates_and_deletions() {
+        let mut before = RefSnapshot::default();
+        for name in ["refs/heads/a", "refs/heads/old"] {
+            before.refs.insert(
+                name.to_string(),
+                RefTip {
+                    oid: "1111".to_string(),
+                    object_type: RefObjectType::Commit,
+                },
+            );
+        }
+        let plan = RefPlan {
+            updates: vec![
+                update("refs/heads/a", Some("1111"), Some("2222")),
+                update("refs/heads/new", None, Some("3333")),
+                update("refs/heads/old", Some("1111"), None),
+            ],
+            head: None,
+        };
+
+        let after = plan.predicted_refs(&before);
+
+        assert_eq!(after.oid("refs/heads/a"), Some("2222"));
+        assert_eq!(after.oid("refs/heads/new"), Some("3333"));
+        assert!(!after.contains("refs/heads/old"));
+    }
+}
@@ -0,0 +1,317 @@
+//! Reference enumeration.
+//!
+//! All refs are read with a single `git for-each-ref` invocation, so loose
+//! and packed refs are handled alike. `HEAD` is resolved with a separate
+//! `git symbolic-ref` query because `for-each-ref` does not list it.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::ops::Bound;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+/// Full hexadecimal object id, as printed by git.
+pub type Oid = String;
+
+/// Type of the object a ref points at.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RefObjectType {
+    Commit,
+    /// Annotated tag object (lightweight tags point at a commit).
+    Tag,
+    Tree,
+    Blob,
+}
+
+impl RefObjectType {
+    fn parse(s: &str) -> Option<Self> {
+        match s {
+            "commit" => Some(Self::Commit),
+            "tag" => Some(Self::Tag),
+            "tree" => Some(Self::Tree),
+            "blob" => Some(Self::Blob),
+            _ => None,
+        }
+    }
+}
+
+/// The object a ref points at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefTip {
+    pub oid: Oid,
+    pub object_type: RefObjectType,
+}
+
+/// Point-in-time view of every ref in a repository.
+///
+/// Capture one snapshot and query it instead of enumerating refs repeatedly.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RefSnapshot {
+    /// Every ref under `refs/`, keyed by full name.
+    pub refs: BTreeMap<String, RefTip>,
+    /// Symbolic refs and their targets, e.g. `HEAD -> refs/heads/main` or
+    /// `refs/remotes/origin/HEAD -> refs/remotes/origin/main`. A detached
+    /// `HEAD` has no entry.
+    pub symbolic_refs: BTreeMap<String, String>,
+}
+
+impl RefSnapshot {
+    /// Reads all refs and symbolic refs of `repo`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use filter_repo_rs::refs::RefSnapshot;
+    /// use std::path::Path;
+    ///
+    /// let snapshot = RefSnapshot::capture(Path::new(".")).unwrap();
+    /// if let Some(head) = snapshot.head() {
+    ///     println!("HEAD -> {}", head);
+    /// }
+    /// ```
+    pub fn capture(repo: &Path) -> io::Result<Self> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .arg("for-each-ref")
+            .arg("--format=%(refname)%00%(objectname)%00%(objecttype)%00%(symref)")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .output()?;
+        if !output.status.success() {
+            return Err(io::Error::other(format!(
+                "git -C {:?} for-each-ref failed: {}",
+                repo,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        let mut snapshot = Self::parse_for_each_ref(&String::from_utf8_lossy(&output.stdout));
+        if let Some(target) = head_symref(repo)? {
+            snapshot.symbolic_refs.insert("HEAD".to_string(), target);
+        }
+        Ok(snapshot)
+    }
+
+    fn parse_for_each_ref(output: &str) -> Self {
+        let mut snapshot = Self::default();
+        for line in output.lines() {
+            let mut fields = line.split('\0');
+            let (Some(name), Some(oid), Some(kind)) = (fields.next(), fields.next(), fields.next())
+            else {
+                continue;
+            };
+            let symref = fields.next().unwrap_or_default();
+            if !symref.is_empty() {
+                snapshot
+                    .symbolic_refs
+                    .insert(name.to_string(), symref.to_string());
+            }
+            // Dangling symrefs and unknown types have no usable tip.
+            let Some(object_type) = RefObjectType::parse(kind) else {
+                continue;
+            };
+            snapshot.refs.insert(
+                name.to_string(),
+                RefTip {
+                    oid: oid.to_string(),
+                    object_type,
+                },
+            );
+        }
+        snapshot
+    }
+
+    /// Ref name to object id for every ref.
+    pub fn oids(&self) -> BTreeMap<String, Oid> {
+        self.refs
+            .iter()
+            .map(|(name, tip)| (name.clone(), tip.oid.clone()))
+            .collect()
+    }
+
+    pub fn contains(&self, refname: &str) -> bool {
+        self.refs.contains_key(refname)
+    }
+
+    pub fn oid(&self, refname: &str) -> Option<&str> {
+        self.refs.get(refname).map(|tip| tip.oid.as_str())
+    }
+
+    /// The first ref, in name order, whose name starts with `prefix`.
+    ///
+    /// Names sharing a prefix sort next to each other, so this is one ordered
+    /// lookup instead of a scan over every ref.
+    pub fn first_with_prefix(&self, prefix: &str) -> Option<&str> {
+        self.refs
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .next()
+            .map(|(name, _)| name.as_str())
+            .filter(|name| name.starts_with(prefix))
+    }
+
+    /// The ref `HEAD` points at, or `None` when `HEAD` is detached.
+    pub fn head(&self) -> Option<&str> {
+        self.symbolic_refs.get("HEAD").map(String::as_str)
+    }
+}
+
+fn head_symref(repo: &Path) -> io::Result<Option<String>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["symbolic-ref", "-q", "HEAD"])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .output()?;
+    // Exit status 1 means HEAD is detached.
+    if !output.status.success() {
+        return Ok(None);
+    }
+    let target = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok((!target.is_empty()).then_some(target))
+}
+
+/// Get all references and the object ids they point at.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use filter_repo_rs::refs;
+/// use std::path::Path;
+///
+/// for (refname, oid) in refs::get_all_refs(Path::new(".")).unwrap() {
+///     println!("{}: {}", refname, oid);
+/// }
+/// ```
+pub fn get_all_refs(repo: &Path) -> io::Result<BTreeMap<String, Oid>> {
+    Ok(RefSnapshot::capture(repo)?.oids())
+}
+
+/// Get symbolic refs (including `HEAD` unless detached) and their targets.
+pub fn get_symbolic_refs(repo: &Path) -> io::Result<BTreeMap<String, String>> {
+    Ok(RefSnapshot::capture(repo)?.symbolic_refs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn git(repo: &Path, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(repo)
+            .args(args)
+            .output()
+            .expect("run git");
+        assert!(
+            output.status.success(),
+            "git {:?} failed: {}",
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    fn repo_with_commit() -> TempDir {
+        let dir = TempDir::new().expect("create tempdir");
+        let repo = dir.path();
+        git(repo, &["init", "-q", "-b", "main"]);
+        git(repo, &["config", "user.name", "Test User"]);
+        git(repo, &["config", "user.email", "test@example.com"]);
+        std::fs::write(repo.join("a.txt"), "a\n").expect("write file");
+        git(repo, &["add", "."]);
+        git(repo, &["commit", "-q", "-m", "init"]);
+        dir
+    }
+
+    #[test]
+    fn snapshot_distinguishes_annotated_and_lightweight_tags() {
+        let dir = repo_with_commit();
+        let repo = dir.path();
+        git(repo, &["tag", "light"]);
+        git(repo, &["tag", "-a", "-m", "annotated", "heavy"]);
+        let head = git(repo, &["rev-parse", "HEAD"]);
+
+        let snapshot = RefSnapshot::capture(repo).expect("capture refs");
+        let light = &snapshot.refs["refs/tags/light"];
+        assert_eq!(light.object_type, RefObjectType::Commit);
+        assert_eq!(light.oid, head);
+        let heavy = &snapshot.refs["refs/tags/heavy"];
+        assert_eq!(heavy.object_type, RefObjectType::Tag);
+        assert_ne!(heavy.oid, head);
+        assert_eq!(snapshot.head(), Some("refs/heads/main"));
+        assert_eq!(snapshot.oid("refs/heads/main"), Some(head.as_str()));
+    }
+
+    #[test]
+    fn snapshot_reports_detached_head_and_remote_symrefs() {
+        let dir = repo_with_commit();
+        let repo = dir.path();
+        let head = git(repo, &["rev-parse", "HEAD"]);
+        git(repo, &["update-ref", "refs/remotes/origin/main", &head]);
+        git(
+            repo,
+            &[
+                "symbolic-ref",
+                "refs/remotes/origin/HEAD",
+                "refs/remotes/origin/main",
+            ],
+        );
+        git(repo, &["checkout", "-q", "--detach"]);
+
+        let symrefs = get_symbolic_refs(repo).expect("symbolic refs");
+        assert!(!symrefs.contains_key("HEAD"), "HEAD is detached");
+        assert_eq!(
+            symrefs.get("refs/remotes/origin/HEAD").map(String::as_str),
+            Some("refs/remotes/origin/main")
+        );
+    }
+
+    #[test]
+    fn get_all_refs_reads_packed_only_refs() {
+        let dir = repo_with_commit();
+        let repo = dir.path();
+        git(repo, &["branch", "feature"]);
+        git(repo, &["tag", "v1"]);
+        git(repo, &["pack-refs", "--all", "--prune"]);
+        assert!(!repo.join(".git/refs/heads/feature").exists());
+
+        let refs = get_all_refs(repo).expect("read refs");
+        let head = git(repo, &["rev-parse", "HEAD"]);
+        for name in ["refs/heads/main", "refs/heads/feature", "refs/tags/v1"] {
+            assert_eq!(refs.get(name), Some(&head), "{name} should be listed");
+        }
+    }
+
+    #[test]
+    fn first_with_prefix_picks_the_smallest_matching_name() {
+        let tip = || RefTip {
+            oid: "0".repeat(40),
+            object_type: RefObjectType::Commit,
+        };
+        let snapshot = RefSnapshot {
+            refs: [
+                "refs/heads/a",
+                "refs/heads/feat-x",
+                "refs/heads/feat/y",
+                "refs/tags/feat",
+            ]
+            .into_iter()
+            .map(|name| (name.to_string(), tip()))
+            .collect(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            snapshot.first_with_prefix("refs/heads/feat"),
+            Some("refs/heads/feat-x")
+        );
+        assert_eq!(
+            snapshot.first_with_prefix("refs/heads/a"),
+            Some("refs/heads/a")
+        );
+        assert_eq!(snapshot.first_with_prefix("refs/heads/b"), None);
+        assert_eq!(snapshot.first_with_prefix("refs/heads/feat/z"), None);
+    }
+}
@@ -0,0 +1,273 @@
+//! Support for `--reproducible`: two runs over identical input with identical
+//! options write byte-identical streams and therefore the same commit-map.
+//!
+//! fast-export numbers marks over everything it emits, so a change in what it
+//! is asked to emit (blob payloads, tag marks) shifts every later mark.
+//! [`MarkRenumberer`] rewrites marks at the point the stream leaves the
+//! filter: the first `mark` line becomes `:1`, the next `:2`, and every
+//! reference follows its definition. Timestamps we would otherwise take from
+//! the clock come from [`timestamp`] instead.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use time::OffsetDateTime;
+
+/// `SOURCE_DATE_EPOCH` when set (the convention reproducible builds use),
+/// otherwise the Unix epoch.
+pub(crate) fn timestamp() -> OffsetDateTime {
+    std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .and_then(|secs| OffsetDateTime::from_unix_timestamp(secs).ok())
+        .unwrap_or(OffsetDateTime::UNIX_EPOCH)
+}
+
+#[derive(Debug, Default)]
+struct Marks {
+    renumbered: HashMap<u32, u32>,
+    /// `original[n - 1]` is the fast-export mark renumbered to `n`.
+    original: Vec<u32>,
+}
+
+/// Mark numbering shared between the import writer and finalize, which reads
+/// fast-import's exported marks back in terms of the original numbers.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MarkTable(Arc<Mutex<Marks>>);
+
+impl MarkTable {
+    fn lock(&self) -> MutexGuard<'_, Marks> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn define(&self, original: u32) -> u32 {
+        let mut marks = self.lock();
+        marks.original.push(original);
+        let renumbered = marks.original.len() as u32;
+        marks.renumbered.insert(original, renumbered);
+        renumbered
+    }
+
+    fn renumbered(&self, original: u32) -> Option<u32> {
+        self.lock().renumbered.get(&original).copied()
+    }
+
+    /// The fast-export mark that was written as `renumbered`.
+    pub(crate) fn original(&self, renumbered: u32) -> Option<u32> {
+        let index = (renumbered as usize).checked_sub(1)?;
+        self.lock().original.get(index).copied()
+    }
+}
+
+/// Writer that renumbers the marks of a fast-import stream passing through
+/// it. Commands are rewritten a whole line at a time; `data` payloads pass
+/// through untouched.
+pub(crate) struct MarkRenumberer<W: Write> {
+    inner: W,
+    table: MarkTable,
+    line: Vec<u8>,
+    data_remaining: usize,
+}
+
+impl<W: Write> MarkRenumberer<W> {
+    pub(crate) fn new(inner: W, table: MarkTable) -> Self {
+        Self {
+            inner,
+            table,
+            line: Vec::with_capacity(256),
+            data_remaining: 0,
+        }
+    }
+
+    fn finish_line(&mut self) -> io::Result<()> {
+        let line = std::mem::take(&mut self.line);
+        let rewritten = rewrite_line(&line, &self.table)?;
+        self.inner.write_all(&rewritten)?;
+        if let Some(size) = line.strip_prefix(b"data ") {
+            self.data_remaining = parse_data_size(size)?;
+        }
+        self.line = line;
+        self.line.clear();
+        Ok(())
+    }
+}
+
+impl<W: Write> Write for MarkRenumberer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while !rest.is_empty() {
+            if self.data_remaining > 0 {
+                let n = self.data_remaining.min(rest.len());
+                self.inner.write_all(&rest[..n])?;
+                self.data_remaining -= n;
+                rest = &rest[n..];
+                continue;
+            }
+            match rest.iter().position(|&b| b == b'\n') {
+                Some(end) => {
+                    self.line.extend_from_slice(&rest[..=end]);
+                    rest = &rest[end + 1..];
+                    self.finish_line()?;
+                }
+                None => {
+                    self.line.extend_from_slice(rest);
+                    rest = &[];
+                }
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn parse_data_size(size: &[u8]) -> io::Result<usize> {
+    std::str::from_utf8(size)
+        .ok()
+        .and_then(|s| s.trim_end().parse().ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "cannot renumber marks around data header {:?}",
+                    String::from_utf8_lossy(size).trim_end()
+                ),
+            )
+        })
+}
+
+fn parse_mark(token: &[u8]) -> Option<u32> {
+    let digits = token.strip_prefix(b":")?;
+    if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+        return None;
+    }
+    std::str::from_utf8(digits).ok()?.parse().ok()
+}
+
+/// Rewrites the mark definition or the leading mark references of one
+/// command line. Paths and other trailing fields are left alone.
+fn rewrite_line(line: &[u8], table: &MarkTable) -> io::Result<Vec<u8>> {
+    let body = line.strip_suffix(b"\n").unwrap_or(line);
+    if let Some(mark) = body.strip_prefix(b"mark ").and_then(parse_mark) {
+        return Ok(format!("mark :{}\n", table.define(mark)).into_bytes());
+    }
+    // How many fields after the command may be a mark reference.
+    let (command, fields) = match body.iter().position(|&b| b == b' ') {
+        Some(pos) => (&body[..pos], &body[pos + 1..]),
+        None => return Ok(line.to_vec()),
+    };
+    let references = match command {
+        b"from" | b"merge" | b"to" | b"get-mark" | b"cat-blob" | b"ls" => 1,
+        // `M <mode> <dataref> <path>` and `N <dataref> <commit-ish>`.
+        b"M" | b"N" => 2,
+        _ => return Ok(line.to_vec()),
+    };
+    let mut out = Vec::with_capacity(line.len());
+    out.extend_from_slice(command);
+    let mut rest = fields;
+    for _ in 0..references {
+        let end = rest.iter().position(|&b| b == b' ').unwrap_or(rest.len());
+        let (token, tail) = rest.split_at(end);
+        out.push(b' ');
+        match parse_mark(token) {
+            Some(mark) => {
+                let renumbered = table.renumbered(mark).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "reference to undefined mark :{mark} in {:?}",
+                            String::from_utf8_lossy(body)
+                        ),
+                    )
+                })?;
+                out.extend_from_slice(format!(":{renumbered}").as_bytes());
+            }
+            None => out.extend_from_slice(token),
+        }
+        match tail.strip_prefix(b" ") {
+            Some(next) => rest = next,
+            None => {
+                rest = tail;
+                break;
+            }
+        }
+    }
+    if !rest.is_empty() {
+        out.push(b' ');
+        out.extend_from_slice(rest);
+    }
+    if line.ends_with(b"\n") {
+        out.push(b'\n');
+    }
+    Ok(out)
+}
+
+/// Renumbers the marks of a stream file in place.
+pub(crate) fn renumber_file(path: &Path) -> io::Result<()> {
+    let tmp = path.with_extension("renumbered");
+    {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut writer =
+            MarkRenumberer::new(BufWriter::new(File::create(&tmp)?), MarkTable::default());
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            let n = reader.read(&mut buf)?;
+            if n == 0 {
+                break;
+            }
+            writer.write_all(&buf[..n])?;
+        }
+        writer.flush()?;
+    }
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn renumber(stream: &[u8], chunk: usize) -> (Vec<u8>, MarkTable) {
+        let table = MarkTable::default();
+        let mut writer = MarkRenumberer::new(Vec::new(), table.clone());
+        for piece in stream.chunks(chunk) {
+            writer.write_all(piece).unwrap();
+        }
+        (writer.inner, table)
+    }
+
+    #[test]
+    fn marks_are_renumbered_in_definition_order_and_payloads_kept() {
+        let stream = b"blob\nmark :7\ndata 8\nmark :9\n\
+commit refs/heads/main\nmark :12\ncommitter A <a@x> 0 +0000\ndata 3\nhi\n\
+M 100644 :7 dir/mark :7.txt\nM 100644 0123456789012345678901234567890123456789 b\n\n\
+reset refs/tags/v1\nfrom :12\n\nalias\nmark :13\nto :12\n\nget-mark :13\ndone\n";
+
+        for chunk in [1, 4, stream.len()] {
+            let (out, table) = renumber(stream, chunk);
+            assert_eq!(
+                String::from_utf8(out).unwrap(),
+                "blob\nmark :1\ndata 8\nmark :9\n\
+commit refs/heads/main\nmark :2\ncommitter A <a@x> 0 +0000\ndata 3\nhi\n\
+M 100644 :1 dir/mark :7.txt\nM 100644 0123456789012345678901234567890123456789 b\n\n\
+reset refs/tags/v1\nfrom :2\n\nalias\nmark :3\nto :2\n\nget-mark :3\ndone\n"
+            );
+            assert_eq!(table.original(2), Some(12));
+            assert_eq!(table.original(4), None);
+        }
+    }
+
+    #[test]
+    fn undefined_mark_reference_is_an_error() {
+        let table = MarkTable::default();
+        let mut writer = MarkRenumberer::new(Vec::new(), table);
+        let err = writer
+            .write_all(b"commit refs/heads/main\nfrom :3\n")
+            .expect_err("mark :3 was never defined");
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+}
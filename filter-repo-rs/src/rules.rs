@@ -0,0 +1,423 @@
+//! Shared parser for the replacement files read by `--replace-text` and
+//! `--replace-message`.
+//!
+//! Both files use the same line format: blank lines and `#` comments are
+//! skipped, `regex:`, `glob:` and `path:PATH:` prefixes select the rule kind,
+//! and anything else is a literal `FROM==>TO` (or bare `FROM`, redacted by
+//! default). A file whose first non-blank byte is `{` is read as the JSON
+//! document written by `--detect-emit json` instead.
+//!
+//! Rules apply in a fixed order: all literal rules in file order, each one
+//! seeing the output of the one before, then all `regex:` and `glob:` rules in
+//! file order. For blobs, `path:` rules run before both. Message files ignore
+//! `path:` rules and, as they always have, also read every `regex:` and
+//! `glob:` line as the literal text it spells.
+//!
+//! Files are read line by line so huge generated rule sets are not held in
+//! memory twice, and are checked against [`RuleLimits`] while being read.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use crate::message::{find_subslice, ReplaceTextDocument, DEFAULT_REDACTION};
+use crate::opts::Options;
+
+const PATH_RULE_PREFIX: &[u8] = b"path:";
+const REGEX_RULE_PREFIX: &[u8] = b"regex:";
+const GLOB_RULE_PREFIX: &[u8] = b"glob:";
+
+pub(crate) const DEFAULT_MAX_RULES: usize = 1_000_000;
+pub(crate) const DEFAULT_MAX_PATTERN_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub(crate) enum RuleKind {
+    Literal,
+    Regex,
+    Glob,
+    /// Literal rule that only applies to blobs stored at this path.
+    Path(Vec<u8>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Rule {
+    pub(crate) kind: RuleKind,
+    pub(crate) pattern: Vec<u8>,
+    pub(crate) replacement: Vec<u8>,
+    /// 1-based line number; the rule's position for JSON files.
+    pub(crate) line: usize,
+}
+
+impl Rule {
+    /// The pattern as the line spelled it, `regex:` or `glob:` prefix
+    /// included; `None` for `path:` rules.
+    pub(crate) fn spelled_pattern(&self) -> Option<Vec<u8>> {
+        let prefix: &[u8] = match self.kind {
+            RuleKind::Literal => b"",
+            RuleKind::Regex => REGEX_RULE_PREFIX,
+            RuleKind::Glob => GLOB_RULE_PREFIX,
+            RuleKind::Path(_) => return None,
+        };
+        Some([prefix, &self.pattern].concat())
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct RuleLimits {
+    pub(crate) max_rules: usize,
+    pub(crate) max_pattern_bytes: usize,
+}
+
+impl Default for RuleLimits {
+    fn default() -> Self {
+        Self {
+            max_rules: DEFAULT_MAX_RULES,
+            max_pattern_bytes: DEFAULT_MAX_PATTERN_BYTES,
+        }
+    }
+}
+
+impl RuleLimits {
+    pub(crate) fn from_options(opts: &Options) -> Self {
+        Self {
+            max_rules: opts.max_replace_rules.unwrap_or(DEFAULT_MAX_RULES),
+            max_pattern_bytes: opts
+                .max_replace_rule_bytes
+                .unwrap_or(DEFAULT_MAX_PATTERN_BYTES),
+        }
+    }
+}
+
+/// Rules read from one file, in file order, with identical duplicates removed.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct RuleFile {
+    pub(crate) rules: Vec<Rule>,
+    pub(crate) duplicates: usize,
+}
+
+impl RuleFile {
+    pub(crate) fn read(path: &Path, limits: &RuleLimits) -> io::Result<Self> {
+        Self::parse(BufReader::new(File::open(path)?), limits)
+    }
+
+    pub(crate) fn parse<R: BufRead>(mut reader: R, limits: &RuleLimits) -> io::Result<Self> {
+        let mut builder = RuleFileBuilder::new(limits);
+        let mut line = Vec::new();
+        let mut line_no = 0usize;
+        // Whitespace-only lines seen before the format is known; they only
+        // become rules if the file turns out not to be JSON.
+        let mut leading: Option<Vec<Vec<u8>>> = Some(Vec::new());
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            line_no += 1;
+            if line.last() == Some(&b'\n') {
+                line.pop();
+            }
+            if let Some(blank) = leading.as_mut() {
+                match line.iter().find(|b| !b.is_ascii_whitespace()) {
+                    Some(b'{') => {
+                        let mut content = std::mem::take(&mut line);
+                        reader.read_to_end(&mut content)?;
+                        builder.push_json(&content)?;
+                        return Ok(builder.finish());
+                    }
+                    Some(_) => {
+                        for (idx, raw) in blank.iter().enumerate() {
+                            builder.push_line(raw, idx + 1)?;
+                        }
+                        leading = None;
+                    }
+                    None => {
+                        blank.push(line.clone());
+                        continue;
+                    }
+                }
+            }
+            builder.push_line(&line, line_no)?;
+        }
+        if let Some(blank) = leading {
+            for (idx, raw) in blank.iter().enumerate() {
+                builder.push_line(raw, idx + 1)?;
+            }
+        }
+        Ok(builder.finish())
+    }
+
+    /// `regex:` rules from `(pattern, replacement)` pairs, checked against
+    /// `limits` as if read from a file; a pair's position is its line number.
+    pub(crate) fn from_regex_pairs<I>(pairs: I, limits: &RuleLimits) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = (String, String)>,
+    {
+        let mut builder = RuleFileBuilder::new(limits);
+        for (idx, (pattern, replacement)) in pairs.into_iter().enumerate() {
+            builder.push(
+                RuleKind::Regex,
+                pattern.into_bytes(),
+                replacement.into_bytes(),
+                idx + 1,
+            )?;
+        }
+        Ok(builder.finish())
+    }
+}
+
+struct RuleFileBuilder<'a> {
+    limits: &'a RuleLimits,
+    rules: Vec<Rule>,
+    // (kind, pattern) -> index into `rules`
+    seen: HashMap<(RuleKind, Vec<u8>), usize>,
+    pattern_bytes: usize,
+    duplicates: usize,
+}
+
+impl<'a> RuleFileBuilder<'a> {
+    fn new(limits: &'a RuleLimits) -> Self {
+        Self {
+            limits,
+            rules: Vec::new(),
+            seen: HashMap::new(),
+            pattern_bytes: 0,
+            duplicates: 0,
+        }
+    }
+
+    fn push_line(&mut self, raw: &[u8], line: usize) -> io::Result<()> {
+        if raw.is_empty() || raw.starts_with(b"#") {
+            return Ok(());
+        }
+        if let Some(rest) = raw.strip_prefix(REGEX_RULE_PREFIX) {
+            let (pattern, replacement) = split_rule(rest);
+            return self.push(RuleKind::Regex, pattern.to_vec(), replacement, line);
+        }
+        if let Some(rest) = raw.strip_prefix(GLOB_RULE_PREFIX) {
+            let (pattern, replacement) = split_rule(rest);
+            return self.push(RuleKind::Glob, pattern.to_vec(), replacement, line);
+        }
+        if let Some(rest) = raw.strip_prefix(PATH_RULE_PREFIX) {
+            let Some(sep) = rest.iter().position(|&b| b == b':') else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "line {line}: invalid path-scoped rule (expected path:PATH:FROM==>TO): {}",
+                        String::from_utf8_lossy(raw)
+                    ),
+                ));
+            };
+            let (pattern, replacement) = split_rule(&rest[sep + 1..]);
+            if sep == 0 || pattern.is_empty() {
+                return Ok(());
+            }
+            let kind = RuleKind::Path(rest[..sep].to_vec());
+            return self.push(kind, pattern.to_vec(), replacement, line);
+        }
+        let (pattern, replacement) = split_rule(raw);
+        if pattern.is_empty() {
+            return Ok(());
+        }
+        self.push(RuleKind::Literal, pattern.to_vec(), replacement, line)
+    }
+
+    fn push_json(&mut self, content: &[u8]) -> io::Result<()> {
+        let doc: ReplaceTextDocument = serde_json::from_slice(content).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid JSON rules file: {e}"),
+            )
+        })?;
+        for (idx, rule) in doc.rules.into_iter().enumerate() {
+            if rule.value.is_empty() {
+                continue;
+            }
+            let pattern = rule.value.into_bytes();
+            let replacement = rule.replacement.into_bytes();
+            if rule.paths.is_empty() {
+                self.push(RuleKind::Literal, pattern, replacement, idx + 1)?;
+                continue;
+            }
+            for p in rule.paths {
+                let kind = RuleKind::Path(p.into_bytes());
+                self.push(kind, pattern.clone(), replacement.clone(), idx + 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn push(
+        &mut self,
+        kind: RuleKind,
+        pattern: Vec<u8>,
+        replacement: Vec<u8>,
+        line: usize,
+    ) -> io::Result<()> {
+        let key = (kind, pattern);
+        if let Some(&idx) = self.seen.get(&key) {
+            let first = &self.rules[idx];
+            if first.replacement != replacement {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!(
+                        "conflicting replacements for rule '{}' on lines {} and {}",
+                        String::from_utf8_lossy(&first.pattern),
+                        first.line,
+                        line
+                    ),
+                ));
+            }
+            self.duplicates += 1;
+            return Ok(());
+        }
+        if self.rules.len() >= self.limits.max_rules {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "line {line}: more than {} replacement rules (raise --max-replace-rules, or \
+                     split rules into path-scoped 'path:PATH:FROM==>TO' entries)",
+                    self.limits.max_rules
+                ),
+            ));
+        }
+        self.pattern_bytes += key.1.len();
+        if self.pattern_bytes > self.limits.max_pattern_bytes {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "line {line}: replacement patterns exceed {} bytes (raise \
+                     --max-replace-rule-bytes, or split rules into path-scoped \
+                     'path:PATH:FROM==>TO' entries)",
+                    self.limits.max_pattern_bytes
+                ),
+            ));
+        }
+        self.seen.insert(key.clone(), self.rules.len());
+        let (kind, pattern) = key;
+        self.rules.push(Rule {
+            kind,
+            pattern,
+            replacement,
+            line,
+        });
+        Ok(())
+    }
+
+    fn finish(self) -> RuleFile {
+        RuleFile {
+            rules: self.rules,
+            duplicates: self.duplicates,
+        }
+    }
+}
+
+/// Splits `FROM==>TO` at the first `==>`; a bare `FROM` gets the default
+/// redaction.
+fn split_rule(raw: &[u8]) -> (&[u8], Vec<u8>) {
+    match find_subslice(raw, b"==>") {
+        Some(pos) => (&raw[..pos], raw[pos + 3..].to_vec()),
+        None => (raw, DEFAULT_REDACTION.as_bytes().to_vec()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &[u8]) -> io::Result<RuleFile> {
+        RuleFile::parse(content, &RuleLimits::default())
+    }
+
+    #[test]
+    fn line_rules_are_classified_in_file_order() {
+        let file = parse(
+            b"# comment\n\nFOO==>BAR\nregex:a+==>b\nglob:x*\npath:cfg/.env:TOKEN\n==>IGNORED\nBAZ",
+        )
+        .unwrap();
+        let summary: Vec<(RuleKind, &[u8], &[u8], usize)> = file
+            .rules
+            .iter()
+            .map(|r| {
+                (
+                    r.kind.clone(),
+                    r.pattern.as_slice(),
+                    r.replacement.as_slice(),
+                    r.line,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (RuleKind::Literal, &b"FOO"[..], &b"BAR"[..], 3),
+                (RuleKind::Regex, b"a+", b"b", 4),
+                (RuleKind::Glob, b"x*", b"***REMOVED***", 5),
+                (
+                    RuleKind::Path(b"cfg/.env".to_vec()),
+                    b"TOKEN",
+                    b"***REMOVED***",
+                    6
+                ),
+                (RuleKind::Literal, b"BAZ", b"***REMOVED***", 8),
+            ]
+        );
+        assert_eq!(file.duplicates, 0);
+    }
+
+    #[test]
+    fn identical_duplicates_are_dropped_and_counted() {
+        let file = parse(b"FOO==>BAR\nregex:FOO==>BAR\nFOO==>BAR\nFOO==>BAR\n").unwrap();
+        assert_eq!(file.rules.len(), 2);
+        assert_eq!(file.duplicates, 2);
+    }
+
+    #[test]
+    fn conflicting_duplicates_report_both_lines() {
+        let err = parse(b"FOO==>BAR\nOTHER\nFOO==>BAZ\n").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        let msg = err.to_string();
+        assert!(msg.contains("'FOO'"), "{msg}");
+        assert!(msg.contains("lines 1 and 3"), "{msg}");
+
+        // The same pattern may map differently under different paths.
+        let file = parse(b"path:a:FOO==>A\npath:b:FOO==>B\n").unwrap();
+        assert_eq!(file.rules.len(), 2);
+    }
+
+    #[test]
+    fn limits_bound_rule_count_and_pattern_bytes() {
+        let limits = RuleLimits {
+            max_rules: 2,
+            max_pattern_bytes: 1024,
+        };
+        let err = RuleFile::parse(&b"A\nB\nC\n"[..], &limits).unwrap_err();
+        assert!(err.to_string().contains("--max-replace-rules"));
+        assert!(RuleFile::parse(&b"A\nB\nA\n"[..], &limits).is_ok());
+
+        let limits = RuleLimits {
+            max_rules: 10,
+            max_pattern_bytes: 5,
+        };
+        let err = RuleFile::parse(&b"ABC\nDEF\n"[..], &limits).unwrap_err();
+        assert!(err.to_string().contains("--max-replace-rule-bytes"));
+    }
+
+    #[test]
+    fn json_documents_are_detected_after_leading_blank_lines() {
+        let file = parse(
+            b"\n  \n{\"rules\":[{\"value\":\"A\"},{\"value\":\"B\",\"paths\":[\"x\",\"y\"]}]}",
+        )
+        .unwrap();
+        let kinds: Vec<RuleKind> = file.rules.iter().map(|r| r.kind.clone()).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                RuleKind::Literal,
+                RuleKind::Path(b"x".to_vec()),
+                RuleKind::Path(b"y".to_vec())
+            ]
+        );
+        assert_eq!(file.rules[1].line, 2);
+    }
+}
@@ -119,11 +119,15 @@ use colored::*;
 mod already_ran;
 mod checks;
 mod debug;
+mod history_shape;
+mod path_rules;
 mod sensitive;
 
 use already_ran::check_already_ran_detection;
 pub use already_ran::{AlreadyRanChecker, AlreadyRanState};
 pub use debug::{DebugOutputManager, GitCommandError, GitCommandExecutor};
+use history_shape::check_history_shape;
+pub use path_rules::check_path_rules;
 pub use sensitive::SensitiveModeValidator;
 
 #[cfg(test)]
@@ -199,6 +203,8 @@ pub enum SanityCheckError {
     },
     /// Multiple worktrees found
     MultipleWorktrees { count: usize },
+    /// Target is a linked worktree rather than the main one
+    LinkedWorktreeTarget { main_worktree: PathBuf },
     /// Stashed changes present
     StashedChanges,
     /// Working tree not clean
@@ -220,6 +226,12 @@ pub enum SanityCheckError {
     },
     /// Sensitive data removal mode incompatibility error
     SensitiveDataIncompatible { option: String, suggestion: String },
+    /// Source is a shallow clone
+    ShallowRepository,
+    /// Source has active `refs/replace/*` refs
+    ReplaceRefsPresent { refs: Vec<String> },
+    /// Source has a legacy `info/grafts` file
+    GraftsPresent { path: PathBuf },
 }
 
 /// Types of reference conflicts that can occur on different filesystems
@@ -441,6 +453,18 @@ impl fmt::Display for SanityCheckError {
                 writeln!(f, "Remove additional worktrees or use the main worktree.")?;
                 write!(f, "Use {} to bypass this check.", highlight_flag("--force"))
             }
+            SanityCheckError::LinkedWorktreeTarget { main_worktree } => {
+                writeln!(f, "Target is a linked worktree.")?;
+                writeln!(
+                    f,
+                    "Rewriting refs from a linked worktree would update them under the main repository."
+                )?;
+                write!(
+                    f,
+                    "Run filter-repo-rs from the main worktree at {} instead.",
+                    main_worktree.display()
+                )
+            }
             SanityCheckError::StashedChanges => {
                 writeln!(f, "Stashed changes present.")?;
                 writeln!(f, "Repository should have a clean state before filtering.")?;
@@ -537,6 +561,54 @@ impl fmt::Display for SanityCheckError {
                     highlight_flag("--force")
                 )
             }
+            SanityCheckError::ShallowRepository => {
+                writeln!(f, "Repository is a shallow clone.")?;
+                writeln!(
+                    f,
+                    "Commits at the shallow boundary would become root commits in the rewritten history."
+                )?;
+                writeln!(
+                    f,
+                    "Unshallow first with {}.",
+                    highlight_cmd("git fetch --unshallow")
+                )?;
+                write!(
+                    f,
+                    "Use {} to rewrite only the history that is present.",
+                    highlight_flag("--allow-shallow")
+                )
+            }
+            SanityCheckError::ReplaceRefsPresent { refs } => {
+                writeln!(f, "Replace refs present ({} total):", refs.len())?;
+                for name in refs.iter().take(10) {
+                    writeln!(f, "  {}", name)?;
+                }
+                if refs.len() > 10 {
+                    writeln!(f, "  ... and {} more", refs.len() - 10)?;
+                }
+                writeln!(
+                    f,
+                    "fast-export would follow them and bake the replacements into the rewritten history."
+                )?;
+                write!(
+                    f,
+                    "Pass {} to export the original objects, or delete them with {}.",
+                    highlight_flag("--ignore-replace-refs"),
+                    highlight_cmd("git replace -d")
+                )
+            }
+            SanityCheckError::GraftsPresent { path } => {
+                writeln!(f, "Legacy grafts file present: {}", path.display())?;
+                writeln!(
+                    f,
+                    "Grafted parents differ from the real ones, so the rewritten history would change shape."
+                )?;
+                write!(
+                    f,
+                    "Convert it with {} and remove the file, then handle the resulting replace refs.",
+                    highlight_cmd("git replace --convert-graft-file")
+                )
+            }
             SanityCheckError::IoError(err) => {
                 write!(f, "IO error during sanity check: {err}")
             }
@@ -771,15 +843,14 @@ impl SanityCheckContext {
 /// }
 /// ```
 pub fn preflight(opts: &Options) -> FilterRepoResult<()> {
-    if opts.force {
-        return Ok(());
-    }
+    checks::check_linked_worktree_target(&opts.target)?;
     // Only enforce when requested
-    if !opts.enforce_sanity {
-        return Ok(());
+    if !opts.force && opts.enforce_sanity {
+        do_preflight_checks(opts)?;
+    } else if !opts.quiet {
+        checks::warn_about_linked_worktrees(&opts.target);
     }
-
-    do_preflight_checks(opts)?;
+    check_history_shape(opts)?;
     Ok(())
 }
 
@@ -8,6 +8,7 @@ use super::{
     ConflictType, GitCommandError, GitCommandExecutor, SanityCheckContext, SanityCheckError,
     UnpushedBranch,
 };
+use crate::diagnostics::{self, Phase};
 use crate::gitutil;
 
 pub(super) fn check_git_dir_structure_with_context(
@@ -216,6 +217,9 @@ pub(super) fn check_stash_presence_with_context(
 pub(super) fn check_working_tree_cleanliness_with_context(
     ctx: &SanityCheckContext,
 ) -> Result<(), SanityCheckError> {
+    if ctx.is_bare {
+        return Ok(());
+    }
     let executor = GitCommandExecutor::new(&ctx.repo_path);
     let staged_dirty = match executor.run_command(&["diff", "--staged", "--quiet"]) {
         Ok(_) => false,
@@ -278,9 +282,10 @@ pub(super) fn check_worktree_count_with_context(
     ctx: &SanityCheckContext,
 ) -> Result<(), SanityCheckError> {
     let executor = GitCommandExecutor::new(&ctx.repo_path);
-    match executor.run_command(&["worktree", "list"]) {
+    match executor.run_command(&["worktree", "list", "--porcelain"]) {
         Ok(output) => {
-            let worktree_count = output.lines().count();
+            // The first entry is the main worktree (or the bare repository).
+            let worktree_count = gitutil::parse_worktree_porcelain(&output).len();
             if worktree_count > 1 {
                 return Err(SanityCheckError::MultipleWorktrees {
                     count: worktree_count,
@@ -297,6 +302,45 @@ pub(super) fn check_worktree_count_with_context(
     Ok(())
 }
 
+/// Refuses a target that is itself a linked worktree, even with `--force`:
+/// its refs live in the main repository and would be rewritten there.
+pub(super) fn check_linked_worktree_target(target: &Path) -> Result<(), SanityCheckError> {
+    if !gitutil::is_linked_worktree(target).unwrap_or(false) {
+        return Ok(());
+    }
+    let main_worktree = gitutil::get_worktree_list(target)
+        .ok()
+        .and_then(|worktrees| worktrees.into_iter().next())
+        .map(|main| main.path)
+        .unwrap_or_default();
+    Err(SanityCheckError::LinkedWorktreeTarget { main_worktree })
+}
+
+/// With the worktree count check skipped, names the linked worktrees whose
+/// `HEAD` will still point at pre-rewrite refs.
+pub(super) fn warn_about_linked_worktrees(target: &Path) {
+    let Ok(worktrees) = gitutil::get_worktree_list(target) else {
+        return;
+    };
+    let linked: Vec<String> = worktrees
+        .iter()
+        .skip(1)
+        .map(|w| format!("{} ({})", w.path.display(), w.head_ref))
+        .collect();
+    if linked.is_empty() {
+        return;
+    }
+    diagnostics::warning(
+        Phase::Sanity,
+        format!(
+            "{} linked worktree(s) will keep HEADs pointing at pre-rewrite refs: {}\n  \
+             Remove them with `git worktree remove` before filtering, or re-check them out afterwards",
+            linked.len(),
+            linked.join(", ")
+        ),
+    );
+}
+
 pub(super) fn quick_repo_checks(target: &Path) -> Result<(), SanityCheckError> {
     let _ = gitutil::git_dir(target).map_err(SanityCheckError::from)?;
     Ok(())
@@ -599,10 +599,10 @@ impl GitCommandExecutor {
                     if let Some(ref err) = last_error {
                         match err {
                             GitCommandError::NotFound => break,
-                            GitCommandError::ExecutionFailed { exit_code, .. } => {
-                                if *exit_code == 128 || *exit_code == 129 {
-                                    break;
-                                }
+                            GitCommandError::ExecutionFailed { exit_code, .. }
+                                if *exit_code == 128 || *exit_code == 129 =>
+                            {
+                                break;
                             }
                             _ => {}
                         }
@@ -0,0 +1,45 @@
+use super::SanityCheckError;
+use crate::diagnostics::{self, Phase};
+use crate::gitutil;
+use crate::opts::Options;
+
+/// Shallow clones, replace refs and grafts all make fast-export see a
+/// different history than the one stored in the repository, so the rewrite
+/// would be silently wrong. Unlike the other preflight checks these are not
+/// bypassed by `--force`; each condition has its own escape hatch.
+pub fn check_history_shape(opts: &Options) -> Result<(), SanityCheckError> {
+    // A stream override never reads the source history.
+    if opts.fe_stream_override.is_some() {
+        return Ok(());
+    }
+    let source = &opts.source;
+    if gitutil::is_shallow_repository(source).unwrap_or(false) {
+        if !opts.allow_shallow {
+            return Err(SanityCheckError::ShallowRepository);
+        }
+        if !opts.quiet {
+            diagnostics::warning(
+                Phase::Sanity,
+                "rewriting a shallow clone: commits at the shallow boundary become root commits and older history is not part of the result",
+            );
+        }
+    }
+    let Ok(git_dir) = gitutil::git_dir(source) else {
+        return Ok(());
+    };
+    let grafts = git_dir.join("info").join("grafts");
+    if std::fs::metadata(&grafts).is_ok_and(|m| m.len() > 0) {
+        return Err(SanityCheckError::GraftsPresent { path: grafts });
+    }
+    if !opts.ignore_replace_refs {
+        let mut refs = Vec::new();
+        let listed = gitutil::stream_refs(source, &["refs/replace/"], |name, _oid| {
+            refs.push(name.to_string());
+            Ok(())
+        });
+        if listed.is_ok() && !refs.is_empty() {
+            return Err(SanityCheckError::ReplaceRefsPresent { refs });
+        }
+    }
+    Ok(())
+}
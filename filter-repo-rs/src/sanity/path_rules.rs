@@ -0,0 +1,237 @@
+use std::process::Command;
+
+use crate::diagnostics::{self, Phase};
+use crate::opts::Options;
+
+/// A path rename rule as the user wrote it, for messages.
+#[derive(Debug, Clone, Copy)]
+enum Rule<'a> {
+    Prefix { old: &'a [u8], new: &'a [u8] },
+    Regex(&'a regex::bytes::Regex),
+}
+
+impl Rule<'_> {
+    fn describe(&self) -> String {
+        match self {
+            Rule::Prefix { old, new } => format!(
+                "--path-rename {}:{}",
+                String::from_utf8_lossy(old),
+                String::from_utf8_lossy(new)
+            ),
+            Rule::Regex(re) => format!("--path-rename-regex {}", re.as_str()),
+        }
+    }
+}
+
+/// A later prefix rule that also rewrites the output of an earlier one.
+#[derive(Debug, PartialEq, Eq)]
+pub(super) struct Shadowing {
+    pub earlier: usize,
+    pub later: usize,
+    /// `(original, after earlier rule, after later rule)`.
+    pub example: (Vec<u8>, Vec<u8>, Vec<u8>),
+}
+
+/// Rename rules are easy to get subtly wrong: a typo in the source prefix
+/// matches nothing, and because the rules apply in order, a later rule can
+/// rewrite what an earlier one produced. Both only warn, since paths that
+/// exist only in older history legitimately match nothing at HEAD.
+pub fn check_path_rules(opts: &Options) {
+    if !opts.validate_paths
+        || opts.quiet
+        || opts.fe_stream_override.is_some()
+        || (opts.path_renames.is_empty() && opts.path_rename_regexes.is_empty())
+    {
+        return;
+    }
+    let head_paths = list_head_paths(opts);
+    let rules: Vec<Rule> = opts
+        .path_renames
+        .iter()
+        .map(|(old, new)| Rule::Prefix { old, new })
+        .chain(
+            opts.path_rename_regexes
+                .iter()
+                .map(|(re, _)| Rule::Regex(re)),
+        )
+        .collect();
+
+    if let Some(paths) = &head_paths {
+        let hits = count_rule_hits(opts, paths);
+        for (rule, hits) in rules.iter().zip(hits) {
+            // An empty prefix matches every path by construction.
+            if hits == 0 && !matches!(rule, Rule::Prefix { old, .. } if old.is_empty()) {
+                diagnostics::warning(
+                    Phase::Sanity,
+                    format!(
+                        "{} matched 0 paths at HEAD — possible typo (paths that only exist in older commits are not checked; pass --no-validate-paths to silence this)",
+                        rule.describe()
+                    ),
+                );
+            }
+        }
+    }
+
+    let samples = head_paths.as_deref().unwrap_or_default();
+    for shadow in find_shadowed_renames(&opts.path_renames, samples) {
+        let (original, first, second) = &shadow.example;
+        diagnostics::warning(
+            Phase::Sanity,
+            format!(
+                "{} also rewrites paths produced by {}, e.g. {} -> {} -> {}",
+                rules[shadow.later].describe(),
+                rules[shadow.earlier].describe(),
+                String::from_utf8_lossy(original),
+                String::from_utf8_lossy(first),
+                String::from_utf8_lossy(second)
+            ),
+        );
+    }
+}
+
+fn list_head_paths(opts: &Options) -> Option<Vec<Vec<u8>>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(&opts.source)
+        .args(["ls-tree", "-r", "-z", "--name-only", "HEAD"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        // Unborn HEAD or not a repository; the stream will report the latter.
+        return None;
+    }
+    Some(
+        output
+            .stdout
+            .split(|&b| b == 0)
+            .filter(|p| !p.is_empty())
+            .map(<[u8]>::to_vec)
+            .collect(),
+    )
+}
+
+/// For every rule (prefix renames first, then regexes), how many of `paths`
+/// it matched when the rules are applied in order like the stream does.
+fn count_rule_hits(opts: &Options, paths: &[Vec<u8>]) -> Vec<usize> {
+    let mut hits = vec![0usize; opts.path_renames.len() + opts.path_rename_regexes.len()];
+    for path in paths {
+        crate::filechange::rewrite_path_with_hits(path.clone(), opts, |rule| hits[rule] += 1);
+    }
+    hits
+}
+
+fn apply_prefix_rename(path: &[u8], old: &[u8], new: &[u8]) -> Vec<u8> {
+    let mut out = new.to_vec();
+    out.extend_from_slice(&path[old.len()..]);
+    out
+}
+
+/// Pairs of prefix renames where a later rule's source is a prefix of an
+/// earlier rule's destination, so everything the earlier rule produces is
+/// renamed again. Later rules with an empty source (`--to-subdirectory-filter`)
+/// are meant to apply to everything and are skipped. The example path is
+/// taken from `samples` when one matches the earlier rule.
+pub(super) fn find_shadowed_renames(
+    renames: &[(Vec<u8>, Vec<u8>)],
+    samples: &[Vec<u8>],
+) -> Vec<Shadowing> {
+    let mut found = Vec::new();
+    for (earlier, (old_a, new_a)) in renames.iter().enumerate() {
+        for (later, (old_b, new_b)) in renames.iter().enumerate().skip(earlier + 1) {
+            if old_b.is_empty() || !new_a.starts_with(old_b) {
+                continue;
+            }
+            let original = samples
+                .iter()
+                .find(|p| p.starts_with(old_a))
+                .cloned()
+                .unwrap_or_else(|| {
+                    let mut p = old_a.clone();
+                    p.extend_from_slice(b"file.txt");
+                    p
+                });
+            let first = apply_prefix_rename(&original, old_a, new_a);
+            let second = apply_prefix_rename(&first, old_b, new_b);
+            found.push(Shadowing {
+                earlier,
+                later,
+                example: (original, first, second),
+            });
+        }
+    }
+    found
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(old: &str, new: &str) -> (Vec<u8>, Vec<u8>) {
+        (old.as_bytes().to_vec(), new.as_bytes().to_vec())
+    }
+
+    #[test]
+    fn later_rule_consuming_earlier_output_is_reported() {
+        let renames = vec![rule("old/", "lib/core/"), rule("lib/", "src/")];
+        let samples = vec![b"old/a.rs".to_vec(), b"lib/b.rs".to_vec()];
+
+        let found = find_shadowed_renames(&renames, &samples);
+
+        assert_eq!(
+            found,
+            vec![Shadowing {
+                earlier: 0,
+                later: 1,
+                example: (
+                    b"old/a.rs".to_vec(),
+                    b"lib/core/a.rs".to_vec(),
+                    b"src/core/a.rs".to_vec()
+                ),
+            }]
+        );
+    }
+
+    #[test]
+    fn independent_and_subdirectory_rules_are_not_shadowing() {
+        let renames = vec![
+            rule("a/", "b/"),
+            rule("c/", "d/"),
+            rule("", "nested/"),
+            rule("b/x/", "e/"),
+        ];
+
+        let found = find_shadowed_renames(&renames, &[]);
+
+        // "b/x/" narrows the earlier destination instead of containing it.
+        assert!(found.is_empty(), "{found:?}");
+
+        let found = find_shadowed_renames(&[rule("a/", "b/x/"), rule("b/", "c/")], &[]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].example.0, b"a/file.txt".to_vec());
+        assert_eq!(found[0].example.2, b"c/x/file.txt".to_vec());
+    }
+
+    #[test]
+    fn rule_hits_follow_the_stream_rewrite() {
+        // "$1x" expands group 1 followed by "x" in the stream, so the second
+        // regex sees "srcx/"; a named group "1x" would leave "/" instead.
+        let opts = Options {
+            path_renames: vec![rule("lib/", "src/")],
+            path_rename_regexes: vec![
+                (
+                    regex::bytes::Regex::new("^(src)/").unwrap(),
+                    b"$1x/".to_vec(),
+                ),
+                (
+                    regex::bytes::Regex::new("^srcx/").unwrap(),
+                    b"out/".to_vec(),
+                ),
+            ],
+            ..Options::default()
+        };
+
+        let hits = count_rule_hits(&opts, &[b"lib/a.rs".to_vec(), b"docs/b.md".to_vec()]);
+
+        assert_eq!(hits, vec![1, 1, 1]);
+    }
+}
@@ -0,0 +1,455 @@
+//! `--split-to NAME=PATHSPEC[,PATHSPEC...]`: split one repository into
+//! several by path sets.
+//!
+//! fast-export runs once. A fan-out thread hands every chunk it reads to
+//! one stream processor per split, each running on its own thread with its
+//! path set as `--path` filters, into a fresh bare repository
+//! `DIR/NAME.git`. Every split has its own filter state, fast-import, marks
+//! and prune-empty decisions, so a commit that ends up empty in one split can
+//! survive in another. Chunks travel over bounded channels, so the export
+//! runs at the pace of the slowest split and is never held in full.
+//! `--split-rest NAME` collects the paths no split claimed; without it they
+//! are dropped. The source repository is only read.
+
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread;
+
+use crate::diagnostics::{self, Phase};
+use crate::error::Result as FilterRepoResult;
+use crate::log::MigrationLog;
+use crate::opts::{CleanupMode, Options};
+use crate::refs::RefSnapshot;
+
+/// Bytes per read from fast-export, and chunks queued per split before the
+/// reader waits for that split to catch up.
+const CHUNK_SIZE: usize = 64 * 1024;
+const QUEUED_CHUNKS: usize = 64;
+
+type Chunk = Result<Arc<[u8]>, String>;
+
+/// One split's copy of the exported stream.
+struct StreamCopy {
+    chunks: Receiver<Chunk>,
+    current: Arc<[u8]>,
+    pos: usize,
+}
+
+impl StreamCopy {
+    fn new(chunks: Receiver<Chunk>) -> Self {
+        StreamCopy {
+            chunks,
+            current: Arc::from(Vec::new()),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for StreamCopy {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.current.len() {
+            match self.chunks.recv() {
+                Ok(Ok(chunk)) => {
+                    self.current = chunk;
+                    self.pos = 0;
+                }
+                Ok(Err(message)) => return Err(io::Error::other(message)),
+                // The reader hung up after the last chunk.
+                Err(_) => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.current.len() - self.pos);
+        buf[..n].copy_from_slice(&self.current[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Reads fast-export's stdout once into every copy. A split that stops
+/// reading drops out; the others carry on. A failed export reaches every
+/// copy still listening as a read error, so no split mistakes a truncated
+/// stream for a complete one.
+fn fan_out(mut fe: Child, mut copies: Vec<SyncSender<Chunk>>) -> io::Result<()> {
+    let mut stdout = fe
+        .stdout
+        .take()
+        .ok_or_else(|| io::Error::other("git fast-export produced no stdout"))?;
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let failure = loop {
+        match stdout.read(&mut buf) {
+            Ok(0) => break None,
+            Ok(n) => {
+                let chunk: Arc<[u8]> = Arc::from(&buf[..n]);
+                copies.retain(|copy| copy.send(Ok(Arc::clone(&chunk))).is_ok());
+                if copies.is_empty() {
+                    // Every split has failed and reports its own error.
+                    let _ = fe.kill();
+                    let _ = fe.wait();
+                    return Ok(());
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => break Some(format!("failed to read git fast-export: {e}")),
+        }
+    };
+    drop(stdout);
+    let failure = match (failure, fe.wait()) {
+        (Some(failure), _) => Some(failure),
+        (None, Ok(status)) if status.success() => None,
+        (None, Ok(status)) => Some(format!("git fast-export failed: {status}")),
+        (None, Err(e)) => Some(format!("failed to wait for git fast-export: {e}")),
+    };
+    match failure {
+        None => Ok(()),
+        Some(message) => {
+            for copy in &copies {
+                let _ = copy.send(Err(message.clone()));
+            }
+            Err(io::Error::other(message))
+        }
+    }
+}
+
+struct Split {
+    name: String,
+    paths: Vec<Vec<u8>>,
+    // The --split-rest repository keeps what matches none of `paths`.
+    invert: bool,
+    repo: PathBuf,
+}
+
+pub(crate) fn run(opts: &Options) -> FilterRepoResult<()> {
+    let Some(out_dir) = opts.split_output_dir.as_deref() else {
+        return Ok(());
+    };
+    let out_dir = if out_dir.is_absolute() {
+        out_dir.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(out_dir)
+    };
+    fs::create_dir_all(&out_dir)?;
+
+    let mut splits: Vec<Split> = opts
+        .split_to
+        .iter()
+        .map(|split| Split {
+            name: split.name.clone(),
+            paths: split.paths.clone(),
+            invert: false,
+            repo: out_dir.join(format!("{}.git", split.name)),
+        })
+        .collect();
+    if let Some(rest) = &opts.split_rest {
+        splits.push(Split {
+            name: rest.clone(),
+            paths: opts
+                .split_to
+                .iter()
+                .flat_map(|split| split.paths.iter().cloned())
+                .collect(),
+            invert: true,
+            repo: out_dir.join(format!("{rest}.git")),
+        });
+    }
+    for split in &splits {
+        if split.repo.exists() {
+            return Err(io::Error::other(format!(
+                "{} already exists; remove it or pick another --split-output-dir",
+                split.repo.display()
+            ))
+            .into());
+        }
+    }
+    for split in &splits {
+        init_bare(&split.repo)?;
+    }
+
+    let redacting;
+    let opts = if opts.redact_paths {
+        redacting = crate::with_path_redactions(opts)?;
+        &redacting
+    } else {
+        opts
+    };
+    let split_opts: Vec<Options> = splits
+        .iter()
+        .map(|split| Options {
+            target: split.repo.clone(),
+            split_to: Vec::new(),
+            split_output_dir: None,
+            split_rest: None,
+            paths: split.paths.clone(),
+            invert_paths: split.invert,
+            migration_log: opts
+                .migration_log
+                .as_deref()
+                .map(|log| split_log_path(log, &split.name)),
+            // The split repos start empty, and nothing in the source may
+            // change: no backup, no origin migration, no reset or repack.
+            force: true,
+            backup: false,
+            partial: true,
+            reset: false,
+            cleanup: CleanupMode::None,
+            ..opts.clone()
+        })
+        .collect();
+    for split_opts in &split_opts {
+        crate::sanity::preflight(split_opts)?;
+    }
+
+    let source_refs = RefSnapshot::capture(&opts.source)?;
+    // The splits import into empty repositories, so the stream must carry
+    // blob data even where a same-repo run would use --no-data.
+    let fe = crate::pipes::build_fast_export_cmd(
+        &Options {
+            target: out_dir.clone(),
+            ..opts.clone()
+        },
+        &source_refs,
+    )?
+    .spawn()
+    .map_err(|e| io::Error::other(format!("failed to spawn git fast-export: {e}")))?;
+    let (senders, copies): (Vec<_>, Vec<_>) = splits
+        .iter()
+        .map(|_| mpsc::sync_channel(QUEUED_CHUNKS))
+        .unzip();
+
+    let results = thread::scope(|scope| {
+        let reader = scope.spawn(move || fan_out(fe, senders));
+        let workers: Vec<_> = splits
+            .iter()
+            .zip(&split_opts)
+            .zip(copies)
+            .map(|((split, split_opts), copy)| {
+                let source_refs = &source_refs;
+                scope.spawn(move || {
+                    diagnostics::info(
+                        Phase::Stream,
+                        format!("Writing split '{}' to {}", split.name, split.repo.display()),
+                    );
+                    write_split(split_opts, source_refs, StreamCopy::new(copy))
+                })
+            })
+            .collect();
+        let results: Vec<FilterRepoResult<()>> = workers
+            .into_iter()
+            .map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|_| Err(io::Error::other("split writer panicked").into()))
+            })
+            .collect();
+        let export = reader
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("fast-export reader panicked")));
+        (results, export)
+    });
+    let (results, export) = results;
+    // A failed export is the cause of whatever the splits saw.
+    export?;
+    for result in results {
+        result?;
+    }
+
+    let head = source_head(&opts.source);
+    let mut summaries = Vec::with_capacity(splits.len());
+    for split in &splits {
+        if let Some(head) = &head {
+            // Best effort: a split without that branch keeps git's default.
+            let _ = Command::new("git")
+                .arg("-C")
+                .arg(&split.repo)
+                .args(["symbolic-ref", "HEAD", head])
+                .status();
+        }
+        summaries.push((split, count_commits(&split.repo)?, repo_size(&split.repo)?));
+    }
+
+    diagnostics::info(
+        Phase::Finalize,
+        format!(
+            "Split into {} repositories under {}:",
+            summaries.len(),
+            out_dir.display()
+        ),
+    );
+    for (split, commits, size) in summaries {
+        diagnostics::info(
+            Phase::Finalize,
+            format!(
+                "  {}.git: {} commit(s), {}",
+                split.name,
+                commits,
+                crate::analysis::format_size_human(size)
+            ),
+        );
+    }
+    Ok(())
+}
+
+/// One split's share of [`crate::rewrite`]: its own migration log and
+/// stream processor over its copy of the export.
+fn write_split(
+    opts: &Options,
+    source_refs: &RefSnapshot,
+    export: StreamCopy,
+) -> FilterRepoResult<()> {
+    let mut migration_log = MigrationLog::open(opts.migration_log.as_deref())?;
+    if opts.reproducible {
+        migration_log = migration_log.pinned_to(crate::reproducible::timestamp())?;
+    }
+    crate::stream::run_on_export(opts, &migration_log, source_refs, Box::new(export))?;
+    Ok(migration_log.flush()?)
+}
+
+fn init_bare(repo: &Path) -> io::Result<()> {
+    let status = Command::new("git")
+        .args(["init", "--bare", "-q"])
+        .arg(repo)
+        .status()
+        .map_err(|e| io::Error::other(format!("failed to run git init: {e}")))?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "git init --bare {} failed: {status}",
+            repo.display()
+        )));
+    }
+    Ok(())
+}
+
+fn source_head(source: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(source)
+        .args(["symbolic-ref", "-q", "HEAD"])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// `--migration-log out.jsonl` becomes `out.NAME.jsonl` for each split.
+fn split_log_path(log: &Path, name: &str) -> PathBuf {
+    let stem = log
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let file_name = match log.extension() {
+        Some(ext) => format!("{stem}.{name}.{}", ext.to_string_lossy()),
+        None => format!("{stem}.{name}"),
+    };
+    log.with_file_name(file_name)
+}
+
+fn count_commits(repo: &Path) -> io::Result<usize> {
+    let stdout = git_stdout(repo, &["rev-list", "--all", "--count"])?;
+    stdout.trim().parse().map_err(|e| {
+        io::Error::other(format!(
+            "unexpected git rev-list --count output {:?} in {}: {e}",
+            stdout.trim(),
+            repo.display()
+        ))
+    })
+}
+
+/// Loose plus packed object bytes, from `git count-objects -v`.
+fn repo_size(repo: &Path) -> io::Result<u64> {
+    let kib: u64 = git_stdout(repo, &["count-objects", "-v"])?
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(": ")?;
+            matches!(key, "size" | "size-pack")
+                .then(|| value.trim().parse::<u64>().ok())
+                .flatten()
+        })
+        .sum();
+    Ok(kib * 1024)
+}
+
+fn git_stdout(repo: &Path, args: &[&str]) -> io::Result<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git {} failed in {}: {}",
+            args.join(" "),
+            repo.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_log_path_inserts_the_split_name() {
+        assert_eq!(
+            split_log_path(Path::new("logs/migration.jsonl"), "api"),
+            PathBuf::from("logs/migration.api.jsonl")
+        );
+        assert_eq!(
+            split_log_path(Path::new("migration"), "web"),
+            PathBuf::from("migration.web")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failed_export_reaches_every_copy_as_an_error() {
+        let export = |script: &str| {
+            Command::new("sh")
+                .args(["-c", script])
+                .stdout(std::process::Stdio::piped())
+                .spawn()
+                .unwrap()
+        };
+        let read_copies = |fe: Child| {
+            let (senders, copies): (Vec<_>, Vec<_>) = (0..2).map(|_| mpsc::sync_channel(1)).unzip();
+            let reader = thread::spawn(move || fan_out(fe, senders));
+            let copies: Vec<_> = copies
+                .into_iter()
+                .map(|copy| {
+                    let mut out = Vec::new();
+                    let result = StreamCopy::new(copy).read_to_end(&mut out);
+                    (out, result.is_ok())
+                })
+                .collect();
+            (copies, reader.join().unwrap().is_ok())
+        };
+
+        let (copies, exported) = read_copies(export("printf 'feature done\\n'"));
+        assert!(exported);
+        for (out, ok) in copies {
+            assert_eq!(out, b"feature done\n");
+            assert!(ok);
+        }
+
+        let (copies, exported) = read_copies(export("printf 'commit'; exit 3"));
+        assert!(!exported);
+        for (out, ok) in copies {
+            assert_eq!(out, b"commit");
+            assert!(!ok);
+        }
+    }
+
+    #[test]
+    fn summary_queries_fail_outside_a_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing.git");
+        assert!(count_commits(&missing).is_err());
+        assert!(repo_size(&missing).is_err());
+    }
+}
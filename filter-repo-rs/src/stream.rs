@@ -1,22 +1,38 @@
 use std::borrow::Cow;
 use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::collections::{BTreeSet, HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::{create_dir_all, File, OpenOptions};
 use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
+use std::process::{Child, Command, Stdio};
 use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-use crate::commit::{AuthorRewriter, MailmapRewriter};
+use crate::blob_cache::BlobCache;
+use crate::blob_callback::BlobCallback;
+use crate::commit::{
+    AuthorRewriter, IdentityKind, IdentityOverride, MailmapRewriter, RootFileInjector,
+};
+use crate::diagnostics::{self, Phase};
+use crate::eol::EolNormalizer;
 use crate::error::Result as FilterRepoResult;
 use crate::gitutil::git_dir;
 use crate::limits::parse_data_size_header;
+use crate::log::MigrationLog;
 use crate::message::blob_regex::RegexReplacer as BlobRegexReplacer;
 use crate::message::msg_regex::RegexReplacer as MsgRegexReplacer;
-use crate::message::{MessageReplacer, ShortHashMapper};
+use crate::message::{
+    MessageReplacer, PathScopedReplacer, ShortHashMapper, SubjectNormalizer, TagNameReferences,
+};
 use crate::opts::Options;
+use crate::pipeline::{PipelineGauge, PipelineWriter};
+use crate::pipes::ExportReader;
+use crate::progress::Progress;
+use crate::refs::RefSnapshot;
+use crate::reproducible::{MarkRenumberer, MarkTable};
+use crate::rules::{RuleFile, RuleLimits};
 
 const REPORT_SAMPLE_LIMIT: usize = 20;
 const SHA_HEX_LEN: usize = 40;
@@ -89,6 +105,7 @@ fn rewrite_commit_identity_line(
     committer_rewriter: Option<&AuthorRewriter>,
     email_rewriter: Option<&AuthorRewriter>,
     mailmap_rewriter: Option<&MailmapRewriter>,
+    committer_override: Option<&IdentityOverride>,
 ) -> Vec<u8> {
     let is_author_line = line.starts_with(b"author ");
     let is_committer_line = line.starts_with(b"committer ");
@@ -115,7 +132,12 @@ fn rewrite_commit_identity_line(
         }
     }
 
-    rewrite_timestamp_line(&rewritten, opts).into_owned()
+    let rewritten = rewrite_timestamp_line(&rewritten, opts);
+    // After the date options too, so `--override-committer-date now` wins.
+    match committer_override {
+        Some(o) => o.apply(&rewritten),
+        None => rewritten.into_owned(),
+    }
 }
 
 #[doc(hidden)]
@@ -139,9 +161,134 @@ pub fn benchmark_rewrite_commit_identity_line(
         committer_rewriter,
         email_rewriter,
         mailmap_rewriter,
+        None,
+    )
+}
+
+fn build_mode_normalization_report(
+    opts: &Options,
+    hits: &[usize],
+) -> Option<Vec<crate::finalize::ModeNormalizationReport>> {
+    if opts.mode_normalizations.is_empty() {
+        return None;
+    }
+    Some(
+        opts.mode_normalizations
+            .iter()
+            .zip(hits)
+            .map(
+                |((pattern, mode), &changed)| crate::finalize::ModeNormalizationReport {
+                    pattern: String::from_utf8_lossy(pattern).into_owned(),
+                    mode: String::from_utf8_lossy(mode).into_owned(),
+                    changed,
+                },
+            )
+            .collect(),
     )
 }
 
+/// Rule patterns with their match totals, as returned by `rule_match_counts`.
+type RuleMatchCounts = Vec<(String, usize)>;
+
+fn build_regex_rule_report(
+    sources: &[(&str, Option<RuleMatchCounts>)],
+) -> Option<Vec<crate::finalize::RegexRuleReport>> {
+    let rules: Vec<_> = sources
+        .iter()
+        .filter_map(|(source, counts)| counts.as_ref().map(|c| (source, c)))
+        .flat_map(|(source, counts)| {
+            counts
+                .iter()
+                .map(|(pattern, matches)| crate::finalize::RegexRuleReport {
+                    source: source.to_string(),
+                    pattern: pattern.clone(),
+                    matches: *matches,
+                })
+        })
+        .collect();
+    if rules.is_empty() {
+        None
+    } else {
+        Some(rules)
+    }
+}
+
+fn build_identity_rewrite_report(
+    author_rewriter: Option<&AuthorRewriter>,
+    committer_rewriter: Option<&AuthorRewriter>,
+    email_rewriter: Option<&AuthorRewriter>,
+    mailmap_rewriter: Option<&MailmapRewriter>,
+) -> Option<crate::finalize::IdentityRewriteReport> {
+    use crate::commit::IdentityRuleStat;
+    use crate::finalize::{IdentityRewriteReport, IdentityRuleReport};
+
+    let mut rules = Vec::new();
+    let mut identities: HashSet<Vec<u8>> = HashSet::new();
+    let mut push_rules = |source: &str, stats: Vec<IdentityRuleStat>| {
+        rules.extend(stats.into_iter().map(|stat| IdentityRuleReport {
+            source: source.to_string(),
+            old: stat.old,
+            new: stat.new,
+            author_hits: stat.hits.author,
+            committer_hits: stat.hits.committer,
+            tagger_hits: stat.hits.tagger,
+        }));
+    };
+    if let Some(rw) = mailmap_rewriter {
+        push_rules("mailmap", rw.rule_stats());
+        identities.extend(rw.rewritten_identities());
+    }
+    for (source, rewriter) in [
+        ("author-rewrite", author_rewriter),
+        ("committer-rewrite", committer_rewriter),
+        ("email-rewrite", email_rewriter),
+    ] {
+        if let Some(rw) = rewriter {
+            push_rules(source, rw.rule_stats());
+            identities.extend(rw.rewritten_identities());
+        }
+    }
+    if rules.is_empty() {
+        return None;
+    }
+    let zero_hit_rules = rules.iter().filter(|r| r.total_hits() == 0).count();
+    Some(IdentityRewriteReport {
+        rules,
+        distinct_identities_rewritten: identities.len(),
+        zero_hit_rules,
+    })
+}
+
+fn build_identity_override_report(
+    opts: &Options,
+    committer_override: Option<&IdentityOverride>,
+    tagger_override: Option<&IdentityOverride>,
+) -> Option<Vec<crate::finalize::IdentityOverrideReport>> {
+    let overrides: Vec<_> = [
+        (
+            "committer",
+            committer_override,
+            opts.override_committer_date,
+        ),
+        ("tagger", tagger_override, opts.override_tagger_date),
+    ]
+    .into_iter()
+    .filter_map(|(header, o, date)| {
+        o.map(|o| crate::finalize::IdentityOverrideReport {
+            header,
+            identity: String::from_utf8_lossy(o.identity()).into_owned(),
+            date: date.as_str(),
+            stamped: o.stamped(),
+        })
+    })
+    .collect();
+    if overrides.is_empty() {
+        None
+    } else {
+        Some(overrides)
+    }
+}
+
 /// Add a path sample to the collection if under limit and not already present.
 fn add_sample(samples: &mut Vec<Vec<u8>>, path: &[u8]) {
     if samples.len() < REPORT_SAMPLE_LIMIT && !samples.iter().any(|p| p == path) {
@@ -149,6 +296,24 @@ fn add_sample(samples: &mut Vec<Vec<u8>>, path: &[u8]) {
     }
 }
 
+fn add_content_sample(samples: &mut Vec<(Vec<u8>, usize)>, path: &[u8], pattern: usize) {
+    let path = path.strip_suffix(b"\n").unwrap_or(path);
+    if samples.len() < REPORT_SAMPLE_LIMIT && !samples.iter().any(|(p, _)| p == path) {
+        samples.push((path.to_vec(), pattern));
+    }
+}
+
+fn add_modified_sample(
+    samples: &mut Vec<(Vec<u8>, Option<Vec<u8>>)>,
+    path: &[u8],
+    oid: Option<&[u8]>,
+) {
+    let path = path.strip_suffix(b"\n").unwrap_or(path);
+    if samples.len() < REPORT_SAMPLE_LIMIT && !samples.iter().any(|(p, _)| p == path) {
+        samples.push((path.to_vec(), oid.map(<[u8]>::to_vec)));
+    }
+}
+
 #[derive(Debug, Default)]
 struct PathCompatStats {
     policy: String,
@@ -156,6 +321,90 @@ struct PathCompatStats {
     skipped: usize,
     sanitized_samples: Vec<String>,
     skipped_samples: Vec<String>,
+    // Plain sanitized path -> every original that sanitized onto it.
+    collision_groups: BTreeMap<Vec<u8>, BTreeSet<Vec<u8>>>,
+    // Every event in stream order, for `path-events.jsonl`.
+    events: Vec<PathEventRecord>,
+}
+
+/// One line of `path-events.jsonl`. A path that is rewritten in several
+/// commits gets one record per commit rather than a repeat counter, so each
+/// record names the commit it came from.
+#[derive(Debug)]
+struct PathEventRecord {
+    action: &'static str,
+    original: String,
+    rewritten: Option<String>,
+    reason: String,
+    collides_with: Option<String>,
+    commit: Option<String>,
+}
+
+const PATH_EVENTS_FILE: &str = "path-events.jsonl";
+
+/// Commit outcomes for one ref. A commit reachable from several refs counts
+/// only for the ref its `commit` header names, which is the first ref
+/// fast-export reached it from.
+#[derive(Debug, Default)]
+struct RefCommitCounts {
+    emitted: usize,
+    pruned: usize,
+    degenerate_merges: usize,
+}
+
+fn record_commit_outcome(
+    counts: &mut BTreeMap<Vec<u8>, RefCommitCounts>,
+    commit_ref: &[u8],
+    outcome: crate::commit::CommitOutcome,
+) {
+    let entry = counts.entry(commit_ref.to_vec()).or_default();
+    if outcome.kept {
+        entry.emitted += 1;
+    } else {
+        entry.pruned += 1;
+    }
+    if outcome.degenerate_merge {
+        entry.degenerate_merges += 1;
+    }
+}
+
+/// Per-ref report rows, under the new ref names with the old name for
+/// renamed refs.
+fn build_ref_stats_report(
+    counts: BTreeMap<Vec<u8>, RefCommitCounts>,
+    ref_renames: &BTreeSet<(Vec<u8>, Vec<u8>)>,
+) -> Vec<crate::finalize::RefStatsReport> {
+    let old_names: HashMap<&[u8], &[u8]> = ref_renames
+        .iter()
+        .map(|(old, new)| (new.as_slice(), old.as_slice()))
+        .collect();
+    counts
+        .into_iter()
+        .map(|(name, counts)| crate::finalize::RefStatsReport {
+            renamed_from: old_names
+                .get(name.as_slice())
+                .map(|old| String::from_utf8_lossy(old).into_owned()),
+            name: String::from_utf8_lossy(&name).into_owned(),
+            commits_emitted: counts.emitted,
+            commits_pruned: counts.pruned,
+            merges_degenerated: counts.degenerate_merges,
+        })
+        .collect()
+}
+
+fn load_rule_file(path: &Path, limits: &RuleLimits, quiet: bool) -> io::Result<RuleFile> {
+    let file = RuleFile::read(path, limits)?;
+    if file.duplicates > 0 && !quiet {
+        diagnostics::notice(
+            Phase::Stream,
+            format!(
+                "ignored {} duplicate rule(s) in {}",
+                file.duplicates,
+                path.display()
+            ),
+        );
+    }
+    Ok(file)
 }
 
 struct FinalizeStreamArgs {
@@ -164,28 +413,57 @@ struct FinalizeStreamArgs {
     total_commits: usize,
     total_blobs: usize,
     path_compat_stats: PathCompatStats,
+    identity_rewrites: Option<crate::finalize::IdentityRewriteReport>,
+    identity_overrides: Option<Vec<crate::finalize::IdentityOverrideReport>>,
+    mode_normalizations: Option<Vec<crate::finalize::ModeNormalizationReport>>,
+    regex_rules: Option<Vec<crate::finalize::RegexRuleReport>>,
+    blob_cache: Option<crate::finalize::BlobCacheReport>,
+    subject_normalization: Option<crate::finalize::SubjectNormalizationReport>,
+    tag_name_references: Option<crate::finalize::TagNameReferencesReport>,
+    root_files: Option<crate::finalize::RootFilesReport>,
+    pipeline: Option<crate::finalize::PipelineReport>,
+    ref_namespaces: Option<crate::finalize::RefNamespacesReport>,
+    ref_stats: Vec<crate::finalize::RefStatsReport>,
+    eol_normalization: Option<crate::finalize::EolNormalizationReport>,
+    dropped_tags: Vec<crate::finalize::DroppedTagReport>,
+    pruned_tags: Vec<crate::finalize::PrunedTagReport>,
 }
 
+/// Filtered stream on its way to fast-import, queued by [`PipelineWriter`].
+type ImportWriter = BufWriter<PipelineWriter>;
+
 struct StreamIo {
     filt_file: BufWriter<File>,
     orig_file_opt: Option<BufWriter<File>>,
-    fe: std::process::Child,
+    /// `None` when the stream is a `--split-to` copy; the split waits for
+    /// the one fast-export itself.
+    fe: Option<std::process::Child>,
     fi: Option<std::process::Child>,
-    fe_out: BufReader<std::process::ChildStdout>,
-    fi_in_opt: Option<BufWriter<std::process::ChildStdin>>,
+    fe_out: ExportReader,
+    fi_in_opt: Option<ImportWriter>,
     fi_out_opt: Option<BufReader<std::process::ChildStdout>>,
+    import_gauge: Option<Arc<PipelineGauge>>,
 }
 
 struct Rewriters {
     replacer: Option<MessageReplacer>,
     msg_regex_replacer: Option<MsgRegexReplacer>,
+    // --tag-message-file; tags use the commit rules when unset
+    tag_message_rules: Option<(Option<MessageReplacer>, Option<MsgRegexReplacer>)>,
     short_hash_mapper: Option<ShortHashMapper>,
+    subject_normalizer: Option<SubjectNormalizer>,
+    tag_name_refs: Option<TagNameReferences>,
     content_replacer: Option<MessageReplacer>,
     content_regex_replacer: Option<BlobRegexReplacer>,
+    content_scoped_replacer: Option<ScopedContentReplacer>,
+    eol_normalizer: Option<EolNormalizer>,
+    blob_callback: Option<BlobCallback>,
     author_rewriter: Option<AuthorRewriter>,
     committer_rewriter: Option<AuthorRewriter>,
     email_rewriter: Option<AuthorRewriter>,
     mailmap_rewriter: Option<MailmapRewriter>,
+    committer_override: Option<IdentityOverride>,
+    tagger_override: Option<IdentityOverride>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -205,6 +483,7 @@ enum ParseState {
         mark: Option<u32>,
         header_buf: Vec<u8>,
         has_file_changes: bool,
+        has_content_changes: bool,
         commit_ref: Vec<u8>,
     },
     SkippingTagBlock,
@@ -261,6 +540,7 @@ impl ParseState {
             mark: None,
             header_buf: line.to_vec(),
             has_file_changes: false,
+            has_content_changes: false,
             commit_ref,
         }
     }
@@ -271,7 +551,12 @@ impl ParseState {
                 || line.starts_with(b"tag ")
                 || line.starts_with(b"reset ")
                 || line.starts_with(b"blob")
-                || line == b"done\n")
+                || line == b"done\n"
+                || line == b"alias\n"
+                || line == b"checkpoint\n"
+                || line.starts_with(b"feature ")
+                || line.starts_with(b"option ")
+                || line.starts_with(b"progress "))
     }
 
     fn consumes_tag_data_header(&self, line: &[u8]) -> bool {
@@ -305,6 +590,76 @@ impl ParseState {
     }
 }
 
+/// Top-level fast-import commands the dispatcher does not rewrite itself.
+///
+/// `blob`, `commit`, `tag`, `reset` and `done` have their own states; every
+/// other line reaching the idle dispatcher is classified here.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TopLevelCommand {
+    /// `feature`, `option` and `checkpoint`, plus the `from` line after a
+    /// reset of a ref outside `refs/heads/` and `refs/tags/`: forwarded.
+    PassThrough,
+    /// `ls`, `cat-blob`, `get-mark` and `progress` make fast-import answer
+    /// on its stdout, where filter-repo-rs reads its own `get-mark` replies;
+    /// comments carry nothing. All are dropped.
+    Strip,
+    /// `alias`, read together with its `mark` and `to` lines.
+    Alias,
+    Unknown,
+}
+
+impl TopLevelCommand {
+    fn classify(line: &[u8]) -> Self {
+        let body = line.strip_suffix(b"\n").unwrap_or(line);
+        let command = body.split(|&b| b == b' ').next().unwrap_or(body);
+        match command {
+            b"feature" | b"option" | b"checkpoint" | b"from" => Self::PassThrough,
+            b"progress" => Self::Strip,
+            _ if is_query_command(line) || body.starts_with(b"#") => Self::Strip,
+            b"alias" if body == b"alias" => Self::Alias,
+            _ => Self::Unknown,
+        }
+    }
+}
+
+/// `ls`, `cat-blob` and `get-mark`, which may also appear inside a commit.
+pub(crate) fn is_query_command(line: &[u8]) -> bool {
+    line.starts_with(b"ls ") || line.starts_with(b"cat-blob ") || line.starts_with(b"get-mark ")
+}
+
+/// Reads the `mark :N` and `to <target>` lines following an `alias` command.
+fn read_alias_stanza(
+    fe_out: &mut ExportReader,
+    orig_file: Option<&mut BufWriter<File>>,
+) -> io::Result<(u32, Vec<u8>)> {
+    let mut mark_line = Vec::new();
+    fe_out.read_until(b'\n', &mut mark_line)?;
+    let mut to_line = Vec::new();
+    fe_out.read_until(b'\n', &mut to_line)?;
+    if let Some(f) = orig_file {
+        f.write_all(&mark_line)?;
+        f.write_all(&to_line)?;
+    }
+    let mark = mark_line
+        .strip_prefix(b"mark :")
+        .and_then(|m| std::str::from_utf8(m.strip_suffix(b"\n").unwrap_or(m)).ok())
+        .and_then(|m| m.parse::<u32>().ok());
+    let target = to_line
+        .strip_prefix(b"to ")
+        .map(|t| t.strip_suffix(b"\n").unwrap_or(t).to_vec());
+    match (mark, target) {
+        (Some(mark), Some(target)) => Ok((mark, target)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "alias must be followed by 'mark :N' and 'to <target>', got {:?} and {:?}",
+                String::from_utf8_lossy(&mark_line),
+                String::from_utf8_lossy(&to_line)
+            ),
+        )),
+    }
+}
+
 #[derive(Debug)]
 enum ResetDispatch {
     Captured {
@@ -329,9 +684,33 @@ fn path_compat_sample_label(event: &crate::pathutil::PathCompatEvent) -> String
     }
 }
 
-fn record_path_compat_event(stats: &mut PathCompatStats, event: crate::pathutil::PathCompatEvent) {
+fn record_path_compat_event(
+    stats: &mut PathCompatStats,
+    event: crate::pathutil::PathCompatEvent,
+    commit: Option<&[u8]>,
+) {
+    use crate::pathutil::{escape_path_bytes, PathCompatAction};
+    stats.events.push(PathEventRecord {
+        action: match event.action {
+            PathCompatAction::Sanitized => "sanitized",
+            PathCompatAction::SanitizedWithCollision => "collision",
+            PathCompatAction::Skipped => "skipped",
+        },
+        original: escape_path_bytes(&event.original),
+        rewritten: event.rewritten.as_deref().map(escape_path_bytes),
+        reason: event.reason.clone(),
+        collides_with: event.collides_with.as_deref().map(escape_path_bytes),
+        commit: commit.map(|oid| String::from_utf8_lossy(oid).into_owned()),
+    });
+    if let Some(owner) = &event.collides_with {
+        let plain = crate::pathutil::sanitize_invalid_windows_path_bytes(&event.original);
+        let group = stats.collision_groups.entry(plain).or_default();
+        group.insert(owner.clone());
+        group.insert(event.original.clone());
+    }
     match event.action {
-        crate::pathutil::PathCompatAction::Sanitized => {
+        crate::pathutil::PathCompatAction::Sanitized
+        | crate::pathutil::PathCompatAction::SanitizedWithCollision => {
             stats.sanitized += 1;
             if stats.sanitized_samples.len() < REPORT_SAMPLE_LIMIT {
                 stats
@@ -347,6 +726,34 @@ fn record_path_compat_event(stats: &mut PathCompatStats, event: crate::pathutil:
         }
     }
 }
+
+/// Writes `path-events.jsonl` into the debug directory, one JSON object per
+/// line. A stale file from an earlier run is removed when nothing happened.
+fn write_path_events(debug_dir: &Path, events: &[PathEventRecord]) -> io::Result<Option<PathBuf>> {
+    let path = debug_dir.join(PATH_EVENTS_FILE);
+    if events.is_empty() {
+        match std::fs::remove_file(&path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => return Ok(None),
+        }
+    }
+    let mut out = BufWriter::new(File::create(&path)?);
+    for event in events {
+        let line = serde_json::json!({
+            "action": event.action,
+            "original": event.original,
+            "rewritten": event.rewritten,
+            "reason": event.reason,
+            "collides_with": event.collides_with,
+            "commit": event.commit,
+        });
+        serde_json::to_writer(&mut out, &line).map_err(io::Error::other)?;
+        out.write_all(b"\n")?;
+    }
+    out.flush()?;
+    Ok(Some(path))
+}
+
 /// Threshold for deciding whether to keep SHA lookup in memory or on disk.
 /// When number of SHAs exceeds this, use disk-based sorted file.
 /// Lowered from 50,000 to 10,000 to reduce memory spike during sorting
@@ -528,6 +935,10 @@ fn hex_val(b: u8) -> Option<u8> {
     }
 }
 
+/// Applies the global `--replace-text` rules to a blob: every literal rule in
+/// file order, then every `regex:`/`glob:` rule in file order, so a regex
+/// sees the output of the literal rules. Inline and `blob` payloads both go
+/// through here to keep that order in one place.
 fn process_blob_content(
     payload: Vec<u8>,
     content_replacer: &Option<MessageReplacer>,
@@ -552,7 +963,149 @@ fn process_blob_content(
     (data, changed)
 }
 
-/// Tracks which marks/shas were filtered and why (size vs sha-strip).
+/// Maps each original blob id to the paths it was ever stored at, over every
+/// ref, limited to `pathspecs`. The pathspecs go to `git log --stdin` after a
+/// `--` line rather than on the command line, so a large rule set cannot hit
+/// the argument length limit.
+pub(crate) fn blob_paths_in_history(
+    repo: &Path,
+    pathspecs: &[Vec<u8>],
+) -> io::Result<HashMap<Vec<u8>, Vec<Vec<u8>>>> {
+    if let Some(spec) = pathspecs.iter().find(|spec| spec.contains(&b'\n')) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "cannot limit history to a path containing a newline: {}",
+                String::from_utf8_lossy(spec)
+            ),
+        ));
+    }
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args([
+            "log",
+            "--all",
+            "--stdin",
+            "-m",
+            "--raw",
+            "--no-abbrev",
+            "--no-renames",
+            "-z",
+            "--format=",
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    {
+        // git reads all of stdin before it starts walking, so this cannot
+        // block on a full stdout pipe.
+        let mut stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| io::Error::other("failed to open git log stdin"))?;
+        let mut input = b"--\n".to_vec();
+        for spec in pathspecs {
+            input.extend_from_slice(spec);
+            input.push(b'\n');
+        }
+        stdin.write_all(&input)?;
+    }
+    let output = child.wait_with_output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git log failed while resolving blob paths: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    let mut paths_by_blob: HashMap<Vec<u8>, Vec<Vec<u8>>> = HashMap::new();
+    let mut fields = output.stdout.split(|&b| b == 0);
+    while let Some(field) = fields.next() {
+        let meta = field.trim_ascii_start();
+        if !meta.starts_with(b":") {
+            continue;
+        }
+        let Some(path) = fields.next() else {
+            break;
+        };
+        // :<old mode> <new mode> <old oid> <new oid> <status>
+        let Some(new_oid) = meta.split(|&b| b == b' ').nth(3) else {
+            continue;
+        };
+        if new_oid.iter().all(|&b| b == b'0') {
+            continue;
+        }
+        let entry = paths_by_blob
+            .entry(new_oid.to_ascii_lowercase())
+            .or_default();
+        if !entry.iter().any(|p| p == path) {
+            entry.push(path.to_vec());
+        }
+    }
+    Ok(paths_by_blob)
+}
+
+/// Path-scoped `--replace-text` rules plus the original blob ids that were
+/// ever stored at one of the scoped paths, so the blob stage (which has no
+/// path) knows which rules apply.
+struct ScopedContentReplacer {
+    replacer: PathScopedReplacer,
+    paths_by_blob: HashMap<Vec<u8>, Vec<Vec<u8>>>,
+}
+
+impl ScopedContentReplacer {
+    fn load(repo: &Path, replacer: PathScopedReplacer) -> io::Result<Self> {
+        let pathspecs: Vec<Vec<u8>> = replacer
+            .paths()
+            .map(|path| [b":(literal)".as_slice(), path].concat())
+            .collect();
+        let paths_by_blob = blob_paths_in_history(repo, &pathspecs)?;
+        Ok(Self {
+            replacer,
+            paths_by_blob,
+        })
+    }
+
+    fn apply_to_blob(&self, orig_sha: Option<&[u8]>, data: Vec<u8>) -> (Vec<u8>, bool) {
+        let Some(paths) = orig_sha.and_then(|sha| self.paths_by_blob.get(sha)) else {
+            return (data, false);
+        };
+        let mut data = data;
+        let mut changed = false;
+        for path in paths {
+            let (tmp, did_change) = self.replacer.apply_for_path(path, data);
+            changed = changed || did_change;
+            data = tmp;
+        }
+        (data, changed)
+    }
+
+    /// Appends the resolved blob-to-path scope in a stable order, so the
+    /// blob cache notices when history moves a blob into or out of scope.
+    fn scope_fingerprint(&self, out: &mut Vec<u8>) {
+        let mut scope: Vec<(&Vec<u8>, Vec<&Vec<u8>>)> = self
+            .paths_by_blob
+            .iter()
+            .map(|(oid, paths)| {
+                let mut paths: Vec<&Vec<u8>> = paths.iter().collect();
+                paths.sort();
+                (oid, paths)
+            })
+            .collect();
+        scope.sort();
+        for (oid, paths) in scope {
+            out.extend_from_slice(oid);
+            for path in paths {
+                out.push(b' ');
+                out.extend_from_slice(path);
+            }
+            out.push(b'\0');
+        }
+    }
+}
+
+/// Tracks which marks/shas were filtered and why (size, sha-strip or content).
 struct FilterTracker {
     oversize_marks: HashSet<u32>,
     oversize_shas: HashSet<Vec<u8>>,
@@ -560,8 +1113,18 @@ struct FilterTracker {
     suppressed_marks_by_sha: HashSet<u32>,
     suppressed_shas_by_size: HashSet<Vec<u8>>,
     suppressed_shas_by_sha: HashSet<Vec<u8>>,
+    // Index of the --strip-blobs-matching pattern that dropped each mark.
+    suppressed_marks_by_content: HashMap<u32, usize>,
     modified_marks: HashSet<u32>,
+    // Original ids of modified blobs, for blob-map and the report.
+    modified_blob_oids: HashMap<u32, Vec<u8>>,
     emitted_marks: HashSet<u32>,
+    // Original sizes of emitted blobs, kept only for per-extension limits.
+    blob_sizes_by_mark: HashMap<u32, usize>,
+    // Original ids of those blobs, kept only for --replace-blobs-with-pointer.
+    blob_oids_by_mark: HashMap<u32, Vec<u8>>,
+    // Pointer text for marks dropped by size under --replace-blobs-with-pointer.
+    pointer_blobs_by_mark: HashMap<u32, PointerBlobContent>,
 }
 
 impl FilterTracker {
@@ -573,8 +1136,13 @@ impl FilterTracker {
             suppressed_marks_by_sha: HashSet::new(),
             suppressed_shas_by_size: HashSet::new(),
             suppressed_shas_by_sha: HashSet::new(),
+            suppressed_marks_by_content: HashMap::new(),
             modified_marks: HashSet::new(),
+            modified_blob_oids: HashMap::new(),
             emitted_marks: HashSet::new(),
+            blob_sizes_by_mark: HashMap::new(),
+            blob_oids_by_mark: HashMap::new(),
+            pointer_blobs_by_mark: HashMap::new(),
         }
     }
 }
@@ -583,8 +1151,12 @@ impl FilterTracker {
 struct ReportSamples {
     size: Vec<Vec<u8>>,
     sha: Vec<Vec<u8>>,
-    modified: Vec<Vec<u8>>,
+    // Stripped paths with the index of the pattern that matched.
+    content: Vec<(Vec<u8>, usize)>,
+    // Modified paths with the original blob id, when the blob had one.
+    modified: Vec<(Vec<u8>, Option<Vec<u8>>)>,
     inline_modified_paths: HashSet<Vec<u8>>,
+    inline_content_stripped_paths: HashSet<Vec<u8>>,
 }
 
 impl ReportSamples {
@@ -592,23 +1164,32 @@ impl ReportSamples {
         Self {
             size: Vec::new(),
             sha: Vec::new(),
+            content: Vec::new(),
             modified: Vec::new(),
             inline_modified_paths: HashSet::new(),
+            inline_content_stripped_paths: HashSet::new(),
         }
     }
 }
 
 struct PendingInlineDataCtx<'a> {
     opts: &'a Options,
-    fe_out: &'a mut BufReader<std::process::ChildStdout>,
+    fe_out: &'a mut ExportReader,
     orig_file_opt: &'a mut Option<BufWriter<File>>,
     commit_buf: &'a mut Vec<u8>,
     commit_has_changes: &'a mut bool,
+    commit_has_content_changes: &'a mut bool,
     pending_inline: &'a mut Option<(usize, Vec<u8>)>,
     samples: &'a mut ReportSamples,
     path_compat_stats: &'a mut PathCompatStats,
+    sanitized_paths: &'a mut crate::pathutil::SanitizedPathTable,
+    commit_original_oid: Option<&'a [u8]>,
     content_replacer: &'a Option<MessageReplacer>,
     content_regex_replacer: &'a Option<BlobRegexReplacer>,
+    content_scoped_replacer: &'a Option<ScopedContentReplacer>,
+    eol_normalizer: &'a mut Option<EolNormalizer>,
+    blob_callback: &'a Option<BlobCallback>,
+    migration_log: &'a MigrationLog,
 }
 
 fn process_pending_inline_data_line(
@@ -629,45 +1210,96 @@ fn process_pending_inline_data_line(
         f.write_all(&payload)?;
     }
 
+    let decoded = crate::pathutil::decode_fast_export_path_bytes(&path_bytes);
     let mut drop_inline = false;
-    if let Some(max) = ctx.opts.max_blob_size {
+    let mut pointer = None;
+    if let Some(max) = ctx.opts.blob_size_limit_for_path(&decoded) {
         if n > max {
             drop_inline = true;
+            if ctx.opts.replace_blobs_with_pointer {
+                // Inline blobs never existed as objects of their own.
+                pointer = Some(PointerBlobContent {
+                    original_size: n as u64,
+                    original_oid: "none (inline blob)".to_string(),
+                });
+            }
         }
     }
-    if drop_inline {
+    let content_match = if drop_inline {
+        None
+    } else {
+        content_strip_match(ctx.opts, &payload)
+    };
+    if drop_inline || content_match.is_some() {
         ctx.commit_buf.truncate(pos);
-        let decoded = crate::pathutil::decode_fast_export_path_bytes(&path_bytes);
-        let (enc, path_event) =
-            crate::pathutil::encode_path_for_fi_with_policy(&decoded, ctx.opts.path_compat_policy)
-                .map_err(io::Error::other)?;
+        let (enc, path_event) = crate::pathutil::encode_path_for_fi_with_policy(
+            &decoded,
+            ctx.opts.path_compat_policy,
+            ctx.opts.unsafe_path_policy,
+            ctx.sanitized_paths,
+        )
+        .map_err(io::Error::other)?;
         if let Some(event) = path_event {
-            record_path_compat_event(ctx.path_compat_stats, event);
+            record_path_compat_event(ctx.path_compat_stats, event, ctx.commit_original_oid);
         }
         if let Some(enc) = enc {
-            ctx.commit_buf.extend_from_slice(b"D ");
-            ctx.commit_buf.extend_from_slice(&enc);
-            ctx.commit_buf.push(b'\n');
+            match &pointer {
+                Some(pointer) => pointer.write_inline_modify(ctx.commit_buf, &enc),
+                None => {
+                    ctx.commit_buf.extend_from_slice(b"D ");
+                    ctx.commit_buf.extend_from_slice(&enc);
+                    ctx.commit_buf.push(b'\n');
+                }
+            }
             *ctx.commit_has_changes = true;
+            *ctx.commit_has_content_changes = true;
+        }
+        match content_match {
+            Some(idx) => {
+                add_content_sample(&mut ctx.samples.content, &path_bytes, idx);
+                ctx.samples.inline_content_stripped_paths.insert(path_bytes);
+            }
+            None => add_sample(&mut ctx.samples.size, &path_bytes),
         }
-        add_sample(&mut ctx.samples.size, &path_bytes);
         return Ok(true);
     }
 
-    if ctx.content_replacer.is_none() && ctx.content_regex_replacer.is_none() {
+    if ctx.content_replacer.is_none()
+        && ctx.content_regex_replacer.is_none()
+        && ctx.content_scoped_replacer.is_none()
+        && ctx.eol_normalizer.is_none()
+        && ctx.blob_callback.is_none()
+    {
         let header = format!("data {}\n", payload.len());
         ctx.commit_buf.extend_from_slice(header.as_bytes());
         ctx.commit_buf.extend_from_slice(&payload);
     } else {
         let mut new_payload = payload;
         let mut changed = false;
-        if let Some(r) = ctx.content_replacer {
-            let (tmp, did_change) = r.apply_with_change(new_payload);
+        if let Some(scoped) = ctx.content_scoped_replacer {
+            let (tmp, did_change) = scoped.replacer.apply_for_path(&decoded, new_payload);
+            changed = did_change;
+            new_payload = tmp;
+        }
+        let (tmp, did_change) = process_blob_content(
+            new_payload,
+            ctx.content_replacer,
+            ctx.content_regex_replacer,
+        );
+        changed = changed || did_change;
+        new_payload = tmp;
+        if let Some(eol) = ctx.eol_normalizer.as_mut() {
+            let (tmp, did_change) = eol.apply_for_path(&decoded, new_payload);
             changed = changed || did_change;
             new_payload = tmp;
         }
-        if let Some(rr) = ctx.content_regex_replacer {
-            let (tmp, did_change) = rr.apply_regex_with_change(new_payload);
+        if let Some(callback) = ctx.blob_callback {
+            let (tmp, did_change) = callback.apply(new_payload).map_err(|e| {
+                io::Error::other(format!(
+                    "{e} (inline blob at {})",
+                    String::from_utf8_lossy(&decoded)
+                ))
+            })?;
             changed = changed || did_change;
             new_payload = tmp;
         }
@@ -675,11 +1307,13 @@ fn process_pending_inline_data_line(
         ctx.commit_buf.extend_from_slice(header.as_bytes());
         ctx.commit_buf.extend_from_slice(&new_payload);
         if changed {
-            add_sample(&mut ctx.samples.modified, &path_bytes);
+            add_modified_sample(&mut ctx.samples.modified, &path_bytes, None);
             ctx.samples.inline_modified_paths.insert(path_bytes.clone());
+            ctx.migration_log.log_blob_rewritten(None, &decoded)?;
         }
     }
     *ctx.commit_has_changes = true;
+    *ctx.commit_has_content_changes = true;
     Ok(true)
 }
 
@@ -704,12 +1338,16 @@ struct CommitMPrecheckCtx<'a> {
     opts: &'a Options,
     commit_buf: &'a mut Vec<u8>,
     commit_has_changes: &'a mut bool,
+    commit_has_content_changes: &'a mut bool,
     pending_inline: &'a mut Option<(usize, Vec<u8>)>,
     tracker: &'a mut FilterTracker,
     samples: &'a mut ReportSamples,
     path_compat_stats: &'a mut PathCompatStats,
+    sanitized_paths: &'a mut crate::pathutil::SanitizedPathTable,
+    commit_original_oid: Option<&'a [u8]>,
     strip_sha_lookup: &'a StripShaLookup,
     blob_size_tracker: &'a mut BlobSizeTracker,
+    migration_log: &'a MigrationLog,
 }
 
 fn process_commit_m_line_precheck(
@@ -734,6 +1372,8 @@ fn process_commit_m_line_precheck(
     let mut drop_path = false;
     let mut reason_size = false;
     let mut reason_sha = false;
+    let mut content_pattern = None;
+    let mut pointer = None;
     if id.first().copied() == Some(b':') {
         let mut num: u32 = 0;
         let mut seen = false;
@@ -750,12 +1390,34 @@ fn process_commit_m_line_precheck(
         }
         if seen && tracker.oversize_marks.contains(&num) {
             drop_path = true;
-            add_sample(&mut samples.size, path_bytes);
+            content_pattern = tracker.suppressed_marks_by_content.get(&num).copied();
+            if content_pattern.is_none() {
+                add_sample(&mut samples.size, path_bytes);
+            }
             reason_size = tracker.suppressed_marks_by_size.contains(&num);
             reason_sha = tracker.suppressed_marks_by_sha.contains(&num);
+            pointer = tracker.pointer_blobs_by_mark.get(&num).cloned();
+        } else if let Some(&size) = tracker.blob_sizes_by_mark.get(&num) {
+            let decoded = crate::pathutil::decode_fast_export_path_bytes(path_bytes);
+            if matches!(opts.blob_size_limit_for_path(&decoded), Some(max) if size > max) {
+                drop_path = true;
+                reason_size = true;
+                tracker.suppressed_marks_by_size.insert(num);
+                if opts.replace_blobs_with_pointer {
+                    pointer = Some(PointerBlobContent::new(
+                        size,
+                        tracker.blob_oids_by_mark.get(&num).map(Vec::as_slice),
+                    ));
+                }
+            }
         }
         if seen && tracker.modified_marks.contains(&num) {
-            add_sample(&mut samples.modified, path_bytes);
+            let oid = tracker.modified_blob_oids.get(&num);
+            add_modified_sample(&mut samples.modified, path_bytes, oid.map(Vec::as_slice));
+            if let Some(oid) = oid {
+                let decoded = crate::pathutil::decode_fast_export_path_bytes(path_bytes);
+                ctx.migration_log.log_blob_rewritten(Some(oid), &decoded)?;
+            }
         }
     } else if id.len() == 40 && id.iter().all(|b| b.is_ascii_hexdigit()) {
         let sha = id.to_vec();
@@ -764,7 +1426,13 @@ fn process_commit_m_line_precheck(
             reason_sha = true;
             tracker.suppressed_shas_by_sha.insert(sha.clone());
         }
-        if ctx.blob_size_tracker.is_oversize(&sha) {
+        let decoded = crate::pathutil::decode_fast_export_path_bytes(path_bytes);
+        let limit = opts.blob_size_limit_for_path(&decoded);
+        if ctx.blob_size_tracker.is_oversize_for(&sha, limit) {
+            if opts.replace_blobs_with_pointer && !reason_sha {
+                let size = ctx.blob_size_tracker.known_size(&sha).unwrap_or_default();
+                pointer = Some(PointerBlobContent::new(size, Some(&sha)));
+            }
             tracker.oversize_shas.insert(sha.clone());
             tracker.suppressed_shas_by_size.insert(sha);
             drop_path = true;
@@ -783,21 +1451,35 @@ fn process_commit_m_line_precheck(
     }
 
     let decoded = crate::pathutil::decode_fast_export_path_bytes(path_bytes);
-    let (enc, path_event) =
-        crate::pathutil::encode_path_for_fi_with_policy(&decoded, opts.path_compat_policy)
-            .map_err(io::Error::other)?;
+    let (enc, path_event) = crate::pathutil::encode_path_for_fi_with_policy(
+        &decoded,
+        opts.path_compat_policy,
+        opts.unsafe_path_policy,
+        ctx.sanitized_paths,
+    )
+    .map_err(io::Error::other)?;
     if let Some(event) = path_event {
-        record_path_compat_event(ctx.path_compat_stats, event);
+        record_path_compat_event(ctx.path_compat_stats, event, ctx.commit_original_oid);
     }
     if let Some(enc) = enc {
-        ctx.commit_buf.extend_from_slice(b"D ");
-        ctx.commit_buf.extend_from_slice(&enc);
-        ctx.commit_buf.push(b'\n');
+        match &pointer {
+            Some(pointer) => pointer.write_inline_modify(ctx.commit_buf, &enc),
+            None => {
+                ctx.commit_buf.extend_from_slice(b"D ");
+                ctx.commit_buf.extend_from_slice(&enc);
+                ctx.commit_buf.push(b'\n');
+            }
+        }
         *ctx.commit_has_changes = true;
+        *ctx.commit_has_content_changes = true;
+    }
+    if let Some(idx) = content_pattern {
+        add_content_sample(&mut samples.content, path_bytes, idx);
+        return Ok(true);
     }
     let (mut r_size, mut r_sha) = (reason_size, reason_sha);
     if !r_size && !r_sha {
-        if opts.max_blob_size.is_some() {
+        if opts.has_blob_size_limits() {
             r_size = true;
         } else {
             r_sha = true;
@@ -811,17 +1493,71 @@ fn process_commit_m_line_precheck(
     Ok(true)
 }
 
+/// Text that replaces a blob dropped by a size limit under
+/// `--replace-blobs-with-pointer`, so checkouts still find a file there.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PointerBlobContent {
+    pub(crate) original_size: u64,
+    pub(crate) original_oid: String,
+}
+
+impl PointerBlobContent {
+    fn new(original_size: usize, original_oid: Option<&[u8]>) -> Self {
+        Self {
+            original_size: original_size as u64,
+            original_oid: original_oid
+                .map(|oid| String::from_utf8_lossy(oid).into_owned())
+                .unwrap_or_else(|| "unknown".to_string()),
+        }
+    }
+
+    fn render(&self) -> String {
+        format!(
+            "This file was removed by filter-repo-rs.\nOriginal size: {} bytes\nOriginal OID: {}\n",
+            self.original_size, self.original_oid
+        )
+    }
+
+    /// Appends `M 100644 inline <path>` with the pointer text as its data.
+    fn write_inline_modify(&self, buf: &mut Vec<u8>, encoded_path: &[u8]) {
+        let text = self.render();
+        buf.extend_from_slice(b"M 100644 inline ");
+        buf.extend_from_slice(encoded_path);
+        buf.push(b'\n');
+        buf.extend_from_slice(format!("data {}\n", text.len()).as_bytes());
+        buf.extend_from_slice(text.as_bytes());
+    }
+}
+
+/// Index of the first `--strip-blobs-matching` pattern found in `payload`.
+/// Binary blobs and blobs over the scan cap are never matched.
+fn content_strip_match(opts: &Options, payload: &[u8]) -> Option<usize> {
+    if opts.strip_blobs_matching.is_empty()
+        || payload.len() > opts.strip_blobs_matching_max_bytes
+        || crate::detect::looks_binary_blob(payload)
+    {
+        return None;
+    }
+    opts.strip_blobs_matching
+        .iter()
+        .position(|re| re.is_match(payload))
+}
+
 struct BlobPayloadCtx<'a> {
     opts: &'a Options,
     filt_file: &'a mut BufWriter<File>,
-    fi_in_opt: &'a mut Option<BufWriter<std::process::ChildStdin>>,
+    fi_in_opt: &'a mut Option<ImportWriter>,
     content_replacer: &'a Option<MessageReplacer>,
     content_regex_replacer: &'a Option<BlobRegexReplacer>,
+    content_scoped_replacer: &'a Option<ScopedContentReplacer>,
+    eol_normalizer: &'a mut Option<EolNormalizer>,
+    blob_callback: &'a Option<BlobCallback>,
     in_blob: &'a mut bool,
     blob_buf: &'a mut Vec<Vec<u8>>,
     last_blob_mark: &'a mut Option<u32>,
     last_blob_orig_sha: &'a mut Option<Vec<u8>>,
     tracker: &'a mut FilterTracker,
+    blob_cache: &'a mut Option<BlobCache>,
     import_broken: &'a mut bool,
     strip_sha_lookup: &'a StripShaLookup,
 }
@@ -837,7 +1573,8 @@ fn process_blob_data_payload(
     let mut skip_blob = false;
     let mut reason_size = false;
     let mut reason_sha = false;
-    if let Some(max) = opts.max_blob_size {
+    let mut content_pattern = None;
+    if let Some(max) = opts.blob_size_limit_without_path() {
         if n > max {
             if let Some(m) = *ctx.last_blob_mark {
                 tracker.oversize_marks.insert(m);
@@ -859,13 +1596,25 @@ fn process_blob_data_payload(
             }
         }
     }
+    if !skip_blob {
+        content_pattern = content_strip_match(opts, &payload);
+        skip_blob = content_pattern.is_some();
+    }
     if skip_blob {
+        if reason_size && opts.replace_blobs_with_pointer {
+            if let Some(m) = *ctx.last_blob_mark {
+                let pointer = PointerBlobContent::new(n, ctx.last_blob_orig_sha.as_deref());
+                tracker.pointer_blobs_by_mark.insert(m, pointer);
+            }
+        }
         if let Some(m) = ctx.last_blob_mark.take() {
             tracker.oversize_marks.insert(m);
             if reason_size {
                 tracker.suppressed_marks_by_size.insert(m);
             } else if reason_sha {
                 tracker.suppressed_marks_by_sha.insert(m);
+            } else if let Some(idx) = content_pattern {
+                tracker.suppressed_marks_by_content.insert(m, idx);
             }
         }
         if let Some(sha) = ctx.last_blob_orig_sha.take() {
@@ -894,7 +1643,17 @@ fn process_blob_data_payload(
             }
         }
     }
-    if ctx.content_replacer.is_none() && ctx.content_regex_replacer.is_none() {
+    let cached_unmodified = match (ctx.blob_cache.as_mut(), ctx.last_blob_orig_sha.as_deref()) {
+        (Some(cache), Some(sha)) => cache.is_known_unmodified(sha),
+        _ => false,
+    };
+    if cached_unmodified
+        || (ctx.content_replacer.is_none()
+            && ctx.content_regex_replacer.is_none()
+            && ctx.content_scoped_replacer.is_none()
+            && ctx.eol_normalizer.is_none()
+            && ctx.blob_callback.is_none())
+    {
         let header = format!("data {}\n", n);
         ctx.filt_file.write_all(header.as_bytes())?;
         if let Some(ref mut fi_in) = ctx.fi_in_opt {
@@ -917,8 +1676,29 @@ fn process_blob_data_payload(
             }
         }
     } else {
+        let (payload, scoped_changed) = match ctx.content_scoped_replacer {
+            Some(scoped) => scoped.apply_to_blob(ctx.last_blob_orig_sha.as_deref(), payload),
+            None => (payload, false),
+        };
         let (new_payload, changed) =
             process_blob_content(payload, ctx.content_replacer, ctx.content_regex_replacer);
+        let (new_payload, eol_changed) = match ctx.eol_normalizer.as_mut() {
+            Some(eol) => eol.apply_to_blob(ctx.last_blob_orig_sha.as_deref(), new_payload),
+            None => (new_payload, false),
+        };
+        let (new_payload, callback_changed) = match ctx.blob_callback {
+            Some(callback) => callback.apply(new_payload).map_err(|e| {
+                let oid = ctx.last_blob_orig_sha.as_deref().unwrap_or_default();
+                io::Error::other(format!("{e} (blob {})", String::from_utf8_lossy(oid)))
+            })?,
+            None => (new_payload, false),
+        };
+        let changed = changed || scoped_changed || eol_changed || callback_changed;
+        if let (Some(cache), Some(sha)) =
+            (ctx.blob_cache.as_mut(), ctx.last_blob_orig_sha.as_deref())
+        {
+            cache.record(sha, changed);
+        }
         let header = format!("data {}\n", new_payload.len());
         ctx.filt_file.write_all(header.as_bytes())?;
         if let Some(ref mut fi_in) = ctx.fi_in_opt {
@@ -943,11 +1723,22 @@ fn process_blob_data_payload(
         if changed {
             if let Some(m) = *ctx.last_blob_mark {
                 tracker.modified_marks.insert(m);
+                if let Some(oid) = ctx.last_blob_orig_sha.as_ref() {
+                    tracker.modified_blob_oids.insert(m, oid.clone());
+                }
             }
         }
     }
     if let Some(m) = *ctx.last_blob_mark {
         tracker.emitted_marks.insert(m);
+        if !opts.max_blob_size_per_ext.is_empty() {
+            tracker.blob_sizes_by_mark.insert(m, n);
+            if opts.replace_blobs_with_pointer {
+                if let Some(oid) = ctx.last_blob_orig_sha.as_ref() {
+                    tracker.blob_oids_by_mark.insert(m, oid.clone());
+                }
+            }
+        }
     }
     *ctx.in_blob = false;
     *ctx.last_blob_mark = None;
@@ -957,8 +1748,10 @@ fn process_blob_data_payload(
 
 pub(crate) struct BlobSizeTracker {
     source: PathBuf,
-    max_blob_size: Option<usize>,
-    oversize: HashSet<Vec<u8>>,
+    // Smallest limit across the global and per-extension limits; blobs above
+    // it are remembered with their size.
+    threshold: Option<usize>,
+    oversize: HashMap<Vec<u8>, usize>,
     prefetch_ok: bool,
     batch: Option<BatchCat>,
 }
@@ -967,18 +1760,19 @@ impl BlobSizeTracker {
     pub(crate) fn new(opts: &Options) -> Self {
         let mut tracker = BlobSizeTracker {
             source: opts.source.clone(),
-            max_blob_size: opts.max_blob_size,
-            oversize: HashSet::new(),
+            threshold: opts.min_blob_size_limit(),
+            oversize: HashMap::new(),
             prefetch_ok: false,
             batch: None,
         };
-        if opts.max_blob_size.is_some() {
+        if tracker.threshold.is_some() {
             if let Err(e) = tracker.prefetch_oversize() {
                 tracker.oversize.clear();
                 if !opts.quiet {
-                    eprintln!(
-            "Warning: batch blob size pre-computation failed ({e}), falling back to on-demand sizing"
-          );
+                    diagnostics::warning(
+                        Phase::Stream,
+                        format!("batch blob size pre-computation failed ({e}), falling back to on-demand sizing"),
+                    );
                 }
             }
         }
@@ -1046,14 +1840,17 @@ impl BlobSizeTracker {
             .ok()
             .and_then(|s| s.trim().parse::<usize>().ok())
             .unwrap_or_else(|| {
-                eprintln!("WARNING: failed to parse blob size for {:?}", sha);
+                diagnostics::warning(
+                    Phase::Stream,
+                    format!("failed to parse blob size for {:?}", sha),
+                );
                 0
             });
         Ok(size)
     }
 
     fn prefetch_oversize(&mut self) -> io::Result<()> {
-        let max = match self.max_blob_size {
+        let max = match self.threshold {
             Some(m) => m,
             None => return Ok(()),
         };
@@ -1109,7 +1906,7 @@ impl BlobSizeTracker {
                 .and_then(|s| s.trim().parse::<usize>().ok())
                 .unwrap_or(0);
             if size > max {
-                self.oversize.insert(sha.to_vec());
+                self.oversize.insert(sha.to_vec(), size);
             }
         }
         let mut stderr_buf = Vec::new();
@@ -1127,29 +1924,39 @@ impl BlobSizeTracker {
         Ok(())
     }
 
+    #[cfg(test)]
     pub(crate) fn is_oversize(&mut self, sha: &[u8]) -> bool {
-        let max = match self.max_blob_size {
-            Some(m) => m,
-            None => return false,
+        let limit = self.threshold;
+        self.is_oversize_for(sha, limit)
+    }
+
+    /// Whether the blob `sha` exceeds `limit`, the limit that applies to the
+    /// path it is being stored at.
+    pub(crate) fn is_oversize_for(&mut self, sha: &[u8], limit: Option<usize>) -> bool {
+        let (Some(max), Some(threshold)) = (limit, self.threshold) else {
+            return false;
         };
-        if self.oversize.contains(sha) {
-            return true;
+        if let Some(&size) = self.oversize.get(sha) {
+            return size > max;
         }
         if self.prefetch_ok {
             return false;
         }
         let size = self.query_size_via_batch(sha).unwrap_or_default();
-        if size > max {
-            self.oversize.insert(sha.to_vec());
-            true
-        } else {
-            false
+        if size > threshold {
+            self.oversize.insert(sha.to_vec(), size);
         }
+        size > max
+    }
+
+    /// Size of a blob already found to be over the smallest limit.
+    fn known_size(&self, sha: &[u8]) -> Option<usize> {
+        self.oversize.get(sha).copied()
     }
 
     #[cfg(test)]
     pub(crate) fn known_oversize(&self, sha: &[u8]) -> bool {
-        self.oversize.contains(sha)
+        self.oversize.contains_key(sha)
     }
 
     #[cfg(test)]
@@ -1175,10 +1982,24 @@ impl Drop for BatchCat {
 struct StreamProcessor<'a> {
     opts: &'a Options,
     debug_dir: PathBuf,
+    migration_log: &'a MigrationLog,
+    // Source refs as captured once for this run
+    source_refs: &'a RefSnapshot,
+    // Target branches before fast-import touches them, for HEAD handling
+    branches_before: BTreeSet<String>,
+    head_before: crate::finalize::HeadState,
+    // fast-import temp packs that predate this run, kept if it is cancelled
+    tmp_packs_before: BTreeSet<PathBuf>,
+    // Marks as renumbered on the way to fast-import (--reproducible)
+    mark_table: Option<MarkTable>,
 }
 
 impl<'a> StreamProcessor<'a> {
-    fn new(opts: &'a Options) -> io::Result<Self> {
+    fn new(
+        opts: &'a Options,
+        migration_log: &'a MigrationLog,
+        source_refs: &'a RefSnapshot,
+    ) -> io::Result<Self> {
         let target_git_dir = git_dir(&opts.target).map_err(|e| {
             io::Error::other(format!("Target {:?} is not a git repo: {e}", opts.target))
         })?;
@@ -1186,15 +2007,74 @@ impl<'a> StreamProcessor<'a> {
             io::Error::other(format!("Source {:?} is not a git repo: {e}", opts.source))
         })?;
 
+        let tmp_packs_before = list_tmp_packs(&target_git_dir);
         let debug_dir = target_git_dir.join("filter-repo");
         if !debug_dir.exists() {
             create_dir_all(&debug_dir)?;
         }
 
-        Ok(Self { opts, debug_dir })
+        // Only a separate target repository needs a snapshot of its own.
+        let target_refs;
+        let refs_before = if opts.source == opts.target {
+            source_refs
+        } else {
+            target_refs = RefSnapshot::capture(&opts.target)?;
+            &target_refs
+        };
+        let branches_before = refs_before
+            .refs
+            .keys()
+            .filter(|name| name.starts_with("refs/heads/"))
+            .cloned()
+            .collect();
+
+        Ok(Self {
+            opts,
+            debug_dir,
+            migration_log,
+            source_refs,
+            branches_before,
+            head_before: crate::finalize::HeadState::read(&opts.target)?,
+            tmp_packs_before,
+            mark_table: opts.reproducible.then(MarkTable::default),
+        })
     }
 
-    fn init_stream_io(&self) -> io::Result<StreamIo> {
+    /// On cancellation, kills both git children and removes the partial pack
+    /// fast-import was writing. fast-import never received `done`, so no ref
+    /// has moved.
+    fn check_cancelled(
+        &self,
+        fe: &mut Option<Child>,
+        fi: &mut Option<Child>,
+        fi_in_opt: &mut Option<ImportWriter>,
+    ) -> io::Result<()> {
+        let Err(err) = crate::cancel::check(self.opts.cancel.as_ref()) else {
+            return Ok(());
+        };
+        if let Some(child) = fi.as_mut() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        // Kill the import first: closing its stdin would let it finish.
+        drop(fi_in_opt.take());
+        if let Some(fe) = fe.as_mut() {
+            let _ = fe.kill();
+            let _ = fe.wait();
+        }
+        if let Some(git_dir) = self.debug_dir.parent() {
+            for path in list_tmp_packs(git_dir) {
+                if !self.tmp_packs_before.contains(&path) {
+                    let _ = std::fs::remove_file(path);
+                }
+            }
+        }
+        Err(err)
+    }
+
+    /// `export` is this run's copy of a shared stream; without it the run
+    /// spawns its own fast-export.
+    fn init_stream_io(&self, export: Option<Box<dyn Read + Send>>) -> io::Result<StreamIo> {
         let opts = self.opts;
         let debug_dir = &self.debug_dir;
         let filt_file = BufWriter::new(File::create(debug_dir.join("fast-export.filtered"))?);
@@ -1207,10 +2087,22 @@ impl<'a> StreamProcessor<'a> {
             None
         };
 
-        let mut fe_cmd = crate::pipes::build_fast_export_cmd(opts)?;
-        let mut fe = fe_cmd
-            .spawn()
-            .map_err(|e| io::Error::other(format!("failed to spawn git fast-export: {e}")))?;
+        let (fe, fe_out) = match export {
+            Some(export) => (None, BufReader::new(export)),
+            None => {
+                let mut fe = crate::pipes::build_fast_export_cmd(opts, self.source_refs)?
+                    .spawn()
+                    .map_err(|e| {
+                        io::Error::other(format!("failed to spawn git fast-export: {e}"))
+                    })?;
+                let stdout: Box<dyn Read + Send> = Box::new(
+                    fe.stdout
+                        .take()
+                        .ok_or_else(|| io::Error::other("git fast-export produced no stdout"))?,
+                );
+                (Some(fe), BufReader::new(stdout))
+            }
+        };
         let mut fi = if opts.dry_run {
             None
         } else {
@@ -1223,17 +2115,21 @@ impl<'a> StreamProcessor<'a> {
             )
         };
 
-        let fe_out = BufReader::new(
-            fe.stdout
-                .take()
-                .ok_or_else(|| io::Error::other("git fast-export produced no stdout"))?,
-        );
-        let fi_in_opt: Option<BufWriter<std::process::ChildStdin>> = if let Some(ref mut child) = fi
-        {
-            child.stdin.take().map(BufWriter::new)
+        let fi_in_opt: Option<ImportWriter> = if let Some(ref mut child) = fi {
+            child.stdin.take().map(|stdin| {
+                let writer = match &self.mark_table {
+                    Some(table) => PipelineWriter::spawn(
+                        MarkRenumberer::new(stdin, table.clone()),
+                        opts.pipeline_buffer_bytes,
+                    ),
+                    None => PipelineWriter::spawn(stdin, opts.pipeline_buffer_bytes),
+                };
+                BufWriter::new(writer)
+            })
         } else {
             None
         };
+        let import_gauge = fi_in_opt.as_ref().map(|w| w.get_ref().gauge());
         let fi_out_opt: Option<BufReader<std::process::ChildStdout>> =
             if let Some(ref mut child) = fi {
                 child.stdout.take().map(BufReader::new)
@@ -1249,6 +2145,7 @@ impl<'a> StreamProcessor<'a> {
             fe_out,
             fi_in_opt,
             fi_out_opt,
+            import_gauge,
         })
     }
 
@@ -1256,31 +2153,68 @@ impl<'a> StreamProcessor<'a> {
         let opts = self.opts;
         let debug_dir = &self.debug_dir;
 
-        let replacer =
+        let rule_limits = RuleLimits::from_options(opts);
+        let message_rules =
             match &opts.replace_message_file {
-                Some(p) => Some(MessageReplacer::from_file(p).map_err(|e| {
+                Some(p) => Some(load_rule_file(p, &rule_limits, opts.quiet).map_err(|e| {
                     io::Error::other(format!("failed to read --replace-message: {e}"))
                 })?),
                 None => None,
             };
-        let msg_regex_replacer: Option<MsgRegexReplacer> = match &opts.replace_message_file {
-            Some(p) => MsgRegexReplacer::from_file(p)
+        let replacer = message_rules
+            .as_ref()
+            .map(MessageReplacer::from_message_rules);
+        let msg_regex_replacer: Option<MsgRegexReplacer> = match &message_rules {
+            Some(rules) => MsgRegexReplacer::from_rules(rules)
                 .map_err(|e| io::Error::other(format!("failed to read --replace-message: {e}")))?,
             None => None,
         };
+        let tag_message_rules = match &opts.replace_tag_message_file {
+            Some(p) => {
+                let rules = load_rule_file(p, &rule_limits, opts.quiet).map_err(|e| {
+                    io::Error::other(format!("failed to read --tag-message-file: {e}"))
+                })?;
+                let regex = MsgRegexReplacer::from_rules(&rules).map_err(|e| {
+                    io::Error::other(format!("failed to read --tag-message-file: {e}"))
+                })?;
+                Some((Some(MessageReplacer::from_message_rules(&rules)), regex))
+            }
+            None => None,
+        };
         let short_hash_mapper = ShortHashMapper::from_debug_dir(debug_dir)?;
-        let content_replacer = match &opts.replace_text_file {
+        let subject_normalizer = SubjectNormalizer::from_options(opts);
+        let tag_name_refs = if opts.update_tag_name_references {
+            TagNameReferences::new(crate::tag::tag_rename_pairs(opts, self.source_refs))?
+        } else {
+            None
+        };
+        let text_rules = match &opts.replace_text_file {
             Some(p) => Some(
-                MessageReplacer::from_file(p)
+                load_rule_file(p, &rule_limits, opts.quiet)
                     .map_err(|e| io::Error::other(format!("failed to read --replace-text: {e}")))?,
             ),
             None => None,
         };
-        let content_regex_replacer: Option<BlobRegexReplacer> = match &opts.replace_text_file {
-            Some(p) => BlobRegexReplacer::from_file(p)
+        let content_replacer = text_rules.as_ref().map(MessageReplacer::from_rules);
+        let content_regex_replacer: Option<BlobRegexReplacer> = match &text_rules {
+            Some(rules) => BlobRegexReplacer::from_rules(rules)
                 .map_err(|e| io::Error::other(format!("failed to read --replace-text: {e}")))?,
             None => None,
         };
+        let content_scoped_replacer =
+            match text_rules.as_ref().and_then(PathScopedReplacer::from_rules) {
+                Some(r) => Some(ScopedContentReplacer::load(&opts.source, r).map_err(|e| {
+                    io::Error::other(format!("failed to read --replace-text: {e}"))
+                })?),
+                None => None,
+            };
+        let eol_normalizer = match &opts.normalize_eol {
+            Some(config) => Some(EolNormalizer::load(&opts.source, config).map_err(|e| {
+                io::Error::other(format!("failed to resolve --normalize-eol paths: {e}"))
+            })?),
+            None => None,
+        };
+        let blob_callback = crate::blob_callback::load(opts.blob_callback_so.as_deref())?;
 
         let author_rewriter =
             match &opts.author_rewrite_file {
@@ -1310,16 +2244,49 @@ impl<'a> StreamProcessor<'a> {
             None => None,
         };
 
+        let now = if opts.reproducible {
+            crate::reproducible::timestamp().unix_timestamp()
+        } else {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0)
+        };
+        let committer_override = opts.override_committer.as_deref().map(|identity| {
+            IdentityOverride::new(
+                IdentityKind::Committer,
+                identity,
+                opts.override_committer_date,
+                now,
+            )
+        });
+        let tagger_override = opts.override_tagger.as_deref().map(|identity| {
+            IdentityOverride::new(
+                IdentityKind::Tagger,
+                identity,
+                opts.override_tagger_date,
+                now,
+            )
+        });
+
         Ok(Rewriters {
             replacer,
             msg_regex_replacer,
+            tag_message_rules,
             short_hash_mapper,
+            subject_normalizer,
+            tag_name_refs,
             content_replacer,
             content_regex_replacer,
+            content_scoped_replacer,
+            eol_normalizer,
+            blob_callback,
             author_rewriter,
             committer_rewriter,
             email_rewriter,
             mailmap_rewriter,
+            committer_override,
+            tagger_override,
         })
     }
 
@@ -1327,8 +2294,8 @@ impl<'a> StreamProcessor<'a> {
         &self,
         ctx: crate::finalize::FinalizeContext<'_>,
         filt_file: &mut BufWriter<File>,
-        fi_in_opt: &mut Option<BufWriter<std::process::ChildStdin>>,
-        fe: &mut std::process::Child,
+        fi_in_opt: &mut Option<ImportWriter>,
+        fe: &mut Option<std::process::Child>,
         fi: &mut Option<std::process::Child>,
         stream_args: FinalizeStreamArgs,
     ) -> FilterRepoResult<()> {
@@ -1338,6 +2305,20 @@ impl<'a> StreamProcessor<'a> {
             total_commits,
             total_blobs,
             path_compat_stats,
+            identity_rewrites,
+            identity_overrides,
+            mode_normalizations,
+            regex_rules,
+            blob_cache,
+            subject_normalization,
+            tag_name_references,
+            root_files,
+            pipeline,
+            ref_namespaces,
+            ref_stats,
+            eol_normalization,
+            dropped_tags,
+            pruned_tags,
         } = stream_args;
         let fi_writer_for_finalize: Option<Box<dyn Write>> =
             fi_in_opt.take().map(|bw| Box::new(bw) as Box<dyn Write>);
@@ -1345,11 +2326,19 @@ impl<'a> StreamProcessor<'a> {
 
         let report = {
             use crate::finalize::{
-                Metadata, ReportData, Samples, Statistics, Summary, WindowsPathReport,
-                WindowsPathSamples, WindowsPathSummary,
+                ContentMatchSample, Metadata, ModifiedBlobSample, PathCollisionGroup, ReportData,
+                Samples, Statistics, Summary, WindowsPathReport, WindowsPathSamples,
+                WindowsPathSummary,
             };
             Some(ReportData {
                 summary: Summary {
+                    export_mode: if self.opts.metadata_only {
+                        "metadata-only"
+                    } else if crate::pipes::uses_no_data(self.opts) {
+                        "no-data"
+                    } else {
+                        "full"
+                    },
                     blobs_stripped_by_size: tracker
                         .suppressed_shas_by_size
                         .len()
@@ -1358,6 +2347,8 @@ impl<'a> StreamProcessor<'a> {
                         .suppressed_shas_by_sha
                         .len()
                         .max(tracker.suppressed_marks_by_sha.len()),
+                    blobs_stripped_by_content: tracker.suppressed_marks_by_content.len()
+                        + samples.inline_content_stripped_paths.len(),
                     blobs_modified: tracker.modified_marks.len()
                         + samples.inline_modified_paths.len(),
                 },
@@ -1378,9 +2369,25 @@ impl<'a> StreamProcessor<'a> {
                         .map(|p| String::from_utf8_lossy(&p).into_owned())
                         .collect(),
                     modified: samples
+                        .modified
+                        .iter()
+                        .map(|(p, _)| String::from_utf8_lossy(p).into_owned())
+                        .collect(),
+                    modified_blobs: samples
                         .modified
                         .into_iter()
-                        .map(|p| String::from_utf8_lossy(&p).into_owned())
+                        .map(|(p, oid)| ModifiedBlobSample {
+                            path: String::from_utf8_lossy(&p).into_owned(),
+                            original_oid: oid.map(|o| String::from_utf8_lossy(&o).into_owned()),
+                        })
+                        .collect(),
+                    by_content: samples
+                        .content
+                        .into_iter()
+                        .map(|(p, idx)| ContentMatchSample {
+                            path: String::from_utf8_lossy(&p).into_owned(),
+                            pattern: self.opts.strip_blobs_matching[idx].as_str().to_string(),
+                        })
                         .collect(),
                 },
                 windows_path: if path_compat_stats.sanitized + path_compat_stats.skipped > 0 {
@@ -1389,7 +2396,23 @@ impl<'a> StreamProcessor<'a> {
                             policy: path_compat_stats.policy,
                             sanitized: path_compat_stats.sanitized,
                             skipped: path_compat_stats.skipped,
+                            collisions: path_compat_stats
+                                .collision_groups
+                                .values()
+                                .map(|originals| originals.len() - 1)
+                                .sum(),
                         },
+                        collisions: path_compat_stats
+                            .collision_groups
+                            .iter()
+                            .map(|(sanitized, originals)| PathCollisionGroup {
+                                sanitized: crate::pathutil::format_path_bytes_for_report(sanitized),
+                                originals: originals
+                                    .iter()
+                                    .map(|p| crate::pathutil::format_path_bytes_for_report(p))
+                                    .collect(),
+                            })
+                            .collect(),
                         samples: if path_compat_stats.sanitized_samples.is_empty()
                             && path_compat_stats.skipped_samples.is_empty()
                         {
@@ -1404,12 +2427,33 @@ impl<'a> StreamProcessor<'a> {
                 } else {
                     None
                 },
+                identity_rewrites,
+                identity_overrides,
+                mode_normalizations,
+                regex_rules,
+                blob_cache,
+                subject_normalization,
+                tag_name_references,
+                root_files,
+                pipeline,
+                ref_namespaces,
+                ref_stats: (!ref_stats.is_empty()).then_some(ref_stats),
+                eol_normalization,
+                dropped_tags: (!dropped_tags.is_empty()).then_some(dropped_tags),
+                pruned_tags: (!pruned_tags.is_empty()).then_some(pruned_tags),
+                post_check: None,
                 metadata: Metadata {
                     version: env!("CARGO_PKG_VERSION").to_string(),
-                    timestamp: std::time::SystemTime::now()
-                        .duration_since(std::time::UNIX_EPOCH)
-                        .map(|d| d.as_secs().to_string())
-                        .unwrap_or_default(),
+                    timestamp: if self.opts.reproducible {
+                        crate::reproducible::timestamp()
+                            .unix_timestamp()
+                            .to_string()
+                    } else {
+                        std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .map(|d| d.as_secs().to_string())
+                            .unwrap_or_default()
+                    },
                 },
             })
         };
@@ -1418,10 +2462,14 @@ impl<'a> StreamProcessor<'a> {
             ctx,
             filt_file as &mut dyn Write,
             fi_writer_for_finalize,
-            fe,
+            fe.as_mut(),
             fi.as_mut(),
             report,
         )?;
+        if self.opts.reproducible {
+            filt_file.flush()?;
+            crate::reproducible::renumber_file(&self.debug_dir.join("fast-export.filtered"))?;
+        }
 
         Ok(())
     }
@@ -1429,7 +2477,7 @@ impl<'a> StreamProcessor<'a> {
     fn record_emitted_commit_mark(
         tracker: &mut FilterTracker,
         short_hash_mapper: &mut Option<ShortHashMapper>,
-        fi_in_opt: &mut Option<BufWriter<std::process::ChildStdin>>,
+        fi_in_opt: &mut Option<ImportWriter>,
         fi_out_opt: &mut Option<BufReader<std::process::ChildStdout>>,
         commit_pairs: &[(Vec<u8>, Option<u32>)],
         commit_mark: Option<u32>,
@@ -1453,7 +2501,7 @@ impl<'a> StreamProcessor<'a> {
         Ok(())
     }
 
-    fn process(&self) -> FilterRepoResult<()> {
+    fn process(&self, export: Option<Box<dyn Read + Send>>) -> FilterRepoResult<()> {
         let opts = self.opts;
         let StreamIo {
             mut filt_file,
@@ -1463,17 +2511,26 @@ impl<'a> StreamProcessor<'a> {
             mut fe_out,
             mut fi_in_opt,
             mut fi_out_opt,
-        } = self.init_stream_io()?;
+            import_gauge,
+        } = self.init_stream_io(export)?;
         let Rewriters {
             replacer,
             msg_regex_replacer,
+            tag_message_rules,
             mut short_hash_mapper,
+            subject_normalizer,
+            tag_name_refs,
             content_replacer,
             content_regex_replacer,
+            content_scoped_replacer,
+            mut eol_normalizer,
+            blob_callback,
             author_rewriter,
             committer_rewriter,
             email_rewriter,
             mailmap_rewriter,
+            committer_override,
+            tagger_override,
         } = self.init_rewriters()?;
 
         let mut state = ParseState::Idle;
@@ -1483,6 +2540,8 @@ impl<'a> StreamProcessor<'a> {
         let mut commit_pairs: Vec<(Vec<u8>, Option<u32>)> = Vec::new();
         let mut parent_lines: Vec<crate::commit::ParentLine> = Vec::new();
         let mut alias_map: HashMap<u32, u32> = HashMap::new();
+        // Mark -> original id of every pruned commit, for --pruned-tag
+        let mut pruned_commits: HashMap<u32, Vec<u8>> = HashMap::new();
         let mut import_broken = false;
         let mut ref_renames: BTreeSet<(Vec<u8>, Vec<u8>)> = BTreeSet::new();
         // Track which refs we have updated (to avoid multiple updates of same ref via tag blocks)
@@ -1495,6 +2554,15 @@ impl<'a> StreamProcessor<'a> {
         let mut branch_reset_targets: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
         // Buffer lightweight tag resets (ref, from-line)
         let mut buffered_tag_resets: Vec<(Vec<u8>, Vec<u8>)> = Vec::new();
+        // Tags dropped because the blob (or inner tag) they point at was stripped
+        let mut dropped_tag_marks: HashSet<u32> = HashSet::new();
+        let mut dropped_tags: Vec<crate::finalize::DroppedTagReport> = Vec::new();
+        let mut pruned_tags: Vec<crate::finalize::PrunedTagReport> = Vec::new();
+        let mut ref_commit_counts: BTreeMap<Vec<u8>, RefCommitCounts> = BTreeMap::new();
+        let ref_namespaces = crate::pipes::ref_namespaces(opts, self.source_refs);
+        if let Some(namespaces) = ref_namespaces.as_ref() {
+            report_untouched_refs(opts, namespaces)?;
+        }
         let strip_sha_lookup = match &opts.strip_blobs_with_ids {
             Some(path) => StripShaLookup::from_path(path).map_err(|e| {
                 io::Error::other(format!("failed to load --strip-blobs-with-ids: {e}"))
@@ -1504,18 +2572,45 @@ impl<'a> StreamProcessor<'a> {
         let mut last_blob_orig_sha: Option<Vec<u8>> = None;
         let mut blob_size_tracker = BlobSizeTracker::new(opts);
         let mut tracker = FilterTracker::new();
+        let mut cache_scope = Vec::new();
+        if let Some(scoped) = &content_scoped_replacer {
+            scoped.scope_fingerprint(&mut cache_scope);
+        }
+        if let Some(eol) = &eol_normalizer {
+            eol.scope_fingerprint(&mut cache_scope);
+        }
+        let mut blob_cache = BlobCache::open(opts, &cache_scope)
+            .map_err(|e| io::Error::other(format!("failed to open --blob-cache: {e}")))?;
         let mut samples = ReportSamples::new();
         // Statistics counters
         let mut total_commits: usize = 0;
         let mut total_blobs: usize = 0;
+        let mut mode_rule_hits = vec![0usize; opts.mode_normalizations.len()];
+        let mut root_file_injector = RootFileInjector::from_options(opts)?;
+        let mut rename_diff_tree = crate::filechange::DiffTreeBatch::from_options(opts)?;
+        let mut progress = (!opts.quiet).then(|| {
+            let progress = Progress::stderr("Rewriting commits", None);
+            match import_gauge.clone() {
+                Some(gauge) => progress.with_detail(move || {
+                    format!(
+                        "import queue {:.2} MiB",
+                        gauge.queued() as f64 / (1024.0 * 1024.0)
+                    )
+                }),
+                None => progress,
+            }
+        });
         let mut path_compat_stats = PathCompatStats {
             policy: opts.path_compat_policy.as_str().to_string(),
             ..PathCompatStats::default()
         };
+        let mut sanitized_paths = crate::pathutil::SanitizedPathTable::default();
         let mut line = Vec::with_capacity(8192);
         let mut replay_line: Option<Vec<u8>> = None;
         // Track if the previous M-line used inline content; store commit_buf position and path bytes
         let mut pending_inline: Option<(usize, Vec<u8>)> = None;
+        // Lines the dispatcher has read; data payloads are not counted.
+        let mut stream_line: u64 = 0;
 
         loop {
             let replaying = replay_line.is_some();
@@ -1531,6 +2626,10 @@ impl<'a> StreamProcessor<'a> {
             };
 
             if !replaying {
+                stream_line += 1;
+                if current_line.starts_with(b"commit ") || current_line == b"done\n" {
+                    self.check_cancelled(&mut fe, &mut fi, &mut fi_in_opt)?;
+                }
                 if let Some(ref mut f) = orig_file_opt {
                     f.write_all(&current_line)?;
                 }
@@ -1567,7 +2666,23 @@ impl<'a> StreamProcessor<'a> {
                                 let mut from_line = b"from ".to_vec();
                                 from_line.extend_from_slice(&target);
                                 from_line.push(b'\n');
-                                buffered_tag_resets.push((ref_name, from_line));
+                                if target == crate::tag::NULL_OID {
+                                    // Fast-export clears a tag ref before re-tagging it as
+                                    // the outer tag of a nested chain. Fast-import only
+                                    // drops the inner tag when the reset arrives in order,
+                                    // and the outer tag block must not be deduplicated.
+                                    updated_refs.remove(&ref_name);
+                                    annotated_tag_refs.remove(&ref_name);
+                                    crate::finalize::flush_lightweight_tag_resets(
+                                        &mut vec![(ref_name, from_line)],
+                                        &BTreeSet::new(),
+                                        &mut filt_file,
+                                        fi_in_opt.as_mut().map(|w| w as &mut dyn Write),
+                                        &mut import_broken,
+                                    )?;
+                                } else {
+                                    buffered_tag_resets.push((ref_name, from_line));
+                                }
                             }
                             ResetStateKind::Branch => {
                                 if !target.is_empty() {
@@ -1605,14 +2720,51 @@ impl<'a> StreamProcessor<'a> {
                                 } else {
                                     None
                                 },
-                                replacer: &replacer,
-                                msg_regex: msg_regex_replacer.as_ref(),
+                                replacer: match &tag_message_rules {
+                                    Some((tag_replacer, _)) => tag_replacer,
+                                    None => &replacer,
+                                },
+                                msg_regex: match &tag_message_rules {
+                                    Some((_, tag_regex)) => tag_regex.as_ref(),
+                                    None => msg_regex_replacer.as_ref(),
+                                },
                                 short_mapper,
+                                subject_normalizer: subject_normalizer
+                                    .as_ref()
+                                    .filter(|_| opts.also_tags),
+                                tag_name_refs: tag_name_refs.as_ref(),
+                                author_rewriter: author_rewriter.as_ref(),
+                                email_rewriter: email_rewriter.as_ref(),
+                                mailmap_rewriter: mailmap_rewriter.as_ref(),
+                                tagger_override: tagger_override.as_ref(),
                                 opts,
                                 updated_refs: &mut updated_refs,
                                 annotated_tag_refs: &mut annotated_tag_refs,
                                 ref_renames: &mut ref_renames,
                                 emitted_marks: &mut tracker.emitted_marks,
+                                target_stripped: &mut |target| match target {
+                                    crate::tag::TagTarget::Mark(m) => {
+                                        Ok(tracker.oversize_marks.contains(m))
+                                    }
+                                    crate::tag::TagTarget::Oid(oid) => {
+                                        Ok(tracker.oversize_shas.contains(oid)
+                                            || strip_sha_lookup.contains_hex(oid)?
+                                            || blob_size_tracker.is_oversize_for(
+                                                oid,
+                                                opts.blob_size_limit_without_path(),
+                                            ))
+                                    }
+                                },
+                                dropped_tag_marks: &mut dropped_tag_marks,
+                                dropped_tags: &mut dropped_tags,
+                                buffered_tag_resets: &mut buffered_tag_resets,
+                                pruned_commits: crate::tag::PrunedCommitView {
+                                    policy: opts.pruned_tag,
+                                    pruned: &pruned_commits,
+                                    alias_map: &alias_map,
+                                    commit_pairs: &commit_pairs,
+                                },
+                                pruned_tags: &mut pruned_tags,
                             },
                         )?;
                         ParseState::Idle
@@ -1622,6 +2774,9 @@ impl<'a> StreamProcessor<'a> {
                         parent_count = 0;
                         parent_lines.clear();
                         total_commits += 1;
+                        if let Some(ref mut p) = progress {
+                            p.update(total_commits)?;
+                        }
                         let hdr = crate::commit::rename_commit_header_ref(
                             &current_line,
                             opts,
@@ -1665,6 +2820,18 @@ impl<'a> StreamProcessor<'a> {
                         }
                         ParseState::Idle
                     } else if current_line == b"done\n" {
+                        crate::tag::PrunedCommitView {
+                            policy: opts.pruned_tag,
+                            pruned: &pruned_commits,
+                            alias_map: &alias_map,
+                            commit_pairs: &commit_pairs,
+                        }
+                        .apply_to_resets(
+                            &mut buffered_tag_resets,
+                            &annotated_tag_refs,
+                            opts.quiet,
+                            &mut pruned_tags,
+                        );
                         crate::finalize::flush_lightweight_tag_resets(
                             &mut buffered_tag_resets,
                             &annotated_tag_refs,
@@ -1754,11 +2921,66 @@ impl<'a> StreamProcessor<'a> {
                             }
                             ParseState::Idle
                         }
+                    } else if current_line == b"\n" {
+                        ParseState::Idle
                     } else {
-                        if current_line != b"\n" {
-                            filt_file.write_all(&current_line)?;
+                        let mut out = Vec::new();
+                        match TopLevelCommand::classify(&current_line) {
+                            TopLevelCommand::PassThrough => out = current_line.clone(),
+                            TopLevelCommand::Strip => {}
+                            TopLevelCommand::Alias => {
+                                let (alias_mark, target) =
+                                    read_alias_stanza(&mut fe_out, orig_file_opt.as_mut())?;
+                                stream_line += 2;
+                                match target
+                                    .strip_prefix(b":")
+                                    .and_then(|m| std::str::from_utf8(m).ok()?.parse::<u32>().ok())
+                                {
+                                    Some(target_mark) => {
+                                        let canonical = crate::commit::resolve_canonical_mark(
+                                            target_mark,
+                                            &alias_map,
+                                        );
+                                        if tracker.emitted_marks.contains(&canonical) {
+                                            alias_map.insert(alias_mark, canonical);
+                                            out = crate::commit::build_alias(alias_mark, canonical);
+                                        } else if tracker.oversize_marks.contains(&target_mark) {
+                                            tracker.oversize_marks.insert(alias_mark);
+                                        } else if !opts.quiet {
+                                            crate::diagnostics::warning(
+                                                crate::diagnostics::Phase::Stream,
+                                                format!(
+                                                    "dropping alias :{alias_mark} at stream line {stream_line} (not counting data payloads): :{target_mark} was not written"
+                                                ),
+                                            );
+                                        }
+                                    }
+                                    None => {
+                                        out = [
+                                            b"alias\nmark :".as_ref(),
+                                            alias_mark.to_string().as_bytes(),
+                                            b"\nto ",
+                                            &target,
+                                            b"\n\n",
+                                        ]
+                                        .concat();
+                                    }
+                                }
+                            }
+                            TopLevelCommand::Unknown => {
+                                crate::diagnostics::warning(
+                                    crate::diagnostics::Phase::Stream,
+                                    format!(
+                                        "ignoring unknown fast-import command at stream line {stream_line} (not counting data payloads): {}",
+                                        String::from_utf8_lossy(&current_line).trim_end()
+                                    ),
+                                );
+                            }
+                        }
+                        if !out.is_empty() {
+                            filt_file.write_all(&out)?;
                             if let Some(ref mut fi_in) = fi_in_opt {
-                                if let Err(e) = fi_in.write_all(&current_line) {
+                                if let Err(e) = fi_in.write_all(&out) {
                                     if e.kind() == io::ErrorKind::BrokenPipe {
                                         import_broken = true;
                                         break;
@@ -1815,11 +3037,15 @@ impl<'a> StreamProcessor<'a> {
                                 fi_in_opt: &mut fi_in_opt,
                                 content_replacer: &content_replacer,
                                 content_regex_replacer: &content_regex_replacer,
+                                content_scoped_replacer: &content_scoped_replacer,
+                                eol_normalizer: &mut eol_normalizer,
+                                blob_callback: &blob_callback,
                                 in_blob: &mut in_blob,
                                 blob_buf: &mut blob_buf,
                                 last_blob_mark: &mut last_blob_mark,
                                 last_blob_orig_sha: &mut last_blob_orig_sha,
                                 tracker: &mut tracker,
+                                blob_cache: &mut blob_cache,
                                 import_broken: &mut import_broken,
                                 strip_sha_lookup: &strip_sha_lookup,
                             };
@@ -1842,6 +3068,7 @@ impl<'a> StreamProcessor<'a> {
                     mut mark,
                     mut header_buf,
                     mut has_file_changes,
+                    mut has_content_changes,
                     commit_ref,
                 } => {
                     if should_end_commit {
@@ -1861,8 +3088,11 @@ impl<'a> StreamProcessor<'a> {
                             &replacer,
                             msg_regex_replacer.as_ref(),
                             short_mapper,
+                            subject_normalizer.as_ref(),
+                            tag_name_refs.as_ref(),
                             &mut header_buf,
                             &mut has_file_changes,
+                            &mut has_content_changes,
                             &mut mark,
                             &mut first_parent_mark,
                             &mut commit_original_oid,
@@ -1871,13 +3101,26 @@ impl<'a> StreamProcessor<'a> {
                             &mut import_broken,
                             &mut parent_lines,
                             &mut alias_map,
+                            &mut pruned_commits,
                             &tracker.emitted_marks,
                             &mut path_events,
+                            &mut sanitized_paths,
+                            &mut mode_rule_hits,
+                            root_file_injector.as_mut(),
+                            rename_diff_tree.as_mut(),
                         )?;
                         for event in path_events {
-                            record_path_compat_event(&mut path_compat_stats, event);
+                            record_path_compat_event(
+                                &mut path_compat_stats,
+                                event,
+                                commit_original_oid.as_deref(),
+                            );
                         }
-                        if matches!(action, crate::commit::CommitAction::Ended) {
+                        if let crate::commit::CommitAction::Ended(outcome) = action {
+                            if let Some(committer_override) = committer_override.as_ref() {
+                                committer_override.settle(outcome.kept);
+                            }
+                            record_commit_outcome(&mut ref_commit_counts, &commit_ref, outcome);
                             Self::record_emitted_commit_mark(
                                 &mut tracker,
                                 &mut short_hash_mapper,
@@ -1898,11 +3141,18 @@ impl<'a> StreamProcessor<'a> {
                         orig_file_opt: &mut orig_file_opt,
                         commit_buf: &mut header_buf,
                         commit_has_changes: &mut has_file_changes,
+                        commit_has_content_changes: &mut has_content_changes,
                         pending_inline: &mut pending_inline,
                         samples: &mut samples,
                         path_compat_stats: &mut path_compat_stats,
+                        sanitized_paths: &mut sanitized_paths,
+                        commit_original_oid: commit_original_oid.as_deref(),
                         content_replacer: &content_replacer,
                         content_regex_replacer: &content_regex_replacer,
+                        content_scoped_replacer: &content_scoped_replacer,
+                        eol_normalizer: &mut eol_normalizer,
+                        blob_callback: &blob_callback,
+                        migration_log: self.migration_log,
                     };
                     let handled_inline_or_m =
                         process_pending_inline_data_line(&current_line, &mut pending_inline_ctx)?
@@ -1911,12 +3161,16 @@ impl<'a> StreamProcessor<'a> {
                                     opts,
                                     commit_buf: &mut header_buf,
                                     commit_has_changes: &mut has_file_changes,
+                                    commit_has_content_changes: &mut has_content_changes,
                                     pending_inline: &mut pending_inline,
                                     tracker: &mut tracker,
                                     samples: &mut samples,
                                     path_compat_stats: &mut path_compat_stats,
+                                    sanitized_paths: &mut sanitized_paths,
+                                    commit_original_oid: commit_original_oid.as_deref(),
                                     strip_sha_lookup: &strip_sha_lookup,
                                     blob_size_tracker: &mut blob_size_tracker,
+                                    migration_log: self.migration_log,
                                 };
                                 process_commit_m_line_precheck(&current_line, &mut ctx)?
                             });
@@ -1925,6 +3179,7 @@ impl<'a> StreamProcessor<'a> {
                             mark,
                             header_buf,
                             has_file_changes,
+                            has_content_changes,
                             commit_ref,
                         }
                     } else {
@@ -1935,6 +3190,7 @@ impl<'a> StreamProcessor<'a> {
                             committer_rewriter.as_ref(),
                             email_rewriter.as_ref(),
                             mailmap_rewriter.as_ref(),
+                            committer_override.as_ref(),
                         );
                         let short_mapper = short_hash_mapper.as_ref();
                         let mut path_events = Vec::new();
@@ -1952,8 +3208,11 @@ impl<'a> StreamProcessor<'a> {
                             &replacer,
                             msg_regex_replacer.as_ref(),
                             short_mapper,
+                            subject_normalizer.as_ref(),
+                            tag_name_refs.as_ref(),
                             &mut header_buf,
                             &mut has_file_changes,
+                            &mut has_content_changes,
                             &mut mark,
                             &mut first_parent_mark,
                             &mut commit_original_oid,
@@ -1962,23 +3221,41 @@ impl<'a> StreamProcessor<'a> {
                             &mut import_broken,
                             &mut parent_lines,
                             &mut alias_map,
+                            &mut pruned_commits,
                             &tracker.emitted_marks,
                             &mut path_events,
+                            &mut sanitized_paths,
+                            &mut mode_rule_hits,
+                            root_file_injector.as_mut(),
+                            rename_diff_tree.as_mut(),
                         )? {
                             crate::commit::CommitAction::Consumed => {
                                 for event in path_events {
-                                    record_path_compat_event(&mut path_compat_stats, event);
+                                    record_path_compat_event(
+                                        &mut path_compat_stats,
+                                        event,
+                                        commit_original_oid.as_deref(),
+                                    );
                                 }
                                 ParseState::InCommit {
                                     mark,
                                     header_buf,
                                     has_file_changes,
+                                    has_content_changes,
                                     commit_ref,
                                 }
                             }
-                            crate::commit::CommitAction::Ended => {
+                            crate::commit::CommitAction::Ended(outcome) => {
+                                if let Some(committer_override) = committer_override.as_ref() {
+                                    committer_override.settle(outcome.kept);
+                                }
+                                record_commit_outcome(&mut ref_commit_counts, &commit_ref, outcome);
                                 for event in path_events {
-                                    record_path_compat_event(&mut path_compat_stats, event);
+                                    record_path_compat_event(
+                                        &mut path_compat_stats,
+                                        event,
+                                        commit_original_oid.as_deref(),
+                                    );
                                 }
                                 Self::record_emitted_commit_mark(
                                     &mut tracker,
@@ -1997,11 +3274,32 @@ impl<'a> StreamProcessor<'a> {
             };
         }
 
+        self.check_cancelled(&mut fe, &mut fi, &mut fi_in_opt)?;
         drop(fi_out_opt);
         if let Some(ref mut of) = orig_file_opt {
             of.flush()?;
         }
+        if let Some(ref mut cache) = blob_cache {
+            cache.persist()?;
+        }
+        if let Some(diff_tree) = rename_diff_tree {
+            diff_tree.finish()?;
+        }
+        if let Some(ref mut p) = progress {
+            p.finish(total_commits)?;
+        }
+        // Hand the last buffered bytes to the import queue so the reported
+        // peak covers the whole stream.
+        if let Some(fi_in) = fi_in_opt.as_mut() {
+            match fi_in.flush() {
+                Err(e) if e.kind() == io::ErrorKind::BrokenPipe => import_broken = true,
+                other => other?,
+            }
+        }
         let allow_flush_tag_resets = !buffered_tag_resets.is_empty();
+        let path_events = write_path_events(&self.debug_dir, &path_compat_stats.events)?
+            .map(|path| (path_compat_stats.events.len(), path));
+        let ref_stats = build_ref_stats_report(ref_commit_counts, &ref_renames);
         let ctx = crate::finalize::FinalizeContext {
             opts,
             debug_dir: &self.debug_dir,
@@ -2013,6 +3311,12 @@ impl<'a> StreamProcessor<'a> {
             branch_reset_targets,
             import_broken,
             allow_flush_tag_resets,
+            modified_blob_oids: std::mem::take(&mut tracker.modified_blob_oids),
+            branches_before: self.branches_before.clone(),
+            head_before: self.head_before.clone(),
+            migration_log: self.migration_log,
+            renumbered_marks: self.mark_table.clone(),
+            path_events,
         };
         let stream_args = FinalizeStreamArgs {
             tracker,
@@ -2020,6 +3324,65 @@ impl<'a> StreamProcessor<'a> {
             total_commits,
             total_blobs,
             path_compat_stats,
+            identity_rewrites: build_identity_rewrite_report(
+                author_rewriter.as_ref(),
+                committer_rewriter.as_ref(),
+                email_rewriter.as_ref(),
+                mailmap_rewriter.as_ref(),
+            ),
+            identity_overrides: build_identity_override_report(
+                opts,
+                committer_override.as_ref(),
+                tagger_override.as_ref(),
+            ),
+            mode_normalizations: build_mode_normalization_report(opts, &mode_rule_hits),
+            regex_rules: build_regex_rule_report(&[
+                (
+                    "replace-text",
+                    content_regex_replacer
+                        .as_ref()
+                        .map(|r| r.rule_match_counts()),
+                ),
+                (
+                    "replace-message",
+                    msg_regex_replacer.as_ref().map(|r| r.rule_match_counts()),
+                ),
+                (
+                    "tag-message-file",
+                    tag_message_rules
+                        .as_ref()
+                        .and_then(|(_, regex)| regex.as_ref())
+                        .map(|r| r.rule_match_counts()),
+                ),
+            ]),
+            blob_cache: blob_cache.as_ref().map(BlobCache::report),
+            subject_normalization: subject_normalizer.as_ref().map(|n| {
+                crate::finalize::SubjectNormalizationReport {
+                    prefix_stripped: n.prefix_stripped(),
+                    pr_suffix_stripped: n.pr_suffix_stripped(),
+                }
+            }),
+            tag_name_references: tag_name_refs.as_ref().map(|refs| {
+                crate::finalize::TagNameReferencesReport {
+                    messages_updated: refs.messages_updated(),
+                }
+            }),
+            root_files: root_file_injector
+                .as_ref()
+                .map(|i| crate::finalize::RootFilesReport {
+                    roots_modified: i.roots_modified(),
+                }),
+            pipeline: import_gauge
+                .as_ref()
+                .map(|gauge| crate::finalize::PipelineReport {
+                    buffer_bytes: opts.pipeline_buffer_bytes,
+                    peak_queued_bytes: gauge.peak(),
+                }),
+            ref_namespaces,
+            ref_stats,
+            eol_normalization: eol_normalizer.as_ref().map(EolNormalizer::report),
+            dropped_tags,
+            pruned_tags,
         };
         self.finalize_stream(
             ctx,
@@ -2031,7 +3394,9 @@ impl<'a> StreamProcessor<'a> {
         )?;
 
         // Wait for child processes to finish
-        let _ = fe.wait()?;
+        if let Some(mut fe) = fe {
+            let _ = fe.wait()?;
+        }
         if let Some(mut child) = fi {
             let _ = child.wait()?;
         }
@@ -2040,8 +3405,37 @@ impl<'a> StreamProcessor<'a> {
     }
 }
 
-pub fn run(opts: &Options) -> FilterRepoResult<()> {
-    StreamProcessor::new(opts)?.process()
+fn list_tmp_packs(git_dir: &Path) -> BTreeSet<PathBuf> {
+    std::fs::read_dir(git_dir.join("objects").join("pack"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("tmp_"))
+        })
+        .collect()
+}
+
+pub fn run(
+    opts: &Options,
+    migration_log: &MigrationLog,
+    source_refs: &RefSnapshot,
+) -> FilterRepoResult<()> {
+    StreamProcessor::new(opts, migration_log, source_refs)?.process(None)
+}
+
+/// [`run`] over `export`, one copy of a stream shared with other runs,
+/// instead of a fast-export of its own.
+pub(crate) fn run_on_export(
+    opts: &Options,
+    migration_log: &MigrationLog,
+    source_refs: &RefSnapshot,
+    export: Box<dyn Read + Send>,
+) -> FilterRepoResult<()> {
+    StreamProcessor::new(opts, migration_log, source_refs)?.process(Some(export))
 }
 
 fn resolve_mark_oid(
@@ -2104,12 +3498,61 @@ fn resolve_mark_oid(
     }
 }
 
+/// Reports the refs `--all` leaves out. They still point at the original
+/// history, so whatever this run strips stays reachable through them and
+/// survives gc; `--sensitive` must not leave any behind.
+fn report_untouched_refs(
+    opts: &Options,
+    namespaces: &crate::finalize::RefNamespacesReport,
+) -> io::Result<()> {
+    if namespaces.excluded.is_empty() {
+        return Ok(());
+    }
+    let skipped: Vec<String> = namespaces
+        .excluded
+        .iter()
+        .map(|ns| match ns.flag {
+            Some(flag) => format!("{} ({} ref(s), {})", ns.namespace, ns.refs, flag),
+            None => format!("{} ({} ref(s))", ns.namespace, ns.refs),
+        })
+        .collect();
+    let message = format!(
+        "--all does not rewrite these refs: {}; they keep the original objects \
+         reachable, so anything stripped from them survives gc",
+        skipped.join(", ")
+    );
+    if opts.sensitive {
+        return Err(io::Error::other(format!(
+            "{message}; --sensitive requires every ref to be rewritten"
+        )));
+    }
+    diagnostics::warning(Phase::Stream, message);
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::path::PathBuf;
     use tempfile::TempDir;
 
+    #[test]
+    fn blob_replacement_applies_literal_rules_before_regex_rules() {
+        // The regex only matches what the literal rule writes, and the
+        // second literal rule only matches what the first one writes.
+        let rules = RuleFile::parse(
+            &b"regex:token-(\\d+)==>[T$1]\npassword==>token\ntoken==>token-42\n"[..],
+            &RuleLimits::default(),
+        )
+        .unwrap();
+        let literal = Some(MessageReplacer::from_rules(&rules));
+        let regex = BlobRegexReplacer::from_rules(&rules).unwrap();
+
+        let (out, changed) = process_blob_content(b"password=1\n".to_vec(), &literal, &regex);
+        assert!(changed);
+        assert_eq!(out, b"[T42]=1\n");
+    }
+
     fn create_test_opts(source: &str) -> Options {
         let mut opts = Options::default();
         opts.source = PathBuf::from(source);
@@ -2337,6 +3780,7 @@ mod tests {
                 mark: None,
                 header_buf: b"commit refs/heads/main\n".to_vec(),
                 has_file_changes: false,
+                has_content_changes: false,
                 commit_ref: b"refs/heads/main".to_vec(),
             }
         );
@@ -2348,6 +3792,7 @@ mod tests {
             mark: Some(7),
             header_buf: b"commit refs/heads/main\n".to_vec(),
             has_file_changes: true,
+            has_content_changes: true,
             commit_ref: b"refs/heads/main".to_vec(),
         };
 
@@ -2397,4 +3842,31 @@ mod tests {
             other => panic!("expected Replay, got {other:?}"),
         }
     }
+
+    #[test]
+    fn untouched_refs_warn_and_fail_under_sensitive() {
+        let namespaces = crate::finalize::RefNamespacesReport {
+            included: vec!["refs/heads/".to_string()],
+            excluded: vec![crate::finalize::ExcludedRefNamespace {
+                namespace: "refs/pull/".to_string(),
+                refs: 2,
+                flag: None,
+            }],
+        };
+        let mut opts = create_test_opts(".");
+        let (result, records) =
+            crate::diagnostics::capture(|| report_untouched_refs(&opts, &namespaces));
+        result.unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].level, crate::diagnostics::Level::Warning);
+        assert!(records[0].message.contains("refs/pull/ (2 ref(s))"));
+        assert!(records[0].message.contains("survives gc"));
+
+        opts.sensitive = true;
+        let (result, records) =
+            crate::diagnostics::capture(|| report_untouched_refs(&opts, &namespaces));
+        let err = result.unwrap_err().to_string();
+        assert!(err.contains("--sensitive requires every ref"), "{err}");
+        assert!(records.is_empty());
+    }
 }
@@ -1,24 +1,225 @@
-use std::collections::BTreeSet;
-use std::io::{self, BufRead, BufReader, Read, Write};
-use std::process::ChildStdout;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::{self, BufRead, Read, Write};
 
-use crate::limits::parse_data_size_header;
-use crate::message::{msg_regex, MessageReplacer, ShortHashMapper};
-use crate::opts::Options;
+use crate::commit::{rewrite_tagger_line, AuthorRewriter, IdentityOverride, MailmapRewriter};
+use crate::diagnostics::{self, Phase};
+use crate::finalize::{DroppedTagReport, PrunedTagReport};
+use crate::limits::{parse_data_size_header, MAX_DATA_SIZE};
+use crate::message::{
+    msg_regex, MessageReplacer, ShortHashMapper, SubjectNormalizer, TagNameReferences,
+};
+use crate::opts::{Options, PrunedTagPolicy};
+use crate::pipes::ExportReader;
+use crate::refs::RefSnapshot;
 
 pub struct TagProcessContext<'a> {
-    pub fe_out: &'a mut BufReader<ChildStdout>,
+    pub fe_out: &'a mut ExportReader,
     pub orig_file: Option<&'a mut dyn Write>,
     pub filt_file: &'a mut dyn Write,
     pub fi_in: Option<&'a mut dyn Write>,
     pub replacer: &'a Option<MessageReplacer>,
     pub msg_regex: Option<&'a msg_regex::RegexReplacer>,
     pub short_mapper: Option<&'a ShortHashMapper>,
+    /// Only set when `--also-tags` extends subject normalization to tags.
+    pub subject_normalizer: Option<&'a SubjectNormalizer>,
+    pub tag_name_refs: Option<&'a TagNameReferences>,
+    /// Identity rules for the `tagger` line; `--committer-rewrite` does not
+    /// apply to tags.
+    pub author_rewriter: Option<&'a AuthorRewriter>,
+    pub email_rewriter: Option<&'a AuthorRewriter>,
+    pub mailmap_rewriter: Option<&'a MailmapRewriter>,
+    pub tagger_override: Option<&'a IdentityOverride>,
     pub opts: &'a Options,
     pub updated_refs: &'a mut BTreeSet<Vec<u8>>,
     pub annotated_tag_refs: &'a mut BTreeSet<Vec<u8>>,
     pub ref_renames: &'a mut BTreeSet<(Vec<u8>, Vec<u8>)>,
     pub emitted_marks: &'a mut std::collections::HashSet<u32>,
+    /// Whether a blob the tag points at was stripped from history.
+    pub target_stripped: &'a mut dyn FnMut(&TagTarget) -> io::Result<bool>,
+    /// Marks of tag blocks dropped so far; tags of those tags follow them.
+    pub dropped_tag_marks: &'a mut HashSet<u32>,
+    pub dropped_tags: &'a mut Vec<DroppedTagReport>,
+    /// Lightweight tag resets flushed at the end of the stream; a dropped
+    /// tag queues a reset to the null id so fast-import deletes its ref.
+    pub buffered_tag_resets: &'a mut Vec<(Vec<u8>, Vec<u8>)>,
+    pub pruned_commits: PrunedCommitView<'a>,
+    pub pruned_tags: &'a mut Vec<PrunedTagReport>,
+}
+
+/// What `--pruned-tag` does with a tag whose commit was pruned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrunedTagAction {
+    Delete,
+    /// Point the tag at this surviving commit mark instead.
+    Retarget(u32),
+    /// Point the tag at the original, unrewritten commit id.
+    KeepOld(Vec<u8>),
+}
+
+impl PrunedTagAction {
+    fn reset_from_line(&self) -> Vec<u8> {
+        match self {
+            Self::Delete => [b"from ", NULL_OID, b"\n"].concat(),
+            Self::Retarget(mark) => format!("from :{mark}\n").into_bytes(),
+            Self::KeepOld(oid) => [b"from ", oid.as_slice(), b"\n"].concat(),
+        }
+    }
+}
+
+/// Which commits were pruned by the time tags are written.
+///
+/// Fast-export sends annotated tags and lightweight tag resets after every
+/// commit, so the pruned set and alias chains are final when they arrive.
+pub struct PrunedCommitView<'a> {
+    pub policy: PrunedTagPolicy,
+    /// Mark -> original id of each pruned commit (empty when unknown).
+    pub pruned: &'a HashMap<u32, Vec<u8>>,
+    /// Pruned commit mark -> first surviving ancestor mark.
+    pub alias_map: &'a HashMap<u32, u32>,
+    pub commit_pairs: &'a [(Vec<u8>, Option<u32>)],
+}
+
+impl PrunedCommitView<'_> {
+    /// The `--pruned-tag` action for tag `tag` pointing at `mark`, or `None`
+    /// when that commit survived. Records the decision in `reports`.
+    pub fn resolve(
+        &self,
+        tag: &[u8],
+        mark: u32,
+        quiet: bool,
+        reports: &mut Vec<PrunedTagReport>,
+    ) -> Option<PrunedTagAction> {
+        let original = self.pruned.get(&mark)?;
+        let tag = String::from_utf8_lossy(tag).into_owned();
+        let commit = if original.is_empty() {
+            format!(":{mark}")
+        } else {
+            String::from_utf8_lossy(original).into_owned()
+        };
+        let ancestor = self
+            .alias_map
+            .contains_key(&mark)
+            .then(|| crate::commit::resolve_canonical_mark(mark, self.alias_map));
+        let action = match self.policy {
+            PrunedTagPolicy::Delete => PrunedTagAction::Delete,
+            PrunedTagPolicy::Ancestor => match ancestor {
+                Some(ancestor) => PrunedTagAction::Retarget(ancestor),
+                None => PrunedTagAction::Delete,
+            },
+            PrunedTagPolicy::KeepOld if !original.is_empty() => {
+                PrunedTagAction::KeepOld(original.clone())
+            }
+            PrunedTagPolicy::KeepOld => PrunedTagAction::Delete,
+        };
+        let (kind, new_target, message) = match &action {
+            PrunedTagAction::Delete => {
+                let why = if self.policy == PrunedTagPolicy::Delete {
+                    ""
+                } else {
+                    " and cannot be kept"
+                };
+                (
+                    "deleted",
+                    None,
+                    format!("deleting tag {tag}: commit {commit} was pruned{why}"),
+                )
+            }
+            PrunedTagAction::Retarget(ancestor) => {
+                let new_target = self
+                    .commit_pairs
+                    .iter()
+                    .find(|(_, m)| *m == Some(*ancestor))
+                    .map(|(oid, _)| String::from_utf8_lossy(oid).into_owned())
+                    .unwrap_or_else(|| format!(":{ancestor}"));
+                let message = format!(
+                    "moving tag {tag} from pruned commit {commit} to its nearest surviving ancestor {new_target}"
+                );
+                ("retargeted", Some(new_target), message)
+            }
+            PrunedTagAction::KeepOld(_) => (
+                "kept-old",
+                None,
+                format!(
+                    "tag {tag} still points at pruned commit {commit}, which is not part of the rewritten history"
+                ),
+            ),
+        };
+        // A tag left on unrewritten history is easy to miss; say so even
+        // with --quiet.
+        if !quiet || kind == "kept-old" {
+            diagnostics::warning(Phase::Stream, message);
+        }
+        reports.push(PrunedTagReport {
+            tag,
+            commit,
+            action: kind,
+            new_target,
+        });
+        Some(action)
+    }
+
+    /// Applies `--pruned-tag` to the buffered lightweight tag resets.
+    pub fn apply_to_resets(
+        &self,
+        resets: &mut [(Vec<u8>, Vec<u8>)],
+        annotated_tag_refs: &BTreeSet<Vec<u8>>,
+        quiet: bool,
+        reports: &mut Vec<PrunedTagReport>,
+    ) {
+        for (ref_full, from_line) in resets.iter_mut() {
+            if annotated_tag_refs.contains(ref_full) {
+                continue;
+            }
+            let Some(TagTarget::Mark(mark)) = TagTarget::parse(from_line) else {
+                continue;
+            };
+            let name = ref_full.strip_prefix(b"refs/tags/").unwrap_or(ref_full);
+            if let Some(action) = self.resolve(name, mark, quiet, reports) {
+                *from_line = action.reset_from_line();
+            }
+        }
+    }
+}
+
+/// The object named by a tag block's `from` line.
+///
+/// Fast-export tags commits, blobs and (with `--mark-tags`) other tags. A
+/// nested tag arrives as the inner tag under the outer tag's name, a reset of
+/// that ref to the null id, then the outer tag pointing at the inner's mark.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagTarget {
+    Mark(u32),
+    Oid(Vec<u8>),
+}
+
+impl TagTarget {
+    fn parse(from_line: &[u8]) -> Option<Self> {
+        let value = from_line.strip_prefix(b"from ")?;
+        let value = value.strip_suffix(b"\n").unwrap_or(value);
+        match value.strip_prefix(b":") {
+            Some(mark) => std::str::from_utf8(mark).ok()?.parse().ok().map(Self::Mark),
+            None => Some(Self::Oid(value.to_vec())),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Self::Mark(m) => format!(":{m}"),
+            Self::Oid(oid) => String::from_utf8_lossy(oid).into_owned(),
+        }
+    }
+}
+
+/// Fast-export's null id, which deletes a ref when used as a reset target.
+pub const NULL_OID: &[u8] = b"0000000000000000000000000000000000000000";
+
+fn parse_mark_line(line: &[u8]) -> Option<u32> {
+    let digits = line.strip_prefix(b"mark :")?;
+    let end = digits
+        .iter()
+        .position(|b| !b.is_ascii_digit())
+        .unwrap_or(digits.len());
+    std::str::from_utf8(&digits[..end]).ok()?.parse().ok()
 }
 
 pub fn precheck_duplicate_tag(
@@ -48,6 +249,28 @@ pub fn precheck_duplicate_tag(
     false
 }
 
+/// Old → new names of every source tag that `--tag-rename` renames.
+///
+/// Commit messages stream before the tag blocks that would fill
+/// `ref_renames`, so `--update-tag-name-references` collects them up front.
+pub fn tag_rename_pairs(opts: &Options, source_refs: &RefSnapshot) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let Some((ref old, ref new_)) = opts.tag_rename else {
+        return Vec::new();
+    };
+    let mut pairs = Vec::new();
+    for refname in source_refs.refs.keys() {
+        let Some(name) = refname.strip_prefix("refs/tags/") else {
+            continue;
+        };
+        let name = name.as_bytes();
+        if !old.is_empty() && name.starts_with(old) {
+            pairs.push((name.to_vec(), [new_, &name[old.len()..]].concat()));
+        }
+    }
+    pairs.sort();
+    pairs
+}
+
 pub fn process_tag_block(first_line: &[u8], mut ctx: TagProcessContext<'_>) -> io::Result<()> {
     // Extract tag name
     let mut tagname = &first_line[b"tag ".len()..];
@@ -88,6 +311,68 @@ pub fn process_tag_block(first_line: &[u8], mut ctx: TagProcessContext<'_>) -> i
             }
             let target_ref = [b"refs/tags/".as_ref(), renamed.as_slice()].concat();
 
+            // A tag whose blob was stripped (or whose inner tag was dropped)
+            // would revive the object or reference an undefined mark.
+            let own_mark = hdrs.iter().find_map(|h| parse_mark_line(h));
+            let target = hdrs.iter().find_map(|h| TagTarget::parse(h));
+            let dropped = match &target {
+                Some(TagTarget::Mark(m)) if ctx.dropped_tag_marks.contains(m) => true,
+                Some(t) => (ctx.target_stripped)(t)?,
+                None => false,
+            };
+            if dropped {
+                if let Some(m) = own_mark {
+                    ctx.dropped_tag_marks.insert(m);
+                }
+                if !ctx.updated_refs.contains(&target_ref)
+                    && !ctx.dropped_tags.iter().any(|d| d.tag.as_bytes() == tagname)
+                {
+                    let target = target.as_ref().map(TagTarget::describe).unwrap_or_default();
+                    if !ctx.opts.quiet {
+                        diagnostics::warning(
+                            Phase::Stream,
+                            format!(
+                                "dropping tag {} because the object it tags ({}) was stripped",
+                                String::from_utf8_lossy(tagname),
+                                target
+                            ),
+                        );
+                    }
+                    ctx.dropped_tags.push(DroppedTagReport {
+                        tag: String::from_utf8_lossy(tagname).into_owned(),
+                        target,
+                    });
+                    let mut from_line = b"from ".to_vec();
+                    from_line.extend_from_slice(NULL_OID);
+                    from_line.push(b'\n');
+                    ctx.buffered_tag_resets.push((target_ref, from_line));
+                }
+                return Ok(());
+            }
+
+            let pruned = match &target {
+                Some(TagTarget::Mark(m)) if !ctx.updated_refs.contains(&target_ref) => ctx
+                    .pruned_commits
+                    .resolve(tagname, *m, ctx.opts.quiet, ctx.pruned_tags),
+                _ => None,
+            };
+            match pruned {
+                Some(PrunedTagAction::Delete) => {
+                    if let Some(m) = own_mark {
+                        ctx.dropped_tag_marks.insert(m);
+                    }
+                    ctx.buffered_tag_resets
+                        .push((target_ref, PrunedTagAction::Delete.reset_from_line()));
+                    return Ok(());
+                }
+                Some(action) => {
+                    if let Some(from) = hdrs.iter_mut().find(|h| h.starts_with(b"from ")) {
+                        *from = action.reset_from_line();
+                    }
+                }
+                None => {}
+            }
+
             // Dedupe annotated tags
             if ctx.updated_refs.contains(&target_ref) {
                 return Ok(()); // skip emitting
@@ -109,57 +394,63 @@ pub fn process_tag_block(first_line: &[u8], mut ctx: TagProcessContext<'_>) -> i
                 fi.write_all(&out)?;
             }
             for h in hdrs.into_iter() {
+                // Tags written by old git may have no tagger line at all.
+                let h = if h.starts_with(b"tagger ") {
+                    rewrite_tagger_line(
+                        &h,
+                        ctx.author_rewriter,
+                        ctx.email_rewriter,
+                        ctx.mailmap_rewriter,
+                        ctx.tagger_override,
+                    )
+                } else {
+                    h
+                };
                 ctx.filt_file.write_all(&h)?;
                 if let Some(ref mut fi) = ctx.fi_in {
                     fi.write_all(&h)?;
                 }
                 // Record emitted tag mark
-                if h.starts_with(b"mark :") {
-                    let mut num: u32 = 0;
-                    let mut seen = false;
-                    for &b in h[b"mark :".len()..].iter() {
-                        if b.is_ascii_digit() {
-                            seen = true;
-                            num = num.saturating_mul(10).saturating_add((b - b'0') as u32);
-                        } else {
-                            break;
-                        }
-                    }
-                    if seen {
-                        ctx.emitted_marks.insert(num);
-                    }
+                if let Some(num) = parse_mark_line(&h) {
+                    ctx.emitted_marks.insert(num);
                 }
             }
+            if let Some(tagger_override) = ctx.tagger_override {
+                tagger_override.settle(true);
+            }
 
-            if ctx.replacer.is_none() && ctx.msg_regex.is_none() && ctx.short_mapper.is_none() {
-                // No modifications needed; forward header and payload without cloning
-                let header = format!("data {}\n", payload.len());
-                ctx.filt_file.write_all(header.as_bytes())?;
-                ctx.filt_file.write_all(&payload)?;
-                if let Some(ref mut fi) = ctx.fi_in {
-                    fi.write_all(header.as_bytes())?;
-                    fi.write_all(&payload)?;
+            let rewrites_message = ctx.replacer.is_some()
+                || ctx.msg_regex.is_some()
+                || ctx.short_mapper.is_some()
+                || ctx.subject_normalizer.is_some()
+                || ctx.tag_name_refs.is_some();
+            let mut new_payload = payload;
+            if rewrites_message {
+                if let Some(r) = ctx.replacer {
+                    new_payload = r.apply(new_payload);
                 }
-            } else {
-                let mut new_payload = if let Some(r) = ctx.replacer {
-                    r.apply(payload)
-                } else {
-                    payload
-                };
                 if let Some(rr) = ctx.msg_regex {
                     new_payload = rr.apply_regex(new_payload);
                 }
+                if let Some(refs) = ctx.tag_name_refs {
+                    new_payload = refs.apply(new_payload);
+                }
                 if let Some(mapper) = ctx.short_mapper {
                     new_payload = mapper.rewrite(new_payload);
                 }
-                let header = format!("data {}\n", new_payload.len());
-                ctx.filt_file.write_all(header.as_bytes())?;
-                ctx.filt_file.write_all(&new_payload)?;
-                if let Some(ref mut fi) = ctx.fi_in {
-                    fi.write_all(header.as_bytes())?;
-                    fi.write_all(&new_payload)?;
+                if let Some(normalizer) = ctx.subject_normalizer {
+                    new_payload = normalizer.apply(new_payload);
                 }
             }
+            let new_payload = enforce_tag_message_limit(tagname, new_payload, ctx.opts)?;
+            // The header always describes the bytes actually emitted.
+            let header = format!("data {}\n", new_payload.len());
+            ctx.filt_file.write_all(header.as_bytes())?;
+            ctx.filt_file.write_all(&new_payload)?;
+            if let Some(ref mut fi) = ctx.fi_in {
+                fi.write_all(header.as_bytes())?;
+                fi.write_all(&new_payload)?;
+            }
             return Ok(());
         } else {
             hdrs.push(l.clone());
@@ -168,6 +459,53 @@ pub fn process_tag_block(first_line: &[u8], mut ctx: TagProcessContext<'_>) -> i
     Ok(())
 }
 
+/// Applies `--max-tag-message-size` (by default the stream's
+/// [`MAX_DATA_SIZE`]) to a tag message about to be emitted. A longer message
+/// is an error naming the tag, or with `--truncate-tag-messages` is cut to
+/// the limit, backing off to a character boundary when it is UTF-8.
+fn enforce_tag_message_limit(
+    tagname: &[u8],
+    mut payload: Vec<u8>,
+    opts: &Options,
+) -> io::Result<Vec<u8>> {
+    let limit = opts.max_tag_message_bytes.unwrap_or(MAX_DATA_SIZE as usize);
+    if payload.len() <= limit {
+        return Ok(payload);
+    }
+    if !opts.truncate_tag_messages {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "tag {} message is {} bytes after rewriting, over the {} byte limit; \
+raise --max-tag-message-size or pass --truncate-tag-messages",
+                String::from_utf8_lossy(tagname),
+                payload.len(),
+                limit
+            ),
+        ));
+    }
+    let original_len = payload.len();
+    let mut cut = limit;
+    if let Ok(text) = std::str::from_utf8(&payload) {
+        while !text.is_char_boundary(cut) {
+            cut -= 1;
+        }
+    }
+    payload.truncate(cut);
+    if !opts.quiet {
+        diagnostics::warning(
+            Phase::Stream,
+            format!(
+                "truncated tag {} message from {} to {} bytes",
+                String::from_utf8_lossy(tagname),
+                original_len,
+                cut
+            ),
+        );
+    }
+    Ok(payload)
+}
+
 /// Classify a `reset refs/tags/<name>` header.
 ///
 /// Returns `Some(ref_full)` when the line targets a tag ref (already rewritten
@@ -0,0 +1,476 @@
+//! `--watch-interval SECONDS`: keep a mirror filtered as new commits arrive.
+//!
+//! The first run performs a normal full rewrite and records, per branch and
+//! tag, the tip it filtered and the tip it left behind in
+//! `.git/filter-repo/watch-state.json`. Each later cycle compares the
+//! current tips (narrowed by `--ref-filter` and `--ref-filter-exclude`)
+//! against the file and exports only the new commits: every recorded tip,
+//! original or rewritten, is excluded (`<ref> ^<tip>...`). fast-export names
+//! the excluded parents by id; a parent an earlier cycle rewrote is looked up
+//! in the commit-map, which the watch keeps cumulative, so new commits graft
+//! onto the filtered history whether they were pushed on top of it or on top
+//! of the original. The state file doubles as the heartbeat: it is rewritten
+//! after every cycle with the time and the cycle count.
+//!
+//! The loop stops once `opts.cancel` is cancelled; an embedder's token also
+//! aborts the cycle in flight, leaving refs as they were. Without a token,
+//! SIGINT and SIGTERM cancel one private to the loop, so the in-flight cycle
+//! finishes and the refs and state file stay consistent; a second signal
+//! terminates at once. The handlers are removed when the watch returns.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::cancel::CancellationToken;
+use crate::diagnostics::{self, Phase};
+use crate::error::{FilterRepoError, Result as FilterRepoResult};
+use crate::gitutil;
+use crate::opts::{CleanupMode, Options, SubjectPrefixPattern};
+
+const STATE_FILE: &str = "watch-state.json";
+const COMMIT_MAP: &str = "commit-map";
+const WATCHED_PREFIXES: [&str; 2] = ["refs/heads/", "refs/tags/"];
+const SLEEP_SLICE: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WatchState {
+    options_hash: String,
+    cycles: u64,
+    heartbeat: String,
+    /// Tips as the last cycle left them, compared against to spot new work.
+    tips: BTreeMap<String, String>,
+    /// Tips the last cycle exported, before it rewrote them.
+    #[serde(default)]
+    sources: BTreeMap<String, String>,
+}
+
+impl WatchState {
+    fn load(path: &Path) -> io::Result<Option<Self>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+        serde_json::from_str(&content).map(Some).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid watch state {}: {e}", path.display()),
+            )
+        })
+    }
+
+    /// Writes through a temporary file so a reader never sees a torn state.
+    fn save(&mut self, path: &Path) -> io::Result<()> {
+        self.heartbeat = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .map_err(io::Error::other)?;
+        let tmp = path.with_extension("json.tmp");
+        let json = serde_json::to_vec_pretty(self).map_err(io::Error::other)?;
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, path)
+    }
+}
+
+pub(crate) fn run(opts: &Options) -> FilterRepoResult<()> {
+    let interval = Duration::from_secs(opts.watch_interval.unwrap_or(1));
+    let state_path = state_path(opts)?;
+    let hash = options_hash(opts);
+
+    let mut state = match WatchState::load(&state_path)? {
+        Some(state) if state.options_hash != hash => {
+            return Err(FilterRepoError::invalid_options(format!(
+                "filter options differ from the saved watch state in {}; rerun with the original options or remove the file to start over",
+                state_path.display()
+            )));
+        }
+        Some(state) => {
+            if !opts.quiet {
+                diagnostics::notice(
+                    Phase::Watch,
+                    format!(
+                        "resuming watch after {} cycle(s) from {}",
+                        state.cycles,
+                        state_path.display()
+                    ),
+                );
+            }
+            state
+        }
+        None => {
+            let sources = watched_tips(opts, &opts.source)?;
+            crate::rewrite(opts)?;
+            WatchState {
+                options_hash: hash,
+                cycles: 0,
+                heartbeat: String::new(),
+                tips: watched_tips(opts, &opts.target)?,
+                sources,
+            }
+        }
+    };
+    state.save(&state_path)?;
+
+    let stop = opts.cancel.clone().unwrap_or_default();
+    #[cfg(unix)]
+    let _signals = match opts.cancel {
+        Some(_) => None,
+        None => Some(StopSignals::install(&stop)?),
+    };
+
+    // Later cycles only touch the new commits: no sanity checks against the
+    // already rewritten repository, no backups, and no remote cleanup.
+    let cycle_opts = Options {
+        partial: true,
+        force: true,
+        backup: false,
+        no_fetch: true,
+        cleanup: CleanupMode::None,
+        ref_filter_globs: Vec::new(),
+        ref_filter_globs_exclude: Vec::new(),
+        ..opts.clone()
+    };
+    let commit_map_path = state_path.with_file_name(COMMIT_MAP);
+    while sleep_unless_cancelled(interval, &stop) {
+        crate::migrate::fetch_all_refs_if_needed(opts)?;
+        let current = watched_tips(opts, &opts.source)?;
+        let excluded: BTreeSet<&String> = state
+            .tips
+            .values()
+            .chain(state.sources.values())
+            .filter(|oid| crate::pipes::resolves(&opts.source, oid))
+            .collect();
+        let refs = cycle_refs(&state.tips, &current, &excluded);
+        if !refs.is_empty() {
+            if !opts.quiet {
+                diagnostics::notice(
+                    Phase::Watch,
+                    format!("watch: filtering new commits on {}", refs[0]),
+                );
+            }
+            let earlier_map = read_commit_map(&commit_map_path)?;
+            crate::rewrite(&Options {
+                refs,
+                ..cycle_opts.clone()
+            })?;
+            let mut commit_map = earlier_map;
+            commit_map.extend_from_slice(&read_commit_map(&commit_map_path)?);
+            fs::write(&commit_map_path, commit_map)?;
+            for (name, oid) in &current {
+                if state.tips.get(name) != Some(oid) {
+                    state.sources.insert(name.clone(), oid.clone());
+                }
+            }
+            state.tips = watched_tips(opts, &opts.target)?;
+        } else {
+            state.tips = current;
+        }
+        state.cycles += 1;
+        state.save(&state_path)?;
+    }
+    if !opts.quiet {
+        diagnostics::notice(
+            Phase::Watch,
+            format!("watch stopped after {} cycle(s)", state.cycles),
+        );
+    }
+    Ok(())
+}
+
+fn state_path(opts: &Options) -> io::Result<PathBuf> {
+    let dir = gitutil::git_dir(&opts.target)?.join("filter-repo");
+    fs::create_dir_all(&dir)?;
+    Ok(dir.join(STATE_FILE))
+}
+
+fn read_commit_map(path: &Path) -> io::Result<Vec<u8>> {
+    match fs::read(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        other => other,
+    }
+}
+
+fn watched_tips(opts: &Options, repo: &Path) -> io::Result<BTreeMap<String, String>> {
+    Ok(gitutil::get_all_refs(repo)?
+        .into_iter()
+        .filter(|(name, _)| {
+            WATCHED_PREFIXES.iter().any(|p| name.starts_with(p))
+                && crate::pipes::ref_selected(opts, name.as_bytes())
+        })
+        .collect())
+}
+
+/// fast-export arguments covering every ref that moved or appeared since the
+/// last cycle, excluding everything reachable from `excluded`.
+fn cycle_refs(
+    known: &BTreeMap<String, String>,
+    current: &BTreeMap<String, String>,
+    excluded: &BTreeSet<&String>,
+) -> Vec<String> {
+    let changed: Vec<String> = current
+        .iter()
+        .filter(|(name, oid)| known.get(*name) != Some(*oid))
+        .map(|(name, _)| name.clone())
+        .collect();
+    if changed.is_empty() {
+        return changed;
+    }
+    changed
+        .into_iter()
+        .chain(excluded.iter().map(|oid| format!("^{oid}")))
+        .collect()
+}
+
+/// Fingerprint of the options that shape the rewritten history, so a resume
+/// with different filters is refused. Rule files count by content. Flags
+/// that only affect how a run is carried out (refs, force, reporting,
+/// backups) are left out and can differ between the initial run and a
+/// resume.
+fn options_hash(opts: &Options) -> String {
+    let bytes = |v: &[u8]| String::from_utf8_lossy(v).into_owned();
+    let list = |items: &[Vec<u8>]| items.iter().map(|v| bytes(v)).collect::<Vec<_>>();
+    let pairs = |items: &[(Vec<u8>, Vec<u8>)]| {
+        items
+            .iter()
+            .map(|(a, b)| [bytes(a), bytes(b)])
+            .collect::<Vec<_>>()
+    };
+    let file = |path: &Option<PathBuf>| {
+        path.as_deref().map(|p| match fs::read(p) {
+            Ok(content) => format!("{:016x}", fnv1a(&content)),
+            Err(_) => p.display().to_string(),
+        })
+    };
+    let fingerprint = json!({
+        "refs": {
+            "include": list(&opts.ref_filter_globs),
+            "exclude": list(&opts.ref_filter_globs_exclude),
+            "stash": opts.include_stash,
+            "notes": opts.include_notes,
+            "remotes": opts.include_remotes,
+            "ignore_replace_refs": opts.ignore_replace_refs,
+        },
+        "paths": {
+            "paths": list(&opts.paths),
+            "invert": opts.invert_paths,
+            "globs": list(&opts.path_globs),
+            "regexes": opts.path_regexes.iter().map(|r| r.as_str()).collect::<Vec<_>>(),
+            "renames": pairs(&opts.path_renames),
+            "rename_regexes": opts
+                .path_rename_regexes
+                .iter()
+                .map(|(r, to)| [r.as_str().to_string(), bytes(to)])
+                .collect::<Vec<_>>(),
+            "redactions": pairs(&opts.path_redactions),
+            "modes": pairs(&opts.mode_normalizations),
+            "synthesize_renames": opts.synthesize_renames,
+            "compat": opts.path_compat_policy.as_str(),
+            "unsafe": opts.unsafe_path_policy.as_str(),
+            "root_files": opts
+                .root_files
+                .iter()
+                .map(|(name, src)| (bytes(name), file(&Some(src.clone()))))
+                .collect::<Vec<_>>(),
+        },
+        "blobs": {
+            "replace_text": file(&opts.replace_text_file),
+            "callback": file(&opts.blob_callback_so),
+            "eol": opts.normalize_eol.as_ref().map(|eol| (eol.target.as_str(), list(&eol.globs))),
+            "max_size": opts.max_blob_size,
+            "max_size_per_ext": opts
+                .max_blob_size_per_ext
+                .iter()
+                .map(|(ext, size)| (bytes(ext), size))
+                .collect::<Vec<_>>(),
+            "pointers": opts.replace_blobs_with_pointer,
+            "strip_ids": file(&opts.strip_blobs_with_ids),
+            "strip_globs": list(&opts.strip_path_globs),
+            "strip_java": opts.strip_java_class_files,
+            "strip_node_modules": opts.strip_node_modules,
+            "strip_python_cache": opts.strip_python_cache,
+            "strip_matching": opts.strip_blobs_matching.iter().map(|r| r.as_str()).collect::<Vec<_>>(),
+            "strip_matching_max_bytes": opts.strip_blobs_matching_max_bytes,
+            "no_data": opts.no_data || opts.metadata_only,
+        },
+        "messages": {
+            "replace": file(&opts.replace_message_file),
+            "replace_tag": file(&opts.replace_tag_message_file),
+            "max_tag_bytes": opts.max_tag_message_bytes,
+            "truncate_tag": opts.truncate_tag_messages,
+            "subject_prefixes": opts
+                .strip_subject_prefixes
+                .iter()
+                .map(|p| match p {
+                    SubjectPrefixPattern::Glob(glob) => format!("glob:{}", bytes(glob)),
+                    SubjectPrefixPattern::Regex(re) => format!("regex:{}", re.as_str()),
+                })
+                .collect::<Vec<_>>(),
+            "pr_suffix": opts.strip_pr_suffix,
+            "tag_name_references": opts.update_tag_name_references,
+            "reencode": opts.reencode,
+        },
+        "people": {
+            "mailmap": file(&opts.mailmap_file),
+            "author": file(&opts.author_rewrite_file),
+            "committer": file(&opts.committer_rewrite_file),
+            "email": file(&opts.email_rewrite_file),
+            "override_committer": opts.override_committer.as_deref().map(bytes),
+            "override_committer_date": opts.override_committer_date.as_str(),
+            "override_tagger": opts.override_tagger.as_deref().map(bytes),
+            "override_tagger_date": opts.override_tagger_date.as_str(),
+        },
+        "dates": {
+            "author_is_committer": opts.author_date_is_committer_date,
+            "committer_is_author": opts.committer_date_is_author_date,
+            "shift": opts.date_shift,
+            "set": opts.date_set,
+        },
+        "shape": {
+            "prune_empty": opts.prune_empty.as_str(),
+            "prune_degenerate": opts.prune_degenerate.as_str(),
+            "pruned_tag": opts.pruned_tag.as_str(),
+            "no_ff": opts.no_ff,
+            "sensitive": opts.sensitive,
+            "redact_paths": opts.redact_paths,
+        },
+    });
+    let serialized = serde_json::to_vec(&fingerprint).expect("fingerprint serializes");
+    format!("{:016x}", fnv1a(&serialized))
+}
+
+/// FNV-1a keeps the value stable across builds, unlike DefaultHasher.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325u64, |h, &b| {
+        (h ^ u64::from(b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Sleeps for `interval`; returns `false` once `stop` is cancelled.
+fn sleep_unless_cancelled(interval: Duration, stop: &CancellationToken) -> bool {
+    let deadline = Instant::now() + interval;
+    while !stop.is_cancelled() {
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        thread::sleep(SLEEP_SLICE.min(deadline - now));
+    }
+    false
+}
+
+/// SIGINT and SIGTERM handlers that cancel the watch loop's token; a signal
+/// arriving after it was already cancelled gets the default action. Both
+/// are unregistered on drop, and signal-hook keeps forwarding to whatever
+/// handler was installed before.
+#[cfg(unix)]
+struct StopSignals(Vec<signal_hook::SigId>);
+
+#[cfg(unix)]
+impl StopSignals {
+    fn install(stop: &CancellationToken) -> io::Result<Self> {
+        use signal_hook::consts::{SIGINT, SIGTERM};
+        use signal_hook::flag;
+
+        let mut guard = StopSignals(Vec::new());
+        for signal in [SIGINT, SIGTERM] {
+            guard
+                .0
+                .push(flag::register_conditional_default(signal, stop.flag())?);
+            guard.0.push(flag::register(signal, stop.flag())?);
+        }
+        Ok(guard)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for StopSignals {
+    fn drop(&mut self) {
+        for id in self.0.drain(..) {
+            signal_hook::low_level::unregister(id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tips(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(r, o)| (r.to_string(), o.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn cycle_refs_cover_moved_and_new_refs_only() {
+        let known = tips(&[("refs/heads/main", "aaa"), ("refs/tags/v1", "bbb")]);
+        let current = tips(&[
+            ("refs/heads/main", "ccc"),
+            ("refs/heads/topic", "ddd"),
+            ("refs/tags/v1", "bbb"),
+        ]);
+        let (aaa, bbb) = ("aaa".to_string(), "bbb".to_string());
+        let excluded = BTreeSet::from([&aaa, &bbb]);
+        assert_eq!(
+            cycle_refs(&known, &current, &excluded),
+            ["refs/heads/main", "refs/heads/topic", "^aaa", "^bbb"]
+        );
+        assert!(cycle_refs(&current, &current, &excluded).is_empty());
+    }
+
+    #[test]
+    fn options_hash_ignores_run_flags_but_not_filters() {
+        let base = Options {
+            paths: vec![b"secrets/".to_vec()],
+            invert_paths: true,
+            ..Options::default()
+        };
+        let resumed = Options {
+            force: true,
+            quiet: true,
+            watch_interval: Some(5),
+            refs: vec!["refs/heads/main".to_string()],
+            ..base.clone()
+        };
+        let different = Options {
+            invert_paths: false,
+            ..base.clone()
+        };
+        assert_eq!(options_hash(&base), options_hash(&resumed));
+        assert_ne!(options_hash(&base), options_hash(&different));
+    }
+
+    #[test]
+    fn options_hash_covers_rule_file_contents() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let rules = dir.path().join("replacements.txt");
+        fs::write(&rules, "secret==>REDACTED\n").unwrap();
+        let opts = Options {
+            replace_text_file: Some(rules.clone()),
+            ..Options::default()
+        };
+        let before = options_hash(&opts);
+        assert_eq!(before, options_hash(&opts.clone()));
+        fs::write(&rules, "password==>REDACTED\n").unwrap();
+        assert_ne!(before, options_hash(&opts));
+    }
+
+    #[test]
+    fn cancelled_token_ends_the_sleep() {
+        let stop = CancellationToken::new();
+        assert!(sleep_unless_cancelled(Duration::ZERO, &stop));
+        stop.cancel();
+        let started = Instant::now();
+        assert!(!sleep_unless_cancelled(Duration::from_secs(60), &stop));
+        assert!(started.elapsed() < Duration::from_secs(60));
+    }
+}
@@ -52,6 +52,128 @@ fn analyze_mode_emits_json() {
     fr::analysis::run(&opts).expect("json analyze run should succeed");
 }
 
+#[test]
+fn analyze_counts_notes_across_namespaces() {
+    let repo = init_repo();
+    assert_eq!(run_git(&repo, &["notes", "add", "-m", "note"]).0, 0);
+    assert_eq!(
+        run_git(
+            &repo,
+            &["notes", "--ref", "refs/notes/ci", "add", "-m", "green"]
+        )
+        .0,
+        0
+    );
+    let opts = fr::Options {
+        source: repo.clone(),
+        target: repo.clone(),
+        mode: fr::Mode::Analyze,
+        force: true,
+        ..Default::default()
+    };
+    let report = fr::analysis::generate_report(&opts).expect("generate analysis report");
+    assert_eq!(
+        report.metrics.notes_refs,
+        vec!["refs/notes/ci", "refs/notes/commits"]
+    );
+    assert_eq!(report.metrics.notes_object_count, 2);
+    assert!(
+        report
+            .warnings
+            .iter()
+            .any(|w| w.level == fr::analysis::WarningLevel::Info
+                && w.message.contains("2 notes in 2 notes refs")),
+        "{:?}",
+        report.warnings
+    );
+}
+
+#[test]
+fn analyze_counts_lfs_pointer_blobs() {
+    let repo = init_repo();
+    write_file(
+        &repo,
+        "assets/model.bin",
+        "version https://git-lfs.github.com/spec/v1\n\
+         oid sha256:4d7a214614ab2935c943f9e0ff69d22eadbb8f32b1258daaa5e2ca24d17e2393\n\
+         size 12345\n",
+    );
+    // Mentions the spec, but not as its first line.
+    write_file(
+        &repo,
+        "docs/lfs.md",
+        "See version https://git-lfs.github.com/spec/v1\n",
+    );
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add pointer"]).0, 0);
+    let pointer_oid = run_git(&repo, &["rev-parse", "HEAD:assets/model.bin"])
+        .1
+        .trim()
+        .to_string();
+
+    let opts = fr::Options {
+        source: repo.clone(),
+        target: repo.clone(),
+        mode: fr::Mode::Analyze,
+        force: true,
+        ..Default::default()
+    };
+    let report = fr::analysis::generate_report(&opts).expect("generate analysis report");
+    assert_eq!(report.metrics.lfs_pointer_count, 1);
+    assert_eq!(report.metrics.lfs_pointer_oids, vec![pointer_oid]);
+    assert!(
+        report
+            .warnings
+            .iter()
+            .any(|w| w.level == fr::analysis::WarningLevel::Info
+                && w.message.contains("Git LFS pointer")
+                && w.recommendation
+                    .as_deref()
+                    .is_some_and(|r| r.contains("git lfs migrate import"))),
+        "{:?}",
+        report.warnings
+    );
+}
+
+#[test]
+fn analyze_suggests_dropping_node_modules() {
+    let repo = init_repo();
+    write_file(&repo, "src/index.js", "console.log('app');\n");
+    write_file(
+        &repo,
+        "node_modules/left-pad/index.js",
+        &"module.exports = pad;\n".repeat(200),
+    );
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "vendor deps"]).0, 0);
+
+    let mut opts = fr::Options {
+        source: repo.clone(),
+        target: repo.clone(),
+        mode: fr::Mode::Analyze,
+        force: true,
+        ..Default::default()
+    };
+    let report = fr::analysis::generate_report(&opts).expect("generate analysis report");
+    let suggestion = report
+        .metrics
+        .suggestions
+        .iter()
+        .find(|s| s.pattern == "node_modules/")
+        .unwrap_or_else(|| panic!("{:?}", report.metrics.suggestions));
+    assert_eq!(suggestion.category, fr::analysis::PathCategory::Vendored);
+    assert!(suggestion.estimated_bytes > 0);
+    assert_eq!(suggestion.command, "--invert-paths --path node_modules/");
+
+    let json = serde_json::to_value(&report).expect("serialize report");
+    assert_eq!(
+        json["metrics"]["suggestions"][0]["category"],
+        serde_json::json!("vendored")
+    );
+    opts.analyze.json = false;
+    fr::analysis::run(&opts).expect("human analyze run should succeed");
+}
+
 #[test]
 fn analyze_mode_limits_top_entries_and_populates_paths() {
     let repo = init_repo();
@@ -185,6 +307,53 @@ fn analyze_mode_warns_on_commit_thresholds() {
     );
 }
 
+#[test]
+fn analyze_flags_long_subjects_and_control_characters() {
+    let repo = init_repo();
+    let long_subject = "s".repeat(40);
+    let (_c, tree, _e) = run_git(&repo, &["rev-parse", "HEAD^{tree}"]);
+    let mut parent = "HEAD".to_string();
+    let mut oids = Vec::new();
+    for message in [long_subject.as_str(), "pasted \u{1b}[0m escape"] {
+        let (code, oid, err) = run_git(
+            &repo,
+            &["commit-tree", tree.trim(), "-p", &parent, "-m", message],
+        );
+        assert_eq!(code, 0, "commit-tree failed: {err}");
+        parent = oid.trim().to_string();
+        oids.push(parent.clone());
+    }
+    assert_eq!(run_git(&repo, &["update-ref", "HEAD", &parent]).0, 0);
+
+    let mut opts = fr::Options {
+        source: repo.clone(),
+        target: repo.clone(),
+        mode: fr::Mode::Analyze,
+        force: true,
+        ..Default::default()
+    };
+    opts.analyze.thresholds.warn_subject_length = 30;
+    let report = fr::analysis::generate_report(&opts).expect("generate analysis report");
+
+    let problematic = &report.metrics.problematic_commit_messages;
+    assert_eq!(problematic.len(), 2, "{problematic:?}");
+    let long = problematic.iter().find(|m| m.oid == oids[0]).unwrap();
+    assert_eq!(long.subject_length, 40);
+    assert!(!long.has_control_chars);
+    let control = problematic.iter().find(|m| m.oid == oids[1]).unwrap();
+    assert!(control.has_control_chars);
+
+    let codes: Vec<&str> = report.warnings.iter().filter_map(|w| w.code).collect();
+    assert!(codes.contains(&"long-commit-subject"), "{codes:?}");
+    assert!(codes.contains(&"commit-message-control-chars"), "{codes:?}");
+    let json = serde_json::to_value(&report).unwrap();
+    assert!(json["warnings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|w| w["code"] == "commit-message-control-chars"));
+}
+
 #[test]
 fn analyze_json_stdout_is_valid_json_without_progress_prefix() {
     let repo = init_repo();
@@ -374,3 +543,760 @@ fn analyze_mode_with_write_report_json_creates_json_report_file() {
         "json should contain warnings"
     );
 }
+
+#[test]
+fn analyze_mode_flags_suspect_identities() {
+    let repo = init_repo();
+    let commit_with_env = |msg: &str, envs: &[(&str, &str)]| {
+        let status = std::process::Command::new("git")
+            .current_dir(&repo)
+            .args(["commit", "-q", "--allow-empty", "-m", msg])
+            .envs(envs.iter().copied())
+            .status()
+            .expect("run git commit");
+        assert!(status.success(), "git commit {msg} failed");
+    };
+    commit_with_env("empty author email", &[("GIT_AUTHOR_EMAIL", "")]);
+    commit_with_env("bare committer email", &[("GIT_COMMITTER_EMAIL", "nobody")]);
+    commit_with_env(
+        "root identity",
+        &[
+            ("GIT_AUTHOR_NAME", "root"),
+            ("GIT_AUTHOR_EMAIL", "root@localhost"),
+        ],
+    );
+
+    let mut opts = fr::Options {
+        source: repo.clone(),
+        target: repo.clone(),
+        mode: fr::Mode::Analyze,
+        force: true,
+        ..Default::default()
+    };
+    opts.analyze.bad_identities = vec!["root@localhost".to_string()];
+    let report = fr::analysis::generate_report(&opts).expect("generate analysis report");
+    let suspects = &report.metrics.suspect_identities;
+
+    assert_eq!(report.metrics.suspect_identities_total, 3, "{suspects:?}");
+    let find = |role: &str, reason: &str| {
+        suspects
+            .iter()
+            .any(|s| s.role == role && s.reason == reason && s.oid.len() == 40)
+    };
+    assert!(find("author", "empty email"), "{suspects:?}");
+    assert!(find("committer", "email missing '@'"), "{suspects:?}");
+    assert!(find("author", "denylisted identity"), "{suspects:?}");
+    assert!(
+        report
+            .warnings
+            .iter()
+            .any(|w| w.message.contains("3 author/committer identities")),
+        "expected suspect identity warning: {:?}",
+        report.warnings
+    );
+}
+
+#[test]
+fn analyze_json_compact_emits_single_line_json() {
+    let repo = init_repo();
+    write_file(&repo, "src/a.txt", "a\n");
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-m", "seed compact json"]).0, 0);
+
+    for flag in ["--analyze-json-compact", "--analyze-json-pretty=false"] {
+        let output = cli_command()
+            .arg("--analyze")
+            .arg(flag)
+            .arg("--source")
+            .arg(repo.to_string_lossy().as_ref())
+            .arg("--target")
+            .arg(repo.to_string_lossy().as_ref())
+            .output()
+            .expect("run filter-repo-rs analyze compact json");
+        assert!(
+            output.status.success(),
+            "{flag} should succeed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let body = stdout.strip_suffix('\n').unwrap_or(&stdout);
+        assert!(
+            !body.contains('\n'),
+            "{flag} output should be a single line: {stdout}"
+        );
+        let parsed: serde_json::Value =
+            serde_json::from_str(body).expect("compact output should be valid json");
+        assert!(parsed.get("metrics").is_some(), "metrics missing: {body}");
+    }
+}
+
+#[test]
+fn analyze_json_selects_pretty_output_over_earlier_compact_settings() {
+    let repo = init_repo();
+    write_file(
+        &repo,
+        ".filter-repo-rs.toml",
+        "[analyze]\njson_compact = true\n",
+    );
+    write_file(&repo, "src/a.txt", "a\n");
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-m", "seed pretty json"]).0, 0);
+
+    for args in [
+        &["--analyze-json"][..],
+        &["--analyze-json-compact", "--analyze-json"][..],
+    ] {
+        let output = cli_command()
+            .current_dir(&repo)
+            .arg("--analyze")
+            .args(args)
+            .output()
+            .expect("run filter-repo-rs analyze pretty json");
+        assert!(
+            output.status.success(),
+            "{args:?} should succeed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let body = stdout.strip_suffix('\n').unwrap_or(&stdout);
+        assert!(
+            body.contains('\n'),
+            "{args:?} output should be pretty-printed: {stdout}"
+        );
+        let parsed: serde_json::Value =
+            serde_json::from_str(body).expect("pretty output should be valid json");
+        assert!(parsed.get("metrics").is_some(), "metrics missing: {body}");
+    }
+}
+
+#[test]
+fn analyze_reports_pack_layout_before_and_after_repack() {
+    let repo = init_repo();
+    for i in 0..3 {
+        write_file(&repo, &format!("file{i}.txt"), &"x".repeat(4096 * (i + 1)));
+        assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+        assert_eq!(
+            run_git(&repo, &["commit", "-q", "-m", &format!("c{i}")]).0,
+            0
+        );
+    }
+    let mut opts = fr::Options {
+        source: repo.clone(),
+        target: repo.clone(),
+        mode: fr::Mode::Analyze,
+        force: true, // Use --force to bypass sanity checks for unit tests
+        ..Default::default()
+    };
+    opts.analyze.thresholds.warn_pack_count = 0;
+    opts.analyze.thresholds.warn_commit_graph_bytes = 0;
+
+    let before = fr::analysis::generate_report(&opts).expect("analyze loose repo");
+    assert_eq!(before.metrics.pack_count, 0);
+    assert_eq!(before.metrics.largest_pack_bytes, 0);
+    assert!(!before.metrics.has_commit_graph);
+    assert!(!before.metrics.has_bitmaps);
+    assert!(!before.metrics.has_multi_pack_index);
+    assert!(before
+        .warnings
+        .iter()
+        .any(|w| w.message.contains("No commit-graph")));
+    assert!(!before
+        .warnings
+        .iter()
+        .any(|w| w.message.contains("packfiles")));
+
+    assert_eq!(run_git(&repo, &["repack", "-q", "-a", "-d", "-b"]).0, 0);
+    assert_eq!(run_git(&repo, &["multi-pack-index", "write"]).0, 0);
+    assert_eq!(
+        run_git(&repo, &["commit-graph", "write", "--reachable"]).0,
+        0
+    );
+
+    let after = fr::analysis::generate_report(&opts).expect("analyze repacked repo");
+    assert_eq!(after.metrics.pack_count, 1);
+    assert!(after.metrics.largest_pack_bytes > 0);
+    assert!(after.metrics.largest_pack_bytes <= after.metrics.packed_size_bytes + 1024);
+    assert!(after.metrics.has_commit_graph);
+    assert!(after.metrics.has_bitmaps);
+    assert!(after.metrics.has_multi_pack_index);
+    assert!(!after
+        .warnings
+        .iter()
+        .any(|w| w.message.contains("No commit-graph")));
+    let pack_warning = after
+        .warnings
+        .iter()
+        .find(|w| w.message.contains("packfiles"))
+        .expect("pack count over threshold should warn");
+    assert!(pack_warning
+        .recommendation
+        .as_deref()
+        .is_some_and(|r| r.contains("repack -adb")));
+
+    let json = serde_json::to_value(&after).expect("serialize report");
+    assert_eq!(json["metrics"]["pack_count"], 1);
+    assert_eq!(json["metrics"]["has_bitmaps"], true);
+}
+
+#[test]
+fn analyze_counts_pack_files_and_flags_fragmentation() {
+    let repo = init_repo();
+    let pack_dir = repo.join(".git").join("objects").join("pack");
+    std::fs::create_dir_all(&pack_dir).unwrap();
+    std::fs::write(pack_dir.join("pack-dummy1.pack"), vec![0u8; 64]).unwrap();
+    std::fs::write(pack_dir.join("pack-dummy2.pack"), vec![0u8; 128]).unwrap();
+    let mut opts = fr::Options {
+        source: repo.clone(),
+        target: repo.clone(),
+        mode: fr::Mode::Analyze,
+        force: true,
+        ..Default::default()
+    };
+
+    let report = fr::analysis::generate_report(&opts).expect("analyze repo");
+    assert_eq!(report.metrics.pack_file_count, 2);
+    assert_eq!(report.metrics.largest_pack_bytes, 128);
+    assert!(!report
+        .warnings
+        .iter()
+        .any(|w| w.message.contains("pack files")));
+
+    opts.analyze.thresholds.warn_pack_fragmentation = 1;
+    let report = fr::analysis::generate_report(&opts).expect("analyze repo");
+    let warning = report
+        .warnings
+        .iter()
+        .find(|w| w.message.contains("2 pack files"))
+        .expect("fragmentation warning");
+    assert!(matches!(warning.level, fr::analysis::WarningLevel::Info));
+    assert!(warning
+        .recommendation
+        .as_deref()
+        .is_some_and(|r| r.contains("git gc --aggressive")));
+}
+
+#[test]
+fn analyze_ignore_path_excludes_matching_blobs_from_metrics() {
+    let repo = init_repo();
+    write_file(&repo, "vendor/jquery.min.js", &"x".repeat(256 * 1024));
+    write_file(&repo, "src/main.rs", "fn main() {}\n");
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "vendor"]).0, 0);
+    assert_eq!(run_git(&repo, &["rm", "-q", "README.md"]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "drop readme"]).0, 0);
+
+    let mut opts = fr::Options {
+        source: repo.clone(),
+        target: repo.clone(),
+        mode: fr::Mode::Analyze,
+        force: true, // Use --force to bypass sanity checks for unit tests
+        ..Default::default()
+    };
+    let full = fr::analysis::generate_report(&opts).expect("analyze without ignores");
+    assert_eq!(
+        full.metrics.largest_blobs[0].path.as_deref(),
+        Some("vendor/jquery.min.js")
+    );
+
+    opts.analyze.analyze_ignore_globs = vec![b"vendor/**".to_vec(), b"README.md".to_vec()];
+    let report = fr::analysis::generate_report(&opts).expect("analyze with ignores");
+    let paths: Vec<_> = report
+        .metrics
+        .largest_blobs
+        .iter()
+        .map(|b| b.path.as_deref().unwrap_or(""))
+        .collect();
+    assert_eq!(paths, ["src/main.rs"]);
+    assert!(report
+        .metrics
+        .largest_files
+        .iter()
+        .all(|f| f.path == "src/main.rs"));
+    assert_eq!(report.metrics.ignored_blob_count, 2);
+    assert_eq!(report.metrics.object_types["blob"], 1);
+    assert_eq!(report.metrics.total_objects, full.metrics.total_objects - 2);
+}
+
+#[test]
+fn analyze_baseline_reports_only_new_blobs_and_growth() {
+    let repo = init_repo();
+    write_file(&repo, "assets/old.bin", &"o".repeat(4096));
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-m", "old asset"]).0, 0);
+
+    let mut opts = fr::Options {
+        source: repo.clone(),
+        target: repo.clone(),
+        mode: fr::Mode::Analyze,
+        force: true, // Use --force to bypass sanity checks for unit tests
+        ..Default::default()
+    };
+    opts.analyze.thresholds.warn_blob_bytes = 2048;
+    let before = fr::analysis::generate_report(&opts).expect("baseline analysis");
+    assert!(before.delta.is_none());
+    let baseline = repo.join("baseline.json");
+    std::fs::write(&baseline, serde_json::to_string(&before).unwrap()).unwrap();
+
+    write_file(&repo, "assets/new.bin", &"n".repeat(64 * 1024));
+    assert_eq!(run_git(&repo, &["add", "assets"]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-m", "new asset"]).0, 0);
+
+    opts.analyze.baseline = Some(baseline);
+    let report = fr::analysis::generate_report(&opts).expect("analysis with baseline");
+    let delta = report.delta.expect("delta against baseline");
+    assert_eq!(
+        delta.baseline_schema_version,
+        Some(u64::from(fr::analysis::ANALYSIS_SCHEMA_VERSION))
+    );
+    let new_blobs = delta.new_blobs_over_threshold.expect("known blob list");
+    let paths: Vec<_> = new_blobs.iter().map(|b| b.path.as_deref()).collect();
+    assert_eq!(paths, [Some("assets/new.bin")]);
+    assert!(delta.total_size_bytes_change.expect("known size") > 0);
+    assert!(delta.total_objects_change.expect("known count") > 0);
+}
+
+#[test]
+fn collect_footprint_and_refs_return_quick_stats() {
+    let repo = init_repo();
+    run_git(&repo, &["tag", "v1"]);
+    run_git(&repo, &["branch", "topic"]);
+
+    let refs = fr::analysis::collect_refs(&repo).expect("collect refs");
+    assert_eq!(refs.heads, 2);
+    assert_eq!(refs.tags, 1);
+    assert_eq!(
+        refs.total,
+        refs.heads + refs.tags + refs.remotes + refs.other
+    );
+
+    let footprint = fr::analysis::collect_footprint(&repo).expect("collect footprint");
+    let opts = fr::Options {
+        source: repo.clone(),
+        target: repo.clone(),
+        mode: fr::Mode::Analyze,
+        force: true, // Use --force to bypass sanity checks for unit tests
+        ..Default::default()
+    };
+    let report = fr::analysis::generate_report(&opts).expect("generate analysis report");
+    assert_eq!(report.metrics.refs_total, refs.total);
+    assert_eq!(
+        report.metrics.total_objects,
+        footprint.loose_objects + footprint.packed_objects
+    );
+}
+
+#[test]
+fn analyze_warn_loose_ratio_flag_controls_loose_object_warning() {
+    let repo = init_repo();
+    let loose_warnings = |ratio_arg: &str| {
+        let output = cli_command()
+            .arg("--analyze")
+            .arg("--analyze-json")
+            .arg(ratio_arg)
+            .arg("--source")
+            .arg(&repo)
+            .arg("--target")
+            .arg(&repo)
+            .output()
+            .expect("run filter-repo-rs --analyze");
+        assert!(output.status.success(), "{output:?}");
+        let v: serde_json::Value = serde_json::from_slice(&output.stdout).expect("valid json");
+        v["warnings"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|w| w["message"].as_str().unwrap().contains("objects are loose"))
+            .count()
+    };
+
+    // A fresh repository has only loose objects.
+    assert_eq!(loose_warnings("--warn-loose-ratio=0.5"), 1);
+    assert_eq!(loose_warnings("--warn-loose-ratio=1"), 0);
+
+    let output = cli_command()
+        .args(["--analyze", "--warn-loose-ratio=1.5"])
+        .output()
+        .expect("run filter-repo-rs --analyze");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("expects a ratio between 0 and 1"));
+}
+
+#[test]
+fn analyze_reports_oldest_surviving_blob_with_its_introduction_time() {
+    let repo = init_repo();
+    for (name, epoch) in [("old.bin", 1_000_000_000), ("new.bin", 1_100_000_000)] {
+        write_file(&repo, name, &name.repeat(200));
+        assert_eq!(run_git(&repo, &["add", name]).0, 0);
+        let date = format!("@{epoch} +0000");
+        let status = std::process::Command::new("git")
+            .current_dir(&repo)
+            .args(["commit", "-q", "-m", name])
+            .env("GIT_AUTHOR_DATE", &date)
+            .env("GIT_COMMITTER_DATE", &date)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    let opts = fr::Options {
+        source: repo.clone(),
+        target: repo.clone(),
+        mode: fr::Mode::Analyze,
+        force: true,
+        ..Default::default()
+    };
+    let report = fr::analysis::generate_report(&opts).expect("generate analysis report");
+
+    let oldest = report
+        .metrics
+        .oldest_surviving_blob
+        .as_ref()
+        .expect("oldest blob detected");
+    assert_eq!(oldest.path.as_deref(), Some("old.bin"));
+    assert_eq!(
+        oldest.introduced_at.as_deref(),
+        Some("2001-09-09T01:46:40Z")
+    );
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(
+        json["metrics"]["oldest_surviving_blob"]["introduced_at"],
+        "2001-09-09T01:46:40Z"
+    );
+}
+
+#[test]
+fn analyze_report_includes_warnings_for_blob_over_threshold_when_path_known() {
+    let repo = init_repo();
+    write_file(&repo, "assets/big.bin", &"z".repeat(4096));
+    let (_c, oid, _e) = run_git(&repo, &["hash-object", "-w", "assets/big.bin"]);
+    let oid = oid.trim().to_string();
+    // The tagged blob is listed by `rev-list --objects` before the tree that
+    // holds it, so its only line there carries no path.
+    assert_eq!(run_git(&repo, &["tag", "blob-tag", &oid]).0, 0);
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-m", "add big blob"]).0, 0);
+    let (_c, objects, _e) = run_git(&repo, &["rev-list", "--objects", "--all"]);
+    assert!(
+        objects.lines().any(|l| l.trim_end() == oid),
+        "expected a path-less line for the tagged blob:\n{objects}"
+    );
+
+    let mut opts = fr::Options {
+        source: repo.clone(),
+        target: repo.clone(),
+        mode: fr::Mode::Analyze,
+        force: true,
+        ..Default::default()
+    };
+    opts.analyze.thresholds.warn_blob_bytes = 2048;
+    let report = fr::analysis::generate_report(&opts).expect("generate analysis report");
+
+    let hit = report
+        .metrics
+        .blobs_over_threshold
+        .iter()
+        .find(|b| b.oid == oid)
+        .expect("big blob over threshold");
+    assert_eq!(hit.path.as_deref(), Some("assets/big.bin"));
+    assert!(
+        report
+            .warnings
+            .iter()
+            .any(|w| w.message.contains(&oid) && w.message.contains("assets/big.bin")),
+        "{:?}",
+        report.warnings
+    );
+}
+
+#[test]
+fn analyze_lists_every_path_a_large_blob_appeared_at() {
+    let repo = init_repo();
+    let payload = "q".repeat(4096);
+    for path in ["c/big.bin", "a/big.bin", "b/big.bin"] {
+        write_file(&repo, path, &payload);
+    }
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "three copies"]).0, 0);
+    // Thresholds in the config file are a debug-mode setting.
+    std::fs::write(
+        repo.join(".filter-repo-rs.toml"),
+        "[analyze.thresholds]\nwarn_blob_bytes = 2048\n",
+    )
+    .unwrap();
+
+    // Without the option, only the path rev-list reached the blob at; no
+    // history walk.
+    let json: serde_json::Value =
+        serde_json::from_str(&analyze_stdout(&repo, &["--debug-mode", "--analyze-json"]))
+            .expect("valid JSON");
+    let hit = &json["metrics"]["blobs_over_threshold"][0];
+    assert_eq!(hit["paths"], serde_json::json!(["a/big.bin"]));
+    assert_eq!(hit["path_count"], 1);
+
+    let json: serde_json::Value = serde_json::from_str(&analyze_stdout(
+        &repo,
+        &[
+            "--debug-mode",
+            "--analyze-json",
+            "--analyze-max-blob-paths",
+            "10",
+        ],
+    ))
+    .expect("valid JSON");
+    let hit = &json["metrics"]["blobs_over_threshold"][0];
+    assert_eq!(
+        hit["paths"],
+        serde_json::json!(["a/big.bin", "b/big.bin", "c/big.bin"])
+    );
+    assert_eq!(hit["path_count"], 3);
+    assert_eq!(hit["path"], "a/big.bin");
+    assert_eq!(json["metrics"]["largest_blobs"][0]["paths"], hit["paths"]);
+    let warning = json["warnings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find_map(|w| w["message"].as_str().filter(|m| m.starts_with("Blob ")))
+        .expect("blob over threshold warning");
+    assert!(
+        warning.contains("(a/big.bin, b/big.bin, c/big.bin)"),
+        "{warning}"
+    );
+
+    let capped: serde_json::Value = serde_json::from_str(&analyze_stdout(
+        &repo,
+        &[
+            "--debug-mode",
+            "--analyze-json",
+            "--analyze-max-blob-paths",
+            "1",
+        ],
+    ))
+    .expect("valid JSON");
+    let hit = &capped["metrics"]["blobs_over_threshold"][0];
+    assert_eq!(hit["paths"], serde_json::json!(["a/big.bin"]));
+    assert_eq!(hit["path_count"], 3);
+
+    let stdout = analyze_stdout(&repo, &["--debug-mode", "--analyze-max-blob-paths=5"]);
+    assert!(stdout.contains("a/big.bin (+2 more)"), "{stdout}");
+}
+
+#[test]
+fn analyze_counts_commits_per_month() {
+    let repo = init_repo();
+    for date in [
+        "2023-12-31T12:00:00 +0000",
+        "2024-02-03T12:00:00 +0000",
+        "2024-02-20T12:00:00 +0000",
+    ] {
+        let status = std::process::Command::new("git")
+            .current_dir(&repo)
+            .args(["commit", "-q", "--allow-empty", "-m", date])
+            .env("GIT_COMMITTER_DATE", date)
+            .status()
+            .unwrap();
+        assert!(status.success());
+    }
+
+    let histogram = fr::analysis::compute_commit_frequency_histogram(&repo).expect("histogram");
+    assert_eq!(histogram.get("2023-12"), Some(&1));
+    assert_eq!(histogram.get("2024-01"), None);
+    assert_eq!(histogram.get("2024-02"), Some(&2));
+    // Plus the initial commit made by init_repo.
+    assert_eq!(histogram.values().sum::<u64>(), 4);
+
+    let opts = fr::Options {
+        source: repo.clone(),
+        target: repo.clone(),
+        mode: fr::Mode::Analyze,
+        force: true,
+        ..Default::default()
+    };
+    let report = fr::analysis::generate_report(&opts).expect("generate analysis report");
+    assert_eq!(report.metrics.commit_frequency, histogram);
+    let json = serde_json::to_value(&report).unwrap();
+    assert_eq!(json["metrics"]["commit_frequency"]["2024-02"], 2);
+}
+
+fn analyze_report(repo: &std::path::Path) -> fr::analysis::AnalysisReport {
+    let opts = fr::Options {
+        source: repo.to_path_buf(),
+        target: repo.to_path_buf(),
+        mode: fr::Mode::Analyze,
+        force: true,
+        ..Default::default()
+    };
+    fr::analysis::generate_report(&opts).expect("generate analysis report")
+}
+
+#[test]
+fn analyze_from_linked_worktree_reports_the_shared_object_store() {
+    let repo = init_repo();
+    write_file(&repo, "data.bin", &"x".repeat(4096));
+    run_git(&repo, &["add", "."]);
+    run_git(&repo, &["commit", "-q", "-m", "add data"]);
+    assert_eq!(run_git(&repo, &["repack", "-adq"]).0, 0);
+    let wt = mktemp("fr_rs_analyze_wt");
+    let wt_str = wt.to_string_lossy().to_string();
+    assert_eq!(
+        run_git(&repo, &["worktree", "add", "-q", "-b", "side", &wt_str]).0,
+        0
+    );
+
+    let main = analyze_report(&repo).metrics;
+    let linked = analyze_report(&wt).metrics;
+    assert!(main.packed_objects > 0);
+    assert_eq!(linked.total_objects, main.total_objects);
+    assert_eq!(linked.total_size_bytes, main.total_size_bytes);
+    assert_eq!(linked.pack_file_count, 1);
+    assert_eq!(linked.largest_pack_bytes, main.largest_pack_bytes);
+    assert_eq!(
+        linked.object_types.get("blob"),
+        main.object_types.get("blob")
+    );
+}
+
+#[test]
+fn analyze_counts_alternate_object_stores_separately() {
+    let origin = init_repo();
+    write_file(&origin, "data.bin", &"y".repeat(4096));
+    run_git(&origin, &["add", "."]);
+    run_git(&origin, &["commit", "-q", "-m", "add data"]);
+    let origin_objects = fr::analysis::collect_footprint(&origin)
+        .expect("origin footprint")
+        .loose_objects;
+
+    let parent = mktemp("fr_rs_alternates");
+    std::fs::create_dir_all(&parent).unwrap();
+    let clone = parent.join("clone");
+    let (code, _out, err) = run_git(
+        &parent,
+        &[
+            "clone",
+            "-q",
+            "--shared",
+            &origin.to_string_lossy(),
+            &clone.to_string_lossy(),
+        ],
+    );
+    assert_eq!(code, 0, "{err}");
+    write_file(&clone, "local.txt", "only in the clone\n");
+    run_git(&clone, &["add", "."]);
+    let (code, _out, err) = run_git(
+        &clone,
+        &[
+            "-c",
+            "user.name=A U Thor",
+            "-c",
+            "user.email=a.u.thor@example.com",
+            "commit",
+            "-q",
+            "-m",
+            "local",
+        ],
+    );
+    assert_eq!(code, 0, "{err}");
+
+    let metrics = analyze_report(&clone).metrics;
+    assert_eq!(metrics.alternates.len(), 1, "{:?}", metrics.alternates);
+    let alternate = &metrics.alternates[0];
+    let origin_store = origin.join(".git/objects").canonicalize().unwrap();
+    assert_eq!(alternate.path, origin_store.display().to_string());
+    assert_eq!(alternate.objects, origin_objects);
+    assert!(alternate.size_bytes > 0);
+    // The clone's own store holds just the new commit, tree and blob.
+    assert_eq!(metrics.loose_objects + metrics.packed_objects, 3);
+    assert_eq!(metrics.total_objects, 3 + origin_objects);
+    let json = serde_json::to_value(&metrics).unwrap();
+    assert_eq!(json["alternates"][0]["objects"], origin_objects);
+}
+
+fn strip_ansi(s: &str) -> String {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+fn repo_with_long_vendored_path() -> (std::path::PathBuf, String) {
+    let repo = init_repo();
+    let dir: String = (1..=8)
+        .map(|i| format!("very-long-package-name-{i}/"))
+        .collect();
+    let path = format!("node_modules/{dir}dist/esm/index.min.js");
+    write_file(&repo, &path, &"x".repeat(4096));
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "vendor"]).0, 0);
+    (repo, path)
+}
+
+fn analyze_stdout(repo: &std::path::Path, extra: &[&str]) -> String {
+    let output = cli_command()
+        .arg("--analyze")
+        .args(extra)
+        .arg("--source")
+        .arg(repo)
+        .arg("--target")
+        .arg(repo)
+        .output()
+        .expect("run filter-repo-rs analyze");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    strip_ansi(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[test]
+fn analyze_table_shortens_long_paths_in_the_middle() {
+    let (repo, path) = repo_with_long_vendored_path();
+
+    let stdout = analyze_stdout(&repo, &["--analyze-max-path-width", "40"]);
+    let row = stdout
+        .lines()
+        .find(|line| line.contains("index.min.js"))
+        .unwrap_or_else(|| panic!("no row for the vendored file: {stdout}"));
+    let cells: Vec<&str> = row.split('┆').map(str::trim).collect();
+    assert_eq!(cells[1], "4.00 KiB", "{row}");
+    assert_eq!(
+        cells[4], "node_modules/…me-8/dist/esm/index.min.js",
+        "{row}"
+    );
+    assert!(!stdout.contains(&path), "{stdout}");
+
+    let json = analyze_stdout(&repo, &["--debug-mode", "--analyze-json"]);
+    assert!(json.contains(&path), "JSON keeps the full path: {json}");
+}
+
+#[test]
+fn analyze_plain_prints_tab_separated_tables() {
+    let (repo, path) = repo_with_long_vendored_path();
+
+    let stdout = analyze_stdout(&repo, &["--analyze-plain"]);
+    assert!(!stdout.contains('│') && !stdout.contains('╭'), "{stdout}");
+    let header = stdout
+        .lines()
+        .position(|line| line == "#\tSize\t%\tCum %\tPath\tVers\tOID")
+        .unwrap_or_else(|| panic!("no largest-files header: {stdout}"));
+    let rows: Vec<Vec<&str>> = stdout
+        .lines()
+        .skip(header + 1)
+        .take_while(|line| line.contains('\t'))
+        .map(|line| line.split('\t').collect())
+        .collect();
+    assert_eq!(rows.len(), 2, "{stdout}");
+    assert!(rows.iter().all(|fields| fields.len() == 7), "{rows:?}");
+    assert_eq!(rows[0][4], path);
+}
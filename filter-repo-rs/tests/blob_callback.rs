@@ -0,0 +1,72 @@
+mod common;
+use common::*;
+
+#[cfg(feature = "dynamic-callbacks")]
+fn build_example_callback() -> std::path::PathBuf {
+    let out_dir = mktemp("fr_rs_blob_callback");
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let library = out_dir.join(format!(
+        "{}blob_callback{}",
+        std::env::consts::DLL_PREFIX,
+        std::env::consts::DLL_SUFFIX
+    ));
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let status = std::process::Command::new(rustc)
+        .args(["--edition", "2021", "--crate-type", "cdylib", "-o"])
+        .arg(&library)
+        .arg(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/examples/blob_callback.rs"
+        ))
+        .status()
+        .expect("run rustc");
+    assert!(status.success(), "building the example callback failed");
+    library
+}
+
+#[cfg(feature = "dynamic-callbacks")]
+#[test]
+fn blob_callback_so_runs_after_replace_text() {
+    let repo = init_repo();
+    write_file(&repo, "notes.txt", "token=abc   \nkeep\t\n");
+    write_file(&repo, "clean.txt", "already clean\n");
+    run_git(&repo, &["add", "."]);
+    run_git(&repo, &["commit", "-q", "-m", "add notes"]);
+    let rules = repo.join(".git").join("rules.txt");
+    std::fs::write(&rules, "abc==>xyz   \n").unwrap();
+    let library = build_example_callback();
+
+    run_tool_expect_success(&repo, |o| {
+        o.replace_text_file = Some(rules.clone());
+        o.blob_callback_so = Some(library.clone());
+    });
+
+    let (_c, notes, _e) = run_git(&repo, &["show", "HEAD:notes.txt"]);
+    // The replacement's own trailing spaces are stripped too.
+    assert_eq!(notes, "token=xyz\nkeep\n");
+    let (_c, clean, _e) = run_git(&repo, &["show", "HEAD:clean.txt"]);
+    assert_eq!(clean, "already clean\n");
+}
+
+#[cfg(feature = "dynamic-callbacks")]
+#[test]
+fn blob_callback_so_rejects_a_file_that_is_not_a_library() {
+    let repo = init_repo();
+    let bogus = repo.join(".git").join("not-a-library.so");
+    std::fs::write(&bogus, "not a shared library").unwrap();
+
+    let err = run_tool(&repo, |o| o.blob_callback_so = Some(bogus.clone()))
+        .expect_err("loading a non-library should fail");
+    assert!(err.to_string().contains("--blob-callback-so"), "{err}");
+}
+
+#[cfg(not(feature = "dynamic-callbacks"))]
+#[test]
+fn blob_callback_so_requires_the_feature() {
+    let repo = init_repo();
+    let err = run_tool(&repo, |o| {
+        o.blob_callback_so = Some(repo.join("libcallback.so"));
+    })
+    .expect_err("--blob-callback-so without the feature");
+    assert!(err.to_string().contains("dynamic-callbacks"), "{err}");
+}
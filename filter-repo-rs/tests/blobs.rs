@@ -19,6 +19,41 @@ fn max_blob_size_drops_large_blobs() {
     assert!(!tree.contains("big.bin"));
 }
 
+#[test]
+fn replace_blobs_with_pointer_keeps_path_with_pointer_text() {
+    for no_data in [true, false] {
+        let repo = init_repo();
+        std::fs::write(repo.join("big.bin"), vec![b'A'; 2 * 1024 * 1024]).unwrap();
+        std::fs::write(repo.join("small.bin"), b"small").unwrap();
+        run_git(&repo, &["add", "."]);
+        assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add blobs"]).0, 0);
+        let (_c, big_oid, _e) = run_git(&repo, &["rev-parse", "HEAD:big.bin"]);
+
+        run_tool_expect_success(&repo, |o| {
+            o.max_blob_size = Some(1024 * 1024);
+            o.replace_blobs_with_pointer = true;
+            o.no_data = no_data;
+        });
+        let (_c, tree, _e) = run_git(&repo, &["ls-tree", "-r", "HEAD"]);
+        assert!(
+            tree.lines()
+                .any(|l| l.starts_with("100644 blob") && l.ends_with("\tbig.bin")),
+            "big.bin should remain as a regular file (no_data={no_data}): {tree}"
+        );
+        let (_c, pointer, _e) = run_git(&repo, &["show", "HEAD:big.bin"]);
+        assert_eq!(
+            pointer,
+            format!(
+                "This file was removed by filter-repo-rs.\nOriginal size: 2097152 bytes\nOriginal OID: {}\n",
+                big_oid.trim()
+            ),
+            "no_data={no_data}"
+        );
+        let (_c, small, _e) = run_git(&repo, &["show", "HEAD:small.bin"]);
+        assert_eq!(small, "small");
+    }
+}
+
 #[test]
 fn max_blob_size_threshold_boundary() {
     let repo = init_repo();
@@ -38,3 +73,93 @@ fn max_blob_size_threshold_boundary() {
     assert!(tree.contains("exact.txt"));
     assert!(!tree.contains("over.txt"));
 }
+
+#[test]
+fn max_blob_size_per_ext_overrides_global_limit() {
+    for no_data in [true, false] {
+        let repo = init_repo();
+        let three_mib = vec![b'Z'; 3 * 1024 * 1024];
+        std::fs::write(repo.join("image.png"), &three_mib).unwrap();
+        std::fs::write(repo.join("archive.zip"), &three_mib).unwrap();
+        std::fs::write(repo.join("notes.txt"), vec![b'n'; 2 * 1024 * 1024]).unwrap();
+        run_git(&repo, &["add", "."]);
+        assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add assets"]).0, 0);
+        run_tool_expect_success(&repo, |o| {
+            o.max_blob_size = Some(1024 * 1024);
+            o.max_blob_size_per_ext = vec![
+                (b"zip".to_vec(), 50 * 1024 * 1024),
+                (b"png".to_vec(), 2 * 1024 * 1024),
+            ];
+            o.no_data = no_data;
+        });
+        let (_c, tree, _e) = run_git(&repo, &["ls-tree", "-r", "--name-only", "HEAD"]);
+        assert!(
+            !tree.contains("image.png"),
+            "png over its 2M limit should be dropped (no_data={no_data}): {tree}"
+        );
+        assert!(
+            tree.contains("archive.zip"),
+            "zip under its 50M limit should survive the 1M global limit (no_data={no_data}): {tree}"
+        );
+        assert!(
+            !tree.contains("notes.txt"),
+            "files without an override use the global limit (no_data={no_data}): {tree}"
+        );
+    }
+}
+
+#[test]
+fn tags_of_stripped_blobs_are_dropped_and_nested_tags_survive() {
+    let repo = init_repo();
+    std::fs::write(repo.join("big.bin"), vec![b'A'; 4096]).unwrap();
+    std::fs::write(repo.join("small.bin"), b"small").unwrap();
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add blobs"]).0, 0);
+    let (_c, big_oid, _e) = run_git(&repo, &["rev-parse", "HEAD:big.bin"]);
+    let (_c, small_oid, _e) = run_git(&repo, &["rev-parse", "HEAD:small.bin"]);
+    for (name, target) in [
+        ("bigtag", big_oid.trim()),
+        ("smalltag", small_oid.trim()),
+        ("v1", "HEAD"),
+    ] {
+        assert_eq!(
+            run_git(&repo, &["tag", "-a", "-m", name, name, target]).0,
+            0,
+            "tag {name}"
+        );
+    }
+    assert_eq!(
+        run_git(&repo, &["tag", "-a", "-m", "nested", "nested", "v1"]).0,
+        0
+    );
+
+    run_tool_expect_success(&repo, |o| {
+        o.max_blob_size = Some(1024);
+        o.no_data = false;
+        o.write_report = true;
+    });
+
+    let (_c, tags, _e) = run_git(&repo, &["tag", "-l"]);
+    assert!(!tags.contains("bigtag"), "tag of a stripped blob: {tags}");
+    let (_c, kind, _e) = run_git(&repo, &["cat-file", "-t", "smalltag^{}"]);
+    assert_eq!(kind.trim(), "blob");
+    let (_c, kind, _e) = run_git(&repo, &["cat-file", "-t", "nested^{tag}"]);
+    assert_eq!(kind.trim(), "tag");
+    let (_c, inner, _e) = run_git(&repo, &["cat-file", "-p", "nested"]);
+    assert!(
+        inner.contains("type tag"),
+        "nested tag should tag a tag: {inner}"
+    );
+    assert_eq!(run_git(&repo, &["fsck", "--no-dangling"]).0, 0);
+
+    let report =
+        std::fs::read_to_string(repo.join(".git").join("filter-repo").join("report.txt")).unwrap();
+    assert!(report.contains("=== Dropped tags ==="), "{report}");
+    assert!(
+        report.contains(&format!(
+            "bigtag (tagged {}, which was stripped)",
+            big_oid.trim()
+        )),
+        "{report}"
+    );
+}
@@ -293,3 +293,53 @@ fn max_blob_size_precise_threshold_handling() {
     assert!(!tree.contains("exactly_101_bytes.txt"));
     assert!(!tree.contains("just_over_100.txt"));
 }
+
+#[test]
+fn strip_blobs_matching_drops_only_matching_version() {
+    let repo = init_repo();
+    for (i, host) in ["localhost", "db.internal.corp", "127.0.0.1"]
+        .iter()
+        .enumerate()
+    {
+        write_file(&repo, "config.ini", &format!("[db]\nhost = {host}\n"));
+        run_git(&repo, &["add", "config.ini"]);
+        run_git(
+            &repo,
+            &["commit", "-q", "-m", &format!("config v{}", i + 1)],
+        );
+    }
+    run_tool_expect_success(&repo, |o| {
+        o.strip_blobs_matching = vec![regex::bytes::Regex::new(r"\.internal\.corp").unwrap()];
+        o.write_report = true;
+    });
+
+    let (_c, history, _e) = run_git(&repo, &["log", "--all", "-p"]);
+    assert!(!history.contains("internal.corp"));
+    let (_c, v1, _e) = run_git(&repo, &["show", "HEAD~2:config.ini"]);
+    assert!(v1.contains("host = localhost"));
+    let (_c, tree, _e) = run_git(&repo, &["ls-tree", "--name-only", "HEAD~1"]);
+    assert!(!tree.lines().any(|p| p == "config.ini"));
+    let (_c, v3, _e) = run_git(&repo, &["show", "HEAD:config.ini"]);
+    assert!(v3.contains("host = 127.0.0.1"));
+
+    let report = std::fs::read_to_string(repo.join(".git/filter-repo/report.txt")).expect("report");
+    assert!(report.contains("Blobs stripped by content: 1"));
+    assert!(report.contains("config.ini (matched \\.internal\\.corp)"));
+}
+
+#[test]
+fn strip_blobs_matching_skips_binary_blobs() {
+    let repo = init_repo();
+    let mut binary = b"\0\x01secret-token".to_vec();
+    binary.extend_from_slice(&[0xff; 64]);
+    std::fs::write(repo.join("blob.bin"), &binary).unwrap();
+    write_file(&repo, "notes.txt", "secret-token");
+    run_git(&repo, &["add", "."]);
+    run_git(&repo, &["commit", "-q", "-m", "add files"]);
+    run_tool_expect_success(&repo, |o| {
+        o.strip_blobs_matching = vec![regex::bytes::Regex::new("secret-token").unwrap()];
+    });
+    let (_c, tree, _e) = run_git(&repo, &["ls-tree", "-r", "--name-only", "HEAD"]);
+    assert!(tree.contains("blob.bin"));
+    assert!(!tree.contains("notes.txt"));
+}
@@ -0,0 +1,115 @@
+mod common;
+use common::*;
+
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use filter_repo_rs::{CancellationToken, FilterRepoError};
+
+/// Adds `count` commits on top of the current branch in one fast-import run.
+fn add_commits(repo: &Path, count: usize) {
+    let branch = current_branch(repo);
+    let mut stream = String::new();
+    for i in 0..count {
+        let content = format!("line {i}\n");
+        stream.push_str(&format!(
+            "commit refs/heads/{branch}\ncommitter A U Thor <a.u.thor@example.com> {} +0000\ndata 10\nsynthetic\n",
+            1_700_000_000 + i
+        ));
+        if i == 0 {
+            stream.push_str(&format!("from refs/heads/{branch}^0\n"));
+        }
+        stream.push_str(&format!(
+            "M 100644 inline file{}.txt\ndata {}\n{content}\n",
+            i % 50,
+            content.len()
+        ));
+    }
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["fast-import", "--quiet"])
+        .stdin(Stdio::piped())
+        .spawn()
+        .expect("spawn git fast-import");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stream.as_bytes())
+        .unwrap();
+    assert!(child.wait().unwrap().success());
+}
+
+fn all_refs(repo: &Path) -> String {
+    run_git(repo, &["for-each-ref", "--format=%(refname) %(objectname)"]).1
+}
+
+/// git processes still running against `repo`, found by command line.
+#[cfg(target_os = "linux")]
+fn git_processes_for(repo: &Path) -> Vec<String> {
+    let needle = repo.to_string_lossy().into_owned();
+    std::fs::read_dir("/proc")
+        .unwrap()
+        .flatten()
+        .filter_map(|entry| std::fs::read(entry.path().join("cmdline")).ok())
+        .map(|raw| String::from_utf8_lossy(&raw).replace('\0', " "))
+        .filter(|cmd| cmd.contains(&needle) && cmd.contains("fast-"))
+        .collect()
+}
+
+#[test]
+fn cancelled_rewrite_leaves_refs_untouched() {
+    let repo = init_repo();
+    add_commits(&repo, 2000);
+    let before = all_refs(&repo);
+
+    let token = CancellationToken::new();
+    let worker = {
+        let repo = repo.clone();
+        let token = token.clone();
+        std::thread::spawn(move || {
+            run_tool(&repo, |o| {
+                o.path_renames.push((Vec::new(), b"moved/".to_vec()));
+                o.cancel = Some(token);
+            })
+        })
+    };
+    // Cancel once the stream is running so the git children are live.
+    let filtered = repo.join(".git/filter-repo/fast-export.filtered");
+    while !filtered.exists() && !worker.is_finished() {
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    token.cancel();
+    let result = worker.join().expect("rewrite thread panicked");
+
+    assert!(
+        matches!(result, Err(FilterRepoError::Cancelled)),
+        "{result:?}"
+    );
+    assert_eq!(all_refs(&repo), before, "refs must not move");
+    #[cfg(target_os = "linux")]
+    assert_eq!(git_processes_for(&repo), Vec::<String>::new());
+    let leftovers: Vec<_> = std::fs::read_dir(repo.join(".git/objects/pack"))
+        .unwrap()
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with("tmp_"))
+        .collect();
+    assert!(leftovers.is_empty(), "{leftovers:?}");
+}
+
+#[test]
+fn cancelled_analysis_returns_cancelled() {
+    let repo = init_repo();
+    let token = CancellationToken::new();
+    token.cancel();
+    let result = run_tool(&repo, |o| {
+        o.mode = filter_repo_rs::Mode::Analyze;
+        o.cancel = Some(token);
+    });
+    assert!(
+        matches!(result, Err(FilterRepoError::Cancelled)),
+        "{result:?}"
+    );
+}
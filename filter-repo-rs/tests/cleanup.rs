@@ -147,3 +147,83 @@ fn cleanup_disabled_on_partial() {
         cmds
     );
 }
+
+#[test]
+fn keep_unreachable_packs_refs_and_never_prunes() {
+    let repo = init_repo();
+    let (out, inv) = run_cli_with_git_spy(&repo, &["--keep-unreachable"]);
+    assert!(out.status.success(), "run should succeed");
+    let cmds = git_commands_for_repo(&repo, &inv);
+
+    assert!(
+        any_cmd_contains_seq(&cmds, &["pack-refs", "--all"]),
+        "expected git pack-refs --all; cmds: {:?}",
+        cmds
+    );
+    assert!(
+        any_cmd_contains_seq(&cmds, &["gc", "--prune=never"]),
+        "expected git gc --prune=never; cmds: {:?}",
+        cmds
+    );
+    assert!(
+        !any_cmd_contains_seq(&cmds, &["gc", "--prune=now"])
+            && !any_cmd_contains_seq(&cmds, &["prune"]),
+        "unreachable objects must not be pruned; cmds: {:?}",
+        cmds
+    );
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(stderr.contains("git gc --prune=<date>"), "{stderr}");
+}
+
+fn total_object_count(repo: &std::path::Path) -> u64 {
+    let (code, out, err) = run_git(repo, &["count-objects", "-v"]);
+    assert_eq!(code, 0, "count-objects failed: {err}");
+    out.lines()
+        .filter_map(|l| {
+            l.strip_prefix("count: ")
+                .or_else(|| l.strip_prefix("in-pack: "))
+        })
+        .map(|n| n.trim().parse::<u64>().unwrap())
+        .sum()
+}
+
+#[test]
+fn keep_unreachable_retains_objects_dropped_from_history() {
+    let run = |keep_unreachable: bool| {
+        let repo = init_repo();
+        write_file(&repo, "big.bin", &"x".repeat(4096));
+        assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+        assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add big"]).0, 0);
+        run_tool_expect_success(&repo, |o| {
+            o.max_blob_size = Some(1024);
+            o.cleanup = filter_repo_rs::opts::CleanupMode::Standard;
+            o.keep_unreachable = keep_unreachable;
+        });
+        let (_c, files, _e) = run_git(&repo, &["ls-tree", "-r", "--name-only", "HEAD"]);
+        assert!(!files.contains("big.bin"), "{files}");
+        total_object_count(&repo)
+    };
+
+    let pruned = run(false);
+    let kept = run(true);
+    assert!(
+        kept > pruned,
+        "expected more objects with --keep-unreachable: kept {kept}, pruned {pruned}"
+    );
+}
+
+#[test]
+fn keep_unreachable_is_rejected_with_sensitive() {
+    let repo = init_repo();
+    let output = cli_command()
+        .current_dir(&repo)
+        .args(["--keep-unreachable", "--sensitive", "--no-fetch", "--force"])
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--keep-unreachable cannot be combined with --sensitive"),
+        "{stderr}"
+    );
+}
@@ -226,7 +226,7 @@ fn max_blob_size_accepts_size_suffixes() {
 
 #[test]
 fn max_blob_size_rejects_invalid_suffix() {
-    let invalid_cases = &["10T", "1.5M", "K", ""];
+    let invalid_cases = &["10T", "1.5M", "K", "", "0", "-1"];
 
     for &case in invalid_cases {
         let output = cli_command()
@@ -249,6 +249,11 @@ fn max_blob_size_rejects_invalid_suffix() {
             case,
             stderr
         );
+        assert!(
+            stderr.matches("suffixed with K, M, or G").count() <= 1,
+            "size hint should appear at most once; got: {}",
+            stderr
+        );
     }
 }
 
@@ -770,3 +775,45 @@ fn detect_pattern_runtime_failure_keeps_cause_chain() {
         stderr
     );
 }
+
+#[test]
+fn strip_blobs_bigger_than_and_per_ext_flags_parse() {
+    let output = cli_command()
+        .args(["--strip-blobs-bigger-than", "10M"])
+        .args(["--max-blob-size-per-ext", "zip=50M,png=2M"])
+        .arg("--help")
+        .output()
+        .expect("run filter-repo-rs with size flags");
+    assert!(
+        output.status.success(),
+        "size flags should parse: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let output = cli_command()
+        .args(["--max-blob-size-per-ext", "png=2MB"])
+        .arg("--help")
+        .output()
+        .expect("run filter-repo-rs with invalid per-ext size");
+    assert!(!output.status.success(), "png=2MB should be rejected");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--max-blob-size-per-ext"),
+        "expected per-ext parse error; got: {stderr}"
+    );
+}
+
+#[test]
+fn pruned_tag_rejects_unknown_policies() {
+    let output = cli_command()
+        .arg("--pruned-tag=move")
+        .output()
+        .expect("run filter-repo-rs --pruned-tag=move");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--pruned-tag: expected delete|ancestor|keep-old, got 'move'"),
+        "{stderr}"
+    );
+}
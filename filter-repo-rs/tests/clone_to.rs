@@ -0,0 +1,96 @@
+mod common;
+use common::*;
+
+use std::path::{Path, PathBuf};
+
+/// `secret.txt` is added in one commit and deleted in the next.
+fn repo_with_deleted_secret() -> (PathBuf, String) {
+    let repo = init_repo();
+    write_file(&repo, "secret.txt", "hunter2\n");
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add secret"]).0, 0);
+    let blob = run_git(&repo, &["rev-parse", "HEAD:secret.txt"])
+        .1
+        .trim()
+        .to_string();
+    assert_eq!(run_git(&repo, &["rm", "-q", "secret.txt"]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "drop secret"]).0, 0);
+    (repo, blob)
+}
+
+fn has_object(repo: &Path, oid: &str) -> bool {
+    run_git(repo, &["cat-file", "-e", oid]).0 == 0
+}
+
+fn clone_path() -> PathBuf {
+    mktemp("fr_rs_clone_to").join("clone.git")
+}
+
+#[test]
+fn clone_to_filters_a_no_local_mirror_and_leaves_the_source_alone() {
+    let (repo, blob) = repo_with_deleted_secret();
+    let refs_before = run_git(&repo, &["show-ref"]).1;
+    let clone = clone_path();
+
+    // No --force: a fresh mirror clone passes the freshness checks.
+    let output = cli_command()
+        .current_dir(&repo)
+        .args(["--path", "secret.txt", "--invert-paths", "--clone-to"])
+        .arg(&clone)
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("Cloned "),
+        "the clone is reported through diagnostics: {output:?}"
+    );
+
+    assert_eq!(run_git(&repo, &["show-ref"]).1, refs_before);
+    assert!(has_object(&repo, &blob));
+
+    assert!(!has_object(&clone, &blob));
+    let (_c, log, _e) = run_git(&clone, &["log", "--all", "--format=%s", "--name-only"]);
+    assert!(!log.contains("secret.txt"), "{log}");
+    assert!(log.contains("init commit"), "{log}");
+    // Nothing links the clone back to the source's object store.
+    assert!(!clone.join("objects/info/alternates").exists());
+}
+
+#[test]
+fn clone_to_refuses_an_existing_path() {
+    let (repo, _blob) = repo_with_deleted_secret();
+    let existing = mktemp("fr_rs_clone_to");
+    std::fs::create_dir_all(&existing).unwrap();
+    let output = cli_command()
+        .current_dir(&repo)
+        .arg("--clone-to")
+        .arg(&existing)
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("already exists"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn clone_local_requires_clone_to() {
+    let (repo, _blob) = repo_with_deleted_secret();
+    let output = cli_command()
+        .current_dir(&repo)
+        .args(["--force", "--clone-local"])
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("--clone-local requires --clone-to"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
@@ -81,6 +81,10 @@ pub fn github_pat() -> String {
     concat(&["ghp_", "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefgh"])
 }
 
+pub fn github_token_40() -> String {
+    concat(&["ghp_", &"A".repeat(40)])
+}
+
 pub fn slack_token() -> String {
     concat(&[
         "xoxb",
@@ -69,6 +69,26 @@ fn detect_secrets_reports_zero_when_no_matches() {
     );
 }
 
+#[test]
+fn help_patterns_lists_built_in_patterns_and_exits() {
+    let repo = init_repo();
+    let output = cli_command()
+        .arg("--help-patterns")
+        .current_dir(&repo)
+        .output()
+        .expect("run --help-patterns");
+
+    assert!(output.status.success(), "--help-patterns should succeed");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for expected in ["Example Match", "aws_access_key_id", "github_token"] {
+        assert!(
+            stdout.contains(expected),
+            "expected {expected} in pattern table: {stdout}"
+        );
+    }
+    assert!(!repo.join("detected-secrets.txt").exists());
+}
+
 #[test]
 fn detect_secrets_supports_custom_detect_pattern() {
     let repo = init_repo();
@@ -105,6 +125,98 @@ fn detect_secrets_supports_custom_detect_pattern() {
     );
 }
 
+#[test]
+fn detect_patterns_file_rules_are_named_in_the_draft() {
+    let repo = init_repo();
+    let aws_access_key_id = fake_secrets::aws_access_key_id();
+    write_file(
+        &repo,
+        "config.ini",
+        &format!(
+            "ticket_token=TKT-0123456789\ndeploy_secret: swordfish42\naws={aws_access_key_id}\n"
+        ),
+    );
+    run_git(&repo, &["add", "."]);
+    run_git(&repo, &["commit", "-q", "-m", "add team secrets"]);
+
+    let rules_dir = mktemp("fr_rs_detect_rules");
+    std::fs::create_dir_all(&rules_dir).expect("create rules dir");
+    let rules_file = rules_dir.join("rules.txt");
+    std::fs::write(
+        &rules_file,
+        "# team-specific rules\n\
+ticket_token\thigh\tTKT-[0-9]{10}\n\
+deploy_password==>deploy_(secret|password): ([a-z0-9]{8,}) group=2\n",
+    )
+    .expect("write rules file");
+
+    let detect = |no_builtin: bool| {
+        let mut cmd = cli_command();
+        cmd.arg("--detect-secrets")
+            .arg("--detect-patterns-file")
+            .arg(&rules_file);
+        if no_builtin {
+            cmd.arg("--detect-no-builtin");
+        }
+        let output = cmd
+            .arg("--dry-run")
+            .current_dir(&repo)
+            .output()
+            .expect("run detect-secrets with a patterns file");
+        assert!(
+            output.status.success(),
+            "stderr: {}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+        std::fs::read_to_string(repo.join("detected-secrets.txt")).expect("read draft")
+    };
+
+    let content = detect(false);
+    assert!(
+        content.contains("# ticket_token [high] @ config.ini"),
+        "{content}"
+    );
+    assert!(
+        content.contains("# deploy_password @ config.ini"),
+        "{content}"
+    );
+    assert!(content.contains(&fake_secrets::removed_rule("TKT-0123456789")));
+    assert!(content.contains(&fake_secrets::removed_rule("swordfish42")));
+    assert!(content.contains(&fake_secrets::removed_rule(&aws_access_key_id)));
+
+    let content = detect(true);
+    assert!(content.contains("ticket_token"), "{content}");
+    assert!(
+        !content.contains(&aws_access_key_id),
+        "built-in patterns should be off: {content}"
+    );
+}
+
+#[test]
+fn detect_patterns_file_reports_the_bad_line() {
+    let repo = init_repo();
+    let rules_dir = mktemp("fr_rs_detect_rules");
+    std::fs::create_dir_all(&rules_dir).expect("create rules dir");
+    let rules_file = rules_dir.join("rules.txt");
+    std::fs::write(&rules_file, "ok==>abc\nbroken==>(unclosed\n").expect("write rules");
+
+    let output = cli_command()
+        .arg("--detect-secrets")
+        .arg("--detect-patterns-file")
+        .arg(&rules_file)
+        .arg("--dry-run")
+        .current_dir(&repo)
+        .output()
+        .expect("run detect-secrets with a bad patterns file");
+
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("rules.txt:2: invalid regex for rule 'broken'"),
+        "{stderr}"
+    );
+}
+
 #[test]
 fn detect_secrets_detects_openai_api_key() {
     let repo = init_repo();
@@ -290,3 +402,208 @@ QWEN_API_KEY={qwen_api_key}\n"
         content
     );
 }
+
+fn commit_secret_and_innocent_copy(repo: &std::path::Path, secret: &str) {
+    write_file(repo, "config/prod.env", &format!("password={secret}\n"));
+    write_file(
+        repo,
+        "docs/example.txt",
+        &format!("The string {secret} is quoted here on purpose.\n"),
+    );
+    run_git(repo, &["add", "."]);
+    run_git(repo, &["commit", "-q", "-m", "add prod config"]);
+    write_file(
+        repo,
+        "config/prod.env",
+        &format!("password={secret}\nregion=eu\n"),
+    );
+    run_git(repo, &["add", "."]);
+    run_git(repo, &["commit", "-q", "-m", "add region"]);
+}
+
+fn history_contents(repo: &std::path::Path, path: &str) -> String {
+    let (_c, out, _e) = run_git(repo, &["log", "--all", "-p", "--format=", "--", path]);
+    out
+}
+
+fn run_detect_with_emit(repo: &std::path::Path, mode: &str) {
+    let output = cli_command()
+        .arg("--detect-secrets")
+        .arg(format!("--detect-emit={mode}"))
+        .arg("--dry-run")
+        .current_dir(repo)
+        .output()
+        .expect("run detect-secrets mode");
+    assert!(
+        output.status.success(),
+        "detect-secrets --detect-emit={mode} should succeed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn detect_emit_scoped_rules_only_redact_detected_path() {
+    let repo = init_repo();
+    let secret = fake_secrets::super_secret_123();
+    commit_secret_and_innocent_copy(&repo, &secret);
+
+    run_detect_with_emit(&repo, "scoped");
+
+    let draft = repo.join("detected-secrets.txt");
+    let content = std::fs::read_to_string(&draft).expect("read detected-secrets.txt");
+    assert!(
+        content.contains(&format!("path:config/prod.env:{secret}==>***REMOVED***")),
+        "scoped draft should pin the rule to config/prod.env: {content}"
+    );
+    assert!(
+        !content.contains(&format!("\n{secret}==>")),
+        "scoped draft should not contain a global rule: {content}"
+    );
+
+    let rules_dir = mktemp("fr_rs_scoped_rules");
+    std::fs::create_dir_all(&rules_dir).expect("create rules dir");
+    let rules = rules_dir.join("rules.txt");
+    std::fs::rename(&draft, &rules).expect("move draft out of the worktree");
+    run_tool_expect_success(&repo, |o| {
+        o.replace_text_file = Some(rules.clone());
+    });
+
+    let prod = history_contents(&repo, "config/prod.env");
+    assert!(!prod.contains(&secret), "secret should be gone: {prod}");
+    assert!(prod.contains("***REMOVED***"), "redaction expected: {prod}");
+    let docs = history_contents(&repo, "docs/example.txt");
+    assert!(
+        docs.contains(&secret),
+        "innocent copy in another file must be untouched: {docs}"
+    );
+}
+
+#[test]
+fn detect_emit_json_round_trips_through_replace_text() {
+    let repo = init_repo();
+    let secret = fake_secrets::super_secret_123();
+    commit_secret_and_innocent_copy(&repo, &secret);
+
+    run_detect_with_emit(&repo, "json");
+
+    let draft = repo.join("detected-secrets.json");
+    let doc: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&draft).expect("read detected-secrets.json"))
+            .expect("draft should be valid JSON");
+    let rules = doc["rules"].as_array().expect("rules array");
+    let rule = rules
+        .iter()
+        .find(|r| r["value"] == secret.as_str())
+        .expect("rule for the detected secret");
+    assert_eq!(rule["paths"], serde_json::json!(["config/prod.env"]));
+
+    let rules_dir = mktemp("fr_rs_json_rules");
+    std::fs::create_dir_all(&rules_dir).expect("create rules dir");
+    let rules_path = rules_dir.join("rules.json");
+    std::fs::rename(&draft, &rules_path).expect("move draft out of the worktree");
+    run_tool_expect_success(&repo, |o| {
+        o.replace_text_file = Some(rules_path.clone());
+    });
+
+    assert!(!history_contents(&repo, "config/prod.env").contains(&secret));
+    assert!(history_contents(&repo, "docs/example.txt").contains(&secret));
+}
+
+#[test]
+fn detect_scan_messages_reports_secrets_in_commit_messages() {
+    let repo = init_repo();
+    let aws_access_key_id = fake_secrets::aws_access_key_id();
+    run_git(
+        &repo,
+        &[
+            "commit",
+            "-q",
+            "--allow-empty",
+            "-m",
+            &format!("fix: key {aws_access_key_id} was causing the bug"),
+        ],
+    );
+
+    let run_detect = |extra: &[&str]| {
+        let output = cli_command()
+            .arg("--detect-secrets")
+            .args(extra)
+            .arg("--dry-run")
+            .current_dir(&repo)
+            .output()
+            .expect("run detect-secrets mode");
+        assert!(output.status.success(), "detect-secrets should succeed");
+        std::fs::read_to_string(repo.join("detected-secrets.txt"))
+            .expect("read detected-secrets.txt")
+    };
+
+    let blobs_only = run_detect(&[]);
+    assert!(
+        !blobs_only.contains(&aws_access_key_id),
+        "commit messages are not scanned by default: {blobs_only}"
+    );
+
+    let content = run_detect(&["--detect-scan-messages"]);
+    assert!(
+        content.contains(&fake_secrets::removed_rule(&aws_access_key_id)),
+        "draft should include the key from the commit message: {content}"
+    );
+    assert!(
+        content.contains("@ <commit-message> (commit:"),
+        "message detections should carry a commit: oid: {content}"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn sensitive_redact_paths_renames_files_named_after_secrets() {
+    let repo = init_repo();
+    let token = fake_secrets::github_token_40();
+    let secret_path = format!("apikey_{token}.json");
+    write_file(&repo, &secret_path, "{\"scope\": \"repo\"}\n");
+    run_git(&repo, &["add", "."]);
+    run_git(&repo, &["commit", "-q", "-m", "add api key file"]);
+
+    let output = cli_command()
+        .args(["--detect-secrets", "--sensitive-redact-paths"])
+        .args(["--detect-pattern", "ghp_[A-Za-z0-9]{36,}"])
+        .current_dir(&repo)
+        .output()
+        .expect("run detect-secrets with path scanning");
+    assert!(output.status.success(), "detect-secrets should succeed");
+    let draft = repo.join("detected-secrets.txt");
+    let content = std::fs::read_to_string(&draft).expect("read detected-secrets.txt");
+    assert!(
+        content.contains(&fake_secrets::removed_rule(&token)),
+        "draft should include the token found in the path name: {content}"
+    );
+    assert!(content.contains("--sensitive-redact-paths"));
+
+    run_tool_expect_success(&repo, |o| {
+        o.replace_text_file = Some(draft.clone());
+        o.redact_paths = true;
+    });
+    let (_c, tree, _e) = run_git(&repo, &["ls-tree", "-r", "--name-only", "HEAD"]);
+    assert!(
+        tree.lines().any(|p| p == "apikey_***REMOVED***.json"),
+        "expected redacted file name, got:\n{tree}"
+    );
+    let (_c, all_paths, _e) = run_git(&repo, &["log", "--all", "--format=", "--name-only"]);
+    assert!(!all_paths.contains(&token));
+}
+
+#[test]
+fn sensitive_redact_paths_requires_replace_text() {
+    let repo = init_repo();
+    let output = cli_command()
+        .arg("--sensitive-redact-paths")
+        .current_dir(&repo)
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--sensitive-redact-paths requires --replace-text"),
+        "unexpected stderr: {stderr}"
+    );
+}
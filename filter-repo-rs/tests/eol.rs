@@ -0,0 +1,93 @@
+mod common;
+use common::*;
+
+use filter_repo_rs::opts::{EolNormalization, EolTarget};
+use std::path::{Path, PathBuf};
+
+const BINARY: &[u8] = b"\x00\x01\r\n\x02\r\n";
+
+/// One commit holding CRLF, LF, mixed, lone-CR and binary files.
+fn repo_with_line_endings() -> PathBuf {
+    let repo = init_repo();
+    std::fs::write(repo.join("crlf.txt"), b"a\r\nb\r\n").unwrap();
+    std::fs::write(repo.join("lf.txt"), b"a\nb\n").unwrap();
+    std::fs::write(repo.join("mixed.txt"), b"a\r\nb\nc\r\n").unwrap();
+    std::fs::write(repo.join("mac.txt"), b"a\rb\r").unwrap();
+    std::fs::write(repo.join("run.bat"), b"echo\r\n").unwrap();
+    std::fs::write(repo.join("image.bin"), BINARY).unwrap();
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "line endings"]).0, 0);
+    repo
+}
+
+fn blob(repo: &Path, path: &str) -> Vec<u8> {
+    let out = std::process::Command::new("git")
+        .current_dir(repo)
+        .args(["cat-file", "blob", &format!("HEAD:{path}")])
+        .output()
+        .expect("git cat-file");
+    assert!(out.status.success(), "missing {path}");
+    out.stdout
+}
+
+fn normalize(repo: &Path, target: EolTarget, globs: &[&str]) -> serde_json::Value {
+    run_tool_expect_success(repo, |o| {
+        o.normalize_eol = Some(EolNormalization {
+            target,
+            globs: globs.iter().map(|g| g.as_bytes().to_vec()).collect(),
+        });
+        o.write_report_json = true;
+    });
+    let json = std::fs::read_to_string(repo.join(".git/filter-repo/report.json"))
+        .expect("read report.json");
+    let report: serde_json::Value = serde_json::from_str(&json).expect("valid json");
+    report["eol_normalization"].clone()
+}
+
+#[test]
+fn normalize_eol_lf_converts_text_and_is_idempotent() {
+    let repo = repo_with_line_endings();
+
+    let report = normalize(&repo, EolTarget::Lf, &[]);
+    assert_eq!(blob(&repo, "crlf.txt"), b"a\nb\n");
+    assert_eq!(blob(&repo, "lf.txt"), b"a\nb\n");
+    assert_eq!(blob(&repo, "mixed.txt"), b"a\nb\nc\n");
+    assert_eq!(blob(&repo, "run.bat"), b"echo\n");
+    assert_eq!(blob(&repo, "mac.txt"), b"a\rb\r");
+    assert_eq!(blob(&repo, "image.bin"), BINARY);
+    assert_eq!(report["blobs_converted"], 3, "{report}");
+    assert_eq!(report["bytes_removed"], 5, "{report}");
+    assert_eq!(report["skipped_suspicious_eol"], 1, "{report}");
+
+    let head = run_git(&repo, &["rev-parse", "HEAD"]).1;
+    let report = normalize(&repo, EolTarget::Lf, &[]);
+    assert_eq!(report["blobs_converted"], 0, "{report}");
+    assert_eq!(report["bytes_removed"], 0, "{report}");
+    assert_eq!(run_git(&repo, &["rev-parse", "HEAD"]).1, head);
+}
+
+#[test]
+fn normalize_eol_glob_limits_the_paths() {
+    let repo = repo_with_line_endings();
+
+    let report = normalize(&repo, EolTarget::Lf, &["*.txt"]);
+    assert_eq!(blob(&repo, "crlf.txt"), b"a\nb\n");
+    assert_eq!(blob(&repo, "mixed.txt"), b"a\nb\nc\n");
+    assert_eq!(blob(&repo, "run.bat"), b"echo\r\n");
+    assert_eq!(report["blobs_converted"], 2, "{report}");
+}
+
+#[test]
+fn normalize_eol_crlf_converts_bare_lf() {
+    let repo = repo_with_line_endings();
+
+    let report = normalize(&repo, EolTarget::Crlf, &[]);
+    assert_eq!(blob(&repo, "crlf.txt"), b"a\r\nb\r\n");
+    assert_eq!(blob(&repo, "lf.txt"), b"a\r\nb\r\n");
+    assert_eq!(blob(&repo, "mixed.txt"), b"a\r\nb\r\nc\r\n");
+    assert_eq!(blob(&repo, "mac.txt"), b"a\rb\r");
+    assert_eq!(blob(&repo, "image.bin"), BINARY);
+    assert_eq!(report["blobs_converted"], 2, "{report}");
+    assert_eq!(report["bytes_added"], 3, "{report}");
+    assert_eq!(report["skipped_suspicious_eol"], 1, "{report}");
+}
@@ -305,3 +305,145 @@ fn error_handling_extremely_long_paths() {
         "expected extremely long paths to trigger an error"
     );
 }
+
+fn write_hostile_stream(repo: &std::path::Path) -> std::path::PathBuf {
+    let stream_path = repo.join("fe-hostile.stream");
+    let stream = r#"blob
+mark :1
+data 6
+owned
+
+commit refs/heads/main
+mark :2
+author Tester <tester@example.com> 0 +0000
+committer Tester <tester@example.com> 0 +0000
+data 7
+escape
+M 100644 :1 ../../outside.txt
+M 100644 :1 inside.txt
+
+done
+"#;
+    std::fs::write(&stream_path, stream).expect("write hostile stream");
+    stream_path
+}
+
+#[test]
+fn error_handling_unsafe_path_in_stream_aborts_naming_the_path() {
+    let repo = init_repo();
+    let stream_path = write_hostile_stream(&repo);
+    let head_before = run_git(&repo, &["rev-parse", "HEAD"]).1;
+
+    let result = run_tool(&repo, |o| {
+        o.debug_mode = true;
+        #[allow(deprecated)]
+        {
+            o.fe_stream_override = Some(stream_path.clone());
+        }
+    });
+    let err = result.expect_err("unsafe stream path must abort the run");
+    let msg = format!("{err}");
+    assert!(msg.contains("../../outside.txt"), "{msg}");
+    assert!(msg.contains("'..' segment"), "{msg}");
+    assert_eq!(run_git(&repo, &["rev-parse", "HEAD"]).1, head_before);
+    assert!(!repo.parent().unwrap().join("outside.txt").exists());
+}
+
+#[test]
+fn error_handling_unsafe_path_in_stream_can_be_skipped() {
+    let repo = init_repo();
+    let stream_path = write_hostile_stream(&repo);
+
+    run_tool_expect_success(&repo, |o| {
+        o.debug_mode = true;
+        o.dry_run = true;
+        o.unsafe_path_policy = fr::pathutil::UnsafePathPolicy::Skip;
+        #[allow(deprecated)]
+        {
+            o.fe_stream_override = Some(stream_path.clone());
+        }
+    });
+    let filtered = std::fs::read_to_string(repo.join(".git/filter-repo/fast-export.filtered"))
+        .expect("read filtered stream");
+    assert!(!filtered.contains("outside.txt"), "{filtered}");
+    assert!(filtered.contains("M 100644 :1 inside.txt\n"), "{filtered}");
+}
+
+#[test]
+fn skipped_stream_paths_are_logged_to_path_events_jsonl() {
+    let repo = init_repo();
+    let stream_path = repo.join("fe-hostile-events.stream");
+    let first = "1111111111111111111111111111111111111111";
+    let second = "2222222222222222222222222222222222222222";
+    let stream = format!(
+        "blob\nmark :1\ndata 6\nowned\n\n\
+commit refs/heads/main\nmark :2\noriginal-oid {first}\n\
+author Tester <tester@example.com> 0 +0000\ncommitter Tester <tester@example.com> 0 +0000\n\
+data 4\none\nM 100644 :1 ../outside.txt\nM 100644 :1 inside.txt\n\n\
+commit refs/heads/main\nmark :3\noriginal-oid {second}\n\
+author Tester <tester@example.com> 1 +0000\ncommitter Tester <tester@example.com> 1 +0000\n\
+data 4\ntwo\nfrom :2\nM 100644 :1 /etc/absolute.txt\n\ndone\n"
+    );
+    std::fs::write(&stream_path, stream).expect("write hostile stream");
+
+    run_tool_expect_success(&repo, |o| {
+        o.debug_mode = true;
+        o.dry_run = true;
+        o.unsafe_path_policy = fr::pathutil::UnsafePathPolicy::Skip;
+        #[allow(deprecated)]
+        {
+            o.fe_stream_override = Some(stream_path.clone());
+        }
+    });
+    let log = std::fs::read_to_string(repo.join(".git/filter-repo/path-events.jsonl"))
+        .expect("read path-events.jsonl");
+    let records: Vec<serde_json::Value> = log
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("valid JSON line"))
+        .collect();
+    assert_eq!(records.len(), 2, "{log}");
+    assert_eq!(records[0]["action"], "skipped");
+    assert_eq!(records[0]["original"], "../outside.txt");
+    assert_eq!(records[0]["rewritten"], serde_json::Value::Null);
+    assert_eq!(records[0]["commit"], first);
+    assert!(records[0]["reason"]
+        .as_str()
+        .is_some_and(|r| r.contains("'..' segment")));
+    assert_eq!(records[1]["original"], "/etc/absolute.txt");
+    assert_eq!(records[1]["commit"], second);
+}
+
+#[test]
+fn error_handling_leading_parent_segment_in_rename_regex_is_rejected_up_front() {
+    let repo = init_repo();
+    let head_before = run_git(&repo, &["rev-parse", "HEAD"]).1;
+    let output = cli_command()
+        .current_dir(&repo)
+        .args([
+            "--path-rename-regex",
+            "^README.md$==>../README.md",
+            "--force",
+        ])
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("'..' segments are not allowed"), "{stderr}");
+    assert_eq!(run_git(&repo, &["rev-parse", "HEAD"]).1, head_before);
+}
+
+#[test]
+fn error_handling_unsafe_path_from_rename_names_the_rename_options() {
+    let repo = init_repo();
+    let head_before = run_git(&repo, &["rev-parse", "HEAD"]).1;
+    let output = cli_command()
+        .current_dir(&repo)
+        .args(["--path-rename-regex", "^README.md$==>docs/..", "--force"])
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("refusing to write unsafe path"), "{stderr}");
+    assert!(stderr.contains("--path-rename-regex"), "{stderr}");
+    assert_eq!(run_git(&repo, &["rev-parse", "HEAD"]).1, head_before);
+}
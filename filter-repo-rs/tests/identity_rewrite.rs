@@ -148,3 +148,369 @@ fn mailmap_takes_precedence_over_other_identity_rewriters() {
         "mailmap should take precedence over other identity rewriters"
     );
 }
+
+#[test]
+fn mailmap_rule_hits_are_summarized_in_report() {
+    let repo = init_repo();
+    commit_with_identity(
+        &repo,
+        FileCommit {
+            rel_path: "report-target.txt",
+            contents: "payload",
+            message: "commit for mailmap report",
+        },
+        Identity {
+            name: "Old Author",
+            email: "old@example.com",
+        },
+        Identity {
+            name: "Old Committer",
+            email: "old@example.com",
+        },
+    );
+
+    let mailmap = repo.join("report.mailmap");
+    std::fs::write(
+        &mailmap,
+        "Canonical Name <canonical@example.com> <old@example.com>\n\
+         Typo Name <typo@example.com> <tpyo@example.com>\n",
+    )
+    .expect("write mailmap rules");
+
+    run_tool_expect_success(&repo, |o| {
+        o.mailmap_file = Some(mailmap.clone());
+        o.no_data = true;
+        o.write_report = true;
+        o.write_report_json = true;
+    });
+
+    let debug_dir = repo.join(".git").join("filter-repo");
+    let report = std::fs::read_to_string(debug_dir.join("report.txt")).expect("read report.txt");
+    assert!(report.contains("=== Identity rewrites ==="), "{report}");
+    assert!(
+        report.contains("Distinct identities rewritten: 2"),
+        "{report}"
+    );
+    assert!(report.contains("Rules with no matches: 1"), "{report}");
+    let matched = report
+        .lines()
+        .find(|l| l.contains("<old@example.com>"))
+        .expect("matching rule listed");
+    assert!(
+        matched.contains("author: 1, committer: 1, tagger: 0") && !matched.contains("NO MATCHES"),
+        "{matched}"
+    );
+    let unmatched = report
+        .lines()
+        .find(|l| l.contains("<tpyo@example.com>"))
+        .expect("non-matching rule listed");
+    assert!(unmatched.contains("[NO MATCHES]"), "{unmatched}");
+
+    let json: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(debug_dir.join("report.json")).expect("read report.json"),
+    )
+    .expect("parse report.json");
+    let rewrites = &json["identity_rewrites"];
+    assert_eq!(rewrites["zero_hit_rules"], 1);
+    assert_eq!(rewrites["rules"][0]["author_hits"], 1);
+    assert_eq!(rewrites["rules"][0]["committer_hits"], 1);
+    assert_eq!(rewrites["rules"][1]["author_hits"], 0);
+}
+
+#[test]
+fn mailmap_rewrites_annotated_tag_tagger() {
+    let repo = init_repo();
+    let output = Command::new("git")
+        .current_dir(&repo)
+        .env("GIT_COMMITTER_NAME", "Old Tagger")
+        .env("GIT_COMMITTER_EMAIL", "old@example.com")
+        .args(["tag", "-a", "v1.0", "-m", "release notes"])
+        .output()
+        .expect("run git tag");
+    assert!(output.status.success(), "{output:?}");
+    let target_before = run_git(&repo, &["rev-parse", "v1.0^{commit}"]).1;
+
+    let mailmap = repo.join("tags.mailmap");
+    std::fs::write(&mailmap, "New Tagger <new@example.com> <old@example.com>\n")
+        .expect("write mailmap rules");
+
+    run_tool_expect_success(&repo, |o| {
+        o.mailmap_file = Some(mailmap.clone());
+        o.write_report = true;
+    });
+
+    let (_c, tag, _e) = run_git(&repo, &["cat-file", "-p", "refs/tags/v1.0"]);
+    assert!(
+        tag.contains("\ntagger New Tagger <new@example.com> "),
+        "{tag}"
+    );
+    assert!(!tag.contains("old@example.com"), "{tag}");
+    assert!(tag.ends_with("\nrelease notes\n"), "{tag}");
+    assert_eq!(
+        run_git(&repo, &["rev-parse", "v1.0^{commit}"]).1,
+        target_before
+    );
+
+    let report =
+        std::fs::read_to_string(repo.join(".git/filter-repo/report.txt")).expect("read report.txt");
+    assert!(
+        report.contains("author: 0, committer: 0, tagger: 1"),
+        "{report}"
+    );
+}
+
+#[test]
+fn committer_date_is_author_date_normalizes_committer_timestamps() {
+    let repo = init_repo();
+    write_file(&repo, "rebased.txt", "picked\n");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(
+        run_git(
+            &repo,
+            &[
+                "commit",
+                "-q",
+                "-m",
+                "cherry-picked",
+                "--date",
+                "2001-02-03T04:05:06+0130"
+            ]
+        )
+        .0,
+        0
+    );
+    let dates =
+        |repo: &std::path::Path| run_git(repo, &["log", "-1", "--format=%ad|%cd", "--date=raw"]).1;
+    let before = dates(&repo);
+    let (author, committer) = before.trim().split_once('|').unwrap();
+    assert_ne!(author, committer);
+
+    let clone = mktemp("fr_rs_dates_clone");
+    let (code, _, err) = run_git(
+        &repo,
+        &[
+            "clone",
+            "-q",
+            repo.to_str().unwrap(),
+            clone.to_str().unwrap(),
+        ],
+    );
+    assert_eq!(code, 0, "{err}");
+
+    run_tool_expect_success(&repo, |o| o.committer_date_is_author_date = true);
+    assert_eq!(dates(&repo).trim(), format!("{author}|{author}"));
+
+    run_tool_expect_success(&clone, |o| {
+        o.author_date_is_committer_date = true;
+        o.force = true;
+    });
+    assert_eq!(dates(&clone).trim(), format!("{committer}|{committer}"));
+}
+
+fn identity_fields(repo: &std::path::Path, format: &str) -> Vec<String> {
+    run_git(
+        repo,
+        &["log", "-1", &format!("--format={format}"), "--date=raw"],
+    )
+    .1
+    .trim()
+    .split('|')
+    .map(str::to_string)
+    .collect()
+}
+
+fn legacy_commit(repo: &std::path::Path) {
+    commit_with_identity(
+        repo,
+        FileCommit {
+            rel_path: "stamped.txt",
+            contents: "payload",
+            message: "commit to stamp",
+        },
+        Identity {
+            name: "Old Author",
+            email: "old@example.com",
+        },
+        Identity {
+            name: "Old Committer",
+            email: "committer@example.com",
+        },
+    );
+}
+
+#[test]
+fn override_committer_keeps_author_and_committer_date() {
+    let repo = init_repo();
+    legacy_commit(&repo);
+    let before = identity_fields(&repo, "%an <%ae>|%ad|%cd");
+    let commits: usize = run_git(&repo, &["rev-list", "--count", "HEAD"])
+        .1
+        .trim()
+        .parse()
+        .unwrap();
+
+    run_tool_expect_success(&repo, |o| {
+        o.override_committer = Some(b"History Rewrite Bot <bot@corp>".to_vec());
+        o.write_report = true;
+        o.write_report_json = true;
+    });
+
+    let after = identity_fields(&repo, "%an <%ae>|%ad|%cn <%ce>|%cd");
+    assert_eq!(after[0], "Old Author <old@example.com>");
+    assert_eq!(after[1], before[1], "author date untouched");
+    assert_eq!(after[2], "History Rewrite Bot <bot@corp>");
+    assert_eq!(after[3], before[2], "committer date kept by default");
+    let committers = run_git(&repo, &["log", "--format=%ce"]).1;
+    assert!(committers.lines().all(|c| c == "bot@corp"), "{committers}");
+
+    let debug_dir = repo.join(".git").join("filter-repo");
+    let report = std::fs::read_to_string(debug_dir.join("report.txt")).expect("read report.txt");
+    assert!(
+        report.contains(&format!(
+            "committer: History Rewrite Bot <bot@corp> (date: keep): {commits} line(s) stamped"
+        )),
+        "{report}"
+    );
+    let json: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(debug_dir.join("report.json")).expect("read report.json"),
+    )
+    .expect("parse report.json");
+    assert_eq!(json["identity_overrides"][0]["header"], "committer");
+    assert_eq!(json["identity_overrides"][0]["stamped"], commits);
+}
+
+#[test]
+fn override_committer_date_now_stamps_the_run_time() {
+    let repo = init_repo();
+    legacy_commit(&repo);
+    let author_date = identity_fields(&repo, "%ad")[0].clone();
+    let started = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+
+    let output = cli_command()
+        .current_dir(&repo)
+        .arg("--force")
+        .args([
+            "--override-committer",
+            "Rewrite Bot <bot@corp>",
+            "--override-committer-date",
+            "now",
+        ])
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(output.status.success(), "{output:?}");
+
+    let after = identity_fields(&repo, "%ad|%cd");
+    assert_eq!(after[0], author_date, "author date untouched");
+    let (ts, tz) = after[1].split_once(' ').unwrap();
+    assert!(ts.parse::<u64>().unwrap() >= started, "{}", after[1]);
+    assert_eq!(tz, "+0000");
+}
+
+#[test]
+fn override_committer_counts_only_emitted_commits() {
+    let repo = init_repo();
+    legacy_commit(&repo);
+    write_file(&repo, "dropped/secret.txt", "gone");
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "pruned"]).0, 0);
+
+    run_tool_expect_success(&repo, |o| {
+        o.override_committer = Some(b"Rewrite Bot <bot@corp>".to_vec());
+        o.paths.push(b"dropped/".to_vec());
+        o.invert_paths = true;
+        o.write_report_json = true;
+    });
+
+    let kept: usize = run_git(&repo, &["rev-list", "--count", "HEAD"])
+        .1
+        .trim()
+        .parse()
+        .unwrap();
+    assert_eq!(kept, 2, "the dropped/ commit is pruned");
+    let json: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(repo.join(".git/filter-repo/report.json"))
+            .expect("read report.json"),
+    )
+    .expect("parse report.json");
+    assert_eq!(json["identity_overrides"][0]["stamped"], kept);
+}
+
+#[test]
+fn override_committer_date_now_is_pinned_by_reproducible() {
+    let repo = init_repo();
+    legacy_commit(&repo);
+
+    let output = cli_command()
+        .current_dir(&repo)
+        .env("SOURCE_DATE_EPOCH", "1234567890")
+        .args([
+            "--force",
+            "--reproducible",
+            "--override-committer",
+            "Rewrite Bot <bot@corp>",
+            "--override-committer-date",
+            "now",
+        ])
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(output.status.success(), "{output:?}");
+
+    assert_eq!(identity_fields(&repo, "%cd")[0], "1234567890 +0000");
+}
+
+#[test]
+fn override_committer_wins_over_mailmap() {
+    let repo = init_repo();
+    legacy_commit(&repo);
+    let mailmap = repo.join("override.mailmap");
+    std::fs::write(
+        &mailmap,
+        "Mapped Author <mapped@example.com> <old@example.com>\n\
+         Mapped Committer <mapped-c@example.com> <committer@example.com>\n",
+    )
+    .expect("write mailmap rules");
+
+    run_tool_expect_success(&repo, |o| {
+        o.mailmap_file = Some(mailmap.clone());
+        o.override_committer = Some(b"Rewrite Bot <bot@corp>".to_vec());
+        o.no_data = true;
+        o.write_report = true;
+    });
+
+    let after = identity_fields(&repo, "%an <%ae>|%cn <%ce>");
+    assert_eq!(after[0], "Mapped Author <mapped@example.com>");
+    assert_eq!(after[1], "Rewrite Bot <bot@corp>");
+    let report =
+        std::fs::read_to_string(repo.join(".git/filter-repo/report.txt")).expect("read report.txt");
+    assert!(report.contains("=== Identity rewrites ==="), "{report}");
+    assert!(report.contains("=== Identity overrides ==="), "{report}");
+}
+
+#[test]
+fn override_tagger_stamps_annotated_tags_only() {
+    let repo = init_repo();
+    let output = Command::new("git")
+        .current_dir(&repo)
+        .env("GIT_COMMITTER_NAME", "Old Tagger")
+        .env("GIT_COMMITTER_EMAIL", "old@example.com")
+        .env("GIT_COMMITTER_DATE", "1600000000 +0200")
+        .args(["tag", "-a", "v1.0", "-m", "release notes"])
+        .output()
+        .expect("run git tag");
+    assert!(output.status.success(), "{output:?}");
+    let committer_before = identity_fields(&repo, "%cn <%ce>");
+
+    run_tool_expect_success(&repo, |o| {
+        o.override_tagger = Some(b"Release Bot <release@corp>".to_vec());
+    });
+
+    let (_c, tag, _e) = run_git(&repo, &["cat-file", "-p", "refs/tags/v1.0"]);
+    assert!(
+        tag.contains("\ntagger Release Bot <release@corp> 1600000000 +0200\n"),
+        "{tag}"
+    );
+    assert_eq!(identity_fields(&repo, "%cn <%ce>"), committer_before);
+}
@@ -0,0 +1,59 @@
+mod common;
+use common::*;
+
+fn stderr_records(stderr: &str) -> Vec<serde_json::Value> {
+    stderr
+        .lines()
+        .map(|line| {
+            serde_json::from_str(line)
+                .unwrap_or_else(|e| panic!("stderr line is not JSON ({e}): {line:?}"))
+        })
+        .collect()
+}
+
+#[test]
+fn log_format_json_writes_one_object_per_stderr_line() {
+    let repo = init_repo();
+    write_file(&repo, "keep.txt", "keep");
+    write_file(&repo, "drop.txt", "drop");
+    run_git(&repo, &["add", "."]);
+    run_git(&repo, &["commit", "-q", "-m", "add files"]);
+
+    let output = cli_command()
+        .current_dir(&repo)
+        .args(["--path", "keep.txt", "--force", "--log-format", "json"])
+        .output()
+        .expect("run filter-repo-rs");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "stderr: {stderr}");
+
+    let records = stderr_records(&stderr);
+    assert!(!records.is_empty(), "expected at least one record");
+    for record in &records {
+        assert!(record["phase"].is_string(), "missing phase: {record}");
+        assert!(record["level"].is_string(), "missing level: {record}");
+        assert!(record["context"].is_object(), "missing context: {record}");
+    }
+    assert!(records.iter().any(|r| r["phase"] == "finalize"
+        && r["message"]
+            .as_str()
+            .is_some_and(|m| m.starts_with("New history written"))));
+}
+
+#[test]
+fn log_format_json_reports_errors_as_json() {
+    let repo = init_repo();
+    let output = cli_command()
+        .current_dir(&repo)
+        .args(["--log-format=json", "--path-rename", "same:same"])
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(!output.status.success());
+    let records = stderr_records(&String::from_utf8_lossy(&output.stderr));
+    let last = records.last().expect("an error record");
+    assert_eq!(last["level"], "error");
+    assert_eq!(last["phase"], "exit");
+    assert!(last["message"]
+        .as_str()
+        .is_some_and(|m| m.contains("must differ")));
+}
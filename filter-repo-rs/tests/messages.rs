@@ -29,6 +29,72 @@ fn replace_message_edits_commit_and_tag_messages() {
     assert!(tag_obj.contains("BAR"));
 }
 
+#[test]
+fn replace_message_reads_prefixed_lines_of_literal_files_as_text() {
+    let repo = init_repo();
+    write_file(&repo, "src/a.txt", "x");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(
+        run_git(
+            &repo,
+            &["commit", "-q", "-m", "ignore glob:*.tmp in the FOO cleanup"]
+        )
+        .0,
+        0
+    );
+    // A literal-only file written before regex:/glob: rules existed.
+    let repl = repo.join("repl.txt");
+    std::fs::write(
+        &repl,
+        "glob:*.tmp==>glob:*.bak
+FOO==>BAR
+",
+    )
+    .unwrap();
+    run_tool_expect_success(&repo, |o| {
+        o.replace_message_file = Some(repl.clone());
+        o.no_data = true;
+    });
+    let (_c, msg, _e) = run_git(&repo, &["log", "-1", "--format=%s"]);
+    assert_eq!(msg.trim(), "ignore glob:*.bak in the BAR cleanup");
+}
+
+#[test]
+fn tag_message_file_rewrites_tags_with_their_own_rules() {
+    let repo = init_repo();
+    write_file(&repo, "src/a.txt", "x");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(
+        run_git(&repo, &["commit", "-q", "-m", "fix FOO, see JIRA-1"]).0,
+        0
+    );
+    assert_eq!(
+        run_git(&repo, &["tag", "-a", "-m", "notes FOO, see JIRA-1", "v2.0"]).0,
+        0
+    );
+    let commit_rules = repo.join("commit-rules.txt");
+    std::fs::write(&commit_rules, "FOO==>BAR\n").unwrap();
+    let tag_rules = repo.join("tag-rules.txt");
+    std::fs::write(
+        &tag_rules,
+        "regex:JIRA-(\\d+)==>https://tracker.example.com/JIRA-$1\n",
+    )
+    .unwrap();
+    run_tool_expect_success(&repo, |o| {
+        o.replace_message_file = Some(commit_rules.clone());
+        o.replace_tag_message_file = Some(tag_rules.clone());
+        o.no_data = true;
+    });
+
+    let (_c1, msg, _e1) = run_git(&repo, &["log", "-1", "--format=%B"]);
+    assert!(msg.contains("fix BAR, see JIRA-1"), "commit message: {msg}");
+    let (_c2, tag_obj, _e2) = run_git(&repo, &["cat-file", "-p", "refs/tags/v2.0"]);
+    assert!(
+        tag_obj.contains("notes FOO, see https://tracker.example.com/JIRA-1"),
+        "tag message: {tag_obj}"
+    );
+}
+
 #[test]
 fn second_run_rewrites_short_hashes_in_messages() {
     let repo = init_repo();
@@ -104,3 +170,70 @@ fn second_run_rewrites_short_hashes_in_messages() {
     assert!(tag_obj.contains(&new_short));
     assert!(!tag_obj.contains(&old_short));
 }
+
+#[test]
+fn strip_subject_prefix_and_pr_suffix_rewrite_commit_subjects_only() {
+    let repo = init_repo();
+    write_file(&repo, "a.txt", "a\n");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(
+        run_git(
+            &repo,
+            &[
+                "commit",
+                "-q",
+                "-m",
+                "[WIP] [JIRA-7] Add parser (#12)",
+                "-m",
+                "[WIP] body stays (#3)",
+            ],
+        )
+        .0,
+        0
+    );
+    assert_eq!(
+        run_git(&repo, &["tag", "-a", "-m", "[WIP] release (#5)", "v1"]).0,
+        0
+    );
+
+    run_tool_expect_success(&repo, |o| {
+        o.strip_subject_prefixes = vec![
+            filter_repo_rs::opts::SubjectPrefixPattern::Glob(b"WIP".to_vec()),
+            filter_repo_rs::opts::SubjectPrefixPattern::Glob(b"JIRA-*".to_vec()),
+        ];
+        o.strip_pr_suffix = true;
+        o.write_report = true;
+        o.no_data = true;
+    });
+
+    let (_c, msg, _e) = run_git(&repo, &["log", "-1", "--format=%B"]);
+    assert_eq!(msg.trim_end(), "Add parser\n\n[WIP] body stays (#3)");
+    let (_c, tag_msg, _e) = run_git(&repo, &["tag", "-l", "--format=%(contents)", "v1"]);
+    assert_eq!(tag_msg.trim_end(), "[WIP] release (#5)");
+
+    let report = std::fs::read_to_string(repo.join(".git/filter-repo/report.txt")).unwrap();
+    assert!(report.contains("=== Subject normalization ==="));
+    assert!(report.contains("Prefixes stripped: 1"));
+    assert!(report.contains("PR suffixes stripped: 1"));
+}
+
+#[test]
+fn also_tags_applies_subject_normalization_to_tag_messages() {
+    let repo = init_repo();
+    assert_eq!(
+        run_git(&repo, &["tag", "-a", "-m", "[WIP] release (#5)", "v1"]).0,
+        0
+    );
+
+    run_tool_expect_success(&repo, |o| {
+        o.strip_subject_prefixes = vec![filter_repo_rs::opts::SubjectPrefixPattern::Glob(
+            b"WIP".to_vec(),
+        )];
+        o.strip_pr_suffix = true;
+        o.also_tags = true;
+        o.no_data = true;
+    });
+
+    let (_c, tag_msg, _e) = run_git(&repo, &["tag", "-l", "--format=%(contents)", "v1"]);
+    assert_eq!(tag_msg.trim_end(), "release");
+}
@@ -0,0 +1,58 @@
+mod common;
+use common::*;
+
+fn ls_tree_modes(repo: &std::path::Path, rev: &str) -> String {
+    let (code, out, err) = run_git(repo, &["ls-tree", "-r", rev]);
+    assert_eq!(code, 0, "git ls-tree failed: {err}");
+    out
+}
+
+#[test]
+fn normalize_mode_fixes_exec_bits_across_history() {
+    let repo = init_repo();
+    write_file(&repo, "scripts/build.sh", "#!/bin/sh\necho build\n");
+    write_file(&repo, "NOTES.md", "notes\n");
+    run_git(&repo, &["add", "."]);
+    run_git(&repo, &["update-index", "--chmod=-x", "scripts/build.sh"]);
+    run_git(&repo, &["update-index", "--chmod=+x", "NOTES.md"]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add files"]).0, 0);
+    write_file(&repo, "README.md", "hello again");
+    run_git(&repo, &["add", "README.md"]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "touch readme"]).0, 0);
+
+    run_tool_expect_success(&repo, |o| {
+        o.mode_normalizations = vec![
+            (b"*.sh".to_vec(), b"100755".to_vec()),
+            (b"*.md".to_vec(), b"100644".to_vec()),
+        ];
+        o.write_report = true;
+    });
+
+    for rev in ["HEAD", "HEAD~1"] {
+        let tree = ls_tree_modes(&repo, rev);
+        assert!(
+            tree.lines()
+                .any(|l| l.starts_with("100755 ") && l.ends_with("\tscripts/build.sh")),
+            "build.sh should be executable at {rev}: {tree}"
+        );
+        assert!(
+            tree.lines()
+                .any(|l| l.starts_with("100644 ") && l.ends_with("\tNOTES.md")),
+            "NOTES.md should not be executable at {rev}: {tree}"
+        );
+    }
+
+    let report = std::fs::read_to_string(repo.join(".git/filter-repo/report.txt")).unwrap();
+    assert!(
+        report.contains("=== Mode normalizations ==="),
+        "missing section: {report}"
+    );
+    assert!(
+        report.contains("*.sh -> 100755: 1 file change(s)"),
+        "{report}"
+    );
+    assert!(
+        report.contains("*.md -> 100644: 1 file change(s)"),
+        "{report}"
+    );
+}
@@ -27,3 +27,168 @@ fn auto_no_data_enabled_when_safe_and_useful() {
         fe
     );
 }
+
+#[test]
+fn no_data_rejects_options_that_need_blob_contents() {
+    let repo = init_repo();
+    let rules = repo.join("rules.txt");
+    std::fs::write(&rules, "secret==>REDACTED\n").unwrap();
+
+    let err = run_tool(&repo, |o| {
+        o.no_data = true;
+        o.replace_text_file = Some(rules.clone());
+    })
+    .expect_err("--no-data with --replace-text should fail");
+    assert!(err.to_string().contains("--replace-text"), "{err}");
+
+    let err = run_tool(&repo, |o| {
+        o.no_data = true;
+        o.detect_secrets = true;
+    })
+    .expect_err("--no-data with --detect-secrets should fail");
+    assert!(err.to_string().contains("--detect-secrets"), "{err}");
+}
+
+#[test]
+fn no_data_skips_blob_payloads_on_large_blob_repo() {
+    let seed = init_repo();
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    for i in 0..4 {
+        let payload: Vec<u8> = (0..256 * 1024)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                state as u8
+            })
+            .collect();
+        std::fs::create_dir_all(seed.join("data")).unwrap();
+        std::fs::write(seed.join(format!("data/blob{i}.bin")), payload).unwrap();
+    }
+    run_git(&seed, &["add", "."]);
+    assert_eq!(
+        run_git(&seed, &["commit", "-q", "-m", "add large blobs"]).0,
+        0
+    );
+
+    let parent = mktemp("fr_rs_no_data");
+    std::fs::create_dir_all(&parent).unwrap();
+    let mut streams = Vec::new();
+    let mut trees = Vec::new();
+    for no_data in [false, true] {
+        let clone = parent.join(format!("clone-{no_data}"));
+        let (code, _o, e) = run_git(
+            &parent,
+            &[
+                "clone",
+                "-q",
+                "--no-local",
+                &seed.to_string_lossy(),
+                &clone.to_string_lossy(),
+            ],
+        );
+        assert_eq!(code, 0, "clone failed: {e}");
+        run_tool_expect_success(&clone, |o| {
+            o.no_data = no_data;
+            o.path_renames = vec![(b"data/".to_vec(), b"assets/".to_vec())];
+        });
+        streams.push(std::fs::read(clone.join(".git/filter-repo/fast-export.filtered")).unwrap());
+        trees.push(run_git(&clone, &["ls-tree", "-r", "HEAD"]).1);
+    }
+
+    assert_eq!(trees[0], trees[1], "both runs should write identical trees");
+    assert!(trees[1].contains("assets/blob0.bin"));
+    let blobs = |stream: &[u8]| {
+        stream
+            .split(|&b| b == b'\n')
+            .filter(|line| *line == b"blob")
+            .count()
+    };
+    // --no-data keeps the existing blobs and sends none through fast-import.
+    assert_eq!(blobs(&streams[1]), 0);
+    assert!(
+        streams[1].len() < 64 * 1024,
+        "--no-data stream still carries payloads: {} bytes",
+        streams[1].len()
+    );
+}
+
+#[test]
+fn metadata_only_keeps_trees_and_blobs_while_rewriting_commits() {
+    let repo = init_repo();
+    write_file(&repo, "src/lib.rs", "pub fn answer() -> u32 { 42 }\n");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add lib"]).0, 0);
+    let branch = current_branch(&repo);
+    let tree_before = run_git(&repo, &["rev-parse", "HEAD^{tree}"]).1;
+    let blob_before = run_git(&repo, &["rev-parse", "HEAD:src/lib.rs"]).1;
+    let commit_before = run_git(&repo, &["rev-parse", "HEAD"]).1;
+    let messages = repo.join("messages.txt");
+    std::fs::write(&messages, "add lib==>Add library\n").unwrap();
+
+    run_tool_expect_success(&repo, |o| {
+        o.metadata_only = true;
+        o.no_data = true;
+        o.branch_rename = Some((branch.as_bytes().to_vec(), b"trunk".to_vec()));
+        o.replace_message_file = Some(messages.clone());
+        o.write_report = true;
+    });
+
+    let head = "refs/heads/trunk";
+    assert_eq!(
+        run_git(&repo, &["rev-parse", &format!("{head}^{{tree}}")]).1,
+        tree_before
+    );
+    assert_eq!(
+        run_git(&repo, &["rev-parse", &format!("{head}:src/lib.rs")]).1,
+        blob_before
+    );
+    assert_ne!(run_git(&repo, &["rev-parse", head]).1, commit_before);
+    assert_eq!(
+        run_git(&repo, &["log", "-1", "--format=%s", head]).1.trim(),
+        "Add library"
+    );
+    let report = std::fs::read_to_string(repo.join(".git/filter-repo/report.txt")).unwrap();
+    assert!(report.contains("Export mode: metadata-only"), "{report}");
+}
+
+#[test]
+fn metadata_only_rejects_blob_content_options() {
+    let repo = init_repo();
+    let err = run_tool(&repo, |o| {
+        o.metadata_only = true;
+        o.no_data = true;
+        o.max_blob_size = Some(1024);
+    })
+    .expect_err("--metadata-only with --max-blob-size should fail");
+    assert!(err.to_string().contains("--max-blob-size"), "{err}");
+
+    let output = cli_command()
+        .current_dir(&repo)
+        .args([
+            "--metadata-only",
+            "--strip-blobs-with-ids",
+            "ids.txt",
+            "--force",
+        ])
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("--strip-blobs-with-ids"));
+}
+
+#[test]
+fn no_data_rejects_strip_blobs_matching() {
+    let repo = init_repo();
+    let output = cli_command()
+        .current_dir(&repo)
+        .args(["--no-data", "--strip-blobs-matching", "hostname"])
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--no-data cannot be combined with --strip-blobs-matching"),
+        "unexpected stderr: {stderr}"
+    );
+}
@@ -0,0 +1,71 @@
+mod common;
+use common::*;
+
+#[test]
+fn output_bundle_leaves_source_untouched_and_clones_filtered_tree() {
+    let repo = init_repo();
+    write_file(&repo, "secrets/api.key", "hunter2");
+    write_file(&repo, "src/main.rs", "fn main() {}");
+    run_git(&repo, &["add", "."]);
+    run_git(&repo, &["commit", "-q", "-m", "add files"]);
+    run_git(&repo, &["tag", "v1"]);
+    let branch = current_branch(&repo);
+    let (_c, refs_before, _e) = run_git(&repo, &["for-each-ref"]);
+
+    let out_dir = mktemp("fr_rs_output_bundle");
+    let bundle = out_dir.join("filtered.bundle");
+    let bundle_str = bundle.to_string_lossy().to_string();
+    let output = cli_command()
+        .current_dir(&repo)
+        .args(["--path", "secrets/", "--invert-paths", "--force"])
+        .args(["--output-bundle", &bundle_str])
+        .output()
+        .expect("run filter-repo-rs");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.contains(&format!("Bundle written to {bundle_str}")));
+    assert!(stdout.contains(&format!(" refs/heads/{branch}")));
+    assert!(stdout.contains(" refs/tags/v1"));
+
+    let (_c, refs_after, _e) = run_git(&repo, &["for-each-ref"]);
+    assert_eq!(refs_before, refs_after, "source refs must not change");
+    let (_c, tree, _e) = run_git(&repo, &["ls-tree", "-r", "--name-only", "HEAD"]);
+    assert!(tree.lines().any(|p| p == "secrets/api.key"));
+
+    let (code, _o, err) = run_git(&repo, &["bundle", "verify", &bundle_str]);
+    assert_eq!(code, 0, "git bundle verify failed: {err}");
+
+    let clone = out_dir.join("clone");
+    let clone_str = clone.to_string_lossy().to_string();
+    let (code, _o, err) = run_git(
+        &out_dir,
+        &["clone", "-q", "-b", &branch, &bundle_str, &clone_str],
+    );
+    assert_eq!(code, 0, "clone from bundle failed: {err}");
+    let (_c, files, _e) = run_git(&clone, &["log", "--format=", "--name-only", "--all"]);
+    assert!(files.lines().any(|f| f == "src/main.rs"));
+    assert!(
+        !files.contains("secrets/"),
+        "secrets/ should be filtered from the bundle, got:\n{files}"
+    );
+}
+
+#[test]
+fn output_bundle_rejects_sensitive() {
+    let repo = init_repo();
+    let output = cli_command()
+        .current_dir(&repo)
+        .args(["--sensitive", "--force", "--output-bundle", "out.bundle"])
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("--output-bundle cannot be combined with --sensitive"),
+        "unexpected stderr: {stderr}"
+    );
+}
@@ -0,0 +1,116 @@
+mod common;
+use common::*;
+
+use std::path::{Path, PathBuf};
+
+/// `main` and `feature` share a root commit that adds `secret.txt`; each
+/// branch then has a commit of its own. `origin` is configured and has a
+/// remote-tracking ref for `main`.
+fn repo_with_two_branches() -> PathBuf {
+    let repo = init_repo();
+    let branch = current_branch(&repo);
+    write_file(&repo, "secret.txt", "hunter2\n");
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add secret"]).0, 0);
+    assert_eq!(run_git(&repo, &["branch", "feature"]).0, 0);
+    write_file(&repo, "main.txt", "main\n");
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "main work"]).0, 0);
+    assert_eq!(run_git(&repo, &["checkout", "-q", "feature"]).0, 0);
+    write_file(&repo, "feature.txt", "feature\n");
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "feature work"]).0, 0);
+    assert_eq!(run_git(&repo, &["checkout", "-q", &branch]).0, 0);
+    assert_eq!(
+        run_git(
+            &repo,
+            &[
+                "remote",
+                "add",
+                "origin",
+                "https://example.invalid/repo.git"
+            ]
+        )
+        .0,
+        0
+    );
+    assert_eq!(
+        run_git(&repo, &["update-ref", "refs/remotes/origin/main", "HEAD"]).0,
+        0
+    );
+    repo
+}
+
+fn rev(repo: &Path, name: &str) -> String {
+    run_git(repo, &["rev-parse", name]).1.trim().to_string()
+}
+
+fn assert_partial_rewrite(repo: &Path, branch: &str, main_before: &str, feature_before: &str) {
+    // (1) Refs outside the selection keep their object ids.
+    assert_eq!(rev(repo, &format!("refs/heads/{branch}")), main_before);
+    assert_ne!(rev(repo, "refs/heads/feature"), feature_before);
+    let (_c, tree, _e) = run_git(repo, &["ls-tree", "--name-only", "refs/heads/feature"]);
+    assert!(!tree.contains("secret.txt"), "{tree}");
+
+    // (2) The commit-map lists only the commits that were exported.
+    let commit_map =
+        std::fs::read_to_string(repo.join(".git/filter-repo/commit-map")).expect("read commit-map");
+    let originals: Vec<&str> = commit_map
+        .lines()
+        .filter_map(|line| line.split_whitespace().next())
+        .collect();
+    assert!(originals.contains(&feature_before), "{commit_map}");
+    assert!(!originals.contains(&main_before), "{commit_map}");
+
+    // (3) origin's branches were not migrated to refs/heads/*.
+    assert_eq!(rev(repo, "refs/remotes/origin/main"), main_before);
+
+    // (4) The origin remote is still configured.
+    let (_c, remotes, _e) = run_git(repo, &["remote"]);
+    assert!(remotes.lines().any(|r| r == "origin"), "{remotes}");
+}
+
+#[test]
+fn partial_rewrite_leaves_unselected_refs_and_origin_alone() {
+    let repo = repo_with_two_branches();
+    let branch = current_branch(&repo);
+    let main_before = rev(&repo, "HEAD");
+    let feature_before = rev(&repo, "refs/heads/feature");
+
+    run_tool_expect_success(&repo, |o| {
+        o.refs = vec!["refs/heads/feature".to_string()];
+        o.partial = true;
+        o.paths = vec![b"secret.txt".to_vec()];
+        o.invert_paths = true;
+    });
+
+    assert_partial_rewrite(&repo, &branch, &main_before, &feature_before);
+}
+
+#[test]
+fn refs_flag_replaces_the_default_all() {
+    let repo = repo_with_two_branches();
+    let branch = current_branch(&repo);
+    let main_before = rev(&repo, "HEAD");
+    let feature_before = rev(&repo, "refs/heads/feature");
+
+    let output = cli_command()
+        .current_dir(&repo)
+        .args([
+            "--force",
+            "--refs",
+            "refs/heads/feature",
+            "--path",
+            "secret.txt",
+            "--invert-paths",
+        ])
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert_partial_rewrite(&repo, &branch, &main_before, &feature_before);
+}
@@ -546,3 +546,399 @@ fn cli_path_rejects_absolute_and_dot_segments() {
     let (out3, _inv3) = run_cli_with_git_spy(&repo, &["--path", "C:\\foo"]);
     assert_eq!(out3.status.code(), Some(2));
 }
+
+#[test]
+fn add_root_file_adds_file_to_roots_missing_it() {
+    let repo = init_repo();
+    write_file(&repo, "a.txt", "a");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add a"]).0, 0);
+    write_file(&repo, "b.txt", "b");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add b"]).0, 0);
+    let main = current_branch(&repo);
+
+    // An orphan root that already carries its own NOTICE.
+    assert_eq!(
+        run_git(&repo, &["checkout", "-q", "--orphan", "other"]).0,
+        0
+    );
+    run_git(&repo, &["rm", "-rq", "--cached", "."]);
+    std::fs::remove_file(repo.join("README.md")).unwrap();
+    std::fs::remove_file(repo.join("a.txt")).unwrap();
+    std::fs::remove_file(repo.join("b.txt")).unwrap();
+    write_file(&repo, "NOTICE", "own notice\n");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "other root"]).0, 0);
+    let (_c, other_root_before, _e) = run_git(&repo, &["rev-parse", "HEAD"]);
+    write_file(&repo, "c.txt", "c");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add c"]).0, 0);
+    assert_eq!(run_git(&repo, &["checkout", "-q", &main]).0, 0);
+
+    let notice = repo.join("notice.txt");
+    std::fs::write(&notice, "injected notice\n").unwrap();
+    run_tool_expect_success(&repo, |o| {
+        o.root_files.push((b"NOTICE".to_vec(), notice.clone()));
+        o.write_report = true;
+    });
+
+    let (_c, main_commits, _e) = run_git(&repo, &["rev-list", &main]);
+    let main_commits: Vec<&str> = main_commits.lines().collect();
+    assert_eq!(main_commits.len(), 3);
+    for commit in main_commits {
+        let (code, content, _e) = run_git(&repo, &["show", &format!("{commit}:NOTICE")]);
+        assert_eq!(code, 0, "NOTICE missing from {commit}");
+        assert_eq!(content, "injected notice\n");
+    }
+
+    let (_c, other_root_after, _e) = run_git(&repo, &["rev-list", "--max-parents=0", "other"]);
+    assert_eq!(other_root_after.trim(), other_root_before.trim());
+    let (_c, content, _e) = run_git(&repo, &["show", "other:NOTICE"]);
+    assert_eq!(content, "own notice\n");
+
+    let report = std::fs::read_to_string(repo.join(".git/filter-repo/report.txt")).unwrap();
+    assert!(report.contains("Roots modified: 1"), "report: {report}");
+}
+
+#[test]
+fn path_rename_matching_nothing_at_head_warns() {
+    let repo = init_repo();
+    write_file(&repo, "src/lib.rs", "pub fn f() {}\n");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add src"]).0, 0);
+
+    let run = |extra: &[&str]| {
+        let output = cli_command()
+            .arg("--source")
+            .arg(&repo)
+            .arg("--target")
+            .arg(&repo)
+            .args(["--path-rename", "scr/:code/", "--path-rename", "src/:lib/"])
+            .args(extra)
+            .output()
+            .expect("run filter-repo-rs");
+        assert!(output.status.success(), "{output:?}");
+        String::from_utf8_lossy(&output.stderr).into_owned()
+    };
+
+    let stderr = run(&["--dry-run", "--force"]);
+    assert!(
+        stderr.contains("--path-rename scr/:code/ matched 0 paths at HEAD"),
+        "{stderr}"
+    );
+    assert!(stderr.contains("older commits"), "{stderr}");
+    assert!(
+        !stderr.contains("--path-rename src/:lib/ matched"),
+        "{stderr}"
+    );
+
+    let stderr = run(&["--dry-run", "--force", "--no-validate-paths"]);
+    assert!(!stderr.contains("matched 0 paths"), "{stderr}");
+}
+
+fn commit_build_artifacts(repo: &std::path::Path) {
+    for path in [
+        "src/Main.java",
+        "build/classes/Main.class",
+        "web/node_modules/left-pad/index.js",
+        "web/app.js",
+        "tools/__pycache__/util.cpython-312.pyc",
+        "tools/legacy.pyc",
+        "tools/util.py",
+    ] {
+        write_file(repo, path, path);
+    }
+    assert_eq!(run_git(repo, &["add", "-f", "."]).0, 0);
+    assert_eq!(run_git(repo, &["commit", "-q", "-m", "artifacts"]).0, 0);
+}
+
+fn history_paths(repo: &std::path::Path) -> Vec<String> {
+    let (_c, out, _e) = run_git(repo, &["log", "--all", "--name-only", "--format="]);
+    out.lines()
+        .filter(|l| !l.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[test]
+fn strip_java_class_files_removes_class_files_only() {
+    let repo = init_repo();
+    commit_build_artifacts(&repo);
+    run_tool_expect_success(&repo, |o| o.strip_java_class_files = true);
+    let paths = history_paths(&repo);
+    assert!(!paths.iter().any(|p| p.ends_with(".class")), "{paths:?}");
+    assert!(paths.contains(&"src/Main.java".to_string()), "{paths:?}");
+    assert!(
+        paths.iter().any(|p| p.contains("node_modules")),
+        "{paths:?}"
+    );
+}
+
+#[test]
+fn strip_node_modules_removes_nested_node_modules() {
+    let repo = init_repo();
+    commit_build_artifacts(&repo);
+    run_tool_expect_success(&repo, |o| o.strip_node_modules = true);
+    let paths = history_paths(&repo);
+    assert!(
+        !paths.iter().any(|p| p.contains("node_modules/")),
+        "{paths:?}"
+    );
+    assert!(paths.contains(&"web/app.js".to_string()), "{paths:?}");
+}
+
+#[test]
+fn strip_python_cache_removes_pycache_and_pyc() {
+    let repo = init_repo();
+    commit_build_artifacts(&repo);
+    run_tool_expect_success(&repo, |o| o.strip_python_cache = true);
+    let paths = history_paths(&repo);
+    assert!(
+        !paths
+            .iter()
+            .any(|p| p.contains("__pycache__") || p.ends_with(".pyc")),
+        "{paths:?}"
+    );
+    assert!(paths.contains(&"tools/util.py".to_string()), "{paths:?}");
+}
+
+#[test]
+fn strip_build_artifacts_flag_removes_all_three_kinds() {
+    let repo = init_repo();
+    commit_build_artifacts(&repo);
+    let output = cli_command()
+        .current_dir(&repo)
+        .args(["--strip-build-artifacts", "--force"])
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let mut paths = history_paths(&repo);
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec!["README.md", "src/Main.java", "tools/util.py", "web/app.js"]
+    );
+}
+
+#[test]
+fn synthesize_renames_emits_rename_filechanges_for_moved_files() {
+    let repo = init_repo();
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        write_file(&repo, &format!("old/{name}"), &format!("{name} contents\n"));
+    }
+    write_file(&repo, "old/keep.txt", "stays\n");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add old"]).0, 0);
+    write_file(&repo, "old/a.txt", "a.txt contents, edited\n");
+    assert_eq!(run_git(&repo, &["commit", "-qam", "edit a"]).0, 0);
+    std::fs::create_dir_all(repo.join("moved")).unwrap();
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        let (src, dst) = (format!("old/{name}"), format!("moved/{name}"));
+        assert_eq!(run_git(&repo, &["mv", &src, &dst]).0, 0);
+    }
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "move"]).0, 0);
+
+    run_tool_expect_success(&repo, |o| {
+        o.path_renames.push((b"moved/".to_vec(), b"lib/".to_vec()));
+        o.synthesize_renames = true;
+    });
+
+    let stream = std::fs::read_to_string(repo.join(".git/filter-repo/fast-export.filtered"))
+        .expect("read filtered stream");
+    for name in ["a.txt", "b.txt", "c.txt"] {
+        assert!(
+            stream.contains(&format!("R old/{name} lib/{name}\n")),
+            "{stream}"
+        );
+        assert!(!stream.contains(&format!("D old/{name}\n")), "{stream}");
+    }
+    let (_c, tree, _e) = run_git(&repo, &["ls-tree", "-r", "--name-only", "HEAD"]);
+    assert_eq!(
+        tree,
+        "README.md\nlib/a.txt\nlib/b.txt\nlib/c.txt\nold/keep.txt\n"
+    );
+    let (_c, log, _e) = run_git(
+        &repo,
+        &["log", "--follow", "--format=%s", "--", "lib/a.txt"],
+    );
+    assert_eq!(log, "move\nedit a\nadd old\n");
+}
+
+#[test]
+fn synthesize_renames_diffs_every_commit_against_its_first_parent() {
+    let repo = init_repo();
+    let trunk = current_branch(&repo);
+    write_file(&repo, "one.txt", "one\n");
+    write_file(&repo, "two.txt", "two\n");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add"]).0, 0);
+    assert_eq!(run_git(&repo, &["mv", "one.txt", "first.txt"]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "move one"]).0, 0);
+    assert_eq!(run_git(&repo, &["checkout", "-q", "-b", "side"]).0, 0);
+    assert_eq!(run_git(&repo, &["mv", "two.txt", "second.txt"]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "move two"]).0, 0);
+    assert_eq!(run_git(&repo, &["checkout", "-q", &trunk]).0, 0);
+    write_file(&repo, "trunk.txt", "trunk\n");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "trunk"]).0, 0);
+    assert_eq!(
+        run_git(
+            &repo,
+            &["merge", "-q", "--no-ff", "-m", "merge side", "side"]
+        )
+        .0,
+        0
+    );
+
+    run_tool_expect_success(&repo, |o| o.synthesize_renames = true);
+
+    let stream = std::fs::read_to_string(repo.join(".git/filter-repo/fast-export.filtered"))
+        .expect("read filtered stream");
+    assert!(stream.contains("R one.txt first.txt\n"), "{stream}");
+    // Once on `side`, once in the merge against its first parent.
+    assert_eq!(
+        stream.matches("R two.txt second.txt\n").count(),
+        2,
+        "{stream}"
+    );
+    assert!(!stream.contains("D two.txt\n"), "{stream}");
+}
+
+#[test]
+fn synthesize_renames_only_folds_when_both_paths_rewrite_alike() {
+    let repo = init_repo();
+    let big = "0123456789abcdefghijklmnopqrstuvwxyz\n";
+    write_file(&repo, "data/big.bin", big);
+    write_file(&repo, "docs/notes.txt", "token=secret\n");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add"]).0, 0);
+    assert_eq!(run_git(&repo, &["mv", "data/big.bin", "data/big.dat"]).0, 0);
+    assert_eq!(
+        run_git(&repo, &["mv", "docs/notes.txt", "docs/renamed.txt"]).0,
+        0
+    );
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "move"]).0, 0);
+    let rules = repo.join("rules.txt");
+    std::fs::write(&rules, "path:docs/renamed.txt:secret==>REDACTED\n").unwrap();
+
+    run_tool_expect_success(&repo, |o| {
+        o.synthesize_renames = true;
+        o.max_blob_size_per_ext = vec![(b"bin".to_vec(), 10)];
+        o.replace_blobs_with_pointer = true;
+        o.replace_text_file = Some(rules.clone());
+    });
+
+    let stream = std::fs::read_to_string(repo.join(".git/filter-repo/fast-export.filtered"))
+        .expect("read filtered stream");
+    // The parent holds a pointer at big.bin, not the blob big.dat gets.
+    assert!(!stream.contains("R data/big.bin"), "{stream}");
+    let (_c, content, _e) = run_git(&repo, &["show", "HEAD:data/big.dat"]);
+    assert_eq!(content, big);
+    // The scoped rule rewrites the blob under both names, so this one folds.
+    assert!(
+        stream.contains("R docs/notes.txt docs/renamed.txt\n"),
+        "{stream}"
+    );
+    let (_c, content, _e) = run_git(&repo, &["show", "HEAD:docs/renamed.txt"]);
+    assert_eq!(content, "token=REDACTED\n");
+}
+
+/// Tags `lw-pruned` and `an-pruned` on a commit that only touches `drop/`,
+/// between commits under `keep/`. Returns the original id of that commit and
+/// of its parent.
+fn repo_with_tags_on_a_pruned_commit() -> (std::path::PathBuf, String, String) {
+    let repo = init_repo();
+    write_file(&repo, "keep/a.txt", "a\n");
+    run_git(&repo, &["add", "."]);
+    run_git(&repo, &["commit", "-q", "-m", "keep a"]);
+    let (_c, parent, _e) = run_git(&repo, &["rev-parse", "HEAD"]);
+    write_file(&repo, "drop/b.txt", "b\n");
+    run_git(&repo, &["add", "."]);
+    run_git(&repo, &["commit", "-q", "-m", "drop b"]);
+    let (_c, pruned, _e) = run_git(&repo, &["rev-parse", "HEAD"]);
+    run_git(&repo, &["tag", "lw-pruned"]);
+    run_git(&repo, &["tag", "-a", "an-pruned", "-m", "annotated"]);
+    write_file(&repo, "keep/c.txt", "c\n");
+    run_git(&repo, &["add", "."]);
+    run_git(&repo, &["commit", "-q", "-m", "keep c"]);
+    (repo, pruned.trim().to_string(), parent.trim().to_string())
+}
+
+fn peeled_tag(repo: &std::path::Path, tag: &str) -> Option<String> {
+    let (code, out, _e) = run_git(
+        repo,
+        &["rev-parse", "--verify", &format!("{tag}^{{commit}}")],
+    );
+    (code == 0).then(|| out.trim().to_string())
+}
+
+#[test]
+fn pruned_tag_delete_removes_tags_on_pruned_commits() {
+    let (repo, pruned, _parent) = repo_with_tags_on_a_pruned_commit();
+    run_tool_expect_success(&repo, |o| {
+        o.paths.push(b"keep/".to_vec());
+        o.write_report = true;
+    });
+
+    assert_eq!(peeled_tag(&repo, "lw-pruned"), None);
+    assert_eq!(peeled_tag(&repo, "an-pruned"), None);
+    let report = std::fs::read_to_string(repo.join(".git/filter-repo/report.txt")).unwrap();
+    assert!(
+        report.contains("=== Tags on pruned commits ==="),
+        "{report}"
+    );
+    assert!(report.contains(&format!("an-pruned: deleted (tagged {pruned})")));
+    assert!(report.contains(&format!("lw-pruned: deleted (tagged {pruned})")));
+}
+
+#[test]
+fn pruned_tag_ancestor_moves_tags_to_the_surviving_parent() {
+    let (repo, pruned, parent) = repo_with_tags_on_a_pruned_commit();
+    run_tool_expect_success(&repo, |o| {
+        o.paths.push(b"keep/".to_vec());
+        o.pruned_tag = fr::opts::PrunedTagPolicy::Ancestor;
+        o.write_report = true;
+    });
+
+    let (_c, new_parent, _e) = run_git(&repo, &["rev-parse", "HEAD~1"]);
+    let new_parent = new_parent.trim().to_string();
+    assert_eq!(
+        peeled_tag(&repo, "lw-pruned").as_deref(),
+        Some(&*new_parent)
+    );
+    assert_eq!(
+        peeled_tag(&repo, "an-pruned").as_deref(),
+        Some(&*new_parent)
+    );
+    let (_c, kind, _e) = run_git(&repo, &["cat-file", "-t", "an-pruned"]);
+    assert_eq!(kind.trim(), "tag");
+    let report = std::fs::read_to_string(repo.join(".git/filter-repo/report.txt")).unwrap();
+    assert!(
+        report.contains(&format!(
+            "an-pruned: moved from {pruned} to ancestor {parent}"
+        )),
+        "{report}"
+    );
+}
+
+#[test]
+fn pruned_tag_keep_old_leaves_tags_on_unrewritten_commits() {
+    let (repo, pruned, _parent) = repo_with_tags_on_a_pruned_commit();
+    run_tool_expect_success(&repo, |o| {
+        o.paths.push(b"keep/".to_vec());
+        o.pruned_tag = fr::opts::PrunedTagPolicy::KeepOld;
+    });
+
+    assert_eq!(peeled_tag(&repo, "lw-pruned").as_deref(), Some(&*pruned));
+    assert_eq!(peeled_tag(&repo, "an-pruned").as_deref(), Some(&*pruned));
+    let (_c, message, _e) = run_git(&repo, &["tag", "-l", "--format=%(contents)", "an-pruned"]);
+    assert_eq!(message.trim(), "annotated");
+    // The old commit is not part of the rewritten branch.
+    let (code, _o, _e) = run_git(&repo, &["merge-base", "--is-ancestor", &pruned, "HEAD"]);
+    assert_ne!(code, 0);
+}
@@ -1,6 +1,8 @@
 mod common;
 use common::*;
 
+use std::io::Write;
+
 #[test]
 fn performance_large_repository_batch_optimization() {
     let repo = init_repo();
@@ -170,3 +172,80 @@ fn performance_batch_vs_individual_optimization() {
     assert!(d1 > std::time::Duration::from_micros(0));
     assert!(d2 > std::time::Duration::from_micros(0));
 }
+
+#[test]
+fn performance_guard_thousands_of_refs_are_renamed_in_one_pass() {
+    let repo = init_repo();
+    let head = run_git(&repo, &["rev-parse", "HEAD"]).1.trim().to_string();
+    let branches = 3000;
+    let tags = 2000;
+    let mut stdin = String::new();
+    for i in 0..branches {
+        stdin.push_str(&format!("create refs/heads/pr/{i} {head}\n"));
+    }
+    for i in 0..tags {
+        stdin.push_str(&format!("create refs/tags/v{i} {head}\n"));
+    }
+    let mut child = std::process::Command::new("git")
+        .current_dir(&repo)
+        .args(["update-ref", "--stdin"])
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+        .expect("spawn git update-ref");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    assert!(child.wait().unwrap().success());
+    run_git(&repo, &["config", "branch.pr/7.remote", "origin"]);
+    run_git(&repo, &["config", "branch.pr/7.merge", "refs/heads/pr/7"]);
+
+    let start = std::time::Instant::now();
+    run_tool_expect_success(&repo, |o| {
+        o.branch_rename = Some((b"pr/".to_vec(), b"imported/".to_vec()));
+        o.tag_rename = Some((b"v".to_vec(), b"release-".to_vec()));
+        o.no_data = true;
+    });
+    let elapsed = start.elapsed();
+
+    let (_c, listed, _e) = run_git(
+        &repo,
+        &["for-each-ref", "--format=%(refname) %(objectname)"],
+    );
+    let refs: Vec<(&str, &str)> = listed
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .collect();
+    let new_head = run_git(&repo, &["rev-parse", "HEAD"]).1.trim().to_string();
+    let renamed_branches = refs
+        .iter()
+        .filter(|(name, oid)| name.starts_with("refs/heads/imported/") && *oid == new_head)
+        .count();
+    let renamed_tags = refs
+        .iter()
+        .filter(|(name, oid)| name.starts_with("refs/tags/release-") && *oid == new_head)
+        .count();
+    assert_eq!(renamed_branches, branches);
+    assert_eq!(renamed_tags, tags);
+    assert!(
+        !refs
+            .iter()
+            .any(|(name, _)| name.starts_with("refs/heads/pr/") || name.starts_with("refs/tags/v")),
+        "old refs should be deleted"
+    );
+    assert_eq!(
+        run_git(&repo, &["config", "branch.imported/7.merge"])
+            .1
+            .trim(),
+        "refs/heads/pr/7"
+    );
+    // Linear work finishes in a few seconds even in debug builds; a
+    // quadratic pass over 5k refs takes far longer.
+    assert!(
+        elapsed < std::time::Duration::from_secs(30),
+        "renaming {} refs took {elapsed:?}",
+        branches + tags
+    );
+}
@@ -0,0 +1,85 @@
+mod common;
+use common::*;
+
+use std::path::Path;
+
+fn read_report_json(repo: &Path) -> serde_json::Value {
+    let json = std::fs::read_to_string(repo.join(".git/filter-repo/report.json"))
+        .expect("read report.json");
+    serde_json::from_str(&json).expect("valid report.json")
+}
+
+#[test]
+fn post_check_fsck_passes_on_a_normal_rewrite() {
+    let repo = init_repo();
+    write_file(&repo, "secret.txt", "hunter2\n");
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add secret"]).0, 0);
+
+    let output = cli_command()
+        .current_dir(&repo)
+        .args([
+            "--force",
+            "--path",
+            "secret.txt",
+            "--invert-paths",
+            "--post-check",
+            "fsck",
+            "--write-report-json",
+        ])
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Post-check fsck passed in"), "{stderr}");
+
+    let report = read_report_json(&repo);
+    assert_eq!(report["post_check"]["check"], "fsck");
+    assert_eq!(report["post_check"]["passed"], true);
+    assert!(report["post_check"]["elapsed_ms"].is_u64());
+}
+
+#[test]
+fn post_check_reports_objects_missing_from_the_rewritten_repository() {
+    let repo = init_repo();
+    let branch = current_branch(&repo);
+    // `stale` is left out of the rewrite, so losing its blob does not stop
+    // fast-export; the check still walks every ref afterwards.
+    assert_eq!(run_git(&repo, &["checkout", "-q", "-b", "stale"]).0, 0);
+    write_file(&repo, "stale.txt", "only on the stale branch\n");
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "stale work"]).0, 0);
+    assert_eq!(run_git(&repo, &["checkout", "-q", &branch]).0, 0);
+    let blob = run_git(&repo, &["rev-parse", "stale:stale.txt"])
+        .1
+        .trim()
+        .to_string();
+    std::fs::remove_file(repo.join(".git/objects").join(&blob[..2]).join(&blob[2..]))
+        .expect("remove loose blob");
+
+    for check in ["fsck", "connectivity"] {
+        let output = cli_command()
+            .current_dir(&repo)
+            .args(["--force", "--partial", "--refs"])
+            .arg(format!("refs/heads/{branch}"))
+            .args(["--post-check", check, "--write-report-json"])
+            .output()
+            .expect("run filter-repo-rs");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(3), "{check}: {stderr}");
+        assert!(
+            stderr.contains(&format!("post-check {check} failed")) && stderr.contains(&blob),
+            "{check}: {stderr}"
+        );
+
+        let report = read_report_json(&repo);
+        assert_eq!(report["post_check"]["check"], check);
+        assert_eq!(report["post_check"]["passed"], false);
+        let output = report["post_check"]["output"].as_str().unwrap();
+        assert!(output.contains(&blob), "{output}");
+    }
+}
@@ -0,0 +1,139 @@
+use std::fs;
+use std::path::Path;
+
+mod common;
+use common::*;
+
+fn rev(repo: &Path, spec: &str) -> String {
+    let (code, out, err) = run_git(repo, &["rev-parse", "--verify", "-q", spec]);
+    assert_eq!(code, 0, "rev-parse {spec} failed: {err}");
+    out.trim().to_string()
+}
+
+fn commit_file(repo: &Path, name: &str) -> String {
+    write_file(repo, name, name);
+    assert_eq!(run_git(repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(repo, &["commit", "-q", "-m", name]).0, 0);
+    rev(repo, "HEAD")
+}
+
+fn write_ref_plan(repo: &Path, plan: serde_json::Value) {
+    let dir = repo.join(".git").join("filter-repo");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("ref-plan.json"), plan.to_string()).unwrap();
+}
+
+#[test]
+fn resume_ref_update_applies_only_outstanding_updates() {
+    let repo = init_repo();
+    run_tool_expect_success(&repo, |o| o.no_data = true);
+    assert!(
+        repo.join(".git/filter-repo/ref-plan.json").exists(),
+        "a normal run records its ref plan"
+    );
+
+    let base = rev(&repo, "HEAD");
+    let branch = current_branch(&repo);
+    let second = commit_file(&repo, "second.txt");
+    let third = commit_file(&repo, "third.txt");
+    assert_eq!(run_git(&repo, &["reset", "-q", "--hard", &base]).0, 0);
+    for name in ["a", "b"] {
+        assert_eq!(run_git(&repo, &["branch", name, &base]).0, 0);
+    }
+    assert_eq!(run_git(&repo, &["tag", "old-tag", &base]).0, 0);
+
+    write_ref_plan(
+        &repo,
+        serde_json::json!({
+            "updates": [
+                {"name": "refs/heads/a", "old": base, "new": second},
+                {"name": "refs/heads/b", "old": base, "new": third},
+                {"name": "refs/heads/c", "old": null, "new": second},
+                {"name": "refs/tags/old-tag", "old": base, "new": null},
+            ],
+            "head": {"before": format!("ref: refs/heads/{branch}"), "after": "ref: refs/heads/b"},
+        }),
+    );
+    // The interrupted run got as far as moving `a` and deleting the tag.
+    assert_eq!(
+        run_git(&repo, &["update-ref", "refs/heads/a", &second]).0,
+        0
+    );
+    assert_eq!(run_git(&repo, &["tag", "-d", "old-tag"]).0, 0);
+
+    run_tool_expect_success(&repo, |o| o.resume_ref_update = true);
+
+    assert_eq!(rev(&repo, "refs/heads/a"), second);
+    assert_eq!(rev(&repo, "refs/heads/b"), third);
+    assert_eq!(rev(&repo, "refs/heads/c"), second);
+    assert_eq!(
+        run_git(&repo, &["rev-parse", "-q", "--verify", "refs/tags/old-tag"]).0,
+        1
+    );
+    assert_eq!(current_branch(&repo), "b");
+}
+
+#[test]
+fn resume_ref_update_refuses_when_a_ref_moved_elsewhere() {
+    let repo = init_repo();
+    let base = rev(&repo, "HEAD");
+    let second = commit_file(&repo, "second.txt");
+    let third = commit_file(&repo, "third.txt");
+    assert_eq!(run_git(&repo, &["branch", "a", &base]).0, 0);
+    assert_eq!(run_git(&repo, &["branch", "b", &base]).0, 0);
+
+    write_ref_plan(
+        &repo,
+        serde_json::json!({
+            "updates": [
+                {"name": "refs/heads/a", "old": base, "new": second},
+                {"name": "refs/heads/b", "old": base, "new": second},
+            ],
+        }),
+    );
+    // Someone committed onto `b` after the interrupted run.
+    assert_eq!(run_git(&repo, &["update-ref", "refs/heads/b", &third]).0, 0);
+
+    let err = run_tool(&repo, |o| o.resume_ref_update = true)
+        .expect_err("resume must refuse when a ref moved");
+    let msg = err.to_string();
+    assert!(msg.contains("refusing to resume"), "{msg}");
+    assert!(
+        msg.contains(&format!(
+            "refs/heads/b: expected {base} (before) or {second} (planned), found {third}"
+        )),
+        "{msg}"
+    );
+    assert_eq!(rev(&repo, "refs/heads/a"), base, "nothing is applied");
+}
+
+#[test]
+fn ref_plan_records_head_move_before_applying_it() {
+    let repo = init_repo();
+    assert_eq!(
+        run_git(&repo, &["checkout", "-q", "-b", "original-topic"]).0,
+        0
+    );
+    commit_file(&repo, "topic.txt");
+
+    run_tool_expect_success(&repo, |o| {
+        o.branch_rename = Some((b"original-".to_vec(), b"renamed-".to_vec()));
+    });
+
+    let plan: serde_json::Value =
+        serde_json::from_slice(&fs::read(repo.join(".git/filter-repo/ref-plan.json")).unwrap())
+            .unwrap();
+    assert_eq!(
+        plan["head"],
+        serde_json::json!({
+            "before": "ref: refs/heads/original-topic",
+            "after": "ref: refs/heads/renamed-topic",
+        })
+    );
+    assert!(plan["updates"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|u| u["name"] == "refs/heads/original-topic" && u["new"].is_null()));
+    assert_eq!(current_branch(&repo), "renamed-topic");
+}
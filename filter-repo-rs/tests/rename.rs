@@ -106,6 +106,64 @@ fn tag_rename_handles_annotated_and_lightweight_together() {
     );
 }
 
+#[test]
+fn tag_rename_updates_tag_name_references_in_messages() {
+    let repo = init_repo();
+    assert_eq!(run_git(&repo, &["tag", "v-1.2.0"]).0, 0);
+    write_file(&repo, "notes.md", "changes");
+    run_git(&repo, &["add", "."]);
+    run_git(
+        &repo,
+        &[
+            "commit",
+            "-q",
+            "-m",
+            "Fix crash present since v-1.2.0.\n\nUnrelated: dev-1.2.0 and v-1.2.0.1",
+        ],
+    );
+    assert_eq!(
+        run_git(
+            &repo,
+            &["tag", "-a", "-m", "Changes since v-1.2.0", "v-1.3.0"]
+        )
+        .0,
+        0
+    );
+
+    run_tool_expect_success(&repo, |o| {
+        o.tag_rename = Some((b"v-".to_vec(), b"release-".to_vec()));
+        o.update_tag_name_references = true;
+        o.write_report = true;
+    });
+
+    let (_c, msg, _e) = run_git(&repo, &["log", "-1", "--format=%B", "HEAD"]);
+    assert!(
+        msg.contains("since release-1.2.0."),
+        "commit message: {msg}"
+    );
+    assert!(
+        msg.contains("dev-1.2.0 and v-1.2.0.1"),
+        "similar tokens must stay: {msg}"
+    );
+    let (_c, tag_msg, _e) = run_git(
+        &repo,
+        &[
+            "for-each-ref",
+            "--format=%(contents)",
+            "refs/tags/release-1.3.0",
+        ],
+    );
+    assert!(
+        tag_msg.contains("Changes since release-1.2.0"),
+        "tag message: {tag_msg}"
+    );
+    let report = std::fs::read_to_string(repo.join(".git/filter-repo/report.txt")).unwrap();
+    assert!(
+        report.contains("=== Tag name references ===\nMessages updated: 2"),
+        "report: {report}"
+    );
+}
+
 #[test]
 fn branch_rename_updates_ref_and_head() {
     let repo = init_repo();
@@ -125,6 +183,44 @@ fn branch_rename_updates_ref_and_head() {
     assert_eq!(head_after.trim(), new_branch);
 }
 
+#[test]
+fn branch_rename_carries_upstream_tracking_to_new_name() {
+    let repo = init_repo();
+    let branch = current_branch(&repo);
+    let upstream = mktemp("fr_rs_upstream");
+    let upstream_str = upstream.to_str().unwrap();
+    assert_eq!(
+        run_git(&repo, &["clone", "-q", "--bare", ".", upstream_str]).0,
+        0
+    );
+    assert_eq!(
+        run_git(&repo, &["remote", "add", "upstream", upstream_str]).0,
+        0
+    );
+    assert_eq!(run_git(&repo, &["fetch", "-q", "upstream"]).0, 0);
+    let upstream_ref = format!("upstream/{branch}");
+    assert_eq!(
+        run_git(&repo, &["branch", "-q", "--set-upstream-to", &upstream_ref]).0,
+        0
+    );
+
+    run_tool_expect_success(&repo, |o| {
+        o.branch_rename = Some((Vec::new(), b"renamed-".to_vec()));
+        o.no_data = true;
+    });
+
+    let (_c, status, _e) = run_git(&repo, &["status", "-sb"]);
+    assert_eq!(
+        status.lines().next(),
+        Some(format!("## renamed-{branch}...{upstream_ref}").as_str())
+    );
+    let (code, _out, _e) = run_git(
+        &repo,
+        &["config", "--get", &format!("branch.{branch}.merge")],
+    );
+    assert_eq!(code, 1, "old branch tracking config should be removed");
+}
+
 #[test]
 fn branch_rename_without_new_commits_updates_refs() {
     let repo = init_repo();
@@ -284,3 +380,29 @@ fn multi_branch_prefix_rename_maps_head_from_deleted_branch() {
     let (_c1, head_after, _e1) = run_git(&repo, &["symbolic-ref", "HEAD"]);
     assert_eq!(head_after.trim(), "refs/heads/topics/bar");
 }
+
+#[test]
+fn path_rename_regex_moves_packages_src_to_modules_lib() {
+    let repo = init_repo();
+    write_file(&repo, "packages/mylib/src/main.rs", "fn main() {}\n");
+    write_file(&repo, "packages/mylib/README.md", "mylib\n");
+    run_git(&repo, &["add", "."]);
+    run_git(&repo, &["commit", "-q", "-m", "add mylib"]);
+
+    let output = cli_command()
+        .arg("--path-rename-regex=packages/([^/]+)/src/==>modules/$1/lib/")
+        .arg("--force")
+        .current_dir(&repo)
+        .output()
+        .expect("run filter-repo-rs with --path-rename-regex");
+    assert!(
+        output.status.success(),
+        "path-rename-regex run failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let (_c, tree, _e) = run_git(&repo, &["ls-tree", "-r", "--name-only", "HEAD"]);
+    assert!(tree.contains("modules/mylib/lib/main.rs"), "{tree}");
+    assert!(!tree.contains("packages/mylib/src/main.rs"), "{tree}");
+    assert!(tree.contains("packages/mylib/README.md"), "{tree}");
+}
@@ -195,3 +195,212 @@ fn replace_text_glob_invalid_utf8() {
         o.no_data = false;
     });
 }
+
+fn blob_cache_stats(repo: &std::path::Path) -> serde_json::Value {
+    let report = repo.join(".git").join("filter-repo").join("report.json");
+    let json: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(report).expect("read report.json"))
+            .expect("report.json should be valid json");
+    json["blob_cache"].clone()
+}
+
+#[test]
+fn blob_cache_skips_known_unmodified_blobs_until_rules_change() {
+    let repo = init_repo();
+    let secret = fake_secrets::secret_abc_123();
+    write_file(&repo, "secret.txt", &format!("token={secret}\n"));
+    for i in 0..8 {
+        write_file(&repo, &format!("docs/page{i}.md"), &format!("page {i}\n"));
+    }
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add files"]).0, 0);
+
+    let work = mktemp("fr_rs_blob_cache");
+    std::fs::create_dir_all(&work).unwrap();
+    let rules = work.join("rules.txt");
+    let cache_dir = work.join("cache");
+    std::fs::write(&rules, fake_secrets::replace_rule_line(&secret, "REDACTED")).unwrap();
+    let run = |clear: bool| {
+        run_tool_expect_success(&repo, |o| {
+            o.replace_text_file = Some(rules.clone());
+            o.blob_cache_dir = Some(cache_dir.clone());
+            o.blob_cache_clear = clear;
+            o.write_report_json = true;
+        });
+    };
+
+    run(false);
+    let first = blob_cache_stats(&repo);
+    assert_eq!(first["hits"], 0, "cold cache: {first}");
+    let (_c, head_after_first, _e) = run_git(&repo, &["rev-parse", "HEAD"]);
+
+    run(false);
+    let second = blob_cache_stats(&repo);
+    // README plus the eight pages were unmodified on the first run; only the
+    // redacted secret.txt blob is new.
+    assert_eq!(second["hits"], 9, "warm cache: {second}");
+    assert_eq!(second["misses"], 1, "warm cache: {second}");
+    let (_c, head_after_second, _e) = run_git(&repo, &["rev-parse", "HEAD"]);
+    assert_eq!(head_after_first, head_after_second);
+    let (_c, content, _e) = run_git(&repo, &["show", "HEAD:secret.txt"]);
+    assert!(content.contains("REDACTED") && !content.contains(&secret));
+
+    std::fs::write(&rules, "page 3==>PAGE THREE\n").unwrap();
+    run(false);
+    let changed = blob_cache_stats(&repo);
+    assert_eq!(changed["hits"], 0, "rule change must invalidate: {changed}");
+    let (_c, page, _e) = run_git(&repo, &["show", "HEAD:docs/page3.md"]);
+    assert!(page.contains("PAGE THREE"), "{page}");
+
+    run(true);
+    let cleared = blob_cache_stats(&repo);
+    assert_eq!(
+        cleared["hits"], 0,
+        "--blob-cache-clear starts cold: {cleared}"
+    );
+}
+
+#[test]
+fn blob_cache_misses_when_history_moves_a_blob_into_scope() {
+    let repo = init_repo();
+    write_file(&repo, "docs/notes.txt", "token=abc123\n");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "notes"]).0, 0);
+
+    let work = mktemp("fr_rs_blob_cache_scope");
+    std::fs::create_dir_all(&work).unwrap();
+    let rules = work.join("rules.txt");
+    let cache_dir = work.join("cache");
+    std::fs::write(&rules, "path:config/prod.env:abc123==>***REMOVED***\n").unwrap();
+    let run = || {
+        run_tool_expect_success(&repo, |o| {
+            o.replace_text_file = Some(rules.clone());
+            o.blob_cache_dir = Some(cache_dir.clone());
+            o.write_report_json = true;
+        });
+    };
+
+    // The notes blob is out of scope, so the cache learns it as unmodified.
+    run();
+    let (_c, notes, _e) = run_git(&repo, &["show", "HEAD:docs/notes.txt"]);
+    assert_eq!(notes, "token=abc123\n");
+
+    // Same rules, but history now stores that blob at the scoped path.
+    write_file(&repo, "config/prod.env", "token=abc123\n");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "config"]).0, 0);
+    run();
+    let stats = blob_cache_stats(&repo);
+    assert_eq!(stats["hits"], 0, "scope change must invalidate: {stats}");
+    let (_c, config, _e) = run_git(&repo, &["show", "HEAD:config/prod.env"]);
+    assert_eq!(config, "token=***REMOVED***\n");
+}
+
+#[test]
+fn replace_text_writes_blob_map_and_reports_original_oids() {
+    let repo = init_repo();
+    let secret = fake_secrets::secret_abc_123();
+    write_file(&repo, "secret.txt", &format!("token={secret}\n"));
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add secret"]).0, 0);
+    let (_c, old_oid, _e) = run_git(&repo, &["rev-parse", "HEAD:secret.txt"]);
+    let old_oid = old_oid.trim().to_string();
+
+    let repl = repo.join("repl-blobs.txt");
+    std::fs::write(&repl, fake_secrets::replace_rule_line(&secret, "REDACTED")).unwrap();
+    run_tool_expect_success(&repo, |o| {
+        o.replace_text_file = Some(repl.clone());
+        o.write_report = true;
+    });
+
+    let (_c, new_oid, _e) = run_git(&repo, &["rev-parse", "HEAD:secret.txt"]);
+    let new_oid = new_oid.trim().to_string();
+    assert_ne!(old_oid, new_oid);
+
+    let debug_dir = repo.join(".git").join("filter-repo");
+    let blob_map = std::fs::read_to_string(debug_dir.join("blob-map")).expect("read blob-map");
+    assert_eq!(blob_map, format!("{old_oid} {new_oid}\n"));
+
+    let report = std::fs::read_to_string(debug_dir.join("report.txt")).expect("read report");
+    assert!(
+        report.contains(&format!("secret.txt (original {old_oid})")),
+        "report: {report}"
+    );
+}
+
+/// `notes.txt` and the commit message both say `password=1`; the rules file
+/// rewrites it through a literal rule and then a regex listed before it.
+fn repo_with_password_in_blob_and_message() -> (std::path::PathBuf, std::path::PathBuf) {
+    let repo = init_repo();
+    write_file(&repo, "notes.txt", "password=1\n");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(
+        run_git(&repo, &["commit", "-q", "-m", "set password=1"]).0,
+        0
+    );
+    let rules = repo.join("rules.txt");
+    std::fs::write(&rules, "regex:token-(\\d+)==>[T$1]\npassword==>token-42\n").unwrap();
+    (repo, rules)
+}
+
+fn run_cli(repo: &std::path::Path, flag: &str, rules: &std::path::Path) {
+    let output = cli_command()
+        .current_dir(repo)
+        .arg("--force")
+        .arg(flag)
+        .arg(rules)
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn replace_text_and_its_blobs_alias_leave_commit_messages_alone() {
+    for flag in ["--replace-text", "--replace-text-blobs"] {
+        let (repo, rules) = repo_with_password_in_blob_and_message();
+        run_cli(&repo, flag, &rules);
+
+        let (_c, content, _e) = run_git(&repo, &["show", "HEAD:notes.txt"]);
+        assert_eq!(content, "[T42]=1\n", "{flag}");
+        let (_c, subject, _e) = run_git(&repo, &["log", "-1", "--format=%s"]);
+        assert_eq!(subject.trim(), "set password=1", "{flag}");
+    }
+}
+
+#[test]
+fn replace_text_messages_alias_leaves_blobs_alone() {
+    let (repo, rules) = repo_with_password_in_blob_and_message();
+    run_cli(&repo, "--replace-text-messages", &rules);
+
+    let (_c, subject, _e) = run_git(&repo, &["log", "-1", "--format=%s"]);
+    assert_eq!(subject.trim(), "set [T42]=1");
+    let (_c, content, _e) = run_git(&repo, &["show", "HEAD:notes.txt"]);
+    assert_eq!(content, "password=1\n");
+}
+
+#[test]
+fn path_scoped_rules_beyond_the_argument_length_limit() {
+    let repo = init_repo();
+    write_file(&repo, "config/prod.env", "token=abc123\n");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "config"]).0, 0);
+    // Roughly 3 MiB of scoped paths, more than a command line may carry.
+    let mut rules = String::new();
+    for i in 0..40_000 {
+        rules.push_str(&format!(
+            "path:generated/{i:06}/some/fairly/deep/directory/tree/file.env:abc123==>***\n"
+        ));
+    }
+    rules.push_str("path:config/prod.env:abc123==>***REMOVED***\n");
+    let rules_path = repo.join("scoped-rules.txt");
+    std::fs::write(&rules_path, rules).unwrap();
+
+    run_tool_expect_success(&repo, |o| o.replace_text_file = Some(rules_path.clone()));
+
+    let (_c, content, _e) = run_git(&repo, &["show", "HEAD:config/prod.env"]);
+    assert_eq!(content, "token=***REMOVED***\n");
+}
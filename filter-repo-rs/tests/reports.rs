@@ -115,6 +115,73 @@ done
     }
 }
 
+#[test]
+fn sanitized_paths_that_collide_get_distinct_names() {
+    let repo = init_repo();
+    let stream_path = repo.join("fe-sanitize-collision.stream");
+    let stream = r#"blob
+mark :1
+data 1
+x
+
+blob
+mark :2
+data 1
+y
+
+commit refs/heads/main
+mark :3
+author Tester <tester@example.com> 0 +0000
+committer Tester <tester@example.com> 0 +0000
+data 3
+c1
+M 100644 :1 "a?.txt"
+M 100644 :2 "a*.txt"
+
+done
+"#;
+    std::fs::write(&stream_path, stream).expect("write sanitize collision stream");
+
+    run_tool_expect_success(&repo, |o| {
+        o.debug_mode = true;
+        o.dry_run = true;
+        o.path_compat_policy = filter_repo_rs::pathutil::PathCompatPolicy::Sanitize;
+        #[allow(deprecated)]
+        {
+            o.fe_stream_override = Some(stream_path.clone());
+        }
+    });
+
+    let filtered = std::fs::read_to_string(
+        repo.join(".git")
+            .join("filter-repo")
+            .join("fast-export.filtered"),
+    )
+    .expect("read filtered stream");
+    if cfg!(windows) {
+        assert!(filtered.contains("M 100644 :1 a_.txt"), "{}", filtered);
+        assert!(filtered.contains("M 100644 :2 a__1.txt"), "{}", filtered);
+        let mut s = String::new();
+        File::open(
+            repo.join(".git")
+                .join("filter-repo")
+                .join("windows-path-report.txt"),
+        )
+        .unwrap()
+        .read_to_string(&mut s)
+        .unwrap();
+        assert!(
+            s.contains("Collisions: 1"),
+            "missing collision count: {}",
+            s
+        );
+        assert!(s.contains("\"a_.txt\" <- \"a?.txt\", \"a*.txt\""), "{}", s);
+    } else {
+        assert!(filtered.contains("M 100644 :1 a?.txt"), "{}", filtered);
+        assert!(filtered.contains("M 100644 :2 a*.txt"), "{}", filtered);
+    }
+}
+
 #[test]
 fn windows_path_summary_is_included_in_text_and_json_reports() {
     let repo = init_repo();
@@ -188,3 +255,130 @@ done
         );
     }
 }
+
+#[test]
+fn report_lists_regex_rule_match_counts() {
+    let repo = init_repo();
+    write_file(&repo, "config.txt", "token=abc1 token=abc2\n");
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(
+        run_git(&repo, &["commit", "-q", "-m", "add token abc9"]).0,
+        0
+    );
+
+    let text_rules = repo.join("text-rules.txt");
+    std::fs::write(&text_rules, "regex:abc\\d==>***\nregex:never-matches==>x\n").unwrap();
+    let message_rules = repo.join("message-rules.txt");
+    std::fs::write(&message_rules, "regex:abc\\d==>***\n").unwrap();
+    run_tool_expect_success(&repo, |o| {
+        o.write_report = true;
+        o.replace_text_file = Some(text_rules.clone());
+        o.replace_message_file = Some(message_rules.clone());
+    });
+
+    let report = std::fs::read_to_string(repo.join(".git").join("filter-repo").join("report.txt"))
+        .expect("read report");
+    assert!(
+        report.contains("=== Regex rule match counts ==="),
+        "{report}"
+    );
+    assert!(
+        report.contains("replace-text abc\\d: 2 match(es)"),
+        "{report}"
+    );
+    assert!(
+        report.contains("replace-text never-matches: 0 match(es)"),
+        "{report}"
+    );
+    assert!(
+        report.contains("replace-message abc\\d: 1 match(es)"),
+        "{report}"
+    );
+}
+
+#[test]
+fn migration_log_records_commits_blobs_and_refs_as_json_lines() {
+    let repo = init_repo();
+    write_file(&repo, "secret.txt", "token=SECRET\n");
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add secret"]).0, 0);
+    assert_eq!(run_git(&repo, &["tag", "v1"]).0, 0);
+
+    let rules = repo.join("rules.txt");
+    std::fs::write(&rules, "SECRET==>REDACTED\n").unwrap();
+    let log_path = repo.join("migration.jsonl");
+    run_tool_expect_success(&repo, |o| {
+        o.replace_text_file = Some(rules.clone());
+        o.tag_rename = Some((b"v".to_vec(), b"release-".to_vec()));
+        o.migration_log = Some(log_path.clone());
+    });
+
+    let content = std::fs::read_to_string(&log_path).expect("read migration log");
+    let events: Vec<serde_json::Value> = content
+        .lines()
+        .map(|l| serde_json::from_str(l).expect("each line is JSON"))
+        .collect();
+    assert!(events.iter().all(|e| e["timestamp"].is_string()));
+    let of_type = |t: &str| events.iter().filter(|e| e["type"] == t).count();
+    assert_eq!(of_type("commit_kept"), 2, "{content}");
+    assert_eq!(of_type("blob_rewritten"), 1, "{content}");
+    assert_eq!(of_type("ref_renamed"), 1, "{content}");
+    let blob = events
+        .iter()
+        .find(|e| e["type"] == "blob_rewritten")
+        .unwrap();
+    assert_eq!(blob["path"], "secret.txt");
+    assert_eq!(blob["oid"].as_str().map(str::len), Some(40));
+}
+
+#[test]
+fn report_counts_commits_per_ref() {
+    let repo = init_repo();
+    let branch = current_branch(&repo);
+    write_file(&repo, "keep.txt", "base\n");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "base"]).0, 0);
+    assert_eq!(run_git(&repo, &["branch", "feature"]).0, 0);
+    write_file(&repo, "keep.txt", "main\n");
+    run_git(&repo, &["add", "."]);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "main work"]).0, 0);
+    assert_eq!(run_git(&repo, &["checkout", "-q", "feature"]).0, 0);
+    for (i, msg) in ["drop one", "drop two"].iter().enumerate() {
+        write_file(&repo, &format!("drop/{i}.txt"), "x\n");
+        run_git(&repo, &["add", "."]);
+        assert_eq!(run_git(&repo, &["commit", "-q", "-m", msg]).0, 0);
+    }
+    assert_eq!(run_git(&repo, &["checkout", "-q", &branch]).0, 0);
+    let (_c, count, _e) = run_git(&repo, &["rev-list", "--all", "--count"]);
+    let total: u64 = count.trim().parse().expect("commit count");
+
+    run_tool_expect_success(&repo, |o| {
+        o.paths = vec![b"drop/".to_vec()];
+        o.invert_paths = true;
+        o.write_report = true;
+        o.write_report_json = true;
+    });
+
+    let debug_dir = repo.join(".git").join("filter-repo");
+    let json = std::fs::read_to_string(debug_dir.join("report.json")).expect("read report.json");
+    let report: serde_json::Value = serde_json::from_str(&json).expect("valid json");
+    let rows = report["ref_stats"].as_array().expect("ref_stats array");
+    let row = |name: &str| {
+        rows.iter()
+            .find(|r| r["name"] == name)
+            .unwrap_or_else(|| panic!("no row for {name}: {json}"))
+    };
+    let main = row(&format!("refs/heads/{branch}"));
+    let feature = row("refs/heads/feature");
+    assert_eq!(feature["commits_pruned"], 2, "{json}");
+    assert_eq!(main["commits_pruned"], 0, "{json}");
+    // Shared commits count for whichever ref fast-export names them under.
+    let emitted =
+        main["commits_emitted"].as_u64().unwrap() + feature["commits_emitted"].as_u64().unwrap();
+    assert_eq!(emitted, total - 2, "{json}");
+    assert_eq!(main["merges_degenerated"], 0, "{json}");
+
+    let txt = std::fs::read_to_string(debug_dir.join("report.txt")).expect("read report.txt");
+    assert!(txt.contains("=== Commits per ref ==="), "{txt}");
+    assert!(txt.contains("refs/heads/feature"), "{txt}");
+}
@@ -0,0 +1,97 @@
+mod common;
+use common::*;
+
+use std::path::{Path, PathBuf};
+
+fn scratch_repo() -> PathBuf {
+    let dir = mktemp("fr_rs_scratch");
+    std::fs::create_dir_all(&dir).unwrap();
+    assert_eq!(run_git(&dir, &["init", "-q"]).0, 0);
+    dir
+}
+
+fn reproducible_rewrite(source: &Path, target: &Path) {
+    let log = target.join(".git/migration.jsonl");
+    run_tool_expect_success(source, |o| {
+        o.target = target.to_path_buf();
+        o.reproducible = true;
+        o.max_blob_size = Some(1000);
+        o.path_renames
+            .push((b"docs/".to_vec(), b"manual/".to_vec()));
+        o.migration_log = Some(log);
+        o.write_report_json = true;
+    });
+}
+
+fn read(path: PathBuf) -> String {
+    std::fs::read_to_string(&path).unwrap_or_else(|e| panic!("read {}: {e}", path.display()))
+}
+
+#[test]
+fn reproducible_runs_into_scratch_repos_match() {
+    let source = init_repo();
+    write_file(&source, "docs/guide.md", "guide\n");
+    write_file(&source, "assets/big.bin", &"x".repeat(4096));
+    assert_eq!(run_git(&source, &["add", "."]).0, 0);
+    assert_eq!(run_git(&source, &["commit", "-q", "-m", "add docs"]).0, 0);
+    assert_eq!(run_git(&source, &["tag", "-a", "v1", "-m", "release"]).0, 0);
+    write_file(&source, "docs/guide.md", "guide v2\n");
+    assert_eq!(run_git(&source, &["commit", "-qam", "update docs"]).0, 0);
+
+    let first = scratch_repo();
+    let second = scratch_repo();
+    reproducible_rewrite(&source, &first);
+    reproducible_rewrite(&source, &second);
+
+    let commit_map = read(first.join(".git/filter-repo/commit-map"));
+    assert!(commit_map.lines().count() > 1, "{commit_map}");
+    assert_eq!(commit_map, read(second.join(".git/filter-repo/commit-map")));
+    let refs = run_git(&first, &["rev-parse", "--all"]).1;
+    assert!(!refs.trim().is_empty());
+    assert_eq!(refs, run_git(&second, &["rev-parse", "--all"]).1);
+    let stream = read(first.join(".git/filter-repo/fast-export.filtered"));
+    assert!(stream.contains("mark :1\n"), "{stream}");
+    assert_eq!(
+        stream,
+        read(second.join(".git/filter-repo/fast-export.filtered"))
+    );
+    for file in ["target-marks", "report.json"] {
+        assert_eq!(
+            read(first.join(".git/filter-repo").join(file)),
+            read(second.join(".git/filter-repo").join(file)),
+            "{file} differs"
+        );
+    }
+    let log = read(first.join(".git/migration.jsonl"));
+    assert!(
+        log.contains("\"timestamp\":\"1970-01-01T00:00:00Z\""),
+        "{log}"
+    );
+    assert_eq!(log, read(second.join(".git/migration.jsonl")));
+
+    // Rewriting in place would otherwise leave blob payloads out of the
+    // stream and shift every mark.
+    run_tool_expect_success(&source, |o| {
+        o.reproducible = true;
+        o.max_blob_size = Some(1000);
+        o.path_renames
+            .push((b"docs/".to_vec(), b"manual/".to_vec()));
+    });
+    assert_eq!(
+        read(source.join(".git/filter-repo/fast-export.filtered")),
+        stream
+    );
+    assert_eq!(read(source.join(".git/filter-repo/commit-map")), commit_map);
+}
+
+#[test]
+fn reproducible_rejects_date_order() {
+    let repo = init_repo();
+    let result = run_tool(&repo, |o| {
+        o.reproducible = true;
+        o.debug_mode = true;
+        o.date_order = true;
+    });
+    let err = result.expect_err("--date-order conflicts with --reproducible");
+    assert!(err.to_string().contains("--reproducible"), "{err}");
+}
@@ -46,3 +46,123 @@ fn early_dirty_fails_fast() {
     let ran_show_ref = cmds.iter().any(|c| c.iter().any(|a| a == "show-ref"));
     assert!(!ran_show_ref, "should fail before ref scans: {:?}", cmds);
 }
+
+#[test]
+fn linked_worktree_blocks_run_unless_forced() {
+    let repo = init_repo();
+    let wt = mktemp("fr_rs_wt");
+    let wt_str = wt.to_string_lossy().to_string();
+    assert_eq!(
+        run_git(&repo, &["worktree", "add", "-q", "-b", "side", &wt_str]).0,
+        0
+    );
+
+    let (out, _inv) = run_cli_with_git_spy(&repo, &[]);
+    assert!(
+        !out.status.success(),
+        "linked worktree should block the run"
+    );
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains("Multiple worktrees found (2 total)"),
+        "unexpected stderr: {stderr}"
+    );
+
+    let (out, _inv) = run_cli_with_git_spy(&repo, &["--force"]);
+    assert!(out.status.success(), "--force should proceed");
+    let stderr = String::from_utf8_lossy(&out.stderr);
+    assert!(
+        stderr.contains("WARNING: 1 linked worktree(s)") && stderr.contains("refs/heads/side"),
+        "expected linked worktree warning: {stderr}"
+    );
+}
+
+#[test]
+fn linked_worktree_target_is_refused_even_with_force() {
+    let repo = init_repo();
+    let wt = mktemp("fr_rs_wt");
+    let wt_str = wt.to_string_lossy().to_string();
+    assert_eq!(
+        run_git(&repo, &["worktree", "add", "-q", "-b", "side", &wt_str]).0,
+        0
+    );
+    let (_c, before, _e) = run_git(&repo, &["rev-parse", "side"]);
+
+    let err = run_tool(&wt, |o| o.force = true).expect_err("linked worktree target");
+    let msg = err.to_string();
+    assert!(msg.contains("Target is a linked worktree"), "{msg}");
+    assert!(msg.contains("main worktree at"), "{msg}");
+    assert_eq!(run_git(&repo, &["rev-parse", "side"]).1, before);
+}
+
+fn repo_with_two_commits() -> std::path::PathBuf {
+    let repo = init_repo();
+    write_file(&repo, "second.txt", "second");
+    run_git(&repo, &["add", "."]);
+    run_git(&repo, &["commit", "-q", "-m", "second"]);
+    repo
+}
+
+#[test]
+fn shallow_clone_is_refused_even_with_force_unless_allowed() {
+    let origin = repo_with_two_commits();
+    let parent = mktemp("fr_rs_shallow");
+    std::fs::create_dir_all(&parent).unwrap();
+    let url = format!("file://{}", origin.display());
+    let clone = parent.join("clone");
+    let clone_str = clone.to_string_lossy().to_string();
+    assert_eq!(
+        run_git(&parent, &["clone", "-q", "--depth", "1", &url, &clone_str]).0,
+        0
+    );
+
+    let err = run_tool(&clone, |_| {}).expect_err("shallow clone should be refused");
+    let msg = err.to_string();
+    assert!(msg.contains("shallow clone"), "unexpected error: {msg}");
+    assert!(
+        msg.contains("git fetch --unshallow"),
+        "unexpected error: {msg}"
+    );
+
+    run_tool_expect_success(&clone, |o| o.allow_shallow = true);
+}
+
+#[test]
+fn replace_refs_are_refused_unless_ignored() {
+    let repo = repo_with_two_commits();
+    let (_c, tree, _e) = run_git(&repo, &["rev-parse", "HEAD^{tree}"]);
+    let (_c, fake, _e) = run_git(&repo, &["commit-tree", tree.trim(), "-m", "replaced"]);
+    assert_eq!(run_git(&repo, &["replace", "HEAD", fake.trim()]).0, 0);
+
+    let err = run_tool(&repo, |_| {}).expect_err("replace refs should be refused");
+    let msg = err.to_string();
+    assert!(
+        msg.contains("Replace refs present"),
+        "unexpected error: {msg}"
+    );
+    assert!(
+        msg.contains("--ignore-replace-refs"),
+        "unexpected error: {msg}"
+    );
+
+    run_tool_expect_success(&repo, |o| o.ignore_replace_refs = true);
+    let (_c, subject, _e) = run_git(
+        &repo,
+        &["--no-replace-objects", "log", "-1", "--format=%s", "HEAD"],
+    );
+    assert_eq!(subject.trim(), "second", "original commit must be exported");
+}
+
+#[test]
+fn grafts_file_is_refused() {
+    let repo = repo_with_two_commits();
+    let (_c, head, _e) = run_git(&repo, &["rev-parse", "HEAD"]);
+    write_file(&repo, ".git/info/grafts", head.trim());
+
+    let err = run_tool(&repo, |_| {}).expect_err("grafts should be refused");
+    let msg = err.to_string();
+    assert!(
+        msg.contains("grafts file present"),
+        "unexpected error: {msg}"
+    );
+}
@@ -0,0 +1,192 @@
+mod common;
+use common::*;
+
+use std::path::{Path, PathBuf};
+
+/// A monorepo whose import commit adds `api/`, `web/` and `docs/`, followed
+/// by two `api` commits, one `web` commit and one touching `api` and `docs`.
+/// The fixture's "init commit" root only adds README.md; like any root
+/// commit it is kept, empty, in every split.
+fn monorepo() -> PathBuf {
+    let repo = init_repo();
+    let commit = |files: &[(&str, &str)], msg: &str| {
+        for (path, contents) in files {
+            write_file(&repo, path, contents);
+        }
+        assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+        assert_eq!(run_git(&repo, &["commit", "-q", "-m", msg]).0, 0);
+    };
+    commit(
+        &[
+            ("api/main.rs", "fn main() {}\n"),
+            ("web/index.html", "<html></html>\n"),
+            ("docs/guide.md", "# Guide\n"),
+        ],
+        "import monorepo",
+    );
+    commit(&[("api/main.rs", "fn main() { serve() }\n")], "api: serve");
+    commit(&[("web/index.html", "<html>hi</html>\n")], "web: greet");
+    commit(&[("api/routes.rs", "// routes\n")], "api: routes");
+    commit(
+        &[
+            ("api/main.rs", "fn main() { run() }\n"),
+            ("docs/api.md", "# API\n"),
+        ],
+        "api: run, with docs",
+    );
+    repo
+}
+
+fn log(repo: &Path) -> Vec<String> {
+    let (code, out, err) = run_git(repo, &["log", "--all", "--format=%s"]);
+    assert_eq!(code, 0, "{err}");
+    out.lines().map(str::to_string).collect()
+}
+
+fn files(repo: &Path) -> Vec<String> {
+    let (code, out, err) = run_git(
+        repo,
+        &[
+            "log",
+            "--all",
+            "--format=",
+            "--name-only",
+            "--diff-filter=AM",
+        ],
+    );
+    assert_eq!(code, 0, "{err}");
+    let mut files: Vec<String> = out
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect();
+    files.sort();
+    files.dedup();
+    files
+}
+
+#[test]
+fn split_to_writes_one_repository_per_directory() {
+    let repo = monorepo();
+    let before = run_git(&repo, &["rev-parse", "HEAD"]).1;
+    let out_dir = mktemp("fr_rs_split");
+
+    run_tool_expect_success(&repo, |o| {
+        for (name, path) in [("api", "api/"), ("web", "web/"), ("docs", "docs/")] {
+            o.split_to.push(filter_repo_rs::opts::SplitTarget {
+                name: name.to_string(),
+                paths: vec![path.as_bytes().to_vec()],
+            });
+        }
+        o.split_output_dir = Some(out_dir.clone());
+    });
+
+    let api = out_dir.join("api.git");
+    assert_eq!(
+        log(&api),
+        [
+            "api: run, with docs",
+            "api: routes",
+            "api: serve",
+            "import monorepo",
+            "init commit"
+        ]
+    );
+    assert_eq!(files(&api), ["api/main.rs", "api/routes.rs"]);
+
+    let web = out_dir.join("web.git");
+    assert_eq!(log(&web), ["web: greet", "import monorepo", "init commit"]);
+    assert_eq!(files(&web), ["web/index.html"]);
+
+    let docs = out_dir.join("docs.git");
+    assert_eq!(
+        log(&docs),
+        ["api: run, with docs", "import monorepo", "init commit"]
+    );
+    assert_eq!(files(&docs), ["docs/api.md", "docs/guide.md"]);
+
+    // The source repository is only read.
+    assert_eq!(run_git(&repo, &["rev-parse", "HEAD"]).1, before);
+    // The export is streamed to the splits, never written out.
+    let mut entries: Vec<String> = std::fs::read_dir(&out_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    entries.sort();
+    assert_eq!(entries, ["api.git", "docs.git", "web.git"]);
+}
+
+#[test]
+fn split_rest_collects_unclaimed_paths() {
+    let repo = monorepo();
+    let out_dir = mktemp("fr_rs_split");
+
+    let output = cli_command()
+        .current_dir(&repo)
+        .args(["--force", "--split-to", "api=api/", "--split-rest", "other"])
+        .arg("--split-output-dir")
+        .arg(&out_dir)
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("api.git: 5 commit(s)"), "{stderr}");
+    assert!(stderr.contains("other.git: 4 commit(s)"), "{stderr}");
+
+    let other = out_dir.join("other.git");
+    assert_eq!(
+        files(&other),
+        [
+            "README.md",
+            "docs/api.md",
+            "docs/guide.md",
+            "web/index.html"
+        ]
+    );
+}
+
+#[test]
+fn split_to_requires_an_output_dir() {
+    let repo = monorepo();
+    let output = cli_command()
+        .current_dir(&repo)
+        .args(["--force", "--split-to", "api=api/"])
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("--split-output-dir"),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn split_summary_follows_log_format_json() {
+    let repo = monorepo();
+    let out_dir = mktemp("fr_rs_split");
+
+    let output = cli_command()
+        .current_dir(&repo)
+        .args(["--force", "--log-format", "json", "--split-to", "web=web/"])
+        .arg("--split-output-dir")
+        .arg(&out_dir)
+        .output()
+        .expect("run filter-repo-rs");
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output.stdout.is_empty(), "{output:?}");
+    let summary = String::from_utf8_lossy(&output.stderr)
+        .lines()
+        .filter_map(|line| serde_json::from_str::<serde_json::Value>(line).ok())
+        .filter_map(|record| record["message"].as_str().map(str::to_string))
+        .find(|message| message.contains("web.git:"));
+    assert!(summary.is_some(), "{output:?}");
+}
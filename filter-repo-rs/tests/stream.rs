@@ -527,6 +527,314 @@ fn tag_data_block_rejects_oversized_header_without_panicking() {
     );
 }
 
+/// Tag payloads covering the awkward cases: empty, NUL bytes, invalid
+/// UTF-8, multi-megabyte, and messages the rules grow or erase entirely.
+fn generated_tag_payloads() -> Vec<Vec<u8>> {
+    let mut payloads: Vec<Vec<u8>> = vec![
+        Vec::new(),
+        b"\n".to_vec(),
+        b"x".to_vec(),
+        b"grow".to_vec(),
+        b"plain message\n".to_vec(),
+        b"\0".to_vec(),
+        b"a\0b\n".to_vec(),
+        b"\0grow\0grow\0".to_vec(),
+        vec![0xff, 0xfe, b'\n'],
+        vec![b'z', 0xc3],
+        "caf\u{e9} grow\n".as_bytes().to_vec(),
+        b"ERASE".to_vec(),
+        b"ERASE everything\nincluding the body\n".to_vec(),
+        b"ERASE\0with a NUL\xff\n".to_vec(),
+        [&b"z".repeat(3 << 20)[..], b"grow\n"].concat(),
+        [&b"\0\xff".repeat(1 << 20)[..], b"grow"].concat(),
+    ];
+    // A fixed LCG keeps the "random" payloads reproducible.
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next = move || {
+        state = state
+            .wrapping_mul(6_364_136_223_846_793_005)
+            .wrapping_add(1_442_695_040_888_963_407);
+        (state >> 33) as usize
+    };
+    let pieces: [&[u8]; 9] = [
+        b"z",
+        b"\0",
+        b"\xff",
+        b"\n",
+        b"grow",
+        b"ERASE",
+        b" ",
+        b"\xc3\xa9",
+        b"\r\n",
+    ];
+    for len in [1usize, 2, 7, 63, 64, 65, 300, 4096, 70_000] {
+        for _ in 0..3 {
+            let mut payload = Vec::new();
+            while payload.len() < len {
+                payload.extend_from_slice(pieces[next() % pieces.len()]);
+            }
+            payloads.push(payload);
+        }
+    }
+    payloads
+}
+
+fn replace_all(haystack: &[u8], needle: &[u8], replacement: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(haystack.len());
+    let mut rest = haystack;
+    while let Some(pos) = rest.windows(needle.len()).position(|w| w == needle) {
+        out.extend_from_slice(&rest[..pos]);
+        out.extend_from_slice(replacement);
+        rest = &rest[pos + needle.len()..];
+    }
+    out.extend_from_slice(rest);
+    out
+}
+
+/// What the rules written by `tag_payloads_keep_consistent_data_headers`
+/// make of a payload.
+fn expected_tag_message(payload: &[u8]) -> Vec<u8> {
+    let grown = replace_all(payload, b"grow", b"GROWN-GROWN-GROWN");
+    if grown.starts_with(b"ERASE") {
+        Vec::new()
+    } else {
+        grown
+    }
+}
+
+fn write_tag_stream(repo: &std::path::Path, payloads: &[Vec<u8>]) -> std::path::PathBuf {
+    let mut stream = b"blob\nmark :1\ndata 3\nhi\n\n\
+commit refs/heads/main\nmark :2\n\
+author Tester <tester@example.com> 0 +0000\n\
+committer Tester <tester@example.com> 0 +0000\n\
+data 5\ninit\nM 100644 :1 a.txt\n\n"
+        .to_vec();
+    for (idx, payload) in payloads.iter().enumerate() {
+        stream.extend_from_slice(
+            format!(
+                "tag t{idx:02}\nfrom :2\ntagger Tester <tester@example.com> 0 +0000\ndata {}\n",
+                payload.len()
+            )
+            .as_bytes(),
+        );
+        stream.extend_from_slice(payload);
+        stream.push(b'\n');
+    }
+    stream.extend_from_slice(b"done\n");
+    let path = repo.join("generated-tags.stream");
+    std::fs::write(&path, stream).expect("write generated tag stream");
+    path
+}
+
+/// Walks a fast-import stream, checking that every `data N` header is
+/// followed by N bytes, and returns each tag's message.
+fn tag_messages_in_stream(stream: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let mut tags = Vec::new();
+    let mut current_tag = None;
+    let mut rest = stream;
+    while !rest.is_empty() {
+        let end = rest
+            .iter()
+            .position(|&b| b == b'\n')
+            .expect("stream ends with a newline");
+        let line = &rest[..end];
+        rest = &rest[end + 1..];
+        if let Some(name) = line.strip_prefix(b"tag ") {
+            current_tag = Some(String::from_utf8(name.to_vec()).unwrap());
+        } else if let Some(size) = line.strip_prefix(b"data ") {
+            let n: usize = std::str::from_utf8(size).unwrap().parse().unwrap();
+            assert!(n <= rest.len(), "data {n} runs past the end of the stream");
+            if let Some(name) = current_tag.take() {
+                tags.push((name, rest[..n].to_vec()));
+            }
+            rest = &rest[n..];
+        } else if line.starts_with(b"commit ") {
+            current_tag = None;
+        }
+    }
+    tags
+}
+
+#[test]
+fn tag_payloads_keep_consistent_data_headers() {
+    let repo = init_repo();
+    let payloads = generated_tag_payloads();
+    assert!(payloads.len() > 36);
+    let stream_path = write_tag_stream(&repo, &payloads);
+    let rules = repo.join(".git").join("tag-rules.txt");
+    std::fs::write(
+        &rules,
+        "grow==>GROWN-GROWN-GROWN\nregex:(?s-u)^ERASE.*==>\n",
+    )
+    .unwrap();
+
+    run_tool_expect_success(&repo, |o| {
+        o.debug_mode = true;
+        o.dry_run = true;
+        o.replace_message_file = Some(rules.clone());
+        #[allow(deprecated)]
+        {
+            o.fe_stream_override = Some(stream_path.clone());
+        }
+    });
+
+    let filtered_path = repo.join(".git/filter-repo/fast-export.filtered");
+    let filtered = std::fs::read(&filtered_path).expect("read filtered stream");
+    let tags = tag_messages_in_stream(&filtered);
+    assert_eq!(tags.len(), payloads.len());
+    for (idx, payload) in payloads.iter().enumerate() {
+        let (name, message) = &tags[idx];
+        assert_eq!(name, &format!("t{idx:02}"));
+        assert!(
+            message == &expected_tag_message(payload),
+            "tag {name}: {} bytes in, {} bytes out",
+            payload.len(),
+            message.len()
+        );
+    }
+
+    let import_repo = mktemp("fr_rs_tag_fuzz_import");
+    std::fs::create_dir_all(&import_repo).unwrap();
+    assert_eq!(run_git(&import_repo, &["init", "-q"]).0, 0);
+    let output = std::process::Command::new("git")
+        .current_dir(&import_repo)
+        .args(["fast-import", "--quiet"])
+        .stdin(std::fs::File::open(&filtered_path).unwrap())
+        .output()
+        .expect("run git fast-import");
+    assert!(
+        output.status.success(),
+        "fast-import rejected the filtered stream: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let (_c, listed, _e) = run_git(&import_repo, &["tag", "--list"]);
+    assert_eq!(listed.lines().count(), payloads.len());
+}
+
+#[test]
+fn tag_messages_over_the_limit_fail_or_are_truncated() {
+    let repo = init_repo();
+    let payloads = vec![
+        b"short\n".to_vec(),
+        "\u{e9}\u{e9}\u{e9}\u{e9}\u{e9} grow\n".as_bytes().to_vec(),
+        b"\xff\xfe grow".to_vec(),
+    ];
+    let stream_path = write_tag_stream(&repo, &payloads);
+    let rules = repo.join(".git").join("tag-rules.txt");
+    std::fs::write(&rules, "grow==>GROWN-GROWN-GROWN\n").unwrap();
+    let run = |truncate: bool| {
+        run_tool(&repo, |o| {
+            o.debug_mode = true;
+            o.dry_run = true;
+            o.replace_message_file = Some(rules.clone());
+            o.max_tag_message_bytes = Some(9);
+            o.truncate_tag_messages = truncate;
+            #[allow(deprecated)]
+            {
+                o.fe_stream_override = Some(stream_path.clone());
+            }
+        })
+    };
+
+    let err = run(false).expect_err("a grown tag message over the limit");
+    let msg = err.to_string();
+    assert!(msg.contains("tag t01 message"), "{msg}");
+    assert!(msg.contains("--truncate-tag-messages"), "{msg}");
+
+    run(true).expect("truncating run");
+    let filtered = std::fs::read(repo.join(".git/filter-repo/fast-export.filtered")).unwrap();
+    let tags = tag_messages_in_stream(&filtered);
+    assert_eq!(tags[0].1, b"short\n");
+    // Four two-byte characters fit; the ninth byte would split the fifth.
+    assert_eq!(tags[1].1, "\u{e9}\u{e9}\u{e9}\u{e9}".as_bytes());
+    // Not UTF-8, so cut at exactly the limit.
+    assert_eq!(tags[2].1, b"\xff\xfe GROWN-");
+}
+
+#[test]
+fn every_fast_import_command_survives_filtering() {
+    let repo = init_repo();
+    let stream = b"feature done\n\
+option git quiet\n\
+progress starting\n\
+# a comment\n\
+blob\nmark :1\ndata 3\nhi\n\n\
+commit refs/heads/main\nmark :2\n\
+author Tester <tester@example.com> 0 +0000\n\
+committer Tester <tester@example.com> 0 +0000\n\
+data 5\ninit\n\
+M 100644 :1 a.txt\n\
+ls \"a.txt\"\n\
+cat-blob :1\n\
+get-mark :1\n\n\
+checkpoint\n\n\
+alias\nmark :3\nto :2\n\n\
+get-mark :3\n\
+cat-blob :1\n\
+ls :2 a.txt\n\
+progress importing\n\
+bogus-command here\n\
+reset refs/tags/v1\nfrom :3\n\n\
+done\n";
+    let stream_path = repo.join("every-command.stream");
+    std::fs::write(&stream_path, stream).unwrap();
+
+    run_tool_expect_success(&repo, |o| {
+        o.debug_mode = true;
+        o.dry_run = true;
+        #[allow(deprecated)]
+        {
+            o.fe_stream_override = Some(stream_path.clone());
+        }
+    });
+
+    let filtered_path = repo.join(".git/filter-repo/fast-export.filtered");
+    let filtered = std::fs::read_to_string(&filtered_path).unwrap();
+    for kept in [
+        "feature done\n",
+        "option git quiet\n",
+        "checkpoint\n",
+        "alias\nmark :3\nto :2\n",
+    ] {
+        assert!(filtered.contains(kept), "missing {kept:?} in\n{filtered}");
+    }
+    for dropped in [
+        "progress",
+        "# a comment",
+        "ls ",
+        "cat-blob",
+        "get-mark",
+        "bogus-command",
+    ] {
+        assert!(
+            !filtered.contains(dropped),
+            "kept {dropped:?} in\n{filtered}"
+        );
+    }
+
+    let import_repo = mktemp("fr_rs_every_command_import");
+    std::fs::create_dir_all(&import_repo).unwrap();
+    assert_eq!(run_git(&import_repo, &["init", "-q"]).0, 0);
+    let output = std::process::Command::new("git")
+        .current_dir(&import_repo)
+        .arg("fast-import")
+        .stdin(std::fs::File::open(&filtered_path).unwrap())
+        .output()
+        .expect("run git fast-import");
+    assert!(
+        output.status.success(),
+        "fast-import rejected the filtered stream: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(output.stdout.is_empty(), "fast-import answered a query");
+    let (_c, main, _e) = run_git(&import_repo, &["rev-parse", "refs/heads/main"]);
+    // The lightweight tag reaches the commit through the alias.
+    let (_c, tagged, _e) = run_git(&import_repo, &["rev-parse", "refs/tags/v1"]);
+    assert_eq!(main, tagged);
+    let (_c, content, _e) = run_git(&import_repo, &["show", "main:a.txt"]);
+    assert_eq!(content, "hi\n");
+}
+
 #[test]
 fn commit_message_data_rejects_oversized_header_without_panicking() {
     let repo = init_repo();
@@ -556,3 +864,265 @@ fn commit_message_data_rejects_oversized_header_without_panicking() {
         "unexpected error: {msg}"
     );
 }
+
+/// A repo whose `refs/stash` and `refs/notes/commits` both point into the
+/// history a `README.md` rename rewrites.
+fn repo_with_stash_and_note() -> std::path::PathBuf {
+    let repo = init_repo();
+    assert_eq!(
+        run_git(&repo, &["notes", "add", "-m", "reviewed", "HEAD"]).0,
+        0
+    );
+    write_file(&repo, "README.md", "stash me");
+    assert_eq!(run_git(&repo, &["stash", "push", "-q", "-m", "wip"]).0, 0);
+    repo
+}
+
+fn ref_oid(repo: &std::path::Path, name: &str) -> String {
+    run_git(repo, &["rev-parse", name]).1.trim().to_string()
+}
+
+#[test]
+fn default_refs_leave_stash_and_notes_untouched() {
+    let repo = repo_with_stash_and_note();
+    let stash_before = ref_oid(&repo, "refs/stash");
+    let notes_before = ref_oid(&repo, "refs/notes/commits");
+    let head_before = ref_oid(&repo, "HEAD");
+
+    run_tool_expect_success(&repo, |o| {
+        o.path_renames
+            .push((b"README.md".to_vec(), b"DOC.md".to_vec()));
+        o.write_report = true;
+    });
+
+    assert_ne!(ref_oid(&repo, "HEAD"), head_before);
+    assert_eq!(ref_oid(&repo, "refs/stash"), stash_before);
+    assert_eq!(ref_oid(&repo, "refs/notes/commits"), notes_before);
+    let debug_dir = repo.join(".git").join("filter-repo");
+    let filtered =
+        std::fs::read_to_string(debug_dir.join("fast-export.filtered")).expect("read stream");
+    assert!(!filtered.contains("refs/stash"), "{filtered}");
+    assert!(!filtered.contains("refs/notes/"), "{filtered}");
+    let report = std::fs::read_to_string(debug_dir.join("report.txt")).expect("read report");
+    assert!(report.contains("Included: refs/heads/"), "{report}");
+    assert!(
+        report.contains("Excluded: refs/stash (1 ref(s); pass --include-stash to rewrite)"),
+        "{report}"
+    );
+    assert!(
+        report.contains("Excluded: refs/notes/ (1 ref(s); pass --include-notes to rewrite)"),
+        "{report}"
+    );
+}
+
+#[test]
+fn default_refs_leave_pull_request_refs_untouched() {
+    let repo = init_repo();
+    let head_before = ref_oid(&repo, "HEAD");
+    assert_eq!(
+        run_git(&repo, &["update-ref", "refs/pull/1/head", &head_before]).0,
+        0
+    );
+
+    run_tool_expect_success(&repo, |o| {
+        o.path_renames
+            .push((b"README.md".to_vec(), b"DOC.md".to_vec()));
+        o.write_report = true;
+    });
+
+    assert_ne!(ref_oid(&repo, "HEAD"), head_before);
+    assert_eq!(ref_oid(&repo, "refs/pull/1/head"), head_before);
+    let debug_dir = repo.join(".git").join("filter-repo");
+    let filtered =
+        std::fs::read_to_string(debug_dir.join("fast-export.filtered")).expect("read stream");
+    assert!(!filtered.contains("refs/pull/"), "{filtered}");
+    let report = std::fs::read_to_string(debug_dir.join("report.txt")).expect("read report");
+    assert!(
+        report.contains("Excluded: refs/pull/ (1 ref(s); outside branches and tags)"),
+        "{report}"
+    );
+}
+
+#[test]
+fn include_flags_rewrite_stash_and_notes() {
+    let repo = repo_with_stash_and_note();
+    let stash_before = ref_oid(&repo, "refs/stash");
+
+    run_tool_expect_success(&repo, |o| {
+        o.path_renames
+            .push((b"README.md".to_vec(), b"DOC.md".to_vec()));
+        o.include_stash = true;
+        o.include_notes = true;
+        o.write_report = true;
+    });
+
+    assert_ne!(ref_oid(&repo, "refs/stash"), stash_before);
+    let (_c, tree, _e) = run_git(&repo, &["ls-tree", "--name-only", "refs/stash"]);
+    assert_eq!(tree.trim(), "DOC.md");
+    let debug_dir = repo.join(".git").join("filter-repo");
+    let filtered =
+        std::fs::read_to_string(debug_dir.join("fast-export.filtered")).expect("read stream");
+    assert!(filtered.contains("refs/notes/commits"), "{filtered}");
+    let report = std::fs::read_to_string(debug_dir.join("report.txt")).expect("read report");
+    assert!(report.contains("refs/notes/"), "{report}");
+    assert!(report.contains("refs/stash"), "{report}");
+    assert!(!report.contains("Excluded:"), "{report}");
+}
+
+#[test]
+fn ref_filter_limits_fast_export_to_matching_refs() {
+    let repo = init_repo();
+    let branch = current_branch(&repo);
+    assert_eq!(
+        run_git(&repo, &["update-ref", "refs/remotes/origin/main", "HEAD"]).0,
+        0
+    );
+    write_file(&repo, "pr.txt", "pull request\n");
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "pr"]).0, 0);
+    assert_eq!(
+        run_git(&repo, &["update-ref", "refs/pull/1/head", "HEAD"]).0,
+        0
+    );
+    assert_eq!(run_git(&repo, &["reset", "-q", "--hard", "HEAD~1"]).0, 0);
+
+    run_tool_expect_success(&repo, |o| {
+        o.ref_filter_globs = vec![b"refs/heads/*".to_vec()];
+        o.partial = true;
+    });
+
+    let filtered = std::fs::read_to_string(
+        repo.join(".git")
+            .join("filter-repo")
+            .join("fast-export.filtered"),
+    )
+    .expect("read filtered stream");
+    assert!(
+        filtered.contains(&format!("commit refs/heads/{branch}\n")),
+        "branch should be exported: {filtered}"
+    );
+    assert!(
+        !filtered.contains("refs/remotes/") && !filtered.contains("refs/pull/"),
+        "filtered-out refs must not be exported: {filtered}"
+    );
+    let (_c, refs, _e) = run_git(&repo, &["for-each-ref", "--format=%(refname)"]);
+    assert!(
+        refs.contains("refs/pull/1/head"),
+        "unexported refs are kept"
+    );
+}
+
+#[test]
+fn import_queue_stays_within_pipeline_buffer_budget() {
+    let repo = init_repo();
+    let stream_path = repo.join("fe-large-blobs.stream");
+    let (_hc, headref, _he) = run_git(&repo, &["symbolic-ref", "-q", "HEAD"]);
+    let commit_ref = headref.trim();
+    let blob_count = 40u32;
+    let blob_len = 256 * 1024;
+    let mut stream = Vec::new();
+    for mark in 1..=blob_count {
+        let payload: Vec<u8> = (0..blob_len)
+            .map(|i| (i as u32 * mark % 251) as u8)
+            .collect();
+        stream.extend_from_slice(format!("blob\nmark :{mark}\ndata {blob_len}\n").as_bytes());
+        stream.extend_from_slice(&payload);
+        stream.push(b'\n');
+    }
+    let msg = "large blobs\n";
+    stream.extend_from_slice(
+        format!(
+            "commit {commit_ref}\nmark :{}\ncommitter A U Thor <a.u.thor@example.com> 1737070000 +0000\ndata {}\n{msg}",
+            blob_count + 1,
+            msg.len()
+        )
+        .as_bytes(),
+    );
+    for mark in 1..=blob_count {
+        stream.extend_from_slice(format!("M 100644 :{mark} blob-{mark}.bin\n").as_bytes());
+    }
+    stream.extend_from_slice(b"\ndone\n");
+    std::fs::write(&stream_path, stream).unwrap();
+
+    let budget = 64 * 1024;
+    run_tool_expect_success(&repo, |o| {
+        o.debug_mode = true;
+        o.no_data = false;
+        o.write_report_json = true;
+        o.pipeline_buffer_bytes = budget;
+        #[allow(deprecated)]
+        {
+            o.fe_stream_override = Some(stream_path.clone());
+        }
+    });
+
+    let (_c, size, _e) = run_git(&repo, &["cat-file", "-s", "HEAD:blob-40.bin"]);
+    assert_eq!(size.trim(), blob_len.to_string());
+    let report: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(repo.join(".git").join("filter-repo").join("report.json"))
+            .unwrap(),
+    )
+    .unwrap();
+    let peak = report["pipeline"]["peak_queued_bytes"].as_u64().unwrap();
+    assert_eq!(report["pipeline"]["buffer_bytes"], budget);
+    assert!(peak > 0 && peak <= budget as u64, "peak {peak}");
+}
+
+#[test]
+fn rename_only_commits_are_kept_so_descendants_see_new_paths() {
+    let stream = r#"blob
+mark :1
+data 4
+one
+
+commit refs/heads/main
+mark :2
+author Tester <tester@example.com> 0 +0000
+committer Tester <tester@example.com> 0 +0000
+data 3
+c1
+M 100644 :1 a.txt
+
+commit refs/heads/main
+mark :3
+author Tester <tester@example.com> 1 +0000
+committer Tester <tester@example.com> 1 +0000
+data 7
+rename
+from :2
+R a.txt b.txt
+
+commit refs/heads/main
+mark :4
+author Tester <tester@example.com> 2 +0000
+committer Tester <tester@example.com> 2 +0000
+data 3
+c3
+from :3
+M 100644 :1 c.txt
+
+done
+"#;
+    let repo = init_repo();
+    let stream_path = repo.join("fe-rename-only.stream");
+    std::fs::write(&stream_path, stream).expect("write custom fast-export stream");
+    run_tool_expect_success(&repo, |o| {
+        o.debug_mode = true;
+        o.prune_empty = filter_repo_rs::opts::PruneMode::Always;
+        #[allow(deprecated)]
+        {
+            o.fe_stream_override = Some(stream_path.clone());
+        }
+    });
+    let filtered = std::fs::read_to_string(
+        repo.join(".git")
+            .join("filter-repo")
+            .join("fast-export.filtered"),
+    )
+    .expect("read filtered stream");
+    assert!(filtered.contains("R a.txt b.txt"), "{filtered}");
+    assert!(filtered.contains("from :3"), "{filtered}");
+
+    let (_c, tree, _e) = run_git(&repo, &["ls-tree", "-r", "--name-only", "refs/heads/main"]);
+    assert_eq!(tree.lines().collect::<Vec<_>>(), ["b.txt", "c.txt"]);
+}
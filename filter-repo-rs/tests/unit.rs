@@ -355,3 +355,38 @@ fn unit_test_git_utilities() {
     let (_c, output, _e) = run_git(&repo, &["show-ref", head_ref]);
     assert!(!output.is_empty());
 }
+
+#[test]
+fn detached_head_is_remapped_to_surviving_ancestor() {
+    let repo = init_repo();
+    write_file(&repo, "secret.txt", "token");
+    assert_eq!(run_git(&repo, &["add", "."]).0, 0);
+    assert_eq!(run_git(&repo, &["commit", "-q", "-m", "add secret"]).0, 0);
+    let secret_commit = run_git(&repo, &["rev-parse", "HEAD"]).1.trim().to_string();
+    write_file(&repo, "README.md", "more");
+    assert_eq!(run_git(&repo, &["commit", "-q", "-am", "more"]).0, 0);
+    assert_eq!(run_git(&repo, &["checkout", "-q", &secret_commit]).0, 0);
+
+    run_tool_expect_success(&repo, |o| {
+        o.paths = vec![b"secret.txt".to_vec()];
+        o.invert_paths = true;
+    });
+
+    // The commit HEAD was on only added secret.txt, so it was pruned and
+    // HEAD lands on its rewritten parent, the root commit.
+    let (code, _o, _e) = run_git(&repo, &["symbolic-ref", "-q", "HEAD"]);
+    assert_ne!(code, 0, "HEAD should stay detached");
+    let head = run_git(&repo, &["rev-parse", "HEAD"]).1.trim().to_string();
+    let root = run_git(&repo, &["rev-list", "--max-parents=0", "HEAD"])
+        .1
+        .trim()
+        .to_string();
+    assert_eq!(head, root);
+    assert_eq!(
+        run_git(&repo, &["log", "-1", "--format=%s"]).1.trim(),
+        "init commit"
+    );
+
+    let head_map = std::fs::read_to_string(repo.join(".git/filter-repo/head-map")).unwrap();
+    assert_eq!(head_map, format!("before {secret_commit}\nafter {head}\n"));
+}
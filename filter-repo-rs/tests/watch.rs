@@ -0,0 +1,237 @@
+#![cfg(unix)]
+
+mod common;
+use common::*;
+
+use std::path::Path;
+use std::process::{Child, Stdio};
+use std::time::{Duration, Instant};
+
+fn read_state(mirror: &Path) -> Option<serde_json::Value> {
+    let content =
+        std::fs::read_to_string(mirror.join("filter-repo").join("watch-state.json")).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn wait_for(what: &str, mut done: impl FnMut() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(60);
+    while !done() {
+        assert!(Instant::now() < deadline, "timed out waiting for {what}");
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+fn stop(mut child: Child) -> std::process::ExitStatus {
+    let pid = child.id().to_string();
+    assert!(std::process::Command::new("kill")
+        .args(["-TERM", &pid])
+        .status()
+        .unwrap()
+        .success());
+    let deadline = Instant::now() + Duration::from_secs(60);
+    loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            return status;
+        }
+        if Instant::now() >= deadline {
+            child.kill().ok();
+            panic!("watch process did not exit after SIGTERM");
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+fn push_commit(work: &Path, name: &str) {
+    write_file(work, &format!("{name}.txt"), name);
+    write_file(work, &format!("secrets/{name}.key"), "hunter2");
+    run_git(work, &["add", "."]);
+    assert_eq!(run_git(work, &["commit", "-q", "-m", name]).0, 0);
+    assert_eq!(run_git(work, &["push", "-q", "origin", "HEAD"]).0, 0);
+}
+
+#[test]
+fn watch_interval_filters_commits_pushed_after_initial_rewrite() {
+    let seed = init_repo();
+    write_file(&seed, "secrets/initial.key", "hunter2");
+    run_git(&seed, &["add", "."]);
+    run_git(&seed, &["commit", "-q", "-m", "add key"]);
+    let branch = current_branch(&seed);
+
+    let parent = mktemp("fr_rs_watch");
+    std::fs::create_dir_all(&parent).unwrap();
+    let mirror = parent.join("mirror.git");
+    let seed_str = seed.to_string_lossy().to_string();
+    let mirror_str = mirror.to_string_lossy().to_string();
+    assert_eq!(
+        run_git(&parent, &["clone", "-q", "--bare", &seed_str, &mirror_str]).0,
+        0
+    );
+
+    let child = cli_command()
+        .current_dir(&mirror)
+        .args([
+            "--path",
+            "secrets/",
+            "--invert-paths",
+            "--force",
+            "--quiet",
+            "--watch-interval",
+            "1",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("spawn watch");
+    wait_for("initial watch state", || read_state(&mirror).is_some());
+
+    let work = parent.join("work");
+    let work_str = work.to_string_lossy().to_string();
+    assert_eq!(
+        run_git(&parent, &["clone", "-q", &mirror_str, &work_str]).0,
+        0
+    );
+    run_git(&work, &["config", "user.name", "Pusher"]);
+    run_git(&work, &["config", "user.email", "pusher@example.com"]);
+    push_commit(&work, "first");
+    push_commit(&work, "second");
+
+    let tip_ref = format!("refs/heads/{branch}");
+    wait_for("watch state to record both pushed commits", || {
+        let Some(state) = read_state(&mirror) else {
+            return false;
+        };
+        let Some(tip) = state["tips"][&tip_ref].as_str() else {
+            return false;
+        };
+        let (_c, subjects, _e) = run_git(&mirror, &["log", "--format=%s", tip]);
+        subjects.lines().any(|s| s == "first") && subjects.lines().any(|s| s == "second")
+    });
+
+    let status = stop(child);
+    assert!(status.success(), "watch should exit cleanly: {status:?}");
+
+    let state = read_state(&mirror).unwrap();
+    assert!(state["cycles"].as_u64().unwrap() >= 1);
+    assert!(state["heartbeat"].as_str().is_some_and(|h| !h.is_empty()));
+    let (_c, tip, _e) = run_git(&mirror, &["rev-parse", &tip_ref]);
+    assert_eq!(tip.trim(), state["tips"][&tip_ref].as_str().unwrap());
+
+    let (_c, files, _e) = run_git(&mirror, &["log", "--format=", "--name-only", "--all"]);
+    assert!(files.lines().any(|f| f == "second.txt"));
+    assert!(
+        !files.contains("secrets/"),
+        "secrets/ should be filtered from every commit, got:\n{files}"
+    );
+}
+
+#[test]
+fn watch_refuses_state_written_with_different_options() {
+    let repo = init_repo();
+    let state_dir = repo.join(".git").join("filter-repo");
+    std::fs::create_dir_all(&state_dir).unwrap();
+    std::fs::write(
+        state_dir.join("watch-state.json"),
+        r#"{"options_hash":"0000000000000000","cycles":3,"heartbeat":"","tips":{}}"#,
+    )
+    .unwrap();
+
+    let output = cli_command()
+        .current_dir(&repo)
+        .args(["--path", "secrets/", "--invert-paths", "--force"])
+        .args(["--watch-interval", "1"])
+        .output()
+        .expect("run watch");
+    assert!(!output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("differ from the saved watch state"),
+        "unexpected stderr: {stderr}"
+    );
+}
+
+#[test]
+fn watch_grafts_commits_pushed_on_the_original_history() {
+    let seed = init_repo();
+    write_file(&seed, "secrets/initial.key", "hunter2");
+    run_git(&seed, &["add", "."]);
+    run_git(&seed, &["commit", "-q", "-m", "add key"]);
+    let branch = current_branch(&seed);
+
+    let parent = mktemp("fr_rs_watch_orig");
+    std::fs::create_dir_all(&parent).unwrap();
+    let mirror = parent.join("mirror.git");
+    let seed_str = seed.to_string_lossy().to_string();
+    let mirror_str = mirror.to_string_lossy().to_string();
+    assert_eq!(
+        run_git(&parent, &["clone", "-q", "--bare", &seed_str, &mirror_str]).0,
+        0
+    );
+
+    let child = cli_command()
+        .current_dir(&mirror)
+        .args([
+            "--path",
+            "secrets/",
+            "--invert-paths",
+            "--force",
+            "--quiet",
+            "--watch-interval",
+            "1",
+        ])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("spawn watch");
+    wait_for("initial watch state", || read_state(&mirror).is_some());
+    let tip_ref = format!("refs/heads/{branch}");
+    let (_c, filtered_tip, _e) = run_git(&mirror, &["rev-parse", &tip_ref]);
+    let filtered_tip = filtered_tip.trim().to_string();
+
+    // Someone still working on the unfiltered clone force-pushes to the mirror.
+    push_commit_to(&seed, &mirror_str, &branch, "third");
+
+    wait_for("watch to filter the pushed commit", || {
+        let Some(state) = read_state(&mirror) else {
+            return false;
+        };
+        let Some(tip) = state["tips"][&tip_ref].as_str() else {
+            return false;
+        };
+        let (_c, subject, _e) = run_git(&mirror, &["log", "-1", "--format=%s", tip]);
+        subject.trim() == "third" && !tip.is_empty()
+    });
+    let status = stop(child);
+    assert!(status.success(), "watch should exit cleanly: {status:?}");
+
+    let (_c, parent_oid, _e) = run_git(&mirror, &["rev-parse", &format!("{tip_ref}^")]);
+    assert_eq!(
+        parent_oid.trim(),
+        filtered_tip,
+        "the new commit should sit on the filtered history"
+    );
+    let (_c, subjects, _e) = run_git(&mirror, &["log", "--format=%s", &tip_ref]);
+    assert_eq!(
+        subjects.lines().collect::<Vec<_>>(),
+        ["third", "init commit"]
+    );
+    let (_c, files, _e) = run_git(&mirror, &["log", "--format=", "--name-only", &tip_ref]);
+    assert!(
+        !files.contains("secrets/"),
+        "secrets/ should be filtered from every commit, got:\n{files}"
+    );
+    let commit_map =
+        std::fs::read_to_string(mirror.join("filter-repo").join("commit-map")).unwrap();
+    assert!(
+        commit_map.lines().count() >= 3,
+        "commit-map should cover every cycle:\n{commit_map}"
+    );
+}
+
+fn push_commit_to(work: &Path, remote: &str, branch: &str, name: &str) {
+    write_file(work, &format!("{name}.txt"), name);
+    write_file(work, &format!("secrets/{name}.key"), "hunter2");
+    run_git(work, &["add", "."]);
+    assert_eq!(run_git(work, &["commit", "-q", "-m", name]).0, 0);
+    let refspec = format!("+HEAD:refs/heads/{branch}");
+    assert_eq!(run_git(work, &["push", "-q", remote, &refspec]).0, 0);
+}